@@ -84,16 +84,17 @@ impl Collector for StatsCollector {
         false
     }
 
-    fn merge_fruits(&self, segment_stats: Vec<Option<Stats>>) -> tantivy::Result<Option<Stats>> {
-        let mut stats = Stats::default();
-        for segment_stats_opt in segment_stats {
-            if let Some(segment_stats) = segment_stats_opt {
-                stats.count += segment_stats.count;
-                stats.sum += segment_stats.sum;
-                stats.squared_sum += segment_stats.squared_sum;
-            }
-        }
-        Ok(stats.non_zero_count())
+    // Segment stats are merged pairwise, so this merge needs to be associative.
+    fn merge_fruits(&self, left: Stats, right: Stats) -> tantivy::Result<Stats> {
+        Ok(Stats {
+            count: left.count + right.count,
+            sum: left.sum + right.sum,
+            squared_sum: left.squared_sum + right.squared_sum,
+        })
+    }
+
+    fn finalize_fruit(&self, merged_stats: Option<Stats>) -> tantivy::Result<Option<Stats>> {
+        Ok(merged_stats.and_then(Stats::non_zero_count))
     }
 }
 
@@ -103,7 +104,7 @@ struct StatsSegmentCollector {
 }
 
 impl SegmentCollector for StatsSegmentCollector {
-    type Fruit = Option<Stats>;
+    type Fruit = Stats;
 
     fn collect(&mut self, doc: u32, _score: Score) {
         let value = self.fast_field_reader.get(doc) as f64;
@@ -113,7 +114,7 @@ impl SegmentCollector for StatsSegmentCollector {
     }
 
     fn harvest(self) -> <Self as SegmentCollector>::Fruit {
-        self.stats.non_zero_count()
+        self.stats
     }
 }
 
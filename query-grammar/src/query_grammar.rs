@@ -137,6 +137,27 @@ fn literal<'a>() -> impl Parser<&'a str, Output = UserInputLeaf> {
         .map(UserInputLeaf::from)
 }
 
+/// Returns true if the word is a wildcard pattern, e.g. `hel*` or `?ello`.
+///
+/// A single `*` is not a wildcard pattern: it matches all documents.
+fn is_wildcard_pattern(word: &str) -> bool {
+    word != "*" && word.contains(|c| c == '*' || c == '?')
+}
+
+/// Parses a wildcard pattern, optionally prefixed by a field name.
+fn wildcard<'a>() -> impl Parser<&'a str, Output = UserInputLeaf> {
+    (optional(attempt(field_name())), word()).and_then(|(field_name, pattern)| {
+        if is_wildcard_pattern(&pattern) {
+            Ok(UserInputLeaf::Wildcard {
+                field_name,
+                pattern,
+            })
+        } else {
+            Err(StringStreamError::UnexpectedParse)
+        }
+    })
+}
+
 fn negative_number<'a>() -> impl Parser<&'a str, Output = String> {
     (
         char('-'),
@@ -241,11 +262,12 @@ fn leaf<'a>() -> impl Parser<&'a str, Output = UserInputAst> {
         char('(')
             .with(ast())
             .skip(char(')'))
-            .or(char('*').map(|_| UserInputAst::from(UserInputLeaf::All)))
             .or(attempt(
                 string("NOT").skip(spaces1()).with(leaf()).map(negate),
             ))
             .or(attempt(range().map(UserInputAst::from)))
+            .or(attempt(wildcard().map(UserInputAst::from)))
+            .or(char('*').map(|_| UserInputAst::from(UserInputLeaf::All)))
             .or(literal().map(UserInputAst::from))
             .parse_stream(input)
             .into_result()
@@ -442,6 +464,21 @@ mod test {
         test_parse_query_to_ast_helper("www-form-encoded", "\"www-form-encoded\"");
     }
 
+    #[test]
+    fn test_parse_query_to_ast_wildcard() {
+        test_parse_query_to_ast_helper("hel*", "wildcard(\"hel*\")");
+        test_parse_query_to_ast_helper("title:h?llo", "\"title\":wildcard(\"h?llo\")");
+        test_parse_query_to_ast_helper("*llo", "wildcard(\"*llo\")");
+        test_parse_query_to_ast_helper(
+            "+title:hel* -body:*lo",
+            "(+\"title\":wildcard(\"hel*\") -\"body\":wildcard(\"*lo\"))",
+        );
+        test_parse_query_to_ast_helper("hel*^2", "(wildcard(\"hel*\"))^2");
+        test_parse_query_to_ast_helper("*", "*");
+        test_parse_query_to_ast_helper("\"hel*\"", "\"hel*\"");
+        test_parse_query_to_ast_helper("title:*", "\"title\":\"*\"");
+    }
+
     #[test]
     fn test_parse_query_to_ast_not_op() {
         assert_eq!(
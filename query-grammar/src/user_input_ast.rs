@@ -7,6 +7,10 @@ use crate::Occur;
 pub enum UserInputLeaf {
     Literal(UserInputLiteral),
     All,
+    Wildcard {
+        field_name: Option<String>,
+        pattern: String,
+    },
     Range {
         field: Option<String>,
         lower: UserInputBound,
@@ -32,6 +36,15 @@ impl Debug for UserInputLeaf {
                 Ok(())
             }
             UserInputLeaf::All => write!(formatter, "*"),
+            UserInputLeaf::Wildcard {
+                ref field_name,
+                ref pattern,
+            } => {
+                if let Some(ref field_name) = field_name {
+                    write!(formatter, "\"{}\":", field_name)?;
+                }
+                write!(formatter, "wildcard(\"{}\")", pattern)
+            }
         }
     }
 }
@@ -0,0 +1,547 @@
+/*!
+Deep verification of the segments of an index, in the spirit of Lucene's `CheckIndex`.
+
+[`Index::check`](../struct.Index.html#method.check) reads all of the data of the segments
+of the last commit, and cross-checks their components:
+
+- the checksums of the files of the segments,
+- the bounds of the deleted documents,
+- the number of documents of the doc store, and whether they can be deserialized,
+- the ordering of the terms of the term dictionaries,
+- the postings: doc ids are increasing, lower than `max_doc`, and as many as the
+  document frequency of their term,
+- the fieldnorms: one per document, and non-zero for documents containing a term
+  of a text field,
+- the fast fields: one value per document, within the bounds recorded by the fast field.
+
+Problems are reported per segment, in a structured report that can be serialized.
+Checking does not modify the index. The corrupt segments can be dropped with
+[`IndexWriter::drop_corrupt_segments`](../struct.IndexWriter.html#method.drop_corrupt_segments).
+
+```rust
+use tantivy::schema::{Schema, TEXT};
+use tantivy::{doc, Index};
+
+# fn main() -> tantivy::Result<()> {
+let mut schema_builder = Schema::builder();
+let title = schema_builder.add_text_field("title", TEXT);
+let index = Index::create_in_ram(schema_builder.build());
+let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+index_writer.add_document(doc!(title => "The diary of Muadib"));
+index_writer.commit()?;
+
+let index_check = index.check()?;
+assert!(index_check.is_ok());
+assert_eq!(index_check.segments[0].max_doc, 1);
+# Ok(())
+# }
+```
+*/
+
+use crate::directory::error::OpenReadError;
+use crate::fastfield::FastFieldReader;
+use crate::schema::{
+    Cardinality, Field, FieldEntry, FieldNormEncoding, FieldType, IndexRecordOption,
+};
+use crate::{DocId, Index, Segment, SegmentComponent, SegmentId, SegmentReader, TantivyError};
+use serde::{Deserialize, Serialize};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::PathBuf;
+
+/// Report of the check of the committed segments of an index.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IndexCheck {
+    /// Checks of the segments of the index.
+    pub segments: Vec<SegmentCheck>,
+}
+
+impl IndexCheck {
+    /// Returns true iff no problem was found.
+    pub fn is_ok(&self) -> bool {
+        self.segments.iter().all(SegmentCheck::is_ok)
+    }
+
+    /// Returns the ids of the segments in which problems were found.
+    pub fn corrupt_segment_ids(&self) -> Vec<SegmentId> {
+        self.segments
+            .iter()
+            .filter(|segment_check| !segment_check.is_ok())
+            .map(|segment_check| segment_check.segment_id)
+            .collect()
+    }
+}
+
+/// Report of the check of a segment.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SegmentCheck {
+    /// Id of the segment.
+    pub segment_id: SegmentId,
+    /// Number of documents of the segment, deleted documents included,
+    /// as recorded in the metas of the segment.
+    pub max_doc: u32,
+    /// Number of deleted documents, as recorded in the metas of the segment.
+    pub num_deleted_docs: u32,
+    /// Problems found in the segment.
+    pub issues: Vec<CheckIssue>,
+}
+
+impl SegmentCheck {
+    /// Returns true iff no problem was found in the segment.
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// A problem found in a segment.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckIssue {
+    /// Component in which the problem was found, if it is specific to a component.
+    pub component: Option<SegmentComponent>,
+    /// Name of the field in which the problem was found, if it is specific to a field.
+    pub field: Option<String>,
+    /// Description of the problem.
+    pub message: String,
+}
+
+// Collects the issues of a segment.
+struct Issues(Vec<CheckIssue>);
+
+impl Issues {
+    fn push(
+        &mut self,
+        component: Option<SegmentComponent>,
+        field_entry: Option<&FieldEntry>,
+        message: String,
+    ) {
+        self.0.push(CheckIssue {
+            component,
+            field: field_entry.map(|field_entry| field_entry.name().to_string()),
+            message,
+        });
+    }
+}
+
+/// Checks the segments of the last commit of `index`.
+pub fn check_index(index: &Index) -> crate::Result<IndexCheck> {
+    let segments = index
+        .searchable_segments()?
+        .iter()
+        .map(check_segment)
+        .collect();
+    Ok(IndexCheck { segments })
+}
+
+/// Checks a segment.
+///
+/// Failing to read the segment is reported as an issue of the segment.
+pub fn check_segment(segment: &Segment) -> SegmentCheck {
+    let mut issues = Issues(Vec::new());
+    // Reading corrupt data may panic.
+    if let Err(panic_payload) = catch_unwind(AssertUnwindSafe(|| {
+        check_segment_components(segment, &mut issues)
+    })) {
+        let error = TantivyError::worker_panicked("index check", panic_payload);
+        issues.push(None, None, error.to_string());
+    }
+    SegmentCheck {
+        segment_id: segment.id(),
+        max_doc: segment.meta().max_doc(),
+        num_deleted_docs: segment.meta().num_deleted_docs(),
+        issues: issues.0,
+    }
+}
+
+fn check_segment_components(segment: &Segment, issues: &mut Issues) {
+    check_checksums(segment, issues);
+    let segment_reader = match SegmentReader::open(segment) {
+        Ok(segment_reader) => segment_reader,
+        Err(error) => {
+            issues.push(None, None, format!("Failed to open the segment: {}", error));
+            return;
+        }
+    };
+    check_deletes(segment, &segment_reader, issues);
+    check_store(&segment_reader, issues);
+    for (field, field_entry) in segment.schema().fields() {
+        if field_entry.is_indexed() {
+            check_postings(&segment_reader, field, field_entry, issues);
+            check_fieldnorms(&segment_reader, field, field_entry, issues);
+        }
+        if let Some(cardinality) = fast_field_cardinality(field_entry.field_type()) {
+            check_fast_field(&segment_reader, field, field_entry, cardinality, issues);
+        }
+    }
+}
+
+fn check_checksums(segment: &Segment, issues: &mut Issues) {
+    let component_paths: Vec<(SegmentComponent, PathBuf)> = SegmentComponent::iterator()
+        .filter(|&&component| component != SegmentComponent::Delete)
+        .map(|&component| (component, segment.relative_path(component)))
+        .chain(
+            segment
+                .meta()
+                .delete_file_paths()
+                .into_iter()
+                .map(|path| (SegmentComponent::Delete, path)),
+        )
+        .collect();
+    for (component, path) in component_paths {
+        match segment.index().directory().validate_checksum(&path) {
+            Ok(true) => {}
+            Ok(false) => issues.push(
+                Some(component),
+                None,
+                format!("Checksum mismatch in {:?}", path),
+            ),
+            // Components such as the temporary store or the deletes may not exist.
+            Err(OpenReadError::FileDoesNotExist(_)) => {}
+            Err(error) => issues.push(
+                Some(component),
+                None,
+                format!("Failed to read {:?}: {}", path, error),
+            ),
+        }
+    }
+}
+
+fn check_deletes(segment: &Segment, segment_reader: &SegmentReader, issues: &mut Issues) {
+    let max_doc = segment_reader.max_doc();
+    let num_deleted = if let Some(delete_bitset) = segment_reader.delete_bitset() {
+        let num_deleted_in_bounds = (0..max_doc)
+            .filter(|&doc| delete_bitset.is_deleted(doc))
+            .count();
+        if num_deleted_in_bounds != delete_bitset.num_deleted() {
+            issues.push(
+                Some(SegmentComponent::Delete),
+                None,
+                format!(
+                    "{} deleted docs are out of the bounds of the segment (max doc {})",
+                    delete_bitset.num_deleted() - num_deleted_in_bounds,
+                    max_doc
+                ),
+            );
+        }
+        delete_bitset.num_deleted() as u32
+    } else {
+        0
+    };
+    if num_deleted != segment.meta().num_deleted_docs() {
+        issues.push(
+            Some(SegmentComponent::Delete),
+            None,
+            format!(
+                "The metas record {} deleted docs, the deletes hold {}",
+                segment.meta().num_deleted_docs(),
+                num_deleted
+            ),
+        );
+    }
+}
+
+fn check_store(segment_reader: &SegmentReader, issues: &mut Issues) {
+    let store_reader = match segment_reader.get_store_reader() {
+        Ok(store_reader) => store_reader,
+        Err(error) => {
+            issues.push(
+                Some(SegmentComponent::Store),
+                None,
+                format!("Failed to open the doc store: {}", error),
+            );
+            return;
+        }
+    };
+    let mut num_docs: DocId = 0;
+    for doc_result in store_reader.iter(None) {
+        if let Err(error) = doc_result {
+            issues.push(
+                Some(SegmentComponent::Store),
+                None,
+                format!("Failed to read doc {}: {}", num_docs, error),
+            );
+            return;
+        }
+        num_docs += 1;
+    }
+    if num_docs != segment_reader.max_doc() {
+        issues.push(
+            Some(SegmentComponent::Store),
+            None,
+            format!(
+                "The doc store holds {} docs, the segment {}",
+                num_docs,
+                segment_reader.max_doc()
+            ),
+        );
+    }
+}
+
+fn check_postings(
+    segment_reader: &SegmentReader,
+    field: Field,
+    field_entry: &FieldEntry,
+    issues: &mut Issues,
+) {
+    if let Err(error) = check_postings_inner(segment_reader, field, field_entry, issues) {
+        issues.push(
+            Some(SegmentComponent::Postings),
+            Some(field_entry),
+            format!("Failed to read the postings: {}", error),
+        );
+    }
+}
+
+// Reports at most one issue per field: the postings that follow a corruption
+// are not meaningful.
+fn check_postings_inner(
+    segment_reader: &SegmentReader,
+    field: Field,
+    field_entry: &FieldEntry,
+    issues: &mut Issues,
+) -> crate::Result<()> {
+    let max_doc = segment_reader.max_doc();
+    let inverted_index = segment_reader.inverted_index(field)?;
+    // Documents containing a token of a text field have a non-zero fieldnorm.
+    let fieldnorm_reader = match field_entry.field_type() {
+        FieldType::Str(_)
+            if field_entry.field_type().get_fieldnorm_encoding()
+                != Some(FieldNormEncoding::Disabled) =>
+        {
+            Some(segment_reader.get_fieldnorms_reader(field)?)
+        }
+        _ => None,
+    };
+    let mut term_stream = inverted_index.terms().stream()?;
+    let mut previous_key: Option<Vec<u8>> = None;
+    while term_stream.advance() {
+        let key = term_stream.key();
+        if let Some(previous_key) = previous_key.as_ref() {
+            if previous_key.as_slice() >= key {
+                issues.push(
+                    Some(SegmentComponent::Terms),
+                    Some(field_entry),
+                    format!("Term {:?} is not greater than {:?}", key, previous_key),
+                );
+                return Ok(());
+            }
+        }
+        previous_key = Some(key.to_vec());
+        let term_info = term_stream.value();
+        let mut block_postings = inverted_index
+            .read_block_postings_from_terminfo(term_info, IndexRecordOption::Basic)?;
+        let mut num_docs = 0u32;
+        let mut previous_doc: Option<DocId> = None;
+        loop {
+            let docs = block_postings.docs();
+            if docs.is_empty() {
+                break;
+            }
+            for &doc in docs {
+                let message = if previous_doc.map_or(false, |previous_doc| doc <= previous_doc) {
+                    Some(format!(
+                        "Doc {} is not greater than doc {}",
+                        doc,
+                        previous_doc.unwrap()
+                    ))
+                } else if doc >= max_doc {
+                    Some(format!(
+                        "Doc {} is out of bounds (max doc {})",
+                        doc, max_doc
+                    ))
+                } else if fieldnorm_reader.as_ref().map_or(false, |fieldnorm_reader| {
+                    fieldnorm_reader.fieldnorm_id(doc) == 0
+                }) {
+                    Some(format!(
+                        "Doc {} has no tokens according to its fieldnorm",
+                        doc
+                    ))
+                } else {
+                    None
+                };
+                if let Some(message) = message {
+                    issues.push(
+                        Some(SegmentComponent::Postings),
+                        Some(field_entry),
+                        format!("Postings of term {:?}: {}", key, message),
+                    );
+                    return Ok(());
+                }
+                previous_doc = Some(doc);
+                num_docs += 1;
+            }
+            block_postings.advance();
+        }
+        if num_docs != term_info.doc_freq {
+            issues.push(
+                Some(SegmentComponent::Postings),
+                Some(field_entry),
+                format!(
+                    "Postings of term {:?} hold {} docs, its doc freq is {}",
+                    key, num_docs, term_info.doc_freq
+                ),
+            );
+            return Ok(());
+        }
+    }
+    Ok(())
+}
+
+fn check_fieldnorms(
+    segment_reader: &SegmentReader,
+    field: Field,
+    field_entry: &FieldEntry,
+    issues: &mut Issues,
+) {
+    if field_entry.field_type().get_fieldnorm_encoding() == Some(FieldNormEncoding::Disabled) {
+        return;
+    }
+    match segment_reader.get_fieldnorms_reader(field) {
+        Ok(fieldnorm_reader) => {
+            if fieldnorm_reader.num_docs() != segment_reader.max_doc() {
+                issues.push(
+                    Some(SegmentComponent::FieldNorms),
+                    Some(field_entry),
+                    format!(
+                        "The fieldnorms hold {} docs, the segment {}",
+                        fieldnorm_reader.num_docs(),
+                        segment_reader.max_doc()
+                    ),
+                );
+            }
+        }
+        Err(error) => issues.push(
+            Some(SegmentComponent::FieldNorms),
+            Some(field_entry),
+            format!("Failed to open the fieldnorms: {}", error),
+        ),
+    }
+}
+
+fn fast_field_cardinality(field_type: &FieldType) -> Option<Cardinality> {
+    match field_type {
+        FieldType::U64(int_options)
+        | FieldType::I64(int_options)
+        | FieldType::F64(int_options)
+        | FieldType::Date(int_options) => int_options.get_fastfield_cardinality(),
+        _ => None,
+    }
+}
+
+fn check_fast_field(
+    segment_reader: &SegmentReader,
+    field: Field,
+    field_entry: &FieldEntry,
+    cardinality: Cardinality,
+    issues: &mut Issues,
+) {
+    let max_doc = segment_reader.max_doc();
+    let fast_fields = segment_reader.fast_fields();
+    // Reading past the values of a truncated fast field panics.
+    let check_result = catch_unwind(AssertUnwindSafe(|| -> crate::Result<Option<String>> {
+        match cardinality {
+            Cardinality::SingleValue => {
+                let reader = fast_fields.u64_lenient(field)?;
+                let (min_value, max_value) = (reader.min_value(), reader.max_value());
+                for doc in 0..max_doc {
+                    let value = reader.get(doc);
+                    if value < min_value || value > max_value {
+                        return Ok(Some(format!(
+                            "Value {} of doc {} is out of the bounds [{}, {}]",
+                            value, doc, min_value, max_value
+                        )));
+                    }
+                }
+            }
+            Cardinality::MultiValues => {
+                let reader = fast_fields.u64s_lenient(field)?;
+                let num_vals: u64 = (0..max_doc).map(|doc| reader.num_vals(doc) as u64).sum();
+                if num_vals != reader.total_num_vals() {
+                    return Ok(Some(format!(
+                        "The docs hold {} values, the fast field {}",
+                        num_vals,
+                        reader.total_num_vals()
+                    )));
+                }
+            }
+        }
+        Ok(None)
+    }));
+    let message = match check_result {
+        Ok(Ok(None)) => return,
+        Ok(Ok(Some(message))) => message,
+        Ok(Err(error)) => format!("Failed to open the fast field: {}", error),
+        Err(_) => format!(
+            "The fast field holds fewer values than the {} docs",
+            max_doc
+        ),
+    };
+    issues.push(
+        Some(SegmentComponent::FastFields),
+        Some(field_entry),
+        message,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schema::{Schema, FAST, STORED, TEXT};
+    use crate::{Directory, Index, SegmentComponent, Term};
+
+    #[test]
+    fn test_check_index() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let num_field = schema_builder.add_u64_field("num", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(crate::merge_policy::NoMergePolicy));
+        index_writer.add_document(doc!(text_field => "a b", num_field => 3u64));
+        index_writer.add_document(doc!(text_field => "b c", num_field => 5u64));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text_field => "c d", num_field => 7u64));
+        index_writer.delete_term(Term::from_field_text(text_field, "a"));
+        index_writer.commit()?;
+
+        let index_check = index.check()?;
+        assert!(index_check.is_ok(), "{:?}", index_check);
+        assert_eq!(index_check.segments.len(), 2);
+        let mut num_deleted_docs: Vec<u32> = index_check
+            .segments
+            .iter()
+            .map(|segment_check| segment_check.num_deleted_docs)
+            .collect();
+        num_deleted_docs.sort_unstable();
+        assert_eq!(num_deleted_docs, vec![0, 1]);
+
+        // Corrupts the doc store of the first segment.
+        let segment = index
+            .searchable_segments()?
+            .into_iter()
+            .find(|segment| segment.meta().max_doc() == 2)
+            .unwrap();
+        let store_path = segment.relative_path(SegmentComponent::Store);
+        let mut store_bytes = index.directory().atomic_read(&store_path)?;
+        store_bytes[0] ^= 0xFF;
+        index.directory().atomic_write(&store_path, &store_bytes)?;
+
+        let index_check = index.check()?;
+        assert!(!index_check.is_ok());
+        assert_eq!(index_check.corrupt_segment_ids(), vec![segment.id()]);
+        let segment_check = index_check
+            .segments
+            .iter()
+            .find(|segment_check| segment_check.segment_id == segment.id())
+            .unwrap();
+        assert_eq!(
+            segment_check.issues[0].component,
+            Some(SegmentComponent::Store)
+        );
+
+        index_writer.drop_corrupt_segments()?;
+        index_writer.commit()?;
+        let index_check = index.check()?;
+        assert!(index_check.is_ok());
+        assert_eq!(index_check.segments.len(), 1);
+        assert_eq!(index.reader()?.searcher().num_docs(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,222 @@
+/*!
+Codecs make it possible to read segments whose components were written in a custom format.
+
+Each file of a segment records, in its footer, the name of the format in which it was
+written. Files written by tantivy itself are in the default format, and do not record any name.
+
+When a segment is opened, the formats of its postings, term dictionary and fast field files are
+looked up in the [`CodecManager`](./struct.CodecManager.html) of the index, and each of these
+files is decoded into the default format by the matching
+[`PostingsFormat`](./trait.PostingsFormat.html),
+[`TermDictionaryFormat`](./trait.TermDictionaryFormat.html) or
+[`FastFieldFormat`](./trait.FastFieldFormat.html).
+Opening a segment with a format that is not registered fails with an
+`Incompatibility::UnknownFormat` error.
+
+Experimental encodings can therefore be introduced segment by segment,
+without a bump of the index format version: segments in the default format remain readable,
+and merging segments rewrites them in the default format.
+*/
+
+use crate::core::SegmentComponent;
+use crate::directory::error::Incompatibility;
+use crate::directory::FileSlice;
+use crate::TantivyError;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// Format of the postings file of a segment.
+pub trait PostingsFormat: Send + Sync + 'static {
+    /// Name of the format, as recorded in the footer of the files written in this format.
+    fn name(&self) -> &str;
+
+    /// Decodes a postings file written in this format (without its footer)
+    /// into the default postings format.
+    ///
+    /// The returned `FileSlice` may decode the data lazily.
+    fn decode_postings(&self, file: FileSlice) -> crate::Result<FileSlice>;
+}
+
+/// Format of the term dictionary file of a segment.
+pub trait TermDictionaryFormat: Send + Sync + 'static {
+    /// Name of the format, as recorded in the footer of the files written in this format.
+    fn name(&self) -> &str;
+
+    /// Decodes a term dictionary file written in this format (without its footer)
+    /// into the default term dictionary format.
+    ///
+    /// The returned `FileSlice` may decode the data lazily.
+    fn decode_term_dictionary(&self, file: FileSlice) -> crate::Result<FileSlice>;
+}
+
+/// Format of the fast field file of a segment.
+pub trait FastFieldFormat: Send + Sync + 'static {
+    /// Name of the format, as recorded in the footer of the files written in this format.
+    fn name(&self) -> &str;
+
+    /// Decodes a fast field file written in this format (without its footer)
+    /// into the default fast field format.
+    ///
+    /// The returned `FileSlice` may decode the data lazily.
+    fn decode_fast_fields(&self, file: FileSlice) -> crate::Result<FileSlice>;
+}
+
+#[derive(Default)]
+struct Formats {
+    postings: HashMap<String, Arc<dyn PostingsFormat>>,
+    term_dictionary: HashMap<String, Arc<dyn TermDictionaryFormat>>,
+    fast_fields: HashMap<String, Arc<dyn FastFieldFormat>>,
+}
+
+/// The codec manager serves as a store for the custom formats
+/// in which the segments of an index may be written.
+///
+/// It is empty by default: segments written by tantivy are in the default format,
+/// which is always supported.
+#[derive(Clone, Default)]
+pub struct CodecManager {
+    formats: Arc<RwLock<Formats>>,
+}
+
+impl CodecManager {
+    /// Registers a postings format under its name.
+    pub fn register_postings_format(&self, format: Arc<dyn PostingsFormat>) {
+        self.formats
+            .write()
+            .expect("Acquiring the lock should never fail")
+            .postings
+            .insert(format.name().to_string(), format);
+    }
+
+    /// Registers a term dictionary format under its name.
+    pub fn register_term_dictionary_format(&self, format: Arc<dyn TermDictionaryFormat>) {
+        self.formats
+            .write()
+            .expect("Acquiring the lock should never fail")
+            .term_dictionary
+            .insert(format.name().to_string(), format);
+    }
+
+    /// Registers a fast field format under its name.
+    pub fn register_fast_field_format(&self, format: Arc<dyn FastFieldFormat>) {
+        self.formats
+            .write()
+            .expect("Acquiring the lock should never fail")
+            .fast_fields
+            .insert(format.name().to_string(), format);
+    }
+
+    /// Decodes a segment component file into its default format,
+    /// given the name of the format it was written in.
+    ///
+    /// Components without a pluggable format, or written in the default
+    /// format (`format_name` is `None`), are returned as is.
+    pub(crate) fn decode(
+        &self,
+        component: SegmentComponent,
+        format_name: Option<&str>,
+        file: FileSlice,
+    ) -> crate::Result<FileSlice> {
+        let format_name = if let Some(format_name) = format_name {
+            format_name
+        } else {
+            return Ok(file);
+        };
+        let formats = self
+            .formats
+            .read()
+            .expect("Acquiring the lock should never fail");
+        let decoded_file_opt = match component {
+            SegmentComponent::Postings => formats
+                .postings
+                .get(format_name)
+                .map(|format| format.decode_postings(file)),
+            SegmentComponent::Terms => formats
+                .term_dictionary
+                .get(format_name)
+                .map(|format| format.decode_term_dictionary(file)),
+            SegmentComponent::FastFields => formats
+                .fast_fields
+                .get(format_name)
+                .map(|format| format.decode_fast_fields(file)),
+            _ => None,
+        };
+        decoded_file_opt.unwrap_or_else(|| {
+            Err(TantivyError::IncompatibleIndex(
+                Incompatibility::UnknownFormat {
+                    component: format!("{:?}", component),
+                    format_name: format_name.to_string(),
+                },
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FastFieldFormat;
+    use crate::core::SegmentComponent;
+    use crate::directory::error::Incompatibility;
+    use crate::directory::{Directory, FileSlice, TerminatingWrite};
+    use crate::fastfield::FastFieldReader;
+    use crate::schema::{Schema, FAST, INDEXED};
+    use crate::{DocAddress, Index, TantivyError, Term};
+    use std::io::Write;
+    use std::sync::Arc;
+
+    /// Fast field format storing the bytes of the default format negated.
+    struct NegatedFastFieldFormat;
+
+    impl FastFieldFormat for NegatedFastFieldFormat {
+        fn name(&self) -> &str {
+            "negated"
+        }
+
+        fn decode_fast_fields(&self, file: FileSlice) -> crate::Result<FileSlice> {
+            let bytes = file.read_bytes()?;
+            let decoded: Vec<u8> = bytes.as_slice().iter().map(|byte| !byte).collect();
+            Ok(FileSlice::from(decoded))
+        }
+    }
+
+    #[test]
+    fn test_custom_fast_field_format() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let id_field = schema_builder.add_u64_field("id", INDEXED | FAST);
+        let mut index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for id in 0u64..10u64 {
+            index_writer.add_document(doc!(id_field => id * 3));
+        }
+        index_writer.commit()?;
+        drop(index_writer);
+
+        // Rewrites the fast field file of the segment in the `negated` format.
+        let segment = index.searchable_segments()?.into_iter().next().unwrap();
+        let path = segment.relative_path(SegmentComponent::FastFields);
+        let bytes = index.directory().open_read(&path)?.read_bytes()?;
+        let negated: Vec<u8> = bytes.as_slice().iter().map(|byte| !byte).collect();
+        index.directory_mut().delete(&path).unwrap();
+        let mut write = index
+            .directory_mut()
+            .open_write_with_format(&path, "negated")?;
+        write.write_all(&negated)?;
+        write.terminate()?;
+
+        let err = index.reader().err().unwrap();
+        assert!(matches!(
+            err.root_cause(),
+            TantivyError::IncompatibleIndex(Incompatibility::UnknownFormat { .. })
+        ));
+
+        index
+            .codecs()
+            .register_fast_field_format(Arc::new(NegatedFastFieldFormat));
+        let searcher = index.reader()?.searcher();
+        let fast_field_reader = searcher.segment_reader(0u32).fast_fields().u64(id_field)?;
+        assert_eq!(fast_field_reader.get(4), 12);
+        assert_eq!(searcher.doc_freq(&Term::from_field_u64(id_field, 9))?, 1);
+        assert!(searcher.doc(DocAddress::new(0, 0)).is_ok());
+        Ok(())
+    }
+}
@@ -1,5 +1,6 @@
 use super::Collector;
 use crate::collector::SegmentCollector;
+use crate::query::Weight;
 use crate::DocId;
 use crate::Score;
 use crate::SegmentOrdinal;
@@ -36,6 +37,7 @@ use crate::SegmentReader;
 ///
 /// assert_eq!(count, 2);
 /// ```
+#[derive(Debug)]
 pub struct Count;
 
 impl Collector for Count {
@@ -55,8 +57,23 @@ impl Collector for Count {
         false
     }
 
-    fn merge_fruits(&self, segment_counts: Vec<usize>) -> crate::Result<usize> {
-        Ok(segment_counts.into_iter().sum())
+    fn merge_fruits(&self, left: usize, right: usize) -> crate::Result<usize> {
+        Ok(left + right)
+    }
+
+    fn finalize_fruit(&self, merged_count: Option<usize>) -> crate::Result<usize> {
+        Ok(merged_count.unwrap_or(0))
+    }
+
+    // Weights may count their documents without visiting them, e.g. from the
+    // number of alive documents of the segment.
+    fn collect_segment(
+        &self,
+        weight: &dyn Weight,
+        _segment_ord: u32,
+        reader: &SegmentReader,
+    ) -> crate::Result<usize> {
+        Ok(weight.count(reader)? as usize)
     }
 }
 
@@ -70,8 +70,16 @@ where
         false
     }
 
-    fn merge_fruits(&self, segment_fruits: Vec<Self::Fruit>) -> crate::Result<Self::Fruit> {
-        self.collector.merge_fruits(segment_fruits)
+    fn segment_memory_usage(&self, _segment: &SegmentReader) -> usize {
+        self.collector.segment_memory_usage()
+    }
+
+    fn merge_fruits(&self, left: Self::Fruit, right: Self::Fruit) -> crate::Result<Self::Fruit> {
+        self.collector.merge_fruits(left, right)
+    }
+
+    fn finalize_fruit(&self, merged_fruit: Option<Self::Fruit>) -> crate::Result<Self::Fruit> {
+        self.collector.finalize_fruit(merged_fruit)
     }
 }
 
@@ -0,0 +1,211 @@
+use crate::chrono::{Datelike, TimeZone, Timelike, Utc};
+use crate::collector::histogram_collector::FastFieldValues;
+use crate::collector::{Collector, SegmentCollector};
+use crate::schema::Field;
+use crate::{DateTime, DocId, Score, SegmentOrdinal, SegmentReader};
+use std::collections::BTreeMap;
+
+/// Granularity to which dates are truncated.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DateTruncation {
+    /// Truncates dates to the beginning of their hour.
+    Hour,
+    /// Truncates dates to the beginning of their day.
+    Day,
+    /// Truncates dates to the beginning of the first day of their month.
+    Month,
+}
+
+impl DateTruncation {
+    /// Truncates `date`, in UTC.
+    ///
+    /// ```rust
+    /// use tantivy::chrono::{TimeZone, Utc};
+    /// use tantivy::collector::DateTruncation;
+    ///
+    /// let date = Utc.ymd(2021, 6, 17).and_hms(13, 42, 7);
+    /// assert_eq!(DateTruncation::Hour.truncate(date), Utc.ymd(2021, 6, 17).and_hms(13, 0, 0));
+    /// assert_eq!(DateTruncation::Day.truncate(date), Utc.ymd(2021, 6, 17).and_hms(0, 0, 0));
+    /// assert_eq!(DateTruncation::Month.truncate(date), Utc.ymd(2021, 6, 1).and_hms(0, 0, 0));
+    /// ```
+    pub fn truncate(self, date: DateTime) -> DateTime {
+        match self {
+            DateTruncation::Hour => date.date().and_hms(date.hour(), 0, 0),
+            DateTruncation::Day => date.date().and_hms(0, 0, 0),
+            DateTruncation::Month => Utc.ymd(date.year(), date.month(), 1).and_hms(0, 0, 0),
+        }
+    }
+}
+
+/// `DateHistogramCollector` counts the collected documents per hour, day or month,
+/// given a date fast field.
+///
+/// Contrary to the [`HistogramCollector`](./struct.HistogramCollector.html), the buckets
+/// follow the calendar (months do not have the same length), and only the non-empty
+/// buckets are returned, as a map from the beginning of each bucket to its count of documents.
+///
+/// The date field may be a multi-valued fast field, in which case a document
+/// is counted once in each of the buckets of its dates.
+///
+/// ```rust
+/// use tantivy::chrono::{TimeZone, Utc};
+/// use tantivy::collector::{DateHistogramCollector, DateTruncation};
+/// use tantivy::query::AllQuery;
+/// use tantivy::schema::{Cardinality, IntOptions, Schema};
+/// use tantivy::{doc, Index};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let timestamps = schema_builder.add_date_field(
+///     "timestamps",
+///     IntOptions::default().set_fast(Cardinality::MultiValues),
+/// );
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer(3_000_000)?;
+/// index_writer.add_document(doc!(
+///     timestamps => Utc.ymd(2021, 6, 17).and_hms(13, 42, 7),
+///     timestamps => Utc.ymd(2021, 6, 20).and_hms(8, 0, 0),
+///     timestamps => Utc.ymd(2021, 7, 1).and_hms(0, 0, 0)
+/// ));
+/// index_writer.add_document(doc!(timestamps => Utc.ymd(2021, 6, 2).and_hms(0, 0, 0)));
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let collector = DateHistogramCollector::new(timestamps, DateTruncation::Month);
+/// let histogram = searcher.search(&AllQuery, &collector)?;
+/// let counts: Vec<u64> = histogram.values().cloned().collect();
+/// assert_eq!(counts, vec![2, 1]);
+/// assert_eq!(histogram.get(&Utc.ymd(2021, 7, 1).and_hms(0, 0, 0)), Some(&1));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct DateHistogramCollector {
+    field: Field,
+    truncation: DateTruncation,
+}
+
+impl DateHistogramCollector {
+    /// Creates a `DateHistogramCollector` counting the documents per bucket
+    /// of the date fast field `field`, the buckets being defined by `truncation`.
+    pub fn new(field: Field, truncation: DateTruncation) -> DateHistogramCollector {
+        DateHistogramCollector { field, truncation }
+    }
+}
+
+pub struct SegmentDateHistogramCollector {
+    ff_values: FastFieldValues<DateTime>,
+    truncation: DateTruncation,
+    dates_buffer: Vec<DateTime>,
+    counts: BTreeMap<DateTime, u64>,
+}
+
+impl SegmentCollector for SegmentDateHistogramCollector {
+    type Fruit = BTreeMap<DateTime, u64>;
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        self.ff_values.get_vals(doc, &mut self.dates_buffer);
+        for date in self.dates_buffer.iter_mut() {
+            *date = self.truncation.truncate(*date);
+        }
+        self.dates_buffer.sort_unstable();
+        self.dates_buffer.dedup();
+        for &date in &self.dates_buffer {
+            *self.counts.entry(date).or_insert(0) += 1;
+        }
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        self.counts
+    }
+}
+
+impl Collector for DateHistogramCollector {
+    type Fruit = BTreeMap<DateTime, u64>;
+    type Child = SegmentDateHistogramCollector;
+
+    fn for_segment(
+        &self,
+        _segment_local_id: SegmentOrdinal,
+        segment: &SegmentReader,
+    ) -> crate::Result<Self::Child> {
+        let fast_fields = segment.fast_fields();
+        let ff_values = FastFieldValues::open(
+            segment,
+            self.field,
+            |field| fast_fields.date(field),
+            |field| fast_fields.dates(field),
+        )?;
+        Ok(SegmentDateHistogramCollector {
+            ff_values,
+            truncation: self.truncation,
+            dates_buffer: Vec::new(),
+            counts: BTreeMap::new(),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(
+        &self,
+        mut left: BTreeMap<DateTime, u64>,
+        right: BTreeMap<DateTime, u64>,
+    ) -> crate::Result<BTreeMap<DateTime, u64>> {
+        for (date, count) in right {
+            *left.entry(date).or_insert(0) += count;
+        }
+        Ok(left)
+    }
+
+    fn finalize_fruit(
+        &self,
+        merged_histogram: Option<BTreeMap<DateTime, u64>>,
+    ) -> crate::Result<BTreeMap<DateTime, u64>> {
+        Ok(merged_histogram.unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DateHistogramCollector, DateTruncation};
+    use crate::chrono::{TimeZone, Utc};
+    use crate::query::AllQuery;
+    use crate::schema::{Schema, FAST};
+    use crate::Index;
+
+    #[test]
+    fn test_date_histogram_single_valued() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let date_field = schema_builder.add_date_field("date", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut writer = index.writer_for_tests()?;
+        writer.add_document(doc!(date_field => Utc.ymd(2021, 6, 17).and_hms(13, 42, 7)));
+        writer.add_document(doc!(date_field => Utc.ymd(2021, 6, 17).and_hms(13, 0, 0)));
+        writer.commit()?;
+        writer.add_document(doc!(date_field => Utc.ymd(2021, 6, 17).and_hms(15, 1, 0)));
+        writer.commit()?;
+        let searcher = index.reader()?.searcher();
+
+        let collector = DateHistogramCollector::new(date_field, DateTruncation::Hour);
+        let histogram: Vec<_> = searcher
+            .search(&AllQuery, &collector)?
+            .into_iter()
+            .collect();
+        assert_eq!(
+            histogram,
+            vec![
+                (Utc.ymd(2021, 6, 17).and_hms(13, 0, 0), 2),
+                (Utc.ymd(2021, 6, 17).and_hms(15, 0, 0), 1)
+            ]
+        );
+        let collector = DateHistogramCollector::new(date_field, DateTruncation::Day);
+        let histogram: Vec<_> = searcher
+            .search(&AllQuery, &collector)?
+            .into_iter()
+            .collect();
+        assert_eq!(histogram, vec![(Utc.ymd(2021, 6, 17).and_hms(0, 0, 0), 3)]);
+        Ok(())
+    }
+}
@@ -0,0 +1,283 @@
+use crate::collector::top_collector::{ComparableDoc, TopCollector};
+use crate::collector::{Collector, SegmentCollector};
+use crate::fastfield::{DynamicFastFieldReader, FastFieldReader};
+use crate::schema::Field;
+use crate::{DocAddress, DocId, Score, SegmentOrdinal, SegmentReader};
+use std::collections::{BinaryHeap, HashMap};
+use std::mem;
+
+/// Keeps track of the `limit` best documents, with at most `max_per_key`
+/// documents per key.
+///
+/// Documents are kept in one heap per key: once a key reaches its cap, a new document
+/// of this key can only replace the worst document of the key, and once the limit
+/// is reached, a new document can only replace the worst document overall.
+/// No more than `limit` documents are therefore retained.
+pub(crate) struct DiversifiedTopHeap<T, D> {
+    limit: usize,
+    max_per_key: usize,
+    heaps: HashMap<u64, BinaryHeap<ComparableDoc<T, D>>>,
+    len: usize,
+    // Key of the heap holding the worst document, once the limit is reached.
+    worst_key: Option<u64>,
+}
+
+impl<T: PartialOrd + Clone, D: PartialOrd + Clone> DiversifiedTopHeap<T, D> {
+    pub fn new(limit: usize, max_per_key: usize) -> DiversifiedTopHeap<T, D> {
+        DiversifiedTopHeap {
+            limit,
+            max_per_key,
+            heaps: HashMap::new(),
+            len: 0,
+            worst_key: None,
+        }
+    }
+
+    fn worst(&self) -> Option<&ComparableDoc<T, D>> {
+        self.worst_key
+            .and_then(|key| self.heaps.get(&key))
+            .and_then(BinaryHeap::peek)
+    }
+
+    /// Collects a document of the given key, scored by the given feature.
+    pub fn push(&mut self, key: u64, feature: T, doc: D) {
+        let candidate = ComparableDoc { feature, doc };
+        // `ComparableDoc`s are ordered from the best to the worst.
+        // The worst document of a key cannot be worse than the worst document overall.
+        if let Some(worst) = self.worst() {
+            if candidate >= *worst {
+                return;
+            }
+        }
+        let heap = self.heaps.entry(key).or_insert_with(BinaryHeap::new);
+        if heap.len() >= self.max_per_key {
+            // It's ok to unwrap as long as a cap of 0 is forbidden.
+            let mut key_worst = heap.peek_mut().unwrap();
+            if candidate >= *key_worst {
+                return;
+            }
+            *key_worst = candidate;
+        } else if self.len < self.limit {
+            heap.push(candidate);
+            self.len += 1;
+        } else {
+            heap.push(candidate);
+            if let Some(worst_key) = self.worst_key {
+                if let Some(worst_heap) = self.heaps.get_mut(&worst_key) {
+                    worst_heap.pop();
+                    if worst_heap.is_empty() {
+                        self.heaps.remove(&worst_key);
+                    }
+                }
+            }
+        }
+        if self.len >= self.limit {
+            self.worst_key = self
+                .heaps
+                .iter()
+                .filter_map(|(key, heap)| heap.peek().map(|key_worst| (*key, key_worst)))
+                .max_by(|left, right| left.1.cmp(right.1))
+                .map(|(key, _)| key);
+        }
+    }
+
+    /// Returns the documents, from the best to the worst, along with their key.
+    pub fn into_sorted_vec(self) -> Vec<(T, D, u64)> {
+        let mut comparable_docs: Vec<(u64, ComparableDoc<T, D>)> = self
+            .heaps
+            .into_iter()
+            .flat_map(|(key, heap)| heap.into_iter().map(move |doc| (key, doc)))
+            .collect();
+        comparable_docs.sort_by(|left, right| left.1.cmp(&right.1));
+        comparable_docs
+            .into_iter()
+            .map(|(key, comparable_doc)| (comparable_doc.feature, comparable_doc.doc, key))
+            .collect()
+    }
+}
+
+/// Collector of the top documents by score, with at most `max_per_value` documents
+/// per value of a fast field.
+pub(crate) struct DiversifiedTopCollector {
+    field: Field,
+    max_per_value: usize,
+    collector: TopCollector<Score>,
+}
+
+impl DiversifiedTopCollector {
+    /// # Panics
+    /// The method panics if `max_per_value` is 0.
+    pub fn new(
+        field: Field,
+        max_per_value: usize,
+        collector: TopCollector<Score>,
+    ) -> DiversifiedTopCollector {
+        if max_per_value < 1 {
+            panic!("The number of documents per value must be strictly greater than 0.");
+        }
+        DiversifiedTopCollector {
+            field,
+            max_per_value,
+            collector,
+        }
+    }
+
+    fn num_top_docs(&self) -> usize {
+        self.collector.limit + self.collector.offset
+    }
+}
+
+impl Collector for DiversifiedTopCollector {
+    type Fruit = Vec<(Score, DocAddress)>;
+
+    type Child = DiversifiedTopSegmentCollector;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        segment_reader: &SegmentReader,
+    ) -> crate::Result<Self::Child> {
+        // Values are compared through their u64 representation, regardless of the field type.
+        let ff_reader = segment_reader.fast_fields().u64_lenient(self.field)?;
+        Ok(DiversifiedTopSegmentCollector {
+            ff_reader,
+            heap: DiversifiedTopHeap::new(self.num_top_docs(), self.max_per_value),
+            segment_ord: segment_local_id,
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        true
+    }
+
+    fn segment_memory_usage(&self, _segment: &SegmentReader) -> usize {
+        self.num_top_docs()
+            .saturating_mul(mem::size_of::<(u64, ComparableDoc<Score, DocId>)>())
+    }
+
+    fn merge_fruits(
+        &self,
+        left: Vec<(Score, DocAddress, u64)>,
+        right: Vec<(Score, DocAddress, u64)>,
+    ) -> crate::Result<Vec<(Score, DocAddress, u64)>> {
+        let mut heap = DiversifiedTopHeap::new(self.num_top_docs(), self.max_per_value);
+        for (score, doc_address, value) in left.into_iter().chain(right) {
+            heap.push(value, score, doc_address);
+        }
+        Ok(heap.into_sorted_vec())
+    }
+
+    fn finalize_fruit(
+        &self,
+        merged_fruit: Option<Vec<(Score, DocAddress, u64)>>,
+    ) -> crate::Result<Vec<(Score, DocAddress)>> {
+        let top_docs = merged_fruit
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(score, doc_address, _)| (score, doc_address))
+            .collect();
+        self.collector.finalize_fruit(Some(top_docs))
+    }
+}
+
+/// Segment Collector associated to `TopDocs::diversify_by_fast_field`.
+pub struct DiversifiedTopSegmentCollector {
+    ff_reader: DynamicFastFieldReader<u64>,
+    heap: DiversifiedTopHeap<Score, DocId>,
+    segment_ord: SegmentOrdinal,
+}
+
+impl SegmentCollector for DiversifiedTopSegmentCollector {
+    type Fruit = Vec<(Score, DocAddress, u64)>;
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        let value = self.ff_reader.get(doc);
+        self.heap.push(value, score, doc);
+    }
+
+    fn harvest(self) -> Vec<(Score, DocAddress, u64)> {
+        let segment_ord = self.segment_ord;
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|(score, doc, value)| (score, DocAddress::new(segment_ord, doc), value))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DiversifiedTopHeap;
+    use crate::collector::TopDocs;
+    use crate::indexer::NoMergePolicy;
+    use crate::query::AllQuery;
+    use crate::schema::{Schema, FAST, TEXT};
+    use crate::{DocAddress, Index};
+
+    #[test]
+    fn test_diversified_top_heap() {
+        let mut heap = DiversifiedTopHeap::new(4, 2);
+        heap.push(1, 0.9, 0);
+        heap.push(1, 0.8, 1);
+        // Replaces the worst document of the key 1.
+        heap.push(1, 0.85, 2);
+        // Worse than the documents of the key 1.
+        heap.push(1, 0.7, 3);
+        heap.push(2, 0.1, 4);
+        heap.push(3, 0.2, 5);
+        // Replaces the worst document overall.
+        heap.push(4, 0.3, 6);
+        heap.push(2, 0.05, 7);
+        assert_eq!(
+            heap.into_sorted_vec(),
+            vec![(0.9, 0, 1), (0.85, 2, 1), (0.3, 6, 4), (0.2, 5, 3)]
+        );
+    }
+
+    #[test]
+    fn test_diversify_by_fast_field() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let site_field = schema_builder.add_u64_field("site", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        for site in &[0u64, 0, 0, 1, 2] {
+            index_writer.add_document(doc!(text_field => "a", site_field => *site));
+        }
+        index_writer.commit()?;
+        for site in &[0u64, 0, 1] {
+            index_writer.add_document(doc!(text_field => "a", site_field => *site));
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+        // All of the documents have the same score, and are ranked by address.
+        let top_docs = searcher.search(
+            &AllQuery,
+            &TopDocs::with_limit(5).diversify_by_fast_field(site_field, 2),
+        )?;
+        let doc_addresses: Vec<DocAddress> = top_docs
+            .iter()
+            .map(|(_, doc_address)| *doc_address)
+            .collect();
+        assert_eq!(
+            doc_addresses,
+            vec![
+                DocAddress::new(0, 0),
+                DocAddress::new(0, 1),
+                DocAddress::new(0, 3),
+                DocAddress::new(0, 4),
+                DocAddress::new(1, 2),
+            ]
+        );
+        let top_docs_with_offset = searcher.search(
+            &AllQuery,
+            &TopDocs::with_limit(3)
+                .and_offset(2)
+                .diversify_by_fast_field(site_field, 2),
+        )?;
+        assert_eq!(&top_docs_with_offset[..], &top_docs[2..]);
+        Ok(())
+    }
+}
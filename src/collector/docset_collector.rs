@@ -30,16 +30,18 @@ impl Collector for DocSetCollector {
 
     fn merge_fruits(
         &self,
-        segment_fruits: Vec<(u32, HashSet<DocId>)>,
+        mut left: HashSet<DocAddress>,
+        right: HashSet<DocAddress>,
+    ) -> crate::Result<HashSet<DocAddress>> {
+        left.extend(right);
+        Ok(left)
+    }
+
+    fn finalize_fruit(
+        &self,
+        merged_docs: Option<HashSet<DocAddress>>,
     ) -> crate::Result<Self::Fruit> {
-        let len: usize = segment_fruits.iter().map(|(_, docset)| docset.len()).sum();
-        let mut result = HashSet::with_capacity(len);
-        for (segment_local_id, docs) in segment_fruits {
-            for doc in docs {
-                result.insert(DocAddress::new(segment_local_id, doc));
-            }
-        }
-        Ok(result)
+        Ok(merged_docs.unwrap_or_default())
     }
 }
 
@@ -49,13 +51,17 @@ pub struct DocSetChildCollector {
 }
 
 impl SegmentCollector for DocSetChildCollector {
-    type Fruit = (u32, HashSet<DocId>);
+    type Fruit = HashSet<DocAddress>;
 
     fn collect(&mut self, doc: crate::DocId, _score: Score) {
         self.docs.insert(doc);
     }
 
-    fn harvest(self) -> (u32, HashSet<DocId>) {
-        (self.segment_local_id, self.docs)
+    fn harvest(self) -> HashSet<DocAddress> {
+        let segment_local_id = self.segment_local_id;
+        self.docs
+            .into_iter()
+            .map(|doc| DocAddress::new(segment_local_id, doc))
+            .collect()
     }
 }
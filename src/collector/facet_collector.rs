@@ -1,6 +1,6 @@
 use crate::collector::Collector;
 use crate::collector::SegmentCollector;
-use crate::fastfield::FacetReader;
+use crate::fastfield::{DynamicFastFieldReader, FacetReader, FastFieldReader};
 use crate::schema::Facet;
 use crate::schema::Field;
 use crate::DocId;
@@ -12,6 +12,7 @@ use std::collections::btree_map;
 use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 use std::collections::BinaryHeap;
+use std::collections::HashSet;
 use std::iter::Peekable;
 use std::ops::Bound;
 use std::{u64, usize};
@@ -44,6 +45,13 @@ impl<'a> Ord for Hit<'a> {
     }
 }
 
+/// Returns true iff the encoded facet `facet_bytes` is `facet` or one of its descendants.
+fn is_within_facet(facet: &Facet, facet_bytes: &[u8]) -> bool {
+    let prefix = facet.encoded_str().as_bytes();
+    facet_bytes.starts_with(prefix)
+        && (facet_bytes.len() == prefix.len() || facet_bytes[prefix.len()] == 0u8)
+}
+
 fn facet_depth(facet_bytes: &[u8]) -> usize {
     if facet_bytes.is_empty() {
         0
@@ -79,6 +87,11 @@ fn facet_depth(facet_bytes: &[u8]) -> usize {
 /// This implementation assumes you are working with a number of facets that
 /// is much hundreds of time lower than your number of documents.
 ///
+/// For high-cardinality facets, `.set_top_k(...)` makes the collector only return
+/// the facets with the highest counts, and `.exclude_facet(...)` and `.include_facet(...)`
+/// restrict the facets that are counted. `.set_sum_field(...)` additionally computes,
+/// for each facet, the sum of a `u64` fast field over the documents of the facet.
+///
 ///
 /// ```rust
 /// use tantivy::collector::FacetCollector;
@@ -176,15 +189,22 @@ fn facet_depth(facet_bytes: &[u8]) -> usize {
 pub struct FacetCollector {
     field: Field,
     facets: BTreeSet<Facet>,
+    top_k: Option<usize>,
+    excluded_facets: Vec<Facet>,
+    included_facets: Vec<Facet>,
+    sum_field: Option<Field>,
 }
 
 pub struct FacetSegmentCollector {
     reader: FacetReader,
+    sum_reader: Option<DynamicFastFieldReader<u64>>,
     facet_ords_buf: Vec<u64>,
     // facet_ord -> collapse facet_id
     collapse_mapping: Vec<usize>,
     // collapse facet_id -> count
     counts: Vec<u64>,
+    // collapse facet_id -> sum of the values of the sum field
+    sums: Vec<u64>,
     // collapse facet_id -> facet_ord
     collapse_facet_ords: Vec<u64>,
 }
@@ -227,9 +247,56 @@ impl FacetCollector {
         FacetCollector {
             field,
             facets: BTreeSet::default(),
+            top_k: None,
+            excluded_facets: Vec::new(),
+            included_facets: Vec::new(),
+            sum_field: None,
         }
     }
 
+    /// Only keeps the `k` facets with the highest counts,
+    /// for each of the facets added with `.add_facet(...)`.
+    ///
+    /// Ties are broken by facet order.
+    /// The pruning happens when the counts of the different segments are merged,
+    /// so that the resulting counts are exact.
+    pub fn set_top_k(&mut self, k: usize) {
+        self.top_k = Some(k);
+    }
+
+    /// Excludes a facet, and all of its descendants, from the counts.
+    pub fn exclude_facet<T>(&mut self, facet_from: T)
+    where
+        Facet: From<T>,
+    {
+        self.excluded_facets.push(Facet::from(facet_from));
+    }
+
+    /// Restricts the counts to the given facet, and its descendants.
+    ///
+    /// If this method is called several times, the counts are restricted
+    /// to any of the given facets. Excluded facets take precedence over included ones.
+    pub fn include_facet<T>(&mut self, facet_from: T)
+    where
+        Facet: From<T>,
+    {
+        self.included_facets.push(Facet::from(facet_from));
+    }
+
+    /// Computes, for each facet, the sum of the values of the `u64` fast field `field`
+    /// over the documents of this facet.
+    ///
+    /// The sums can be retrieved with [`FacetCounts::sum(...)`](./struct.FacetCounts.html#method.sum).
+    pub fn set_sum_field(&mut self, field: Field) {
+        self.sum_field = Some(field);
+    }
+
+    fn is_counted(&self, facet_bytes: &[u8]) -> bool {
+        let is_within = |facet: &Facet| is_within_facet(facet, facet_bytes);
+        !self.excluded_facets.iter().any(is_within)
+            && (self.included_facets.is_empty() || self.included_facets.iter().any(is_within))
+    }
+
     /// Adds a facet that we want to record counts
     ///
     /// Adding facet `Facet::from("/country")` for instance,
@@ -295,8 +362,14 @@ impl Collector for FacetCollector {
                                     continue 'outer;
                                 }
                                 if depth == collapse_depth + 1 {
-                                    collapsed_id = collapse_facet_ords.len();
-                                    collapse_facet_ords.push(facet_streamer.term_ord());
+                                    if self.is_counted(facet_streamer.key()) {
+                                        collapsed_id = collapse_facet_ords.len();
+                                        collapse_facet_ords.push(facet_streamer.term_ord());
+                                    } else {
+                                        // Facets that are not counted are collapsed
+                                        // with the facets that were not requested.
+                                        collapsed_id = 0;
+                                    }
                                 }
                                 collapse_mapping.push(collapsed_id);
                             }
@@ -315,11 +388,23 @@ impl Collector for FacetCollector {
 
         counts.resize(collapse_facet_ords.len(), 0);
 
+        let sum_reader = self
+            .sum_field
+            .map(|sum_field| reader.fast_fields().u64(sum_field))
+            .transpose()?;
+        let sums = if sum_reader.is_some() {
+            vec![0u64; collapse_facet_ords.len()]
+        } else {
+            Vec::new()
+        };
+
         Ok(FacetSegmentCollector {
             reader: facet_reader,
+            sum_reader,
             facet_ords_buf: Vec::with_capacity(255),
             collapse_mapping,
             counts,
+            sums,
             collapse_facet_ords,
         })
     }
@@ -328,14 +413,41 @@ impl Collector for FacetCollector {
         false
     }
 
-    fn merge_fruits(&self, segments_facet_counts: Vec<FacetCounts>) -> crate::Result<FacetCounts> {
-        let mut facet_counts: BTreeMap<Facet, u64> = BTreeMap::new();
-        for segment_facet_counts in segments_facet_counts {
-            for (facet, count) in segment_facet_counts.facet_counts {
-                *(facet_counts.entry(facet).or_insert(0)) += count;
-            }
+    fn merge_fruits(
+        &self,
+        mut left: FacetCounts,
+        right: FacetCounts,
+    ) -> crate::Result<FacetCounts> {
+        for (facet, count) in right.facet_counts {
+            *(left.facet_counts.entry(facet).or_insert(0)) += count;
+        }
+        for (facet, sum) in right.facet_sums {
+            *(left.facet_sums.entry(facet).or_insert(0)) += sum;
+        }
+        Ok(left)
+    }
+
+    fn finalize_fruit(
+        &self,
+        merged_facet_counts: Option<FacetCounts>,
+    ) -> crate::Result<FacetCounts> {
+        let mut merged_facet_counts = merged_facet_counts.unwrap_or_default();
+        // The top k facets can only be selected once the counts of all of the segments are known.
+        if let Some(k) = self.top_k {
+            let top_facets: HashSet<Facet> = self
+                .facets
+                .iter()
+                .flat_map(|facet| merged_facet_counts.top_k(facet.clone(), k))
+                .map(|(facet, _)| facet.clone())
+                .collect();
+            merged_facet_counts
+                .facet_counts
+                .retain(|facet, _| top_facets.contains(facet));
+            merged_facet_counts
+                .facet_sums
+                .retain(|facet, _| top_facets.contains(facet));
         }
-        Ok(FacetCounts { facet_counts })
+        Ok(merged_facet_counts)
     }
 }
 
@@ -347,11 +459,12 @@ impl SegmentCollector for FacetSegmentCollector {
         let mut previous_collapsed_ord: usize = usize::MAX;
         for &facet_ord in &self.facet_ords_buf {
             let collapsed_ord = self.collapse_mapping[facet_ord as usize];
-            self.counts[collapsed_ord] += if collapsed_ord == previous_collapsed_ord {
-                0
-            } else {
-                1
-            };
+            if collapsed_ord != previous_collapsed_ord {
+                self.counts[collapsed_ord] += 1;
+                if let Some(sum_reader) = self.sum_reader.as_ref() {
+                    self.sums[collapsed_ord] += sum_reader.get(doc);
+                }
+            }
             previous_collapsed_ord = collapsed_ord;
         }
     }
@@ -362,6 +475,7 @@ impl SegmentCollector for FacetSegmentCollector {
     /// it also translates the facet ordinals of the last segment.
     fn harvest(self) -> FacetCounts {
         let mut facet_counts = BTreeMap::new();
+        let mut facet_sums = BTreeMap::new();
         let facet_dict = self.reader.facet_dict();
         for (collapsed_facet_ord, count) in self.counts.iter().cloned().enumerate() {
             if count == 0 {
@@ -372,18 +486,26 @@ impl SegmentCollector for FacetSegmentCollector {
             // TODO handle errors.
             if facet_dict.ord_to_term(facet_ord as u64, &mut facet).is_ok() {
                 if let Ok(facet) = Facet::from_encoded(facet) {
+                    if let Some(&sum) = self.sums.get(collapsed_facet_ord) {
+                        facet_sums.insert(facet.clone(), sum);
+                    }
                     facet_counts.insert(facet, count);
                 }
             }
         }
-        FacetCounts { facet_counts }
+        FacetCounts {
+            facet_counts,
+            facet_sums,
+        }
     }
 }
 
 /// Intermediary result of the `FacetCollector` that stores
 /// the facet counts for all the segments.
+#[derive(Default)]
 pub struct FacetCounts {
     facet_counts: BTreeMap<Facet, u64>,
+    facet_sums: BTreeMap<Facet, u64>,
 }
 
 pub struct FacetChildIterator<'a> {
@@ -420,6 +542,18 @@ impl FacetCounts {
         FacetChildIterator { underlying }
     }
 
+    /// Returns the sum of the values of the sum field over the documents of `facet`.
+    ///
+    /// Returns `None` if no sum field was set on the `FacetCollector`,
+    /// or if `facet` was not counted.
+    /// See [`FacetCollector::set_sum_field(...)`](./struct.FacetCollector.html#method.set_sum_field).
+    pub fn sum<T>(&self, facet_from: T) -> Option<u64>
+    where
+        Facet: From<T>,
+    {
+        self.facet_sums.get(&Facet::from(facet_from)).cloned()
+    }
+
     /// Returns a vector of top `k` facets with their counts, sorted highest-to-lowest by counts.
     /// See the documentation for `FacetCollector` for a usage example.
     pub fn top_k<T>(&self, facet: T, k: usize) -> Vec<(&Facet, u64)>
@@ -462,7 +596,7 @@ mod tests {
     use crate::collector::Count;
     use crate::core::Index;
     use crate::query::{AllQuery, QueryParser, TermQuery};
-    use crate::schema::{Document, Facet, Field, IndexRecordOption, Schema, INDEXED};
+    use crate::schema::{Document, Facet, Field, IndexRecordOption, Schema, FAST, INDEXED};
     use crate::Term;
     use rand::distributions::Uniform;
     use rand::prelude::SliceRandom;
@@ -693,6 +827,60 @@ mod tests {
         );
         Ok(())
     }
+
+    #[test]
+    fn test_facet_collector_top_k_filters_and_sum() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let facet_field = schema_builder.add_facet_field("facet", INDEXED);
+        let price_field = schema_builder.add_u64_field("price", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        let docs = vec![
+            ("/facet/a", 1u64),
+            ("/facet/a", 2u64),
+            ("/facet/b/x", 10u64),
+            ("/facet/b/y", 20u64),
+            ("/facet/b/y", 30u64),
+            ("/facet/c", 100u64),
+            ("/other/d", 1000u64),
+        ];
+        for (i, (facet, price)) in docs.into_iter().enumerate() {
+            index_writer.add_document(doc!(
+                facet_field => Facet::from(facet),
+                price_field => price
+            ));
+            if i == 3 {
+                index_writer.commit()?;
+            }
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+
+        let mut facet_collector = FacetCollector::for_field(facet_field);
+        facet_collector.add_facet("/facet");
+        facet_collector.set_top_k(2);
+        facet_collector.set_sum_field(price_field);
+        let counts = searcher.search(&AllQuery, &facet_collector)?;
+        let facets: Vec<(&Facet, u64)> = counts.get("/facet").collect();
+        assert_eq!(
+            facets,
+            vec![(&Facet::from("/facet/a"), 2), (&Facet::from("/facet/b"), 3)]
+        );
+        assert_eq!(counts.sum("/facet/a"), Some(3));
+        assert_eq!(counts.sum("/facet/b"), Some(60));
+        assert_eq!(counts.sum("/facet/c"), None);
+
+        let mut facet_collector = FacetCollector::for_field(facet_field);
+        facet_collector.add_facet("/facet");
+        facet_collector.include_facet("/facet/b");
+        facet_collector.include_facet("/facet/c");
+        facet_collector.exclude_facet("/facet/c");
+        let counts = searcher.search(&AllQuery, &facet_collector)?;
+        let facets: Vec<(&Facet, u64)> = counts.get("/facet").collect();
+        assert_eq!(facets, vec![(&Facet::from("/facet/b"), 3)]);
+        assert_eq!(counts.sum("/facet/b"), None);
+        Ok(())
+    }
 }
 
 #[cfg(all(test, feature = "unstable"))]
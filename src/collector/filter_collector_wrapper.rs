@@ -144,9 +144,17 @@ where
 
     fn merge_fruits(
         &self,
-        segment_fruits: Vec<<TCollector::Child as SegmentCollector>::Fruit>,
+        left: <TCollector::Child as SegmentCollector>::Fruit,
+        right: <TCollector::Child as SegmentCollector>::Fruit,
+    ) -> crate::Result<<TCollector::Child as SegmentCollector>::Fruit> {
+        self.collector.merge_fruits(left, right)
+    }
+
+    fn finalize_fruit(
+        &self,
+        merged_fruit: Option<<TCollector::Child as SegmentCollector>::Fruit>,
     ) -> crate::Result<TCollector::Fruit> {
-        self.collector.merge_fruits(segment_fruits)
+        self.collector.finalize_fruit(merged_fruit)
     }
 }
 
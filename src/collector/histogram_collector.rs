@@ -1,7 +1,9 @@
 use crate::collector::{Collector, SegmentCollector};
-use crate::fastfield::{DynamicFastFieldReader, FastFieldReader, FastValue};
-use crate::schema::{Field, Type};
-use crate::{DocId, Score};
+use crate::fastfield::{
+    DynamicFastFieldReader, FastFieldReader, FastValue, MultiValuedFastFieldReader,
+};
+use crate::schema::{Cardinality, Field, Type};
+use crate::{DocId, Score, SegmentReader};
 use fastdivide::DividerU64;
 
 /// Histogram builds an histogram of the values of a fastfield for the
@@ -14,6 +16,9 @@ use fastdivide::DividerU64;
 /// An histogram is then defined as a `Vec<u64>` of length `num_buckets`, that contains a count of
 /// documents for each value bucket.
 ///
+/// Multi-valued fast fields are supported: a document is counted once in each
+/// of the buckets of its values.
+///
 /// See also [`HistogramCollector::new()`].
 ///
 /// # Warning
@@ -58,6 +63,39 @@ impl HistogramCollector {
     }
 }
 
+/// The values of a single-valued or a multi-valued fast field.
+pub(crate) enum FastFieldValues<Item: FastValue> {
+    SingleValue(DynamicFastFieldReader<Item>),
+    MultiValues(MultiValuedFastFieldReader<Item>),
+}
+
+impl<Item: FastValue> FastFieldValues<Item> {
+    /// Opens the values of the fast field `field`, whatever its cardinality.
+    pub(crate) fn open(
+        segment: &SegmentReader,
+        field: Field,
+        single_value: impl FnOnce(Field) -> crate::Result<DynamicFastFieldReader<Item>>,
+        multi_values: impl FnOnce(Field) -> crate::Result<MultiValuedFastFieldReader<Item>>,
+    ) -> crate::Result<FastFieldValues<Item>> {
+        if segment.fast_fields().cardinality(field) == Some(Cardinality::MultiValues) {
+            multi_values(field).map(FastFieldValues::MultiValues)
+        } else {
+            single_value(field).map(FastFieldValues::SingleValue)
+        }
+    }
+
+    /// Fills `vals` with the values of the document `doc`.
+    pub(crate) fn get_vals(&self, doc: DocId, vals: &mut Vec<Item>) {
+        match self {
+            FastFieldValues::SingleValue(reader) => {
+                vals.clear();
+                vals.push(reader.get(doc));
+            }
+            FastFieldValues::MultiValues(reader) => reader.get_vals(doc, vals),
+        }
+    }
+}
+
 struct HistogramComputer {
     counts: Vec<u64>,
     min_value: u64,
@@ -66,14 +104,35 @@ struct HistogramComputer {
 
 impl HistogramComputer {
     #[inline]
-    pub(crate) fn add_value(&mut self, value: u64) {
+    fn bucket_id(&self, value: u64) -> Option<usize> {
         if value < self.min_value {
-            return;
+            return None;
         }
         let delta = value - self.min_value;
         let delta_u64 = delta.to_u64();
         let bucket_id: usize = self.divider.divide(delta_u64) as usize;
         if bucket_id < self.counts.len() {
+            Some(bucket_id)
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    pub(crate) fn add_value(&mut self, value: u64) {
+        if let Some(bucket_id) = self.bucket_id(value) {
+            self.counts[bucket_id] += 1;
+        }
+    }
+
+    /// Adds the values of a single document, counting the document
+    /// at most once per bucket.
+    pub(crate) fn add_values(&mut self, values: &[u64], bucket_ids: &mut Vec<usize>) {
+        bucket_ids.clear();
+        bucket_ids.extend(values.iter().filter_map(|&value| self.bucket_id(value)));
+        bucket_ids.sort_unstable();
+        bucket_ids.dedup();
+        for &bucket_id in bucket_ids.iter() {
             self.counts[bucket_id] += 1;
         }
     }
@@ -84,15 +143,26 @@ impl HistogramComputer {
 }
 pub struct SegmentHistogramCollector {
     histogram_computer: HistogramComputer,
-    ff_reader: DynamicFastFieldReader<u64>,
+    ff_values: FastFieldValues<u64>,
+    vals_buffer: Vec<u64>,
+    bucket_ids_buffer: Vec<usize>,
 }
 
 impl SegmentCollector for SegmentHistogramCollector {
     type Fruit = Vec<u64>;
 
     fn collect(&mut self, doc: DocId, _score: Score) {
-        let value = self.ff_reader.get(doc);
-        self.histogram_computer.add_value(value);
+        match &self.ff_values {
+            FastFieldValues::SingleValue(ff_reader) => {
+                let value = ff_reader.get(doc);
+                self.histogram_computer.add_value(value);
+            }
+            FastFieldValues::MultiValues(ff_reader) => {
+                ff_reader.get_vals(doc, &mut self.vals_buffer);
+                self.histogram_computer
+                    .add_values(&self.vals_buffer, &mut self.bucket_ids_buffer);
+            }
+        }
     }
 
     fn harvest(self) -> Self::Fruit {
@@ -109,14 +179,22 @@ impl Collector for HistogramCollector {
         _segment_local_id: crate::SegmentOrdinal,
         segment: &crate::SegmentReader,
     ) -> crate::Result<Self::Child> {
-        let ff_reader = segment.fast_fields().u64_lenient(self.field)?;
+        let fast_fields = segment.fast_fields();
+        let ff_values = FastFieldValues::open(
+            segment,
+            self.field,
+            |field| fast_fields.u64_lenient(field),
+            |field| fast_fields.u64s_lenient(field),
+        )?;
         Ok(SegmentHistogramCollector {
             histogram_computer: HistogramComputer {
                 counts: vec![0; self.num_buckets],
                 min_value: self.min_value,
                 divider: self.divider,
             },
-            ff_reader,
+            ff_values,
+            vals_buffer: Vec::new(),
+            bucket_ids_buffer: Vec::new(),
         })
     }
 
@@ -124,8 +202,13 @@ impl Collector for HistogramCollector {
         false
     }
 
-    fn merge_fruits(&self, child_histograms: Vec<Vec<u64>>) -> crate::Result<Vec<u64>> {
-        Ok(add_vecs(child_histograms, self.num_buckets))
+    fn merge_fruits(&self, mut left: Vec<u64>, right: Vec<u64>) -> crate::Result<Vec<u64>> {
+        add_arrays_into(&mut left, &right);
+        Ok(left)
+    }
+
+    fn finalize_fruit(&self, merged_histogram: Option<Vec<u64>>) -> crate::Result<Vec<u64>> {
+        Ok(merged_histogram.unwrap_or_else(|| vec![0u64; self.num_buckets]))
     }
 }
 
@@ -136,35 +219,31 @@ pub fn add_arrays_into(acc: &mut [u64], add: &[u64]) {
     }
 }
 
-fn add_vecs(mut vals_list: Vec<Vec<u64>>, len: usize) -> Vec<u64> {
-    let mut acc = vals_list.pop().unwrap_or_else(|| vec![0u64; len]);
-    assert_eq!(acc.len(), len);
-    for vals in vals_list {
-        add_arrays_into(&mut acc, &vals);
-    }
-    acc
-}
-
 #[cfg(test)]
 mod tests {
-    use super::{add_vecs, HistogramCollector, HistogramComputer};
+    use super::{HistogramCollector, HistogramComputer};
     use crate::chrono::{TimeZone, Utc};
-    use crate::schema::{Schema, FAST};
+    use crate::collector::Collector;
+    use crate::schema::{Cardinality, Field, IntOptions, Schema, FAST};
     use crate::{doc, query, Index};
     use fastdivide::DividerU64;
     use query::AllQuery;
 
     #[test]
-    fn test_add_histograms_simple() {
+    fn test_add_histograms_simple() -> crate::Result<()> {
+        let collector = HistogramCollector::new(Field::from_field_id(0), 0u64, 1, 3);
         assert_eq!(
-            add_vecs(vec![vec![1, 0, 3], vec![11, 2, 3], vec![0, 0, 1]], 3),
+            collector.reduce_fruits(vec![vec![1, 0, 3], vec![11, 2, 3], vec![0, 0, 1]])?,
             vec![12, 2, 7]
-        )
+        );
+        Ok(())
     }
 
     #[test]
-    fn test_add_histograms_empty() {
-        assert_eq!(add_vecs(vec![], 3), vec![0, 0, 0])
+    fn test_add_histograms_empty() -> crate::Result<()> {
+        let collector = HistogramCollector::new(Field::from_field_id(0), 0u64, 1, 3);
+        assert_eq!(collector.reduce_fruits(vec![])?, vec![0, 0, 0]);
+        Ok(())
     }
 
     #[test]
@@ -288,4 +367,35 @@ mod tests {
         assert_eq!(week_histogram, vec![0, 0, 1, 1, 0, 0, 1, 0, 0, 0]);
         Ok(())
     }
+
+    #[test]
+    fn test_histogram_multivalued_dates() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let date_field = schema_builder.add_date_field(
+            "date_field",
+            IntOptions::default().set_fast(Cardinality::MultiValues),
+        );
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        let mut writer = index.writer_with_num_threads(1, 4_000_000)?;
+        writer.add_document(doc!(
+            date_field=>Utc.ymd(1982, 9, 17).and_hms(0, 0, 0),
+            date_field=>Utc.ymd(1982, 10, 17).and_hms(0, 0, 0),
+            date_field=>Utc.ymd(1986, 3, 9).and_hms(0, 0, 0)
+        ));
+        writer.add_document(doc!(date_field=>Utc.ymd(1983, 9, 27).and_hms(0, 0, 0)));
+        writer.add_document(doc!());
+        writer.commit()?;
+        let reader = index.reader()?;
+        let searcher = reader.searcher();
+        let year_histogram_collector = HistogramCollector::new(
+            date_field,
+            Utc.ymd(1980, 1, 1).and_hms(0, 0, 0),
+            3600 * 24 * 365,
+            10,
+        );
+        let year_histogram = searcher.search(&AllQuery, &year_histogram_collector)?;
+        assert_eq!(year_histogram, vec![0, 0, 1, 1, 0, 0, 1, 0, 0, 0]);
+        Ok(())
+    }
 }
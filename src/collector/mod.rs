@@ -9,6 +9,7 @@ Your fruit could for instance be :
 - [the count of matching documents](./struct.Count.html)
 - [the top 10 documents, by relevancy or by a fast field](./struct.TopDocs.html)
 - [facet counts](./struct.FacetCollector.html)
+- [document counts per hour, day or month](./struct.DateHistogramCollector.html)
 
 At one point in your code, you will trigger the actual search operation by calling
 [the `search(...)` method of your `Searcher` object](../struct.Searcher.html#method.search).
@@ -96,11 +97,16 @@ pub use self::count_collector::Count;
 mod histogram_collector;
 pub use histogram_collector::HistogramCollector;
 
+mod date_histogram_collector;
+pub use self::date_histogram_collector::{DateHistogramCollector, DateTruncation};
+
 mod multi_collector;
 pub use self::multi_collector::MultiCollector;
 
 mod top_collector;
 
+mod diversified_top_collector;
+
 mod top_score_collector;
 pub use self::top_score_collector::TopDocs;
 
@@ -110,6 +116,9 @@ pub use self::custom_score_top_collector::{CustomScorer, CustomSegmentScorer};
 mod tweak_score_top_collector;
 pub use self::tweak_score_top_collector::{ScoreSegmentTweaker, ScoreTweaker};
 
+mod rescore;
+pub use self::rescore::{QueryRescorer, Rescore, Rescorer, ScoreNormalization};
+
 mod facet_collector;
 pub use self::facet_collector::FacetCollector;
 pub use self::facet_collector::FacetCounts;
@@ -162,13 +171,58 @@ pub trait Collector: Sync + Send {
     /// Returns true iff the collector requires to compute scores for documents.
     fn requires_scoring(&self) -> bool;
 
-    /// Combines the fruit associated to the collection of each segments
-    /// into one fruit.
+    /// Returns an estimate of the number of bytes allocated by the
+    /// `SegmentCollector` of the given segment.
+    ///
+    /// This estimate is charged to the memory budget of the query before
+    /// collecting the segment. By default, it is assumed to be negligible.
+    fn segment_memory_usage(&self, _segment: &SegmentReader) -> usize {
+        0
+    }
+
+    /// Merges the fruits of two disjoint sets of segments into
+    /// the fruit of their union.
+    ///
+    /// The merge needs to be associative, as fruits may be merged in any grouping:
+    /// by a tree reduction, in parallel, or across the shards of an index.
+    /// `left` however always covers segments that come before those of `right`.
     fn merge_fruits(
         &self,
-        segment_fruits: Vec<<Self::Child as SegmentCollector>::Fruit>,
+        left: <Self::Child as SegmentCollector>::Fruit,
+        right: <Self::Child as SegmentCollector>::Fruit,
+    ) -> crate::Result<<Self::Child as SegmentCollector>::Fruit>;
+
+    /// Turns the merged fruit of all of the segments into the fruit of the collector.
+    ///
+    /// `merged_fruit` is `None` if there are no segments.
+    fn finalize_fruit(
+        &self,
+        merged_fruit: Option<<Self::Child as SegmentCollector>::Fruit>,
     ) -> crate::Result<Self::Fruit>;
 
+    /// Combines the fruits of the collection of each segment into one fruit.
+    ///
+    /// The fruits are merged pairwise, as a balanced tree, before being finalized.
+    fn reduce_fruits(
+        &self,
+        segment_fruits: Vec<<Self::Child as SegmentCollector>::Fruit>,
+    ) -> crate::Result<Self::Fruit> {
+        let mut fruits = segment_fruits;
+        while fruits.len() > 1 {
+            let mut merged_fruits = Vec::with_capacity((fruits.len() + 1) / 2);
+            let mut fruits_it = fruits.into_iter();
+            while let Some(left) = fruits_it.next() {
+                if let Some(right) = fruits_it.next() {
+                    merged_fruits.push(self.merge_fruits(left, right)?);
+                } else {
+                    merged_fruits.push(left);
+                }
+            }
+            fruits = merged_fruits;
+        }
+        self.finalize_fruit(fruits.pop())
+    }
+
     /// Created a segment collector and
     fn collect_segment(
         &self,
@@ -231,16 +285,29 @@ impl<TCollector: Collector> Collector for Option<TCollector> {
             .unwrap_or(false)
     }
 
+    fn segment_memory_usage(&self, segment: &SegmentReader) -> usize {
+        self.as_ref()
+            .map(|inner| inner.segment_memory_usage(segment))
+            .unwrap_or(0)
+    }
+
     fn merge_fruits(
         &self,
-        segment_fruits: Vec<<Self::Child as SegmentCollector>::Fruit>,
+        left: <Self::Child as SegmentCollector>::Fruit,
+        right: <Self::Child as SegmentCollector>::Fruit,
+    ) -> crate::Result<<Self::Child as SegmentCollector>::Fruit> {
+        match (self.as_ref(), left, right) {
+            (Some(inner), Some(left), Some(right)) => Ok(Some(inner.merge_fruits(left, right)?)),
+            (_, left, right) => Ok(left.or(right)),
+        }
+    }
+
+    fn finalize_fruit(
+        &self,
+        merged_fruit: Option<<Self::Child as SegmentCollector>::Fruit>,
     ) -> crate::Result<Self::Fruit> {
         if let Some(inner) = self.as_ref() {
-            let inner_segment_fruits: Vec<_> = segment_fruits
-                .into_iter()
-                .flat_map(|fruit_opt| fruit_opt.into_iter())
-                .collect();
-            let fruit = inner.merge_fruits(inner_segment_fruits)?;
+            let fruit = inner.finalize_fruit(merged_fruit.flatten())?;
             Ok(Some(fruit))
         } else {
             Ok(None)
@@ -290,19 +357,32 @@ where
         self.0.requires_scoring() || self.1.requires_scoring()
     }
 
+    fn segment_memory_usage(&self, segment: &SegmentReader) -> usize {
+        self.0.segment_memory_usage(segment) + self.1.segment_memory_usage(segment)
+    }
+
     fn merge_fruits(
         &self,
-        segment_fruits: Vec<<Self::Child as SegmentCollector>::Fruit>,
+        left: <Self::Child as SegmentCollector>::Fruit,
+        right: <Self::Child as SegmentCollector>::Fruit,
+    ) -> crate::Result<<Self::Child as SegmentCollector>::Fruit> {
+        Ok((
+            self.0.merge_fruits(left.0, right.0)?,
+            self.1.merge_fruits(left.1, right.1)?,
+        ))
+    }
+
+    fn finalize_fruit(
+        &self,
+        merged_fruit: Option<<Self::Child as SegmentCollector>::Fruit>,
     ) -> crate::Result<(Left::Fruit, Right::Fruit)> {
-        let mut left_fruits = vec![];
-        let mut right_fruits = vec![];
-        for (left_fruit, right_fruit) in segment_fruits {
-            left_fruits.push(left_fruit);
-            right_fruits.push(right_fruit);
-        }
+        let (left_fruit, right_fruit) = match merged_fruit {
+            Some((left_fruit, right_fruit)) => (Some(left_fruit), Some(right_fruit)),
+            None => (None, None),
+        };
         Ok((
-            self.0.merge_fruits(left_fruits)?,
-            self.1.merge_fruits(right_fruits)?,
+            self.0.finalize_fruit(left_fruit)?,
+            self.1.finalize_fruit(right_fruit)?,
         ))
     }
 }
@@ -350,22 +430,38 @@ where
         self.0.requires_scoring() || self.1.requires_scoring() || self.2.requires_scoring()
     }
 
+    fn segment_memory_usage(&self, segment: &SegmentReader) -> usize {
+        self.0.segment_memory_usage(segment)
+            + self.1.segment_memory_usage(segment)
+            + self.2.segment_memory_usage(segment)
+    }
+
     fn merge_fruits(
         &self,
-        children: Vec<<Self::Child as SegmentCollector>::Fruit>,
+        left: <Self::Child as SegmentCollector>::Fruit,
+        right: <Self::Child as SegmentCollector>::Fruit,
+    ) -> crate::Result<<Self::Child as SegmentCollector>::Fruit> {
+        Ok((
+            self.0.merge_fruits(left.0, right.0)?,
+            self.1.merge_fruits(left.1, right.1)?,
+            self.2.merge_fruits(left.2, right.2)?,
+        ))
+    }
+
+    fn finalize_fruit(
+        &self,
+        merged_fruit: Option<<Self::Child as SegmentCollector>::Fruit>,
     ) -> crate::Result<Self::Fruit> {
-        let mut one_fruits = vec![];
-        let mut two_fruits = vec![];
-        let mut three_fruits = vec![];
-        for (one_fruit, two_fruit, three_fruit) in children {
-            one_fruits.push(one_fruit);
-            two_fruits.push(two_fruit);
-            three_fruits.push(three_fruit);
-        }
+        let (one_fruit, two_fruit, three_fruit) = match merged_fruit {
+            Some((one_fruit, two_fruit, three_fruit)) => {
+                (Some(one_fruit), Some(two_fruit), Some(three_fruit))
+            }
+            None => (None, None, None),
+        };
         Ok((
-            self.0.merge_fruits(one_fruits)?,
-            self.1.merge_fruits(two_fruits)?,
-            self.2.merge_fruits(three_fruits)?,
+            self.0.finalize_fruit(one_fruit)?,
+            self.1.finalize_fruit(two_fruit)?,
+            self.2.finalize_fruit(three_fruit)?,
         ))
     }
 }
@@ -420,25 +516,44 @@ where
             || self.3.requires_scoring()
     }
 
+    fn segment_memory_usage(&self, segment: &SegmentReader) -> usize {
+        self.0.segment_memory_usage(segment)
+            + self.1.segment_memory_usage(segment)
+            + self.2.segment_memory_usage(segment)
+            + self.3.segment_memory_usage(segment)
+    }
+
     fn merge_fruits(
         &self,
-        children: Vec<<Self::Child as SegmentCollector>::Fruit>,
+        left: <Self::Child as SegmentCollector>::Fruit,
+        right: <Self::Child as SegmentCollector>::Fruit,
+    ) -> crate::Result<<Self::Child as SegmentCollector>::Fruit> {
+        Ok((
+            self.0.merge_fruits(left.0, right.0)?,
+            self.1.merge_fruits(left.1, right.1)?,
+            self.2.merge_fruits(left.2, right.2)?,
+            self.3.merge_fruits(left.3, right.3)?,
+        ))
+    }
+
+    fn finalize_fruit(
+        &self,
+        merged_fruit: Option<<Self::Child as SegmentCollector>::Fruit>,
     ) -> crate::Result<Self::Fruit> {
-        let mut one_fruits = vec![];
-        let mut two_fruits = vec![];
-        let mut three_fruits = vec![];
-        let mut four_fruits = vec![];
-        for (one_fruit, two_fruit, three_fruit, four_fruit) in children {
-            one_fruits.push(one_fruit);
-            two_fruits.push(two_fruit);
-            three_fruits.push(three_fruit);
-            four_fruits.push(four_fruit);
-        }
+        let (one_fruit, two_fruit, three_fruit, four_fruit) = match merged_fruit {
+            Some((one_fruit, two_fruit, three_fruit, four_fruit)) => (
+                Some(one_fruit),
+                Some(two_fruit),
+                Some(three_fruit),
+                Some(four_fruit),
+            ),
+            None => (None, None, None, None),
+        };
         Ok((
-            self.0.merge_fruits(one_fruits)?,
-            self.1.merge_fruits(two_fruits)?,
-            self.2.merge_fruits(three_fruits)?,
-            self.3.merge_fruits(four_fruits)?,
+            self.0.finalize_fruit(one_fruit)?,
+            self.1.finalize_fruit(two_fruit)?,
+            self.2.finalize_fruit(three_fruit)?,
+            self.3.finalize_fruit(four_fruit)?,
         ))
     }
 }
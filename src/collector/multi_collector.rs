@@ -32,24 +32,36 @@ impl<TCollector: Collector> Collector for CollectorWrapper<TCollector> {
         self.0.requires_scoring()
     }
 
+    fn segment_memory_usage(&self, segment: &SegmentReader) -> usize {
+        self.0.segment_memory_usage(segment)
+    }
+
     fn merge_fruits(
         &self,
-        children: Vec<<Self::Child as SegmentCollector>::Fruit>,
+        left: Box<dyn Fruit>,
+        right: Box<dyn Fruit>,
     ) -> crate::Result<Box<dyn Fruit>> {
-        let typed_fruit: Vec<<TCollector::Child as SegmentCollector>::Fruit> = children
-            .into_iter()
-            .map(|untyped_fruit| {
-                untyped_fruit
-                    .downcast::<<TCollector::Child as SegmentCollector>::Fruit>()
-                    .map(|boxed_but_typed| *boxed_but_typed)
-                    .map_err(|_| {
-                        TantivyError::InvalidArgument("Failed to cast child fruit.".to_string())
-                    })
-            })
-            .collect::<crate::Result<_>>()?;
-        let merged_fruit = self.0.merge_fruits(typed_fruit)?;
+        let merged_fruit = self
+            .0
+            .merge_fruits(downcast_fruit(left)?, downcast_fruit(right)?)?;
         Ok(Box::new(merged_fruit))
     }
+
+    fn finalize_fruit(
+        &self,
+        merged_fruit: Option<Box<dyn Fruit>>,
+    ) -> crate::Result<Box<dyn Fruit>> {
+        let typed_fruit = merged_fruit.map(downcast_fruit).transpose()?;
+        let fruit = self.0.finalize_fruit(typed_fruit)?;
+        Ok(Box::new(fruit))
+    }
+}
+
+fn downcast_fruit<TFruit: Fruit>(untyped_fruit: Box<dyn Fruit>) -> crate::Result<TFruit> {
+    untyped_fruit
+        .downcast::<TFruit>()
+        .map(|boxed_but_typed| *boxed_but_typed)
+        .map_err(|_| TantivyError::InvalidArgument("Failed to cast child fruit.".to_string()))
 }
 
 impl SegmentCollector for Box<dyn BoxableSegmentCollector> {
@@ -193,23 +205,36 @@ impl<'a> Collector for MultiCollector<'a> {
             .any(Collector::requires_scoring)
     }
 
-    fn merge_fruits(&self, segments_multifruits: Vec<MultiFruit>) -> crate::Result<MultiFruit> {
-        let mut segment_fruits_list: Vec<Vec<Box<dyn Fruit>>> = (0..self.collector_wrappers.len())
-            .map(|_| Vec::with_capacity(segments_multifruits.len()))
-            .collect::<Vec<_>>();
-        for segment_multifruit in segments_multifruits {
-            for (idx, segment_fruit_opt) in segment_multifruit.sub_fruits.into_iter().enumerate() {
-                if let Some(segment_fruit) = segment_fruit_opt {
-                    segment_fruits_list[idx].push(segment_fruit);
-                }
-            }
-        }
+    fn segment_memory_usage(&self, segment: &SegmentReader) -> usize {
+        self.collector_wrappers
+            .iter()
+            .map(|collector_wrapper| collector_wrapper.segment_memory_usage(segment))
+            .sum()
+    }
+
+    fn merge_fruits(&self, left: MultiFruit, right: MultiFruit) -> crate::Result<MultiFruit> {
+        let sub_fruits = self
+            .collector_wrappers
+            .iter()
+            .zip(left.sub_fruits.into_iter().zip(right.sub_fruits))
+            .map(|(child_collector, sub_fruits)| match sub_fruits {
+                (Some(left), Some(right)) => Ok(Some(child_collector.merge_fruits(left, right)?)),
+                (left, right) => Ok(left.or(right)),
+            })
+            .collect::<crate::Result<_>>()?;
+        Ok(MultiFruit { sub_fruits })
+    }
+
+    fn finalize_fruit(&self, merged_fruit: Option<MultiFruit>) -> crate::Result<MultiFruit> {
+        let merged_sub_fruits = merged_fruit
+            .map(|multifruit| multifruit.sub_fruits)
+            .unwrap_or_else(|| self.collector_wrappers.iter().map(|_| None).collect());
         let sub_fruits = self
             .collector_wrappers
             .iter()
-            .zip(segment_fruits_list)
-            .map(|(child_collector, segment_fruits)| {
-                Ok(Some(child_collector.merge_fruits(segment_fruits)?))
+            .zip(merged_sub_fruits)
+            .map(|(child_collector, merged_sub_fruit)| {
+                Ok(Some(child_collector.finalize_fruit(merged_sub_fruit)?))
             })
             .collect::<crate::Result<_>>()?;
         Ok(MultiFruit { sub_fruits })
@@ -0,0 +1,349 @@
+use crate::query::rewrite::try_rewrite_query;
+use crate::query::Query;
+use crate::{DocAddress, DocSet, Score, Searcher, TantivyError};
+use std::cmp::Ordering;
+use std::fmt;
+
+/// Computes the second-phase scores of the hits of a rescoring window.
+///
+/// See [`Rescore`](./struct.Rescore.html).
+///
+/// This trait is implemented for closures taking the searcher and
+/// the addresses of the hits.
+pub trait Rescorer: Send + Sync {
+    /// Returns the score of each of the `doc_addresses`, in the same order,
+    /// or `None` for the documents that are not matched by the rescorer.
+    ///
+    /// `doc_addresses` are sorted by increasing `DocAddress`.
+    fn rescore(
+        &self,
+        searcher: &Searcher,
+        doc_addresses: &[DocAddress],
+    ) -> crate::Result<Vec<Option<Score>>>;
+}
+
+impl<F> Rescorer for F
+where
+    F: Fn(&Searcher, &[DocAddress]) -> crate::Result<Vec<Option<Score>>> + Send + Sync,
+{
+    fn rescore(
+        &self,
+        searcher: &Searcher,
+        doc_addresses: &[DocAddress],
+    ) -> crate::Result<Vec<Option<Score>>> {
+        (self)(searcher, doc_addresses)
+    }
+}
+
+/// `Rescorer` scoring the hits with a query.
+///
+/// The query is only evaluated on the hits of the rescoring window, which makes it possible
+/// to use queries that would be too expensive to run on the whole index,
+/// e.g. a sloppy phrase query.
+pub struct QueryRescorer {
+    query: Box<dyn Query>,
+}
+
+impl QueryRescorer {
+    /// Creates a rescorer scoring the hits with `query`.
+    pub fn new(query: Box<dyn Query>) -> QueryRescorer {
+        QueryRescorer { query }
+    }
+}
+
+impl fmt::Debug for QueryRescorer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "QueryRescorer(query={:?})", self.query)
+    }
+}
+
+impl Rescorer for QueryRescorer {
+    fn rescore(
+        &self,
+        searcher: &Searcher,
+        doc_addresses: &[DocAddress],
+    ) -> crate::Result<Vec<Option<Score>>> {
+        let rewritten_query_opt = try_rewrite_query(self.query.as_ref(), searcher, true)?;
+        let query: &dyn Query = rewritten_query_opt
+            .as_deref()
+            .unwrap_or(self.query.as_ref());
+        let weight = query.weight(searcher, true)?;
+        let mut scores = Vec::with_capacity(doc_addresses.len());
+        let mut start = 0;
+        while start < doc_addresses.len() {
+            let segment_ord = doc_addresses[start].segment_ord;
+            let end = start
+                + doc_addresses[start..]
+                    .iter()
+                    .take_while(|doc_address| doc_address.segment_ord == segment_ord)
+                    .count();
+            let mut scorer = weight.scorer(searcher.segment_reader(segment_ord), 1.0)?;
+            for doc_address in &doc_addresses[start..end] {
+                let doc = doc_address.doc_id;
+                // The scorer may already be past `doc`, in which case it does not match.
+                if scorer.doc() <= doc && scorer.seek(doc) == doc {
+                    scores.push(Some(scorer.score()));
+                } else {
+                    scores.push(None);
+                }
+            }
+            start = end;
+        }
+        Ok(scores)
+    }
+}
+
+/// Normalization of the scores of each phase, before they are combined.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScoreNormalization {
+    /// Scores are combined as is.
+    None,
+    /// Scores are scaled to `[0, 1]` using the minimum and maximum scores
+    /// of the phase over the rescoring window.
+    ///
+    /// This makes scores of different scales comparable, e.g. BM25 scores and vector similarities.
+    /// If all of the scores of a phase are equal, they are scaled to 1.
+    MinMax,
+}
+
+/// Rescoring stage of a two-phase search.
+///
+/// The first phase retrieves the top hits of a cheap query, e.g. with
+/// [`TopDocs`](./struct.TopDocs.html). The second phase rescores the `window_size` best
+/// of these hits with a [`Rescorer`](./trait.Rescorer.html), without running the collection
+/// again.
+///
+/// The score of a rescored hit is
+/// `query_weight * first_phase_score + rescore_weight * second_phase_score`,
+/// where the second phase score of the hits that are not matched by the rescorer is 0.
+/// Both weights are 1 by default, and the scores can be normalized beforehand
+/// (see [`ScoreNormalization`](./enum.ScoreNormalization.html)).
+///
+/// The rescored hits are sorted by decreasing score, and are followed by the hits
+/// beyond the window, which keep their first phase scores and order.
+///
+/// ```rust
+/// use tantivy::collector::{QueryRescorer, Rescore};
+/// use tantivy::query::{PhraseQuery, QueryParser};
+/// use tantivy::schema::{Schema, TEXT};
+/// use tantivy::{doc, Index, Term};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer(3_000_000)?;
+/// index_writer.add_document(doc!(title => "The diary of the wind"));
+/// index_writer.add_document(doc!(title => "The name of the wind"));
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let query = QueryParser::for_index(&index, vec![title]).parse_query("name wind")?;
+/// let phrase_query = PhraseQuery::new(vec![
+///     Term::from_field_text(title, "name"),
+///     Term::from_field_text(title, "of"),
+/// ]);
+/// // Rescores the 100 best hits, boosting the ones matching the phrase.
+/// let rescore = Rescore::new(QueryRescorer::new(Box::new(phrase_query)), 100)
+///     .rescore_weight(2.0);
+/// let top_docs = searcher.search_with_rescore(&query, 10, &rescore)?;
+/// assert_eq!(top_docs.len(), 2);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Rescore<R> {
+    rescorer: R,
+    window_size: usize,
+    query_weight: Score,
+    rescore_weight: Score,
+    normalization: ScoreNormalization,
+}
+
+impl<R: Rescorer> Rescore<R> {
+    /// Creates a rescoring stage rescoring the `window_size` best hits with `rescorer`.
+    pub fn new(rescorer: R, window_size: usize) -> Rescore<R> {
+        Rescore {
+            rescorer,
+            window_size,
+            query_weight: 1.0,
+            rescore_weight: 1.0,
+            normalization: ScoreNormalization::None,
+        }
+    }
+
+    /// Sets the weight of the first phase scores.
+    pub fn query_weight(mut self, query_weight: Score) -> Rescore<R> {
+        self.query_weight = query_weight;
+        self
+    }
+
+    /// Sets the weight of the second phase scores.
+    pub fn rescore_weight(mut self, rescore_weight: Score) -> Rescore<R> {
+        self.rescore_weight = rescore_weight;
+        self
+    }
+
+    /// Sets the normalization of the scores of each phase.
+    pub fn normalization(mut self, normalization: ScoreNormalization) -> Rescore<R> {
+        self.normalization = normalization;
+        self
+    }
+
+    /// Returns the number of hits rescored.
+    pub fn window_size(&self) -> usize {
+        self.window_size
+    }
+
+    /// Rescores `hits`, sorted by decreasing first phase score.
+    pub fn rescore(
+        &self,
+        searcher: &Searcher,
+        mut hits: Vec<(Score, DocAddress)>,
+    ) -> crate::Result<Vec<(Score, DocAddress)>> {
+        let rest = hits.split_off(self.window_size.min(hits.len()));
+        let mut window = hits;
+        window.sort_by_key(|&(_, doc_address)| doc_address);
+        let doc_addresses: Vec<DocAddress> =
+            window.iter().map(|&(_, doc_address)| doc_address).collect();
+        let rescores = self.rescorer.rescore(searcher, &doc_addresses)?;
+        if rescores.len() != window.len() {
+            return Err(TantivyError::InvalidArgument(format!(
+                "The rescorer returned {} scores for {} hits",
+                rescores.len(),
+                window.len()
+            )));
+        }
+        let mut query_scores: Vec<Score> = window.iter().map(|&(score, _)| score).collect();
+        let mut rescores: Vec<Score> = rescores
+            .into_iter()
+            .map(Option::unwrap_or_default)
+            .collect();
+        if self.normalization == ScoreNormalization::MinMax {
+            normalize_min_max(&mut query_scores);
+            normalize_min_max(&mut rescores);
+        }
+        let mut rescored_hits: Vec<(Score, DocAddress)> = query_scores
+            .into_iter()
+            .zip(rescores)
+            .zip(doc_addresses)
+            .map(|((query_score, rescore), doc_address)| {
+                (
+                    self.query_weight * query_score + self.rescore_weight * rescore,
+                    doc_address,
+                )
+            })
+            .collect();
+        // In case of a tie, hits are sorted by ascending `DocAddress`, like in `TopDocs`.
+        rescored_hits.sort_by(|left, right| {
+            right
+                .0
+                .partial_cmp(&left.0)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| left.1.cmp(&right.1))
+        });
+        rescored_hits.extend(rest);
+        Ok(rescored_hits)
+    }
+}
+
+fn normalize_min_max(scores: &mut [Score]) {
+    let min = scores.iter().cloned().fold(Score::INFINITY, Score::min);
+    let max = scores.iter().cloned().fold(Score::NEG_INFINITY, Score::max);
+    for score in scores.iter_mut() {
+        *score = if max > min {
+            (*score - min) / (max - min)
+        } else {
+            1.0
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{QueryRescorer, Rescore, ScoreNormalization};
+    use crate::collector::TopDocs;
+    use crate::fastfield::FastFieldReader;
+    use crate::query::TermQuery;
+    use crate::schema::{IndexRecordOption, Schema, FAST, TEXT};
+    use crate::{DocAddress, Index, Score, Searcher, Term};
+
+    #[test]
+    fn test_rescore() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let popularity_field = schema_builder.add_u64_field("popularity", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for (text, popularity) in &[
+            ("a a a", 1u64),
+            ("a b", 3u64),
+            ("a a", 2u64),
+            ("a", 4u64),
+            ("b", 5u64),
+        ] {
+            index_writer.add_document(doc!(text_field => *text, popularity_field => *popularity));
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let term_query = |text: &str| {
+            TermQuery::new(
+                Term::from_field_text(text_field, text),
+                IndexRecordOption::WithFreqs,
+            )
+        };
+        let doc_ids = |hits: &[(Score, DocAddress)]| -> Vec<u32> {
+            hits.iter()
+                .map(|(_, doc_address)| doc_address.doc_id)
+                .collect()
+        };
+        let hits = searcher.search(&term_query("a"), &TopDocs::with_limit(10))?;
+        assert_eq!(doc_ids(&hits), vec![0, 2, 3, 1]);
+
+        // Only the 3 best hits are rescored, the 4th one keeps its rank.
+        let rescore =
+            Rescore::new(QueryRescorer::new(Box::new(term_query("b"))), 3).rescore_weight(10.0);
+        let rescored_hits = rescore.rescore(&searcher, hits.clone())?;
+        assert_eq!(doc_ids(&rescored_hits), vec![0, 2, 3, 1]);
+        let rescore =
+            Rescore::new(QueryRescorer::new(Box::new(term_query("b"))), 4).rescore_weight(10.0);
+        let rescored_hits = searcher.search_with_rescore(&term_query("a"), 2, &rescore)?;
+        assert_eq!(doc_ids(&rescored_hits), vec![1, 0]);
+        let b_score = searcher
+            .search(&term_query("b"), &TopDocs::with_limit(10))?
+            .into_iter()
+            .find(|(_, doc_address)| doc_address.doc_id == 1)
+            .unwrap()
+            .0;
+        let a_score = hits[3].0;
+        assert!((rescored_hits[0].0 - (a_score + 10.0 * b_score)).abs() < 1e-5);
+
+        // Custom rescorer, ranking the hits by popularity only.
+        let popularity_rescorer = move |searcher: &Searcher,
+                                        doc_addresses: &[DocAddress]|
+              -> crate::Result<Vec<Option<Score>>> {
+            doc_addresses
+                .iter()
+                .map(|doc_address| {
+                    let popularity_reader = searcher
+                        .segment_reader(doc_address.segment_ord)
+                        .fast_fields()
+                        .u64(popularity_field)?;
+                    Ok(Some(popularity_reader.get(doc_address.doc_id) as Score))
+                })
+                .collect()
+        };
+        let rescore = Rescore::new(popularity_rescorer, 10).query_weight(0.0);
+        let rescored_hits = rescore.rescore(&searcher, hits.clone())?;
+        assert_eq!(doc_ids(&rescored_hits), vec![3, 1, 2, 0]);
+        assert_eq!(rescored_hits[0].0, 4.0);
+
+        let rescore = Rescore::new(popularity_rescorer, 10)
+            .normalization(ScoreNormalization::MinMax)
+            .query_weight(2.0);
+        let rescored_hits = rescore.rescore(&searcher, hits)?;
+        assert_eq!(doc_ids(&rescored_hits), vec![3, 2, 0, 1]);
+        // The best first phase score and the lowest popularity.
+        assert_eq!(rescored_hits[2].0, 2.0);
+        Ok(())
+    }
+}
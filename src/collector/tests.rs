@@ -122,21 +122,14 @@ impl Collector for TestCollector {
         self.compute_score
     }
 
-    fn merge_fruits(&self, mut children: Vec<TestFruit>) -> crate::Result<TestFruit> {
-        children.sort_by_key(|fruit| {
-            if fruit.docs().is_empty() {
-                0
-            } else {
-                fruit.docs()[0].segment_ord
-            }
-        });
-        let mut docs = vec![];
-        let mut scores = vec![];
-        for child in children {
-            docs.extend(child.docs());
-            scores.extend(child.scores);
-        }
-        Ok(TestFruit { docs, scores })
+    fn merge_fruits(&self, mut left: TestFruit, right: TestFruit) -> crate::Result<TestFruit> {
+        left.docs.extend(right.docs);
+        left.scores.extend(right.scores);
+        Ok(left)
+    }
+
+    fn finalize_fruit(&self, merged_fruit: Option<TestFruit>) -> crate::Result<TestFruit> {
+        Ok(merged_fruit.unwrap_or_default())
     }
 }
 
@@ -195,8 +188,13 @@ impl Collector for FastFieldTestCollector {
         false
     }
 
-    fn merge_fruits(&self, children: Vec<Vec<u64>>) -> crate::Result<Vec<u64>> {
-        Ok(children.into_iter().flat_map(|v| v.into_iter()).collect())
+    fn merge_fruits(&self, mut left: Vec<u64>, right: Vec<u64>) -> crate::Result<Vec<u64>> {
+        left.extend(right);
+        Ok(left)
+    }
+
+    fn finalize_fruit(&self, merged_vals: Option<Vec<u64>>) -> crate::Result<Vec<u64>> {
+        Ok(merged_vals.unwrap_or_default())
     }
 }
 
@@ -252,8 +250,13 @@ impl Collector for BytesFastFieldTestCollector {
         false
     }
 
-    fn merge_fruits(&self, children: Vec<Vec<u8>>) -> crate::Result<Vec<u8>> {
-        Ok(children.into_iter().flat_map(|c| c.into_iter()).collect())
+    fn merge_fruits(&self, mut left: Vec<u8>, right: Vec<u8>) -> crate::Result<Vec<u8>> {
+        left.extend(right);
+        Ok(left)
+    }
+
+    fn finalize_fruit(&self, merged_vals: Option<Vec<u8>>) -> crate::Result<Vec<u8>> {
+        Ok(merged_vals.unwrap_or_default())
     }
 }
 
@@ -296,3 +299,45 @@ fn test_option_collector_none() -> crate::Result<()> {
     assert_eq!(counts, None);
     Ok(())
 }
+
+#[test]
+fn test_reduce_fruits_preserves_segment_order() -> crate::Result<()> {
+    let segment_fruits: Vec<TestFruit> = (0..5u32)
+        .map(|segment_ord| TestFruit {
+            docs: vec![DocAddress::new(segment_ord, 0)],
+            scores: vec![segment_ord as Score],
+        })
+        .collect();
+    let fruit = TEST_COLLECTOR_WITH_SCORE.reduce_fruits(segment_fruits)?;
+    let expected_docs: Vec<DocAddress> = (0..5u32)
+        .map(|segment_ord| DocAddress::new(segment_ord, 0))
+        .collect();
+    assert_eq!(fruit.docs(), &expected_docs[..]);
+    assert_eq!(fruit.scores(), &[0.0, 1.0, 2.0, 3.0, 4.0]);
+    assert!(TEST_COLLECTOR_WITH_SCORE
+        .reduce_fruits(Vec::new())?
+        .docs()
+        .is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_reduce_fruits_tuple() -> crate::Result<()> {
+    let collector = (Count, TopDocs::with_limit(2).and_offset(1));
+    let segment_fruits = vec![
+        (
+            2,
+            vec![(3.0, DocAddress::new(0, 1)), (1.0, DocAddress::new(0, 0))],
+        ),
+        (1, vec![(2.0, DocAddress::new(1, 0))]),
+        (1, vec![(4.0, DocAddress::new(2, 0))]),
+    ];
+    let (count, top_docs) = collector.reduce_fruits(segment_fruits)?;
+    assert_eq!(count, 4);
+    assert_eq!(
+        top_docs,
+        vec![(3.0, DocAddress::new(0, 1)), (2.0, DocAddress::new(1, 0))]
+    );
+    assert_eq!(collector.reduce_fruits(Vec::new())?, (0, Vec::new()));
+    Ok(())
+}
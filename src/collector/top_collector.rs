@@ -5,6 +5,7 @@ use crate::SegmentReader;
 use std::cmp::Ordering;
 use std::collections::BinaryHeap;
 use std::marker::PhantomData;
+use std::mem;
 
 /// Contains a feature (field, score, etc.) of a document along with the document address.
 ///
@@ -89,33 +90,43 @@ where
         self
     }
 
+    /// Merges the top documents of two sets of segments, keeping the `limit + offset`
+    /// best ones.
     pub fn merge_fruits(
         &self,
-        children: Vec<Vec<(T, DocAddress)>>,
+        left: Vec<(T, DocAddress)>,
+        right: Vec<(T, DocAddress)>,
     ) -> crate::Result<Vec<(T, DocAddress)>> {
-        if self.limit == 0 {
-            return Ok(Vec::new());
-        }
         let mut top_collector = BinaryHeap::new();
-        for child_fruit in children {
-            for (feature, doc) in child_fruit {
-                if top_collector.len() < (self.limit + self.offset) {
-                    top_collector.push(ComparableDoc { feature, doc });
-                } else if let Some(mut head) = top_collector.peek_mut() {
-                    if head.feature < feature {
-                        *head = ComparableDoc { feature, doc };
-                    }
+        for (feature, doc) in left.into_iter().chain(right) {
+            if top_collector.len() < (self.limit + self.offset) {
+                top_collector.push(ComparableDoc { feature, doc });
+            } else if let Some(mut head) = top_collector.peek_mut() {
+                if head.feature < feature {
+                    *head = ComparableDoc { feature, doc };
                 }
             }
         }
         Ok(top_collector
             .into_sorted_vec()
             .into_iter()
-            .skip(self.offset)
             .map(|cdoc| (cdoc.feature, cdoc.doc))
             .collect())
     }
 
+    /// Skips the `offset` first of the merged top documents.
+    pub fn finalize_fruit(
+        &self,
+        merged_fruit: Option<Vec<(T, DocAddress)>>,
+    ) -> crate::Result<Vec<(T, DocAddress)>> {
+        Ok(merged_fruit
+            .unwrap_or_default()
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit)
+            .collect())
+    }
+
     pub(crate) fn for_segment<F: PartialOrd>(
         &self,
         segment_id: SegmentOrdinal,
@@ -124,6 +135,11 @@ where
         TopSegmentCollector::new(segment_id, self.limit + self.offset)
     }
 
+    /// Returns the number of bytes of the heap of a `TopSegmentCollector`.
+    pub(crate) fn segment_memory_usage(&self) -> usize {
+        (self.limit + self.offset).saturating_mul(mem::size_of::<ComparableDoc<T, DocId>>())
+    }
+
     /// Create a new TopCollector with the same limit and offset.
     ///
     /// Ideally we would use Into but the blanket implementation seems to cause the Scorer traits
@@ -278,13 +294,13 @@ mod tests {
         let collector = TopCollector::with_limit(2).and_offset(1);
 
         let results = collector
-            .merge_fruits(vec![vec![
+            .finalize_fruit(Some(vec![
                 (0.9, DocAddress::new(0, 1)),
                 (0.8, DocAddress::new(0, 2)),
                 (0.7, DocAddress::new(0, 3)),
                 (0.6, DocAddress::new(0, 4)),
                 (0.5, DocAddress::new(0, 5)),
-            ]])
+            ]))
             .unwrap();
 
         assert_eq!(
@@ -298,10 +314,10 @@ mod tests {
         let collector = TopCollector::with_limit(2).and_offset(1);
 
         let results = collector
-            .merge_fruits(vec![vec![
+            .finalize_fruit(Some(vec![
                 (0.9, DocAddress::new(0, 1)),
                 (0.8, DocAddress::new(0, 2)),
-            ]])
+            ]))
             .unwrap();
 
         assert_eq!(results, vec![(0.8, DocAddress::new(0, 2)),]);
@@ -312,14 +328,32 @@ mod tests {
         let collector = TopCollector::with_limit(2).and_offset(20);
 
         let results = collector
-            .merge_fruits(vec![vec![
+            .finalize_fruit(Some(vec![
                 (0.9, DocAddress::new(0, 1)),
                 (0.8, DocAddress::new(0, 2)),
-            ]])
+            ]))
             .unwrap();
 
         assert_eq!(results, vec![]);
     }
+
+    #[test]
+    fn test_top_collector_merge_with_offset() -> crate::Result<()> {
+        let collector = TopCollector::with_limit(2).and_offset(1);
+        let left = collector.merge_fruits(
+            vec![(0.9, DocAddress::new(0, 1)), (0.5, DocAddress::new(0, 2))],
+            vec![(0.7, DocAddress::new(1, 1))],
+        )?;
+        assert_eq!(left.len(), 3);
+        let right = vec![(0.8, DocAddress::new(2, 1)), (0.6, DocAddress::new(2, 2))];
+        let merged = collector.merge_fruits(left, right)?;
+        assert_eq!(
+            collector.finalize_fruit(Some(merged))?,
+            vec![(0.8, DocAddress::new(2, 1)), (0.7, DocAddress::new(1, 1))]
+        );
+        assert!(collector.finalize_fruit(None)?.is_empty());
+        Ok(())
+    }
 }
 
 #[cfg(all(test, feature = "unstable"))]
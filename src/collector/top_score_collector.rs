@@ -1,12 +1,15 @@
 use super::Collector;
+use crate::collector::diversified_top_collector::DiversifiedTopCollector;
 use crate::collector::top_collector::{ComparableDoc, TopCollector};
 use crate::collector::tweak_score_top_collector::TweakedScoreTopCollector;
 use crate::collector::{
     CustomScorer, CustomSegmentScorer, ScoreSegmentTweaker, ScoreTweaker, SegmentCollector,
 };
+use crate::docset::{DocSet, TERMINATED};
 use crate::fastfield::{DynamicFastFieldReader, FastFieldReader};
 use crate::query::Weight;
-use crate::schema::Field;
+use crate::schema::{Field, Type};
+use crate::u64_to_f64;
 use crate::DocAddress;
 use crate::DocId;
 use crate::Score;
@@ -23,9 +26,31 @@ struct FastFieldConvertCollector<
 > {
     pub collector: TCollector,
     pub field: Field,
+    // If true, the collector ranks `u64::MAX - val` rather than `val`.
+    pub ascending: bool,
+    // Number of documents collected per segment, i.e. limit + offset.
+    pub num_top_docs: usize,
     pub fast_value: std::marker::PhantomData<TFastValue>,
 }
 
+impl<TCollector, TFastValue> FastFieldConvertCollector<TCollector, TFastValue>
+where
+    TCollector: Collector<Fruit = Vec<(u64, DocAddress)>>,
+    TFastValue: FastValue,
+{
+    // Returns true iff the documents of the segment are sorted in the order they are ranked.
+    fn is_segment_sorted_by_rank(&self, segment: &SegmentReader) -> bool {
+        // NaN values are ranked last in both orders, which does not match the sort of the index.
+        if TFastValue::to_type() == Type::F64 {
+            return false;
+        }
+        let field_name = segment.schema().get_field_entry(self.field).name();
+        segment.index_sort().map_or(false, |index_sort| {
+            index_sort.field == field_name && index_sort.order.is_asc() == self.ascending
+        })
+    }
+}
+
 impl<TCollector, TFastValue> Collector for FastFieldConvertCollector<TCollector, TFastValue>
 where
     TCollector: Collector<Fruit = Vec<(u64, DocAddress)>>,
@@ -61,18 +86,66 @@ where
         self.collector.for_segment(segment_local_id, segment)
     }
 
+    fn collect_segment(
+        &self,
+        weight: &dyn Weight,
+        segment_ord: u32,
+        reader: &SegmentReader,
+    ) -> crate::Result<<Self::Child as SegmentCollector>::Fruit> {
+        let mut segment_collector = self.for_segment(segment_ord, reader)?;
+        if self.is_segment_sorted_by_rank(reader) {
+            // The first alive matching documents of the segment are its top documents.
+            let mut scorer = weight.scorer(reader, 1.0)?;
+            let mut num_collected = 0;
+            let mut doc = scorer.doc();
+            while doc != TERMINATED && num_collected < self.num_top_docs {
+                if !reader.is_deleted(doc) {
+                    segment_collector.collect(doc, scorer.score());
+                    num_collected += 1;
+                }
+                doc = scorer.advance();
+            }
+        } else if let Some(delete_bitset) = reader.delete_bitset() {
+            weight.for_each(reader, &mut |doc, score| {
+                if delete_bitset.is_alive(doc) {
+                    segment_collector.collect(doc, score);
+                }
+            })?;
+        } else {
+            weight.for_each(reader, &mut |doc, score| {
+                segment_collector.collect(doc, score);
+            })?;
+        }
+        Ok(segment_collector.harvest())
+    }
+
     fn requires_scoring(&self) -> bool {
         self.collector.requires_scoring()
     }
 
     fn merge_fruits(
         &self,
-        segment_fruits: Vec<<Self::Child as SegmentCollector>::Fruit>,
+        left: <Self::Child as SegmentCollector>::Fruit,
+        right: <Self::Child as SegmentCollector>::Fruit,
+    ) -> crate::Result<<Self::Child as SegmentCollector>::Fruit> {
+        self.collector.merge_fruits(left, right)
+    }
+
+    fn finalize_fruit(
+        &self,
+        merged_fruit: Option<<Self::Child as SegmentCollector>::Fruit>,
     ) -> crate::Result<Self::Fruit> {
-        let raw_result = self.collector.merge_fruits(segment_fruits)?;
+        let raw_result = self.collector.finalize_fruit(merged_fruit)?;
         let transformed_result = raw_result
             .into_iter()
-            .map(|(score, doc_address)| (TFastValue::from_u64(score), doc_address))
+            .map(|(score, doc_address)| {
+                let val = if self.ascending {
+                    u64::MAX - score
+                } else {
+                    score
+                };
+                (TFastValue::from_u64(val), doc_address)
+            })
             .collect::<Vec<_>>();
         Ok(transformed_result)
     }
@@ -130,16 +203,29 @@ impl fmt::Debug for TopDocs {
 
 struct ScorerByFastFieldReader {
     ff_reader: DynamicFastFieldReader<u64>,
+    ascending: bool,
+    is_f64: bool,
 }
 
 impl CustomSegmentScorer<u64> for ScorerByFastFieldReader {
     fn score(&mut self, doc: DocId) -> u64 {
-        self.ff_reader.get(doc)
+        let val = self.ff_reader.get(doc);
+        // NaN values are ranked last, whatever the order.
+        // The lowest score, 0, is itself the u64 representation of a NaN value.
+        if self.is_f64 && u64_to_f64(val).is_nan() {
+            return 0u64;
+        }
+        if self.ascending {
+            u64::MAX - val
+        } else {
+            val
+        }
     }
 }
 
 struct ScorerByField {
     field: Field,
+    ascending: bool,
 }
 
 impl CustomScorer<u64> for ScorerByField {
@@ -154,7 +240,17 @@ impl CustomScorer<u64> for ScorerByField {
         let ff_reader = segment_reader
             .fast_fields()
             .typed_fast_field_reader(self.field)?;
-        Ok(ScorerByFastFieldReader { ff_reader })
+        let is_f64 = segment_reader
+            .schema()
+            .get_field_entry(self.field)
+            .field_type()
+            .value_type()
+            == Type::F64;
+        Ok(ScorerByFastFieldReader {
+            ff_reader,
+            ascending: self.ascending,
+            is_f64,
+        })
     }
 }
 
@@ -284,7 +380,11 @@ impl TopDocs {
         self,
         field: Field,
     ) -> impl Collector<Fruit = Vec<(u64, DocAddress)>> {
-        CustomScoreTopCollector::new(ScorerByField { field }, self.0.into_tscore())
+        let scorer_by_field = ScorerByField {
+            field,
+            ascending: false,
+        };
+        CustomScoreTopCollector::new(scorer_by_field, self.0.into_tscore())
     }
 
     /// Set top-K to rank documents by a given fast field.
@@ -298,6 +398,8 @@ impl TopDocs {
     /// Implementation-wise, for performance reason, tantivy will manipulate the u64 representation of your fast
     /// field until the last moment.
     ///
+    /// For `f64` fields, documents whose value is NaN are ranked last.
+    ///
     /// # Example
     ///
     /// ```rust
@@ -363,10 +465,40 @@ impl TopDocs {
     where
         TFastValue: FastValue,
     {
+        let num_top_docs = self.0.limit + self.0.offset;
         let u64_collector = self.order_by_u64_field(fast_field);
         FastFieldConvertCollector {
             collector: u64_collector,
             field: fast_field,
+            ascending: false,
+            num_top_docs,
+            fast_value: PhantomData,
+        }
+    }
+
+    /// Set top-K to rank documents by a given fast field, in ascending order.
+    ///
+    /// This is the same as [.order_by_fast_field(...)](#method.order_by_fast_field),
+    /// except that the documents with the lowest values come first.
+    ///
+    /// For `f64` fields, documents whose value is NaN are ranked last in both orders.
+    pub fn order_by_fast_field_asc<TFastValue>(
+        self,
+        fast_field: Field,
+    ) -> impl Collector<Fruit = Vec<(TFastValue, DocAddress)>>
+    where
+        TFastValue: FastValue,
+    {
+        let scorer_by_field = ScorerByField {
+            field: fast_field,
+            ascending: true,
+        };
+        let num_top_docs = self.0.limit + self.0.offset;
+        FastFieldConvertCollector {
+            collector: CustomScoreTopCollector::new(scorer_by_field, self.0.into_tscore()),
+            field: fast_field,
+            ascending: true,
+            num_top_docs,
             fast_value: PhantomData,
         }
     }
@@ -593,6 +725,56 @@ impl TopDocs {
     {
         CustomScoreTopCollector::new(custom_score, self.0.into_tscore())
     }
+
+    /// Diversifies the top documents by keeping at most `max_per_value` documents
+    /// per value of a single-valued fast field, e.g. at most 2 results per site or per author.
+    ///
+    /// The best documents of each value are kept, and the other documents are skipped
+    /// in favor of the next best documents of other values. This is lighter than grouping:
+    /// the cap is enforced while collecting, and no more than `limit + offset` documents
+    /// are retained per segment.
+    ///
+    /// Values are compared through their `u64` representation, whatever the type of the field.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use tantivy::schema::{Schema, FAST, TEXT};
+    /// # use tantivy::{doc, Index};
+    /// # use tantivy::query::QueryParser;
+    /// use tantivy::collector::TopDocs;
+    ///
+    /// # fn main() -> tantivy::Result<()> {
+    /// #   let mut schema_builder = Schema::builder();
+    /// #   let title = schema_builder.add_text_field("title", TEXT);
+    /// #   let site = schema_builder.add_u64_field("site", FAST);
+    /// #   let index = Index::create_in_ram(schema_builder.build());
+    /// #   let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+    /// #   index_writer.add_document(doc!(title => "The Name of the Wind", site => 1u64));
+    /// #   index_writer.add_document(doc!(title => "The Wind in the Willows", site => 1u64));
+    /// #   index_writer.add_document(doc!(title => "Gone with the Wind", site => 2u64));
+    /// #   index_writer.commit()?;
+    /// #   let searcher = index.reader()?.searcher();
+    /// #   let query = QueryParser::for_index(&index, vec![title]).parse_query("wind")?;
+    /// // At most one document per site.
+    /// let top_docs = searcher.search(
+    ///     &query,
+    ///     &TopDocs::with_limit(10).diversify_by_fast_field(site, 1),
+    /// )?;
+    /// assert_eq!(top_docs.len(), 2);
+    /// #   Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    /// The method panics if `max_per_value` is 0.
+    pub fn diversify_by_fast_field(
+        self,
+        fast_field: Field,
+        max_per_value: usize,
+    ) -> impl Collector<Fruit = Vec<(Score, DocAddress)>> {
+        DiversifiedTopCollector::new(fast_field, max_per_value, self.0)
+    }
 }
 
 impl Collector for TopDocs {
@@ -613,11 +795,23 @@ impl Collector for TopDocs {
         true
     }
 
+    fn segment_memory_usage(&self, _segment: &SegmentReader) -> usize {
+        self.0.segment_memory_usage()
+    }
+
     fn merge_fruits(
         &self,
-        child_fruits: Vec<Vec<(Score, DocAddress)>>,
+        left: Vec<(Score, DocAddress)>,
+        right: Vec<(Score, DocAddress)>,
+    ) -> crate::Result<Vec<(Score, DocAddress)>> {
+        self.0.merge_fruits(left, right)
+    }
+
+    fn finalize_fruit(
+        &self,
+        merged_fruit: Option<Vec<(Score, DocAddress)>>,
     ) -> crate::Result<Self::Fruit> {
-        self.0.merge_fruits(child_fruits)
+        self.0.finalize_fruit(merged_fruit)
     }
 
     fn collect_segment(
@@ -707,11 +901,12 @@ mod tests {
     use super::TopDocs;
     use crate::collector::Collector;
     use crate::query::{AllQuery, Query, QueryParser};
-    use crate::schema::{Field, Schema, FAST, STORED, TEXT};
+    use crate::schema::{Field, Schema, FAST, INDEXED, STORED, TEXT};
     use crate::Index;
     use crate::IndexWriter;
     use crate::Score;
     use crate::{DocAddress, DocId, SegmentReader};
+    use crate::{IndexSettings, IndexSortByField, Order, Term};
 
     fn make_index() -> Index {
         let mut schema_builder = Schema::builder();
@@ -975,6 +1170,127 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_top_field_collector_f64_order_and_nan() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let altitude = schema_builder.add_f64_field("altitude", FAST);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        let mut index_writer = index.writer_for_tests()?;
+        for &val in &[f64::NAN, -1.5f64, 40f64, -f64::NAN, 0f64, f64::NEG_INFINITY] {
+            index_writer.add_document(doc!(altitude => val));
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let sorted_values = |top_docs: Vec<(f64, DocAddress)>| {
+            top_docs
+                .into_iter()
+                .map(|(val, _)| val.to_string())
+                .collect::<Vec<_>>()
+        };
+        let top_docs: Vec<(f64, DocAddress)> = searcher.search(
+            &AllQuery,
+            &TopDocs::with_limit(6).order_by_fast_field(altitude),
+        )?;
+        assert_eq!(
+            sorted_values(top_docs),
+            vec!["40", "0", "-1.5", "-inf", "NaN", "NaN"]
+        );
+        let top_docs: Vec<(f64, DocAddress)> = searcher.search(
+            &AllQuery,
+            &TopDocs::with_limit(5).order_by_fast_field_asc(altitude),
+        )?;
+        assert_eq!(
+            sorted_values(top_docs),
+            vec!["-inf", "-1.5", "0", "40", "NaN"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_top_field_collector_i64_asc() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let altitude = schema_builder.add_i64_field("altitude", FAST);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        let mut index_writer = index.writer_for_tests()?;
+        for &val in &[3i64, -1i64, 40i64, -7i64] {
+            index_writer.add_document(doc!(altitude => val));
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let top_collector = TopDocs::with_limit(3).order_by_fast_field_asc(altitude);
+        let top_docs: Vec<(i64, DocAddress)> = searcher.search(&AllQuery, &top_collector)?;
+        assert_eq!(
+            &top_docs[..],
+            &[
+                (-7i64, DocAddress::new(0, 3)),
+                (-1i64, DocAddress::new(0, 1)),
+                (3i64, DocAddress::new(0, 0)),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_top_field_collector_on_sorted_index() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let altitude = schema_builder.add_i64_field("altitude", FAST | INDEXED);
+        let schema = schema_builder.build();
+        let values: Vec<i64> = (0i64..300i64).map(|i| (i * 37) % 101 - 50).collect();
+        let create_index = |index_sort: Option<IndexSortByField>| -> crate::Result<Index> {
+            let settings = IndexSettings {
+                sort_by_field: index_sort,
+                ..Default::default()
+            };
+            let index = Index::builder()
+                .schema(schema.clone())
+                .settings(settings)
+                .create_in_ram()?;
+            let mut index_writer = index.writer_for_tests()?;
+            for (i, &val) in values.iter().enumerate() {
+                index_writer.add_document(doc!(altitude => val));
+                if i == 150 {
+                    index_writer.commit()?;
+                }
+            }
+            index_writer.delete_term(Term::from_field_i64(altitude, 3));
+            index_writer.delete_term(Term::from_field_i64(altitude, -50));
+            index_writer.commit()?;
+            Ok(index)
+        };
+        let top_values = |index: &Index, ascending: bool| -> crate::Result<Vec<i64>> {
+            let searcher = index.reader()?.searcher();
+            let top_docs = TopDocs::with_limit(10).and_offset(5);
+            let top_docs: Vec<(i64, DocAddress)> = if ascending {
+                searcher.search(&AllQuery, &top_docs.order_by_fast_field_asc(altitude))?
+            } else {
+                searcher.search(&AllQuery, &top_docs.order_by_fast_field(altitude))?
+            };
+            Ok(top_docs.into_iter().map(|(val, _)| val).collect())
+        };
+        let unsorted_index = create_index(None)?;
+        assert!(unsorted_index.reader()?.searcher().index_sort().is_none());
+        for order in vec![Order::Asc, Order::Desc] {
+            let index_sort = IndexSortByField {
+                field: "altitude".to_string(),
+                order,
+            };
+            let sorted_index = create_index(Some(index_sort.clone()))?;
+            assert_eq!(
+                sorted_index.reader()?.searcher().index_sort(),
+                Some(&index_sort)
+            );
+            for &ascending in &[false, true] {
+                assert_eq!(
+                    top_values(&sorted_index, ascending)?,
+                    top_values(&unsorted_index, ascending)?
+                );
+            }
+        }
+        Ok(())
+    }
+
     #[test]
     #[should_panic]
     fn test_field_does_not_exist() {
@@ -73,8 +73,16 @@ where
         true
     }
 
-    fn merge_fruits(&self, segment_fruits: Vec<Self::Fruit>) -> Result<Self::Fruit> {
-        self.collector.merge_fruits(segment_fruits)
+    fn segment_memory_usage(&self, _segment: &SegmentReader) -> usize {
+        self.collector.segment_memory_usage()
+    }
+
+    fn merge_fruits(&self, left: Self::Fruit, right: Self::Fruit) -> Result<Self::Fruit> {
+        self.collector.merge_fruits(left, right)
+    }
+
+    fn finalize_fruit(&self, merged_fruit: Option<Self::Fruit>) -> Result<Self::Fruit> {
+        self.collector.finalize_fruit(merged_fruit)
     }
 }
 
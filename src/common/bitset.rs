@@ -1,4 +1,5 @@
 use std::fmt;
+use std::mem;
 use std::u64;
 
 #[derive(Clone, Copy, Eq, PartialEq)]
@@ -118,8 +119,17 @@ impl TinySet {
     pub fn range_greater_or_equal(from_included: u32) -> TinySet {
         TinySet::range_lower(from_included).complement()
     }
+
+    /// Reads a `TinySet` from up to 8 bytes, in which the element `el` is
+    /// represented by the bit `el % 8` of the byte `el / 8`.
+    pub fn from_le_bytes(bytes: &[u8]) -> TinySet {
+        let mut buffer = [0u8; 8];
+        buffer[..bytes.len()].copy_from_slice(bytes);
+        TinySet(u64::from_le_bytes(buffer))
+    }
 }
 
+/// A compact set of `u32` elements within `[0, max_value[`.
 #[derive(Clone)]
 pub struct BitSet {
     tinysets: Box<[TinySet]>,
@@ -144,6 +154,12 @@ impl BitSet {
         }
     }
 
+    /// Returns the number of bytes allocated by a `BitSet` that may contain
+    /// elements within `[0, max_val[`.
+    pub(crate) fn memory_usage(max_value: u32) -> usize {
+        num_buckets(max_value) as usize * mem::size_of::<TinySet>()
+    }
+
     /// Removes all elements from the `BitSet`.
     pub fn clear(&mut self) {
         for tinyset in self.tinysets.iter_mut() {
@@ -186,10 +202,29 @@ impl BitSet {
             .map(|delta_bucket| bucket + delta_bucket as u32)
     }
 
+    /// Returns the upper bound (excluded) of the elements of the `BitSet`.
     pub fn max_value(&self) -> u32 {
         self.max_value
     }
 
+    /// Returns the smallest element greater or equal to `el`, if any.
+    pub(crate) fn first_element_greater_or_equal(&self, el: u32) -> Option<u32> {
+        if el >= self.max_value {
+            return None;
+        }
+        let bucket = el / 64u32;
+        let mut tinyset = self
+            .tinyset(bucket)
+            .intersect(TinySet::range_greater_or_equal(el));
+        if let Some(lower) = tinyset.pop_lowest() {
+            return Some((bucket * 64u32) | lower);
+        }
+        let non_empty_bucket = self.first_non_empty_bucket(bucket + 1)?;
+        self.tinyset(non_empty_bucket)
+            .pop_lowest()
+            .map(|lower| (non_empty_bucket * 64u32) | lower)
+    }
+
     /// Returns the tiny bitset representing the
     /// the set restricted to the number range from
     /// `bucket * 64` to `(bucket + 1) * 64`.
@@ -353,6 +388,27 @@ mod tests {
             assert!(!bitset.contains(el));
         }
     }
+
+    #[test]
+    fn test_bitset_first_element_greater_or_equal() {
+        let mut bitset = BitSet::with_max_value(1_000);
+        bitset.insert(3u32);
+        bitset.insert(64u32);
+        bitset.insert(700u32);
+        assert_eq!(bitset.first_element_greater_or_equal(0u32), Some(3u32));
+        assert_eq!(bitset.first_element_greater_or_equal(3u32), Some(3u32));
+        assert_eq!(bitset.first_element_greater_or_equal(4u32), Some(64u32));
+        assert_eq!(bitset.first_element_greater_or_equal(65u32), Some(700u32));
+        assert_eq!(bitset.first_element_greater_or_equal(701u32), None);
+        assert_eq!(bitset.first_element_greater_or_equal(1_000u32), None);
+    }
+
+    #[test]
+    fn test_tinyset_from_le_bytes() {
+        let tinyset = TinySet::from_le_bytes(&[0b0000_0101, 0u8, 0b1000_0000]);
+        assert_eq!(tinyset.into_iter().collect::<Vec<u32>>(), vec![0, 2, 23]);
+        assert!(TinySet::from_le_bytes(&[]).is_empty());
+    }
 }
 
 #[cfg(all(test, feature = "unstable"))]
@@ -1,5 +1,6 @@
 mod bitset;
 mod composite_file;
+pub(crate) mod numeric_trie;
 
 pub use self::bitset::BitSet;
 pub(crate) use self::bitset::TinySet;
@@ -10,6 +11,7 @@ pub use common::{
     read_u32_vint, read_u32_vint_no_advance, serialize_vint_u32, write_u32_vint, VInt,
 };
 pub use common::{BinarySerializable, DeserializeFrom, FixedSize};
+use std::ops::Bound;
 
 /// Segment's max doc must be `< MAX_DOC_LIMIT`.
 ///
@@ -75,6 +77,12 @@ pub fn u64_to_i64(val: u64) -> i64 {
 /// Daniel Lemire's [blog post](https://lemire.me/blog/2020/12/14/converting-floating-point-numbers-to-integers-while-preserving-order/)
 /// explains the mapping in a clear manner.
 ///
+/// The mapping follows the IEEE 754 total order: in addition to the numeric order,
+/// `-0.0` is mapped below `0.0`, NaN values with their sign bit set are mapped below
+/// `f64::NEG_INFINITY`, and the other NaN values above `f64::INFINITY`.
+/// Use [`f64_bounds_to_u64_bounds`](./fn.f64_bounds_to_u64_bounds.html) to map a range
+/// of `f64` following the usual comparison of `f64` instead.
+///
 /// # See also
 /// The [reverse mapping is `u64_to_f64`](./fn.u64_to_f64.html).
 #[inline]
@@ -87,7 +95,7 @@ pub fn f64_to_u64(val: f64) -> u64 {
     }
 }
 
-/// Reverse the mapping given by [`i64_to_u64`](./fn.i64_to_u64.html).
+/// Reverse the mapping given by [`f64_to_u64`](./fn.f64_to_u64.html).
 #[inline]
 pub fn u64_to_f64(val: u64) -> f64 {
     f64::from_bits(if val & HIGHEST_BIT != 0 {
@@ -97,13 +105,52 @@ pub fn u64_to_f64(val: u64) -> f64 {
     })
 }
 
+/// Maps the bounds of a range of `f64` to the bounds of the range of the `u64`
+/// given by [`f64_to_u64`](./fn.f64_to_u64.html) for the values of this range.
+///
+/// Contrary to mapping each bound with `f64_to_u64`, the resulting range
+/// follows the usual comparison of `f64`:
+/// - `-0.0` and `0.0` are equal, so that both belong to the range `0.0..1.0`,
+/// - NaN values do not belong to any range, including unbounded ones,
+/// - a range with a NaN bound is empty.
+pub fn f64_bounds_to_u64_bounds(
+    left_bound: Bound<f64>,
+    right_bound: Bound<f64>,
+) -> (Bound<u64>, Bound<u64>) {
+    use std::ops::Bound::*;
+    let is_nan = |bound: &Bound<f64>| match bound {
+        Included(val) | Excluded(val) => val.is_nan(),
+        Unbounded => false,
+    };
+    if is_nan(&left_bound) || is_nan(&right_bound) {
+        return (Excluded(u64::MAX), Excluded(u64::MAX));
+    }
+    // `-0.0` is the lowest zero and `0.0` the highest one.
+    let left_bound = match left_bound {
+        Included(val) if val == 0.0 => Included(f64_to_u64(-0.0)),
+        Excluded(val) if val == 0.0 => Excluded(f64_to_u64(0.0)),
+        Included(val) => Included(f64_to_u64(val)),
+        Excluded(val) => Excluded(f64_to_u64(val)),
+        Unbounded => Included(f64_to_u64(f64::NEG_INFINITY)),
+    };
+    let right_bound = match right_bound {
+        Included(val) if val == 0.0 => Included(f64_to_u64(0.0)),
+        Excluded(val) if val == 0.0 => Excluded(f64_to_u64(-0.0)),
+        Included(val) => Included(f64_to_u64(val)),
+        Excluded(val) => Excluded(f64_to_u64(val)),
+        Unbounded => Included(f64_to_u64(f64::INFINITY)),
+    };
+    (left_bound, right_bound)
+}
+
 #[cfg(test)]
 pub(crate) mod test {
 
-    use super::{f64_to_u64, i64_to_u64, u64_to_f64, u64_to_i64};
+    use super::{f64_bounds_to_u64_bounds, f64_to_u64, i64_to_u64, u64_to_f64, u64_to_i64};
     use common::{BinarySerializable, FixedSize};
     use proptest::prelude::*;
     use std::f64;
+    use std::ops::{Bound, RangeBounds};
     use tantivy_bitpacker::compute_num_bits;
     pub use tantivy_bitpacker::minmax;
 
@@ -165,6 +212,27 @@ pub(crate) mod test {
         assert!(f64_to_u64(-2.0) < f64_to_u64(-1.5));
     }
 
+    #[test]
+    fn test_f64_bounds_to_u64_bounds() {
+        use std::ops::Bound::*;
+        let contains = |left: Bound<f64>, right: Bound<f64>, val: f64| {
+            f64_bounds_to_u64_bounds(left, right).contains(&f64_to_u64(val))
+        };
+        assert!(contains(Included(0.0), Excluded(1.0), -0.0));
+        assert!(contains(Included(-0.0), Excluded(1.0), 0.0));
+        assert!(!contains(Excluded(-0.0), Excluded(1.0), 0.0));
+        assert!(contains(Excluded(-1.0), Included(-0.0), 0.0));
+        assert!(!contains(Excluded(-1.0), Excluded(0.0), -0.0));
+        assert!(contains(Included(-1.0), Excluded(1.0), -1.0));
+        assert!(!contains(Included(-1.0), Excluded(1.0), 1.0));
+        assert!(contains(Unbounded, Unbounded, f64::NEG_INFINITY));
+        assert!(contains(Unbounded, Unbounded, f64::INFINITY));
+        assert!(!contains(Unbounded, Unbounded, f64::NAN));
+        assert!(!contains(Unbounded, Unbounded, -f64::NAN));
+        assert!(!contains(Included(f64::NAN), Unbounded, 1.0));
+        assert!(!contains(Unbounded, Included(f64::NAN), f64::NAN));
+    }
+
     #[test]
     fn test_compute_num_bits() {
         assert_eq!(compute_num_bits(1), 1u8);
@@ -0,0 +1,185 @@
+//! Numeric values indexed at several precisions.
+//!
+//! The term of a numeric value is the big-endian representation of its `u64` encoding.
+//! If the field has a precision step, each value is also indexed as the prefixes of
+//! this representation obtained by dropping `precision_step`, `2 * precision_step`, ...
+//! bits. Since the precision step is a multiple of 8, these prefixes are
+//! simply truncated terms.
+//!
+//! A range of values is then covered by a few ranges of prefixes: the middle of
+//! the range is covered by low precision prefixes, each of them matching
+//! a large number of values, and only its edges require full precision terms.
+
+/// Length, in bytes, of the full precision term of a value.
+pub(crate) const FULL_PRECISION_NUM_BYTES: usize = 8;
+
+/// Returns true if the term, of a field with a precision step, is the full precision
+/// term of a value rather than one of its truncated prefixes.
+pub(crate) fn is_full_precision_term(value_bytes: &[u8]) -> bool {
+    value_bytes.len() == FULL_PRECISION_NUM_BYTES
+}
+
+/// Returns the lengths, in bytes, of the truncated terms indexed in addition to
+/// the full precision term of each value.
+pub(crate) fn prefix_lengths(precision_step: u8) -> impl Iterator<Item = usize> {
+    let step_num_bytes = usize::from(precision_step / 8);
+    (1..)
+        .map(move |level| level * step_num_bytes)
+        .take_while(|&num_dropped_bytes| num_dropped_bytes < 8)
+        .map(|num_dropped_bytes| 8 - num_dropped_bytes)
+}
+
+/// Range of the prefixes of a given length, with both bounds included.
+///
+/// `start` and `end` are the values of the prefixes, i.e. the `num_bytes`
+/// most significant bytes of a value.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct PrefixRange {
+    pub num_bytes: usize,
+    pub start: u64,
+    pub end: u64,
+}
+
+impl PrefixRange {
+    fn new(start: u64, end: u64, shift: u32) -> PrefixRange {
+        PrefixRange {
+            num_bytes: 8 - (shift / 8) as usize,
+            start: start >> shift,
+            end: end >> shift,
+        }
+    }
+}
+
+/// Splits the range of values `[start, end]` into disjoint ranges of prefixes.
+///
+/// At most two ranges are emitted for each precision, so that a range query
+/// reads at most `2 * (256 ^ (precision_step / 8) - 1)` terms of each precision,
+/// regardless of the width of the range.
+pub(crate) fn split_range(mut start: u64, mut end: u64, precision_step: u8) -> Vec<PrefixRange> {
+    debug_assert!(start <= end);
+    let step = u32::from(precision_step);
+    let mut prefix_ranges = Vec::new();
+    let mut shift = 0u32;
+    loop {
+        if shift + step >= 64 {
+            // There is no lower precision.
+            prefix_ranges.push(PrefixRange::new(start, end, shift));
+            break;
+        }
+        let diff = 1u64 << (shift + step);
+        let mask = ((1u64 << step) - 1) << shift;
+        let has_lower = start & mask != 0;
+        let has_upper = end & mask != mask;
+        let next_start = if has_lower {
+            start.wrapping_add(diff)
+        } else {
+            start
+        } & !mask;
+        let next_end = if has_upper {
+            end.wrapping_sub(diff)
+        } else {
+            end
+        } & !mask;
+        if next_start > next_end || next_start < start || next_end > end {
+            // The remaining range cannot be covered with lower precision prefixes.
+            prefix_ranges.push(PrefixRange::new(start, end, shift));
+            break;
+        }
+        if has_lower {
+            prefix_ranges.push(PrefixRange::new(start, start | mask, shift));
+        }
+        if has_upper {
+            prefix_ranges.push(PrefixRange::new(end & !mask, end, shift));
+        }
+        start = next_start;
+        end = next_end;
+        shift += step;
+    }
+    prefix_ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{prefix_lengths, split_range};
+
+    #[test]
+    fn test_prefix_lengths() {
+        assert_eq!(
+            prefix_lengths(8).collect::<Vec<_>>(),
+            vec![7, 6, 5, 4, 3, 2, 1]
+        );
+        assert_eq!(prefix_lengths(16).collect::<Vec<_>>(), vec![6, 4, 2]);
+        assert_eq!(prefix_lengths(24).collect::<Vec<_>>(), vec![5, 2]);
+        assert_eq!(prefix_lengths(56).collect::<Vec<_>>(), vec![1]);
+    }
+
+    fn check_split_range(start: u64, end: u64, precision_step: u8) {
+        let prefix_ranges = split_range(start, end, precision_step);
+        assert!(prefix_ranges.len() <= 2 * (64 / precision_step as usize + 1));
+        let num_matching_ranges = |val: u64| {
+            prefix_ranges
+                .iter()
+                .filter(|prefix_range| {
+                    let prefix = val >> (8 * (8 - prefix_range.num_bytes));
+                    prefix_range.start <= prefix && prefix <= prefix_range.end
+                })
+                .count()
+        };
+        let vals = [
+            start,
+            end,
+            start.wrapping_sub(1),
+            end.wrapping_add(1),
+            start / 2 + end / 2,
+            start | 0xFF,
+            end & !0xFF,
+            start | 0xFFFF,
+            end & !0xFFFF,
+        ];
+        for &val in &vals {
+            let expected = if start <= val && val <= end { 1 } else { 0 };
+            assert_eq!(
+                num_matching_ranges(val),
+                expected,
+                "start={} end={} val={} step={}",
+                start,
+                end,
+                val,
+                precision_step
+            );
+        }
+    }
+
+    #[test]
+    fn test_split_range() {
+        assert_eq!(split_range(3, 3, 16).len(), 1);
+        // A range aligned on the precision step is covered by a single prefix.
+        let prefix_ranges = split_range(0x0001_0000, 0x0001_FFFF, 16);
+        assert_eq!(prefix_ranges.len(), 1);
+        assert_eq!(prefix_ranges[0].num_bytes, 6);
+        assert_eq!(split_range(0, u64::MAX, 8).len(), 1);
+        let bounds = [
+            0u64,
+            1,
+            255,
+            256,
+            1_000,
+            65_535,
+            65_536,
+            1 << 40,
+            (1 << 40) + 12_345,
+            1 << 63,
+            u64::MAX - 1_000,
+            u64::MAX,
+        ];
+        for &precision_step in &[8u8, 16, 24, 32, 56] {
+            for &start in &bounds {
+                for &end in &bounds {
+                    if start <= end {
+                        check_split_range(start, end, precision_step);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use crate::TantivyError;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// What multi-term queries do once their `ExpansionBudget` is exhausted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExpansionOverflow {
+    /// The query fails with `TantivyError::ExpansionBudgetExceeded`.
+    Fail,
+    /// Multi-term queries stop rewriting themselves into unions of term queries,
+    /// and match their documents by filling a bitset per segment instead.
+    ///
+    /// The query succeeds, but the memory and the time it takes do not depend
+    /// on the number of posting lists opened anymore.
+    Degrade,
+}
+
+#[derive(Debug)]
+struct InnerExpansionBudget {
+    limit: Option<usize>,
+    overflow: ExpansionOverflow,
+    num_terms_expanded: AtomicUsize,
+    exceeded: AtomicBool,
+}
+
+/// Caps the number of terms into which the multi-term queries of a query
+/// (fuzzy, regex, wildcard, prefix or term set queries) expand.
+///
+/// Multi-term queries charge each term of the term dictionaries of the segments
+/// they match to the budget. The budget is shared by all of the multi-term
+/// queries of a query, and is not replenished.
+/// Once it is exhausted, the query either fails or degrades, depending on its
+/// [`ExpansionOverflow`](./enum.ExpansionOverflow.html).
+///
+/// Cloning an `ExpansionBudget` returns a handle to the same budget.
+#[derive(Clone, Debug)]
+pub struct ExpansionBudget {
+    inner: Arc<InnerExpansionBudget>,
+}
+
+impl ExpansionBudget {
+    /// Creates a budget without any limit.
+    pub fn unlimited() -> ExpansionBudget {
+        ExpansionBudget::new(None, ExpansionOverflow::Fail)
+    }
+
+    /// Creates a budget of `max_num_terms` terms.
+    pub fn with_limit(max_num_terms: usize, overflow: ExpansionOverflow) -> ExpansionBudget {
+        ExpansionBudget::new(Some(max_num_terms), overflow)
+    }
+
+    fn new(limit: Option<usize>, overflow: ExpansionOverflow) -> ExpansionBudget {
+        ExpansionBudget {
+            inner: Arc::new(InnerExpansionBudget {
+                limit,
+                overflow,
+                num_terms_expanded: AtomicUsize::new(0),
+                exceeded: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    /// Returns the limit of the budget, in terms, or `None` if the budget is unlimited.
+    pub fn limit(&self) -> Option<usize> {
+        self.inner.limit
+    }
+
+    /// Returns what happens once the budget is exhausted.
+    pub fn overflow(&self) -> ExpansionOverflow {
+        self.inner.overflow
+    }
+
+    /// Returns the number of terms charged to the budget so far.
+    pub fn num_terms_expanded(&self) -> usize {
+        self.inner.num_terms_expanded.load(Ordering::SeqCst)
+    }
+
+    /// Returns true if an expansion did not fit in the budget.
+    pub fn is_exceeded(&self) -> bool {
+        self.inner.exceeded.load(Ordering::SeqCst)
+    }
+
+    /// Charges the expansion of `num_terms` terms to the budget.
+    ///
+    /// Returns `Ok(true)` if the terms fit in the budget.
+    /// Otherwise nothing is charged, and the method returns
+    /// `TantivyError::ExpansionBudgetExceeded` if the overflow is `Fail`,
+    /// or `Ok(false)` if it is `Degrade`.
+    pub fn expand(&self, num_terms: usize) -> crate::Result<bool> {
+        let limit = self.inner.limit.unwrap_or(usize::MAX);
+        let fits = self
+            .inner
+            .num_terms_expanded
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |num_terms_expanded| {
+                num_terms_expanded
+                    .checked_add(num_terms)
+                    .filter(|&new_num_terms_expanded| new_num_terms_expanded <= limit)
+            })
+            .is_ok();
+        if fits {
+            return Ok(true);
+        }
+        self.inner.exceeded.store(true, Ordering::SeqCst);
+        match self.inner.overflow {
+            ExpansionOverflow::Fail => Err(TantivyError::ExpansionBudgetExceeded(limit)),
+            ExpansionOverflow::Degrade => Ok(false),
+        }
+    }
+}
+
+impl Default for ExpansionBudget {
+    fn default() -> ExpansionBudget {
+        ExpansionBudget::unlimited()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ExpansionBudget, ExpansionOverflow};
+    use crate::TantivyError;
+
+    #[test]
+    fn test_expansion_budget_fail() {
+        let expansion_budget = ExpansionBudget::with_limit(10, ExpansionOverflow::Fail);
+        assert_eq!(expansion_budget.limit(), Some(10));
+        assert!(expansion_budget.expand(6).unwrap());
+        assert!(matches!(
+            expansion_budget.clone().expand(6),
+            Err(TantivyError::ExpansionBudgetExceeded(10))
+        ));
+        assert!(expansion_budget.is_exceeded());
+        assert_eq!(expansion_budget.num_terms_expanded(), 6);
+        assert!(expansion_budget.expand(4).unwrap());
+        assert_eq!(expansion_budget.num_terms_expanded(), 10);
+    }
+
+    #[test]
+    fn test_expansion_budget_degrade() {
+        let expansion_budget = ExpansionBudget::with_limit(10, ExpansionOverflow::Degrade);
+        assert!(expansion_budget.expand(10).unwrap());
+        assert!(!expansion_budget.is_exceeded());
+        assert!(!expansion_budget.expand(1).unwrap());
+        assert!(expansion_budget.is_exceeded());
+        assert_eq!(expansion_budget.num_terms_expanded(), 10);
+    }
+}
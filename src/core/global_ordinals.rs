@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+use std::io;
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+use crate::core::{InvertedIndexReader, MultiTerms};
+use crate::schema::Field;
+use crate::space_usage::ByteCount;
+use crate::termdict::TermOrdinal;
+use crate::SegmentOrdinal;
+
+/// Maps the segment-local term ordinals of a field to global term ordinals,
+/// consistent across all of the segments of a `Searcher`.
+///
+/// Term ordinals stored in fast fields (e.g. the facet ordinals returned by
+/// [`FacetReader::facet_ords`](../fastfield/struct.FacetReader.html#method.facet_ords))
+/// only make sense within their segment. Translating them into global ordinals
+/// makes it possible to aggregate or compare terms coming from different segments
+/// with integer operations, rather than by comparing the terms themselves.
+///
+/// Global ordinals are defined as the position of the term in the sorted list
+/// of the terms of all of the segments, so that they preserve the order of the terms.
+///
+/// Global ordinals are built lazily, on the first call to
+/// [`Searcher::global_ordinals(...)`](./struct.Searcher.html#method.global_ordinals),
+/// and shared by all of the searchers of a given reload of the `IndexReader`.
+/// Use a [`Warmer`](./trait.Warmer.html) to build them eagerly upon reload.
+pub struct GlobalOrdinals {
+    inverted_indexes: Vec<Arc<InvertedIndexReader>>,
+    // For each segment, the global ordinal of each segment term ordinal.
+    segment_to_global_ords: Vec<Vec<TermOrdinal>>,
+    // For each global ordinal, a segment containing the term and its
+    // ordinal in this segment.
+    global_to_segment_ords: Vec<(SegmentOrdinal, TermOrdinal)>,
+}
+
+impl GlobalOrdinals {
+    pub(crate) fn build(multi_terms: &MultiTerms) -> io::Result<GlobalOrdinals> {
+        let inverted_indexes = multi_terms.inverted_indexes().to_vec();
+        let mut segment_to_global_ords: Vec<Vec<TermOrdinal>> = inverted_indexes
+            .iter()
+            .map(|inverted_index| Vec::with_capacity(inverted_index.terms().num_terms()))
+            .collect();
+        let mut global_to_segment_ords = Vec::new();
+        let mut stream = multi_terms.stream()?;
+        while stream.advance() {
+            let global_ord = global_to_segment_ords.len() as TermOrdinal;
+            for (i, (segment_ord, term_ord)) in stream.segment_ords_and_term_ords().enumerate() {
+                if i == 0 {
+                    global_to_segment_ords.push((segment_ord as SegmentOrdinal, term_ord));
+                }
+                debug_assert_eq!(
+                    segment_to_global_ords[segment_ord].len() as TermOrdinal,
+                    term_ord
+                );
+                segment_to_global_ords[segment_ord].push(global_ord);
+            }
+        }
+        Ok(GlobalOrdinals {
+            inverted_indexes,
+            segment_to_global_ords,
+            global_to_segment_ords,
+        })
+    }
+
+    /// Returns an estimate of the heap usage of the global ordinals.
+    pub(crate) fn num_bytes(&self) -> ByteCount {
+        let segment_to_global_num_bytes: usize = self
+            .segment_to_global_ords
+            .iter()
+            .map(|ords| ords.len() * mem::size_of::<TermOrdinal>())
+            .sum();
+        segment_to_global_num_bytes
+            + self.global_to_segment_ords.len() * mem::size_of::<(SegmentOrdinal, TermOrdinal)>()
+    }
+
+    /// Returns the number of distinct terms over all of the segments.
+    ///
+    /// Global ordinals range from `0` to `num_terms() - 1`.
+    pub fn num_terms(&self) -> usize {
+        self.global_to_segment_ords.len()
+    }
+
+    /// Returns the global ordinals of the terms of a segment,
+    /// indexed by their segment term ordinal.
+    pub fn segment_mapping(&self, segment_ord: SegmentOrdinal) -> &[TermOrdinal] {
+        &self.segment_to_global_ords[segment_ord as usize]
+    }
+
+    /// Returns the global ordinal of the term with the ordinal `term_ord`
+    /// in the segment `segment_ord`.
+    pub fn global_ord(&self, segment_ord: SegmentOrdinal, term_ord: TermOrdinal) -> TermOrdinal {
+        self.segment_mapping(segment_ord)[term_ord as usize]
+    }
+
+    /// Writes the term associated to the global ordinal `global_ord` into `bytes`.
+    ///
+    /// Returns false if there is no such global ordinal.
+    pub fn term_from_global_ord(
+        &self,
+        global_ord: TermOrdinal,
+        bytes: &mut Vec<u8>,
+    ) -> io::Result<bool> {
+        if let Some(&(segment_ord, term_ord)) = self.global_to_segment_ords.get(global_ord as usize)
+        {
+            self.inverted_indexes[segment_ord as usize]
+                .terms()
+                .ord_to_term(term_ord, bytes)
+        } else {
+            Ok(false)
+        }
+    }
+}
+
+/// Global ordinals already built for a given reload of an `IndexReader`.
+#[derive(Default)]
+pub(crate) struct GlobalOrdinalsCache {
+    global_ordinals: Mutex<HashMap<Field, Arc<GlobalOrdinals>>>,
+}
+
+impl GlobalOrdinalsCache {
+    /// Returns the global ordinals of `field`, building them with `build`
+    /// if they are not in the cache yet.
+    ///
+    /// The lock is held while building, so that concurrent searchers
+    /// do not build the same global ordinals twice.
+    pub fn get_or_build<F>(&self, field: Field, build: F) -> crate::Result<Arc<GlobalOrdinals>>
+    where
+        F: FnOnce() -> crate::Result<GlobalOrdinals>,
+    {
+        let mut global_ordinals = self
+            .global_ordinals
+            .lock()
+            .expect("Global ordinals cache lock is poisoned");
+        if let Some(field_global_ordinals) = global_ordinals.get(&field) {
+            return Ok(field_global_ordinals.clone());
+        }
+        let field_global_ordinals = Arc::new(build()?);
+        global_ordinals.insert(field, field_global_ordinals.clone());
+        Ok(field_global_ordinals)
+    }
+
+    /// Returns an estimate of the heap usage of the cached global ordinals.
+    pub fn num_bytes(&self) -> ByteCount {
+        self.global_ordinals
+            .lock()
+            .expect("Global ordinals cache lock is poisoned")
+            .values()
+            .map(|global_ordinals| global_ordinals.num_bytes())
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schema::{Facet, Schema, INDEXED, STRING};
+    use crate::{Index, SegmentOrdinal, TantivyError};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_global_ordinals() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let facet_field = schema_builder.add_facet_field("facet", INDEXED);
+        let text_field = schema_builder.add_text_field("text", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        let docs_facets = vec![
+            vec!["/b", "/d"],
+            vec!["/a"],
+            vec!["/c", "/d"],
+            vec!["/e"],
+            vec!["/b"],
+        ];
+        for facets in docs_facets {
+            let mut doc = doc!(text_field => "hello");
+            for facet in facets {
+                doc.add_facet(facet_field, facet);
+            }
+            index_writer.add_document(doc);
+            index_writer.commit()?;
+        }
+        let reader = index.reader()?;
+        let searcher = reader.searcher();
+        let global_ordinals = searcher.global_ordinals(facet_field)?;
+        // The terms of the field are the facets and their ancestors.
+        let num_terms = searcher.terms(facet_field)?.stream().map(|mut stream| {
+            let mut num_terms = 0;
+            while stream.advance() {
+                num_terms += 1;
+            }
+            num_terms
+        })?;
+        assert_eq!(global_ordinals.num_terms(), num_terms);
+
+        let mut facet_ords = Vec::new();
+        let mut global_facets = Vec::new();
+        for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
+            let mut facet_reader = segment_reader.facet_reader(facet_field)?;
+            assert_eq!(
+                global_ordinals
+                    .segment_mapping(segment_ord as SegmentOrdinal)
+                    .len(),
+                facet_reader.num_facets()
+            );
+            facet_reader.facet_ords(0, &mut facet_ords);
+            for &facet_ord in &facet_ords {
+                let mut facet = Facet::root();
+                facet_reader.facet_from_ord(facet_ord, &mut facet)?;
+                let global_ord =
+                    global_ordinals.global_ord(segment_ord as SegmentOrdinal, facet_ord);
+                let mut term_bytes = Vec::new();
+                assert!(global_ordinals.term_from_global_ord(global_ord, &mut term_bytes)?);
+                assert_eq!(Facet::from_encoded(term_bytes).unwrap(), facet);
+                global_facets.push((global_ord, facet));
+            }
+        }
+        // Global ordinals follow the order of the facets.
+        global_facets.sort();
+        global_facets.dedup();
+        let facets: Vec<String> = global_facets
+            .iter()
+            .map(|(_, facet)| facet.to_string())
+            .collect();
+        assert_eq!(facets, vec!["/a", "/b", "/c", "/d", "/e"]);
+        let mut term_bytes = Vec::new();
+        assert!(!global_ordinals.term_from_global_ord(num_terms as u64, &mut term_bytes)?);
+
+        // Global ordinals are built once per reload.
+        let other_searcher = reader.searcher();
+        assert!(Arc::ptr_eq(
+            &global_ordinals,
+            &other_searcher.global_ordinals(facet_field)?
+        ));
+        assert!(matches!(
+            searcher.global_ordinals(text_field),
+            Err(TantivyError::SchemaError(_))
+        ));
+        Ok(())
+    }
+}
@@ -1,60 +1,167 @@
 use super::{segment::Segment, IndexSettings};
+use crate::check::{check_index, IndexCheck};
+use crate::codec::CodecManager;
 use crate::core::Executor;
 use crate::core::IndexMeta;
+use crate::core::SegmentComponent;
 use crate::core::SegmentId;
 use crate::core::SegmentMeta;
 use crate::core::SegmentMetaInventory;
 use crate::core::META_FILEPATH;
+use crate::core::{meta_generation_filepath, meta_generation_from_filepath};
 use crate::directory::error::OpenReadError;
 use crate::directory::ManagedDirectory;
 #[cfg(feature = "mmap")]
 use crate::directory::MmapDirectory;
 use crate::directory::INDEX_WRITER_LOCK;
-use crate::directory::{Directory, RamDirectory};
+use crate::directory::{Directory, DirectoryLock, RamDirectory};
 use crate::error::DataCorruption;
+use crate::error::SettingsError;
 use crate::error::TantivyError;
 use crate::indexer::index_writer::{HEAP_SIZE_MIN, MAX_NUM_THREAD};
 use crate::indexer::segment_updater::save_new_metas;
+use crate::metrics::{Metrics, NoMetrics};
 use crate::reader::IndexReader;
 use crate::reader::IndexReaderBuilder;
+use crate::schema::Cardinality;
 use crate::schema::Field;
 use crate::schema::FieldType;
 use crate::schema::Schema;
 use crate::tokenizer::{TextAnalyzer, TokenizerManager};
 use crate::IndexWriter;
+use crate::Opstamp;
 use std::collections::HashSet;
 use std::fmt;
-
-#[cfg(feature = "mmap")]
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
-fn load_metas(
+fn load_meta_file(
     directory: &dyn Directory,
+    path: &Path,
     inventory: &SegmentMetaInventory,
 ) -> crate::Result<IndexMeta> {
-    let meta_data = directory.atomic_read(&META_FILEPATH)?;
+    let meta_data = directory.atomic_read(path)?;
     let meta_string = String::from_utf8(meta_data).map_err(|_utf8_err| {
         error!("Meta data is not valid utf8.");
         DataCorruption::new(
-            META_FILEPATH.to_path_buf(),
+            path.to_path_buf(),
             "Meta file does not contain valid utf8 file.".to_string(),
         )
     })?;
     IndexMeta::deserialize(&meta_string, inventory)
-        .map_err(|e| {
-            DataCorruption::new(
-                META_FILEPATH.to_path_buf(),
-                format!(
-                    "Meta file cannot be deserialized. {:?}. Content: {:?}",
-                    e, meta_string
-                ),
-            )
-        })
+        .map_err(|comment| DataCorruption::new(path.to_path_buf(), comment))
         .map_err(From::from)
 }
 
+/// Returns the generations of the meta files of the directory, in increasing order.
+pub(crate) fn list_meta_generations(directory: &ManagedDirectory) -> Vec<u64> {
+    let mut generations: Vec<u64> = directory
+        .list_managed_files()
+        .iter()
+        .filter_map(|path| meta_generation_from_filepath(path))
+        .collect();
+    generations.sort_unstable();
+    generations
+}
+
+fn load_metas(
+    directory: &ManagedDirectory,
+    commit_generation: Option<u64>,
+    inventory: &SegmentMetaInventory,
+) -> crate::Result<IndexMeta> {
+    if let Some(generation) = commit_generation {
+        let path = meta_generation_filepath(generation);
+        return load_meta_file(directory, &path, inventory);
+    }
+    match load_meta_file(directory, &META_FILEPATH, inventory) {
+        Ok(metas) => Ok(metas),
+        Err(TantivyError::DataCorruption(data_corruption)) => {
+            // On some filesystems, a crash while writing `meta.json` may leave it truncated.
+            // We then fall back to the last valid commit.
+            for generation in list_meta_generations(directory).into_iter().rev() {
+                let path = meta_generation_filepath(generation);
+                if let Ok(metas) = load_meta_file(directory, &path, inventory) {
+                    warn!(
+                        "{:?} Falling back to the commit of generation {}.",
+                        data_corruption, generation
+                    );
+                    return Ok(metas);
+                }
+            }
+            Err(data_corruption.into())
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Checks that the settings can be applied to an index with the given schema.
+///
+/// The tokenizers are only checked if `tokenizers` is given.
+fn validate_settings(
+    schema: &Schema,
+    settings: &IndexSettings,
+    tokenizers: Option<&TokenizerManager>,
+) -> crate::Result<()> {
+    if let Some(sort_by_field) = settings.sort_by_field.as_ref() {
+        let field = schema
+            .get_field(&sort_by_field.field)
+            .ok_or_else(|| SettingsError::SortFieldNotFound(sort_by_field.field.clone()))?;
+        let is_valid_sort_field = match schema.get_field_entry(field).field_type() {
+            FieldType::U64(int_options)
+            | FieldType::I64(int_options)
+            | FieldType::F64(int_options)
+            | FieldType::Date(int_options) => {
+                int_options.get_fastfield_cardinality() == Some(Cardinality::SingleValue)
+            }
+            _ => false,
+        };
+        if !is_valid_sort_field {
+            return Err(SettingsError::InvalidSortField(sort_by_field.field.clone()).into());
+        }
+    }
+    let compressor = settings.docstore_compression;
+    if !compressor.is_enabled() {
+        return Err(SettingsError::CompressorNotEnabled {
+            compressor,
+            feature: compressor.feature_flag(),
+        }
+        .into());
+    }
+    if let Some(tokenizers) = tokenizers {
+        for (_, field_entry) in schema.fields() {
+            if let FieldType::Str(text_options) = field_entry.field_type() {
+                if let Some(indexing_options) = text_options.get_indexing_options() {
+                    let tokenizer_name = indexing_options.tokenizer();
+                    if tokenizers.get(tokenizer_name).is_none() {
+                        return Err(SettingsError::TokenizerNotFound {
+                            field: field_entry.name().to_string(),
+                            tokenizer: tokenizer_name.to_string(),
+                        }
+                        .into());
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// File belonging to a commit, as listed by
+/// [`Index::list_files_for_commit`](./struct.Index.html#method.list_files_for_commit).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommitFile {
+    /// Path of the file, relative to the index directory.
+    pub path: PathBuf,
+    /// Length of the file in bytes, footer included.
+    pub num_bytes: u64,
+    /// CRC32 checksum recorded in the footer of the file.
+    ///
+    /// The meta file does not have a footer, and hence no checksum.
+    pub checksum: Option<u32>,
+}
+
 /// IndexBuilder can be used to create an index.
 ///
 /// Use in conjunction with `SchemaBuilder`. Global index settings
@@ -80,9 +187,14 @@ fn load_metas(
 /// let index = Index::builder().schema(schema).settings(settings).create_in_ram();
 ///
 /// ```
+///
+/// The settings are checked against the schema when the index is created:
+/// an `InvalidSettings` error is returned if, for instance, the index is sorted
+/// by a field that is not a single-valued fast field.
 pub struct IndexBuilder {
     schema: Option<Schema>,
     index_settings: IndexSettings,
+    tokenizers: Option<TokenizerManager>,
 }
 impl Default for IndexBuilder {
     fn default() -> Self {
@@ -95,6 +207,7 @@ impl IndexBuilder {
         Self {
             schema: None,
             index_settings: IndexSettings::default(),
+            tokenizers: None,
         }
     }
     /// Set the settings
@@ -107,15 +220,21 @@ impl IndexBuilder {
         self.schema = Some(schema);
         self
     }
+    /// Set the tokenizer manager of the index.
+    ///
+    /// Creating the index then fails if a text field of the schema uses a tokenizer
+    /// that is not registered in this manager.
+    pub fn tokenizers(mut self, tokenizers: TokenizerManager) -> Self {
+        self.tokenizers = Some(tokenizers);
+        self
+    }
     /// Creates a new index using the `RAMDirectory`.
     ///
     /// The index will be allocated in anonymous memory.
     /// This should only be used for unit tests.
     pub fn create_in_ram(self) -> Result<Index, TantivyError> {
         let ram_directory = RamDirectory::create();
-        Ok(self
-            .create(ram_directory)
-            .expect("Creating a RAMDirectory should never fail"))
+        self.create(ram_directory)
     }
     /// Creates a new index in a given filepath.
     /// The index will use the `MMapDirectory`.
@@ -153,8 +272,11 @@ impl IndexBuilder {
         if !Index::exists(&dir)? {
             return self.create(dir);
         }
-        let index = Index::open(dir)?;
+        let mut index = Index::open(dir)?;
         if index.schema() == self.get_expect_schema()? {
+            if let Some(tokenizers) = self.tokenizers {
+                index.tokenizers = tokenizers;
+            }
             Ok(index)
         } else {
             Err(TantivyError::SchemaError(
@@ -166,15 +288,16 @@ impl IndexBuilder {
     ///
     /// If a directory previously existed, it will be erased.
     fn create<Dir: Directory>(self, dir: Dir) -> crate::Result<Index> {
+        let schema = self.get_expect_schema()?;
+        validate_settings(&schema, &self.index_settings, self.tokenizers.as_ref())?;
         let directory = ManagedDirectory::wrap(dir)?;
-        save_new_metas(
-            self.get_expect_schema()?,
-            self.index_settings.clone(),
-            &directory,
-        )?;
-        let mut metas = IndexMeta::with_schema(self.get_expect_schema()?);
+        save_new_metas(schema.clone(), self.index_settings.clone(), &directory)?;
+        let mut metas = IndexMeta::with_schema(schema);
         metas.index_settings = self.index_settings;
-        let index = Index::open_from_metas(directory, &metas, SegmentMetaInventory::default());
+        let mut index = Index::open_from_metas(directory, &metas, SegmentMetaInventory::default());
+        if let Some(tokenizers) = self.tokenizers {
+            index.tokenizers = tokenizers;
+        }
         Ok(index)
     }
 }
@@ -187,7 +310,12 @@ pub struct Index {
     settings: IndexSettings,
     executor: Arc<Executor>,
     tokenizers: TokenizerManager,
+    codecs: CodecManager,
+    metrics: Arc<dyn Metrics>,
     inventory: SegmentMetaInventory,
+    commit_generation: Option<u64>,
+    // Sequence number of the next segment id derived from the deterministic seed.
+    segment_id_sequence: Arc<AtomicU64>,
 }
 
 impl Index {
@@ -226,6 +354,19 @@ impl Index {
         self.set_multithread_executor(default_num_threads)
     }
 
+    /// Accessor to the metrics of the index.
+    pub fn metrics(&self) -> &Arc<dyn Metrics> {
+        &self.metrics
+    }
+
+    /// Sets the `Metrics` to which the indexer and the readers
+    /// report their activity.
+    ///
+    /// Only the writers and readers created afterwards report to these metrics.
+    pub fn set_metrics(&mut self, metrics: Arc<dyn Metrics>) {
+        self.metrics = metrics;
+    }
+
     /// Creates a new index using the `RamDirectory`.
     ///
     /// The index will be allocated in anonymous memory.
@@ -291,8 +432,20 @@ impl Index {
             directory,
             schema,
             tokenizers: TokenizerManager::default(),
+            codecs: CodecManager::default(),
+            metrics: Arc::new(NoMetrics),
             executor: Arc::new(Executor::single_thread()),
             inventory,
+            commit_generation: None,
+            segment_id_sequence: Arc::default(),
+        }
+    }
+
+    /// Returns a copy of this index, reading and writing its files in `directory`.
+    pub(crate) fn with_directory(&self, directory: ManagedDirectory) -> Index {
+        Index {
+            directory,
+            ..self.clone()
         }
     }
 
@@ -301,6 +454,12 @@ impl Index {
         &self.tokenizers
     }
 
+    /// Accessor for the codec manager, in which the custom formats
+    /// of the segments of the index are registered.
+    pub fn codecs(&self) -> &CodecManager {
+        &self.codecs
+    }
+
     /// Helper to access the tokenizer associated to a specific field.
     pub fn tokenizer_for_field(&self, field: Field) -> crate::Result<TextAnalyzer> {
         let field_entry = self.schema.get_field_entry(field);
@@ -367,14 +526,53 @@ impl Index {
     pub fn open<D: Directory>(directory: D) -> crate::Result<Index> {
         let directory = ManagedDirectory::wrap(directory)?;
         let inventory = SegmentMetaInventory::default();
-        let metas = load_metas(&directory, &inventory)?;
+        let metas = load_metas(&directory, None, &inventory)?;
         let index = Index::open_from_metas(directory, &metas, inventory);
         Ok(index)
     }
 
+    /// Opens the index as of the commit of generation `generation`,
+    /// rather than as of its last commit.
+    ///
+    /// The meta files of the last commits are kept in the directory
+    /// (see [`IndexWriter::set_num_commit_generations`](./struct.IndexWriter.html#method.set_num_commit_generations)),
+    /// and their generations are listed by [`.commit_generations()`](#method.commit_generations).
+    ///
+    /// The returned index is read-only: creating an `IndexWriter` on it fails.
+    pub fn open_commit<D: Directory>(directory: D, generation: u64) -> crate::Result<Index> {
+        let directory = ManagedDirectory::wrap(directory)?;
+        let inventory = SegmentMetaInventory::default();
+        let metas = load_metas(&directory, Some(generation), &inventory)?;
+        let mut index = Index::open_from_metas(directory, &metas, inventory);
+        index.commit_generation = Some(generation);
+        Ok(index)
+    }
+
+    /// Returns the generations of the commits that can be opened with
+    /// [`Index::open_commit`](#method.open_commit), in increasing order.
+    pub fn commit_generations(&self) -> crate::Result<Vec<u64>> {
+        let mut generations = Vec::new();
+        for generation in list_meta_generations(&self.directory) {
+            if self
+                .directory
+                .exists(&meta_generation_filepath(generation))?
+            {
+                generations.push(generation);
+            }
+        }
+        Ok(generations)
+    }
+
     /// Reads the index meta file from the directory.
+    ///
+    /// If `meta.json` is corrupted, the metas of the last valid commit are returned.
     pub fn load_metas(&self) -> crate::Result<IndexMeta> {
-        load_metas(self.directory(), &self.inventory)
+        load_metas(self.directory(), self.commit_generation, &self.inventory)
+    }
+
+    /// Reads the metas of the commit of generation `generation`.
+    pub(crate) fn load_commit_metas(&self, generation: u64) -> crate::Result<IndexMeta> {
+        load_metas(self.directory(), Some(generation), &self.inventory)
     }
 
     /// Open a new index writer. Attempts to acquire a lockfile.
@@ -386,7 +584,9 @@ impl Index {
     /// it is safe to manually delete the lockfile.
     ///
     /// - `num_threads` defines the number of indexing workers that
-    /// should work at the same time.
+    /// should work at the same time. With `0`, or if the index has a deterministic seed
+    /// (see `IndexSettings::deterministic_seed`), the writer does not spawn any thread,
+    /// see [`single_threaded_writer`](#method.single_threaded_writer).
     ///
     /// - `overall_heap_size_in_bytes` sets the amount of memory
     /// allocated for all indexing thread.
@@ -402,8 +602,48 @@ impl Index {
         num_threads: usize,
         overall_heap_size_in_bytes: usize,
     ) -> crate::Result<IndexWriter> {
-        let directory_lock = self
-            .directory
+        if num_threads == 0 || self.settings.deterministic_seed.is_some() {
+            return self.single_threaded_writer(overall_heap_size_in_bytes);
+        }
+        let directory_lock = self.acquire_writer_lock()?;
+        let heap_size_in_bytes_per_thread = overall_heap_size_in_bytes / num_threads;
+        IndexWriter::new(
+            self,
+            num_threads,
+            heap_size_in_bytes_per_thread,
+            directory_lock,
+            false,
+        )
+    }
+
+    /// Open a new single-threaded index writer. Attempts to acquire a lockfile.
+    ///
+    /// The writer does not spawn any thread: indexing, merging and committing all run
+    /// on the calling thread. The added documents are queued, and indexed within a budget
+    /// of `heap_size_in_bytes` when the queue is full, or upon commit. Merges run as part
+    /// of the commit, or of the future returned by `IndexWriter::merge`.
+    ///
+    /// This is useful for embedded environments, for deterministic tests, and for
+    /// platforms without threads, such as the `wasm32-unknown-unknown` target.
+    ///
+    /// Errors encountered while indexing the queued documents are returned by the
+    /// next commit.
+    ///
+    /// # Errors
+    /// If the lockfile already exists, returns `Error::DirectoryLockBusy` or an `Error::IoError`.
+    pub fn single_threaded_writer(&self, heap_size_in_bytes: usize) -> crate::Result<IndexWriter> {
+        let directory_lock = self.acquire_writer_lock()?;
+        IndexWriter::new(self, 1, heap_size_in_bytes, directory_lock, true)
+    }
+
+    fn acquire_writer_lock(&self) -> crate::Result<DirectoryLock> {
+        if let Some(generation) = self.commit_generation {
+            return Err(TantivyError::InvalidArgument(format!(
+                "The index was opened as of the commit of generation {}, and is read-only.",
+                generation
+            )));
+        }
+        self.directory
             .acquire_lock(&INDEX_WRITER_LOCK)
             .map_err(|err| {
                 TantivyError::LockFailure(
@@ -416,14 +656,7 @@ impl Index {
                             .to_string(),
                     ),
                 )
-            })?;
-        let heap_size_in_bytes_per_thread = overall_heap_size_in_bytes / num_threads;
-        IndexWriter::new(
-            self,
-            num_threads,
-            heap_size_in_bytes_per_thread,
-            directory_lock,
-        )
+            })
     }
 
     /// Helper to create an index writer for tests.
@@ -447,6 +680,9 @@ impl Index {
     /// # Panics
     /// If the heap size per thread is too small, panics.
     pub fn writer(&self, overall_heap_size_in_bytes: usize) -> crate::Result<IndexWriter> {
+        if self.settings.deterministic_seed.is_some() {
+            return self.single_threaded_writer(overall_heap_size_in_bytes);
+        }
         let mut num_threads = std::cmp::min(num_cpus::get(), MAX_NUM_THREAD);
         let heap_size_in_bytes_per_thread = overall_heap_size_in_bytes / num_threads;
         if heap_size_in_bytes_per_thread < HEAP_SIZE_MIN {
@@ -461,6 +697,43 @@ impl Index {
         &self.settings
     }
 
+    /// Returns the primary key field, as defined in the index settings.
+    ///
+    /// The primary key field needs to be an indexed `u64`, `i64` or bytes field,
+    /// or a text field indexed with the `raw` tokenizer.
+    ///
+    /// Returns `None` if the index does not have any primary key.
+    pub fn primary_key_field(&self) -> crate::Result<Option<Field>> {
+        let field_name = if let Some(field_name) = self.settings.primary_key.as_ref() {
+            field_name
+        } else {
+            return Ok(None);
+        };
+        let field = self.schema.get_field(field_name).ok_or_else(|| {
+            TantivyError::SchemaError(format!(
+                "Primary key field {:?} does not exist in the schema",
+                field_name
+            ))
+        })?;
+        let field_entry = self.schema.get_field_entry(field);
+        let is_valid_primary_key = match field_entry.field_type() {
+            FieldType::Str(text_options) => text_options
+                .get_indexing_options()
+                .map(|indexing_options| indexing_options.tokenizer() == "raw")
+                .unwrap_or(false),
+            FieldType::U64(_) | FieldType::I64(_) | FieldType::Bytes(_) => field_entry.is_indexed(),
+            _ => false,
+        };
+        if !is_valid_primary_key {
+            return Err(TantivyError::SchemaError(format!(
+                "Primary key field {:?} needs to be an indexed u64, i64 or bytes field, or a \
+                 text field indexed with the raw tokenizer",
+                field_name
+            )));
+        }
+        Ok(Some(field))
+    }
+
     /// Accessor to the index settings
     ///
     pub fn settings_mut(&mut self) -> &mut IndexSettings {
@@ -474,6 +747,21 @@ impl Index {
         self.schema.clone()
     }
 
+    /// Returns the oldest version of the index format among the searchable segments.
+    ///
+    /// Segments written by a previous version of tantivy remain readable, as long as
+    /// their index format is supported. `IndexWriter::upgrade_in_place()` rewrites them
+    /// in the current index format.
+    ///
+    /// If the index has no segment, the current index format version is returned.
+    pub fn index_format_version(&self) -> crate::Result<u32> {
+        let mut index_format_version = crate::INDEX_FORMAT_VERSION;
+        for segment in self.searchable_segments()? {
+            index_format_version = index_format_version.min(segment.index_format_version()?);
+        }
+        Ok(index_format_version)
+    }
+
     /// Returns the list of segments that are searchable
     pub fn searchable_segments(&self) -> crate::Result<Vec<Segment>> {
         Ok(self
@@ -489,11 +777,33 @@ impl Index {
     }
 
     /// Creates a new segment.
+    ///
+    /// If the index has a deterministic seed, the id of the segment is derived from it.
     pub fn new_segment(&self) -> Segment {
-        let segment_meta = self
-            .inventory
-            .new_segment_meta(SegmentId::generate_random(), 0);
-        self.segment(segment_meta)
+        let seed = if let Some(seed) = self.settings.deterministic_seed {
+            seed
+        } else {
+            let segment_meta = self
+                .inventory
+                .new_segment_meta(SegmentId::generate_random(), 0);
+            return self.segment(segment_meta);
+        };
+        loop {
+            let sequence = self.segment_id_sequence.fetch_add(1, Ordering::SeqCst);
+            let segment_meta = self
+                .inventory
+                .new_segment_meta(SegmentId::from_seed(seed, sequence), 0);
+            // The ids of the segments written by former writers of the index are skipped.
+            let is_used = SegmentComponent::iterator()
+                .filter(|&&component| component != SegmentComponent::Delete)
+                .any(|&component| {
+                    let path = segment_meta.relative_path(component);
+                    self.directory.exists(&path).unwrap_or(true)
+                });
+            if !is_used {
+                return self.segment(segment_meta);
+            }
+        }
     }
 
     /// Return a reference to the index directory.
@@ -525,6 +835,62 @@ impl Index {
     pub fn validate_checksum(&self) -> crate::Result<HashSet<PathBuf>> {
         self.directory.list_damaged().map_err(Into::into)
     }
+
+    /// Checks the consistency of all of the data of the segments of the last commit.
+    ///
+    /// This reads the whole index, and is therefore expensive.
+    /// See the [`check`](../check/index.html) module.
+    pub fn check(&self) -> crate::Result<IndexCheck> {
+        check_index(self)
+    }
+
+    /// Lists the files required to open the index at the commit identified by `opstamp`.
+    ///
+    /// This is meant for replication: a follower can compare the list with the files it
+    /// already has, fetch the missing ones using
+    /// [`Directory::read_bytes_range`](../directory/trait.Directory.html#method.read_bytes_range),
+    /// and finally write the meta file, which is always listed last.
+    ///
+    /// Only the last commit is available. If `opstamp` does not match the opstamp of
+    /// the last commit, an `InvalidArgument` error is returned.
+    ///
+    /// Files of a commit may be garbage collected after a newer commit. The leader should keep
+    /// a `Searcher` on the commit alive until its files have been copied.
+    pub fn list_files_for_commit(&self, opstamp: Opstamp) -> crate::Result<Vec<CommitFile>> {
+        let metas = self.load_metas()?;
+        if metas.opstamp != opstamp {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Commit {} is not available. The last commit is {}.",
+                opstamp, metas.opstamp
+            )));
+        }
+        let mut segment_paths: Vec<PathBuf> = Vec::new();
+        for segment_meta in &metas.segments {
+            for path in segment_meta.list_files() {
+                // Some components, like the delete bitset, are optional.
+                if self.directory.exists(&path)? {
+                    segment_paths.push(path);
+                }
+            }
+        }
+        segment_paths.sort();
+        let mut commit_files = Vec::with_capacity(segment_paths.len() + 1);
+        for path in segment_paths {
+            let (num_bytes, checksum) = self.directory.len_and_checksum(&path)?;
+            commit_files.push(CommitFile {
+                path,
+                num_bytes,
+                checksum: Some(checksum),
+            });
+        }
+        let meta_data = self.directory.atomic_read(&META_FILEPATH)?;
+        commit_files.push(CommitFile {
+            path: META_FILEPATH.to_path_buf(),
+            num_bytes: meta_data.len() as u64,
+            checksum: None,
+        });
+        Ok(commit_files)
+    }
 }
 
 impl fmt::Debug for Index {
@@ -535,8 +901,11 @@ impl fmt::Debug for Index {
 
 #[cfg(test)]
 mod tests {
+    use crate::error::{ErrorCode, SettingsError};
     use crate::schema::Field;
-    use crate::schema::{Schema, INDEXED, TEXT};
+    use crate::schema::{Cardinality, IntOptions, TextFieldIndexing, TextOptions};
+    use crate::schema::{Schema, FAST, INDEXED, TEXT};
+    use crate::tokenizer::{RawTokenizer, TokenizerManager};
     use crate::IndexReader;
     use crate::ReloadPolicy;
     use crate::{
@@ -544,6 +913,7 @@ mod tests {
         IndexSettings,
     };
     use crate::{Directory, Index};
+    use crate::{IndexSortByField, Order, TantivyError};
 
     #[test]
     fn test_indexer_for_field() {
@@ -559,6 +929,75 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_index_builder_validates_sort_field() {
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_u64_field("indexed", INDEXED);
+        schema_builder.add_u64_field(
+            "multivalued",
+            IntOptions::default().set_fast(Cardinality::MultiValues),
+        );
+        schema_builder.add_text_field("text", TEXT);
+        schema_builder.add_i64_field("fast", FAST);
+        let schema = schema_builder.build();
+        let create_index = |field: &str| {
+            let settings = IndexSettings {
+                sort_by_field: Some(IndexSortByField {
+                    field: field.to_string(),
+                    order: Order::Asc,
+                }),
+                ..Default::default()
+            };
+            Index::builder()
+                .schema(schema.clone())
+                .settings(settings)
+                .create_in_ram()
+        };
+        assert!(matches!(
+            create_index("missing"),
+            Err(TantivyError::InvalidSettings(
+                SettingsError::SortFieldNotFound(_)
+            ))
+        ));
+        for field in &["indexed", "multivalued", "text"] {
+            assert!(matches!(
+                create_index(field),
+                Err(TantivyError::InvalidSettings(
+                    SettingsError::InvalidSortField(_)
+                ))
+            ));
+        }
+        assert!(create_index("fast").is_ok());
+    }
+
+    #[test]
+    fn test_index_builder_validates_tokenizers() {
+        let mut schema_builder = Schema::builder();
+        let text_options = TextOptions::default()
+            .set_indexing_options(TextFieldIndexing::default().set_tokenizer("custom"));
+        schema_builder.add_text_field("text", text_options);
+        let schema = schema_builder.build();
+        let tokenizers = TokenizerManager::default();
+        let err = Index::builder()
+            .schema(schema.clone())
+            .tokenizers(tokenizers.clone())
+            .create_in_ram()
+            .err()
+            .unwrap();
+        assert_eq!(err.code(), ErrorCode::InvalidSettings);
+        assert!(matches!(
+            err,
+            TantivyError::InvalidSettings(SettingsError::TokenizerNotFound { .. })
+        ));
+        tokenizers.register("custom", RawTokenizer);
+        let index = Index::builder()
+            .schema(schema)
+            .tokenizers(tokenizers)
+            .create_in_ram()
+            .unwrap();
+        assert!(index.tokenizers().get("custom").is_some());
+    }
+
     #[test]
     fn test_index_exists() {
         let directory = RamDirectory::create();
@@ -787,4 +1226,214 @@ mod tests {
             mem_right_after_commit
         );
     }
+
+    #[test]
+    fn test_list_files_for_commit_and_replicate() -> crate::Result<()> {
+        use crate::directory::TerminatingWrite;
+        use std::io::Write;
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut writer = index.writer_for_tests()?;
+        writer.add_document(doc!(text_field => "hello"));
+        let first_opstamp = writer.commit()?;
+        writer.add_document(doc!(text_field => "happy tax payer"));
+        let opstamp = writer.commit()?;
+        assert!(index.list_files_for_commit(first_opstamp).is_err());
+        let commit_files = index.list_files_for_commit(opstamp)?;
+        assert_eq!(
+            commit_files
+                .last()
+                .map(|commit_file| commit_file.path.as_path()),
+            Some(std::path::Path::new("meta.json"))
+        );
+        let follower_directory = RamDirectory::create();
+        for commit_file in &commit_files {
+            if commit_file.checksum.is_none() {
+                let meta_data = index.directory().atomic_read(&commit_file.path)?;
+                follower_directory.atomic_write(&commit_file.path, &meta_data)?;
+                continue;
+            }
+            let mut wrt = follower_directory.open_write(&commit_file.path)?;
+            let num_bytes = commit_file.num_bytes as usize;
+            // Copies the file by chunks of 10 bytes.
+            for start in (0..num_bytes).step_by(10) {
+                let end = (start + 10).min(num_bytes);
+                let chunk = index
+                    .directory()
+                    .read_bytes_range(&commit_file.path, start..end)?;
+                wrt.write_all(chunk.as_slice())?;
+            }
+            wrt.terminate()?;
+        }
+        assert!(index
+            .directory()
+            .read_bytes_range(
+                &commit_files[0].path,
+                0..commit_files[0].num_bytes as usize + 1
+            )
+            .is_err());
+        let follower_index = Index::open(follower_directory)?;
+        assert!(follower_index.validate_checksum()?.is_empty());
+        assert_eq!(follower_index.reader()?.searcher().num_docs(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_meta_fallback_to_last_generation() -> crate::Result<()> {
+        use crate::core::META_FILEPATH;
+        let directory = RamDirectory::create();
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create(
+            directory.clone(),
+            schema_builder.build(),
+            IndexSettings::default(),
+        )?;
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text_field => "b"));
+        index_writer.commit()?;
+        drop(index_writer);
+        let opstamp = index.load_metas()?.opstamp;
+        let meta_data = directory.atomic_read(&META_FILEPATH)?;
+        let meta_string = String::from_utf8(meta_data.clone()).unwrap();
+
+        // A valid JSON file, whose checksum does not match.
+        let tampered_meta = meta_string.replace(
+            &format!("\"opstamp\": {}", opstamp),
+            &format!("\"opstamp\": {}", opstamp + 1),
+        );
+        assert_ne!(tampered_meta, meta_string);
+        directory.atomic_write(&META_FILEPATH, tampered_meta.as_bytes())?;
+        let index = Index::open(directory.clone())?;
+        assert_eq!(index.load_metas()?.opstamp, opstamp);
+
+        // A truncated file.
+        directory.atomic_write(&META_FILEPATH, &meta_data[..meta_data.len() / 2])?;
+        let index = Index::open(directory)?;
+        assert_eq!(index.reader()?.searcher().num_docs(), 2);
+        // The next commit restores `meta.json`.
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field => "c"));
+        index_writer.commit()?;
+        assert!(index.directory().atomic_read(&META_FILEPATH)?.len() > 0);
+        assert_eq!(index.reader()?.searcher().num_docs(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_commit() -> crate::Result<()> {
+        let directory = RamDirectory::create();
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create(
+            directory.clone(),
+            schema_builder.build(),
+            IndexSettings::default(),
+        )?;
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(crate::indexer::NoMergePolicy));
+        index_writer.set_num_commit_generations(2);
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.commit()?;
+        index_writer.delete_all_documents()?;
+        index_writer.add_document(doc!(text_field => "b"));
+        index_writer.add_document(doc!(text_field => "c"));
+        index_writer.commit()?;
+        futures::executor::block_on(index_writer.garbage_collect_files())?;
+
+        let generations = index.commit_generations()?;
+        assert_eq!(generations.len(), 2);
+        let previous_commit = Index::open_commit(directory.clone(), generations[0])?;
+        assert_eq!(previous_commit.reader()?.searcher().num_docs(), 1);
+        assert!(matches!(
+            previous_commit.writer_for_tests(),
+            Err(crate::TantivyError::InvalidArgument(_))
+        ));
+        let last_commit = Index::open_commit(directory, generations[1])?;
+        assert_eq!(last_commit.reader()?.searcher().num_docs(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_commit_after_rollback() -> crate::Result<()> {
+        let directory = RamDirectory::create();
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create(
+            directory.clone(),
+            schema_builder.build(),
+            IndexSettings::default(),
+        )?;
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(crate::indexer::NoMergePolicy));
+        index_writer.set_num_commit_generations(3);
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text_field => "b"));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text_field => "c"));
+        index_writer.rollback()?;
+        index_writer.set_merge_policy(Box::new(crate::indexer::NoMergePolicy));
+        index_writer.add_document(doc!(text_field => "d"));
+        index_writer.commit()?;
+        futures::executor::block_on(index_writer.garbage_collect_files())?;
+
+        let generations = index.commit_generations()?;
+        assert_eq!(generations.len(), 3);
+        let num_docs = generations
+            .into_iter()
+            .map(|generation| {
+                let commit = Index::open_commit(directory.clone(), generation)?;
+                Ok(commit.reader()?.searcher().num_docs())
+            })
+            .collect::<crate::Result<Vec<u64>>>()?;
+        assert_eq!(num_docs, vec![1, 2, 3]);
+        Ok(())
+    }
+
+    fn build_deterministic_index(seed: u64) -> crate::Result<Vec<(std::path::PathBuf, Vec<u8>)>> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT | crate::schema::STORED);
+        let num = schema_builder.add_u64_field("num", INDEXED | crate::schema::FAST);
+        let directory = RamDirectory::create();
+        let settings = IndexSettings {
+            deterministic_seed: Some(seed),
+            ..Default::default()
+        };
+        let index = Index::create(directory.clone(), schema_builder.build(), settings)?;
+        let mut index_writer = index.writer_with_num_threads(4, 12_000_000)?;
+        assert!(index_writer.is_single_threaded());
+        for commit in 0..3u64 {
+            for i in 0..100u64 {
+                index_writer.add_document(doc!(text => format!("doc {} {}", commit, i), num => i));
+            }
+            index_writer.commit()?;
+        }
+        let segment_ids = index.searchable_segment_ids()?;
+        futures::executor::block_on(index_writer.merge(&segment_ids))?;
+        index_writer.wait_merging_threads()?;
+        // Reopening the index does not reuse the ids of its segments.
+        let index = Index::open(directory.clone())?;
+        let mut index_writer = index.writer_with_num_threads(1, 12_000_000)?;
+        index_writer.add_document(doc!(text => "last", num => 0u64));
+        index_writer.commit()?;
+        index_writer.wait_merging_threads()?;
+        assert_eq!(index.searchable_segment_ids()?.len(), 2);
+        directory.export_files()
+    }
+
+    #[test]
+    fn test_deterministic_indexing() -> crate::Result<()> {
+        let files = build_deterministic_index(42)?;
+        assert_eq!(files, build_deterministic_index(42)?);
+        let other_seed_files = build_deterministic_index(43)?;
+        let paths = |files: &[(std::path::PathBuf, Vec<u8>)]| -> Vec<std::path::PathBuf> {
+            files.iter().map(|(path, _)| path.clone()).collect()
+        };
+        assert_ne!(paths(&files), paths(&other_seed_files));
+        Ok(())
+    }
 }
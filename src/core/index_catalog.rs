@@ -0,0 +1,327 @@
+use crate::core::{Index, IndexSettings, Searcher};
+use crate::directory::MmapDirectory;
+use crate::reader::IndexReader;
+use crate::schema::Schema;
+use crate::LeasedItem;
+use crate::TantivyError;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Rules deciding when the active partition of an `IndexCatalog` should be rolled over.
+///
+/// A rollover is needed as soon as one of the thresholds is reached.
+/// A policy without any threshold never requires a rollover.
+#[derive(Clone, Debug, Default)]
+pub struct RolloverPolicy {
+    /// Maximum number of committed documents in the active partition.
+    pub max_num_docs: Option<u64>,
+    /// Maximum age of the active partition.
+    pub max_age: Option<Duration>,
+}
+
+/// One of the time-partitioned indexes of an `IndexCatalog`.
+pub struct IndexPartition {
+    created_at_millis: u64,
+    path: PathBuf,
+    index: Index,
+    reader: IndexReader,
+}
+
+impl IndexPartition {
+    fn open(path: PathBuf, created_at_millis: u64, index: Index) -> crate::Result<IndexPartition> {
+        let reader = index.reader()?;
+        Ok(IndexPartition {
+            created_at_millis,
+            path,
+            index,
+            reader,
+        })
+    }
+
+    /// Returns the time at which the partition was created.
+    pub fn created_at(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(self.created_at_millis)
+    }
+
+    /// Returns the directory hosting the partition.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the index of the partition.
+    pub fn index(&self) -> &Index {
+        &self.index
+    }
+
+    /// Returns the reader of the partition.
+    pub fn reader(&self) -> &IndexReader {
+        &self.reader
+    }
+}
+
+/// Manages a family of time-partitioned indexes sharing the same schema,
+/// under one root directory.
+///
+/// Each partition is an index living in its own subdirectory,
+/// named after its creation time.
+/// Documents are expected to be added to the active partition, which is the
+/// most recent one. Once the active partition reaches one of the thresholds
+/// of the `RolloverPolicy`, a new partition is created and becomes the active one.
+///
+/// Old partitions can then be dropped as a whole, which is a cheap way to expire
+/// old data, e.g. for log indexes.
+pub struct IndexCatalog {
+    root: PathBuf,
+    schema: Schema,
+    settings: IndexSettings,
+    rollover_policy: RolloverPolicy,
+    // Sorted by creation time.
+    partitions: Vec<IndexPartition>,
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0u64)
+}
+
+fn partition_dirname(created_at_millis: u64) -> String {
+    format!("{:020}", created_at_millis)
+}
+
+impl IndexCatalog {
+    /// Opens the catalog located in the `root` directory, creating it if necessary.
+    ///
+    /// If the catalog does not have any partition yet, an empty partition is created.
+    /// All of the existing partitions are required to have the given schema.
+    pub fn open_or_create<P: AsRef<Path>>(
+        root: P,
+        schema: Schema,
+        settings: IndexSettings,
+        rollover_policy: RolloverPolicy,
+    ) -> crate::Result<IndexCatalog> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(&root)?;
+        let mut partitions = Vec::new();
+        for dir_entry_res in fs::read_dir(&root)? {
+            let dir_entry = dir_entry_res?;
+            if !dir_entry.file_type()?.is_dir() {
+                continue;
+            }
+            let created_at_millis = match dir_entry
+                .file_name()
+                .to_str()
+                .and_then(|dirname| dirname.parse::<u64>().ok())
+            {
+                Some(created_at_millis) => created_at_millis,
+                None => continue,
+            };
+            let directory = MmapDirectory::open(dir_entry.path())?;
+            if !Index::exists(&directory)? {
+                continue;
+            }
+            let index = Index::open(directory)?;
+            if index.schema() != schema {
+                return Err(TantivyError::SchemaError(format!(
+                    "The schema of the partition {:?} does not match the catalog schema.",
+                    dir_entry.path()
+                )));
+            }
+            partitions.push(IndexPartition::open(
+                dir_entry.path(),
+                created_at_millis,
+                index,
+            )?);
+        }
+        partitions.sort_by_key(|partition| partition.created_at_millis);
+        let mut catalog = IndexCatalog {
+            root,
+            schema,
+            settings,
+            rollover_policy,
+            partitions,
+        };
+        if catalog.partitions.is_empty() {
+            catalog.rollover()?;
+        }
+        Ok(catalog)
+    }
+
+    /// Returns the root directory of the catalog.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the partitions of the catalog, from the oldest to the most recent.
+    pub fn partitions(&self) -> &[IndexPartition] {
+        &self.partitions
+    }
+
+    /// Returns the active partition, in which new documents should be added.
+    pub fn active_partition(&self) -> &IndexPartition {
+        self.partitions
+            .last()
+            .expect("An index catalog always has at least one partition")
+    }
+
+    /// Returns true if the active partition reached one of the thresholds
+    /// of the rollover policy.
+    ///
+    /// Only committed documents are taken in account.
+    pub fn needs_rollover(&self) -> crate::Result<bool> {
+        let active_partition = self.active_partition();
+        if let Some(max_age) = self.rollover_policy.max_age {
+            let age_millis = now_millis().saturating_sub(active_partition.created_at_millis);
+            if Duration::from_millis(age_millis) >= max_age {
+                return Ok(true);
+            }
+        }
+        if let Some(max_num_docs) = self.rollover_policy.max_num_docs {
+            let num_docs: u64 = active_partition
+                .index
+                .searchable_segment_metas()?
+                .iter()
+                .map(|segment_meta| u64::from(segment_meta.num_docs()))
+                .sum();
+            if num_docs >= max_num_docs {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Creates a new partition, which becomes the active partition.
+    ///
+    /// The `IndexWriter` of the previous active partition, if any,
+    /// should be committed and dropped by the caller.
+    pub fn rollover(&mut self) -> crate::Result<&IndexPartition> {
+        let mut created_at_millis = now_millis();
+        if let Some(last_partition) = self.partitions.last() {
+            // Partitions are identified by their creation time, which needs to be unique.
+            created_at_millis = created_at_millis.max(last_partition.created_at_millis + 1);
+        }
+        let path = self.root.join(partition_dirname(created_at_millis));
+        fs::create_dir_all(&path)?;
+        let index = Index::builder()
+            .schema(self.schema.clone())
+            .settings(self.settings.clone())
+            .create_in_dir(&path)?;
+        self.partitions
+            .push(IndexPartition::open(path, created_at_millis, index)?);
+        Ok(self.active_partition())
+    }
+
+    /// Rolls over to a new partition if the rollover policy requires it.
+    ///
+    /// Returns true if a new partition was created.
+    pub fn rollover_if_needed(&mut self) -> crate::Result<bool> {
+        if !self.needs_rollover()? {
+            return Ok(false);
+        }
+        self.rollover()?;
+        Ok(true)
+    }
+
+    /// Returns a searcher for each of the partitions, from the oldest to the most recent.
+    pub fn searchers(&self) -> Vec<LeasedItem<Searcher>> {
+        self.partitions
+            .iter()
+            .map(|partition| partition.reader.searcher())
+            .collect()
+    }
+
+    /// Drops the partitions that only contain documents older than `max_age`.
+    ///
+    /// A partition is considered to only contain documents older than `max_age`
+    /// if its successor was created more than `max_age` ago.
+    /// The active partition is never dropped.
+    ///
+    /// No `IndexWriter` should be open on the dropped partitions.
+    /// Returns the number of dropped partitions.
+    pub fn drop_partitions_older_than(&mut self, max_age: Duration) -> crate::Result<usize> {
+        let now = now_millis();
+        let num_expired_partitions = self
+            .partitions
+            .windows(2)
+            .take_while(|partitions| {
+                let successor_age_millis = now.saturating_sub(partitions[1].created_at_millis);
+                Duration::from_millis(successor_age_millis) >= max_age
+            })
+            .count();
+        let expired_partitions: Vec<IndexPartition> =
+            self.partitions.drain(..num_expired_partitions).collect();
+        for expired_partition in expired_partitions {
+            let path = expired_partition.path.clone();
+            drop(expired_partition);
+            fs::remove_dir_all(&path)?;
+        }
+        Ok(num_expired_partitions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IndexCatalog, RolloverPolicy};
+    use crate::schema::{Schema, TEXT};
+    use crate::IndexSettings;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_index_catalog_rollover() -> crate::Result<()> {
+        let tempdir = TempDir::new()?;
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        let rollover_policy = RolloverPolicy {
+            max_num_docs: Some(2),
+            max_age: None,
+        };
+        let mut catalog = IndexCatalog::open_or_create(
+            tempdir.path(),
+            schema.clone(),
+            IndexSettings::default(),
+            rollover_policy.clone(),
+        )?;
+        assert_eq!(catalog.partitions().len(), 1);
+        assert!(!catalog.needs_rollover()?);
+        {
+            let mut index_writer = catalog.active_partition().index().writer_for_tests()?;
+            index_writer.add_document(doc!(text_field => "a"));
+            index_writer.add_document(doc!(text_field => "b"));
+            index_writer.commit()?;
+        }
+        assert!(catalog.rollover_if_needed()?);
+        assert!(!catalog.rollover_if_needed()?);
+        {
+            let mut index_writer = catalog.active_partition().index().writer_for_tests()?;
+            index_writer.add_document(doc!(text_field => "c"));
+            index_writer.commit()?;
+        }
+        let mut catalog = IndexCatalog::open_or_create(
+            tempdir.path(),
+            schema,
+            IndexSettings::default(),
+            rollover_policy,
+        )?;
+        let num_docs: Vec<u64> = catalog
+            .searchers()
+            .iter()
+            .map(|searcher| searcher.num_docs())
+            .collect();
+        assert_eq!(num_docs, vec![2, 1]);
+        assert_eq!(
+            catalog.drop_partitions_older_than(Duration::from_secs(3_600))?,
+            0
+        );
+        assert_eq!(
+            catalog.drop_partitions_older_than(Duration::from_secs(0))?,
+            1
+        );
+        assert_eq!(catalog.partitions().len(), 1);
+        assert_eq!(catalog.searchers()[0].num_docs(), 1);
+        Ok(())
+    }
+}
@@ -1,9 +1,14 @@
 use super::SegmentComponent;
-use crate::schema::Schema;
+use crate::schema::{Field, Schema};
 use crate::Opstamp;
-use crate::{core::SegmentId, store::Compressor};
+use crate::TantivyError;
+use crate::{
+    core::SegmentId,
+    store::{Compressor, DocSerialization},
+};
 use census::{Inventory, TrackedObject};
 use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
 use std::path::PathBuf;
 use std::{collections::HashSet, sync::atomic::AtomicBool};
 use std::{fmt, sync::Arc};
@@ -12,6 +17,25 @@ use std::{fmt, sync::Arc};
 struct DeleteMeta {
     num_deleted_docs: u32,
     opstamp: Opstamp,
+    /// Opstamps of the previous delete files of the segment.
+    ///
+    /// Each delete file only holds the docs deleted since the previous one.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    previous_opstamps: Vec<Opstamp>,
+    /// False for the delete files written by former versions of tantivy,
+    /// with one bit per document.
+    #[serde(default)]
+    chunked: bool,
+}
+
+/// Min and max values of a fast field in a segment,
+/// in their `u64` fast field representation.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct FastFieldRange {
+    pub field: Field,
+    pub min_value: u64,
+    pub max_value: u64,
 }
 
 #[derive(Clone, Default)]
@@ -35,6 +59,9 @@ impl SegmentMetaInventory {
             max_doc,
             include_temp_doc_store: Arc::new(AtomicBool::new(true)),
             deletes: None,
+            routing_partition: None,
+            fast_field_ranges: Vec::new(),
+            index_sort: None,
         };
         SegmentMeta::from(self.inventory.track(inner))
     }
@@ -106,6 +133,12 @@ impl SegmentMeta {
     /// is by removing all files that have been created by tantivy
     /// and are not used by any segment anymore.
     pub fn list_files(&self) -> HashSet<PathBuf> {
+        let mut files = self.list_component_files();
+        files.extend(self.delete_file_paths());
+        files
+    }
+
+    fn list_component_files(&self) -> HashSet<PathBuf> {
         if self
             .tracked
             .include_temp_doc_store
@@ -141,6 +174,34 @@ impl SegmentMeta {
         PathBuf::from(path)
     }
 
+    /// Returns the relative paths of the delete files of the segment,
+    /// from the oldest to the most recent one.
+    pub(crate) fn delete_file_paths(&self) -> Vec<PathBuf> {
+        let delete_meta = if let Some(delete_meta) = self.tracked.deletes.as_ref() {
+            delete_meta
+        } else {
+            return Vec::new();
+        };
+        let segment_id = self.id().uuid_string();
+        let mut paths: Vec<PathBuf> = delete_meta
+            .previous_opstamps
+            .iter()
+            .map(|opstamp| PathBuf::from(format!("{}.{}.del", segment_id, opstamp)))
+            .collect();
+        paths.push(self.relative_path(SegmentComponent::Delete));
+        paths
+    }
+
+    /// Returns true iff the segment has a delete file written by a former
+    /// version of tantivy, with one bit per document.
+    pub(crate) fn has_dense_delete_file(&self) -> bool {
+        self.tracked
+            .deletes
+            .as_ref()
+            .map(|delete_meta| !delete_meta.chunked)
+            .unwrap_or(false)
+    }
+
     /// Return the highest doc id + 1
     ///
     /// If there are no deletes, then num_docs = max_docs
@@ -170,6 +231,59 @@ impl SegmentMeta {
         self.num_deleted_docs() > 0
     }
 
+    /// Returns the routing partition of the segment.
+    ///
+    /// A segment has a routing partition iff all of its documents were added
+    /// with a routing key (see `IndexWriter::add_document_with_routing_key`),
+    /// and all of these routing keys belong to the same partition.
+    pub fn routing_partition(&self) -> Option<u32> {
+        self.tracked.routing_partition
+    }
+
+    /// Returns the min and max values of the fast field `field` in the segment,
+    /// in their `u64` fast field representation.
+    ///
+    /// Ranges are only recorded for the single-valued numeric and date fast fields
+    /// that are also indexed. Values of deleted documents are included.
+    pub fn fast_field_range(&self, field: Field) -> Option<RangeInclusive<u64>> {
+        self.tracked
+            .fast_field_ranges
+            .iter()
+            .find(|fast_field_range| fast_field_range.field == field)
+            .map(|fast_field_range| fast_field_range.min_value..=fast_field_range.max_value)
+    }
+
+    pub(crate) fn fast_field_ranges(&self) -> &[FastFieldRange] {
+        &self.tracked.fast_field_ranges
+    }
+
+    /// Returns the field and order the documents of the segment are sorted by,
+    /// if the segment was written by an index sorted by a field.
+    ///
+    /// Segments written before the sort was recorded in their meta return `None`.
+    pub fn index_sort(&self) -> Option<&IndexSortByField> {
+        self.tracked.index_sort.as_ref()
+    }
+
+    /// Returns an error if the segment records a sort that is not `index_sort`,
+    /// the sort of the index it is added to or merged in.
+    pub(crate) fn validate_index_sort(
+        &self,
+        index_sort: Option<&IndexSortByField>,
+    ) -> crate::Result<()> {
+        match (index_sort, self.index_sort()) {
+            (Some(index_sort), Some(segment_sort)) if index_sort != segment_sort => {
+                Err(TantivyError::InvalidArgument(format!(
+                    "Segment {} is sorted by {:?}, but the index is sorted by {:?}",
+                    self.id().uuid_string(),
+                    segment_sort,
+                    index_sort
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Updates the max_doc value from the `SegmentMeta`.
     ///
     /// This method is only used when updating `max_doc` from 0
@@ -182,6 +296,54 @@ impl SegmentMeta {
             max_doc,
             deletes: None,
             include_temp_doc_store: Arc::new(AtomicBool::new(true)),
+            routing_partition: inner_meta.routing_partition,
+            fast_field_ranges: inner_meta.fast_field_ranges.clone(),
+            index_sort: inner_meta.index_sort.clone(),
+        });
+        SegmentMeta { tracked }
+    }
+
+    /// Updates the routing partition of the `SegmentMeta`.
+    pub(crate) fn with_routing_partition(self, routing_partition: Option<u32>) -> SegmentMeta {
+        let tracked = self.tracked.map(move |inner_meta| InnerSegmentMeta {
+            segment_id: inner_meta.segment_id,
+            max_doc: inner_meta.max_doc,
+            deletes: inner_meta.deletes.clone(),
+            include_temp_doc_store: inner_meta.include_temp_doc_store.clone(),
+            routing_partition,
+            fast_field_ranges: inner_meta.fast_field_ranges.clone(),
+            index_sort: inner_meta.index_sort.clone(),
+        });
+        SegmentMeta { tracked }
+    }
+
+    /// Updates the fast field ranges of the `SegmentMeta`.
+    pub(crate) fn with_fast_field_ranges(
+        self,
+        fast_field_ranges: Vec<FastFieldRange>,
+    ) -> SegmentMeta {
+        let tracked = self.tracked.map(move |inner_meta| InnerSegmentMeta {
+            segment_id: inner_meta.segment_id,
+            max_doc: inner_meta.max_doc,
+            deletes: inner_meta.deletes.clone(),
+            include_temp_doc_store: inner_meta.include_temp_doc_store.clone(),
+            routing_partition: inner_meta.routing_partition,
+            fast_field_ranges,
+            index_sort: inner_meta.index_sort.clone(),
+        });
+        SegmentMeta { tracked }
+    }
+
+    /// Updates the sort of the documents of the `SegmentMeta`.
+    pub(crate) fn with_index_sort(self, index_sort: Option<IndexSortByField>) -> SegmentMeta {
+        let tracked = self.tracked.map(move |inner_meta| InnerSegmentMeta {
+            segment_id: inner_meta.segment_id,
+            max_doc: inner_meta.max_doc,
+            deletes: inner_meta.deletes.clone(),
+            include_temp_doc_store: inner_meta.include_temp_doc_store.clone(),
+            routing_partition: inner_meta.routing_partition,
+            fast_field_ranges: inner_meta.fast_field_ranges.clone(),
+            index_sort,
         });
         SegmentMeta { tracked }
     }
@@ -191,12 +353,39 @@ impl SegmentMeta {
         let delete_meta = DeleteMeta {
             num_deleted_docs,
             opstamp,
+            previous_opstamps: Vec::new(),
+            chunked: true,
         };
+        self.with_deletes(delete_meta)
+    }
+
+    /// Updates the deletes of the `SegmentMeta`, with a new delete file
+    /// only holding the docs deleted since the current one.
+    pub(crate) fn with_delete_file(self, num_deleted_docs: u32, opstamp: Opstamp) -> SegmentMeta {
+        let mut previous_opstamps = Vec::new();
+        if let Some(delete_meta) = self.tracked.deletes.as_ref() {
+            assert!(delete_meta.chunked);
+            previous_opstamps.extend_from_slice(&delete_meta.previous_opstamps);
+            previous_opstamps.push(delete_meta.opstamp);
+        }
+        let delete_meta = DeleteMeta {
+            num_deleted_docs,
+            opstamp,
+            previous_opstamps,
+            chunked: true,
+        };
+        self.with_deletes(delete_meta)
+    }
+
+    fn with_deletes(self, delete_meta: DeleteMeta) -> SegmentMeta {
         let tracked = self.tracked.map(move |inner_meta| InnerSegmentMeta {
             segment_id: inner_meta.segment_id,
             max_doc: inner_meta.max_doc,
             include_temp_doc_store: Arc::new(AtomicBool::new(true)),
             deletes: Some(delete_meta),
+            routing_partition: inner_meta.routing_partition,
+            fast_field_ranges: inner_meta.fast_field_ranges.clone(),
+            index_sort: inner_meta.index_sort.clone(),
         });
         SegmentMeta { tracked }
     }
@@ -212,6 +401,19 @@ struct InnerSegmentMeta {
     #[serde(skip)]
     #[serde(default = "default_temp_store")]
     pub(crate) include_temp_doc_store: Arc<AtomicBool>,
+    /// Partition of the routing keys of the documents of the segment, if
+    /// all of them were routed to the same partition.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    routing_partition: Option<u32>,
+    /// Min and max values of the indexed numeric fast fields of the segment.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    fast_field_ranges: Vec<FastFieldRange>,
+    /// Sort of the documents of the segment, if the index is sorted.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    index_sort: Option<IndexSortByField>,
 }
 fn default_temp_store() -> Arc<AtomicBool> {
     Arc::new(AtomicBool::new(false))
@@ -238,13 +440,52 @@ pub struct IndexSettings {
     /// The `Compressor` used to compress the doc store.
     #[serde(default)]
     pub docstore_compression: Compressor,
+    /// The format in which documents are serialized in the doc store.
+    #[serde(default)]
+    pub docstore_serialization: DocSerialization,
+    /// Size, in bytes, above which a stored value is compressed on its own in the doc store.
+    ///
+    /// Documents are compressed by blocks of about 16KB, which are decompressed as a
+    /// whole to read any of their documents. Compressing the large values on their own
+    /// spares reading the other documents of their block the cost of decompressing them.
+    ///
+    /// Disabled by default.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub docstore_large_value_threshold: Option<usize>,
+    /// Name of the field used as the primary key of the documents.
+    ///
+    /// See `IndexWriter::upsert` and `Searcher::doc_by_key`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub primary_key: Option<String>,
+    /// Seed making the indexing deterministic.
+    ///
+    /// When set, segment ids are derived from the seed instead of being random, and
+    /// the writers of the index do not spawn any thread, so that the same documents
+    /// added in the same order with the same settings give byte-identical segment files.
+    ///
+    /// See [`Index::single_threaded_writer`](../struct.Index.html#method.single_threaded_writer).
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deterministic_seed: Option<u64>,
+    /// Number of partitions the routing keys are hashed into.
+    ///
+    /// See `IndexWriter::add_document_with_routing_key`. As it is persisted with the index,
+    /// the partition of a routing key does not change when the index is reopened,
+    /// whatever the number of indexing threads of the writer.
+    ///
+    /// Defaults to 4.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_routing_partitions: Option<u32>,
 }
 /// Settings to presort the documents in an index
 ///
 /// Presorting documents can greatly performance
 /// in some scenarios, by applying top n
 /// optimizations.
-#[derive(Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
 pub struct IndexSortByField {
     /// The field to sort the documents by
     pub field: String,
@@ -252,7 +493,7 @@ pub struct IndexSortByField {
     pub order: Order,
 }
 /// The order to sort by
-#[derive(Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
 pub enum Order {
     /// Ascending Order
     Asc,
@@ -270,6 +511,12 @@ impl Order {
     }
 }
 
+// Fields appended by `IndexMeta::serialize_meta_file` to the pretty printed JSON object.
+// Strings in JSON cannot contain a raw line break, so these cannot appear
+// within the serialized metas.
+const GENERATION_FIELD_PREFIX: &str = ",\n  \"generation\": ";
+const CHECKSUM_FIELD_PREFIX: &str = ",\n  \"checksum\": ";
+
 /// Meta information about the `Index`.
 ///
 /// This object is serialized on disk in the `meta.json` file.
@@ -296,6 +543,12 @@ pub struct IndexMeta {
     /// to help identify this commit.
     /// This payload is entirely unused by tantivy.
     pub payload: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    /// Content hashes of the documents added by the last commit.
+    ///
+    /// They are only recorded if deduplication is enabled on the `IndexWriter`
+    /// (see `IndexWriter::set_deduplication`).
+    pub dedup_hashes: Vec<u64>,
 }
 
 #[derive(Deserialize)]
@@ -307,6 +560,8 @@ struct UntrackedIndexMeta {
     pub opstamp: Opstamp,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub payload: Option<String>,
+    #[serde(default)]
+    pub dedup_hashes: Vec<u64>,
 }
 
 impl UntrackedIndexMeta {
@@ -321,6 +576,7 @@ impl UntrackedIndexMeta {
             schema: self.schema,
             opstamp: self.opstamp,
             payload: self.payload,
+            dedup_hashes: self.dedup_hashes,
         }
     }
 }
@@ -338,14 +594,49 @@ impl IndexMeta {
             schema,
             opstamp: 0u64,
             payload: None,
+            dedup_hashes: Vec::new(),
         }
     }
 
+    /// Serializes the metas into the content of the meta file of the commit
+    /// of generation `generation`.
+    ///
+    /// The generation and a checksum are appended as the last two fields of the JSON object.
+    /// The checksum is the CRC32 of the content of the file preceding it.
+    pub(crate) fn serialize_meta_file(&self, generation: u64) -> serde_json::Result<Vec<u8>> {
+        let mut buffer = serde_json::to_vec_pretty(self)?;
+        // Removes the closing "\n}" of the JSON object.
+        buffer.truncate(buffer.len() - 2);
+        buffer.extend_from_slice(format!("{}{}", GENERATION_FIELD_PREFIX, generation).as_bytes());
+        let checksum = crc32fast::hash(&buffer);
+        buffer.extend_from_slice(format!("{}{}\n}}\n", CHECKSUM_FIELD_PREFIX, checksum).as_bytes());
+        Ok(buffer)
+    }
+
+    /// Deserializes the content of a meta file, after checking its checksum.
+    ///
+    /// Meta files written by older versions of tantivy have no checksum.
     pub(crate) fn deserialize(
         meta_json: &str,
         inventory: &SegmentMetaInventory,
-    ) -> serde_json::Result<IndexMeta> {
-        let untracked_meta_json: UntrackedIndexMeta = serde_json::from_str(meta_json)?;
+    ) -> Result<IndexMeta, String> {
+        let meta_json_trimmed = meta_json.trim_end();
+        if let Some(checksum_pos) = meta_json_trimmed.rfind(CHECKSUM_FIELD_PREFIX) {
+            let expected_checksum: u32 = meta_json_trimmed
+                [checksum_pos + CHECKSUM_FIELD_PREFIX.len()..]
+                .strip_suffix("\n}")
+                .and_then(|checksum_str| checksum_str.parse().ok())
+                .ok_or_else(|| "Meta file checksum cannot be parsed.".to_string())?;
+            let checksum = crc32fast::hash(meta_json_trimmed[..checksum_pos].as_bytes());
+            if checksum != expected_checksum {
+                return Err(format!(
+                    "Meta file checksum mismatch: expected {}, got {}.",
+                    expected_checksum, checksum
+                ));
+            }
+        }
+        let untracked_meta_json: UntrackedIndexMeta = serde_json::from_str(meta_json)
+            .map_err(|err| format!("Meta file cannot be deserialized. {:?}.", err))?;
         Ok(untracked_meta_json.track(inventory))
     }
 }
@@ -389,11 +680,12 @@ mod tests {
             schema,
             opstamp: 0u64,
             payload: None,
+            dedup_hashes: Vec::new(),
         };
         let json = serde_json::ser::to_string(&index_metas).expect("serialization failed");
         assert_eq!(
             json,
-            r#"{"index_settings":{"sort_by_field":{"field":"text","order":"Asc"},"docstore_compression":"lz4"},"segments":[],"schema":[{"name":"text","type":"text","options":{"indexing":{"record":"position","tokenizer":"default"},"stored":false}}],"opstamp":0}"#
+            r#"{"index_settings":{"sort_by_field":{"field":"text","order":"Asc"},"docstore_compression":"lz4","docstore_serialization":"default"},"segments":[],"schema":[{"name":"text","type":"text","options":{"indexing":{"record":"position","tokenizer":"default"},"stored":false}}],"opstamp":0}"#
         );
     }
 }
@@ -1,10 +1,11 @@
 use std::io;
 
-use crate::common::BinarySerializable;
-use crate::directory::FileSlice;
-use crate::positions::PositionReader;
+use crate::common::{BinarySerializable, VInt};
+use crate::directory::{FileSlice, LazyFileSlice};
+use crate::positions::{split_positions_and_offsets, PositionReader};
+use crate::postings::compression::COMPRESSION_BLOCK_SIZE;
 use crate::postings::TermInfo;
-use crate::postings::{BlockSegmentPostings, SegmentPostings};
+use crate::postings::{BlockSegmentPostings, DenseDocSet, SegmentPostings};
 use crate::schema::IndexRecordOption;
 use crate::schema::Term;
 use crate::termdict::TermDictionary;
@@ -19,13 +20,16 @@ use crate::termdict::TermDictionary;
 /// the `FileSlice` it is relying on should
 /// stay available.
 ///
+/// The positions are an exception: they are only opened when they are
+/// first read, e.g. by a phrase query. Until then, the `SegmentReader`
+/// protects their file from garbage collection.
 ///
 /// `InvertedIndexReader` are created by calling
 /// the `SegmentReader`'s [`.inverted_index(...)`] method
 pub struct InvertedIndexReader {
     termdict: TermDictionary,
     postings_file_slice: FileSlice,
-    positions_file_slice: FileSlice,
+    positions_file_slice: LazyFileSlice,
     record_option: IndexRecordOption,
     total_num_tokens: u64,
 }
@@ -35,7 +39,7 @@ impl InvertedIndexReader {
     pub(crate) fn new(
         termdict: TermDictionary,
         postings_file_slice: FileSlice,
-        positions_file_slice: FileSlice,
+        positions_file_slice: LazyFileSlice,
         record_option: IndexRecordOption,
     ) -> io::Result<InvertedIndexReader> {
         let (total_num_tokens_slice, postings_body) = postings_file_slice.split(8);
@@ -55,14 +59,14 @@ impl InvertedIndexReader {
         InvertedIndexReader {
             termdict: TermDictionary::empty(),
             postings_file_slice: FileSlice::empty(),
-            positions_file_slice: FileSlice::empty(),
+            positions_file_slice: LazyFileSlice::from(FileSlice::empty()),
             record_option,
             total_num_tokens: 0u64,
         }
     }
 
     /// Returns the term info associated with the term.
-    pub fn get_term_info(&self, term: &Term) -> io::Result<Option<TermInfo>> {
+    pub fn get_term_info<B: AsRef<[u8]>>(&self, term: &Term<B>) -> io::Result<Option<TermInfo>> {
         self.termdict.get(term.value_bytes())
     }
 
@@ -97,9 +101,9 @@ impl InvertedIndexReader {
     /// This method is for an advanced usage only.
     ///
     /// Most user should prefer using `read_postings` instead.
-    pub fn read_block_postings(
+    pub fn read_block_postings<B: AsRef<[u8]>>(
         &self,
-        term: &Term,
+        term: &Term<B>,
         option: IndexRecordOption,
     ) -> io::Result<Option<BlockSegmentPostings>> {
         self.get_term_info(term)?
@@ -137,21 +141,31 @@ impl InvertedIndexReader {
         option: IndexRecordOption,
     ) -> io::Result<SegmentPostings> {
         let block_postings = self.read_block_postings_from_terminfo(term_info, option)?;
+        let mut offsets_reader = None;
         let position_reader = {
             if option.has_positions() {
-                let positions_data = self
+                let mut positions_data = self
                     .positions_file_slice
+                    .get()?
                     .read_bytes_slice(term_info.positions_range.clone())?;
+                if self.record_option.has_offsets() {
+                    let (term_positions_data, offsets_data) =
+                        split_positions_and_offsets(positions_data)?;
+                    if option.has_offsets() {
+                        offsets_reader = Some(PositionReader::open(offsets_data)?);
+                    }
+                    positions_data = term_positions_data;
+                }
                 let position_reader = PositionReader::open(positions_data)?;
                 Some(position_reader)
             } else {
                 None
             }
         };
-        Ok(SegmentPostings::from_block_postings(
-            block_postings,
-            position_reader,
-        ))
+        Ok(
+            SegmentPostings::from_block_postings(block_postings, position_reader)
+                .with_offsets_reader(offsets_reader),
+        )
     }
 
     /// Returns the total number of tokens recorded for all documents
@@ -170,9 +184,9 @@ impl InvertedIndexReader {
     /// For instance, requesting `IndexRecordOption::Freq` for a
     /// `TextIndexingOptions` that does not index position will return a `SegmentPostings`
     /// with `DocId`s and frequencies.
-    pub fn read_postings(
+    pub fn read_postings<B: AsRef<[u8]>>(
         &self,
-        term: &Term,
+        term: &Term<B>,
         option: IndexRecordOption,
     ) -> io::Result<Option<SegmentPostings>> {
         self.get_term_info(term)?
@@ -180,9 +194,9 @@ impl InvertedIndexReader {
             .transpose()
     }
 
-    pub(crate) fn read_postings_no_deletes(
+    pub(crate) fn read_postings_no_deletes<B: AsRef<[u8]>>(
         &self,
-        term: &Term,
+        term: &Term<B>,
         option: IndexRecordOption,
     ) -> io::Result<Option<SegmentPostings>> {
         self.get_term_info(term)?
@@ -190,8 +204,38 @@ impl InvertedIndexReader {
             .transpose()
     }
 
+    /// Returns the documents containing the term as a `DenseDocSet`,
+    /// if its posting list is serialized as a bitmap.
+    ///
+    /// The bitmap is read in place, without being copied.
+    ///
+    /// Only the posting lists of terms appearing in a large fraction of the documents
+    /// of a field indexed with `IndexRecordOption::Basic` are serialized as bitmaps.
+    /// `None` is returned for all of the other terms.
+    pub(crate) fn read_dense_docset<B: AsRef<[u8]>>(
+        &self,
+        term: &Term<B>,
+    ) -> io::Result<Option<DenseDocSet>> {
+        let term_info = match self.get_term_info(term)? {
+            Some(term_info) => term_info,
+            None => return Ok(None),
+        };
+        if term_info.doc_freq < COMPRESSION_BLOCK_SIZE as u32 {
+            return Ok(None);
+        }
+        let mut postings_data = self
+            .postings_file_slice
+            .read_bytes_slice(term_info.postings_range)?;
+        // Dense posting lists have an empty skip list.
+        let skip_len = VInt::deserialize(&mut postings_data)?.0;
+        if skip_len != 0 {
+            return Ok(None);
+        }
+        Ok(Some(DenseDocSet::new(postings_data, term_info.doc_freq)))
+    }
+
     /// Returns the number of documents containing the term.
-    pub fn doc_freq(&self, term: &Term) -> io::Result<u32> {
+    pub fn doc_freq<B: AsRef<[u8]>>(&self, term: &Term<B>) -> io::Result<u32> {
         Ok(self
             .get_term_info(term)?
             .map(|term_info| term_info.doc_freq)
@@ -0,0 +1,109 @@
+use crate::TantivyError;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[derive(Debug)]
+struct InnerMemoryBudget {
+    limit: Option<usize>,
+    num_bytes_allocated: AtomicUsize,
+}
+
+/// Caps the memory allocated by the transient data structures of a query,
+/// such as the bitsets built by multi-term queries, the scorers or
+/// the heaps of the top-k collectors.
+///
+/// Queries charge their allocations to the budget before performing them.
+/// Once the budget is exhausted, the query fails with
+/// `TantivyError::MemoryBudgetExceeded` rather than allocating more memory.
+///
+/// A budget is not replenished: it caps the overall amount of memory allocated
+/// during the execution of a query.
+/// Cloning a `MemoryBudget` returns a handle to the same budget.
+#[derive(Clone, Debug)]
+pub struct MemoryBudget {
+    inner: Arc<InnerMemoryBudget>,
+}
+
+impl MemoryBudget {
+    /// Creates a budget without any limit.
+    pub fn unlimited() -> MemoryBudget {
+        MemoryBudget::new(None)
+    }
+
+    /// Creates a budget of `num_bytes` bytes.
+    pub fn with_limit(num_bytes: usize) -> MemoryBudget {
+        MemoryBudget::new(Some(num_bytes))
+    }
+
+    fn new(limit: Option<usize>) -> MemoryBudget {
+        MemoryBudget {
+            inner: Arc::new(InnerMemoryBudget {
+                limit,
+                num_bytes_allocated: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    /// Returns the limit of the budget, in bytes, or `None` if the budget is unlimited.
+    pub fn limit(&self) -> Option<usize> {
+        self.inner.limit
+    }
+
+    /// Returns the number of bytes charged to the budget so far.
+    pub fn num_bytes_allocated(&self) -> usize {
+        self.inner.num_bytes_allocated.load(Ordering::SeqCst)
+    }
+
+    /// Charges an allocation of `num_bytes` bytes to the budget.
+    ///
+    /// Returns `TantivyError::MemoryBudgetExceeded` if the allocation
+    /// does not fit in the budget. In that case, nothing is charged.
+    pub fn allocate(&self, num_bytes: usize) -> crate::Result<()> {
+        let limit = self.inner.limit.unwrap_or(usize::MAX);
+        self.inner
+            .num_bytes_allocated
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |num_bytes_allocated| {
+                num_bytes_allocated
+                    .checked_add(num_bytes)
+                    .filter(|&new_num_bytes_allocated| new_num_bytes_allocated <= limit)
+            })
+            .map(|_| ())
+            .map_err(|_| TantivyError::MemoryBudgetExceeded(limit))
+    }
+}
+
+impl Default for MemoryBudget {
+    fn default() -> MemoryBudget {
+        MemoryBudget::unlimited()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MemoryBudget;
+    use crate::TantivyError;
+
+    #[test]
+    fn test_memory_budget() {
+        let memory_budget = MemoryBudget::with_limit(100);
+        assert_eq!(memory_budget.limit(), Some(100));
+        assert!(memory_budget.allocate(60).is_ok());
+        assert!(matches!(
+            memory_budget.clone().allocate(60),
+            Err(TantivyError::MemoryBudgetExceeded(100))
+        ));
+        assert_eq!(memory_budget.num_bytes_allocated(), 60);
+        assert!(memory_budget.allocate(40).is_ok());
+        assert!(memory_budget.allocate(1).is_err());
+        assert!(memory_budget.allocate(usize::MAX).is_err());
+        assert_eq!(memory_budget.num_bytes_allocated(), 100);
+    }
+
+    #[test]
+    fn test_memory_budget_unlimited() {
+        let memory_budget = MemoryBudget::unlimited();
+        assert_eq!(memory_budget.limit(), None);
+        assert!(memory_budget.allocate(1_000_000_000).is_ok());
+        assert_eq!(memory_budget.num_bytes_allocated(), 1_000_000_000);
+    }
+}
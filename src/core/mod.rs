@@ -1,7 +1,14 @@
 mod executor;
+mod expansion_budget;
+pub(crate) mod global_ordinals;
 pub mod index;
+#[cfg(feature = "mmap")]
+mod index_catalog;
 mod index_meta;
 mod inverted_index_reader;
+mod memory_budget;
+mod multi_terms;
+mod scoring_stats;
 pub mod searcher;
 mod segment;
 mod segment_component;
@@ -9,24 +16,50 @@ mod segment_id;
 mod segment_reader;
 
 pub use self::executor::Executor;
-pub use self::index::{Index, IndexBuilder};
+pub use self::expansion_budget::{ExpansionBudget, ExpansionOverflow};
+pub use self::global_ordinals::GlobalOrdinals;
+pub use self::index::{CommitFile, Index, IndexBuilder};
+#[cfg(feature = "mmap")]
+pub use self::index_catalog::{IndexCatalog, IndexPartition, RolloverPolicy};
+pub(crate) use self::index_meta::FastFieldRange;
 pub use self::index_meta::{
     IndexMeta, IndexSettings, IndexSortByField, Order, SegmentMeta, SegmentMetaInventory,
 };
 pub use self::inverted_index_reader::InvertedIndexReader;
-pub use self::searcher::Searcher;
+pub use self::memory_budget::MemoryBudget;
+pub use self::multi_terms::{MultiTermStreamer, MultiTerms};
+pub use self::scoring_stats::ScoringStats;
+pub use self::searcher::{FieldStats, Searcher, TermStats};
 pub use self::segment::Segment;
 pub use self::segment_component::SegmentComponent;
 pub use self::segment_id::SegmentId;
 pub use self::segment_reader::SegmentReader;
 
 use once_cell::sync::Lazy;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// The meta file contains all the information about the list of segments and the schema
 /// of the index.
 pub static META_FILEPATH: Lazy<&'static Path> = Lazy::new(|| Path::new("meta.json"));
 
+/// Returns the path of the meta file of the commit of generation `generation`.
+///
+/// Each commit writes its meta file both under this path and under `META_FILEPATH`,
+/// so that the last commits can be recovered if `meta.json` gets corrupted.
+pub(crate) fn meta_generation_filepath(generation: u64) -> PathBuf {
+    PathBuf::from(format!("meta.{}.json", generation))
+}
+
+/// Returns the generation of a meta file, or `None` if `path` is not
+/// the path of a meta file generation.
+pub(crate) fn meta_generation_from_filepath(path: &Path) -> Option<u64> {
+    path.to_str()?
+        .strip_prefix("meta.")?
+        .strip_suffix(".json")?
+        .parse()
+        .ok()
+}
+
 /// The managed file contains a list of files that were created by the tantivy
 /// and will therefore be garbage collected when they are deemed useless by tantivy.
 ///
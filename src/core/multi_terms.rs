@@ -0,0 +1,236 @@
+use std::io;
+use std::ops::Bound;
+use std::sync::Arc;
+
+use crate::common::numeric_trie::is_full_precision_term;
+use crate::core::InvertedIndexReader;
+use crate::postings::TermInfo;
+use crate::termdict::{TermMerger, TermOrdinal, TermStreamer};
+
+/// The terms of a field, over all of the segments of a `Searcher`.
+///
+/// This is the equivalent of Lucene's `MultiTerms`: the term dictionaries
+/// of the segments are merged into a single sorted stream of unique terms.
+///
+/// For fields indexed at several precisions, only the full precision terms
+/// are returned: the truncated prefixes of the values are skipped.
+///
+/// `MultiTerms` are created by calling the `Searcher`'s
+/// [`.terms(...)`](./struct.Searcher.html#method.terms) method.
+pub struct MultiTerms {
+    inverted_indexes: Vec<Arc<InvertedIndexReader>>,
+    full_precision_only: bool,
+}
+
+impl MultiTerms {
+    pub(crate) fn new(
+        inverted_indexes: Vec<Arc<InvertedIndexReader>>,
+        full_precision_only: bool,
+    ) -> MultiTerms {
+        MultiTerms {
+            inverted_indexes,
+            full_precision_only,
+        }
+    }
+
+    /// Returns the inverted indexes of the field, in the order of the segments.
+    pub fn inverted_indexes(&self) -> &[Arc<InvertedIndexReader>] {
+        &self.inverted_indexes
+    }
+
+    /// Returns a stream over all of the terms of the field.
+    pub fn stream(&self) -> io::Result<MultiTermStreamer<'_>> {
+        self.range(Bound::Unbounded, Bound::Unbounded)
+    }
+
+    /// Returns a stream over the terms of the field within the given bounds.
+    pub fn range(
+        &self,
+        lower_bound: Bound<&[u8]>,
+        upper_bound: Bound<&[u8]>,
+    ) -> io::Result<MultiTermStreamer<'_>> {
+        let streams = self
+            .inverted_indexes
+            .iter()
+            .map(|inverted_index| {
+                let mut range = inverted_index.terms().range();
+                range = match lower_bound {
+                    Bound::Included(bound) => range.ge(bound),
+                    Bound::Excluded(bound) => range.gt(bound),
+                    Bound::Unbounded => range,
+                };
+                range = match upper_bound {
+                    Bound::Included(bound) => range.le(bound),
+                    Bound::Excluded(bound) => range.lt(bound),
+                    Bound::Unbounded => range,
+                };
+                range.into_stream()
+            })
+            .collect::<io::Result<Vec<TermStreamer<'_>>>>()?;
+        Ok(MultiTermStreamer {
+            term_merger: TermMerger::new(streams),
+            doc_freq: 0u64,
+            full_precision_only: self.full_precision_only,
+        })
+    }
+}
+
+/// Cursor over the sorted unique terms of a `MultiTerms`.
+pub struct MultiTermStreamer<'a> {
+    term_merger: TermMerger<'a>,
+    doc_freq: u64,
+    full_precision_only: bool,
+}
+
+impl<'a> MultiTermStreamer<'a> {
+    /// Advances the stream to the next term.
+    /// Returns false if there is no more term.
+    pub fn advance(&mut self) -> bool {
+        loop {
+            if !self.term_merger.advance() {
+                return false;
+            }
+            if !self.full_precision_only || is_full_precision_term(self.term_merger.key()) {
+                break;
+            }
+        }
+        self.doc_freq = self
+            .term_merger
+            .current_segment_ordinals_and_term_infos()
+            .map(|(_, term_info)| u64::from(term_info.doc_freq))
+            .sum();
+        true
+    }
+
+    /// Returns the current term.
+    ///
+    /// This method may be called
+    /// iff advance() has been called before
+    /// and "true" was returned.
+    pub fn key(&self) -> &[u8] {
+        self.term_merger.key()
+    }
+
+    /// Returns the number of documents containing the current term,
+    /// over all of the segments (including deleted documents).
+    ///
+    /// This method may be called
+    /// iff advance() has been called before
+    /// and "true" was returned.
+    pub fn doc_freq(&self) -> u64 {
+        self.doc_freq
+    }
+
+    /// Iterator over the (segment ordinal, segment term ordinal) pairs of the current term,
+    /// sorted by segment ordinal.
+    pub fn segment_ords_and_term_ords(&self) -> impl Iterator<Item = (usize, TermOrdinal)> + '_ {
+        self.term_merger.matching_segments()
+    }
+
+    /// Iterator over the (segment ordinal, `TermInfo`) pairs of the current term,
+    /// sorted by segment ordinal.
+    ///
+    /// The segment ordinals index the segment readers of the `Searcher`.
+    pub fn segment_ords_and_term_infos(&self) -> impl Iterator<Item = (usize, TermInfo)> + '_ {
+        self.term_merger.current_segment_ordinals_and_term_infos()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schema::{IntOptions, Schema, INDEXED, STORED, STRING};
+    use crate::{Index, TantivyError, Term};
+    use std::ops::Bound;
+
+    #[test]
+    fn test_searcher_terms_precision_step() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let id_options = IntOptions::default().set_indexed().set_precision_step(16);
+        let id_field = schema_builder.add_u64_field("id", id_options);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for id in &[1u64, 70_000u64, 1u64 << 40] {
+            index_writer.add_document(doc!(id_field => *id));
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        // The truncated prefixes of the values are not enumerated.
+        let id_terms = searcher.terms(id_field)?;
+        let mut stream = id_terms.stream()?;
+        let mut ids = Vec::new();
+        while stream.advance() {
+            let mut id_bytes = [0u8; 8];
+            id_bytes.copy_from_slice(stream.key());
+            ids.push(u64::from_be_bytes(id_bytes));
+        }
+        assert_eq!(ids, vec![1u64, 70_000u64, 1u64 << 40]);
+        assert_eq!(searcher.field_stats(id_field)?.num_terms, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_searcher_terms() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let tag_field = schema_builder.add_text_field("tag", STRING);
+        let id_field = schema_builder.add_u64_field("id", INDEXED);
+        let stored_field = schema_builder.add_text_field("stored", STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(tag_field => "blue", id_field => 1u64));
+        index_writer.add_document(doc!(tag_field => "red", id_field => 2u64));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(tag_field => "green", id_field => 3u64));
+        index_writer.add_document(doc!(tag_field => "red", id_field => 4u64));
+        index_writer.add_document(doc!(tag_field => "red", id_field => 5u64));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+
+        let terms = searcher.terms(tag_field)?;
+        assert_eq!(terms.inverted_indexes().len(), 2);
+        let mut stream = terms.stream()?;
+        let mut terms_and_doc_freqs = Vec::new();
+        while stream.advance() {
+            terms_and_doc_freqs.push((
+                String::from_utf8(stream.key().to_vec()).unwrap(),
+                stream.doc_freq(),
+                stream.segment_ords_and_term_infos().count(),
+            ));
+        }
+        assert_eq!(
+            terms_and_doc_freqs,
+            vec![
+                ("blue".to_string(), 1, 1),
+                ("green".to_string(), 1, 1),
+                ("red".to_string(), 3, 2),
+            ]
+        );
+
+        let mut stream = terms.range(
+            Bound::Excluded(&b"blue"[..]),
+            Bound::Included(&b"green"[..]),
+        )?;
+        assert!(stream.advance());
+        assert_eq!(stream.key(), &b"green"[..]);
+        assert!(!stream.advance());
+
+        let mut num_ids = 0;
+        let id_terms = searcher.terms(id_field)?;
+        let mut stream = id_terms.stream()?;
+        while stream.advance() {
+            assert_eq!(stream.doc_freq(), 1);
+            num_ids += 1;
+        }
+        assert_eq!(num_ids, 5);
+        assert_eq!(
+            searcher.doc_freq(&Term::from_field_text(tag_field, "red"))?,
+            3
+        );
+
+        assert!(matches!(
+            searcher.terms(stored_field),
+            Err(TantivyError::SchemaError(_))
+        ));
+        Ok(())
+    }
+}
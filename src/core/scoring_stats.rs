@@ -0,0 +1,234 @@
+use crate::common::{BinarySerializable, VInt};
+use crate::schema::{Field, Term};
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+
+/// Statistics used to compute the BM25 scores of a query.
+///
+/// In a distributed deployment, each shard exports the statistics of a query with
+/// [`Searcher::scoring_stats`](./struct.Searcher.html#method.scoring_stats).
+/// Once merged, the statistics are supplied to the searcher of every shard with
+/// [`Searcher::with_scoring_stats`](./struct.Searcher.html#method.with_scoring_stats),
+/// so that the scores of the shards are computed with the same statistics, and can be compared.
+///
+/// Statistics are serialized in a compact binary format with `to_bytes` and `from_bytes`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ScoringStats {
+    num_docs: u64,
+    num_tokens: BTreeMap<Field, u64>,
+    doc_freqs: BTreeMap<Term, u64>,
+}
+
+impl ScoringStats {
+    /// Creates statistics for a collection of `num_docs` documents (including deleted documents).
+    pub fn new(num_docs: u64) -> ScoringStats {
+        ScoringStats {
+            num_docs,
+            ..ScoringStats::default()
+        }
+    }
+
+    /// Returns the number of documents (including deleted documents).
+    pub fn num_docs(&self) -> u64 {
+        self.num_docs
+    }
+
+    /// Returns the overall number of tokens of a field, if known.
+    pub fn num_tokens(&self, field: Field) -> Option<u64> {
+        self.num_tokens.get(&field).cloned()
+    }
+
+    /// Sets the overall number of tokens of a field.
+    pub fn set_num_tokens(&mut self, field: Field, num_tokens: u64) {
+        self.num_tokens.insert(field, num_tokens);
+    }
+
+    /// Returns the number of documents containing a term, if known.
+    pub fn doc_freq(&self, term: &Term) -> Option<u64> {
+        self.doc_freqs.get(term).cloned()
+    }
+
+    /// Sets the number of documents containing a term.
+    pub fn set_doc_freq(&mut self, term: Term, doc_freq: u64) {
+        self.doc_freqs.insert(term, doc_freq);
+    }
+
+    /// Adds the statistics of another collection of documents, e.g. another shard.
+    pub fn merge(&mut self, other: &ScoringStats) {
+        self.num_docs += other.num_docs;
+        for (field, num_tokens) in &other.num_tokens {
+            *self.num_tokens.entry(*field).or_insert(0) += num_tokens;
+        }
+        for (term, doc_freq) in &other.doc_freqs {
+            *self.doc_freqs.entry(term.clone()).or_insert(0) += doc_freq;
+        }
+    }
+
+    /// Serializes the statistics.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        // Writing to a `Vec` cannot fail.
+        self.serialize(&mut bytes).unwrap();
+        bytes
+    }
+
+    /// Deserializes statistics serialized with `to_bytes`.
+    pub fn from_bytes(mut bytes: &[u8]) -> crate::Result<ScoringStats> {
+        let scoring_stats = ScoringStats::deserialize(&mut bytes)?;
+        if !bytes.is_empty() {
+            return Err(crate::TantivyError::InvalidArgument(format!(
+                "{} unexpected trailing bytes after the scoring statistics",
+                bytes.len()
+            )));
+        }
+        Ok(scoring_stats)
+    }
+}
+
+impl BinarySerializable for ScoringStats {
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        VInt(self.num_docs).serialize(writer)?;
+        VInt(self.num_tokens.len() as u64).serialize(writer)?;
+        for (field, num_tokens) in &self.num_tokens {
+            field.serialize(writer)?;
+            VInt(*num_tokens).serialize(writer)?;
+        }
+        VInt(self.doc_freqs.len() as u64).serialize(writer)?;
+        for (term, doc_freq) in &self.doc_freqs {
+            let term_bytes = term.as_slice();
+            VInt(term_bytes.len() as u64).serialize(writer)?;
+            writer.write_all(term_bytes)?;
+            VInt(*doc_freq).serialize(writer)?;
+        }
+        Ok(())
+    }
+
+    fn deserialize<R: Read>(reader: &mut R) -> io::Result<ScoringStats> {
+        let num_docs = VInt::deserialize_u64(reader)?;
+        let mut num_tokens = BTreeMap::new();
+        for _ in 0..VInt::deserialize_u64(reader)? {
+            let field = Field::deserialize(reader)?;
+            num_tokens.insert(field, VInt::deserialize_u64(reader)?);
+        }
+        let mut doc_freqs = BTreeMap::new();
+        for _ in 0..VInt::deserialize_u64(reader)? {
+            let term_len = VInt::deserialize_u64(reader)? as usize;
+            // A term starts with the 4 bytes of its field.
+            if term_len < 4 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Term is too short to hold its field",
+                ));
+            }
+            let mut term_bytes = Vec::new();
+            reader
+                .by_ref()
+                .take(term_len as u64)
+                .read_to_end(&mut term_bytes)?;
+            if term_bytes.len() != term_len {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Truncated term",
+                ));
+            }
+            doc_freqs.insert(Term::wrap(term_bytes), VInt::deserialize_u64(reader)?);
+        }
+        Ok(ScoringStats {
+            num_docs,
+            num_tokens,
+            doc_freqs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ScoringStats;
+    use crate::collector::TopDocs;
+    use crate::query::QueryParser;
+    use crate::schema::{Field, Schema, TEXT};
+    use crate::{assert_nearly_equals, Index, Term};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_scoring_stats_serialization() -> crate::Result<()> {
+        let text_field = Field::from_field_id(0);
+        let mut scoring_stats = ScoringStats::new(12);
+        scoring_stats.set_num_tokens(text_field, 40);
+        scoring_stats.set_doc_freq(Term::from_field_text(text_field, "a"), 3);
+        let bytes = scoring_stats.to_bytes();
+        assert_eq!(ScoringStats::from_bytes(&bytes)?, scoring_stats);
+        assert!(ScoringStats::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_scoring_stats_across_shards() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        let shard_texts: [&[&str]; 2] = [&["a b", "b c", "c"], &["a", "a c d"]];
+        let shards = shard_texts
+            .iter()
+            .map(|texts| {
+                let index = Index::create_in_ram(schema.clone());
+                let mut index_writer = index.writer_for_tests()?;
+                for text in texts.iter() {
+                    index_writer.add_document(doc!(text_field => *text));
+                }
+                index_writer.commit()?;
+                Ok(index)
+            })
+            .collect::<crate::Result<Vec<Index>>>()?;
+        let query_parser = QueryParser::for_index(&shards[0], vec![text_field]);
+        let query = query_parser.parse_query("a c")?;
+        let searchers: Vec<_> = shards
+            .iter()
+            .map(|index| Ok(index.reader()?.searcher()))
+            .collect::<crate::Result<_>>()?;
+        let mut global_stats = ScoringStats::default();
+        for searcher in &searchers {
+            global_stats.merge(&searcher.scoring_stats(query.as_ref())?);
+        }
+        assert_eq!(global_stats.num_docs(), 5);
+        assert_eq!(global_stats.num_tokens(text_field), Some(9));
+        assert_eq!(
+            global_stats.doc_freq(&Term::from_field_text(text_field, "a")),
+            Some(3)
+        );
+        assert_eq!(
+            global_stats.doc_freq(&Term::from_field_text(text_field, "c")),
+            Some(3)
+        );
+        let global_stats = Arc::new(ScoringStats::from_bytes(&global_stats.to_bytes())?);
+
+        // The scores of the shards match the scores of a single index holding all of the documents.
+        let index = Index::create_in_ram(schema);
+        let mut index_writer = index.writer_for_tests()?;
+        for text in shard_texts.iter().flat_map(|texts| texts.iter()) {
+            index_writer.add_document(doc!(text_field => *text));
+        }
+        index_writer.commit()?;
+        let mut expected_scores: Vec<f32> = index
+            .reader()?
+            .searcher()
+            .search(query.as_ref(), &TopDocs::with_limit(10))?
+            .into_iter()
+            .map(|(score, _)| score)
+            .collect();
+        let mut scores: Vec<f32> = Vec::new();
+        for searcher in &searchers {
+            let searcher = searcher.with_scoring_stats(global_stats.clone());
+            for (score, _) in searcher.search(query.as_ref(), &TopDocs::with_limit(10))? {
+                scores.push(score);
+            }
+        }
+        expected_scores.sort_by(|left, right| left.partial_cmp(right).unwrap());
+        scores.sort_by(|left, right| left.partial_cmp(right).unwrap());
+        assert_eq!(scores.len(), expected_scores.len());
+        for (score, expected_score) in scores.iter().zip(&expected_scores) {
+            assert_nearly_equals!(*score, *expected_score);
+        }
+        Ok(())
+    }
+}
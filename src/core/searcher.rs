@@ -1,28 +1,114 @@
-use crate::collector::Collector;
+use crate::collector::{Collector, Rescore, Rescorer, TopDocs};
+use crate::common::numeric_trie::is_full_precision_term;
 use crate::core::Executor;
 
+use crate::core::global_ordinals::GlobalOrdinalsCache;
 use crate::core::SegmentReader;
-use crate::query::Query;
+use crate::core::{ExpansionBudget, ExpansionOverflow};
+use crate::core::{GlobalOrdinals, MultiTerms};
+use crate::docset::{DocSet, TERMINATED};
+use crate::fastfield::FastFieldReader;
+use crate::query::rewrite::{rewrite_query, try_rewrite_query};
+use crate::query::{DocFilter, FilteredWeight, Query, Scorer, Weight};
+use crate::schema::Cardinality;
 use crate::schema::Document;
+use crate::schema::Field;
+use crate::schema::FieldNormEncoding;
+use crate::schema::FieldType;
+use crate::schema::IndexRecordOption;
 use crate::schema::Schema;
 use crate::schema::Term;
-use crate::space_usage::SearcherSpaceUsage;
-use crate::store::StoreReader;
-use crate::DocAddress;
+use crate::space_usage::{CacheSpaceUsage, SearcherSpaceUsage};
+use crate::store::{DocStoreCache, DocStoreCacheInfo, StoreReader};
 use crate::Index;
+use crate::IndexSortByField;
+use crate::MemoryBudget;
+use crate::Order;
+use crate::ScoringStats;
+use crate::TantivyError;
+use crate::{DocAddress, DocId, Score, SegmentOrdinal};
 
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Instant;
 use std::{fmt, io};
 
+/// Statistics of an indexed field, aggregated over all of the segments of a `Searcher`.
+///
+/// Token-based statistics are only recorded for text fields.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FieldStats {
+    /// Sum over the segments of the number of terms in the segment term dictionary.
+    ///
+    /// A term present in several segments is counted once per segment.
+    /// For fields indexed at several precisions, the truncated prefixes
+    /// of the values are not counted.
+    pub num_terms: u64,
+    /// Number of alive documents with at least one token in the field,
+    /// or at least one value for the fields that are not text fields.
+    ///
+    /// `None` if the fieldnorms of the text field are disabled, as this number
+    /// is then unknown.
+    pub num_docs: Option<u64>,
+    /// Overall number of tokens in the field (including deleted documents).
+    pub total_num_tokens: u64,
+}
+
+/// Statistics of a term, aggregated over all of the segments of a `Searcher`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TermStats {
+    /// Number of documents containing the term (including deleted documents).
+    pub doc_freq: u64,
+    /// Overall number of occurrences of the term (including deleted documents).
+    ///
+    /// `None` if the field was not indexed with term frequencies.
+    pub total_term_freq: Option<u64>,
+}
+
+/// Budgets given to each query run by a `Searcher`.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct QueryBudgets {
+    /// Memory budget, in bytes, of each query.
+    pub memory_budget_per_query: Option<usize>,
+    /// Number of terms into which the multi-term queries of each query may expand.
+    pub expansion_budget_per_query: Option<(usize, ExpansionOverflow)>,
+}
+
+impl QueryBudgets {
+    fn memory_budget(&self) -> MemoryBudget {
+        self.memory_budget_per_query
+            .map(MemoryBudget::with_limit)
+            .unwrap_or_else(MemoryBudget::unlimited)
+    }
+
+    fn expansion_budget(&self) -> ExpansionBudget {
+        self.expansion_budget_per_query
+            .map(|(max_num_terms, overflow)| ExpansionBudget::with_limit(max_num_terms, overflow))
+            .unwrap_or_else(ExpansionBudget::unlimited)
+    }
+}
+
+struct InnerSearcher {
+    schema: Schema,
+    index: Index,
+    segment_readers: Vec<SegmentReader>,
+    store_readers: Vec<StoreReader>,
+    doc_store_cache: DocStoreCache,
+    query_budgets: QueryBudgets,
+    global_ordinals_cache: Arc<GlobalOrdinalsCache>,
+    doc_filter: Option<Arc<dyn DocFilter>>,
+}
+
 /// Holds a list of `SegmentReader`s ready for search.
 ///
 /// It guarantees that the `Segment` will not be removed before
 /// the destruction of the `Searcher`.
 ///
 pub struct Searcher {
-    schema: Schema,
-    index: Index,
-    segment_readers: Vec<SegmentReader>,
-    store_readers: Vec<StoreReader>,
+    inner: Arc<InnerSearcher>,
+    memory_budget: MemoryBudget,
+    expansion_budget: ExpansionBudget,
+    scoring_stats: Option<Arc<ScoringStats>>,
 }
 
 impl Searcher {
@@ -31,22 +117,178 @@ impl Searcher {
         schema: Schema,
         index: Index,
         segment_readers: Vec<SegmentReader>,
+        doc_store_cache: DocStoreCache,
+        query_budgets: QueryBudgets,
+        global_ordinals_cache: Arc<GlobalOrdinalsCache>,
+        doc_filter: Option<Arc<dyn DocFilter>>,
     ) -> io::Result<Searcher> {
         let store_readers: Vec<StoreReader> = segment_readers
             .iter()
-            .map(SegmentReader::get_store_reader)
+            .map(|segment_reader| segment_reader.get_store_reader_with_cache(&doc_store_cache))
             .collect::<io::Result<Vec<_>>>()?;
         Ok(Searcher {
-            schema,
-            index,
-            segment_readers,
-            store_readers,
+            inner: Arc::new(InnerSearcher {
+                schema,
+                index,
+                segment_readers,
+                store_readers,
+                doc_store_cache,
+                query_budgets,
+                global_ordinals_cache,
+                doc_filter,
+            }),
+            memory_budget: MemoryBudget::unlimited(),
+            expansion_budget: ExpansionBudget::unlimited(),
+            scoring_stats: None,
         })
     }
 
+    // Returns a searcher on the same segments, with fresh budgets
+    // for the execution of a single query.
+    pub(crate) fn for_query(&self) -> Searcher {
+        Searcher {
+            inner: self.inner.clone(),
+            memory_budget: self.inner.query_budgets.memory_budget(),
+            expansion_budget: self.inner.query_budgets.expansion_budget(),
+            scoring_stats: self.scoring_stats.clone(),
+        }
+    }
+
+    /// Returns a searcher on the same segments, scoring documents with the given statistics
+    /// instead of the statistics of its own segments.
+    ///
+    /// Fields and terms missing from the statistics are scored with the statistics
+    /// of the segments of the searcher.
+    ///
+    /// See [`ScoringStats`](./struct.ScoringStats.html).
+    pub fn with_scoring_stats(&self, scoring_stats: Arc<ScoringStats>) -> Searcher {
+        Searcher {
+            inner: self.inner.clone(),
+            memory_budget: MemoryBudget::unlimited(),
+            expansion_budget: ExpansionBudget::unlimited(),
+            scoring_stats: Some(scoring_stats),
+        }
+    }
+
+    /// Returns the statistics required to score the given query over the documents of
+    /// the searcher: the number of documents, the number of tokens of the fields of the
+    /// query terms, and the document frequencies of the query terms.
+    ///
+    /// Multi-term queries (e.g. fuzzy or regex queries) are rewritten first, so that
+    /// the statistics cover the terms they match.
+    pub fn scoring_stats(&self, query: &dyn Query) -> crate::Result<ScoringStats> {
+        let rewritten_query = rewrite_query(query, self, true)?;
+        let mut terms = BTreeMap::new();
+        rewritten_query.query_terms(&mut terms);
+        let mut scoring_stats = ScoringStats::new(self.max_doc());
+        for term in terms.into_iter().map(|(term, _)| term) {
+            let field = term.field();
+            if scoring_stats.num_tokens(field).is_none() {
+                scoring_stats.set_num_tokens(field, self.num_tokens(field)?);
+            }
+            let doc_freq = self.doc_freq(&term)?;
+            scoring_stats.set_doc_freq(term, doc_freq);
+        }
+        Ok(scoring_stats)
+    }
+
+    // Number of documents of the segments, including deleted documents.
+    fn max_doc(&self) -> u64 {
+        self.inner
+            .segment_readers
+            .iter()
+            .map(|segment_reader| u64::from(segment_reader.max_doc()))
+            .sum()
+    }
+
+    fn num_tokens(&self, field: Field) -> crate::Result<u64> {
+        let mut num_tokens = 0u64;
+        for segment_reader in &self.inner.segment_readers {
+            num_tokens += segment_reader.inverted_index(field)?.total_num_tokens();
+        }
+        Ok(num_tokens)
+    }
+
+    /// Returns the number of documents and the overall number of tokens of a field
+    /// used to score documents, taken from the scoring statistics if they are supplied.
+    pub(crate) fn scoring_field_stats(&self, field: Field) -> crate::Result<(u64, u64)> {
+        if let Some(scoring_stats) = self.scoring_stats.as_ref() {
+            if let Some(num_tokens) = scoring_stats.num_tokens(field) {
+                return Ok((scoring_stats.num_docs(), num_tokens));
+            }
+        }
+        Ok((self.max_doc(), self.num_tokens(field)?))
+    }
+
+    /// Returns the document frequency of a term used to score documents, taken from
+    /// the scoring statistics if they are supplied.
+    pub(crate) fn scoring_doc_freq(&self, term: &Term) -> crate::Result<u64> {
+        if let Some(doc_freq) = self
+            .scoring_stats
+            .as_ref()
+            .and_then(|scoring_stats| scoring_stats.doc_freq(term))
+        {
+            return Ok(doc_freq);
+        }
+        self.doc_freq(term)
+    }
+
+    /// Returns the memory budget to which queries charge their transient allocations.
+    ///
+    /// `search(...)` and `docs_matching(...)` run each query with a budget of its own, as
+    /// configured by `IndexReaderBuilder::memory_budget_per_query`.
+    /// Outside of these methods, the budget is unlimited.
+    pub fn memory_budget(&self) -> &MemoryBudget {
+        &self.memory_budget
+    }
+
+    /// Returns the budget of the terms into which multi-term queries expand.
+    ///
+    /// `search(...)` and `docs_matching(...)` run each query with a budget of its own, as
+    /// configured by `IndexReaderBuilder::expansion_budget_per_query`.
+    /// Outside of these methods, the budget is unlimited.
+    pub fn expansion_budget(&self) -> &ExpansionBudget {
+        &self.expansion_budget
+    }
+
+    /// Returns the filter installed on the `IndexReader` of the searcher, if any.
+    pub(crate) fn doc_filter(&self) -> Option<&Arc<dyn DocFilter>> {
+        self.inner.doc_filter.as_ref()
+    }
+
+    /// Restricts the documents matched by `weight` to the documents allowed by
+    /// the filter installed on the `IndexReader`, if any.
+    ///
+    /// See [`IndexReaderBuilder::doc_filter`](./struct.IndexReaderBuilder.html#method.doc_filter).
+    pub(crate) fn filter_weight(&self, weight: Box<dyn Weight>) -> Box<dyn Weight> {
+        if let Some(doc_filter) = self.doc_filter() {
+            Box::new(FilteredWeight::new(weight, doc_filter.clone()))
+        } else {
+            weight
+        }
+    }
+
     /// Returns the `Index` associated to the `Searcher`
     pub fn index(&self) -> &Index {
-        &self.index
+        &self.inner.index
+    }
+
+    /// Returns the field and order all of the documents of the searcher are sorted by
+    /// in each segment, if the index is sorted.
+    ///
+    /// Returns `None` if one of the segments does not record that it is sorted
+    /// like the index, e.g. because it was written by a former version of tantivy.
+    pub fn index_sort(&self) -> Option<&IndexSortByField> {
+        let index_sort = self.index().settings().sort_by_field.as_ref()?;
+        if self
+            .segment_readers()
+            .iter()
+            .all(|segment_reader| segment_reader.index_sort() == Some(index_sort))
+        {
+            Some(index_sort)
+        } else {
+            None
+        }
     }
 
     /// Fetches a document from tantivy's store given a `DocAddress`.
@@ -54,18 +296,34 @@ impl Searcher {
     /// The searcher uses the segment ordinal to route the
     /// the request to the right `Segment`.
     pub fn doc(&self, doc_address: DocAddress) -> crate::Result<Document> {
-        let store_reader = &self.store_readers[doc_address.segment_ord as usize];
+        let store_reader = &self.inner.store_readers[doc_address.segment_ord as usize];
         store_reader.get(doc_address.doc_id)
     }
 
+    /// Fetches the values of the given `fields` of a document.
+    ///
+    /// See [`StoreReader::get_fields`](./store/struct.StoreReader.html#method.get_fields).
+    pub fn doc_fields(&self, doc_address: DocAddress, fields: &[Field]) -> crate::Result<Document> {
+        let store_reader = &self.inner.store_readers[doc_address.segment_ord as usize];
+        store_reader.get_fields(doc_address.doc_id, fields)
+    }
+
+    /// Returns the hit/miss statistics of the doc store block cache.
+    ///
+    /// The cache is shared by all of the searchers of an `IndexReader`.
+    pub fn doc_store_cache_info(&self) -> DocStoreCacheInfo {
+        self.inner.doc_store_cache.info()
+    }
+
     /// Access the schema associated to the index of this searcher.
     pub fn schema(&self) -> &Schema {
-        &self.schema
+        &self.inner.schema
     }
 
     /// Returns the overall number of documents in the index.
     pub fn num_docs(&self) -> u64 {
-        self.segment_readers
+        self.inner
+            .segment_readers
             .iter()
             .map(|segment_reader| u64::from(segment_reader.num_docs()))
             .sum::<u64>()
@@ -73,9 +331,9 @@ impl Searcher {
 
     /// Return the overall number of documents containing
     /// the given term.
-    pub fn doc_freq(&self, term: &Term) -> crate::Result<u64> {
+    pub fn doc_freq<B: AsRef<[u8]>>(&self, term: &Term<B>) -> crate::Result<u64> {
         let mut total_doc_freq = 0;
-        for segment_reader in &self.segment_readers {
+        for segment_reader in &self.inner.segment_readers {
             let inverted_index = segment_reader.inverted_index(term.field())?;
             let doc_freq = inverted_index.doc_freq(term)?;
             total_doc_freq += u64::from(doc_freq);
@@ -83,21 +341,319 @@ impl Searcher {
         Ok(total_doc_freq)
     }
 
+    /// Returns the statistics of an indexed field.
+    ///
+    /// Returns an error if the field is not indexed.
+    pub fn field_stats(&self, field: Field) -> crate::Result<FieldStats> {
+        let field_entry = self.inner.schema.get_field_entry(field);
+        if !field_entry.is_indexed() {
+            return Err(TantivyError::SchemaError(format!(
+                "Field {:?} is not indexed",
+                field_entry.name()
+            )));
+        }
+        // Without fieldnorms, the documents of a text field with no token cannot be told apart.
+        let is_num_docs_known =
+            field_entry.field_type().get_fieldnorm_encoding() != Some(FieldNormEncoding::Disabled);
+        let has_precision_step = field_entry.field_type().get_precision_step().is_some();
+        let mut field_stats = FieldStats::default();
+        let mut num_docs = 0u64;
+        for segment_reader in &self.inner.segment_readers {
+            let inverted_index = segment_reader.inverted_index(field)?;
+            field_stats.num_terms += if has_precision_step {
+                // The truncated prefixes of the values are not counted.
+                let mut term_stream = inverted_index.terms().stream()?;
+                let mut num_terms = 0u64;
+                while term_stream.advance() {
+                    if is_full_precision_term(term_stream.key()) {
+                        num_terms += 1;
+                    }
+                }
+                num_terms
+            } else {
+                inverted_index.terms().num_terms() as u64
+            };
+            field_stats.total_num_tokens += inverted_index.total_num_tokens();
+            if is_num_docs_known {
+                num_docs += u64::from(alive_field_doc_count(segment_reader, field)?);
+            }
+        }
+        if is_num_docs_known {
+            field_stats.num_docs = Some(num_docs);
+        }
+        Ok(field_stats)
+    }
+
+    /// Returns the terms of an indexed field, merged over all of the segments.
+    ///
+    /// Terms are streamed in sorted order, each of them once, together with its
+    /// overall document frequency. This is handy to implement features relying on
+    /// the term dictionary, e.g. spelling suggestions or the export of term statistics.
+    ///
+    /// Returns an error if the field is not indexed.
+    pub fn terms(&self, field: Field) -> crate::Result<MultiTerms> {
+        let field_entry = self.inner.schema.get_field_entry(field);
+        if !field_entry.is_indexed() {
+            return Err(TantivyError::SchemaError(format!(
+                "Field {:?} is not indexed",
+                field_entry.name()
+            )));
+        }
+        let inverted_indexes = self
+            .inner
+            .segment_readers
+            .iter()
+            .map(|segment_reader| segment_reader.inverted_index(field))
+            .collect::<crate::Result<Vec<_>>>()?;
+        let full_precision_only = field_entry.field_type().get_precision_step().is_some();
+        Ok(MultiTerms::new(inverted_indexes, full_precision_only))
+    }
+
+    /// Returns the global ordinals of a facet field.
+    ///
+    /// Global ordinals are built on the first call, and shared by all of the searchers
+    /// of the same reload of the `IndexReader`.
+    ///
+    /// Returns an error if the field is not an indexed facet field, as facet fields are
+    /// the only fields whose fast field stores term ordinals.
+    pub fn global_ordinals(&self, field: Field) -> crate::Result<Arc<GlobalOrdinals>> {
+        let field_entry = self.inner.schema.get_field_entry(field);
+        if !matches!(field_entry.field_type(), FieldType::HierarchicalFacet(_)) {
+            return Err(TantivyError::SchemaError(format!(
+                "Global ordinals are only available for facet fields, {:?} is not one",
+                field_entry.name()
+            )));
+        }
+        self.inner.global_ordinals_cache.get_or_build(field, || {
+            let terms = self.terms(field)?;
+            Ok(GlobalOrdinals::build(&terms)?)
+        })
+    }
+
+    /// Returns the statistics of a term.
+    ///
+    /// Computing the total term frequency requires to decode the term
+    /// frequencies of the posting lists.
+    pub fn term_stats<B: AsRef<[u8]>>(&self, term: &Term<B>) -> crate::Result<TermStats> {
+        let has_freqs = self
+            .inner
+            .schema
+            .get_field_entry(term.field())
+            .field_type()
+            .get_index_record_option()
+            .map(|record_option| record_option.has_freq())
+            .unwrap_or(false);
+        let mut doc_freq = 0u64;
+        let mut total_term_freq = 0u64;
+        for segment_reader in &self.inner.segment_readers {
+            let inverted_index = segment_reader.inverted_index(term.field())?;
+            let record_option = if has_freqs {
+                IndexRecordOption::WithFreqs
+            } else {
+                IndexRecordOption::Basic
+            };
+            if let Some(mut block_postings) =
+                inverted_index.read_block_postings(term, record_option)?
+            {
+                doc_freq += u64::from(block_postings.doc_freq());
+                if has_freqs {
+                    while !block_postings.docs().is_empty() {
+                        total_term_freq += block_postings
+                            .freqs()
+                            .iter()
+                            .map(|&term_freq| u64::from(term_freq))
+                            .sum::<u64>();
+                        block_postings.advance();
+                    }
+                }
+            }
+        }
+        Ok(TermStats {
+            doc_freq,
+            total_term_freq: if has_freqs {
+                Some(total_term_freq)
+            } else {
+                None
+            },
+        })
+    }
+
+    /// Returns the address of the first alive document containing the given key.
+    ///
+    /// This is typically used to look up a document by its primary key, if the
+    /// index has one and documents are added via `IndexWriter::upsert`:
+    /// in that case, there is at most one alive document for a given key.
+    ///
+    /// The lookup relies on the term dictionary of each segment, and
+    /// does not require any scoring or collection.
+    ///
+    /// If the field of the key is not indexed, but is a single-valued numeric fast field,
+    /// the key is looked up in the fast field instead. This makes key-value style
+    /// retrieval possible in indexes used as pure document stores. The lookup is a
+    /// binary search if the index is sorted by this field, and a scan of the segments
+    /// whose range of values contains the key otherwise.
+    ///
+    /// If the field has a lookup index (see
+    /// [`IntOptions::set_lookup_index`](./schema/struct.IntOptions.html#method.set_lookup_index)),
+    /// the key is looked up in the map of each segment.
+    pub fn doc_by_key(&self, key: &Term) -> crate::Result<Option<DocAddress>> {
+        let field_entry = self.schema().get_field_entry(key.field());
+        if field_entry.field_type().has_lookup_index() && key.value_bytes().len() == 8 {
+            return self.doc_by_lookup_key(key);
+        }
+        if !field_entry.is_indexed() {
+            return self.doc_by_fast_field_key(key);
+        }
+        for (segment_ord, segment_reader) in self.inner.segment_readers.iter().enumerate() {
+            let inverted_index = segment_reader.inverted_index(key.field())?;
+            if let Some(mut postings) =
+                inverted_index.read_postings(key, IndexRecordOption::Basic)?
+            {
+                let mut doc = postings.doc();
+                while doc != TERMINATED {
+                    if !segment_reader.is_deleted(doc) {
+                        return Ok(Some(DocAddress::new(segment_ord as SegmentOrdinal, doc)));
+                    }
+                    doc = postings.advance();
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn doc_by_lookup_key(&self, key: &Term) -> crate::Result<Option<DocAddress>> {
+        let mut key_bytes = [0u8; 8];
+        key_bytes.copy_from_slice(key.value_bytes());
+        let key_value = u64::from_be_bytes(key_bytes);
+        for (segment_ord, segment_reader) in self.inner.segment_readers.iter().enumerate() {
+            let lookup = segment_reader.fast_field_lookup(key.field())?;
+            if let Some(&doc) = lookup
+                .docs(key_value)
+                .iter()
+                .find(|&&doc| !segment_reader.is_deleted(doc))
+            {
+                return Ok(Some(DocAddress::new(segment_ord as SegmentOrdinal, doc)));
+            }
+        }
+        Ok(None)
+    }
+
+    fn doc_by_fast_field_key(&self, key: &Term) -> crate::Result<Option<DocAddress>> {
+        let field = key.field();
+        let field_entry = self.schema().get_field_entry(field);
+        let is_single_valued = match field_entry.field_type() {
+            FieldType::U64(options)
+            | FieldType::I64(options)
+            | FieldType::F64(options)
+            | FieldType::Date(options) => {
+                options.get_fastfield_cardinality() == Some(Cardinality::SingleValue)
+            }
+            _ => false,
+        };
+        if !is_single_valued || key.value_bytes().len() != 8 {
+            return Err(TantivyError::SchemaError(format!(
+                "Cannot look up a key in the field {:?}: it needs to be indexed, or to be a \
+                 single-valued numeric fast field",
+                field_entry.name()
+            )));
+        }
+        let mut key_bytes = [0u8; 8];
+        key_bytes.copy_from_slice(key.value_bytes());
+        // Numeric terms and fast fields share the same order preserving `u64` encoding.
+        let key_value = u64::from_be_bytes(key_bytes);
+        for (segment_ord, segment_reader) in self.inner.segment_readers.iter().enumerate() {
+            let max_doc = segment_reader.max_doc();
+            if max_doc == 0 {
+                continue;
+            }
+            let sort_order = segment_reader
+                .index_sort()
+                .filter(|index_sort| index_sort.field == field_entry.name())
+                .map(|index_sort| index_sort.order.clone());
+            let fast_field_reader = segment_reader.fast_fields().u64_lenient(field)?;
+            if key_value < fast_field_reader.min_value()
+                || key_value > fast_field_reader.max_value()
+            {
+                continue;
+            }
+            let first_candidate = match sort_order {
+                Some(Order::Asc) => {
+                    partition_point(max_doc, |doc| fast_field_reader.get(doc) < key_value)
+                }
+                Some(Order::Desc) => {
+                    partition_point(max_doc, |doc| fast_field_reader.get(doc) > key_value)
+                }
+                None => 0,
+            };
+            for doc in first_candidate..max_doc {
+                if fast_field_reader.get(doc) == key_value {
+                    if !segment_reader.is_deleted(doc) {
+                        return Ok(Some(DocAddress::new(segment_ord as SegmentOrdinal, doc)));
+                    }
+                } else if sort_order.is_some() {
+                    // In a sorted segment, the documents with the key are contiguous.
+                    break;
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns an iterator over the addresses of all of the alive documents
+    /// matching the query.
+    ///
+    /// Contrary to `search(...)`, matches are not collected: segments are
+    /// walked lazily, one after the other, in a constant amount of memory.
+    /// This is useful to export all of the matching documents, rather than the top-k.
+    ///
+    /// Scoring is disabled. Documents are returned in `DocAddress` order.
+    /// If a segment cannot be read, the error is returned and the iteration stops.
+    pub fn docs_matching<'a>(
+        &'a self,
+        query: &dyn Query,
+    ) -> crate::Result<impl Iterator<Item = crate::Result<DocAddress>> + 'a> {
+        let searcher = self.for_query();
+        let rewritten_query_opt = try_rewrite_query(query, &searcher, false)?;
+        let query: &dyn Query = rewritten_query_opt.as_deref().unwrap_or(query);
+        let weight = self.filter_weight(query.weight(&searcher, false)?);
+        Ok(DocsMatching {
+            segment_readers: &self.inner.segment_readers,
+            weight,
+            segment_ord: 0,
+            scorer: None,
+        })
+    }
+
+    /// Same as [`docs_matching(...)`](#method.docs_matching), but also fetches
+    /// the stored documents.
+    pub fn stored_docs_matching<'a>(
+        &'a self,
+        query: &dyn Query,
+    ) -> crate::Result<impl Iterator<Item = crate::Result<(DocAddress, Document)>> + 'a> {
+        let doc_addresses = self.docs_matching(query)?;
+        Ok(doc_addresses.map(move |doc_address_res| {
+            let doc_address = doc_address_res?;
+            let doc = self.doc(doc_address)?;
+            Ok((doc_address, doc))
+        }))
+    }
+
     /// Return the list of segment readers
     pub fn segment_readers(&self) -> &[SegmentReader] {
-        &self.segment_readers
+        &self.inner.segment_readers
     }
 
     /// Returns the segment_reader associated with the given segment_ordinal
     pub fn segment_reader(&self, segment_ord: u32) -> &SegmentReader {
-        &self.segment_readers[segment_ord as usize]
+        &self.inner.segment_readers[segment_ord as usize]
     }
 
     /// Runs a query on the segment readers wrapped by the searcher.
     ///
     /// Search works as follows :
     ///
-    ///  First the weight object associated to the query is created.
+    ///  First the query is rewritten (see [`Query::rewrite`](../query/trait.Query.html#method.rewrite)),
+    ///  and the weight object associated to the rewritten query is created.
     ///
     ///  Then, the query loops over the segments and for each segment :
     ///  - setup the collector and informs it that the segment being processed has changed.
@@ -112,7 +668,7 @@ impl Searcher {
         query: &dyn Query,
         collector: &C,
     ) -> crate::Result<C::Fruit> {
-        let executor = self.index.search_executor();
+        let executor = self.inner.index.search_executor();
         self.search_with_executor(query, collector, executor)
     }
 
@@ -134,31 +690,164 @@ impl Searcher {
         collector: &C,
         executor: &Executor,
     ) -> crate::Result<C::Fruit> {
+        enter_span!(
+            "search",
+            query = ?query,
+            num_segments = self.segment_readers().len()
+        );
+        let start = Instant::now();
         let scoring_enabled = collector.requires_scoring();
-        let weight = query.weight(self, scoring_enabled)?;
+        let searcher = self.for_query();
+        let rewritten_query_opt = try_rewrite_query(query, &searcher, scoring_enabled)?;
+        let query: &dyn Query = rewritten_query_opt.as_deref().unwrap_or(query);
+        let weight = self.filter_weight(query.weight(&searcher, scoring_enabled)?);
         let segment_readers = self.segment_readers();
         let fruits = executor.map(
             |(segment_ord, segment_reader)| {
+                searcher
+                    .memory_budget()
+                    .allocate(collector.segment_memory_usage(segment_reader))?;
                 collector.collect_segment(weight.as_ref(), segment_ord as u32, segment_reader)
             },
             segment_readers.iter().enumerate(),
         )?;
-        collector.merge_fruits(fruits)
+        let fruit = collector.reduce_fruits(fruits)?;
+        self.inner
+            .index
+            .metrics()
+            .record_query_latency(start.elapsed());
+        Ok(fruit)
+    }
+
+    /// Returns the `limit` best documents matching `query`, after rescoring
+    /// the best hits with `rescore`.
+    ///
+    /// The top `max(limit, window_size)` documents are collected with `TopDocs`,
+    /// and the `window_size` best of them are rescored.
+    /// See [`Rescore`](./collector/struct.Rescore.html) for more details.
+    pub fn search_with_rescore<R: Rescorer>(
+        &self,
+        query: &dyn Query,
+        limit: usize,
+        rescore: &Rescore<R>,
+    ) -> crate::Result<Vec<(Score, DocAddress)>> {
+        let num_hits = limit.max(rescore.window_size());
+        if num_hits == 0 {
+            return Ok(Vec::new());
+        }
+        let hits = self.search(query, &TopDocs::with_limit(num_hits))?;
+        let mut rescored_hits = rescore.rescore(self, hits)?;
+        rescored_hits.truncate(limit);
+        Ok(rescored_hits)
     }
 
     /// Summarize total space usage of this searcher.
+    ///
+    /// This includes the size of the files of each segment, broken down per component
+    /// and per field, as well as the heap usage of the caches.
     pub fn space_usage(&self) -> io::Result<SearcherSpaceUsage> {
-        let mut space_usage = SearcherSpaceUsage::new();
-        for segment_reader in &self.segment_readers {
+        let caches = CacheSpaceUsage::new(
+            self.inner.doc_store_cache.num_bytes(),
+            self.inner.global_ordinals_cache.num_bytes(),
+        );
+        let mut space_usage = SearcherSpaceUsage::new(caches);
+        for segment_reader in &self.inner.segment_readers {
             space_usage.add_segment(segment_reader.space_usage()?);
         }
         Ok(space_usage)
     }
 }
 
+/// Lazy iterator over the documents matching a query, segment after segment.
+struct DocsMatching<'a> {
+    segment_readers: &'a [SegmentReader],
+    weight: Box<dyn Weight>,
+    segment_ord: usize,
+    // Scorer of the current segment, positioned on the next document to return.
+    scorer: Option<Box<dyn Scorer>>,
+}
+
+impl<'a> Iterator for DocsMatching<'a> {
+    type Item = crate::Result<DocAddress>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(scorer) = self.scorer.as_mut() {
+                let segment_reader = &self.segment_readers[self.segment_ord];
+                loop {
+                    let doc = scorer.doc();
+                    if doc == TERMINATED {
+                        break;
+                    }
+                    scorer.advance();
+                    if !segment_reader.is_deleted(doc) {
+                        return Some(Ok(DocAddress::new(self.segment_ord as SegmentOrdinal, doc)));
+                    }
+                }
+                self.scorer = None;
+                self.segment_ord += 1;
+            }
+            let segment_reader = self.segment_readers.get(self.segment_ord)?;
+            match self.weight.scorer(segment_reader, 1.0) {
+                Ok(scorer) => {
+                    self.scorer = Some(scorer);
+                }
+                Err(err) => {
+                    self.segment_ord = self.segment_readers.len();
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+/// Returns the number of alive documents of the segment containing the field.
+///
+/// Without deletes, it is the cached `SegmentReader::field_doc_count`.
+/// Otherwise, the documents containing the field are computed the same way,
+/// from the fieldnorms of text fields or the postings of the other fields,
+/// and the deleted documents are filtered out.
+fn alive_field_doc_count(segment_reader: &SegmentReader, field: Field) -> crate::Result<u32> {
+    if !segment_reader.has_deletes() {
+        return segment_reader.field_doc_count(field);
+    }
+    let max_doc = segment_reader.max_doc();
+    let field_type = segment_reader.schema().get_field_entry(field).field_type();
+    let num_docs = if let FieldType::Str(_) = field_type {
+        let fieldnorm_reader = segment_reader.get_fieldnorms_reader(field)?;
+        (0..max_doc)
+            .filter(|&doc| {
+                !segment_reader.is_deleted(doc) && fieldnorm_reader.fieldnorm_id(doc) != 0
+            })
+            .count()
+    } else {
+        let field_doc_bitset = segment_reader.field_doc_bitset(field)?;
+        (0..max_doc)
+            .filter(|&doc| !segment_reader.is_deleted(doc) && field_doc_bitset.contains(doc))
+            .count()
+    };
+    Ok(num_docs as u32)
+}
+
+/// Returns the first doc id of `0..max_doc` for which `pred` is false,
+/// assuming `pred` is true for all of the doc ids before it, and false after.
+fn partition_point<P: Fn(DocId) -> bool>(max_doc: DocId, pred: P) -> DocId {
+    let (mut start, mut end) = (0, max_doc);
+    while start < end {
+        let mid = start + (end - start) / 2;
+        if pred(mid) {
+            start = mid + 1;
+        } else {
+            end = mid;
+        }
+    }
+    start
+}
+
 impl fmt::Debug for Searcher {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let segment_ids = self
+            .inner
             .segment_readers
             .iter()
             .map(SegmentReader::segment_id)
@@ -166,3 +855,296 @@ impl fmt::Debug for Searcher {
         write!(f, "Searcher({:?})", segment_ids)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::collector::{Count, TopDocs};
+    use crate::error::ErrorCode;
+    use crate::query::{RegexQuery, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, FAST, INDEXED, STORED, STRING, TEXT};
+    use crate::ExpansionOverflow;
+    use crate::{DocAddress, FieldStats, Index, TantivyError, Term, TermStats};
+    use crate::{IndexSettings, IndexSortByField, Order};
+
+    #[test]
+    fn test_docs_matching() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let id_field = schema_builder.add_u64_field("id", INDEXED | STORED);
+        let tag_field = schema_builder.add_text_field("tag", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for id in 0u64..10u64 {
+            let tag = if id % 2 == 0 { "even" } else { "odd" };
+            index_writer.add_document(doc!(id_field => id, tag_field => tag));
+        }
+        index_writer.commit()?;
+        index_writer.add_document(doc!(id_field => 10u64, tag_field => "even"));
+        index_writer.delete_term(Term::from_field_u64(id_field, 0u64));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query = TermQuery::new(
+            Term::from_field_text(tag_field, "even"),
+            IndexRecordOption::Basic,
+        );
+        let doc_addresses = searcher
+            .docs_matching(&query)?
+            .collect::<crate::Result<Vec<DocAddress>>>()?;
+        assert_eq!(doc_addresses.len(), 5);
+        let mut ids = searcher
+            .stored_docs_matching(&query)?
+            .map(|doc_res| {
+                let (_, doc) = doc_res?;
+                Ok(doc.get_first(id_field).unwrap().u64_value().unwrap())
+            })
+            .collect::<crate::Result<Vec<u64>>>()?;
+        ids.sort_unstable();
+        assert_eq!(ids, vec![2, 4, 6, 8, 10]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_field_stats_numeric_field() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let id_field = schema_builder.add_u64_field("id", INDEXED);
+        let tag_field = schema_builder.add_text_field("tag", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(id_field => 1u64, tag_field => "a"));
+        index_writer.add_document(doc!(id_field => 2u64, id_field => 3u64));
+        index_writer.add_document(doc!(tag_field => "b"));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(id_field => 4u64, tag_field => "c"));
+        index_writer.add_document(doc!(id_field => 5u64, tag_field => "d"));
+        index_writer.commit()?;
+        index_writer.delete_term(Term::from_field_text(tag_field, "d"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        // The documents of numeric fields are counted from their postings,
+        // as they do not have any fieldnorm.
+        let id_stats = searcher.field_stats(id_field)?;
+        assert_eq!(id_stats.num_terms, 5);
+        assert_eq!(id_stats.num_docs, Some(3));
+        Ok(())
+    }
+
+    #[test]
+    fn test_field_and_term_stats() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let tag_field = schema_builder.add_text_field("tag", STRING);
+        let stored_field = schema_builder.add_text_field("stored", STORED);
+        let no_fieldnorms_field =
+            schema_builder.add_text_field("no_fieldnorms", TEXT.set_fieldnorms(false));
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(
+            text_field => "a b a",
+            tag_field => "x",
+            no_fieldnorms_field => "a b"
+        ));
+        index_writer.add_document(doc!(text_field => "a c"));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text_field => "c", tag_field => "x"));
+        index_writer.add_document(doc!(tag_field => "y"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(
+            searcher.field_stats(text_field)?,
+            FieldStats {
+                num_terms: 4,
+                num_docs: Some(3),
+                total_num_tokens: 6,
+            }
+        );
+        assert_eq!(
+            searcher.term_stats(&Term::from_field_text(text_field, "a"))?,
+            TermStats {
+                doc_freq: 2,
+                total_term_freq: Some(3),
+            }
+        );
+        assert_eq!(
+            searcher.term_stats(&Term::from_field_text(tag_field, "x"))?,
+            TermStats {
+                doc_freq: 2,
+                total_term_freq: None,
+            }
+        );
+        assert!(searcher.field_stats(stored_field).is_err());
+        // Without fieldnorms, the number of documents with a token in the field is unknown.
+        let no_fieldnorms_stats = searcher.field_stats(no_fieldnorms_field)?;
+        assert_eq!(no_fieldnorms_stats.num_terms, 2);
+        assert_eq!(no_fieldnorms_stats.num_docs, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_doc_store_cache_shared_across_searchers() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field => "hello"));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text_field => "happy tax payer"));
+        index_writer.commit()?;
+        let reader = index
+            .reader_builder()
+            .num_searchers(2)
+            .doc_store_cache_num_blocks(10)
+            .try_into()?;
+        let searcher = reader.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+        searcher.doc(DocAddress::new(0, 0))?;
+        searcher.doc(DocAddress::new(1, 0))?;
+        let cache_info = reader.doc_store_cache_info();
+        assert_eq!(cache_info.miss, 2);
+        assert_eq!(cache_info.num_blocks, 2);
+        assert_eq!(cache_info.capacity, 10);
+        // The cache is shared with the other searchers of the reader.
+        let other_searcher = reader.searcher();
+        other_searcher.doc(DocAddress::new(0, 0))?;
+        let cache_info = other_searcher.doc_store_cache_info();
+        assert_eq!(cache_info.hit, 1);
+        assert_eq!(cache_info.miss, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_memory_budget_per_query() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let tag_field = schema_builder.add_text_field("tag", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for i in 0..100 {
+            let tag = if i % 2 == 0 { "even" } else { "odd" };
+            index_writer.add_document(doc!(tag_field => tag));
+        }
+        index_writer.commit()?;
+        let reader = index
+            .reader_builder()
+            .memory_budget_per_query(10_000)
+            .try_into()?;
+        let searcher = reader.searcher();
+        assert_eq!(searcher.memory_budget().limit(), None);
+        let query = TermQuery::new(
+            Term::from_field_text(tag_field, "even"),
+            IndexRecordOption::Basic,
+        );
+        // Each query gets its own budget.
+        for _ in 0..3 {
+            assert_eq!(searcher.search(&query, &TopDocs::with_limit(10))?.len(), 10);
+        }
+        assert!(matches!(
+            searcher.search(&query, &TopDocs::with_limit(10_000)),
+            Err(TantivyError::MemoryBudgetExceeded(10_000))
+        ));
+        let regex_query = RegexQuery::from_pattern("e.*", tag_field)?;
+        assert_eq!(searcher.search(&regex_query, &Count)?, 50);
+        assert_eq!(searcher.docs_matching(&regex_query)?.count(), 50);
+
+        // Without any budget, queries are not limited.
+        let searcher = index.reader()?.searcher();
+        assert_eq!(
+            searcher.search(&query, &TopDocs::with_limit(10_000))?.len(),
+            50
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_expansion_budget_per_query() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let tag_field = schema_builder.add_text_field("tag", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for i in 0..30 {
+            index_writer.add_document(doc!(tag_field => format!("a{}", i)));
+        }
+        index_writer.commit()?;
+        // Matches 30 terms, executed with a bitset.
+        let wide_query = RegexQuery::from_pattern("a.*", tag_field)?;
+        // Matches 11 terms, rewritten into a union of term queries.
+        let narrow_query = RegexQuery::from_pattern("a1.*", tag_field)?;
+
+        let reader = index
+            .reader_builder()
+            .expansion_budget_per_query(20, ExpansionOverflow::Fail)
+            .try_into()?;
+        let searcher = reader.searcher();
+        assert_eq!(searcher.expansion_budget().limit(), None);
+        assert_eq!(searcher.search(&narrow_query, &Count)?, 11);
+        assert!(matches!(
+            searcher.search(&wide_query, &Count),
+            Err(TantivyError::ExpansionBudgetExceeded(20))
+        ));
+        assert_eq!(
+            searcher.search(&wide_query, &Count).unwrap_err().code(),
+            ErrorCode::ExpansionBudgetExceeded
+        );
+
+        let reader = index
+            .reader_builder()
+            .expansion_budget_per_query(5, ExpansionOverflow::Degrade)
+            .try_into()?;
+        let searcher = reader.searcher();
+        assert_eq!(searcher.search(&narrow_query, &Count)?, 11);
+        assert_eq!(searcher.search(&wide_query, &Count)?, 30);
+        Ok(())
+    }
+
+    fn test_doc_store_only_index_aux(sort_by_id: Option<Order>) -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let id_field = schema_builder.add_i64_field("id", FAST | STORED);
+        let payload_field = schema_builder.add_text_field("payload", STORED);
+        let schema = schema_builder.build();
+        assert!(!schema.has_indexed_fields());
+        let settings = IndexSettings {
+            sort_by_field: sort_by_id.map(|order| IndexSortByField {
+                field: "id".to_string(),
+                order,
+            }),
+            ..Default::default()
+        };
+        let index = Index::builder()
+            .schema(schema)
+            .settings(settings)
+            .create_in_ram()?;
+        let mut index_writer = index.writer_for_tests()?;
+        for segment in 0..3i64 {
+            for i in (0..100i64).rev() {
+                let id = segment * 1_000 + i * 3;
+                index_writer
+                    .add_document(doc!(id_field => id, payload_field => format!("doc {}", id)));
+            }
+            index_writer.commit()?;
+        }
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 3);
+        for &id in &[0i64, 3, 297, 1_150, 2_297] {
+            let doc_address = searcher
+                .doc_by_key(&Term::from_field_i64(id_field, id))?
+                .unwrap();
+            let doc = searcher.doc(doc_address)?;
+            assert_eq!(doc.get_first(id_field).unwrap().i64_value(), Some(id));
+        }
+        for &id in &[-1i64, 1, 500, 2_298, 5_000] {
+            assert!(searcher
+                .doc_by_key(&Term::from_field_i64(id_field, id))?
+                .is_none());
+        }
+        assert!(matches!(
+            searcher.doc_by_key(&Term::from_field_text(payload_field, "doc 0")),
+            Err(TantivyError::SchemaError(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_doc_store_only_index() -> crate::Result<()> {
+        test_doc_store_only_index_aux(None)?;
+        test_doc_store_only_index_aux(Some(Order::Asc))?;
+        test_doc_store_only_index_aux(Some(Order::Desc))?;
+        Ok(())
+    }
+}
@@ -1,10 +1,13 @@
 use super::SegmentComponent;
+use crate::common::CompositeFile;
+use crate::core::FastFieldRange;
 use crate::core::Index;
 use crate::core::SegmentId;
 use crate::core::SegmentMeta;
 use crate::directory::error::{OpenReadError, OpenWriteError};
 use crate::directory::Directory;
 use crate::directory::{FileSlice, WritePtr};
+use crate::fastfield::{DeleteBitSet, FastFieldReaders};
 use crate::schema::Schema;
 use crate::Opstamp;
 use std::fmt;
@@ -63,6 +66,15 @@ impl Segment {
         }
     }
 
+    /// Updates the deletes of the segment, with a new delete file
+    /// only holding the docs deleted since the current one.
+    pub(crate) fn with_delete_file(self, num_deleted_docs: u32, opstamp: Opstamp) -> Segment {
+        Segment {
+            index: self.index,
+            meta: self.meta.with_delete_file(num_deleted_docs, opstamp),
+        }
+    }
+
     /// Returns the segment's id.
     pub fn id(&self) -> SegmentId {
         self.meta.id()
@@ -82,6 +94,55 @@ impl Segment {
         self.index.directory().open_read(&path)
     }
 
+    /// Open one of the component file for a *regular* read, and returns
+    /// the name of the format it was written in, if it is not the default format.
+    pub(crate) fn open_read_with_format(
+        &self,
+        component: SegmentComponent,
+    ) -> Result<(Option<String>, FileSlice), OpenReadError> {
+        let path = self.relative_path(component);
+        let (footer, file_slice) = self.index.directory().open_read_with_footer(&path)?;
+        Ok((footer.format, file_slice))
+    }
+
+    /// Opens the set of deleted docs of the segment, spread over its delete files.
+    pub(crate) fn open_delete_bitset(&self) -> crate::Result<Option<DeleteBitSet>> {
+        if !self.meta.has_deletes() {
+            return Ok(None);
+        }
+        if self.meta.has_dense_delete_file() {
+            let delete_data = self.open_read(SegmentComponent::Delete)?;
+            return DeleteBitSet::open_dense(delete_data).map(Some);
+        }
+        let delete_bitsets = self
+            .meta
+            .delete_file_paths()
+            .iter()
+            .map(|path| DeleteBitSet::open(self.index.directory().open_read(path)?))
+            .collect::<crate::Result<Vec<_>>>()?;
+        Ok(Some(DeleteBitSet::union(delete_bitsets)))
+    }
+
+    /// Returns the version of the index format in which the segment was written.
+    pub fn index_format_version(&self) -> Result<u32, OpenReadError> {
+        let path = self.relative_path(SegmentComponent::Postings);
+        let (footer, _) = self.index.directory().open_read_with_footer(&path)?;
+        Ok(footer.version.index_format_version())
+    }
+
+    /// Computes the min and max values of the indexed numeric fast fields of the segment,
+    /// as recorded in its `SegmentMeta`.
+    ///
+    /// The segment is expected to be written in the default format.
+    pub(crate) fn compute_fast_field_ranges(&self) -> crate::Result<Vec<FastFieldRange>> {
+        if self.meta.max_doc() == 0 {
+            return Ok(Vec::new());
+        }
+        let fast_fields_data = self.open_read(SegmentComponent::FastFields)?;
+        let fast_fields_composite = CompositeFile::open(&fast_fields_data)?;
+        FastFieldReaders::new(self.schema(), fast_fields_composite).value_ranges()
+    }
+
     /// Open one of the component file for *regular* write.
     pub fn open_write(&mut self, component: SegmentComponent) -> Result<WritePtr, OpenWriteError> {
         let path = self.relative_path(component);
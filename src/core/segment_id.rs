@@ -43,12 +43,30 @@ fn create_uuid() -> Uuid {
     Uuid::new_v4()
 }
 
+// SplitMix64 finalizer, used to derive segment ids from a seed.
+fn mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
 impl SegmentId {
     #[doc(hidden)]
     pub fn generate_random() -> SegmentId {
         SegmentId(create_uuid())
     }
 
+    /// Returns the `sequence`-th segment id derived from `seed`.
+    ///
+    /// The same seed and sequence number always give the same segment id,
+    /// which makes it possible to build reproducible indexes.
+    pub fn from_seed(seed: u64, sequence: u64) -> SegmentId {
+        let high = mix(seed ^ mix(sequence));
+        let low = mix(high ^ sequence);
+        SegmentId(Uuid::from_u128((u128::from(high) << 64) | u128::from(low)))
+    }
+
     /// Returns a shorter identifier of the segment.
     ///
     /// We are using UUID4, so only 6 bits are fixed,
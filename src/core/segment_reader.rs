@@ -1,26 +1,65 @@
+use crate::common::BitSet;
+use crate::core::FastFieldRange;
 use crate::core::InvertedIndexReader;
 use crate::core::Segment;
 use crate::core::SegmentComponent;
 use crate::core::SegmentId;
-use crate::directory::FileSlice;
-use crate::fastfield::DeleteBitSet;
+use crate::directory::{FileSlice, LazyFileSlice};
+use crate::error::{ErrorContext, ResultExt};
 use crate::fastfield::FacetReader;
+use crate::fastfield::FastFieldLookup;
 use crate::fastfield::FastFieldReaders;
+use crate::fastfield::{AliveDocs, DeleteBitSet};
 use crate::fieldnorm::{FieldNormReader, FieldNormReaders};
-use crate::schema::FieldType;
 use crate::schema::Schema;
 use crate::schema::{Field, IndexRecordOption};
+use crate::schema::{FieldNormEncoding, FieldType};
 use crate::space_usage::SegmentSpaceUsage;
-use crate::store::StoreReader;
+use crate::store::{DocStoreCache, StoreReader};
 use crate::termdict::TermDictionary;
 use crate::DocId;
+use crate::IndexSortByField;
+use crate::Opstamp;
 use crate::{common::CompositeFile, error::DataCorruption};
 use fail::fail_point;
+use once_cell::sync::OnceCell;
 use std::fmt;
+use std::ops::RangeInclusive;
 use std::sync::Arc;
 use std::sync::RwLock;
 use std::{collections::HashMap, io};
 
+/// Positions of a segment, which are only opened when they are first read.
+///
+/// Most queries do not need positions, so that opening them eagerly would
+/// needlessly slow down the opening of the segment and pollute the page cache.
+///
+/// Holding the `Segment` keeps its files from being garbage collected
+/// until they get opened.
+struct LazyPositions {
+    segment: Segment,
+    composite: OnceCell<CompositeFile>,
+}
+
+impl LazyPositions {
+    fn new(segment: Segment) -> LazyPositions {
+        LazyPositions {
+            segment,
+            composite: OnceCell::new(),
+        }
+    }
+
+    fn composite(&self) -> io::Result<&CompositeFile> {
+        self.composite.get_or_try_init(|| {
+            if let Ok(positions_file) = self.segment.open_read(SegmentComponent::Positions) {
+                CompositeFile::open(&positions_file)
+            } else {
+                Ok(CompositeFile::empty())
+            }
+        })
+    }
+}
+
 /// Entry point to access all of the datastructures of the `Segment`
 ///
 /// - term dictionary
@@ -34,6 +73,8 @@ use std::{collections::HashMap, io};
 #[derive(Clone)]
 pub struct SegmentReader {
     inv_idx_reader_cache: Arc<RwLock<HashMap<Field, Arc<InvertedIndexReader>>>>,
+    field_doc_count_cache: Arc<RwLock<HashMap<Field, u32>>>,
+    fast_field_lookup_cache: Arc<RwLock<HashMap<Field, Arc<FastFieldLookup>>>>,
 
     segment_id: SegmentId,
     max_doc: DocId,
@@ -41,12 +82,15 @@ pub struct SegmentReader {
 
     termdict_composite: CompositeFile,
     postings_composite: CompositeFile,
-    positions_composite: CompositeFile,
+    positions: Arc<LazyPositions>,
     fast_fields_readers: Arc<FastFieldReaders>,
     fieldnorm_readers: FieldNormReaders,
 
     store_file: FileSlice,
     delete_bitset_opt: Option<DeleteBitSet>,
+    delete_opstamp: Option<Opstamp>,
+    fast_field_ranges: Arc<[FastFieldRange]>,
+    index_sort: Option<IndexSortByField>,
     schema: Schema,
 }
 
@@ -124,6 +168,15 @@ impl SegmentReader {
     /// They are simply stored as a fast field, serialized in
     /// the `.fieldnorm` file of the segment.
     pub fn get_fieldnorms_reader(&self, field: Field) -> crate::Result<FieldNormReader> {
+        let fieldnorm_encoding = self
+            .schema
+            .get_field_entry(field)
+            .field_type()
+            .get_fieldnorm_encoding();
+        if fieldnorm_encoding == Some(FieldNormEncoding::Disabled) {
+            // Without fieldnorms, all of the documents are considered to have the same length.
+            return Ok(FieldNormReader::constant(self.max_doc, 1));
+        }
         self.fieldnorm_readers.get_field(field)?.ok_or_else(|| {
             let field_name = self.schema.get_field_name(field);
             let err_msg = format!(
@@ -139,46 +192,73 @@ impl SegmentReader {
         StoreReader::open(self.store_file.clone())
     }
 
+    /// Accessor to the segment's `StoreReader`, using a block cache
+    /// shared with other segments.
+    pub(crate) fn get_store_reader_with_cache(
+        &self,
+        cache: &DocStoreCache,
+    ) -> io::Result<StoreReader> {
+        StoreReader::open_with_cache(
+            self.store_file.clone(),
+            cache.clone(),
+            Some(self.segment_id()),
+        )
+    }
+
     /// Open a new segment for reading.
+    ///
+    /// Errors are returned with the id of the segment as context.
     pub fn open(segment: &Segment) -> crate::Result<SegmentReader> {
-        let termdict_file = segment.open_read(SegmentComponent::Terms)?;
+        SegmentReader::open_without_context(segment)
+            .with_context(|| ErrorContext::Segment(segment.id()))
+    }
+
+    fn open_without_context(segment: &Segment) -> crate::Result<SegmentReader> {
+        let codecs = segment.index().codecs();
+        let (termdict_format, termdict_file) =
+            segment.open_read_with_format(SegmentComponent::Terms)?;
+        let termdict_file = codecs.decode(
+            SegmentComponent::Terms,
+            termdict_format.as_deref(),
+            termdict_file,
+        )?;
         let termdict_composite = CompositeFile::open(&termdict_file)?;
 
         let store_file = segment.open_read(SegmentComponent::Store)?;
 
         fail_point!("SegmentReader::open#middle");
 
-        let postings_file = segment.open_read(SegmentComponent::Postings)?;
+        let (postings_format, postings_file) =
+            segment.open_read_with_format(SegmentComponent::Postings)?;
+        let postings_file = codecs.decode(
+            SegmentComponent::Postings,
+            postings_format.as_deref(),
+            postings_file,
+        )?;
         let postings_composite = CompositeFile::open(&postings_file)?;
 
-        let positions_composite = {
-            if let Ok(positions_file) = segment.open_read(SegmentComponent::Positions) {
-                CompositeFile::open(&positions_file)?
-            } else {
-                CompositeFile::empty()
-            }
-        };
-
         let schema = segment.schema();
 
-        let fast_fields_data = segment.open_read(SegmentComponent::FastFields)?;
+        let (fast_fields_format, fast_fields_data) =
+            segment.open_read_with_format(SegmentComponent::FastFields)?;
+        let fast_fields_data = codecs.decode(
+            SegmentComponent::FastFields,
+            fast_fields_format.as_deref(),
+            fast_fields_data,
+        )?;
         let fast_fields_composite = CompositeFile::open(&fast_fields_data)?;
         let fast_field_readers =
             Arc::new(FastFieldReaders::new(schema.clone(), fast_fields_composite));
 
         let fieldnorm_data = segment.open_read(SegmentComponent::FieldNorms)?;
-        let fieldnorm_readers = FieldNormReaders::open(fieldnorm_data)?;
-
-        let delete_bitset_opt = if segment.meta().has_deletes() {
-            let delete_data = segment.open_read(SegmentComponent::Delete)?;
-            let delete_bitset = DeleteBitSet::open(delete_data)?;
-            Some(delete_bitset)
-        } else {
-            None
-        };
+        let fieldnorm_readers = FieldNormReaders::open(fieldnorm_data, schema.clone())?;
+
+        let delete_bitset_opt = segment.open_delete_bitset()?;
 
         Ok(SegmentReader {
             inv_idx_reader_cache: Default::default(),
+            field_doc_count_cache: Default::default(),
+            fast_field_lookup_cache: Default::default(),
             max_doc: segment.meta().max_doc(),
             num_docs: segment.meta().num_docs(),
             termdict_composite,
@@ -188,11 +268,31 @@ impl SegmentReader {
             segment_id: segment.id(),
             store_file,
             delete_bitset_opt,
-            positions_composite,
+            delete_opstamp: segment.meta().delete_opstamp(),
+            fast_field_ranges: segment.meta().fast_field_ranges().into(),
+            index_sort: segment.meta().index_sort().cloned(),
+            positions: Arc::new(LazyPositions::new(segment.clone())),
             schema,
         })
     }
 
+    /// Returns a reader of `segment`, a more recent version of the segment of this reader
+    /// that only differs by its deletes.
+    ///
+    /// Only the delete bitset is read: the other files and the caches of the reader are shared.
+    pub(crate) fn reopen_with_deletes(&self, segment: &Segment) -> crate::Result<SegmentReader> {
+        assert_eq!(segment.id(), self.segment_id);
+        let delete_bitset_opt = segment
+            .open_delete_bitset()
+            .with_context(|| ErrorContext::Segment(segment.id()))?;
+        Ok(SegmentReader {
+            num_docs: segment.meta().num_docs(),
+            delete_bitset_opt,
+            delete_opstamp: segment.meta().delete_opstamp(),
+            ..self.clone()
+        })
+    }
+
     /// Returns a field reader associated to the field given in argument.
     /// If the field was not present in the index during indexing time,
     /// the InvertedIndexReader is empty.
@@ -241,13 +341,14 @@ impl SegmentReader {
                DataCorruption::comment_only(format!("Failed to open field {:?}'s term dictionary in the composite file. Has the schema been modified?", field_entry.name()))
             )?;
 
-        let positions_file = self
-            .positions_composite
-            .open_read(field)
-            .ok_or_else(|| {
-                let error_msg = format!("Failed to open field {:?}'s positions in the composite file. Has the schema been modified?", field_entry.name());
-               DataCorruption::comment_only(error_msg)
-            })?;
+        let positions = Arc::clone(&self.positions);
+        let field_name = field_entry.name().to_string();
+        let positions_file = LazyFileSlice::new(move || {
+            positions.composite()?.open_read(field).ok_or_else(|| {
+                let error_msg = format!("Failed to open field {:?}'s positions in the composite file. Has the schema been modified?", field_name);
+                io::Error::new(io::ErrorKind::InvalidData, error_msg)
+            })
+        });
 
         let inv_idx_reader = Arc::new(InvertedIndexReader::new(
             TermDictionary::open(termdict_file)?,
@@ -266,11 +367,137 @@ impl SegmentReader {
         Ok(inv_idx_reader)
     }
 
+    /// Returns the number of documents of the segment containing the field `field`,
+    /// deleted documents included.
+    ///
+    /// Like the other statistics used for scoring, e.g. `max_doc()` or
+    /// `InvertedIndexReader::total_num_tokens()`, it includes the deleted documents.
+    /// It makes it possible to score sparse fields against the documents that actually
+    /// contain them, rather than against all of the documents of the segment.
+    ///
+    /// For text fields, it is the number of documents with at least one token, computed
+    /// from the fieldnorms. If the fieldnorms of the field are disabled, all of the documents
+    /// are considered to contain the field. For the other indexed fields, the postings of all
+    /// of the terms of the field are read.
+    ///
+    /// The count is computed on the first call, and cached.
+    ///
+    /// Returns an error if the field is not indexed.
+    pub fn field_doc_count(&self, field: Field) -> crate::Result<u32> {
+        if let Some(&doc_count) = self
+            .field_doc_count_cache
+            .read()
+            .expect("Lock poisoned. This should never happen")
+            .get(&field)
+        {
+            return Ok(doc_count);
+        }
+        let field_entry = self.schema.get_field_entry(field);
+        let doc_count = match field_entry.field_type() {
+            _ if !field_entry.is_indexed() => {
+                return Err(crate::TantivyError::SchemaError(format!(
+                    "Field {:?} is not indexed",
+                    field_entry.name()
+                )));
+            }
+            FieldType::Str(_) => self.get_fieldnorms_reader(field)?.num_docs_with_tokens(),
+            _ => self.field_doc_bitset(field)?.len() as u32,
+        };
+        self.field_doc_count_cache
+            .write()
+            .expect("Field doc count cache lock poisoned. This should never happen.")
+            .insert(field, doc_count);
+        Ok(doc_count)
+    }
+
+    /// Returns the set of the documents of the segment containing at least one term
+    /// of the field, deleted documents included, by reading the postings of all of
+    /// the terms of the field.
+    pub(crate) fn field_doc_bitset(&self, field: Field) -> crate::Result<BitSet> {
+        let inverted_index = self.inverted_index(field)?;
+        let mut doc_bitset = BitSet::with_max_value(self.max_doc);
+        let mut term_stream = inverted_index.terms().stream()?;
+        while term_stream.advance() {
+            let mut block_segment_postings = inverted_index
+                .read_block_postings_from_terminfo(term_stream.value(), IndexRecordOption::Basic)?;
+            loop {
+                let docs = block_segment_postings.docs();
+                if docs.is_empty() {
+                    break;
+                }
+                for &doc in docs {
+                    doc_bitset.insert(doc);
+                }
+                block_segment_postings.advance();
+            }
+        }
+        Ok(doc_bitset)
+    }
+
+    /// Returns the map from the values of a numeric fast field to the documents
+    /// holding them, deleted documents included.
+    ///
+    /// The map is built on the first call, and cached.
+    ///
+    /// Returns an error if the field is not configured with a lookup index
+    /// (see [`IntOptions::set_lookup_index`](./schema/struct.IntOptions.html#method.set_lookup_index)).
+    pub fn fast_field_lookup(&self, field: Field) -> crate::Result<Arc<FastFieldLookup>> {
+        if let Some(lookup) = self
+            .fast_field_lookup_cache
+            .read()
+            .expect("Lock poisoned. This should never happen")
+            .get(&field)
+        {
+            return Ok(Arc::clone(lookup));
+        }
+        let field_entry = self.schema.get_field_entry(field);
+        if !field_entry.field_type().has_lookup_index() {
+            return Err(crate::TantivyError::SchemaError(format!(
+                "Field {:?} does not have a lookup index",
+                field_entry.name()
+            )));
+        }
+        let fast_field_reader = self.fast_fields().u64_lenient(field)?;
+        let lookup = Arc::new(FastFieldLookup::build(&fast_field_reader, self.max_doc));
+        // As for the inverted indexes, the lookup may be built twice, which is fine.
+        self.fast_field_lookup_cache
+            .write()
+            .expect("Fast field lookup cache lock poisoned. This should never happen.")
+            .insert(field, Arc::clone(&lookup));
+        Ok(lookup)
+    }
+
     /// Returns the segment id
     pub fn segment_id(&self) -> SegmentId {
         self.segment_id
     }
 
+    /// Returns the opstamp of the last delete operation
+    /// taken in account in the delete bitset of the segment.
+    pub(crate) fn delete_opstamp(&self) -> Option<Opstamp> {
+        self.delete_opstamp
+    }
+
+    /// Returns the min and max values of the fast field `field` in the segment,
+    /// in their `u64` fast field representation.
+    ///
+    /// See [`SegmentMeta::fast_field_range`](./struct.SegmentMeta.html#method.fast_field_range).
+    pub fn fast_field_range(&self, field: Field) -> Option<RangeInclusive<u64>> {
+        self.fast_field_ranges
+            .iter()
+            .find(|fast_field_range| fast_field_range.field == field)
+            .map(|fast_field_range| fast_field_range.min_value..=fast_field_range.max_value)
+    }
+
+    /// Returns the field and order the documents of the segment are sorted by,
+    /// as recorded in its `SegmentMeta`.
+    ///
+    /// Collectors and queries can rely on it to process the documents of the segment
+    /// in the order of this field.
+    pub fn index_sort(&self) -> Option<&IndexSortByField> {
+        self.index_sort.as_ref()
+    }
+
     /// Returns the bitset representing
     /// the documents that have been deleted.
     pub fn delete_bitset(&self) -> Option<&DeleteBitSet> {
@@ -285,9 +512,16 @@ impl SegmentReader {
             .unwrap_or(false)
     }
 
+    /// Returns an iterator over the alive document ids, in increasing order.
+    ///
+    /// Deleted documents are skipped a word of the delete bitset at a time.
+    pub fn alive_docs(&self) -> AliveDocs<'_> {
+        AliveDocs::new(self.delete_bitset(), self.max_doc)
+    }
+
     /// Returns an iterator that will iterate over the alive document ids
     pub fn doc_ids_alive(&self) -> impl Iterator<Item = DocId> + '_ {
-        (0u32..self.max_doc).filter(move |doc| !self.is_deleted(*doc))
+        self.alive_docs()
     }
 
     /// Summarize total space usage of this segment.
@@ -296,7 +530,7 @@ impl SegmentReader {
             self.num_docs(),
             self.termdict_composite.space_usage(),
             self.postings_composite.space_usage(),
-            self.positions_composite.space_usage(),
+            self.positions.composite()?.space_usage(),
             self.fast_fields_readers.space_usage(),
             self.fieldnorm_readers.space_usage(),
             self.get_store_reader()?.space_usage(),
@@ -317,6 +551,7 @@ impl fmt::Debug for SegmentReader {
 #[cfg(test)]
 mod test {
     use crate::core::Index;
+    use crate::query::Query;
     use crate::schema::{Schema, Term, STORED, TEXT};
     use crate::DocId;
 
@@ -377,4 +612,122 @@ mod test {
         assert_eq!(vec![0u32, 2u32], docs);
         Ok(())
     }
+
+    #[test]
+    fn test_field_doc_count() -> crate::Result<()> {
+        use crate::schema::{INDEXED, STRING};
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let body = schema_builder.add_text_field("body", TEXT);
+        let id = schema_builder.add_text_field("id", STRING);
+        let year = schema_builder.add_u64_field("year", INDEXED);
+        let stored = schema_builder.add_text_field("stored", STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_for_tests()?;
+            index_writer.add_document(doc!(title => "a b", body => "c", id => "1"));
+            index_writer.add_document(doc!(title => "d", year => 2000u64, id => "2"));
+            index_writer.add_document(doc!(title => "e", year => 2001u64, year => 2002u64));
+            index_writer.add_document(doc!(body => ""));
+            index_writer.delete_term(Term::from_field_text(title, "e"));
+            index_writer.commit()?;
+        }
+        let searcher = index.reader()?.searcher();
+        let segment_reader = searcher.segment_reader(0);
+        assert_eq!(segment_reader.field_doc_count(title)?, 3);
+        assert_eq!(segment_reader.field_doc_count(title)?, 3);
+        assert_eq!(segment_reader.field_doc_count(body)?, 1);
+        assert_eq!(segment_reader.field_doc_count(id)?, 2);
+        assert_eq!(segment_reader.field_doc_count(year)?, 2);
+        assert!(segment_reader.field_doc_count(stored).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_positions_are_opened_lazily() -> crate::Result<()> {
+        use crate::directory::error::{DeleteError, OpenReadError, OpenWriteError};
+        use crate::directory::{
+            Directory, FileHandle, RamDirectory, WatchCallback, WatchHandle, WritePtr,
+        };
+        use crate::query::{PhraseQuery, TermQuery};
+        use crate::schema::IndexRecordOption;
+        use crate::IndexSettings;
+        use std::io;
+        use std::path::{Path, PathBuf};
+        use std::sync::{Arc, Mutex};
+
+        /// `RamDirectory` recording the files that get opened.
+        #[derive(Clone, Debug, Default)]
+        struct RecordingDirectory {
+            directory: RamDirectory,
+            opened_files: Arc<Mutex<Vec<PathBuf>>>,
+        }
+
+        impl RecordingDirectory {
+            fn has_opened_positions(&self) -> bool {
+                self.opened_files
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .any(|path| path.extension().map_or(false, |ext| ext == "pos"))
+            }
+        }
+
+        impl Directory for RecordingDirectory {
+            fn get_file_handle(&self, path: &Path) -> Result<Box<dyn FileHandle>, OpenReadError> {
+                self.opened_files.lock().unwrap().push(path.to_path_buf());
+                self.directory.get_file_handle(path)
+            }
+            fn delete(&self, path: &Path) -> Result<(), DeleteError> {
+                self.directory.delete(path)
+            }
+            fn exists(&self, path: &Path) -> Result<bool, OpenReadError> {
+                self.directory.exists(path)
+            }
+            fn open_write(&self, path: &Path) -> Result<WritePtr, OpenWriteError> {
+                self.directory.open_write(path)
+            }
+            fn atomic_read(&self, path: &Path) -> Result<Vec<u8>, OpenReadError> {
+                self.directory.atomic_read(path)
+            }
+            fn atomic_write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+                self.directory.atomic_write(path, data)
+            }
+            fn watch(&self, watch_callback: WatchCallback) -> crate::Result<WatchHandle> {
+                self.directory.watch(watch_callback)
+            }
+        }
+
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let directory = RecordingDirectory::default();
+        let index = Index::create(
+            directory.clone(),
+            schema_builder.build(),
+            IndexSettings::default(),
+        )?;
+        {
+            let mut index_writer = index.writer_for_tests()?;
+            index_writer.add_document(doc!(text => "the old man and the sea"));
+            index_writer.add_document(doc!(text => "an old sea"));
+            index_writer.commit()?;
+        }
+        directory.opened_files.lock().unwrap().clear();
+
+        let searcher = index.reader()?.searcher();
+        let term_query = TermQuery::new(
+            Term::from_field_text(text, "sea"),
+            IndexRecordOption::WithFreqs,
+        );
+        assert_eq!(term_query.count(&searcher)?, 2);
+        assert!(!directory.has_opened_positions());
+
+        let phrase_query = PhraseQuery::new(vec![
+            Term::from_field_text(text, "old"),
+            Term::from_field_text(text, "sea"),
+        ]);
+        assert_eq!(phrase_query.count(&searcher)?, 1);
+        assert!(directory.has_opened_positions());
+        Ok(())
+    }
 }
@@ -3,12 +3,14 @@ use crate::directory::error::LockError;
 use crate::directory::error::{DeleteError, OpenReadError, OpenWriteError};
 use crate::directory::WatchHandle;
 use crate::directory::{FileHandle, WatchCallback};
-use crate::directory::{FileSlice, WritePtr};
+use crate::directory::{FileSlice, OwnedBytes, WritePtr};
+use crate::HasLen;
 use std::fmt;
 use std::io;
 use std::io::Write;
 use std::marker::Send;
 use std::marker::Sync;
+use std::ops::Range;
 use std::path::Path;
 use std::path::PathBuf;
 use std::thread;
@@ -127,6 +129,36 @@ pub trait Directory: DirectoryClone + fmt::Debug + Send + Sync + 'static {
         Ok(FileSlice::new(file_handle))
     }
 
+    /// Reads a range of bytes of a file, as it is stored in the directory.
+    ///
+    /// Contrary to `open_read`, wrapping directories such as the `ManagedDirectory`
+    /// are expected to return the raw bytes of the file, footer included.
+    /// This makes it possible to copy the files of an index chunk by chunk,
+    /// e.g. to replicate it from one node to another.
+    ///
+    /// Requesting a range exceeding the length of the file yields an `OpenReadError::IoError`.
+    fn read_bytes_range(
+        &self,
+        path: &Path,
+        range: Range<usize>,
+    ) -> Result<OwnedBytes, OpenReadError> {
+        let file_slice = self.open_read(path)?;
+        if range.start > range.end || range.end > file_slice.len() {
+            let io_error = io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!(
+                    "Range {:?} exceeds the file length ({}).",
+                    range,
+                    file_slice.len()
+                ),
+            );
+            return Err(OpenReadError::wrap_io_error(io_error, path.to_path_buf()));
+        }
+        file_slice
+            .read_bytes_slice(range)
+            .map_err(|io_error| OpenReadError::wrap_io_error(io_error, path.to_path_buf()))
+    }
+
     /// Removes a file
     ///
     /// Removing a file will not affect an eventual
@@ -176,6 +208,15 @@ pub trait Directory: DirectoryClone + fmt::Debug + Send + Sync + 'static {
     /// The file may or may not previously exist.
     fn atomic_write(&self, path: &Path, data: &[u8]) -> io::Result<()>;
 
+    /// Returns the number of bytes available to write new files,
+    /// or `None` if it is unknown.
+    ///
+    /// The `IndexWriter` relies on it to fail early when running out of disk space.
+    /// See [`IndexWriter::set_disk_space_reserve`](../struct.IndexWriter.html#method.set_disk_space_reserve).
+    fn available_space(&self) -> io::Result<Option<u64>> {
+        Ok(None)
+    }
+
     /// Acquire a lock in the given directory.
     ///
     /// The method is blocking or not depending on the `Lock` object.
@@ -5,10 +5,11 @@ use std::path::PathBuf;
 ///
 /// A lock is associated to a specific path and some
 /// [`LockParams`](./enum.LockParams.html).
-/// Tantivy itself uses only two locks but client application
+/// Tantivy itself uses only three locks but client application
 /// can use the directory facility to define their own locks.
 /// - [INDEX_WRITER_LOCK](./struct.INDEX_WRITER_LOCK.html)
 /// - [META_LOCK](./struct.META_LOCK.html)
+/// - [LEASES_LOCK](./struct.LEASES_LOCK.html)
 ///
 /// Check out these locks documentation for more information.
 ///
@@ -53,3 +54,11 @@ pub static META_LOCK: Lazy<Lock> = Lazy::new(|| Lock {
     filepath: PathBuf::from(".tantivy-meta.lock"),
     is_blocking: true,
 });
+/// The leases lock file protects the updates of the leases file of the index,
+/// which may be updated by several processes.
+///
+/// See [`Lease`](./struct.Lease.html).
+pub static LEASES_LOCK: Lazy<Lock> = Lazy::new(|| Lock {
+    filepath: PathBuf::from(".tantivy-leases.lock"),
+    is_blocking: true,
+});
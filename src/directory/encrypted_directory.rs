@@ -0,0 +1,512 @@
+use crate::common::HasLen;
+use crate::directory::error::{DeleteError, LockError, OpenReadError, OpenWriteError};
+use crate::directory::{
+    AntiCallToken, Directory, DirectoryLock, FileHandle, FileSlice, Lock, OwnedBytes,
+    TerminatingWrite, WatchCallback, WatchHandle, WritePtr,
+};
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::aead::{Aead, NewAead, Payload};
+use aes_gcm::Aes256Gcm;
+use std::fmt;
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+const MAGIC: [u8; 4] = *b"TENC";
+/// magic (4 bytes) + key id (4 bytes) + nonce prefix (8 bytes)
+const HEADER_LEN: usize = 16;
+const TAG_LEN: usize = 16;
+/// Number of bytes of plaintext per encrypted block.
+const BLOCK_SIZE: usize = 16_384;
+const ENCRYPTED_BLOCK_SIZE: usize = BLOCK_SIZE + TAG_LEN;
+
+/// Provides the keys with which the files of an `EncryptedDirectory` are encrypted.
+///
+/// Each file records the id of the key it was encrypted with, so that keys can be rotated:
+/// new files are encrypted with the current key, while files written before the rotation
+/// remain readable as long as their key is provided.
+pub trait KeyProvider: Send + Sync + 'static {
+    /// Returns the id of the key with which new files should be encrypted.
+    fn current_key_id(&self) -> u32;
+
+    /// Returns the 256-bit AES key associated to `key_id`.
+    fn key(&self, key_id: u32) -> io::Result<[u8; 32]>;
+}
+
+/// `KeyProvider` with a single key, of id `0`.
+#[derive(Clone)]
+pub struct StaticKeyProvider {
+    key: [u8; 32],
+}
+
+impl StaticKeyProvider {
+    /// Creates a `KeyProvider` always returning `key`.
+    pub fn new(key: [u8; 32]) -> StaticKeyProvider {
+        StaticKeyProvider { key }
+    }
+}
+
+impl KeyProvider for StaticKeyProvider {
+    fn current_key_id(&self) -> u32 {
+        0
+    }
+
+    fn key(&self, key_id: u32) -> io::Result<[u8; 32]> {
+        if key_id != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unknown key id {}", key_id),
+            ));
+        }
+        Ok(self.key)
+    }
+}
+
+/// Directory wrapper encrypting all of the files it stores with AES-256-GCM.
+///
+/// Files are split into blocks of 16KB, encrypted and authenticated independently,
+/// so that ranges of a file can be read without decrypting the whole file.
+/// Reads go through a `FileHandle` decrypting the requested blocks into memory,
+/// whatever the read path of the wrapped directory. Files are therefore never memory mapped
+/// in the clear, and reading an encrypted index requires more memory and cpu than
+/// reading a plain one.
+///
+/// Locks are not encrypted, as they do not contain any data.
+///
+/// ```rust
+/// use tantivy::directory::{EncryptedDirectory, RamDirectory, StaticKeyProvider};
+/// use tantivy::schema::{Schema, TEXT};
+/// use tantivy::Index;
+/// use std::sync::Arc;
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let key_provider = Arc::new(StaticKeyProvider::new([42u8; 32]));
+/// let directory = EncryptedDirectory::wrap(RamDirectory::create(), key_provider);
+/// let mut schema_builder = Schema::builder();
+/// schema_builder.add_text_field("title", TEXT);
+/// let _index = Index::create(directory, schema_builder.build(), Default::default())?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct EncryptedDirectory {
+    directory: Arc<dyn Directory>,
+    key_provider: Arc<dyn KeyProvider>,
+}
+
+impl EncryptedDirectory {
+    /// Wraps a directory, encrypting its files with the keys of `key_provider`.
+    pub fn wrap<D: Directory>(
+        directory: D,
+        key_provider: Arc<dyn KeyProvider>,
+    ) -> EncryptedDirectory {
+        EncryptedDirectory {
+            directory: Arc::new(directory),
+            key_provider,
+        }
+    }
+
+    fn new_writer<W: TerminatingWrite>(&self, writer: W) -> io::Result<EncryptingWriter<W>> {
+        let key_id = self.key_provider.current_key_id();
+        let cipher = new_cipher(&self.key_provider.key(key_id)?)?;
+        let mut header = [0u8; HEADER_LEN];
+        header[..4].copy_from_slice(&MAGIC);
+        header[4..8].copy_from_slice(&key_id.to_le_bytes());
+        // The nonce prefix is drawn from the CSPRNG of the operating system,
+        // so that no two files share a nonce.
+        getrandom::getrandom(&mut header[8..])
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?;
+        Ok(EncryptingWriter {
+            writer: Some(writer),
+            cipher,
+            header,
+            buffer: Vec::with_capacity(BLOCK_SIZE),
+            block_ord: 0,
+            header_written: false,
+        })
+    }
+
+    fn open_encrypted(&self, encrypted: FileSlice) -> io::Result<DecryptingFileHandle> {
+        let header_bytes = encrypted.read_bytes_slice(0..HEADER_LEN.min(encrypted.len()))?;
+        if header_bytes.len() < HEADER_LEN || header_bytes.as_slice()[..4] != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "The file is not encrypted, or is corrupted.",
+            ));
+        }
+        let mut header = [0u8; HEADER_LEN];
+        header.copy_from_slice(header_bytes.as_slice());
+        let mut key_id_bytes = [0u8; 4];
+        key_id_bytes.copy_from_slice(&header[4..8]);
+        let key_id = u32::from_le_bytes(key_id_bytes);
+        let cipher = new_cipher(&self.key_provider.key(key_id)?)?;
+
+        let body_len = encrypted.len() - HEADER_LEN;
+        let num_full_blocks = body_len / ENCRYPTED_BLOCK_SIZE;
+        let last_block_len = (body_len % ENCRYPTED_BLOCK_SIZE)
+            .checked_sub(TAG_LEN)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "The encrypted file is truncated.",
+                )
+            })?;
+        Ok(DecryptingFileHandle {
+            encrypted,
+            cipher,
+            header,
+            num_full_blocks,
+            len: num_full_blocks * BLOCK_SIZE + last_block_len,
+        })
+    }
+}
+
+impl fmt::Debug for EncryptedDirectory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "EncryptedDirectory({:?})", self.directory)
+    }
+}
+
+fn new_cipher(key: &[u8; 32]) -> io::Result<Aes256Gcm> {
+    Aes256Gcm::new_from_slice(&key[..])
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Invalid AES-256 key."))
+}
+
+/// The nonce of a block is the nonce prefix of its file, followed by its ordinal.
+fn block_nonce(header: &[u8; HEADER_LEN], block_ord: usize) -> [u8; 12] {
+    let mut nonce = [0u8; 12];
+    nonce[..8].copy_from_slice(&header[8..]);
+    nonce[8..].copy_from_slice(&(block_ord as u32).to_le_bytes());
+    nonce
+}
+
+/// The header and the position of the block (last or not) are authenticated,
+/// so that blocks cannot be swapped across files, and files cannot be truncated.
+fn block_aad(header: &[u8; HEADER_LEN], is_last_block: bool) -> [u8; HEADER_LEN + 1] {
+    let mut aad = [0u8; HEADER_LEN + 1];
+    aad[..HEADER_LEN].copy_from_slice(header);
+    aad[HEADER_LEN] = is_last_block as u8;
+    aad
+}
+
+/// Encrypts the data written into blocks.
+///
+/// Blocks are only encrypted and written once full, or upon termination:
+/// `flush` does not write the pending partial block.
+struct EncryptingWriter<W: TerminatingWrite> {
+    /// always Some except after terminate call
+    writer: Option<W>,
+    cipher: Aes256Gcm,
+    header: [u8; HEADER_LEN],
+    buffer: Vec<u8>,
+    block_ord: usize,
+    header_written: bool,
+}
+
+impl<W: TerminatingWrite> EncryptingWriter<W> {
+    fn write_block(&mut self, is_last_block: bool) -> io::Result<()> {
+        let writer = self.writer.as_mut().unwrap();
+        if !self.header_written {
+            writer.write_all(&self.header)?;
+            self.header_written = true;
+        }
+        let nonce = block_nonce(&self.header, self.block_ord);
+        let aad = block_aad(&self.header, is_last_block);
+        let encrypted_block = self
+            .cipher
+            .encrypt(
+                GenericArray::from_slice(&nonce),
+                Payload {
+                    msg: &self.buffer,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "Failed to encrypt a block."))?;
+        writer.write_all(&encrypted_block)?;
+        self.buffer.clear();
+        self.block_ord += 1;
+        Ok(())
+    }
+}
+
+impl<W: TerminatingWrite> Write for EncryptingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let num_bytes = buf.len().min(BLOCK_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..num_bytes]);
+        if self.buffer.len() == BLOCK_SIZE {
+            self.write_block(false)?;
+        }
+        Ok(num_bytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.as_mut().unwrap().flush()
+    }
+}
+
+impl<W: TerminatingWrite> TerminatingWrite for EncryptingWriter<W> {
+    fn terminate_ref(&mut self, _: AntiCallToken) -> io::Result<()> {
+        // The last block is always written, even if empty.
+        self.write_block(true)?;
+        self.writer.take().unwrap().terminate()
+    }
+}
+
+/// Decrypts the blocks of an encrypted file as they are read.
+struct DecryptingFileHandle {
+    encrypted: FileSlice,
+    cipher: Aes256Gcm,
+    header: [u8; HEADER_LEN],
+    num_full_blocks: usize,
+    len: usize,
+}
+
+impl DecryptingFileHandle {
+    fn decrypt_block(&self, block_ord: usize) -> io::Result<Vec<u8>> {
+        let is_last_block = block_ord == self.num_full_blocks;
+        let start = HEADER_LEN + block_ord * ENCRYPTED_BLOCK_SIZE;
+        let end = if is_last_block {
+            self.encrypted.len()
+        } else {
+            start + ENCRYPTED_BLOCK_SIZE
+        };
+        let encrypted_block = self.encrypted.read_bytes_slice(start..end)?;
+        let nonce = block_nonce(&self.header, block_ord);
+        let aad = block_aad(&self.header, is_last_block);
+        self.cipher
+            .decrypt(
+                GenericArray::from_slice(&nonce),
+                Payload {
+                    msg: encrypted_block.as_slice(),
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "Failed to decrypt block {}: the key is wrong or the file is corrupted.",
+                        block_ord
+                    ),
+                )
+            })
+    }
+}
+
+impl fmt::Debug for DecryptingFileHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DecryptingFileHandle(len={})", self.len)
+    }
+}
+
+impl HasLen for DecryptingFileHandle {
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl FileHandle for DecryptingFileHandle {
+    fn read_bytes(&self, range: Range<usize>) -> io::Result<OwnedBytes> {
+        if range.start >= range.end {
+            return Ok(OwnedBytes::empty());
+        }
+        let first_block = range.start / BLOCK_SIZE;
+        let last_block = (range.end - 1) / BLOCK_SIZE;
+        let mut bytes = Vec::with_capacity(range.end - range.start);
+        for block_ord in first_block..=last_block {
+            let block = self.decrypt_block(block_ord)?;
+            let block_start = block_ord * BLOCK_SIZE;
+            let start = range.start.max(block_start) - block_start;
+            let end = range.end.min(block_start + block.len()) - block_start;
+            bytes.extend_from_slice(&block[start..end]);
+        }
+        Ok(OwnedBytes::new(bytes))
+    }
+}
+
+impl Directory for EncryptedDirectory {
+    fn get_file_handle(&self, path: &Path) -> Result<Box<dyn FileHandle>, OpenReadError> {
+        let encrypted = self.directory.open_read(path)?;
+        let file_handle = self
+            .open_encrypted(encrypted)
+            .map_err(|io_error| OpenReadError::wrap_io_error(io_error, path.to_path_buf()))?;
+        Ok(Box::new(file_handle))
+    }
+
+    fn delete(&self, path: &Path) -> Result<(), DeleteError> {
+        self.directory.delete(path)
+    }
+
+    fn exists(&self, path: &Path) -> Result<bool, OpenReadError> {
+        self.directory.exists(path)
+    }
+
+    fn open_write(&self, path: &Path) -> Result<WritePtr, OpenWriteError> {
+        let write = self.directory.open_write(path)?;
+        let encrypting_writer = self
+            .new_writer(write)
+            .map_err(|io_error| OpenWriteError::wrap_io_error(io_error, path.to_path_buf()))?;
+        Ok(BufWriter::new(Box::new(encrypting_writer)))
+    }
+
+    fn atomic_read(&self, path: &Path) -> Result<Vec<u8>, OpenReadError> {
+        let encrypted = self.directory.atomic_read(path)?;
+        self.open_encrypted(FileSlice::from(encrypted))
+            .and_then(|file_handle| file_handle.read_bytes(0..file_handle.len()))
+            .map(|bytes| bytes.as_slice().to_vec())
+            .map_err(|io_error| OpenReadError::wrap_io_error(io_error, path.to_path_buf()))
+    }
+
+    fn atomic_write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        let mut encrypted = Vec::new();
+        let mut encrypting_writer = self.new_writer(&mut encrypted)?;
+        encrypting_writer.write_all(data)?;
+        encrypting_writer.terminate()?;
+        self.directory.atomic_write(path, &encrypted)
+    }
+
+    fn available_space(&self) -> io::Result<Option<u64>> {
+        self.directory.available_space()
+    }
+
+    fn acquire_lock(&self, lock: &Lock) -> Result<DirectoryLock, LockError> {
+        self.directory.acquire_lock(lock)
+    }
+
+    fn watch(&self, watch_callback: WatchCallback) -> crate::Result<WatchHandle> {
+        self.directory.watch(watch_callback)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EncryptedDirectory, KeyProvider, StaticKeyProvider, BLOCK_SIZE, HEADER_LEN};
+    use crate::directory::{Directory, RamDirectory, TerminatingWrite};
+    use crate::query::TermQuery;
+    use crate::schema::{IndexRecordOption, Schema, STORED, TEXT};
+    use crate::{collector::Count, Index, Term};
+    use std::io::{self, Write};
+    use std::path::Path;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_encrypted_directory_read_ranges() -> crate::Result<()> {
+        let ram_directory = RamDirectory::create();
+        let directory = EncryptedDirectory::wrap(
+            ram_directory.clone(),
+            Arc::new(StaticKeyProvider::new([1u8; 32])),
+        );
+        let path = Path::new("file");
+        let data: Vec<u8> = (0..3 * BLOCK_SIZE + 17).map(|i| (i % 251) as u8).collect();
+        let mut write = directory.open_write(path)?;
+        write.write_all(&data)?;
+        write.terminate()?;
+
+        let file_slice = directory.open_read(path)?;
+        assert_eq!(file_slice.len(), data.len());
+        assert_eq!(file_slice.read_bytes()?.as_slice(), &data[..]);
+        let range = BLOCK_SIZE - 3..2 * BLOCK_SIZE + 5;
+        assert_eq!(
+            file_slice.read_bytes_slice(range.clone())?.as_slice(),
+            &data[range]
+        );
+
+        // The stored bytes are encrypted.
+        let encrypted = ram_directory.open_read(path)?.read_bytes()?;
+        assert!(!encrypted
+            .as_slice()
+            .windows(64)
+            .any(|window| window == &data[..64]));
+
+        let empty_path = Path::new("empty");
+        directory.open_write(empty_path)?.terminate()?;
+        assert_eq!(directory.open_read(empty_path)?.len(), 0);
+
+        directory.atomic_write(Path::new("atomic"), b"hello")?;
+        assert_eq!(directory.atomic_read(Path::new("atomic"))?, b"hello");
+        Ok(())
+    }
+
+    #[test]
+    fn test_encrypted_directory_nonce_prefixes() -> crate::Result<()> {
+        let ram_directory = RamDirectory::create();
+        let directory = EncryptedDirectory::wrap(
+            ram_directory.clone(),
+            Arc::new(StaticKeyProvider::new([1u8; 32])),
+        );
+        directory.atomic_write(Path::new("a"), b"hello")?;
+        directory.atomic_write(Path::new("b"), b"hello")?;
+        let encrypted_a = ram_directory.atomic_read(Path::new("a"))?;
+        let encrypted_b = ram_directory.atomic_read(Path::new("b"))?;
+        // Files encrypted with the same key never share a nonce prefix.
+        assert_ne!(encrypted_a[8..HEADER_LEN], encrypted_b[8..HEADER_LEN]);
+        assert_ne!(encrypted_a[HEADER_LEN..], encrypted_b[HEADER_LEN..]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_encrypted_directory_wrong_key() -> crate::Result<()> {
+        let ram_directory = RamDirectory::create();
+        let directory = EncryptedDirectory::wrap(
+            ram_directory.clone(),
+            Arc::new(StaticKeyProvider::new([1u8; 32])),
+        );
+        let path = Path::new("file");
+        let mut write = directory.open_write(path)?;
+        write.write_all(b"secret")?;
+        write.terminate()?;
+
+        let other_directory =
+            EncryptedDirectory::wrap(ram_directory, Arc::new(StaticKeyProvider::new([2u8; 32])));
+        let file_slice = other_directory.open_read(path)?;
+        assert_eq!(
+            file_slice.read_bytes().err().map(|err| err.kind()),
+            Some(io::ErrorKind::InvalidData)
+        );
+        Ok(())
+    }
+
+    struct RotatingKeyProvider;
+
+    impl KeyProvider for RotatingKeyProvider {
+        fn current_key_id(&self) -> u32 {
+            1
+        }
+
+        fn key(&self, key_id: u32) -> io::Result<[u8; 32]> {
+            Ok([key_id as u8; 32])
+        }
+    }
+
+    #[test]
+    fn test_encrypted_index() -> crate::Result<()> {
+        let ram_directory = RamDirectory::create();
+        let directory = EncryptedDirectory::wrap(
+            ram_directory.clone(),
+            Arc::new(StaticKeyProvider::new([0u8; 32])),
+        );
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let index = Index::create(directory, schema_builder.build(), Default::default())?;
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field => "confidential document"));
+        index_writer.commit()?;
+
+        // Files written with the previous key remain readable after a key rotation.
+        let index = Index::open(EncryptedDirectory::wrap(
+            ram_directory,
+            Arc::new(RotatingKeyProvider),
+        ))?;
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field => "another confidential document"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query = TermQuery::new(
+            Term::from_field_text(text_field, "confidential"),
+            IndexRecordOption::Basic,
+        );
+        assert_eq!(searcher.search(&query, &Count)?, 2);
+        Ok(())
+    }
+}
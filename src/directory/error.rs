@@ -14,7 +14,7 @@ pub enum LockError {
     LockBusy,
     /// Trying to acquire a lock failed with an `IoError`
     #[error("Failed to acquire the lock due to an io:Error.")]
-    IoError(io::Error),
+    IoError(#[source] io::Error),
 }
 
 /// Error that may occur when opening a directory
@@ -80,6 +80,14 @@ pub enum Incompatibility {
         /// Version the index was built with
         index_version: Version,
     },
+    /// A segment component was written in a format that is not registered
+    /// in the `CodecManager` of the index.
+    UnknownFormat {
+        /// Segment component written in this format
+        component: String,
+        /// Name of the format
+        format_name: String,
+    },
 }
 
 impl fmt::Debug for Incompatibility {
@@ -116,6 +124,17 @@ impl fmt::Debug for Incompatibility {
                 );
                 write!(f, "{}. {}", err, advice)?;
             }
+            Incompatibility::UnknownFormat {
+                component,
+                format_name,
+            } => {
+                write!(
+                    f,
+                    "Segment component {} was written in the unknown format {:?}. Register this \
+                     format in the `CodecManager` of the index to read it.",
+                    component, format_name
+                )?;
+            }
         }
 
         Ok(())
@@ -2,6 +2,7 @@ use stable_deref_trait::StableDeref;
 
 use crate::common::HasLen;
 use crate::directory::OwnedBytes;
+use once_cell::sync::OnceCell;
 use std::fmt;
 use std::ops::Range;
 use std::sync::{Arc, Weak};
@@ -175,6 +176,49 @@ impl HasLen for FileSlice {
     }
 }
 
+type OpenFileSlice = Box<dyn Fn() -> io::Result<FileSlice> + Send + Sync>;
+
+/// `FileSlice` that is only opened when it is first accessed.
+///
+/// It is used for data that most readers never touch, like positions,
+/// so that the underlying file is not opened, or paged in, unless needed.
+pub(crate) struct LazyFileSlice {
+    file_slice: OnceCell<FileSlice>,
+    open: Option<OpenFileSlice>,
+}
+
+impl LazyFileSlice {
+    /// Creates a `LazyFileSlice` calling `open` on its first access.
+    ///
+    /// If `open` fails, it is called again on the next access.
+    pub fn new<F>(open: F) -> LazyFileSlice
+    where
+        F: Fn() -> io::Result<FileSlice> + Send + Sync + 'static,
+    {
+        LazyFileSlice {
+            file_slice: OnceCell::new(),
+            open: Some(Box::new(open)),
+        }
+    }
+
+    /// Returns the `FileSlice`, opening it if this is the first access.
+    pub fn get(&self) -> io::Result<&FileSlice> {
+        self.file_slice.get_or_try_init(|| match &self.open {
+            Some(open) => open(),
+            None => Ok(FileSlice::empty()),
+        })
+    }
+}
+
+impl From<FileSlice> for LazyFileSlice {
+    fn from(file_slice: FileSlice) -> LazyFileSlice {
+        LazyFileSlice {
+            file_slice: OnceCell::from(file_slice),
+            open: None,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{FileHandle, FileSlice};
@@ -3,7 +3,7 @@ use crate::directory::FileSlice;
 use crate::{
     common::{BinarySerializable, CountingWriter, DeserializeFrom, FixedSize, HasLen},
     directory::{AntiCallToken, TerminatingWrite},
-    Version, INDEX_FORMAT_VERSION,
+    Version, INDEX_FORMAT_VERSION, MIN_INDEX_FORMAT_VERSION,
 };
 use crc32fast::Hasher;
 use serde::{Deserialize, Serialize};
@@ -23,12 +23,21 @@ type CrcHashU32 = u32;
 pub struct Footer {
     pub version: Version,
     pub crc: CrcHashU32,
+    /// Name of the format the file was written in, if it is not the default format.
+    ///
+    /// See the [`codec`](../codec/index.html) module.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
 }
 
 impl Footer {
     pub fn new(crc: CrcHashU32) -> Self {
         let version = crate::VERSION.clone();
-        Footer { version, crc }
+        Footer {
+            version,
+            crc,
+            format: None,
+        }
     }
 
     pub fn crc(&self) -> CrcHashU32 {
@@ -101,7 +110,7 @@ impl Footer {
     /// Has to be called after `extract_footer` to make sure it's not accessing uninitialised memory
     pub fn is_compatible(&self) -> Result<(), Incompatibility> {
         let library_version = crate::version();
-        if self.version.index_format_version < 4
+        if self.version.index_format_version < MIN_INDEX_FORMAT_VERSION
             || self.version.index_format_version > INDEX_FORMAT_VERSION
         {
             return Err(Incompatibility::IndexMismatch {
@@ -118,13 +127,19 @@ pub(crate) struct FooterProxy<W: TerminatingWrite> {
     hasher: Option<Hasher>,
     /// always Some except after terminate call
     writer: Option<W>,
+    format: Option<String>,
 }
 
 impl<W: TerminatingWrite> FooterProxy<W> {
     pub fn new(writer: W) -> Self {
+        FooterProxy::with_format(writer, None)
+    }
+
+    pub fn with_format(writer: W, format: Option<String>) -> Self {
         FooterProxy {
             hasher: Some(Hasher::new()),
             writer: Some(writer),
+            format,
         }
     }
 }
@@ -144,7 +159,8 @@ impl<W: TerminatingWrite> Write for FooterProxy<W> {
 impl<W: TerminatingWrite> TerminatingWrite for FooterProxy<W> {
     fn terminate_ref(&mut self, _: AntiCallToken) -> io::Result<()> {
         let crc32 = self.hasher.take().unwrap().finalize();
-        let footer = Footer::new(crc32);
+        let mut footer = Footer::new(crc32);
+        footer.format = self.format.take();
         let mut writer = self.writer.take().unwrap();
         footer.append_footer(&mut writer)?;
         writer.terminate()
@@ -171,6 +187,19 @@ mod tests {
         let fileslice = FileSlice::new(Box::new(owned_bytes));
         let (footer_deser, _body) = Footer::extract_footer(fileslice).unwrap();
         assert_eq!(footer_deser.crc(), footer.crc());
+        assert_eq!(footer_deser.format, None);
+    }
+
+    #[test]
+    fn test_deserialize_footer_with_format() {
+        let mut buf: Vec<u8> = vec![];
+        let mut footer = Footer::new(123);
+        footer.format = Some("experimental".to_string());
+        footer.append_footer(&mut buf).unwrap();
+        let owned_bytes = OwnedBytes::new(buf);
+        let fileslice = FileSlice::new(Box::new(owned_bytes));
+        let (footer_deser, _body) = Footer::extract_footer(fileslice).unwrap();
+        assert_eq!(footer_deser, footer);
     }
     #[test]
     fn test_deserialize_footer_missing_magic_byte() {
@@ -0,0 +1,204 @@
+use crate::directory::error::OpenReadError;
+use crate::directory::{Directory, LEASES_LOCK};
+use crate::error::DataCorruption;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// File listing the leases of an index.
+///
+/// It starts with a "." so that it is not managed, and never garbage collected.
+static LEASES_FILEPATH: Lazy<&'static Path> = Lazy::new(|| Path::new(".tantivy-leases.json"));
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LeaseEntry {
+    files: HashSet<PathBuf>,
+    // Milliseconds since the UNIX epoch, so that it is meaningful to other processes.
+    expires_at_millis: u64,
+}
+
+type Leases = HashMap<String, LeaseEntry>;
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0u64)
+}
+
+fn read_leases(directory: &dyn Directory) -> crate::Result<Leases> {
+    match directory.atomic_read(&LEASES_FILEPATH) {
+        Ok(data) => serde_json::from_slice(&data).map_err(|err| {
+            DataCorruption::new(
+                LEASES_FILEPATH.to_path_buf(),
+                format!("Leases file cannot be deserialized: {:?}", err),
+            )
+            .into()
+        }),
+        Err(OpenReadError::FileDoesNotExist(_)) => Ok(Leases::default()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Applies `update` to the leases of the directory, expired leases excluded.
+///
+/// The leases file is read and written while holding the `LEASES_LOCK`,
+/// so that concurrent updates, possibly from different processes, are not lost.
+fn update_leases<F: FnOnce(&mut Leases)>(
+    directory: &dyn Directory,
+    update: F,
+) -> crate::Result<()> {
+    let _leases_lock = directory.acquire_lock(&LEASES_LOCK)?;
+    let mut leases = read_leases(directory)?;
+    let now = now_millis();
+    leases.retain(|_, lease_entry| lease_entry.expires_at_millis > now);
+    update(&mut leases);
+    let data = serde_json::to_vec(&leases)?;
+    directory.atomic_write(&LEASES_FILEPATH, &data)?;
+    Ok(())
+}
+
+/// Returns the files protected by a lease that has not expired yet.
+pub(crate) fn leased_files(directory: &dyn Directory) -> crate::Result<HashSet<PathBuf>> {
+    let now = now_millis();
+    Ok(read_leases(directory)?
+        .into_iter()
+        .filter(|(_, lease_entry)| lease_entry.expires_at_millis > now)
+        .flat_map(|(_, lease_entry)| lease_entry.files)
+        .collect())
+}
+
+/// A `Lease` protects a set of files of an index from garbage collection.
+///
+/// Leases are persisted in the directory of the index, so that a reader can protect
+/// the files it uses from the garbage collection of an `IndexWriter` running in
+/// another process, e.g. on a shared NFS volume, where deleting a file
+/// that is still in use makes its reader crash.
+///
+/// A lease expires if it is not renewed within its duration. That way, the leases of a
+/// process that crashed do not prevent the garbage collection of the files forever.
+///
+/// The lease is released when dropped.
+/// See also [`IndexReaderBuilder::lease_duration`](../struct.IndexReaderBuilder.html#method.lease_duration).
+#[derive(Debug)]
+pub struct Lease {
+    id: String,
+    directory: Box<dyn Directory>,
+}
+
+impl Lease {
+    /// Acquires a lease on `files`, for `duration`.
+    pub fn acquire(
+        directory: &dyn Directory,
+        files: HashSet<PathBuf>,
+        duration: Duration,
+    ) -> crate::Result<Lease> {
+        let lease = Lease {
+            id: uuid::Uuid::new_v4().to_simple().to_string(),
+            directory: directory.box_clone(),
+        };
+        lease.renew(files, duration)?;
+        Ok(lease)
+    }
+
+    /// Returns the id of the lease.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Renews the lease for `duration`, replacing the files it protects by `files`.
+    ///
+    /// Renewing a lease that has already expired acquires it again.
+    pub fn renew(&self, files: HashSet<PathBuf>, duration: Duration) -> crate::Result<()> {
+        let expires_at_millis = now_millis() + duration.as_millis() as u64;
+        update_leases(self.directory.as_ref(), |leases| {
+            leases.insert(
+                self.id.clone(),
+                LeaseEntry {
+                    files,
+                    expires_at_millis,
+                },
+            );
+        })
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        if let Err(err) = update_leases(self.directory.as_ref(), |leases| {
+            leases.remove(&self.id);
+        }) {
+            // The lease will simply expire.
+            warn!("Failed to release lease {}: {:?}", self.id, err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{leased_files, Lease};
+    use crate::directory::RamDirectory;
+    use crate::schema::{Schema, TEXT};
+    use crate::{Index, ReloadPolicy, SegmentComponent};
+    use std::collections::HashSet;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    #[test]
+    fn test_lease() -> crate::Result<()> {
+        let directory = RamDirectory::create();
+        let files: HashSet<PathBuf> = vec![PathBuf::from("a"), PathBuf::from("b")]
+            .into_iter()
+            .collect();
+        let lease = Lease::acquire(&directory, files.clone(), Duration::from_secs(60))?;
+        let expired_lease = Lease::acquire(
+            &directory,
+            vec![PathBuf::from("c")].into_iter().collect(),
+            Duration::from_secs(0),
+        )?;
+        assert_eq!(leased_files(&directory)?, files);
+        lease.renew(
+            vec![PathBuf::from("b")].into_iter().collect(),
+            Duration::from_secs(60),
+        )?;
+        assert_eq!(leased_files(&directory)?.len(), 1);
+        drop(lease);
+        drop(expired_lease);
+        assert!(leased_files(&directory)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_reader_lease_protects_files_from_gc() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.commit()?;
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .lease_duration(Duration::from_secs(60))
+            .try_into()?;
+        let leased_segment = index.searchable_segments()?.into_iter().next().unwrap();
+        let leased_path = leased_segment.relative_path(SegmentComponent::Postings);
+        index_writer.add_document(doc!(text_field => "b"));
+        index_writer.commit()?;
+        let segment_ids = index.searchable_segment_ids()?;
+        futures::executor::block_on(index_writer.merge(&segment_ids))?;
+        futures::executor::block_on(index_writer.garbage_collect_files())?;
+        assert!(index.directory().exists(&leased_path)?);
+        assert!(index
+            .directory()
+            .exists(Path::new(".tantivy-leases.json"))?);
+
+        reader.reload()?;
+        assert_eq!(reader.searcher().num_docs(), 2);
+        futures::executor::block_on(index_writer.garbage_collect_files())?;
+        assert!(!index.directory().exists(&leased_path)?);
+        Ok(())
+    }
+}
@@ -1,23 +1,28 @@
-use crate::core::{MANAGED_FILEPATH, META_FILEPATH};
+use crate::core::{meta_generation_from_filepath, MANAGED_FILEPATH, META_FILEPATH};
 use crate::directory::error::{DeleteError, LockError, OpenReadError, OpenWriteError};
 use crate::directory::footer::{Footer, FooterProxy};
+use crate::directory::lease::leased_files;
 use crate::directory::GarbageCollectionResult;
 use crate::directory::Lock;
 use crate::directory::META_LOCK;
 use crate::directory::{DirectoryLock, FileHandle};
-use crate::directory::{FileSlice, WritePtr};
+use crate::directory::{FileSlice, OwnedBytes, TerminatingWrite, WritePtr};
 use crate::directory::{WatchCallback, WatchHandle};
 use crate::error::DataCorruption;
 use crate::Directory;
+use crate::HasLen;
 
 use crc32fast::Hasher;
-use std::collections::HashSet;
+use fail::fail_point;
+use std::collections::{HashMap, HashSet};
 use std::io;
 use std::io::Write;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 use std::result;
 use std::sync::RwLockWriteGuard;
 use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 
 /// Returns true iff the file is "managed".
 /// Non-managed file are not subject to garbage collection.
@@ -48,6 +53,10 @@ pub struct ManagedDirectory {
 #[derive(Debug, Default)]
 struct MetaInformation {
     managed_paths: HashSet<PathBuf>,
+    // Files found unused by a garbage collection, and the time at which they were
+    // first found unused.
+    pending_deletions: HashMap<PathBuf, Instant>,
+    gc_grace_period: Duration,
 }
 
 /// Saves the file containing the list of existing files
@@ -56,7 +65,10 @@ fn save_managed_paths(
     directory: &dyn Directory,
     wlock: &RwLockWriteGuard<'_, MetaInformation>,
 ) -> io::Result<()> {
-    let mut w = serde_json::to_vec(&wlock.managed_paths)?;
+    // The paths are sorted, so that the file does not depend on the order of the `HashSet`.
+    let mut managed_paths: Vec<&PathBuf> = wlock.managed_paths.iter().collect();
+    managed_paths.sort();
+    let mut w = serde_json::to_vec(&managed_paths)?;
     writeln!(&mut w)?;
     directory.atomic_write(&MANAGED_FILEPATH, &w[..])?;
     Ok(())
@@ -79,6 +91,7 @@ impl ManagedDirectory {
                     directory: Box::new(directory),
                     meta_informations: Arc::new(RwLock::new(MetaInformation {
                         managed_paths: managed_files,
+                        ..MetaInformation::default()
                     })),
                 })
             }
@@ -95,6 +108,21 @@ impl ManagedDirectory {
         }
     }
 
+    /// Sets the grace period of the files that are not used anymore.
+    ///
+    /// The garbage collection only deletes the files that were already found unused
+    /// by a garbage collection at least `grace_period` earlier. This gives the
+    /// searchers of other processes, that do not hold a [`Lease`](./struct.Lease.html),
+    /// some time to release the files they are using.
+    ///
+    /// By default, the grace period is zero, and unused files are deleted right away.
+    pub fn set_gc_grace_period(&self, grace_period: Duration) {
+        self.meta_informations
+            .write()
+            .expect("Managed directory wlock poisoned in set gc grace period.")
+            .gc_grace_period = grace_period;
+    }
+
     /// Garbage collect unused files.
     ///
     /// Removes the files that were created by `tantivy` and are not
@@ -105,16 +133,26 @@ impl ManagedDirectory {
     /// The use a callback ensures that the list of living_files is computed
     /// while we hold the lock on meta.
     ///
+    /// Files protected by a [`Lease`](./struct.Lease.html) that has not expired
+    /// are considered living. Other unused files are only deleted once their
+    /// grace period has elapsed (see `set_gc_grace_period`).
+    ///
     /// This method does not panick nor returns errors.
     /// If a file cannot be deleted (for permission reasons for instance)
     /// an error is simply logged, and the file remains in the list of managed
     /// files.
+    /// Likewise, if the leases cannot be read, an error is logged and no file
+    /// is deleted until the next garbage collection.
     pub fn garbage_collect<L: FnOnce() -> HashSet<PathBuf>>(
         &mut self,
         get_living_files: L,
     ) -> crate::Result<GarbageCollectionResult> {
+        fail_point!(crate::failpoints::GARBAGE_COLLECT, |msg| Err(
+            crate::failpoints::failpoint_error(msg).into()
+        ));
         info!("Garbage collect");
-        let mut files_to_delete = vec![];
+        enter_span!("garbage_collect");
+        let mut unused_files = vec![];
 
         // It is crucial to get the living files after acquiring the
         // read lock of meta informations. That way, we
@@ -141,9 +179,22 @@ impl ManagedDirectory {
             match self.acquire_lock(&META_LOCK) {
                 Ok(_meta_lock) => {
                     let living_files = get_living_files();
+                    let leased_files = match leased_files(self.directory.as_ref()) {
+                        Ok(leased_files) => leased_files,
+                        Err(err) => {
+                            error!("Failed to read the leases, skipping GC: {:?}", err);
+                            return Ok(GarbageCollectionResult {
+                                deleted_files: Vec::new(),
+                                failed_to_delete_files: Vec::new(),
+                                pending_files: Vec::new(),
+                            });
+                        }
+                    };
                     for managed_path in &meta_informations_rlock.managed_paths {
-                        if !living_files.contains(managed_path) {
-                            files_to_delete.push(managed_path.clone());
+                        if !living_files.contains(managed_path)
+                            && !leased_files.contains(managed_path)
+                        {
+                            unused_files.push(managed_path.clone());
                         }
                     }
                 }
@@ -154,6 +205,36 @@ impl ManagedDirectory {
             }
         }
 
+        // Two-phase deletion: files are first marked as pending deletion,
+        // and only deleted once they have been unused for the grace period.
+        let mut files_to_delete = vec![];
+        let mut pending_files = vec![];
+        {
+            let mut meta_informations_wlock = self
+                .meta_informations
+                .write()
+                .expect("Managed directory wlock poisoned in garbage collect.");
+            let now = Instant::now();
+            let grace_period = meta_informations_wlock.gc_grace_period;
+            let previous_pending_deletions =
+                std::mem::take(&mut meta_informations_wlock.pending_deletions);
+            for unused_file in unused_files {
+                let unused_since = previous_pending_deletions
+                    .get(&unused_file)
+                    .cloned()
+                    .unwrap_or(now);
+                if now.duration_since(unused_since) >= grace_period {
+                    files_to_delete.push(unused_file.clone());
+                } else {
+                    pending_files.push(unused_file.clone());
+                }
+                // Files that are used again are dropped from the pending deletions.
+                meta_informations_wlock
+                    .pending_deletions
+                    .insert(unused_file, unused_since);
+            }
+        }
+
         let mut failed_to_delete_files = vec![];
         let mut deleted_files = vec![];
 
@@ -188,9 +269,11 @@ impl ManagedDirectory {
                 .meta_informations
                 .write()
                 .expect("Managed directory wlock poisoned (2).");
-            let managed_paths_write = &mut meta_informations_wlock.managed_paths;
             for delete_file in &deleted_files {
-                managed_paths_write.remove(delete_file);
+                meta_informations_wlock.managed_paths.remove(delete_file);
+                meta_informations_wlock
+                    .pending_deletions
+                    .remove(delete_file);
             }
             save_managed_paths(self.directory.as_mut(), &meta_informations_wlock)?;
         }
@@ -198,6 +281,7 @@ impl ManagedDirectory {
         Ok(GarbageCollectionResult {
             deleted_files,
             failed_to_delete_files,
+            pending_files,
         })
     }
 
@@ -248,6 +332,19 @@ impl ManagedDirectory {
         Ok(footer.crc() == crc)
     }
 
+    /// Returns the length of a managed file, footer included,
+    /// and the checksum recorded in its footer.
+    pub(crate) fn len_and_checksum(
+        &self,
+        path: &Path,
+    ) -> result::Result<(u64, u32), OpenReadError> {
+        let file_slice = self.directory.open_read(path)?;
+        let num_bytes = file_slice.len() as u64;
+        let (footer, _) = Footer::extract_footer(file_slice)
+            .map_err(|io_error| OpenReadError::wrap_io_error(io_error, path.to_path_buf()))?;
+        Ok((num_bytes, footer.crc()))
+    }
+
     /// List files for which checksum does not match content
     pub fn list_damaged(&self) -> result::Result<HashSet<PathBuf>, OpenReadError> {
         let mut managed_paths = self
@@ -257,7 +354,9 @@ impl ManagedDirectory {
             .managed_paths
             .clone();
 
+        // Meta files do not have a footer.
         managed_paths.remove(*META_FILEPATH);
+        managed_paths.retain(|path| meta_generation_from_filepath(path).is_none());
 
         let mut damaged_files = HashSet::new();
         for path in managed_paths {
@@ -269,6 +368,78 @@ impl ManagedDirectory {
     }
 }
 
+impl ManagedDirectory {
+    /// Returns the list of the files managed by the directory.
+    pub(crate) fn list_managed_files(&self) -> HashSet<PathBuf> {
+        self.meta_informations
+            .read()
+            .expect("Managed directory rlock poisoned in list managed files.")
+            .managed_paths
+            .clone()
+    }
+
+    /// Returns the number of bytes of a file, footer included.
+    pub(crate) fn file_num_bytes(&self, path: &Path) -> result::Result<usize, OpenReadError> {
+        Ok(self.directory.open_read(path)?.len())
+    }
+
+    /// Opens a file for read, and returns its footer together with its content.
+    pub(crate) fn open_read_with_footer(
+        &self,
+        path: &Path,
+    ) -> result::Result<(Footer, FileSlice), OpenReadError> {
+        let file_slice = self.directory.open_read(path)?;
+        let (footer, reader) = Footer::extract_footer(file_slice)
+            .map_err(|io_error| OpenReadError::wrap_io_error(io_error, path.to_path_buf()))?;
+        footer.is_compatible()?;
+        Ok((footer, reader))
+    }
+
+    /// Moves a file written by tantivy in another managed directory to this directory.
+    ///
+    /// The file is copied as is, footer included, and then deleted from `source`.
+    pub(crate) fn move_file_from(
+        &self,
+        source: &ManagedDirectory,
+        path: &Path,
+    ) -> crate::Result<()> {
+        self.register_file_as_managed(path)?;
+        {
+            let bytes = source.directory.open_read(path)?.read_bytes()?;
+            let mut write = self.directory.open_write(path)?;
+            write.write_all(bytes.as_slice())?;
+            write.terminate()?;
+        }
+        // The file is already in place, failing to clean up the source is not an error.
+        if let Err(err) = source.delete(path) {
+            warn!("Failed to delete {:?} after moving it: {:?}", path, err);
+        }
+        Ok(())
+    }
+
+    /// Opens a file for write, recording in its footer that it is written
+    /// in the format `format_name`.
+    ///
+    /// This is meant for the writers of custom segment formats.
+    /// See the [`codec`](../codec/index.html) module.
+    pub fn open_write_with_format(
+        &self,
+        path: &Path,
+        format_name: &str,
+    ) -> result::Result<WritePtr, OpenWriteError> {
+        self.register_file_as_managed(path)
+            .map_err(|io_error| OpenWriteError::wrap_io_error(io_error, path.to_path_buf()))?;
+        Ok(io::BufWriter::new(Box::new(FooterProxy::with_format(
+            self.directory
+                .open_write(path)?
+                .into_inner()
+                .map_err(|_| ())
+                .expect("buffer should be empty"),
+            Some(format_name.to_string()),
+        ))))
+    }
+}
+
 impl Directory for ManagedDirectory {
     fn get_file_handle(&self, path: &Path) -> Result<Box<dyn FileHandle>, OpenReadError> {
         let file_slice = self.open_read(path)?;
@@ -276,13 +447,18 @@ impl Directory for ManagedDirectory {
     }
 
     fn open_read(&self, path: &Path) -> result::Result<FileSlice, OpenReadError> {
-        let file_slice = self.directory.open_read(path)?;
-        let (footer, reader) = Footer::extract_footer(file_slice)
-            .map_err(|io_error| OpenReadError::wrap_io_error(io_error, path.to_path_buf()))?;
-        footer.is_compatible()?;
+        let (_footer, reader) = self.open_read_with_footer(path)?;
         Ok(reader)
     }
 
+    fn read_bytes_range(
+        &self,
+        path: &Path,
+        range: Range<usize>,
+    ) -> result::Result<OwnedBytes, OpenReadError> {
+        self.directory.read_bytes_range(path, range)
+    }
+
     fn open_write(&self, path: &Path) -> result::Result<WritePtr, OpenWriteError> {
         self.register_file_as_managed(path)
             .map_err(|io_error| OpenWriteError::wrap_io_error(io_error, path.to_path_buf()))?;
@@ -304,6 +480,10 @@ impl Directory for ManagedDirectory {
         self.directory.atomic_read(path)
     }
 
+    fn available_space(&self) -> io::Result<Option<u64>> {
+        self.directory.available_space()
+    }
+
     fn delete(&self, path: &Path) -> result::Result<(), DeleteError> {
         self.directory.delete(path)
     }
@@ -330,6 +510,72 @@ impl Clone for ManagedDirectory {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use crate::directory::{Directory, Lease, ManagedDirectory, RamDirectory};
+    use std::collections::HashSet;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    #[test]
+    fn test_managed_directory_gc_grace_period() -> crate::Result<()> {
+        let test_path: &'static Path = Path::new("some_path_for_test");
+        let mut managed_directory = ManagedDirectory::wrap(RamDirectory::create())?;
+        managed_directory.atomic_write(test_path, &[0u8, 1u8])?;
+        managed_directory.set_gc_grace_period(Duration::from_millis(50));
+
+        let gc_result = managed_directory.garbage_collect(HashSet::new)?;
+        assert!(gc_result.deleted_files.is_empty());
+        assert_eq!(gc_result.pending_files, vec![test_path.to_path_buf()]);
+        assert!(managed_directory.exists(test_path)?);
+
+        std::thread::sleep(Duration::from_millis(100));
+        let gc_result = managed_directory.garbage_collect(HashSet::new)?;
+        assert_eq!(gc_result.deleted_files, vec![test_path.to_path_buf()]);
+        assert!(!managed_directory.exists(test_path)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_managed_directory_gc_leased_files() -> crate::Result<()> {
+        let test_path: &'static Path = Path::new("some_path_for_test");
+        let mut managed_directory = ManagedDirectory::wrap(RamDirectory::create())?;
+        managed_directory.atomic_write(test_path, &[0u8, 1u8])?;
+        let leased_files: HashSet<PathBuf> = vec![test_path.to_path_buf()].into_iter().collect();
+        let lease = Lease::acquire(&managed_directory, leased_files, Duration::from_secs(60))?;
+
+        let gc_result = managed_directory.garbage_collect(HashSet::new)?;
+        assert!(gc_result.deleted_files.is_empty());
+        assert!(managed_directory.exists(test_path)?);
+
+        drop(lease);
+        let gc_result = managed_directory.garbage_collect(HashSet::new)?;
+        assert_eq!(gc_result.deleted_files, vec![test_path.to_path_buf()]);
+        assert!(!managed_directory.exists(test_path)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_managed_directory_gc_corrupted_leases() -> crate::Result<()> {
+        let test_path: &'static Path = Path::new("some_path_for_test");
+        let leases_path: &'static Path = Path::new(".tantivy-leases.json");
+        let mut managed_directory = ManagedDirectory::wrap(RamDirectory::create())?;
+        managed_directory.atomic_write(test_path, &[0u8, 1u8])?;
+        managed_directory.atomic_write(leases_path, b"{corrupted")?;
+
+        // The files may be leased: nothing is deleted.
+        let gc_result = managed_directory.garbage_collect(HashSet::new)?;
+        assert!(gc_result.deleted_files.is_empty());
+        assert!(managed_directory.exists(test_path)?);
+
+        managed_directory.atomic_write(leases_path, b"{}")?;
+        let gc_result = managed_directory.garbage_collect(HashSet::new)?;
+        assert_eq!(gc_result.deleted_files, vec![test_path.to_path_buf()]);
+        assert!(!managed_directory.exists(test_path)?);
+        Ok(())
+    }
+}
+
 #[cfg(feature = "mmap")]
 #[cfg(test)]
 mod tests_mmap_specific {
@@ -1,3 +1,4 @@
+use crate::common::HasLen;
 use crate::core::META_FILEPATH;
 use crate::directory::error::LockError;
 use crate::directory::error::{DeleteError, OpenDirectoryError, OpenReadError, OpenWriteError};
@@ -20,11 +21,16 @@ use std::fs::OpenOptions;
 use std::fs::{self, File};
 use std::io::{self, Seek, SeekFrom};
 use std::io::{BufWriter, Read, Write};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 use std::result;
-use std::sync::Arc;
-use std::sync::RwLock;
-use std::{collections::HashMap, ops::Deref};
+use std::sync::{Arc, Weak};
+use std::sync::{Mutex, MutexGuard, RwLock};
+use std::time::{Duration, Instant};
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Deref,
+};
 use tempfile::TempDir;
 
 /// Create a default io error given a string.
@@ -32,16 +38,20 @@ pub(crate) fn make_io_err(msg: String) -> io::Error {
     io::Error::new(io::ErrorKind::Other, msg)
 }
 
-/// Returns None iff the file exists, can be read, but is empty (and hence
-/// cannot be mmapped)
-fn open_mmap(full_path: &Path) -> result::Result<Option<Mmap>, OpenReadError> {
-    let file = File::open(full_path).map_err(|io_err| {
+fn open_file(full_path: &Path) -> result::Result<File, OpenReadError> {
+    File::open(full_path).map_err(|io_err| {
         if io_err.kind() == io::ErrorKind::NotFound {
             OpenReadError::FileDoesNotExist(full_path.to_path_buf())
         } else {
             OpenReadError::wrap_io_error(io_err, full_path.to_path_buf())
         }
-    })?;
+    })
+}
+
+/// Returns None iff the file exists, can be read, but is empty (and hence
+/// cannot be mmapped)
+fn open_mmap(full_path: &Path) -> result::Result<Option<Mmap>, OpenReadError> {
+    let file = open_file(full_path)?;
 
     let meta_data = file
         .metadata()
@@ -59,11 +69,97 @@ fn open_mmap(full_path: &Path) -> result::Result<Option<Mmap>, OpenReadError> {
     }
 }
 
+/// Defines how the `MmapDirectory` reads its files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReadMode {
+    /// Files are memory mapped.
+    ///
+    /// Files that cannot be mapped, e.g. because the address space of the process or
+    /// its number of mappings is exhausted, are read with positional reads instead.
+    Mmap,
+    /// Files are read with positional reads (`pread`), without being memory mapped.
+    ///
+    /// Every read copies the requested bytes in memory, but it does not consume
+    /// any address space, which is scarce on 32-bit targets.
+    Pread,
+}
+
+impl Default for ReadMode {
+    /// Files are memory mapped, except on 32-bit targets.
+    fn default() -> ReadMode {
+        if cfg!(target_pointer_width = "32") {
+            ReadMode::Pread
+        } else {
+            ReadMode::Mmap
+        }
+    }
+}
+
+#[cfg(unix)]
+fn read_exact_at(file: &File, buffer: &mut [u8], offset: u64) -> io::Result<()> {
+    use std::os::unix::fs::FileExt;
+    file.read_exact_at(buffer, offset)
+}
+
+#[cfg(windows)]
+fn read_exact_at(file: &File, mut buffer: &mut [u8], mut offset: u64) -> io::Result<()> {
+    use std::os::windows::fs::FileExt;
+    while !buffer.is_empty() {
+        match file.seek_read(buffer, offset) {
+            Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof)),
+            Ok(num_bytes) => {
+                buffer = &mut buffer[num_bytes..];
+                offset += num_bytes as u64;
+            }
+            Err(io_err) if io_err.kind() == io::ErrorKind::Interrupted => {}
+            Err(io_err) => return Err(io_err),
+        }
+    }
+    Ok(())
+}
+
+/// A file read with positional reads.
+#[derive(Debug)]
+struct PreadFile {
+    file: File,
+    len: usize,
+}
+
+impl PreadFile {
+    fn open(full_path: &Path) -> result::Result<PreadFile, OpenReadError> {
+        let file = open_file(full_path)?;
+        let len = file
+            .metadata()
+            .map_err(|io_err| OpenReadError::wrap_io_error(io_err, full_path.to_path_buf()))?
+            .len() as usize;
+        Ok(PreadFile { file, len })
+    }
+}
+
+#[derive(Clone, Debug)]
+struct PreadFileHandle(Arc<PreadFile>);
+
+impl HasLen for PreadFileHandle {
+    fn len(&self) -> usize {
+        self.0.len
+    }
+}
+
+impl FileHandle for PreadFileHandle {
+    fn read_bytes(&self, range: Range<usize>) -> io::Result<OwnedBytes> {
+        let mut buffer = vec![0u8; range.len()];
+        read_exact_at(&self.0.file, &mut buffer, range.start as u64)?;
+        Ok(OwnedBytes::new(buffer))
+    }
+}
+
 #[derive(Default, Clone, Debug, Serialize, Deserialize)]
 pub struct CacheCounters {
     // Number of time the cache prevents to call `mmap`
+    // (or to open a file read with positional reads)
     pub hit: usize,
     // Number of time tantivy had to call `mmap`
+    // (or to open a file read with positional reads)
     // as no entry was in the cache.
     pub miss: usize,
 }
@@ -72,11 +168,14 @@ pub struct CacheCounters {
 pub struct CacheInfo {
     pub counters: CacheCounters,
     pub mmapped: Vec<PathBuf>,
+    // Files read with positional reads.
+    pub pread: Vec<PathBuf>,
 }
 
 struct MmapCache {
     counters: CacheCounters,
     cache: HashMap<PathBuf, WeakArcBytes>,
+    pread_cache: HashMap<PathBuf, Weak<PreadFile>>,
 }
 
 impl Default for MmapCache {
@@ -84,6 +183,7 @@ impl Default for MmapCache {
         MmapCache {
             counters: CacheCounters::default(),
             cache: HashMap::new(),
+            pread_cache: HashMap::new(),
         }
     }
 }
@@ -94,6 +194,7 @@ impl MmapCache {
         CacheInfo {
             counters: self.counters.clone(),
             mmapped: paths,
+            pread: self.pread_cache.keys().cloned().collect(),
         }
     }
 
@@ -107,6 +208,26 @@ impl MmapCache {
         for key in keys_to_remove {
             self.cache.remove(&key);
         }
+        self.pread_cache
+            .retain(|_, pread_file_weakref| pread_file_weakref.upgrade().is_some());
+    }
+
+    fn get_cached_pread_file(&mut self, full_path: &Path) -> Option<PreadFileHandle> {
+        let pread_file = self.pread_cache.get(full_path)?.upgrade()?;
+        self.counters.hit += 1;
+        Some(PreadFileHandle(pread_file))
+    }
+
+    fn get_pread_file(&mut self, full_path: &Path) -> Result<PreadFileHandle, OpenReadError> {
+        if let Some(pread_file_handle) = self.get_cached_pread_file(full_path) {
+            return Ok(pread_file_handle);
+        }
+        self.pread_cache.remove(full_path);
+        self.counters.miss += 1;
+        let pread_file = Arc::new(PreadFile::open(full_path)?);
+        self.pread_cache
+            .insert(full_path.to_owned(), Arc::downgrade(&pread_file));
+        Ok(PreadFileHandle(pread_file))
     }
 
     // Returns None if the file exists but as a len of 0 (and hence is not mmappable).
@@ -129,6 +250,57 @@ impl MmapCache {
     }
 }
 
+/// Defines when the `MmapDirectory` calls `fsync` to persist the content of the files
+/// it writes, as well as the creation and the deletion of files.
+///
+/// Relaxing the policy can speed up indexing significantly, in particular on network
+/// filesystems, at the expense of the durability of the index in case of a crash of
+/// the operating system.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FsyncPolicy {
+    /// Files are synced each time they are flushed, and the directory
+    /// each time a file is created or deleted.
+    Always,
+    /// Files and the directory are only synced upon atomic writes,
+    /// i.e. when the `meta.json` file of a commit is written.
+    ///
+    /// Commits remain durable. A crash may only lose the files
+    /// written since the last commit.
+    OnCommit,
+    /// Files and the directory are synced upon flushes and atomic writes,
+    /// but at most once per given period.
+    ///
+    /// A crash may lose the commits of the last period.
+    Periodic(Duration),
+    /// Syncing is left to the operating system.
+    ///
+    /// A crash of the operating system may lose or corrupt the index.
+    Never,
+}
+
+impl Default for FsyncPolicy {
+    fn default() -> FsyncPolicy {
+        FsyncPolicy::Always
+    }
+}
+
+// Files and directory changes that have not been synced yet.
+struct PendingSync {
+    files: HashSet<PathBuf>,
+    directory: bool,
+    last_sync: Instant,
+}
+
+impl Default for PendingSync {
+    fn default() -> PendingSync {
+        PendingSync {
+            files: HashSet::new(),
+            directory: false,
+            last_sync: Instant::now(),
+        }
+    }
+}
+
 /// Directory storing data in files, read via mmap.
 ///
 /// The Mmap object are cached to limit the
@@ -141,6 +313,8 @@ impl MmapCache {
 /// depending on the implementation.
 ///
 /// On Windows the semantics are again different.
+///
+/// When files are synced to disk is defined by its [`FsyncPolicy`](./enum.FsyncPolicy.html).
 #[derive(Clone)]
 pub struct MmapDirectory {
     inner: Arc<MmapDirectoryInner>,
@@ -151,21 +325,143 @@ struct MmapDirectoryInner {
     mmap_cache: RwLock<MmapCache>,
     _temp_directory: Option<TempDir>,
     watcher: FileWatcher,
+    fsync_policy: FsyncPolicy,
+    read_mode: ReadMode,
+    pending_sync: Mutex<PendingSync>,
 }
 
 impl MmapDirectoryInner {
-    fn new(root_path: PathBuf, temp_directory: Option<TempDir>) -> MmapDirectoryInner {
+    fn new(
+        root_path: PathBuf,
+        temp_directory: Option<TempDir>,
+        fsync_policy: FsyncPolicy,
+        read_mode: ReadMode,
+    ) -> MmapDirectoryInner {
         MmapDirectoryInner {
             mmap_cache: Default::default(),
             _temp_directory: temp_directory,
             watcher: FileWatcher::new(&root_path.join(*META_FILEPATH)),
             root_path,
+            fsync_policy,
+            read_mode,
+            pending_sync: Default::default(),
         }
     }
 
     fn watch(&self, callback: WatchCallback) -> WatchHandle {
         self.watcher.watch(callback)
     }
+
+    /// Sync the root directory.
+    /// In certain FS, this is required to persistently create
+    /// a file.
+    fn sync_directory(&self) -> Result<(), io::Error> {
+        let mut open_opts = OpenOptions::new();
+
+        // Linux needs read to be set, otherwise returns EINVAL
+        // write must not be set, or it fails with EISDIR
+        open_opts.read(true);
+
+        // On Windows, opening a directory requires FILE_FLAG_BACKUP_SEMANTICS
+        // and calling sync_all() only works if write access is requested.
+        #[cfg(windows)]
+        {
+            use std::os::windows::fs::OpenOptionsExt;
+            use winapi::um::winbase;
+
+            open_opts
+                .write(true)
+                .custom_flags(winbase::FILE_FLAG_BACKUP_SEMANTICS);
+        }
+
+        let fd = open_opts.open(&self.root_path)?;
+        fd.sync_all()?;
+        Ok(())
+    }
+
+    fn pending_sync(&self) -> MutexGuard<'_, PendingSync> {
+        self.pending_sync
+            .lock()
+            .expect("Pending sync lock is poisoned")
+    }
+
+    /// Syncs the files and the directory changes that have not been synced yet.
+    fn sync_pending(&self) -> io::Result<()> {
+        let mut pending_sync = self.pending_sync();
+        for full_path in pending_sync.files.drain() {
+            // Files that were deleted in the meantime do not need to be synced.
+            match OpenOptions::new().write(true).open(&full_path) {
+                Ok(file) => file.sync_all()?,
+                Err(io_err) if io_err.kind() == io::ErrorKind::NotFound => {}
+                Err(io_err) => return Err(io_err),
+            }
+        }
+        if pending_sync.directory {
+            self.sync_directory()?;
+            pending_sync.directory = false;
+        }
+        pending_sync.last_sync = Instant::now();
+        Ok(())
+    }
+
+    fn sync_pending_if_period_elapsed(&self, period: Duration) -> io::Result<()> {
+        let period_elapsed = self.pending_sync().last_sync.elapsed() >= period;
+        if period_elapsed {
+            self.sync_pending()?;
+        }
+        Ok(())
+    }
+
+    /// Called when the file at `full_path` is flushed.
+    fn on_file_flush(&self, file: &File, full_path: &Path) -> io::Result<()> {
+        match self.fsync_policy {
+            FsyncPolicy::Always => file.sync_all(),
+            FsyncPolicy::OnCommit => {
+                self.pending_sync().files.insert(full_path.to_path_buf());
+                Ok(())
+            }
+            FsyncPolicy::Periodic(period) => {
+                self.pending_sync().files.insert(full_path.to_path_buf());
+                self.sync_pending_if_period_elapsed(period)
+            }
+            FsyncPolicy::Never => Ok(()),
+        }
+    }
+
+    /// Called when a file is created or deleted.
+    fn on_directory_change(&self) -> io::Result<()> {
+        match self.fsync_policy {
+            FsyncPolicy::Always => self.sync_directory(),
+            FsyncPolicy::OnCommit => {
+                self.pending_sync().directory = true;
+                Ok(())
+            }
+            FsyncPolicy::Periodic(period) => {
+                self.pending_sync().directory = true;
+                self.sync_pending_if_period_elapsed(period)
+            }
+            FsyncPolicy::Never => Ok(()),
+        }
+    }
+
+    /// Called before an atomic write.
+    ///
+    /// Atomic writes are used to write the `meta.json` file, which makes a commit visible.
+    /// The files of the commit need to be persisted before it.
+    fn before_atomic_write(&self) -> io::Result<()> {
+        match self.fsync_policy {
+            FsyncPolicy::OnCommit => self.sync_pending(),
+            FsyncPolicy::Always | FsyncPolicy::Periodic(_) | FsyncPolicy::Never => Ok(()),
+        }
+    }
+
+    /// Called after an atomic write.
+    fn after_atomic_write(&self) -> io::Result<()> {
+        match self.fsync_policy {
+            FsyncPolicy::Always | FsyncPolicy::OnCommit => self.sync_directory(),
+            FsyncPolicy::Periodic(_) | FsyncPolicy::Never => self.on_directory_change(),
+        }
+    }
 }
 
 impl fmt::Debug for MmapDirectory {
@@ -175,8 +471,13 @@ impl fmt::Debug for MmapDirectory {
 }
 
 impl MmapDirectory {
-    fn new(root_path: PathBuf, temp_directory: Option<TempDir>) -> MmapDirectory {
-        let inner = MmapDirectoryInner::new(root_path, temp_directory);
+    fn new(
+        root_path: PathBuf,
+        temp_directory: Option<TempDir>,
+        fsync_policy: FsyncPolicy,
+        read_mode: ReadMode,
+    ) -> MmapDirectory {
+        let inner = MmapDirectoryInner::new(root_path, temp_directory, fsync_policy, read_mode);
         MmapDirectory {
             inner: Arc::new(inner),
         }
@@ -191,6 +492,8 @@ impl MmapDirectory {
         Ok(MmapDirectory::new(
             tempdir.path().to_path_buf(),
             Some(tempdir),
+            FsyncPolicy::default(),
+            ReadMode::default(),
         ))
     }
 
@@ -199,6 +502,32 @@ impl MmapDirectory {
     /// Returns an error if the `directory_path` does not
     /// exist or if it is not a directory.
     pub fn open<P: AsRef<Path>>(directory_path: P) -> Result<MmapDirectory, OpenDirectoryError> {
+        MmapDirectory::open_with_fsync_policy(directory_path, FsyncPolicy::default())
+    }
+
+    /// Opens a MmapDirectory in a directory, syncing the files
+    /// it writes according to `fsync_policy`.
+    ///
+    /// Returns an error if the `directory_path` does not
+    /// exist or if it is not a directory.
+    pub fn open_with_fsync_policy<P: AsRef<Path>>(
+        directory_path: P,
+        fsync_policy: FsyncPolicy,
+    ) -> Result<MmapDirectory, OpenDirectoryError> {
+        MmapDirectory::open_with_options(directory_path, fsync_policy, ReadMode::default())
+    }
+
+    /// Opens a MmapDirectory in a directory, syncing the files
+    /// it writes according to `fsync_policy`, and reading its files
+    /// according to `read_mode`.
+    ///
+    /// Returns an error if the `directory_path` does not
+    /// exist or if it is not a directory.
+    pub fn open_with_options<P: AsRef<Path>>(
+        directory_path: P,
+        fsync_policy: FsyncPolicy,
+        read_mode: ReadMode,
+    ) -> Result<MmapDirectory, OpenDirectoryError> {
         let directory_path: &Path = directory_path.as_ref();
         if !directory_path.exists() {
             Err(OpenDirectoryError::DoesNotExist(PathBuf::from(
@@ -209,7 +538,12 @@ impl MmapDirectory {
                 directory_path,
             )))
         } else {
-            Ok(MmapDirectory::new(PathBuf::from(directory_path), None))
+            Ok(MmapDirectory::new(
+                PathBuf::from(directory_path),
+                None,
+                fsync_policy,
+                read_mode,
+            ))
         }
     }
 
@@ -219,31 +553,14 @@ impl MmapDirectory {
         self.inner.root_path.join(relative_path)
     }
 
-    /// Sync the root directory.
-    /// In certain FS, this is required to persistently create
-    /// a file.
-    fn sync_directory(&self) -> Result<(), io::Error> {
-        let mut open_opts = OpenOptions::new();
-
-        // Linux needs read to be set, otherwise returns EINVAL
-        // write must not be set, or it fails with EISDIR
-        open_opts.read(true);
-
-        // On Windows, opening a directory requires FILE_FLAG_BACKUP_SEMANTICS
-        // and calling sync_all() only works if write access is requested.
-        #[cfg(windows)]
-        {
-            use std::os::windows::fs::OpenOptionsExt;
-            use winapi::um::winbase;
-
-            open_opts
-                .write(true)
-                .custom_flags(winbase::FILE_FLAG_BACKUP_SEMANTICS);
-        }
+    /// Returns the policy defining when files are synced to disk.
+    pub fn fsync_policy(&self) -> FsyncPolicy {
+        self.inner.fsync_policy
+    }
 
-        let fd = open_opts.open(&self.inner.root_path)?;
-        fd.sync_all()?;
-        Ok(())
+    /// Returns how the files of the directory are read.
+    pub fn read_mode(&self) -> ReadMode {
+        self.inner.read_mode
     }
 
     /// Returns some statistical information
@@ -281,29 +598,37 @@ impl Drop for ReleaseLockFile {
 }
 
 /// This Write wraps a File, but has the specificity of
-/// call `sync_all` on flush.
-struct SafeFileWriter(File);
+/// syncing it on flush, as defined by the `FsyncPolicy` of its directory.
+struct SafeFileWriter {
+    file: File,
+    full_path: PathBuf,
+    directory: Arc<MmapDirectoryInner>,
+}
 
 impl SafeFileWriter {
-    fn new(file: File) -> SafeFileWriter {
-        SafeFileWriter(file)
+    fn new(file: File, full_path: PathBuf, directory: Arc<MmapDirectoryInner>) -> SafeFileWriter {
+        SafeFileWriter {
+            file,
+            full_path,
+            directory,
+        }
     }
 }
 
 impl Write for SafeFileWriter {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.0.write(buf)
+        self.file.write(buf)
     }
 
     fn flush(&mut self) -> io::Result<()> {
-        self.0.flush()?;
-        self.0.sync_all()
+        self.file.flush()?;
+        self.directory.on_file_flush(&self.file, &self.full_path)
     }
 }
 
 impl Seek for SafeFileWriter {
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
-        self.0.seek(pos)
+        self.file.seek(pos)
     }
 }
 
@@ -358,15 +683,32 @@ impl Directory for MmapDirectory {
             OpenReadError::wrap_io_error(io_err, path.to_path_buf())
         })?;
 
-        let owned_bytes = mmap_cache
-            .get_mmap(&full_path)?
-            .map(|mmap_arc| {
-                let mmap_arc_obj = MmapArc(mmap_arc);
-                OwnedBytes::new(mmap_arc_obj)
-            })
-            .unwrap_or_else(OwnedBytes::empty);
-
-        Ok(Box::new(owned_bytes))
+        if self.inner.read_mode == ReadMode::Pread {
+            return Ok(Box::new(mmap_cache.get_pread_file(&full_path)?));
+        }
+        // The file may have failed to be mmapped before.
+        if let Some(pread_file_handle) = mmap_cache.get_cached_pread_file(&full_path) {
+            return Ok(Box::new(pread_file_handle));
+        }
+        match mmap_cache.get_mmap(&full_path) {
+            Ok(mmap_opt) => {
+                let owned_bytes = mmap_opt
+                    .map(|mmap_arc| {
+                        let mmap_arc_obj = MmapArc(mmap_arc);
+                        OwnedBytes::new(mmap_arc_obj)
+                    })
+                    .unwrap_or_else(OwnedBytes::empty);
+                Ok(Box::new(owned_bytes))
+            }
+            Err(OpenReadError::IoError { io_error, .. }) => {
+                warn!(
+                    "Failed to mmap {:?} ({}), reading it with positional reads instead.",
+                    full_path, io_error
+                );
+                Ok(Box::new(mmap_cache.get_pread_file(&full_path)?))
+            }
+            Err(err) => Err(err),
+        }
     }
 
     /// Any entry associated to the path in the mmap will be
@@ -374,10 +716,13 @@ impl Directory for MmapDirectory {
     fn delete(&self, path: &Path) -> result::Result<(), DeleteError> {
         let full_path = self.resolve_path(path);
         match fs::remove_file(&full_path) {
-            Ok(_) => self.sync_directory().map_err(|e| DeleteError::IoError {
-                io_error: e,
-                filepath: path.to_path_buf(),
-            }),
+            Ok(_) => self
+                .inner
+                .on_directory_change()
+                .map_err(|e| DeleteError::IoError {
+                    io_error: e,
+                    filepath: path.to_path_buf(),
+                }),
             Err(e) => {
                 if e.kind() == io::ErrorKind::NotFound {
                     Err(DeleteError::FileDoesNotExist(path.to_owned()))
@@ -403,7 +748,7 @@ impl Directory for MmapDirectory {
         let open_res = OpenOptions::new()
             .write(true)
             .create_new(true)
-            .open(full_path);
+            .open(&full_path);
 
         let mut file = open_res.map_err(|io_err| {
             if io_err.kind() == io::ErrorKind::AlreadyExists {
@@ -419,10 +764,11 @@ impl Directory for MmapDirectory {
 
         // Apparetntly, on some filesystem syncing the parent
         // directory is required.
-        self.sync_directory()
+        self.inner
+            .on_directory_change()
             .map_err(|io_err| OpenWriteError::wrap_io_error(io_err, path.to_path_buf()))?;
 
-        let writer = SafeFileWriter::new(file);
+        let writer = SafeFileWriter::new(file, full_path, self.inner.clone());
         Ok(BufWriter::new(Box::new(writer)))
     }
 
@@ -449,8 +795,13 @@ impl Directory for MmapDirectory {
     fn atomic_write(&self, path: &Path, content: &[u8]) -> io::Result<()> {
         debug!("Atomic Write {:?}", path);
         let full_path = self.resolve_path(path);
+        self.inner.before_atomic_write()?;
         atomic_write(&full_path, content)?;
-        self.sync_directory()
+        self.inner.after_atomic_write()
+    }
+
+    fn available_space(&self) -> io::Result<Option<u64>> {
+        fs2::available_space(&self.inner.root_path).map(Some)
     }
 
     fn acquire_lock(&self, lock: &Lock) -> Result<DirectoryLock, LockError> {
@@ -627,4 +978,96 @@ mod tests {
         }
         assert!(mmap_directory.get_cache_info().mmapped.is_empty());
     }
+
+    #[test]
+    fn test_fsync_policy_on_commit() -> crate::Result<()> {
+        let tempdir = TempDir::new().unwrap();
+        let mmap_directory =
+            MmapDirectory::open_with_fsync_policy(tempdir.path(), FsyncPolicy::OnCommit)?;
+        assert_eq!(mmap_directory.fsync_policy(), FsyncPolicy::OnCommit);
+        let path = Path::new("segment_file");
+        let mut write = mmap_directory.open_write(path)?;
+        write.write_all(b"abc")?;
+        write.flush()?;
+        {
+            let pending_sync = mmap_directory.inner.pending_sync();
+            assert!(pending_sync.directory);
+            assert!(pending_sync
+                .files
+                .contains(&mmap_directory.resolve_path(path)));
+        }
+        mmap_directory.atomic_write(Path::new("meta.json"), b"{}")?;
+        let pending_sync = mmap_directory.inner.pending_sync();
+        assert!(!pending_sync.directory);
+        assert!(pending_sync.files.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_with_relaxed_fsync_policies() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        for &fsync_policy in &[
+            FsyncPolicy::OnCommit,
+            FsyncPolicy::Periodic(Duration::from_millis(10)),
+            FsyncPolicy::Never,
+        ] {
+            let tempdir = TempDir::new().unwrap();
+            {
+                let mmap_directory =
+                    MmapDirectory::open_with_fsync_policy(tempdir.path(), fsync_policy)?;
+                let index =
+                    Index::create(mmap_directory, schema.clone(), IndexSettings::default())?;
+                let mut index_writer = index.writer_for_tests()?;
+                index_writer.add_document(doc!(text_field => "abc"));
+                index_writer.commit()?;
+                index_writer.add_document(doc!(text_field => "def"));
+                index_writer.commit()?;
+            }
+            let index = Index::open_in_dir(tempdir.path())?;
+            assert_eq!(index.reader()?.searcher().num_docs(), 2);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_pread_read_mode() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let tempdir = TempDir::new().unwrap();
+        let directory = MmapDirectory::open_with_options(
+            tempdir.path(),
+            FsyncPolicy::default(),
+            ReadMode::Pread,
+        )?;
+        assert_eq!(directory.read_mode(), ReadMode::Pread);
+        let index = Index::create(
+            directory.clone(),
+            schema_builder.build(),
+            IndexSettings::default(),
+        )?;
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field => "abc"));
+        index_writer.add_document(doc!(text_field => "abc def"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query = crate::query::TermQuery::new(
+            crate::Term::from_field_text(text_field, "abc"),
+            crate::schema::IndexRecordOption::Basic,
+        );
+        assert_eq!(searcher.search(&query, &crate::collector::Count)?, 2);
+        let cache_info = directory.get_cache_info();
+        assert!(cache_info.mmapped.is_empty());
+        assert!(!cache_info.pread.is_empty());
+
+        let path = Path::new("file");
+        let mut write = directory.open_write(path)?;
+        write.write_all(b"abcdef")?;
+        write.flush()?;
+        let file_slice = directory.open_read(path)?;
+        assert_eq!(file_slice.len(), 6);
+        assert_eq!(file_slice.read_bytes_slice(2..5)?.as_slice(), b"cde");
+        Ok(())
+    }
 }
@@ -7,11 +7,15 @@ WORM directory abstraction.
 #[cfg(feature = "mmap")]
 mod mmap_directory;
 
+#[cfg(feature = "encryption")]
+mod encrypted_directory;
+
 mod directory;
 mod directory_lock;
 mod file_slice;
 mod file_watcher;
 mod footer;
+mod lease;
 mod managed_directory;
 mod owned_bytes;
 mod ram_directory;
@@ -22,9 +26,14 @@ pub mod error;
 
 pub use self::directory::DirectoryLock;
 pub use self::directory::{Directory, DirectoryClone};
-pub use self::directory_lock::{Lock, INDEX_WRITER_LOCK, META_LOCK};
-pub(crate) use self::file_slice::{ArcBytes, WeakArcBytes};
+pub use self::directory_lock::{Lock, INDEX_WRITER_LOCK, LEASES_LOCK, META_LOCK};
+#[cfg(feature = "encryption")]
+pub use self::encrypted_directory::{EncryptedDirectory, KeyProvider, StaticKeyProvider};
+pub(crate) use self::file_slice::{ArcBytes, LazyFileSlice, WeakArcBytes};
 pub use self::file_slice::{FileHandle, FileSlice};
+#[cfg(test)]
+pub(crate) use self::footer::Footer;
+pub use self::lease::Lease;
 pub use self::owned_bytes::OwnedBytes;
 pub use self::ram_directory::RamDirectory;
 pub use self::watch_event_router::{WatchCallback, WatchCallbackList, WatchHandle};
@@ -45,10 +54,13 @@ pub struct GarbageCollectionResult {
     /// This is not considered a bug, the file will simply be deleted
     /// in the next GC.
     pub failed_to_delete_files: Vec<PathBuf>,
+    /// List of files that are not used anymore, but were not deleted in this cycle,
+    /// as their grace period has not elapsed yet.
+    pub pending_files: Vec<PathBuf>,
 }
 
 #[cfg(feature = "mmap")]
-pub use self::mmap_directory::MmapDirectory;
+pub use self::mmap_directory::{FsyncPolicy, MmapDirectory, ReadMode};
 
 pub use self::managed_directory::ManagedDirectory;
 
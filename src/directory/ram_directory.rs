@@ -3,10 +3,12 @@ use crate::directory::AntiCallToken;
 use crate::directory::WatchCallbackList;
 use crate::directory::{Directory, FileSlice, WatchCallback, WatchHandle};
 use crate::directory::{TerminatingWrite, WritePtr};
+use crate::directory::{INDEX_WRITER_LOCK, LEASES_LOCK, META_LOCK};
 use crate::{common::HasLen, core::META_FILEPATH};
 use fail::fail_point;
 use std::collections::HashMap;
 use std::fmt;
+use std::fs;
 use std::io::{self, BufWriter, Cursor, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 use std::result;
@@ -61,6 +63,17 @@ impl Seek for VecWriter {
 
 impl Write for VecWriter {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Fail early rather than buffering a file that will not fit in the directory.
+        let num_bytes = self
+            .data
+            .get_ref()
+            .len()
+            .max(self.data.position() as usize + buf.len());
+        self.shared_directory
+            .fs
+            .read()
+            .unwrap()
+            .check_capacity(&self.path, num_bytes)?;
         self.is_flushed = false;
         self.data.write_all(buf)?;
         Ok(buf.len())
@@ -69,6 +82,7 @@ impl Write for VecWriter {
     fn flush(&mut self) -> io::Result<()> {
         self.is_flushed = true;
         let mut fs = self.shared_directory.fs.write().unwrap();
+        fs.check_capacity(&self.path, self.data.get_ref().len())?;
         fs.write(self.path.clone(), self.data.get_ref());
         Ok(())
     }
@@ -84,6 +98,7 @@ impl TerminatingWrite for VecWriter {
 struct InnerDirectory {
     fs: HashMap<PathBuf, FileSlice>,
     watch_router: WatchCallbackList,
+    max_num_bytes: Option<usize>,
 }
 
 impl InnerDirectory {
@@ -117,6 +132,36 @@ impl InnerDirectory {
     fn total_mem_usage(&self) -> usize {
         self.fs.values().map(|f| f.len()).sum()
     }
+
+    /// Checks that the file `path` can be (re)written with `num_bytes` bytes
+    /// without exceeding the capacity of the directory.
+    fn check_capacity(&self, path: &Path, num_bytes: usize) -> io::Result<()> {
+        let max_num_bytes = if let Some(max_num_bytes) = self.max_num_bytes {
+            max_num_bytes
+        } else {
+            return Ok(());
+        };
+        let replaced_num_bytes = self.fs.get(path).map(|f| f.len()).unwrap_or(0);
+        let required_num_bytes = self.total_mem_usage() - replaced_num_bytes + num_bytes;
+        if required_num_bytes > max_num_bytes {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!(
+                    "Writing {:?} requires {} bytes, exceeding the capacity of the RamDirectory \
+                     of {} bytes",
+                    path, required_num_bytes, max_num_bytes
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+// Lock files only make sense for the process holding the locks.
+fn is_lock_file(path: &Path) -> bool {
+    [&*INDEX_WRITER_LOCK, &*META_LOCK, &*LEASES_LOCK]
+        .iter()
+        .any(|lock| lock.filepath == path)
 }
 
 impl fmt::Debug for RamDirectory {
@@ -141,6 +186,22 @@ impl RamDirectory {
         Self::default()
     }
 
+    /// Creates a `RamDirectory` holding at most `max_num_bytes` bytes of files.
+    ///
+    /// Writes that would exceed this capacity fail with an io error.
+    /// The capacity is also reported as the available space of the directory,
+    /// so that the `IndexWriter` can fail early with a disk space reserve.
+    pub fn create_with_max_capacity(max_num_bytes: usize) -> RamDirectory {
+        let directory = RamDirectory::create();
+        directory.fs.write().unwrap().max_num_bytes = Some(max_num_bytes);
+        directory
+    }
+
+    /// Returns the maximum number of bytes of files of the directory, if it is bounded.
+    pub fn max_capacity(&self) -> Option<usize> {
+        self.fs.read().unwrap().max_num_bytes
+    }
+
     /// Returns the sum of the size of the different files
     /// in the RamDirectory.
     pub fn total_mem_usage(&self) -> usize {
@@ -162,6 +223,85 @@ impl RamDirectory {
         }
         Ok(())
     }
+
+    /// Returns a copy of the files of the RamDirectory, lock files excluded, with
+    /// `meta.json` last.
+    ///
+    /// Together with [`import_files`](#method.import_files), this makes it possible to
+    /// save an in-memory index in any storage, e.g. IndexedDB when running in a browser.
+    pub fn export_files(&self) -> crate::Result<Vec<(PathBuf, Vec<u8>)>> {
+        let inner = self.fs.read().unwrap();
+        let mut paths: Vec<&PathBuf> = inner.fs.keys().filter(|path| !is_lock_file(path)).collect();
+        paths.sort_by_key(|path| path.as_path() == *META_FILEPATH);
+        let mut files = Vec::with_capacity(paths.len());
+        for path in paths {
+            let data = inner.fs[path].read_bytes()?;
+            files.push((path.clone(), data.as_slice().to_vec()));
+        }
+        Ok(files)
+    }
+
+    /// Loads files in the RamDirectory, e.g. files returned by
+    /// [`export_files`](#method.export_files).
+    ///
+    /// Files of the RamDirectory with the same names are overwritten, and lock files
+    /// are skipped. Returns an error if the files do not fit in the capacity of the
+    /// RamDirectory, in which case they may be loaded partially.
+    pub fn import_files<I: IntoIterator<Item = (PathBuf, Vec<u8>)>>(
+        &self,
+        files: I,
+    ) -> crate::Result<()> {
+        let mut has_meta = false;
+        for (path, data) in files {
+            if is_lock_file(&path) {
+                continue;
+            }
+            let mut inner = self.fs.write().unwrap();
+            inner.check_capacity(&path, data.len())?;
+            has_meta |= path == *META_FILEPATH;
+            inner.write(path, &data);
+        }
+        if has_meta {
+            let _ = self.fs.write().unwrap().watch_router.broadcast();
+        }
+        Ok(())
+    }
+
+    /// Writes a copy of the files of the RamDirectory in the filesystem directory
+    /// `directory_path`, which is created if needed, to checkpoint an in-memory index.
+    ///
+    /// Each file is written to a temporary file, which is then renamed, and `meta.json` is
+    /// written last. An interrupted checkpoint therefore leaves the index of the previous
+    /// checkpoint in place. The files of `directory_path` that are not in the RamDirectory
+    /// are left untouched, and lock files are not persisted.
+    pub fn persist_to<P: AsRef<Path>>(&self, directory_path: P) -> crate::Result<()> {
+        let directory_path = directory_path.as_ref();
+        fs::create_dir_all(directory_path)?;
+        for (path, data) in self.export_files()? {
+            let dest_path = directory_path.join(path);
+            let mut tmp_path = dest_path.clone().into_os_string();
+            tmp_path.push(".tmp");
+            fs::write(&tmp_path, &data)?;
+            fs::rename(&tmp_path, &dest_path)?;
+        }
+        Ok(())
+    }
+
+    /// Loads the files of the filesystem directory `directory_path` in the RamDirectory,
+    /// e.g. to restore an index checkpointed with [`persist_to`](#method.persist_to).
+    ///
+    /// See [`import_files`](#method.import_files).
+    pub fn load_from<P: AsRef<Path>>(&self, directory_path: P) -> crate::Result<()> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(directory_path)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_file() {
+                continue;
+            }
+            files.push((PathBuf::from(entry.file_name()), fs::read(entry.path())?));
+        }
+        self.import_files(files)
+    }
 }
 
 impl Directory for RamDirectory {
@@ -226,7 +366,10 @@ impl Directory for RamDirectory {
         )));
         let path_buf = PathBuf::from(path);
 
-        self.fs.write().unwrap().write(path_buf, data);
+        let mut fs = self.fs.write().unwrap();
+        fs.check_capacity(path, data.len())?;
+        fs.write(path_buf, data);
+        drop(fs);
 
         if path == *META_FILEPATH {
             let _ = self.fs.write().unwrap().watch_router.broadcast();
@@ -234,6 +377,13 @@ impl Directory for RamDirectory {
         Ok(())
     }
 
+    fn available_space(&self) -> io::Result<Option<u64>> {
+        let fs = self.fs.read().unwrap();
+        Ok(fs
+            .max_num_bytes
+            .map(|max_num_bytes| max_num_bytes.saturating_sub(fs.total_mem_usage()) as u64))
+    }
+
     fn watch(&self, watch_callback: WatchCallback) -> crate::Result<WatchHandle> {
         Ok(self.fs.write().unwrap().watch(watch_callback))
     }
@@ -242,7 +392,10 @@ impl Directory for RamDirectory {
 #[cfg(test)]
 mod tests {
     use super::RamDirectory;
-    use crate::Directory;
+    use crate::collector::Count;
+    use crate::query::AllQuery;
+    use crate::schema::{Schema, TEXT};
+    use crate::{Directory, Index, IndexSettings};
     use std::io::Write;
     use std::path::Path;
 
@@ -262,4 +415,56 @@ mod tests {
         assert_eq!(directory_copy.atomic_read(path_atomic).unwrap(), msg_atomic);
         assert_eq!(directory_copy.atomic_read(path_seq).unwrap(), msg_seq);
     }
+
+    #[test]
+    fn test_max_capacity() {
+        let path_a = Path::new("a");
+        let path_b = Path::new("b");
+        let directory = RamDirectory::create_with_max_capacity(10);
+        assert_eq!(directory.max_capacity(), Some(10));
+        assert!(directory.atomic_write(path_a, b"123456").is_ok());
+        assert_eq!(directory.available_space().unwrap(), Some(4));
+        assert!(directory.atomic_write(path_b, b"123456").is_err());
+        assert!(!directory.exists(path_b).unwrap());
+        // Overwriting a file only requires the space of the difference.
+        assert!(directory.atomic_write(path_a, b"1234567890").is_ok());
+        assert_eq!(directory.total_mem_usage(), 10);
+        directory.delete(path_a).unwrap();
+        let mut wrt = directory.open_write(path_b).unwrap();
+        assert!(wrt.write_all(b"12345").is_ok());
+        assert!(wrt.write_all(b"123456").and_then(|_| wrt.flush()).is_err());
+        assert_eq!(RamDirectory::create().available_space().unwrap(), None);
+    }
+
+    #[test]
+    fn test_persist_to_and_load_from() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let directory = RamDirectory::create();
+        let index = Index::create(
+            directory.clone(),
+            schema_builder.build(),
+            IndexSettings::default(),
+        )?;
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text => "a"));
+        index_writer.commit()?;
+        let checkpoint_dir = tempfile::tempdir()?;
+        directory.persist_to(checkpoint_dir.path())?;
+        index_writer.add_document(doc!(text => "b"));
+        index_writer.commit()?;
+        drop(index_writer);
+
+        let restored_directory = RamDirectory::create();
+        restored_directory.load_from(checkpoint_dir.path())?;
+        let restored_index = Index::open(restored_directory)?;
+        let searcher = restored_index.reader()?.searcher();
+        assert_eq!(searcher.search(&AllQuery, &Count)?, 1);
+        // Lock files are not persisted.
+        assert!(restored_index.writer_for_tests().is_ok());
+
+        let small_directory = RamDirectory::create_with_max_capacity(10);
+        assert!(small_directory.load_from(checkpoint_dir.path()).is_err());
+        Ok(())
+    }
 }
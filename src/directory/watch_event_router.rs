@@ -1,5 +1,6 @@
 use futures::channel::oneshot;
-use futures::{Future, TryFutureExt};
+use futures::TryFutureExt;
+use std::future::Future;
 use std::sync::Arc;
 use std::sync::RwLock;
 use std::sync::Weak;
@@ -83,6 +84,14 @@ impl WatchCallbackList {
             let _ = sender.send(());
             return result;
         }
+        // Threads are not available on wasm: the callbacks are called right away.
+        if cfg!(target_arch = "wasm32") {
+            for callback in callbacks {
+                callback.call();
+            }
+            let _ = sender.send(());
+            return result;
+        }
         let spawn_res = std::thread::Builder::new()
             .name("watch-callbacks".to_string())
             .spawn(move || {
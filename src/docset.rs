@@ -83,15 +83,25 @@ pub trait DocSet: Send {
     /// length of the docset.
     fn size_hint(&self) -> u32;
 
+    /// Returns an estimate of the cost of going through the entire docset,
+    /// e.g. the number of postings that need to be decoded.
+    ///
+    /// Contrary to `.size_hint()`, which estimates the number of matching documents,
+    /// this accounts for the work done by the docsets combined by this one.
+    /// It is used to pick the order in which docsets are intersected.
+    ///
+    /// By default, the cost is the size hint.
+    fn cost(&self) -> u64 {
+        self.size_hint() as u64
+    }
+
     /// Returns the number documents matching.
     /// Calling this method consumes the `DocSet`.
     fn count(&mut self, delete_bitset: &DeleteBitSet) -> u32 {
         let mut count = 0u32;
         let mut doc = self.doc();
         while doc != TERMINATED {
-            if !delete_bitset.is_deleted(doc) {
-                count += 1u32;
-            }
+            count += delete_bitset.is_alive(doc) as u32;
             doc = self.advance();
         }
         count
@@ -130,6 +140,10 @@ impl<'a> DocSet for &'a mut dyn DocSet {
         (**self).size_hint()
     }
 
+    fn cost(&self) -> u64 {
+        (**self).cost()
+    }
+
     fn count(&mut self, delete_bitset: &DeleteBitSet) -> u32 {
         (**self).count(delete_bitset)
     }
@@ -160,6 +174,11 @@ impl<TDocSet: DocSet + ?Sized> DocSet for Box<TDocSet> {
         unboxed.size_hint()
     }
 
+    fn cost(&self) -> u64 {
+        let unboxed: &TDocSet = self.borrow();
+        unboxed.cost()
+    }
+
     fn count(&mut self, delete_bitset: &DeleteBitSet) -> u32 {
         let unboxed: &mut TDocSet = self.borrow_mut();
         unboxed.count(delete_bitset)
@@ -1,10 +1,13 @@
 //! Definition of Tantivy's error and result.
 
+use std::any::Any;
 use std::io;
 
+use crate::core::SegmentId;
 use crate::directory::error::{Incompatibility, LockError};
 use crate::fastfield::FastFieldNotAvailableError;
 use crate::query;
+use crate::store::Compressor;
 use crate::{
     directory::error::{OpenDirectoryError, OpenReadError, OpenWriteError},
     schema,
@@ -50,7 +53,146 @@ impl fmt::Debug for DataCorruption {
     }
 }
 
+/// Context attached to an error, as it is propagated.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ErrorContext {
+    /// The error happened while processing the segment.
+    Segment(SegmentId),
+    /// The error happened while processing the file.
+    File(PathBuf),
+    /// The error happened during the described operation.
+    Operation(String),
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorContext::Segment(segment_id) => {
+                write!(f, "In segment '{}'", segment_id.uuid_string())
+            }
+            ErrorContext::File(filepath) => write!(f, "In file '{}'", filepath.display()),
+            ErrorContext::Operation(operation) => write!(f, "While {}", operation),
+        }
+    }
+}
+
+/// Error raised when creating an index whose `IndexSettings` are inconsistent
+/// with its schema, or with the features tantivy was compiled with.
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum SettingsError {
+    /// The field the index is sorted by does not exist in the schema.
+    #[error("The index is sorted by the field {0:?}, which does not exist in the schema")]
+    SortFieldNotFound(String),
+    /// The field the index is sorted by is not a single-valued fast field of
+    /// type u64, i64, f64 or date.
+    #[error(
+        "The index is sorted by the field {0:?}, which needs to be a single-valued u64, i64, \
+         f64 or date fast field"
+    )]
+    InvalidSortField(String),
+    /// The compressor of the doc store requires a feature flag that is not enabled.
+    #[error("The {compressor:?} compressor requires the {feature:?} feature flag")]
+    CompressorNotEnabled {
+        /// The compressor of the doc store.
+        compressor: Compressor,
+        /// The feature flag enabling the compressor.
+        feature: &'static str,
+    },
+    /// A text field of the schema uses a tokenizer that is not registered.
+    #[error("The field {field:?} uses the tokenizer {tokenizer:?}, which is not registered")]
+    TokenizerNotFound {
+        /// Name of the field.
+        field: String,
+        /// Name of the tokenizer.
+        tokenizer: String,
+    },
+}
+
+/// Machine-readable code of a `TantivyError`.
+///
+/// Codes are stable across versions, and are not affected by the context
+/// attached to the error. See [`TantivyError::code()`](./enum.TantivyError.html#method.code).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    /// See `TantivyError::OpenDirectoryError`.
+    OpenDirectory,
+    /// See `TantivyError::OpenReadError`.
+    OpenRead,
+    /// See `TantivyError::OpenWriteError`.
+    OpenWrite,
+    /// See `TantivyError::IndexAlreadyExists`.
+    IndexAlreadyExists,
+    /// See `TantivyError::LockFailure`.
+    LockFailure,
+    /// See `TantivyError::IoError`.
+    Io,
+    /// See `TantivyError::DataCorruption`.
+    DataCorruption,
+    /// See `TantivyError::Poisoned`.
+    Poisoned,
+    /// See `TantivyError::InvalidArgument`.
+    InvalidArgument,
+    /// See `TantivyError::ErrorInThread`.
+    ErrorInThread,
+    /// See `TantivyError::WorkerPanicked`.
+    WorkerPanicked,
+    /// See `TantivyError::IndexBuilderMissingArgument`.
+    IndexBuilderMissingArgument,
+    /// See `TantivyError::SchemaError`.
+    Schema,
+    /// See `TantivyError::SystemError`.
+    System,
+    /// See `TantivyError::IncompatibleIndex`.
+    IncompatibleIndex,
+    /// See `TantivyError::MemoryBudgetExceeded`.
+    MemoryBudgetExceeded,
+    /// See `TantivyError::ExpansionBudgetExceeded`.
+    ExpansionBudgetExceeded,
+    /// See `TantivyError::DiskFull`.
+    DiskFull,
+    /// See `TantivyError::InvalidSettings`.
+    InvalidSettings,
+}
+
+impl ErrorCode {
+    /// Returns the code as a string, e.g. `"open_read"`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::OpenDirectory => "open_directory",
+            ErrorCode::OpenRead => "open_read",
+            ErrorCode::OpenWrite => "open_write",
+            ErrorCode::IndexAlreadyExists => "index_already_exists",
+            ErrorCode::LockFailure => "lock_failure",
+            ErrorCode::Io => "io",
+            ErrorCode::DataCorruption => "data_corruption",
+            ErrorCode::Poisoned => "poisoned",
+            ErrorCode::InvalidArgument => "invalid_argument",
+            ErrorCode::ErrorInThread => "error_in_thread",
+            ErrorCode::WorkerPanicked => "worker_panicked",
+            ErrorCode::IndexBuilderMissingArgument => "index_builder_missing_argument",
+            ErrorCode::Schema => "schema",
+            ErrorCode::System => "system",
+            ErrorCode::IncompatibleIndex => "incompatible_index",
+            ErrorCode::MemoryBudgetExceeded => "memory_budget_exceeded",
+            ErrorCode::ExpansionBudgetExceeded => "expansion_budget_exceeded",
+            ErrorCode::DiskFull => "disk_full",
+            ErrorCode::InvalidSettings => "invalid_settings",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 /// The library's error enum
+///
+/// Errors may be wrapped, as they are propagated, into a `WithContext` error
+/// recording the segment or the file they relate to.
+/// [`.code()`](#method.code), [`.is_retryable()`](#method.is_retryable) and
+/// [`.root_cause()`](#method.root_cause) look through these contexts.
 #[derive(Debug, Error)]
 pub enum TantivyError {
     /// Failed to open the directory.
@@ -67,7 +209,7 @@ pub enum TantivyError {
     IndexAlreadyExists,
     /// Failed to acquire file lock
     #[error("Failed to acquire Lockfile: {0:?}. {1:?}")]
-    LockFailure(LockError, Option<String>),
+    LockFailure(#[source] LockError, Option<String>),
     /// IO Error.
     #[error("An IO error occurred: '{0}'")]
     IoError(#[from] io::Error),
@@ -83,6 +225,14 @@ pub enum TantivyError {
     /// An Error happened in one of the thread.
     #[error("An error occurred in a thread: '{0}'")]
     ErrorInThread(String),
+    /// A worker thread panicked.
+    #[error("Thread '{thread_name}' panicked: '{message}'")]
+    WorkerPanicked {
+        /// Name of the thread, or the kind of task it was running.
+        thread_name: String,
+        /// Message of the panic.
+        message: String,
+    },
     /// An Error appeared related to opening or creating a index.
     #[error("Missing required index builder argument when open/create index: '{0}'")]
     IndexBuilderMissingArgument(&'static str),
@@ -95,6 +245,36 @@ pub enum TantivyError {
     /// Index incompatible with current version of tantivy
     #[error("{0:?}")]
     IncompatibleIndex(Incompatibility),
+    /// The memory budget of a query was exceeded.
+    #[error("The memory budget of the query ({0} bytes) was exceeded")]
+    MemoryBudgetExceeded(usize),
+    /// The multi-term queries of a query expanded into more terms than
+    /// allowed by its expansion budget.
+    #[error("The expansion budget of the query ({0} terms) was exceeded")]
+    ExpansionBudgetExceeded(usize),
+    /// Not enough disk space is available to write new files,
+    /// given the disk space reserve of the `IndexWriter`.
+    #[error(
+        "Not enough disk space: {available_bytes} bytes available, {required_bytes} bytes required"
+    )]
+    DiskFull {
+        /// Number of bytes available in the directory.
+        available_bytes: u64,
+        /// Number of bytes required, reserve included.
+        required_bytes: u64,
+    },
+    /// The settings of the index are inconsistent with its schema.
+    #[error("Invalid index settings: {0}")]
+    InvalidSettings(#[from] SettingsError),
+    /// An error, with the context in which it happened.
+    #[error("{context}: {source}")]
+    WithContext {
+        /// Context of the error.
+        context: ErrorContext,
+        /// The error itself.
+        #[source]
+        source: Box<TantivyError>,
+    },
 }
 
 impl From<DataCorruption> for TantivyError {
@@ -119,6 +299,131 @@ impl From<query::QueryParserError> for TantivyError {
     }
 }
 
+impl TantivyError {
+    /// Wraps the error with a context.
+    pub fn with_context(self, context: ErrorContext) -> TantivyError {
+        TantivyError::WithContext {
+            context,
+            source: Box::new(self),
+        }
+    }
+
+    /// Returns the error, stripped of its contexts.
+    pub fn root_cause(&self) -> &TantivyError {
+        let mut error = self;
+        while let TantivyError::WithContext { source, .. } = error {
+            error = source;
+        }
+        error
+    }
+
+    /// Returns the contexts of the error, from the outermost to the innermost.
+    pub fn contexts(&self) -> Vec<&ErrorContext> {
+        let mut contexts = Vec::new();
+        let mut error = self;
+        while let TantivyError::WithContext { context, source } = error {
+            contexts.push(context);
+            error = source;
+        }
+        contexts
+    }
+
+    /// Returns the machine-readable code of the error.
+    pub fn code(&self) -> ErrorCode {
+        match self.root_cause() {
+            TantivyError::OpenDirectoryError(_) => ErrorCode::OpenDirectory,
+            TantivyError::OpenReadError(_) => ErrorCode::OpenRead,
+            TantivyError::OpenWriteError(_) => ErrorCode::OpenWrite,
+            TantivyError::IndexAlreadyExists => ErrorCode::IndexAlreadyExists,
+            TantivyError::LockFailure(..) => ErrorCode::LockFailure,
+            TantivyError::IoError(_) => ErrorCode::Io,
+            TantivyError::DataCorruption(_) => ErrorCode::DataCorruption,
+            TantivyError::Poisoned => ErrorCode::Poisoned,
+            TantivyError::InvalidArgument(_) => ErrorCode::InvalidArgument,
+            TantivyError::ErrorInThread(_) => ErrorCode::ErrorInThread,
+            TantivyError::WorkerPanicked { .. } => ErrorCode::WorkerPanicked,
+            TantivyError::IndexBuilderMissingArgument(_) => ErrorCode::IndexBuilderMissingArgument,
+            TantivyError::SchemaError(_) => ErrorCode::Schema,
+            TantivyError::SystemError(_) => ErrorCode::System,
+            TantivyError::IncompatibleIndex(_) => ErrorCode::IncompatibleIndex,
+            TantivyError::MemoryBudgetExceeded(_) => ErrorCode::MemoryBudgetExceeded,
+            TantivyError::ExpansionBudgetExceeded(_) => ErrorCode::ExpansionBudgetExceeded,
+            TantivyError::DiskFull { .. } => ErrorCode::DiskFull,
+            TantivyError::InvalidSettings(_) => ErrorCode::InvalidSettings,
+            TantivyError::WithContext { .. } => unreachable!("The root cause has no context"),
+        }
+    }
+
+    /// Returns true if the error is caused by a transient condition,
+    /// and the operation may succeed if retried later.
+    ///
+    /// This is the case of lock contention, of lack of disk space,
+    /// and of io errors such as timeouts or interruptions.
+    /// Other errors (invalid arguments, schema errors, data corruption, ...)
+    /// will happen again if the operation is retried as is.
+    pub fn is_retryable(&self) -> bool {
+        match self.root_cause() {
+            TantivyError::LockFailure(LockError::LockBusy, _) => true,
+            TantivyError::LockFailure(LockError::IoError(io_error), _) => {
+                is_retryable_io_error(io_error)
+            }
+            TantivyError::IoError(io_error) => is_retryable_io_error(io_error),
+            TantivyError::OpenReadError(OpenReadError::IoError { io_error, .. }) => {
+                is_retryable_io_error(io_error)
+            }
+            TantivyError::OpenWriteError(OpenWriteError::IoError { io_error, .. }) => {
+                is_retryable_io_error(io_error)
+            }
+            TantivyError::DiskFull { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Builds a `WorkerPanicked` error, given the payload of the panic.
+    pub(crate) fn worker_panicked(
+        thread_name: &str,
+        panic_payload: Box<dyn Any + Send>,
+    ) -> TantivyError {
+        let message = if let Some(message) = panic_payload.downcast_ref::<&str>() {
+            message.to_string()
+        } else if let Some(message) = panic_payload.downcast_ref::<String>() {
+            message.clone()
+        } else {
+            "Box<Any>".to_string()
+        };
+        TantivyError::WorkerPanicked {
+            thread_name: thread_name.to_string(),
+            message,
+        }
+    }
+}
+
+fn is_retryable_io_error(io_error: &io::Error) -> bool {
+    matches!(
+        io_error.kind(),
+        io::ErrorKind::Interrupted
+            | io::ErrorKind::WouldBlock
+            | io::ErrorKind::TimedOut
+            | io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::BrokenPipe
+    )
+}
+
+/// Extension trait attaching a context to the error of a `Result`.
+pub(crate) trait ResultExt<T> {
+    /// Wraps the error, if any, with the context returned by `context_fn`.
+    fn with_context<F: FnOnce() -> ErrorContext>(self, context_fn: F) -> crate::Result<T>;
+}
+
+impl<T, E: Into<TantivyError>> ResultExt<T> for Result<T, E> {
+    fn with_context<F: FnOnce() -> ErrorContext>(self, context_fn: F) -> crate::Result<T> {
+        self.map_err(|err| err.into().with_context(context_fn()))
+    }
+}
+
 impl<Guard> From<PoisonError<Guard>> for TantivyError {
     fn from(_: PoisonError<Guard>) -> TantivyError {
         TantivyError::Poisoned
@@ -148,3 +453,53 @@ impl From<rayon::ThreadPoolBuildError> for TantivyError {
         TantivyError::SystemError(error.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ErrorCode, ErrorContext, TantivyError};
+    use crate::directory::error::LockError;
+    use crate::directory::Directory;
+    use crate::schema::{Schema, TEXT};
+    use crate::Index;
+    use std::error::Error;
+    use std::io;
+
+    #[test]
+    fn test_error_code_and_retryable() {
+        let lock_busy = TantivyError::LockFailure(LockError::LockBusy, None);
+        assert_eq!(lock_busy.code(), ErrorCode::LockFailure);
+        assert!(lock_busy.is_retryable());
+        let timed_out = TantivyError::from(io::Error::new(io::ErrorKind::TimedOut, "timeout"));
+        assert_eq!(timed_out.code().as_str(), "io");
+        assert!(timed_out.is_retryable());
+        let not_found = TantivyError::from(io::Error::new(io::ErrorKind::NotFound, "not found"));
+        assert!(!not_found.is_retryable());
+        let invalid_argument = TantivyError::InvalidArgument("invalid".to_string());
+        assert_eq!(invalid_argument.code(), ErrorCode::InvalidArgument);
+        assert!(!invalid_argument.is_retryable());
+    }
+
+    #[test]
+    fn test_error_context() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let mut index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field => "hello"));
+        index_writer.commit()?;
+        let segment = index.searchable_segments()?.into_iter().next().unwrap();
+        let path = segment.relative_path(crate::core::SegmentComponent::FieldNorms);
+        index.directory_mut().delete(&path).unwrap();
+
+        let err = index.reader().err().unwrap();
+        assert_eq!(err.code(), ErrorCode::OpenRead);
+        assert_eq!(err.contexts(), vec![&ErrorContext::Segment(segment.id())]);
+        assert!(matches!(err.root_cause(), TantivyError::OpenReadError(_)));
+        assert!(err.to_string().starts_with("In segment"));
+        let source = err.source().unwrap();
+        assert!(source
+            .to_string()
+            .starts_with("Failed to open file for read"));
+        Ok(())
+    }
+}
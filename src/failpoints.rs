@@ -0,0 +1,99 @@
+/*!
+Failure injection points, to test how an application recovers from tantivy failures.
+
+Tantivy declares [fail](https://docs.rs/fail) failpoints on its critical I/O paths.
+They only trigger if tantivy is compiled with the `failpoints` feature, and once
+configured with the functions of this module (or with `fail::cfg`). They are no-op
+otherwise.
+
+The configuration of the failpoints is global to the process: tests relying on them
+should hold a [`FailScenario`](./struct.FailScenario.html), and run in a test binary
+of their own, or single-threaded.
+
+```rust,no_run
+use tantivy::failpoints::{self, FailScenario};
+use tantivy::schema::{Schema, TEXT};
+use tantivy::{doc, Index};
+
+# fn main() -> tantivy::Result<()> {
+let _scenario = FailScenario::setup();
+let mut schema_builder = Schema::builder();
+let text = schema_builder.add_text_field("text", TEXT);
+let index = Index::create_in_ram(schema_builder.build());
+let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+index_writer.add_document(doc!(text => "hello"));
+failpoints::fail_with_error(failpoints::SAVE_METAS)?;
+assert!(index_writer.commit().is_err());
+failpoints::clear(failpoints::SAVE_METAS);
+# Ok(())
+# }
+```
+*/
+
+use crate::TantivyError;
+use std::io;
+
+pub use fail::FailScenario;
+
+/// Writing a file atomically in a `RamDirectory`.
+pub const RAM_DIRECTORY_ATOMIC_WRITE: &str = "RamDirectory::atomic_write";
+/// Deleting a file from a `RamDirectory`.
+pub const RAM_DIRECTORY_DELETE: &str = "RamDirectory::delete";
+/// Writing the files of a merged segment.
+pub const MERGE_WRITE: &str = "IndexMerger::write";
+/// Publishing a merged segment, in place of the segments it merges.
+pub const MERGE_PUBLISH: &str = "SegmentUpdater::end_merge";
+/// Writing the file of the deleted documents of a segment.
+pub const DELETE_BITSET_WRITE: &str = "write_deleted_docs";
+/// Saving the metas of a commit, which publishes its segments.
+pub const SAVE_METAS: &str = "save_metas";
+/// Garbage collecting the files of the index that are not used anymore.
+pub const GARBAGE_COLLECT: &str = "ManagedDirectory::garbage_collect";
+
+/// All of the failpoints declared by tantivy.
+pub const ALL: &[&str] = &[
+    RAM_DIRECTORY_ATOMIC_WRITE,
+    RAM_DIRECTORY_DELETE,
+    MERGE_WRITE,
+    MERGE_PUBLISH,
+    DELETE_BITSET_WRITE,
+    SAVE_METAS,
+    GARBAGE_COLLECT,
+];
+
+fn configure(failpoint: &str, actions: &str) -> crate::Result<()> {
+    fail::cfg(failpoint, actions).map_err(TantivyError::InvalidArgument)
+}
+
+/// Makes the operation of a failpoint return an error, every time it is reached.
+pub fn fail_with_error(failpoint: &str) -> crate::Result<()> {
+    configure(failpoint, &format!("return({})", failpoint))
+}
+
+/// Makes the operation of a failpoint return an error, after `num_successes`
+/// successful operations.
+pub fn fail_after(failpoint: &str, num_successes: usize) -> crate::Result<()> {
+    configure(
+        failpoint,
+        &format!("{}*off->return({})", num_successes, failpoint),
+    )
+}
+
+/// Makes the thread reaching a failpoint panic, e.g. to simulate a crash.
+pub fn panic_at(failpoint: &str) -> crate::Result<()> {
+    configure(failpoint, &format!("panic({})", failpoint))
+}
+
+/// Removes the configuration of a failpoint: its operation succeeds again.
+pub fn clear(failpoint: &str) {
+    fail::remove(failpoint)
+}
+
+/// Builds the error returned by a failpoint configured to return an error.
+#[cfg_attr(not(feature = "failpoints"), allow(dead_code))]
+pub(crate) fn failpoint_error(msg: Option<String>) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Other,
+        msg.unwrap_or_else(|| "Undefined".to_string()),
+    )
+}
@@ -1,11 +1,29 @@
-use crate::common::{BitSet, HasLen};
+use crate::common::{BitSet, CountingWriter, HasLen};
 use crate::directory::FileSlice;
 use crate::directory::OwnedBytes;
 use crate::directory::WritePtr;
+use crate::error::DataCorruption;
 use crate::space_usage::ByteCount;
 use crate::DocId;
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+use fail::fail_point;
 use std::io;
 use std::io::Write;
+use std::sync::Arc;
+
+// Delete bitset files are split in chunks of `CHUNK_NUM_DOCS` doc ids.
+// The deleted docs of a chunk are stored either as a sorted array of `u16`,
+// or as a bitmap if there are more than `MAX_ARRAY_LEN` of them.
+//
+// The file is laid out as follows:
+// - the arrays and bitmaps of the non-empty chunks,
+// - for each chunk, the `u32` offset of its array or bitmap and its `u32` number of deleted docs,
+// - the `u32` number of chunks.
+const CHUNK_NUM_DOCS: u32 = 1 << 16;
+const MAX_ARRAY_LEN: u32 = 4_096;
+const BITMAP_NUM_BYTES: usize = (CHUNK_NUM_DOCS / 8) as usize;
+const CHUNK_ENTRY_NUM_BYTES: usize = 8;
+const CHUNK_NUM_WORDS: usize = (CHUNK_NUM_DOCS / 64) as usize;
 
 /// Write a delete `BitSet`
 ///
@@ -17,31 +35,79 @@ pub fn write_delete_bitset(
     max_doc: u32,
     writer: &mut WritePtr,
 ) -> io::Result<()> {
-    let mut byte = 0u8;
-    let mut shift = 0u8;
-    for doc in 0..max_doc {
-        if delete_bitset.contains(doc) {
-            byte |= 1 << shift;
-        }
-        if shift == 7 {
-            writer.write_all(&[byte])?;
-            shift = 0;
-            byte = 0;
-        } else {
-            shift += 1;
+    write_deleted_docs(
+        (0..max_doc).filter(|&doc| delete_bitset.contains(doc)),
+        writer,
+    )
+}
+
+/// Writes a delete bitset file given the sorted `DocId`s of the deleted docs.
+///
+/// Warning: this function does not call terminate. The caller is in charge of
+/// closing the writer properly.
+pub(crate) fn write_deleted_docs<I: Iterator<Item = DocId>>(
+    deleted_docs: I,
+    writer: &mut WritePtr,
+) -> io::Result<()> {
+    fail_point!(crate::failpoints::DELETE_BITSET_WRITE, |msg| Err(
+        crate::failpoints::failpoint_error(msg)
+    ));
+    let mut writer = CountingWriter::wrap(writer);
+    // (offset, number of deleted docs) of each chunk.
+    let mut chunk_entries: Vec<(u32, u32)> = Vec::new();
+    let mut chunk_docs: Vec<u16> = Vec::new();
+    let mut current_chunk = 0u32;
+    for doc in deleted_docs {
+        let chunk = doc / CHUNK_NUM_DOCS;
+        if chunk != current_chunk {
+            write_chunk(&chunk_docs, &mut chunk_entries, &mut writer)?;
+            chunk_docs.clear();
+            chunk_entries.resize(chunk as usize, (0, 0));
+            current_chunk = chunk;
         }
+        chunk_docs.push((doc % CHUNK_NUM_DOCS) as u16);
     }
-    if max_doc % 8 > 0 {
-        writer.write_all(&[byte])?;
+    write_chunk(&chunk_docs, &mut chunk_entries, &mut writer)?;
+    for &(offset, num_deleted) in &chunk_entries {
+        writer.write_u32::<LittleEndian>(offset)?;
+        writer.write_u32::<LittleEndian>(num_deleted)?;
     }
+    writer.write_u32::<LittleEndian>(chunk_entries.len() as u32)?;
+    Ok(())
+}
+
+fn write_chunk<W: Write>(
+    chunk_docs: &[u16],
+    chunk_entries: &mut Vec<(u32, u32)>,
+    writer: &mut CountingWriter<W>,
+) -> io::Result<()> {
+    let offset = writer.written_bytes() as u32;
+    if chunk_docs.len() as u32 > MAX_ARRAY_LEN {
+        let mut bitmap = vec![0u8; BITMAP_NUM_BYTES];
+        for &doc in chunk_docs {
+            bitmap[(doc / 8) as usize] |= 1 << (doc % 8);
+        }
+        writer.write_all(&bitmap)?;
+    } else {
+        for &doc in chunk_docs {
+            writer.write_u16::<LittleEndian>(doc)?;
+        }
+    }
+    chunk_entries.push((offset, chunk_docs.len() as u32));
     Ok(())
 }
 
 /// Set of deleted `DocId`s.
+///
+/// The deleted docs may be spread over several delete files, each one holding
+/// the docs deleted since the previous one. They are decoded upon opening into
+/// one bit per document, so that checking whether a doc is deleted is a word lookup,
+/// and alive docs are found a word at a time.
 #[derive(Clone)]
 pub struct DeleteBitSet {
-    data: OwnedBytes,
+    words: Arc<[u64]>,
     num_deleted: usize,
+    num_bytes: usize,
 }
 
 impl DeleteBitSet {
@@ -64,20 +130,110 @@ impl DeleteBitSet {
     }
 
     /// Opens a delete bitset given its file.
+    ///
+    /// Returns a `DataCorruption` error if the chunks of the file are out of its bounds.
     pub fn open(file: FileSlice) -> crate::Result<DeleteBitSet> {
+        let num_bytes = file.len();
+        if num_bytes < 4 {
+            return Err(corrupted_delete_file("the file is truncated").into());
+        }
+        let (body, num_chunks_bytes) = file.split_from_end(4);
+        let num_chunks = LittleEndian::read_u32(num_chunks_bytes.read_bytes()?.as_slice()) as usize;
+        if num_chunks * CHUNK_ENTRY_NUM_BYTES > body.len() {
+            return Err(corrupted_delete_file("the chunk entries are out of bounds").into());
+        }
+        let (data, chunk_entries) = body.split_from_end(num_chunks * CHUNK_ENTRY_NUM_BYTES);
+        let data = data.read_bytes()?;
+        let chunk_entries = chunk_entries.read_bytes()?;
+        let mut words = vec![0u64; num_chunks * CHUNK_NUM_WORDS];
+        let mut num_deleted = 0;
+        for (chunk, entry) in chunk_entries
+            .as_slice()
+            .chunks(CHUNK_ENTRY_NUM_BYTES)
+            .enumerate()
+        {
+            let offset = LittleEndian::read_u32(entry) as usize;
+            let chunk_num_deleted = LittleEndian::read_u32(&entry[4..]) as usize;
+            let chunk_words = &mut words[chunk * CHUNK_NUM_WORDS..(chunk + 1) * CHUNK_NUM_WORDS];
+            let is_bitmap = chunk_num_deleted > MAX_ARRAY_LEN as usize;
+            let chunk_num_bytes = if is_bitmap {
+                BITMAP_NUM_BYTES
+            } else {
+                chunk_num_deleted * 2
+            };
+            if chunk_num_deleted > CHUNK_NUM_DOCS as usize || offset + chunk_num_bytes > data.len()
+            {
+                return Err(
+                    corrupted_delete_file(format!("chunk {} is out of bounds", chunk)).into(),
+                );
+            }
+            let chunk_bytes = &data.as_slice()[offset..offset + chunk_num_bytes];
+            if is_bitmap {
+                LittleEndian::read_u64_into(chunk_bytes, chunk_words);
+            } else {
+                for doc_bytes in chunk_bytes.chunks(2) {
+                    let doc = LittleEndian::read_u16(doc_bytes) as usize;
+                    chunk_words[doc / 64] |= 1 << (doc % 64);
+                }
+            }
+            num_deleted += chunk_num_deleted;
+        }
+        Ok(DeleteBitSet {
+            words: words.into(),
+            num_deleted,
+            num_bytes,
+        })
+    }
+
+    /// Opens a delete bitset file written by a former version of tantivy,
+    /// with one bit per document.
+    pub(crate) fn open_dense(file: FileSlice) -> crate::Result<DeleteBitSet> {
         let bytes = file.read_bytes()?;
-        let num_deleted: usize = bytes
+        let words: Vec<u64> = bytes
             .as_slice()
-            .iter()
-            .map(|b| b.count_ones() as usize)
-            .sum();
+            .chunks(8)
+            .map(|word_bytes| {
+                let mut padded_word_bytes = [0u8; 8];
+                padded_word_bytes[..word_bytes.len()].copy_from_slice(word_bytes);
+                u64::from_le_bytes(padded_word_bytes)
+            })
+            .collect();
+        let num_deleted: usize = words.iter().map(|word| word.count_ones() as usize).sum();
         Ok(DeleteBitSet {
-            data: bytes,
+            words: words.into(),
             num_deleted,
+            num_bytes: bytes.len(),
         })
     }
 
+    /// Merges delete bitsets holding disjoint sets of deleted docs.
+    pub(crate) fn union(delete_bitsets: Vec<DeleteBitSet>) -> DeleteBitSet {
+        let num_words = delete_bitsets
+            .iter()
+            .map(|delete_bitset| delete_bitset.words.len())
+            .max()
+            .unwrap_or(0);
+        let mut words = vec![0u64; num_words];
+        for delete_bitset in &delete_bitsets {
+            for (word, delete_bitset_word) in words.iter_mut().zip(delete_bitset.words.iter()) {
+                *word |= delete_bitset_word;
+            }
+        }
+        DeleteBitSet {
+            words: words.into(),
+            num_deleted: delete_bitsets
+                .iter()
+                .map(|delete_bitset| delete_bitset.num_deleted)
+                .sum(),
+            num_bytes: delete_bitsets
+                .iter()
+                .map(|delete_bitset| delete_bitset.num_bytes)
+                .sum(),
+        }
+    }
+
     /// Returns true iff the document is still "alive". In other words, if it has not been deleted.
+    #[inline]
     pub fn is_alive(&self, doc: DocId) -> bool {
         !self.is_deleted(doc)
     }
@@ -85,10 +241,28 @@ impl DeleteBitSet {
     /// Returns true iff the document has been marked as deleted.
     #[inline]
     pub fn is_deleted(&self, doc: DocId) -> bool {
-        let byte_offset = doc / 8u32;
-        let b: u8 = self.data.as_slice()[byte_offset as usize];
-        let shift = (doc & 7u32) as u8;
-        b & (1u8 << shift) != 0
+        let word = self.words.get((doc / 64) as usize).cloned().unwrap_or(0u64);
+        (word >> (doc % 64)) & 1u64 == 1u64
+    }
+
+    /// Returns the first alive document greater than or equal to `doc`.
+    ///
+    /// The returned document may be greater than or equal to the `max_doc` of the segment.
+    pub fn next_alive(&self, doc: DocId) -> DocId {
+        let mut word_ord = (doc / 64) as usize;
+        if word_ord >= self.words.len() {
+            return doc;
+        }
+        // Skips a word at a time, until one of its documents is alive.
+        let mut alive_word = !self.words[word_ord] & (u64::MAX << (doc % 64));
+        while alive_word == 0 {
+            word_ord += 1;
+            if word_ord == self.words.len() {
+                return word_ord as DocId * 64;
+            }
+            alive_word = !self.words[word_ord];
+        }
+        word_ord as DocId * 64 + alive_word.trailing_zeros()
     }
 
     /// The number of deleted docs
@@ -97,7 +271,48 @@ impl DeleteBitSet {
     }
     /// Summarize total space usage of this bitset.
     pub fn space_usage(&self) -> ByteCount {
-        self.data.len()
+        self.num_bytes
+    }
+}
+
+fn corrupted_delete_file<TStr: ToString>(comment: TStr) -> DataCorruption {
+    DataCorruption::comment_only(format!("Corrupted delete file: {}", comment.to_string()))
+}
+
+/// Iterator over the alive documents of a segment, in increasing order.
+///
+/// See [`SegmentReader::alive_docs`](../struct.SegmentReader.html#method.alive_docs).
+pub struct AliveDocs<'a> {
+    delete_bitset_opt: Option<&'a DeleteBitSet>,
+    next_doc: DocId,
+    max_doc: DocId,
+}
+
+impl<'a> AliveDocs<'a> {
+    pub(crate) fn new(delete_bitset_opt: Option<&'a DeleteBitSet>, max_doc: DocId) -> Self {
+        AliveDocs {
+            delete_bitset_opt,
+            next_doc: 0,
+            max_doc,
+        }
+    }
+}
+
+impl<'a> Iterator for AliveDocs<'a> {
+    type Item = DocId;
+
+    fn next(&mut self) -> Option<DocId> {
+        let doc = if let Some(delete_bitset) = self.delete_bitset_opt {
+            delete_bitset.next_alive(self.next_doc)
+        } else {
+            self.next_doc
+        };
+        if doc >= self.max_doc {
+            self.next_doc = self.max_doc;
+            return None;
+        }
+        self.next_doc = doc + 1;
+        Some(doc)
     }
 }
 
@@ -109,8 +324,20 @@ impl HasLen for DeleteBitSet {
 
 #[cfg(test)]
 mod tests {
-    use super::DeleteBitSet;
+    use super::{write_deleted_docs, AliveDocs, DeleteBitSet};
     use crate::common::HasLen;
+    use crate::directory::{Directory, FileSlice, RamDirectory, TerminatingWrite};
+    use crate::TantivyError;
+    use std::path::Path;
+
+    fn delete_bitset_from_docs(docs: &[u32]) -> crate::Result<DeleteBitSet> {
+        let directory = RamDirectory::create();
+        let path = Path::new("deletes");
+        let mut wrt = directory.open_write(path)?;
+        write_deleted_docs(docs.iter().cloned(), &mut wrt)?;
+        wrt.terminate()?;
+        DeleteBitSet::open(directory.open_read(path)?)
+    }
 
     #[test]
     fn test_delete_bitset_empty() {
@@ -140,4 +367,96 @@ mod tests {
         }
         assert_eq!(delete_bitset.len(), 2);
     }
+
+    #[test]
+    fn test_delete_bitset_chunks() -> crate::Result<()> {
+        // An array chunk, an empty chunk, a bitmap chunk and a chunk with a single doc.
+        let docs: Vec<u32> = (0..100u32)
+            .map(|i| i * 7)
+            .chain((0..10_000u32).map(|i| 3 * 65_536 + i * 3))
+            .chain(std::iter::once(4 * 65_536 - 1))
+            .chain(std::iter::once(5 * 65_536 + 12))
+            .collect();
+        let delete_bitset = delete_bitset_from_docs(&docs)?;
+        assert_eq!(delete_bitset.num_deleted(), docs.len());
+        for doc in 0..7 * 65_536 {
+            assert_eq!(
+                delete_bitset.is_deleted(doc),
+                docs.binary_search(&doc).is_ok()
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_delete_bitset_corrupted() -> crate::Result<()> {
+        let directory = RamDirectory::create();
+        let path = Path::new("deletes");
+        let mut wrt = directory.open_write(path)?;
+        write_deleted_docs([1u32, 3].iter().cloned(), &mut wrt)?;
+        wrt.terminate()?;
+        let bytes = directory.open_read(path)?.read_bytes()?.to_vec();
+        // Points the chunk past the end of the file.
+        let mut out_of_bounds_chunk = bytes.clone();
+        let entry_start = out_of_bounds_chunk.len() - 4 - 8;
+        out_of_bounds_chunk[entry_start..entry_start + 4].copy_from_slice(&100u32.to_le_bytes());
+        // Claims more chunk entries than the file holds.
+        let mut too_many_chunks = bytes;
+        let num_chunks_start = too_many_chunks.len() - 4;
+        too_many_chunks[num_chunks_start..].copy_from_slice(&1_000u32.to_le_bytes());
+        for corrupted_bytes in vec![out_of_bounds_chunk, too_many_chunks, vec![1u8]] {
+            let file = FileSlice::from(corrupted_bytes);
+            assert!(matches!(
+                DeleteBitSet::open(file),
+                Err(TantivyError::DataCorruption(_))
+            ));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_delete_bitset_union() -> crate::Result<()> {
+        let delete_bitset = DeleteBitSet::union(vec![
+            delete_bitset_from_docs(&[1, 70_000])?,
+            delete_bitset_from_docs(&[3])?,
+            delete_bitset_from_docs(&[])?,
+        ]);
+        assert_eq!(delete_bitset.num_deleted(), 3);
+        for doc in 0..100_000 {
+            assert_eq!(delete_bitset.is_deleted(doc), [1, 3, 70_000].contains(&doc));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_delete_bitset_dense() -> crate::Result<()> {
+        let directory = RamDirectory::create();
+        let path = Path::new("deletes");
+        directory.atomic_write(path, &[0b0000_0010, 0b1000_0000])?;
+        let delete_bitset = DeleteBitSet::open_dense(directory.open_read(path)?)?;
+        assert_eq!(delete_bitset.num_deleted(), 2);
+        for doc in 0..16 {
+            assert_eq!(delete_bitset.is_deleted(doc), doc == 1 || doc == 15);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_alive_docs() -> crate::Result<()> {
+        let deleted_docs: Vec<u32> = (0..200u32).chain(201..300).chain(320..640).collect();
+        let delete_bitset = delete_bitset_from_docs(&deleted_docs)?;
+        assert_eq!(delete_bitset.next_alive(0), 200);
+        assert_eq!(delete_bitset.next_alive(201), 300);
+        assert_eq!(delete_bitset.next_alive(320), 640);
+        // Beyond the deleted docs, all of the docs are alive.
+        assert_eq!(delete_bitset.next_alive(100_000), 100_000);
+        let alive_docs: Vec<u32> = AliveDocs::new(Some(&delete_bitset), 645).collect();
+        let expected_alive_docs: Vec<u32> = std::iter::once(200)
+            .chain(300..320)
+            .chain(640..645)
+            .collect();
+        assert_eq!(alive_docs, expected_alive_docs);
+        assert_eq!(AliveDocs::new(None, 3).collect::<Vec<u32>>(), vec![0, 1, 2]);
+        Ok(())
+    }
 }
@@ -0,0 +1,91 @@
+use crate::fastfield::FastFieldReader;
+use crate::DocId;
+
+/// Maps the values of a single-valued fast field of a segment to the documents holding them.
+///
+/// It is the inverse of the fast field: it makes it possible to find the documents
+/// with a given value without scanning the fast field, nor encoding the value as a term
+/// and reading its posting list. This is typically handy to look up documents
+/// by a numeric id, e.g. to join indexes on a primary key.
+///
+/// The lookup is built from the fast field, the first time it is requested.
+/// See [`IntOptions::set_lookup_index`](../schema/struct.IntOptions.html#method.set_lookup_index).
+///
+/// Deleted documents are included.
+pub struct FastFieldLookup {
+    // Distinct values, sorted in increasing order.
+    values: Vec<u64>,
+    // The documents holding `values[i]` are `doc_ids[offsets[i]..offsets[i + 1]]`.
+    offsets: Vec<u32>,
+    doc_ids: Vec<DocId>,
+}
+
+impl FastFieldLookup {
+    /// Builds the lookup of the first `max_doc` documents of a fast field.
+    pub fn build<Reader: FastFieldReader<u64>>(reader: &Reader, max_doc: DocId) -> FastFieldLookup {
+        let mut value_docs: Vec<(u64, DocId)> =
+            (0..max_doc).map(|doc| (reader.get(doc), doc)).collect();
+        value_docs.sort_unstable();
+        let mut values = Vec::new();
+        let mut offsets = Vec::new();
+        let mut doc_ids = Vec::with_capacity(value_docs.len());
+        for (value, doc) in value_docs {
+            if values.last() != Some(&value) {
+                values.push(value);
+                offsets.push(doc_ids.len() as u32);
+            }
+            doc_ids.push(doc);
+        }
+        offsets.push(doc_ids.len() as u32);
+        FastFieldLookup {
+            values,
+            offsets,
+            doc_ids,
+        }
+    }
+
+    /// Returns the documents holding the given value, sorted by doc id.
+    pub fn docs(&self, value: u64) -> &[DocId] {
+        match self.values.binary_search(&value) {
+            Ok(ord) => &self.doc_ids[self.offsets[ord] as usize..self.offsets[ord + 1] as usize],
+            Err(_) => &[],
+        }
+    }
+
+    /// Returns the number of distinct values.
+    pub fn num_values(&self) -> usize {
+        self.values.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FastFieldLookup;
+    use crate::fastfield::FastFieldReader;
+    use crate::schema::{Schema, FAST};
+    use crate::Index;
+
+    #[test]
+    fn test_fast_field_lookup() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let id_field = schema_builder.add_u64_field("id", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for &id in &[7u64, 3, 7, 12, 3, 7] {
+            index_writer.add_document(doc!(id_field => id));
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let segment_reader = searcher.segment_reader(0);
+        let reader = segment_reader.fast_fields().u64(id_field)?;
+        assert_eq!(reader.get(0), 7);
+        let lookup = FastFieldLookup::build(&reader, segment_reader.max_doc());
+        assert_eq!(lookup.num_values(), 3);
+        assert_eq!(lookup.docs(7), &[0, 2, 5]);
+        assert_eq!(lookup.docs(3), &[1, 4]);
+        assert_eq!(lookup.docs(12), &[3]);
+        assert!(lookup.docs(8).is_empty());
+        assert!(lookup.docs(100).is_empty());
+        Ok(())
+    }
+}
@@ -25,9 +25,11 @@ Read access performance is comparable to that of an array lookup.
 
 pub use self::bytes::{BytesFastFieldReader, BytesFastFieldWriter};
 pub use self::delete::write_delete_bitset;
-pub use self::delete::DeleteBitSet;
+pub(crate) use self::delete::write_deleted_docs;
+pub use self::delete::{AliveDocs, DeleteBitSet};
 pub use self::error::{FastFieldNotAvailableError, Result};
 pub use self::facet_reader::FacetReader;
+pub use self::lookup::FastFieldLookup;
 pub use self::multivalued::{MultiValuedFastFieldReader, MultiValuedFastFieldWriter};
 pub(crate) use self::reader::BitpackedFastFieldReader;
 pub use self::reader::DynamicFastFieldReader;
@@ -50,6 +52,7 @@ mod bytes;
 mod delete;
 mod error;
 mod facet_reader;
+mod lookup;
 mod multivalued;
 mod reader;
 mod readers;
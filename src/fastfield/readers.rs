@@ -1,4 +1,5 @@
 use crate::common::CompositeFile;
+use crate::core::FastFieldRange;
 use crate::directory::FileSlice;
 use crate::fastfield::MultiValuedFastFieldReader;
 use crate::fastfield::{BitpackedFastFieldReader, FastFieldNotAvailableError};
@@ -8,6 +9,7 @@ use crate::space_usage::PerFieldSpaceUsage;
 use crate::TantivyError;
 
 use super::reader::DynamicFastFieldReader;
+use super::FastFieldReader;
 
 /// Provides access to all of the BitpackedFastFieldReader.
 ///
@@ -57,6 +59,35 @@ impl FastFieldReaders {
         self.fast_fields_composite.space_usage()
     }
 
+    /// Returns the min and max values of the indexed single-valued numeric
+    /// and date fast fields.
+    pub(crate) fn value_ranges(&self) -> crate::Result<Vec<FastFieldRange>> {
+        let mut fast_field_ranges = Vec::new();
+        for (field, field_entry) in self.schema.fields() {
+            if !field_entry.is_indexed() {
+                continue;
+            }
+            if let Some((_, Cardinality::SingleValue)) =
+                type_and_cardinality(field_entry.field_type())
+            {
+                let fast_field_reader = self.u64_lenient(field)?;
+                fast_field_ranges.push(FastFieldRange {
+                    field,
+                    min_value: fast_field_reader.min_value(),
+                    max_value: fast_field_reader.max_value(),
+                });
+            }
+        }
+        Ok(fast_field_ranges)
+    }
+
+    /// Returns the cardinality of the fast field `field`,
+    /// or `None` if `field` is not a numeric, date or facet fast field.
+    pub(crate) fn cardinality(&self, field: Field) -> Option<Cardinality> {
+        type_and_cardinality(self.schema.get_field_entry(field).field_type())
+            .map(|(_, cardinality)| cardinality)
+    }
+
     fn fast_field_data(&self, field: Field, idx: usize) -> crate::Result<FileSlice> {
         self.fast_fields_composite
             .open_read_with_idx(field, idx)
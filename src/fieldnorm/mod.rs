@@ -16,6 +16,10 @@
 //! in a very short array.
 //!
 //! This trick is used by the Bm25 similarity.
+//!
+//! Text fields can also be configured to record their exact fieldnorm
+//! over 4 bytes, or no fieldnorm at all.
+//! (See [`FieldNormEncoding`](../schema/enum.FieldNormEncoding.html))
 mod code;
 mod reader;
 mod serializer;
@@ -26,3 +30,95 @@ pub use self::serializer::FieldNormsSerializer;
 pub use self::writer::FieldNormsWriter;
 
 use self::code::{fieldnorm_to_id, id_to_fieldnorm};
+
+#[cfg(test)]
+mod tests {
+    use crate::collector::TopDocs;
+    use crate::query::TermQuery;
+    use crate::schema::{
+        FieldNormEncoding, IndexRecordOption, Schema, TextFieldIndexing, TextOptions,
+    };
+    use crate::{Index, Term};
+    use futures::executor::block_on;
+
+    fn text_options(fieldnorm_encoding: FieldNormEncoding) -> TextOptions {
+        TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_index_option(IndexRecordOption::WithFreqs)
+                .set_fieldnorm_encoding(fieldnorm_encoding),
+        )
+    }
+
+    #[test]
+    fn test_fieldnorm_encodings() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let exact = schema_builder.add_text_field("exact", text_options(FieldNormEncoding::Exact));
+        let disabled =
+            schema_builder.add_text_field("disabled", text_options(FieldNormEncoding::Disabled));
+        let index = Index::create_in_ram(schema_builder.build());
+        let long_text = vec!["hello"; 1_000].join(" ");
+        {
+            let mut index_writer = index.writer_for_tests()?;
+            index_writer.add_document(doc!(exact => "hello", disabled => "hello"));
+            index_writer.commit()?;
+            index_writer.add_document(
+                doc!(exact => long_text.as_str(), disabled => "hello happy tax payer"),
+            );
+            index_writer.commit()?;
+            let segment_ids = index.searchable_segment_ids()?;
+            block_on(index_writer.merge(&segment_ids))?;
+            index_writer.wait_merging_threads()?;
+        }
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let segment_reader = searcher.segment_reader(0);
+        let exact_fieldnorms = segment_reader.get_fieldnorms_reader(exact)?;
+        assert_eq!(exact_fieldnorms.fieldnorm(0), 1);
+        assert_eq!(exact_fieldnorms.fieldnorm(1), 1_000);
+        assert_eq!(
+            segment_reader.inverted_index(exact)?.total_num_tokens(),
+            1_001
+        );
+        let disabled_fieldnorms = segment_reader.get_fieldnorms_reader(disabled)?;
+        assert_eq!(disabled_fieldnorms.fieldnorm(0), 1);
+        assert_eq!(disabled_fieldnorms.fieldnorm(1), 1);
+        let term_query = TermQuery::new(
+            Term::from_field_text(disabled, "hello"),
+            IndexRecordOption::WithFreqs,
+        );
+        let top_docs = searcher.search(&term_query, &TopDocs::with_limit(2))?;
+        assert_eq!(top_docs.len(), 2);
+        assert_eq!(top_docs[0].0, top_docs[1].0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_field_boost() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let exact = schema_builder.add_text_field("exact", text_options(FieldNormEncoding::Exact));
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        let mut boosted_doc = doc!(exact => "hello happy tax payer");
+        boosted_doc.set_field_boost(exact, 2.0);
+        index_writer.add_document(boosted_doc);
+        index_writer.add_document(doc!(exact => "hello happy tax payer"));
+        let mut shrunk_doc = doc!(exact => "hello");
+        shrunk_doc.set_field_boost(exact, 4.0);
+        index_writer.add_document(shrunk_doc);
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let segment_reader = searcher.segment_reader(0);
+        let fieldnorms = segment_reader.get_fieldnorms_reader(exact)?;
+        assert_eq!(fieldnorms.fieldnorm(0), 2);
+        assert_eq!(fieldnorms.fieldnorm(1), 4);
+        assert_eq!(fieldnorms.fieldnorm(2), 1);
+        let term_query = TermQuery::new(
+            Term::from_field_text(exact, "tax"),
+            IndexRecordOption::WithFreqs,
+        );
+        let top_docs = searcher.search(&term_query, &TopDocs::with_limit(2))?;
+        assert_eq!(top_docs[0].1.doc_id, 0);
+        assert!(top_docs[0].0 > top_docs[1].0);
+        Ok(())
+    }
+}
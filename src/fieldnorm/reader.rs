@@ -2,9 +2,10 @@ use super::{fieldnorm_to_id, id_to_fieldnorm};
 use crate::common::CompositeFile;
 use crate::directory::FileSlice;
 use crate::directory::OwnedBytes;
-use crate::schema::Field;
+use crate::schema::{Field, FieldNormEncoding, Schema};
 use crate::space_usage::PerFieldSpaceUsage;
 use crate::DocId;
+use std::convert::TryInto;
 use std::sync::Arc;
 
 /// Reader for the fieldnorm (for each document, the number of tokens indexed in the
@@ -13,24 +14,38 @@ use std::sync::Arc;
 /// Each fieldnorm is approximately compressed over one byte. We refer to this byte as
 /// `fieldnorm_id`.
 /// The mapping from `fieldnorm` to `fieldnorm_id` is given by monotonic.
+///
+/// Fields configured with `FieldNormEncoding::Exact` record their exact
+/// fieldnorm over 4 bytes instead.
 #[derive(Clone)]
 pub struct FieldNormReaders {
     data: Arc<CompositeFile>,
+    schema: Schema,
 }
 
 impl FieldNormReaders {
     /// Creates a field norm reader.
-    pub fn open(file: FileSlice) -> crate::Result<FieldNormReaders> {
+    pub fn open(file: FileSlice, schema: Schema) -> crate::Result<FieldNormReaders> {
         let data = CompositeFile::open(&file)?;
         Ok(FieldNormReaders {
             data: Arc::new(data),
+            schema,
         })
     }
 
     /// Returns the FieldNormReader for a specific field.
     pub fn get_field(&self, field: Field) -> crate::Result<Option<FieldNormReader>> {
         if let Some(file) = self.data.open_read(field) {
-            let fieldnorm_reader = FieldNormReader::open(file)?;
+            let fieldnorm_encoding = self
+                .schema
+                .get_field_entry(field)
+                .field_type()
+                .get_fieldnorm_encoding();
+            let fieldnorm_reader = if fieldnorm_encoding == Some(FieldNormEncoding::Exact) {
+                FieldNormReader::open_exact(file)?
+            } else {
+                FieldNormReader::open(file)?
+            };
             Ok(Some(fieldnorm_reader))
         } else {
             Ok(None)
@@ -72,6 +87,8 @@ impl From<ReaderImplEnum> for FieldNormReader {
 #[derive(Clone)]
 enum ReaderImplEnum {
     FromData(OwnedBytes),
+    // Exact fieldnorms, encoded as little endian `u32`.
+    Exact(OwnedBytes),
     Const {
         num_docs: u32,
         fieldnorm_id: u8,
@@ -101,6 +118,12 @@ impl FieldNormReader {
         Ok(FieldNormReader::new(data))
     }
 
+    /// Opens a field norm reader given the file of a field with exact fieldnorms.
+    pub fn open_exact(fieldnorm_file: FileSlice) -> crate::Result<Self> {
+        let data = fieldnorm_file.read_bytes()?;
+        Ok(ReaderImplEnum::Exact(data).into())
+    }
+
     fn new(data: OwnedBytes) -> Self {
         ReaderImplEnum::FromData(data).into()
     }
@@ -109,16 +132,57 @@ impl FieldNormReader {
     pub fn num_docs(&self) -> u32 {
         match &self.0 {
             ReaderImplEnum::FromData(data) => data.len() as u32,
+            ReaderImplEnum::Exact(data) => (data.len() / 4) as u32,
             ReaderImplEnum::Const { num_docs, .. } => *num_docs,
         }
     }
 
+    /// Returns the number of documents with a non-zero fieldnorm,
+    /// i.e. the number of documents with at least one token in the field.
+    pub fn num_docs_with_tokens(&self) -> u32 {
+        match &self.0 {
+            ReaderImplEnum::FromData(data) => {
+                data.as_slice().iter().filter(|&&id| id != 0).count() as u32
+            }
+            ReaderImplEnum::Exact(data) => data
+                .as_slice()
+                .chunks_exact(4)
+                .filter(|bytes| bytes.iter().any(|&byte| byte != 0))
+                .count() as u32,
+            ReaderImplEnum::Const {
+                num_docs,
+                fieldnorm_id,
+                ..
+            } => {
+                if *fieldnorm_id == 0 {
+                    0
+                } else {
+                    *num_docs
+                }
+            }
+        }
+    }
+
+    /// Returns the exact fieldnorm associated to a doc id,
+    /// if the field records exact fieldnorms.
+    #[inline]
+    pub fn exact_fieldnorm(&self, doc_id: DocId) -> Option<u32> {
+        match &self.0 {
+            ReaderImplEnum::Exact(data) => {
+                let start = doc_id as usize * 4;
+                let bytes: [u8; 4] = data.as_slice()[start..start + 4].try_into().unwrap();
+                Some(u32::from_le_bytes(bytes))
+            }
+            _ => None,
+        }
+    }
+
     /// Returns the `fieldnorm` associated to a doc id.
     /// The fieldnorm is a value approximating the number
     /// of tokens in a given field of the `doc_id`.
     ///
-    /// It is imprecise, and equal or lower than
-    /// the actual number of tokens.
+    /// Unless the field records exact fieldnorms, it is imprecise,
+    /// and equal or lower than the actual number of tokens.
     ///
     /// The fieldnorm is effectively decoded from the
     /// `fieldnorm_id` by doing a simple table lookup.
@@ -128,6 +192,7 @@ impl FieldNormReader {
                 let fieldnorm_id = data.as_slice()[doc_id as usize];
                 id_to_fieldnorm(fieldnorm_id)
             }
+            ReaderImplEnum::Exact(_) => self.exact_fieldnorm(doc_id).unwrap(),
             ReaderImplEnum::Const { fieldnorm, .. } => *fieldnorm,
         }
     }
@@ -140,6 +205,7 @@ impl FieldNormReader {
                 let fieldnorm_id = data.as_slice()[doc_id as usize];
                 fieldnorm_id
             }
+            ReaderImplEnum::Exact(_) => fieldnorm_to_id(self.exact_fieldnorm(doc_id).unwrap()),
             ReaderImplEnum::Const { fieldnorm_id, .. } => *fieldnorm_id,
         }
     }
@@ -171,6 +237,8 @@ impl FieldNormReader {
 
 #[cfg(test)]
 mod tests {
+    use super::ReaderImplEnum;
+    use crate::directory::OwnedBytes;
     use crate::fieldnorm::FieldNormReader;
 
     #[test]
@@ -185,6 +253,35 @@ mod tests {
         assert_eq!(fieldnorm_reader.fieldnorm(4), 983_064);
     }
 
+    #[test]
+    fn test_num_docs_with_tokens() {
+        assert_eq!(
+            FieldNormReader::for_test(&[0, 2, 0, 1]).num_docs_with_tokens(),
+            2
+        );
+        let data: Vec<u8> = [0u32, 300u32, 0u32]
+            .iter()
+            .flat_map(|fieldnorm| fieldnorm.to_le_bytes().to_vec())
+            .collect();
+        let exact_reader = FieldNormReader::from(ReaderImplEnum::Exact(OwnedBytes::new(data)));
+        assert_eq!(exact_reader.num_docs_with_tokens(), 1);
+        assert_eq!(FieldNormReader::constant(10, 1).num_docs_with_tokens(), 10);
+        assert_eq!(FieldNormReader::constant(10, 0).num_docs_with_tokens(), 0);
+    }
+
+    #[test]
+    fn test_exact_fieldnorms() {
+        let data: Vec<u8> = [1u32, 300u32, 1_000_000u32]
+            .iter()
+            .flat_map(|fieldnorm| fieldnorm.to_le_bytes().to_vec())
+            .collect();
+        let fieldnorm_reader = FieldNormReader::from(ReaderImplEnum::Exact(OwnedBytes::new(data)));
+        assert_eq!(fieldnorm_reader.num_docs(), 3);
+        assert_eq!(fieldnorm_reader.fieldnorm(1), 300);
+        assert_eq!(fieldnorm_reader.exact_fieldnorm(2), Some(1_000_000));
+        assert_eq!(fieldnorm_reader.fieldnorm_id(1), 72u8);
+    }
+
     #[test]
     fn test_const_fieldnorm_reader_small_fieldnorm_id() {
         let fieldnorm_reader = FieldNormReader::constant(1_000_000u32, 10u32);
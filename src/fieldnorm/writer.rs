@@ -2,17 +2,19 @@ use crate::{indexer::doc_id_mapping::DocIdMapping, DocId};
 
 use super::fieldnorm_to_id;
 use super::FieldNormsSerializer;
-use crate::schema::Field;
 use crate::schema::Schema;
+use crate::schema::{Field, FieldNormEncoding};
 use std::{io, iter};
 
 /// The `FieldNormsWriter` is in charge of tracking the fieldnorm byte
 /// of each document for each field with field norms.
 ///
 /// `FieldNormsWriter` stores a Vec<u8> for each tracked field, using a
-/// byte per document per field.
+/// byte per document per field, or 4 bytes for fields with exact fieldnorms.
 pub struct FieldNormsWriter {
     fields: Vec<Field>,
+    // Number of bytes per document for each field, or 0 if the field is not tracked.
+    num_bytes_per_doc: Vec<usize>,
     fieldnorms_buffer: Vec<Vec<u8>>,
 }
 
@@ -23,7 +25,10 @@ impl FieldNormsWriter {
         schema
             .fields()
             .filter_map(|(field, field_entry)| {
-                if field_entry.is_indexed() {
+                let fieldnorm_encoding = field_entry.field_type().get_fieldnorm_encoding();
+                if field_entry.is_indexed()
+                    && fieldnorm_encoding != Some(FieldNormEncoding::Disabled)
+                {
                     Some(field)
                 } else {
                     None
@@ -42,8 +47,22 @@ impl FieldNormsWriter {
             .max()
             .map(|max_field_id| max_field_id as usize + 1)
             .unwrap_or(0);
+        let mut num_bytes_per_doc = vec![0; max_field];
+        for &field in &fields {
+            let fieldnorm_encoding = schema
+                .get_field_entry(field)
+                .field_type()
+                .get_fieldnorm_encoding();
+            num_bytes_per_doc[field.field_id() as usize] =
+                if fieldnorm_encoding == Some(FieldNormEncoding::Exact) {
+                    4
+                } else {
+                    1
+                };
+        }
         FieldNormsWriter {
             fields,
+            num_bytes_per_doc,
             fieldnorms_buffer: iter::repeat_with(Vec::new)
                 .take(max_field)
                 .collect::<Vec<_>>(),
@@ -63,27 +82,45 @@ impl FieldNormsWriter {
     /// Will extend with 0-bytes for documents that have not been seen.
     pub fn fill_up_to_max_doc(&mut self, max_doc: DocId) {
         for field in self.fields.iter() {
-            self.fieldnorms_buffer[field.field_id() as usize].resize(max_doc as usize, 0u8);
+            let num_bytes = self.num_bytes_per_doc[field.field_id() as usize];
+            self.fieldnorms_buffer[field.field_id() as usize]
+                .resize(max_doc as usize * num_bytes, 0u8);
         }
     }
 
     /// Set the fieldnorm byte for the given document for the given field.
     ///
     /// Will internally convert the u32 `fieldnorm` value to the appropriate byte
-    /// to approximate the field norm in less space.
+    /// to approximate the field norm in less space, unless the field
+    /// has exact fieldnorms.
+    ///
+    /// Fields for which fieldnorms are disabled are ignored.
     ///
     /// * doc       - the document id
     /// * field     - the field being set
     /// * fieldnorm - the number of terms present in document `doc` in field `field`
     pub fn record(&mut self, doc: DocId, field: Field, fieldnorm: u32) {
+        let num_bytes = self
+            .num_bytes_per_doc
+            .get(field.field_id() as usize)
+            .cloned()
+            .unwrap_or(0);
+        if num_bytes == 0 {
+            return;
+        }
         let fieldnorm_buffer: &mut Vec<u8> = &mut self.fieldnorms_buffer[field.field_id() as usize];
+        let start = doc as usize * num_bytes;
         assert!(
-            fieldnorm_buffer.len() <= doc as usize,
+            fieldnorm_buffer.len() <= start,
             "Cannot register a given fieldnorm twice"
         );
         // we fill intermediary `DocId` as  having a fieldnorm of 0.
-        fieldnorm_buffer.resize(doc as usize + 1, 0u8);
-        fieldnorm_buffer[doc as usize] = fieldnorm_to_id(fieldnorm);
+        fieldnorm_buffer.resize(start + num_bytes, 0u8);
+        if num_bytes == 1 {
+            fieldnorm_buffer[start] = fieldnorm_to_id(fieldnorm);
+        } else {
+            fieldnorm_buffer[start..].copy_from_slice(&fieldnorm.to_le_bytes());
+        }
     }
 
     /// Serialize the seen fieldnorm values to the serializer for all fields.
@@ -95,10 +132,14 @@ impl FieldNormsWriter {
         for &field in self.fields.iter() {
             let fieldnorm_values: &[u8] = &self.fieldnorms_buffer[field.field_id() as usize][..];
             if let Some(doc_id_map) = doc_id_map {
+                let num_bytes = self.num_bytes_per_doc[field.field_id() as usize];
                 let mut mapped_fieldnorm_values = vec![];
                 mapped_fieldnorm_values.resize(fieldnorm_values.len(), 0u8);
                 for (new_doc_id, old_doc_id) in doc_id_map.iter_old_doc_ids().enumerate() {
-                    mapped_fieldnorm_values[new_doc_id] = fieldnorm_values[old_doc_id as usize];
+                    let new_start = new_doc_id * num_bytes;
+                    let old_start = old_doc_id as usize * num_bytes;
+                    mapped_fieldnorm_values[new_start..new_start + num_bytes]
+                        .copy_from_slice(&fieldnorm_values[old_start..old_start + num_bytes]);
                 }
                 fieldnorms_serializer.serialize_field(field, &mapped_fieldnorm_values)?;
             } else {
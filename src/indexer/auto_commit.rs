@@ -0,0 +1,364 @@
+use crate::schema::{Document, Value};
+use crate::{IndexWriter, Opstamp};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Changes added to an `IndexWriter` since its last commit.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PendingChanges {
+    /// Number of documents added since the last commit.
+    pub num_docs: u64,
+    /// Number of delete operations since the last commit.
+    pub num_deletes: u64,
+    /// Estimated number of bytes of the documents added since the last commit.
+    ///
+    /// The estimate is the size of the values of the documents.
+    pub num_bytes: u64,
+    /// Time elapsed since the last commit.
+    pub elapsed: Duration,
+}
+
+impl PendingChanges {
+    /// Returns true iff there are no pending changes.
+    pub fn is_empty(&self) -> bool {
+        self.num_docs == 0 && self.num_deletes == 0
+    }
+}
+
+/// Listener of the auto-commits of an `IndexWriter`.
+///
+/// Closures taking the result of the commit can be used as listeners.
+pub trait AutoCommitListener: Send + Sync + 'static {
+    /// Called before an auto-commit, with the changes that triggered it.
+    ///
+    /// Returning an error cancels the auto-commit. It is attempted again on the
+    /// next call to `IndexWriter::maybe_auto_commit`.
+    fn before_commit(&self, _pending_changes: &PendingChanges) -> crate::Result<()> {
+        Ok(())
+    }
+
+    /// Called after an auto-commit, with its result.
+    fn after_commit(&self, commit_result: &crate::Result<Opstamp>);
+}
+
+impl<F> AutoCommitListener for F
+where
+    F: Fn(&crate::Result<Opstamp>) + Send + Sync + 'static,
+{
+    fn after_commit(&self, commit_result: &crate::Result<Opstamp>) {
+        self(commit_result)
+    }
+}
+
+/// Policy deciding when an `IndexWriter` commits on its own.
+///
+/// A commit is triggered as soon as one of the configured thresholds is reached:
+/// a number of added documents, an estimated number of bytes of added documents,
+/// or a time interval since the last commit (if there are pending changes).
+/// A random jitter can be added to the interval, so that the writers of several
+/// indexes started together do not all commit at the same time.
+///
+/// The policy is installed with
+/// [`IndexWriter::set_auto_commit_policy`](./struct.IndexWriter.html#method.set_auto_commit_policy),
+/// and evaluated by `IndexWriter::maybe_auto_commit`, which can be called
+/// periodically from a background thread with [`AutoCommitThread`](./struct.AutoCommitThread.html).
+#[derive(Clone, Default)]
+pub struct AutoCommitPolicy {
+    max_num_docs: Option<u64>,
+    max_num_bytes: Option<u64>,
+    interval: Option<Duration>,
+    jitter: Duration,
+    listener: Option<Arc<dyn AutoCommitListener>>,
+}
+
+impl AutoCommitPolicy {
+    /// Creates a policy that never commits, until thresholds are set.
+    pub fn new() -> AutoCommitPolicy {
+        AutoCommitPolicy::default()
+    }
+
+    /// Commits once `num_docs` documents were added since the last commit.
+    pub fn every_num_docs(mut self, num_docs: u64) -> AutoCommitPolicy {
+        self.max_num_docs = Some(num_docs);
+        self
+    }
+
+    /// Commits once the documents added since the last commit are
+    /// estimated to weigh `num_bytes` bytes.
+    pub fn every_num_bytes(mut self, num_bytes: u64) -> AutoCommitPolicy {
+        self.max_num_bytes = Some(num_bytes);
+        self
+    }
+
+    /// Commits the pending changes once `interval` elapsed since the last commit.
+    pub fn every_interval(mut self, interval: Duration) -> AutoCommitPolicy {
+        self.interval = Some(interval);
+        self
+    }
+
+    /// Adds a random duration between 0 and `jitter` to the interval between commits.
+    pub fn with_jitter(mut self, jitter: Duration) -> AutoCommitPolicy {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Sets a listener called around each auto-commit.
+    pub fn with_listener<L: AutoCommitListener>(mut self, listener: L) -> AutoCommitPolicy {
+        self.listener = Some(Arc::new(listener));
+        self
+    }
+
+    pub(crate) fn listener(&self) -> Option<Arc<dyn AutoCommitListener>> {
+        self.listener.clone()
+    }
+
+    fn random_jitter(&self) -> Duration {
+        if self.jitter == Duration::default() {
+            return self.jitter;
+        }
+        let jitter_nanos = self.jitter.as_nanos().min(u128::from(u64::max_value() - 1)) as u64;
+        // Hashers are built with random keys.
+        let random = RandomState::new().build_hasher().finish();
+        Duration::from_nanos(random % (jitter_nanos + 1))
+    }
+}
+
+/// Changes tracked by an `IndexWriter` with an auto-commit policy.
+pub(crate) struct AutoCommitState {
+    policy: AutoCommitPolicy,
+    num_docs: AtomicU64,
+    num_deletes: AtomicU64,
+    num_bytes: AtomicU64,
+    // Time of the last commit, and interval to wait for after it.
+    next_commit: Mutex<(Instant, Option<Duration>)>,
+}
+
+impl AutoCommitState {
+    pub fn new(policy: AutoCommitPolicy) -> AutoCommitState {
+        let state = AutoCommitState {
+            policy,
+            num_docs: AtomicU64::new(0),
+            num_deletes: AtomicU64::new(0),
+            num_bytes: AtomicU64::new(0),
+            next_commit: Mutex::new((Instant::now(), None)),
+        };
+        state.reset();
+        state
+    }
+
+    pub fn policy(&self) -> &AutoCommitPolicy {
+        &self.policy
+    }
+
+    pub fn record_document(&self, document: &Document) {
+        self.num_docs.fetch_add(1, Ordering::Relaxed);
+        self.num_bytes
+            .fetch_add(estimated_num_bytes(document), Ordering::Relaxed);
+    }
+
+    pub fn record_delete(&self) {
+        self.num_deletes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Forgets the pending changes, after a commit or a rollback.
+    pub fn reset(&self) {
+        self.num_docs.store(0, Ordering::Relaxed);
+        self.num_deletes.store(0, Ordering::Relaxed);
+        self.num_bytes.store(0, Ordering::Relaxed);
+        let interval = self
+            .policy
+            .interval
+            .map(|interval| interval + self.policy.random_jitter());
+        *self.next_commit.lock().expect("Auto-commit lock poisoned") = (Instant::now(), interval);
+    }
+
+    /// Returns the pending changes if they should be committed.
+    pub fn due_changes(&self) -> Option<PendingChanges> {
+        let (last_commit, interval) = *self.next_commit.lock().expect("Auto-commit lock poisoned");
+        let pending_changes = PendingChanges {
+            num_docs: self.num_docs.load(Ordering::Relaxed),
+            num_deletes: self.num_deletes.load(Ordering::Relaxed),
+            num_bytes: self.num_bytes.load(Ordering::Relaxed),
+            elapsed: last_commit.elapsed(),
+        };
+        if pending_changes.is_empty() {
+            return None;
+        }
+        let is_due = self.policy.max_num_docs.map_or(false, |max_num_docs| {
+            pending_changes.num_docs >= max_num_docs
+        }) || self.policy.max_num_bytes.map_or(false, |max_num_bytes| {
+            pending_changes.num_bytes >= max_num_bytes
+        }) || interval.map_or(false, |interval| pending_changes.elapsed >= interval);
+        if is_due {
+            Some(pending_changes)
+        } else {
+            None
+        }
+    }
+}
+
+fn estimated_num_bytes(document: &Document) -> u64 {
+    document
+        .field_values()
+        .iter()
+        .map(|field_value| match field_value.value() {
+            Value::Str(text) => text.len(),
+            Value::PreTokStr(pre_tokenized) => pre_tokenized.text.len(),
+            Value::Facet(facet) => facet.encoded_str().len(),
+            Value::Bytes(bytes) => bytes.len(),
+            Value::U64(_) | Value::I64(_) | Value::F64(_) | Value::Date(_) => 8,
+        } as u64)
+        .sum()
+}
+
+/// Background thread calling `IndexWriter::maybe_auto_commit` periodically
+/// on a shared `IndexWriter`.
+///
+/// Time-based auto-commits then happen even if no document is added.
+/// The thread is stopped when the `AutoCommitThread` is dropped.
+pub struct AutoCommitThread {
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    join_handle: Option<JoinHandle<()>>,
+}
+
+impl AutoCommitThread {
+    /// Spawns a thread checking the auto-commit policy of `index_writer`
+    /// every `poll_interval`.
+    ///
+    /// Failed auto-commits are logged, and reported to the listener of the policy.
+    pub fn spawn(
+        index_writer: Arc<RwLock<IndexWriter>>,
+        poll_interval: Duration,
+    ) -> io::Result<AutoCommitThread> {
+        let stop = Arc::new((Mutex::new(false), Condvar::new()));
+        let thread_stop = stop.clone();
+        let join_handle = thread::Builder::new()
+            .name("thrd-tantivy-autocommit".to_string())
+            .spawn(move || {
+                let (stopped_lock, stopped_cvar) = &*thread_stop;
+                let mut stopped = stopped_lock.lock().expect("Auto-commit lock poisoned");
+                loop {
+                    stopped = stopped_cvar
+                        .wait_timeout(stopped, poll_interval)
+                        .expect("Auto-commit lock poisoned")
+                        .0;
+                    if *stopped {
+                        return;
+                    }
+                    let mut index_writer = index_writer.write().expect("IndexWriter lock poisoned");
+                    if let Err(error) = index_writer.maybe_auto_commit() {
+                        error!("Auto-commit failed: {:?}", error);
+                    }
+                }
+            })?;
+        Ok(AutoCommitThread {
+            stop,
+            join_handle: Some(join_handle),
+        })
+    }
+}
+
+impl Drop for AutoCommitThread {
+    fn drop(&mut self) {
+        let (stopped_lock, stopped_cvar) = &*self.stop;
+        *stopped_lock.lock().expect("Auto-commit lock poisoned") = true;
+        stopped_cvar.notify_one();
+        if let Some(join_handle) = self.join_handle.take() {
+            let _ = join_handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AutoCommitPolicy, AutoCommitThread, PendingChanges};
+    use crate::schema::{Schema, TEXT};
+    use crate::{Index, Opstamp};
+    use std::sync::{Arc, Mutex, RwLock};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_auto_commit_every_num_docs() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let commit_results: Arc<Mutex<Vec<Opstamp>>> = Arc::default();
+        let listener_results = commit_results.clone();
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_auto_commit_policy(
+            AutoCommitPolicy::new().every_num_docs(3).with_listener(
+                move |commit_result: &crate::Result<Opstamp>| {
+                    listener_results
+                        .lock()
+                        .unwrap()
+                        .push(*commit_result.as_ref().unwrap());
+                },
+            ),
+        );
+        assert_eq!(index_writer.maybe_auto_commit()?, None);
+        for _ in 0..2 {
+            index_writer.add_document(doc!(text_field => "a"));
+        }
+        assert_eq!(index_writer.maybe_auto_commit()?, None);
+        index_writer.add_document(doc!(text_field => "a"));
+        let opstamp = index_writer.maybe_auto_commit()?;
+        assert!(opstamp.is_some());
+        assert_eq!(*commit_results.lock().unwrap(), vec![opstamp.unwrap()]);
+        assert_eq!(index.reader()?.searcher().num_docs(), 3);
+        // The counters are reset by manual commits too.
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text_field => "a"));
+        assert_eq!(index_writer.maybe_auto_commit()?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_auto_commit_thread() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_auto_commit_policy(
+            AutoCommitPolicy::new()
+                .every_interval(Duration::from_millis(20))
+                .with_jitter(Duration::from_millis(10)),
+        );
+        let index_writer = Arc::new(RwLock::new(index_writer));
+        let auto_commit_thread =
+            AutoCommitThread::spawn(index_writer.clone(), Duration::from_millis(5))?;
+        index_writer
+            .read()
+            .unwrap()
+            .add_document(doc!(text_field => "a"));
+        let reader = index.reader()?;
+        for _ in 0..200 {
+            reader.reload()?;
+            if reader.searcher().num_docs() == 1 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(reader.searcher().num_docs(), 1);
+        drop(auto_commit_thread);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pending_changes_is_empty() {
+        let pending_changes = PendingChanges {
+            num_docs: 0,
+            num_deletes: 0,
+            num_bytes: 0,
+            elapsed: Duration::from_secs(3),
+        };
+        assert!(pending_changes.is_empty());
+    }
+}
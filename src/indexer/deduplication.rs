@@ -0,0 +1,174 @@
+use crate::common::BinarySerializable;
+use crate::schema::{Document, Field, FieldType, Schema, Term};
+use crate::TantivyError;
+use fnv::FnvHasher;
+use std::collections::HashSet;
+use std::hash::Hasher;
+
+/// What the `IndexWriter` does with a document whose content hash was already seen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DedupMode {
+    /// The document is silently skipped.
+    Skip,
+    /// The documents previously added with the same hash are deleted,
+    /// and the document is added.
+    ///
+    /// The writer stores the hash of each document in the given field, which needs
+    /// to be an indexed `u64` field, and must not be set on the added documents.
+    Upsert(Field),
+}
+
+/// Deduplication of the documents added to an `IndexWriter`, by content hash.
+///
+/// The hash of a document is computed over the values of the selected fields.
+/// Documents without any value in these fields are never deduplicated.
+///
+/// A document is a duplicate if a document with the same hash was added since the
+/// last commit, or by the last commit. The hashes of the documents added by a commit
+/// are persisted in the commit, so that replaying the documents added right before a
+/// crash or a restart does not create duplicates.
+///
+/// Deduplication is enabled using
+/// [`IndexWriter::set_deduplication`](./struct.IndexWriter.html#method.set_deduplication).
+#[derive(Clone, Debug)]
+pub struct Deduplication {
+    fields: Vec<Field>,
+    mode: DedupMode,
+}
+
+impl Deduplication {
+    /// Creates a deduplication hashing the values of `fields`.
+    pub fn new(fields: Vec<Field>, mode: DedupMode) -> Deduplication {
+        Deduplication { fields, mode }
+    }
+
+    /// Returns the fields whose values are hashed.
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// Returns what is done with the duplicate documents.
+    pub fn mode(&self) -> DedupMode {
+        self.mode
+    }
+
+    /// Checks that there is at least one hashed field, and that the hash field
+    /// of the `Upsert` mode is an indexed `u64` field.
+    pub(crate) fn validate(&self, schema: &Schema) -> crate::Result<()> {
+        if self.fields.is_empty() {
+            return Err(TantivyError::InvalidArgument(
+                "Deduplication requires at least one field to hash".to_string(),
+            ));
+        }
+        if let DedupMode::Upsert(hash_field) = self.mode {
+            let field_entry = schema.get_field_entry(hash_field);
+            let is_valid =
+                matches!(field_entry.field_type(), FieldType::U64(_)) && field_entry.is_indexed();
+            if !is_valid || self.fields.contains(&hash_field) {
+                return Err(TantivyError::SchemaError(format!(
+                    "The deduplication hash field {:?} needs to be an indexed u64 field, \
+                     that is not hashed",
+                    field_entry.name()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the hash of the values of the selected fields of `document`,
+    /// or `None` if the document has no such value.
+    fn hash(&self, document: &Document) -> Option<u64> {
+        let mut hasher = FnvHasher::default();
+        let mut num_values = 0u64;
+        let mut buffer = Vec::new();
+        for &field in &self.fields {
+            hasher.write_u32(field.field_id());
+            for value in document.get_all(field) {
+                buffer.clear();
+                value
+                    .serialize(&mut buffer)
+                    .expect("Serializing a value in memory should never fail");
+                hasher.write(&buffer);
+                num_values += 1;
+            }
+            // Separates the values of consecutive fields.
+            hasher.write_u64(num_values);
+        }
+        if num_values == 0 {
+            return None;
+        }
+        Some(hasher.finish())
+    }
+}
+
+/// What the `IndexWriter` should do with an added document.
+pub(crate) enum DedupDecision {
+    /// The document is not a duplicate.
+    Add,
+    /// The document is a duplicate, and should be skipped.
+    Skip,
+    /// The document is a duplicate. The documents matching the term should be
+    /// deleted before adding it.
+    Replace(Term),
+}
+
+/// Keeps track of the hashes of the documents added by the last commit,
+/// and since the last commit.
+pub(crate) struct Deduplicator {
+    deduplication: Deduplication,
+    committed_hashes: HashSet<u64>,
+    pending_hashes: HashSet<u64>,
+}
+
+impl Deduplicator {
+    pub fn new(deduplication: Deduplication, committed_hashes: &[u64]) -> Deduplicator {
+        Deduplicator {
+            deduplication,
+            committed_hashes: committed_hashes.iter().cloned().collect(),
+            pending_hashes: HashSet::new(),
+        }
+    }
+
+    pub fn deduplication(&self) -> &Deduplication {
+        &self.deduplication
+    }
+
+    /// Records the hash of `document`, and decides what to do with it.
+    ///
+    /// In `Upsert` mode, the hash is added to the document.
+    pub fn deduplicate(&mut self, document: &mut Document) -> DedupDecision {
+        let hash = if let Some(hash) = self.deduplication.hash(document) {
+            hash
+        } else {
+            return DedupDecision::Add;
+        };
+        // The hash is recorded even if it was committed, so that it remains known
+        // after the next commit.
+        let is_pending_duplicate = !self.pending_hashes.insert(hash);
+        let is_duplicate = is_pending_duplicate || self.committed_hashes.contains(&hash);
+        match self.deduplication.mode {
+            DedupMode::Skip if is_duplicate => DedupDecision::Skip,
+            DedupMode::Skip => DedupDecision::Add,
+            DedupMode::Upsert(hash_field) => {
+                document.add_u64(hash_field, hash);
+                if is_duplicate {
+                    DedupDecision::Replace(Term::from_field_u64(hash_field, hash))
+                } else {
+                    DedupDecision::Add
+                }
+            }
+        }
+    }
+
+    /// Returns the hashes of the documents added since the last commit, sorted.
+    pub fn pending_hashes(&self) -> Vec<u64> {
+        let mut pending_hashes: Vec<u64> = self.pending_hashes.iter().cloned().collect();
+        pending_hashes.sort_unstable();
+        pending_hashes
+    }
+
+    /// Makes the hashes of the documents added since the last commit the committed ones.
+    pub fn commit(&mut self) {
+        self.committed_hashes = std::mem::take(&mut self.pending_hashes);
+    }
+}
@@ -2,7 +2,7 @@ use super::operation::DeleteOperation;
 use crate::Opstamp;
 
 use std::ops::DerefMut;
-use std::sync::{Arc, RwLock, Weak};
+use std::sync::{Arc, PoisonError, RwLock, Weak};
 
 // The DeleteQueue is similar in conceptually to a multiple
 // consumer single producer broadcast channel.
@@ -19,6 +19,9 @@ use std::sync::{Arc, RwLock, Weak};
 // - cloning an existing cursor returns a new cursor, that
 //   is at the exact same position, and can now advance independently
 //   from the original cursor.
+// The locks of the delete queue only guard appends and swaps of
+// blocks, which cannot be left halfway. A poisoned lock (i.e. a thread
+// panicked while holding it) is therefore safe to keep using.
 #[derive(Default)]
 struct InnerDeleteQueue {
     writer: Vec<DeleteOperation>,
@@ -41,14 +44,14 @@ impl DeleteQueue {
     fn get_last_block(&self) -> Arc<Block> {
         {
             // try get the last block with simply acquiring the read lock.
-            let rlock = self.inner.read().unwrap();
+            let rlock = self.inner.read().unwrap_or_else(PoisonError::into_inner);
             if let Some(block) = rlock.last_block.upgrade() {
                 return block;
             }
         }
         // It failed. Let's double check after acquiring the write, as someone could have called
         // `get_last_block` right after we released the rlock.
-        let mut wlock = self.inner.write().unwrap();
+        let mut wlock = self.inner.write().unwrap_or_else(PoisonError::into_inner);
         if let Some(block) = wlock.last_block.upgrade() {
             return block;
         }
@@ -77,7 +80,7 @@ impl DeleteQueue {
     pub fn push(&self, delete_operation: DeleteOperation) {
         self.inner
             .write()
-            .expect("Failed to acquire write lock on delete queue writer")
+            .unwrap_or_else(PoisonError::into_inner)
             .writer
             .push(delete_operation);
     }
@@ -96,10 +99,7 @@ impl DeleteQueue {
     // be some unflushed operations.
     //
     fn flush(&self) -> Option<Arc<Block>> {
-        let mut self_wlock = self
-            .inner
-            .write()
-            .expect("Failed to acquire write lock on delete queue writer");
+        let mut self_wlock = self.inner.write().unwrap_or_else(PoisonError::into_inner);
 
         if self_wlock.writer.is_empty() {
             return None;
@@ -133,20 +133,14 @@ impl From<DeleteQueue> for NextBlock {
 impl NextBlock {
     fn next_block(&self) -> Option<Arc<Block>> {
         {
-            let next_read_lock = self
-                .0
-                .read()
-                .expect("Failed to acquire write lock in delete queue");
+            let next_read_lock = self.0.read().unwrap_or_else(PoisonError::into_inner);
             if let InnerNextBlock::Closed(ref block) = *next_read_lock {
                 return Some(Arc::clone(block));
             }
         }
         let next_block;
         {
-            let mut next_write_lock = self
-                .0
-                .write()
-                .expect("Failed to acquire write lock in delete queue");
+            let mut next_write_lock = self.0.write().unwrap_or_else(PoisonError::into_inner);
             match *next_write_lock {
                 InnerNextBlock::Closed(ref block) => {
                     return Some(Arc::clone(block));
@@ -248,6 +242,7 @@ impl DeleteCursor {
 mod tests {
 
     use super::{DeleteOperation, DeleteQueue};
+    use crate::indexer::operation::DeleteTarget;
     use crate::schema::{Field, Term};
 
     #[test]
@@ -258,7 +253,7 @@ mod tests {
             let field = Field::from_field_id(1u32);
             DeleteOperation {
                 opstamp: i as u64,
-                term: Term::from_field_u64(field, i as u64),
+                target: DeleteTarget::Term(Term::from_field_u64(field, i as u64)),
             }
         };
 
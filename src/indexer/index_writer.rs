@@ -1,6 +1,8 @@
 use super::operation::{AddOperation, UserOperation};
 use super::segment_updater::SegmentUpdater;
 use super::PreparedCommit;
+use crate::check::{check_segment, IndexCheck};
+use crate::common::numeric_trie::is_full_precision_term;
 use crate::common::BitSet;
 use crate::core::Index;
 use crate::core::Segment;
@@ -8,32 +10,42 @@ use crate::core::SegmentComponent;
 use crate::core::SegmentId;
 use crate::core::SegmentMeta;
 use crate::core::SegmentReader;
-use crate::directory::TerminatingWrite;
-use crate::directory::{DirectoryLock, GarbageCollectionResult};
+use crate::directory::{Directory, TerminatingWrite};
+use crate::directory::{DirectoryLock, GarbageCollectionResult, ManagedDirectory};
 use crate::docset::{DocSet, TERMINATED};
-use crate::error::TantivyError;
-use crate::fastfield::write_delete_bitset;
+use crate::error::{DataCorruption, TantivyError};
+use crate::fastfield::FastFieldReader;
+use crate::fastfield::{write_delete_bitset, write_deleted_docs};
+use crate::indexer::auto_commit::{AutoCommitPolicy, AutoCommitState};
+use crate::indexer::deduplication::{DedupDecision, Deduplication, Deduplicator};
 use crate::indexer::delete_queue::{DeleteCursor, DeleteQueue};
 use crate::indexer::doc_opstamp_mapping::DocToOpstampMapping;
-use crate::indexer::operation::DeleteOperation;
+use crate::indexer::index_writer_handle::{HandleState, IndexWriterHandle, SharedHandleState};
+use crate::indexer::operation::{DeleteOperation, DeleteTarget};
+use crate::indexer::retention_policy::RetentionPolicy;
 use crate::indexer::stamper::Stamper;
-use crate::indexer::MergePolicy;
 use crate::indexer::SegmentEntry;
 use crate::indexer::SegmentWriter;
+use crate::indexer::{MergeListener, MergePolicy};
 use crate::schema::Document;
 use crate::schema::IndexRecordOption;
 use crate::schema::Term;
+use crate::schema::{Field, Value};
 use crate::Opstamp;
 use crossbeam::channel;
+use fnv::FnvHasher;
 use futures::executor::block_on;
-use futures::future::Future;
 use smallvec::smallvec;
 use smallvec::SmallVec;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
 use std::mem;
 use std::ops::Range;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::thread::JoinHandle;
+use std::time::Duration;
 
 // Size of the margin for the heap. A segment is closed when the remaining memory
 // in the heap goes below MARGIN_IN_BYTES.
@@ -50,6 +62,14 @@ pub const MAX_NUM_THREAD: usize = 8;
 // reaches `PIPELINE_MAX_SIZE_IN_DOCS`
 const PIPELINE_MAX_SIZE_IN_DOCS: usize = 10_000;
 
+// Number of routing partitions of the indexes that do not set
+// `IndexSettings::num_routing_partitions`.
+const DEFAULT_NUM_ROUTING_PARTITIONS: u32 = 4;
+
+// Maximum number of delete files of a segment. Past this number,
+// all of the deleted docs of the segment are rewritten in a single file.
+const MAX_NUM_DELETE_FILES: usize = 8;
+
 // Group of operations.
 // Most of the time, users will send operation one-by-one, but it can be useful to
 // send them as a small block to ensure that
@@ -57,9 +77,137 @@ const PIPELINE_MAX_SIZE_IN_DOCS: usize = 10_000;
 // - all operations in the group are committed at the same time, making the group
 // atomic.
 type OperationGroup = SmallVec<[AddOperation; 4]>;
-type OperationSender = channel::Sender<OperationGroup>;
+pub(crate) type OperationSender = channel::Sender<OperationGroup>;
 type OperationReceiver = channel::Receiver<OperationGroup>;
 
+// Operation groups received by an indexing worker, along with the routing
+// partition they were sent to. Groups that were not routed are associated to `None`.
+type ReceivedOperationGroup = (Option<u32>, OperationGroup);
+
+/// Error returned by `IndexWriter::try_add_document`.
+#[derive(Debug, Error)]
+pub enum TryAddDocumentError {
+    /// The indexing pipeline is full, and adding the document would block.
+    ///
+    /// The document is handed back, so that it can be added later.
+    #[error("The indexing pipeline is full")]
+    WouldBlock(Document),
+    /// The document could not be sent to the indexing workers.
+    #[error("{0}")]
+    Error(#[from] TantivyError),
+}
+
+fn send_error_to_tantivy_error<T>(send_error: channel::SendError<T>) -> TantivyError {
+    TantivyError::ErrorInThread(format!(
+        "Failed to index documents. Sending to indexing channel failed. This probably means \
+         all of the indexing threads have panicked. {:?}",
+        send_error
+    ))
+}
+
+/// Returns the routing partition associated to a given routing key.
+fn compute_routing_partition<K: Hash + ?Sized>(routing_key: &K, num_partitions: usize) -> usize {
+    let mut hasher = FnvHasher::default();
+    routing_key.hash(&mut hasher);
+    (hasher.finish() % num_partitions as u64) as usize
+}
+
+/// Iterates over the operations dispatched to an indexing worker.
+///
+/// The worker consumes both the queue shared by all workers, and
+/// the queues dedicated to the documents routed to its partitions.
+/// The iterator ends when all of these queues are closed.
+struct WorkerOperations {
+    shared_receiver: Option<OperationReceiver>,
+    routed_receivers: Vec<(u32, OperationReceiver)>,
+}
+
+impl Iterator for WorkerOperations {
+    type Item = ReceivedOperationGroup;
+
+    fn next(&mut self) -> Option<ReceivedOperationGroup> {
+        loop {
+            if self.shared_receiver.is_none() && self.routed_receivers.is_empty() {
+                return None;
+            }
+            let (routed_ordinal_opt, received_group) = {
+                let mut select = channel::Select::new();
+                if let Some(shared_receiver) = self.shared_receiver.as_ref() {
+                    select.recv(shared_receiver);
+                }
+                for (_, routed_receiver) in &self.routed_receivers {
+                    select.recv(routed_receiver);
+                }
+                let selected_operation = select.select();
+                // The shared queue, if any, is the first selected queue.
+                let routed_ordinal_opt = if self.shared_receiver.is_some() {
+                    selected_operation.index().checked_sub(1)
+                } else {
+                    Some(selected_operation.index())
+                };
+                let receiver = if let Some(routed_ordinal) = routed_ordinal_opt {
+                    &self.routed_receivers[routed_ordinal].1
+                } else {
+                    self.shared_receiver
+                        .as_ref()
+                        .expect("The shared queue was selected. This is a bug, please report.")
+                };
+                (routed_ordinal_opt, selected_operation.recv(receiver))
+            };
+            match (routed_ordinal_opt, received_group) {
+                (None, Ok(group)) => {
+                    return Some((None, group));
+                }
+                (Some(routed_ordinal), Ok(group)) => {
+                    return Some((Some(self.routed_receivers[routed_ordinal].0), group));
+                }
+                // The shared queue is closed.
+                (None, Err(_)) => {
+                    self.shared_receiver = None;
+                }
+                // The routed queue is closed.
+                (Some(routed_ordinal), Err(_)) => {
+                    self.routed_receivers.remove(routed_ordinal);
+                }
+            }
+        }
+    }
+}
+
+/// Iterates over the operations pending in the queues of a single-threaded `IndexWriter`,
+/// without waiting for new operations.
+///
+/// Single-threaded writers consume the queues of all of the routing partitions.
+struct PendingOperations {
+    shared_receiver: OperationReceiver,
+    routed_receivers: Vec<OperationReceiver>,
+}
+
+impl Iterator for PendingOperations {
+    type Item = ReceivedOperationGroup;
+
+    fn next(&mut self) -> Option<ReceivedOperationGroup> {
+        if let Ok(group) = self.shared_receiver.try_recv() {
+            return Some((None, group));
+        }
+        self.routed_receivers
+            .iter()
+            .enumerate()
+            .find_map(|(routing_partition, routed_receiver)| {
+                let group = routed_receiver.try_recv().ok()?;
+                Some((Some(routing_partition as u32), group))
+            })
+    }
+}
+
+/// State of the indexing done on the calling thread by single-threaded writers.
+struct InlineIndexer {
+    delete_cursor: DeleteCursor,
+    // First error encountered while indexing the pending operations because
+    // the indexing pipeline was full. It is returned by the next commit.
+    error: Option<TantivyError>,
+}
+
 /// `IndexWriter` is the user entry-point to add document to an index.
 ///
 /// It manages a small number of indexing thread, as well as a shared
@@ -80,6 +228,10 @@ pub struct IndexWriter {
     operation_receiver: OperationReceiver,
     operation_sender: OperationSender,
 
+    // One queue per indexing worker, for documents added with a routing key.
+    routed_operation_receivers: Vec<OperationReceiver>,
+    routed_operation_senders: Vec<OperationSender>,
+
     segment_updater: SegmentUpdater,
 
     worker_id: usize,
@@ -90,6 +242,21 @@ pub struct IndexWriter {
 
     stamper: Stamper,
     committed_opstamp: Opstamp,
+
+    deduplicator: Option<Mutex<Deduplicator>>,
+
+    auto_commit: Option<Arc<AutoCommitState>>,
+
+    handle_state: SharedHandleState,
+
+    // Only set for single-threaded writers, which index the pending operations
+    // on the calling thread instead of using indexing workers.
+    inline_indexer: Option<Mutex<InlineIndexer>>,
+
+    // Set when the indexing of some of the pending operations failed.
+    // Committing would then publish a partial batch, so commits are
+    // rejected until the writer is rolled back.
+    indexing_failure: Option<String>,
 }
 
 fn compute_deleted_bitset(
@@ -107,17 +274,39 @@ fn compute_deleted_bitset(
 
         // A delete operation should only affect
         // document that were inserted before it.
-        let inverted_index = segment_reader.inverted_index(delete_op.term.field())?;
-        if let Some(mut docset) =
-            inverted_index.read_postings(&delete_op.term, IndexRecordOption::Basic)?
-        {
-            let mut doc_matching_deleted_term = docset.doc();
-            while doc_matching_deleted_term != TERMINATED {
-                if doc_opstamps.is_deleted(doc_matching_deleted_term, delete_op.opstamp) {
-                    delete_bitset.insert(doc_matching_deleted_term);
-                    might_have_changed = true;
+        match &delete_op.target {
+            DeleteTarget::Term(term) => {
+                let inverted_index = segment_reader.inverted_index(term.field())?;
+                if let Some(mut docset) =
+                    inverted_index.read_postings(term, IndexRecordOption::Basic)?
+                {
+                    let mut doc_matching_deleted_term = docset.doc();
+                    while doc_matching_deleted_term != TERMINATED {
+                        if doc_opstamps.is_deleted(doc_matching_deleted_term, delete_op.opstamp) {
+                            delete_bitset.insert(doc_matching_deleted_term);
+                            might_have_changed = true;
+                        }
+                        doc_matching_deleted_term = docset.advance();
+                    }
                 }
-                doc_matching_deleted_term = docset.advance();
+            }
+            DeleteTarget::Terms(terms) => {
+                might_have_changed |= delete_sorted_terms(
+                    delete_bitset,
+                    segment_reader,
+                    terms,
+                    doc_opstamps,
+                    delete_op.opstamp,
+                )?;
+            }
+            DeleteTarget::TermRange(term_range) => {
+                might_have_changed |= delete_term_range(
+                    delete_bitset,
+                    segment_reader,
+                    term_range,
+                    doc_opstamps,
+                    delete_op.opstamp,
+                )?;
             }
         }
         delete_cursor.advance();
@@ -125,6 +314,121 @@ fn compute_deleted_bitset(
     Ok(might_have_changed)
 }
 
+// Deletes the documents containing any of the given sorted terms.
+//
+// Rather than looking up each term in the term dictionary, the dictionary of each field
+// is streamed once over the range of the terms of the field, and merged with the terms.
+fn delete_sorted_terms(
+    delete_bitset: &mut BitSet,
+    segment_reader: &SegmentReader,
+    terms: &[Term],
+    doc_opstamps: &DocToOpstampMapping,
+    delete_opstamp: Opstamp,
+) -> crate::Result<bool> {
+    let mut might_have_changed = false;
+    let mut remaining_terms = terms;
+    while let Some(first_term) = remaining_terms.first() {
+        // Sorted terms are grouped by field, as terms start with their field.
+        let field = first_term.field();
+        let num_field_terms = remaining_terms
+            .iter()
+            .take_while(|term| term.field() == field)
+            .count();
+        let (field_terms, other_terms) = remaining_terms.split_at(num_field_terms);
+        remaining_terms = other_terms;
+        let inverted_index = segment_reader.inverted_index(field)?;
+        let last_term = &field_terms[num_field_terms - 1];
+        let mut term_stream = inverted_index
+            .terms()
+            .range()
+            .ge(first_term.value_bytes())
+            .le(last_term.value_bytes())
+            .into_stream()?;
+        let mut field_terms_it = field_terms.iter().map(Term::value_bytes).peekable();
+        while term_stream.advance() {
+            let key = term_stream.key();
+            // Skips the terms that are not in the dictionary.
+            while field_terms_it
+                .peek()
+                .map_or(false, |term_bytes| *term_bytes < key)
+            {
+                field_terms_it.next();
+            }
+            match field_terms_it.peek() {
+                Some(term_bytes) if *term_bytes == key => {}
+                Some(_) => continue,
+                None => break,
+            }
+            let mut block_postings = inverted_index
+                .read_block_postings_from_terminfo(term_stream.value(), IndexRecordOption::Basic)?;
+            loop {
+                let docs = block_postings.docs();
+                if docs.is_empty() {
+                    break;
+                }
+                for &doc in docs {
+                    if doc_opstamps.is_deleted(doc, delete_opstamp) {
+                        delete_bitset.insert(doc);
+                        might_have_changed = true;
+                    }
+                }
+                block_postings.advance();
+            }
+        }
+    }
+    Ok(might_have_changed)
+}
+
+// Deletes the documents containing any of the terms within the given range.
+//
+// If the field is indexed at several precisions, the truncated prefixes of the values
+// are skipped: they sort among the full precision terms, but also match values
+// outside of the range.
+fn delete_term_range(
+    delete_bitset: &mut BitSet,
+    segment_reader: &SegmentReader,
+    term_range: &Range<Term>,
+    doc_opstamps: &DocToOpstampMapping,
+    delete_opstamp: Opstamp,
+) -> crate::Result<bool> {
+    let mut might_have_changed = false;
+    let field = term_range.start.field();
+    let has_precision_step = segment_reader
+        .schema()
+        .get_field_entry(field)
+        .field_type()
+        .get_precision_step()
+        .is_some();
+    let inverted_index = segment_reader.inverted_index(field)?;
+    let mut term_stream = inverted_index
+        .terms()
+        .range()
+        .ge(term_range.start.value_bytes())
+        .lt(term_range.end.value_bytes())
+        .into_stream()?;
+    while term_stream.advance() {
+        if has_precision_step && !is_full_precision_term(term_stream.key()) {
+            continue;
+        }
+        let mut block_postings = inverted_index
+            .read_block_postings_from_terminfo(term_stream.value(), IndexRecordOption::Basic)?;
+        loop {
+            let docs = block_postings.docs();
+            if docs.is_empty() {
+                break;
+            }
+            for &doc in docs {
+                if doc_opstamps.is_deleted(doc, delete_opstamp) {
+                    delete_bitset.insert(doc);
+                    might_have_changed = true;
+                }
+            }
+            block_postings.advance();
+        }
+    }
+    Ok(might_have_changed)
+}
+
 /// Advance delete for the given segment up to the target opstamp.
 ///
 /// Note that there are no guarantee that the resulting `segment_entry` delete_opstamp
@@ -178,27 +482,87 @@ pub(crate) fn advance_deletes(
     let num_deleted_docs: u32 = delete_bitset.len() as u32;
     if num_deleted_docs > num_deleted_docs_before {
         // There are new deletes. We need to write a new delete file.
-        segment = segment.with_delete_meta(num_deleted_docs as u32, target_opstamp);
-        let mut delete_file = segment.open_write(SegmentComponent::Delete)?;
-        write_delete_bitset(&delete_bitset, max_doc, &mut delete_file)?;
-        delete_file.terminate()?;
+        let previous_delete_bitset = segment_reader
+            .delete_bitset()
+            .filter(|_| !segment.meta().has_dense_delete_file())
+            .filter(|_| segment.meta().delete_file_paths().len() < MAX_NUM_DELETE_FILES);
+        if let Some(previous_delete_bitset) = previous_delete_bitset {
+            // The new delete file only holds the docs deleted since the previous one.
+            segment = segment.with_delete_file(num_deleted_docs as u32, target_opstamp);
+            let mut delete_file = segment.open_write(SegmentComponent::Delete)?;
+            let new_deleted_docs = (0..max_doc)
+                .filter(|&doc| delete_bitset.contains(doc) && previous_delete_bitset.is_alive(doc));
+            write_deleted_docs(new_deleted_docs, &mut delete_file)?;
+            delete_file.terminate()?;
+        } else {
+            segment = segment.with_delete_meta(num_deleted_docs as u32, target_opstamp);
+            let mut delete_file = segment.open_write(SegmentComponent::Delete)?;
+            write_delete_bitset(&delete_bitset, max_doc, &mut delete_file)?;
+            delete_file.terminate()?;
+        }
     }
 
     segment_entry.set_meta(segment.meta().clone());
     Ok(())
 }
 
+/// Returns the term identifying the document in the primary key field.
+///
+/// The document is required to have exactly one value for the primary key field.
+fn primary_key_term(primary_key_field: Field, document: &Document) -> crate::Result<Term> {
+    let mut primary_key_values = document.get_all(primary_key_field);
+    let primary_key_value = primary_key_values.next().ok_or_else(|| {
+        TantivyError::InvalidArgument("The document does not have any primary key".to_string())
+    })?;
+    if primary_key_values.next().is_some() {
+        return Err(TantivyError::InvalidArgument(
+            "The document has more than one primary key".to_string(),
+        ));
+    }
+    match primary_key_value {
+        Value::Str(text) => Ok(Term::from_field_text(primary_key_field, text)),
+        Value::U64(val) => Ok(Term::from_field_u64(primary_key_field, *val)),
+        Value::I64(val) => Ok(Term::from_field_i64(primary_key_field, *val)),
+        Value::Bytes(bytes) => Ok(Term::from_field_bytes(primary_key_field, bytes)),
+        _ => Err(TantivyError::InvalidArgument(format!(
+            "Unsupported primary key value {:?}",
+            primary_key_value
+        ))),
+    }
+}
+
+/// Waits for an indexing worker to terminate, and returns its result.
+///
+/// A panic of the worker is returned as a `TantivyError::WorkerPanicked` error.
+fn join_indexing_worker(join_handle: JoinHandle<crate::Result<()>>) -> crate::Result<()> {
+    let thread_name = join_handle
+        .thread()
+        .name()
+        .unwrap_or("indexing worker")
+        .to_string();
+    join_handle
+        .join()
+        .map_err(|panic_payload| TantivyError::worker_panicked(&thread_name, panic_payload))?
+}
+
 fn index_documents(
     memory_budget: usize,
     segment: Segment,
-    grouped_document_iterator: &mut dyn Iterator<Item = OperationGroup>,
+    grouped_document_iterator: &mut dyn Iterator<Item = ReceivedOperationGroup>,
     segment_updater: &mut SegmentUpdater,
     mut delete_cursor: DeleteCursor,
 ) -> crate::Result<bool> {
     let schema = segment.schema();
 
     let mut segment_writer = SegmentWriter::for_segment(memory_budget, segment.clone(), &schema)?;
-    for document_group in grouped_document_iterator {
+    // The segment only gets a routing partition if all of its documents
+    // were routed to the same partition.
+    let mut segment_routing_partition: Option<Option<u32>> = None;
+    for (routing_partition, document_group) in grouped_document_iterator {
+        segment_routing_partition = Some(match segment_routing_partition {
+            Some(previous_partition) if previous_partition != routing_partition => None,
+            _ => routing_partition,
+        });
         for doc in document_group {
             segment_writer.add_document(doc, &schema)?;
         }
@@ -222,14 +586,29 @@ fn index_documents(
     // the worker thread.
     assert!(max_doc > 0);
 
+    enter_span!("flush_segment", segment_id = %segment.id(), num_docs = max_doc);
+    segment_updater.check_available_space(
+        segment.index().directory(),
+        segment_writer.mem_usage() as u64,
+    )?;
     let doc_opstamps: Vec<Opstamp> = segment_writer.finalize()?;
+    segment
+        .index()
+        .metrics()
+        .record_docs_indexed(u64::from(max_doc));
 
     let segment_with_max_doc = segment.with_max_doc(max_doc);
 
     let delete_bitset_opt =
         apply_deletes(&segment_with_max_doc, &mut delete_cursor, &doc_opstamps)?;
 
-    let meta = segment_with_max_doc.meta().clone();
+    let fast_field_ranges = segment_with_max_doc.compute_fast_field_ranges()?;
+    let meta = segment_with_max_doc
+        .meta()
+        .clone()
+        .with_routing_partition(segment_routing_partition.flatten())
+        .with_fast_field_ranges(fast_field_ranges)
+        .with_index_sort(segment.index().settings().sort_by_field.clone());
     meta.untrack_temp_docstore();
     // update segment_updater inventory to remove tempstore
     let segment_entry = SegmentEntry::new(meta, delete_cursor, delete_bitset_opt);
@@ -285,6 +664,9 @@ impl IndexWriter {
     ///
     /// `num_threads` specifies the number of indexing workers that
     /// should work at the same time.
+    ///
+    /// A `single_threaded` writer does not spawn any thread: the documents are indexed,
+    /// and the segments are merged, on the calling thread.
     /// # Errors
     /// If the lockfile already exists, returns `Error::FileAlreadyExists`.
     /// # Panics
@@ -294,6 +676,7 @@ impl IndexWriter {
         num_threads: usize,
         heap_size_in_bytes_per_thread: usize,
         directory_lock: DirectoryLock,
+        single_threaded: bool,
     ) -> crate::Result<IndexWriter> {
         if heap_size_in_bytes_per_thread < HEAP_SIZE_MIN {
             let err_msg = format!(
@@ -306,8 +689,23 @@ impl IndexWriter {
             let err_msg = format!("The heap size per thread cannot exceed {}", HEAP_SIZE_MAX);
             return Err(TantivyError::InvalidArgument(err_msg));
         }
+        let num_routing_partitions = index
+            .settings()
+            .num_routing_partitions
+            .unwrap_or(DEFAULT_NUM_ROUTING_PARTITIONS);
+        if num_routing_partitions == 0 {
+            return Err(TantivyError::InvalidArgument(
+                "The number of routing partitions must be at least 1".to_string(),
+            ));
+        }
         let (document_sender, document_receiver): (OperationSender, OperationReceiver) =
             channel::bounded(PIPELINE_MAX_SIZE_IN_DOCS);
+        let (routed_document_senders, routed_document_receivers): (
+            Vec<OperationSender>,
+            Vec<OperationReceiver>,
+        ) = (0..num_routing_partitions)
+            .map(|_| channel::bounded(PIPELINE_MAX_SIZE_IN_DOCS))
+            .unzip();
 
         let delete_queue = DeleteQueue::new();
 
@@ -315,8 +713,20 @@ impl IndexWriter {
 
         let stamper = Stamper::new(current_opstamp);
 
-        let segment_updater =
-            SegmentUpdater::create(index.clone(), stamper.clone(), &delete_queue.cursor())?;
+        let segment_updater = SegmentUpdater::create(
+            index.clone(),
+            stamper.clone(),
+            &delete_queue.cursor(),
+            single_threaded,
+        )?;
+        let inline_indexer = if single_threaded {
+            Some(Mutex::new(InlineIndexer {
+                delete_cursor: delete_queue.cursor(),
+                error: None,
+            }))
+        } else {
+            None
+        };
 
         let mut index_writer = IndexWriter {
             _directory_lock: Some(directory_lock),
@@ -327,6 +737,9 @@ impl IndexWriter {
             operation_receiver: document_receiver,
             operation_sender: document_sender,
 
+            routed_operation_receivers: routed_document_receivers,
+            routed_operation_senders: routed_document_senders,
+
             segment_updater,
 
             workers_join_handle: vec![],
@@ -338,14 +751,34 @@ impl IndexWriter {
             stamper,
 
             worker_id: 0,
+
+            deduplicator: None,
+
+            auto_commit: None,
+
+            handle_state: SharedHandleState::default(),
+
+            inline_indexer,
+
+            indexing_failure: None,
         };
-        index_writer.start_workers()?;
+        if !single_threaded {
+            index_writer.start_workers()?;
+        }
         Ok(index_writer)
     }
 
     fn drop_sender(&mut self) {
         let (sender, _receiver) = channel::bounded(1);
         self.operation_sender = sender;
+        for routed_operation_sender in &mut self.routed_operation_senders {
+            let (sender, _receiver) = channel::bounded(1);
+            *routed_operation_sender = sender;
+        }
+        // The handles fail from now on.
+        if let Ok(mut handle_state) = self.handle_state.write() {
+            *handle_state = None;
+        }
     }
 
     /// If there are some merging threads, blocks until they all finish their work and
@@ -357,12 +790,13 @@ impl IndexWriter {
 
         let former_workers_handles = std::mem::take(&mut self.workers_join_handle);
         for join_handle in former_workers_handles {
-            join_handle
-                .join()
-                .expect("Indexing Worker thread panicked")
-                .map_err(|_| {
+            join_indexing_worker(join_handle).map_err(|err| {
+                if let TantivyError::WorkerPanicked { .. } = err {
+                    err
+                } else {
                     TantivyError::ErrorInThread("Error in indexing worker thread.".into())
-                })?;
+                }
+            })?;
         }
 
         let result = self
@@ -377,6 +811,10 @@ impl IndexWriter {
         result
     }
 
+    /// Adds a segment built outside of this `IndexWriter`, e.g. by a
+    /// [`SegmentBuilder`](./struct.SegmentBuilder.html).
+    ///
+    /// The segment becomes searchable after the next commit.
     #[doc(hidden)]
     pub fn add_segment(&self, segment_meta: SegmentMeta) -> crate::Result<()> {
         let delete_cursor = self.delete_queue.cursor();
@@ -384,6 +822,110 @@ impl IndexWriter {
         block_on(self.segment_updater.schedule_add_segment(segment_entry))
     }
 
+    /// Imports segments from another index sharing the same schema and settings,
+    /// typically built offline using a [`SegmentBuilder`](./struct.SegmentBuilder.html).
+    ///
+    /// Before anything is published, the segments are validated:
+    /// - the schema and the index sorting of `source` need to match the ones of this index.
+    /// - all of the files of the segments need to be present and their checksums valid.
+    ///
+    /// The files are then copied into this index under a fresh segment id, so that
+    /// imported segments can never collide with existing ones.
+    ///
+    /// The imported segments are published atomically: they all become searchable
+    /// after the next commit. If an error occurs, none of them is added.
+    ///
+    /// Returns the metas of the imported segments, in the same order as `segment_metas`.
+    pub fn add_segments(
+        &self,
+        source: &Index,
+        segment_metas: &[SegmentMeta],
+    ) -> crate::Result<Vec<SegmentMeta>> {
+        if source.schema() != self.index.schema() {
+            return Err(TantivyError::SchemaError(
+                "The schema of the imported segments does not match the schema of the index."
+                    .to_string(),
+            ));
+        }
+        if source.settings().sort_by_field != self.index.settings().sort_by_field {
+            return Err(TantivyError::InvalidArgument(
+                "The imported segments are not sorted like the index.".to_string(),
+            ));
+        }
+        for segment_meta in segment_metas {
+            segment_meta.validate_index_sort(self.index.settings().sort_by_field.as_ref())?;
+        }
+        let components: Vec<SegmentComponent> = SegmentComponent::iterator()
+            .cloned()
+            .filter(|component| *component != SegmentComponent::TempStore)
+            .collect();
+        for segment_meta in segment_metas {
+            let paths = components
+                .iter()
+                .filter(|&&component| component != SegmentComponent::Delete)
+                .map(|&component| segment_meta.relative_path(component))
+                .chain(segment_meta.delete_file_paths());
+            for path in paths {
+                if !source.directory().validate_checksum(&path)? {
+                    return Err(DataCorruption::new(
+                        path,
+                        "Checksum mismatch in imported segment file.".to_string(),
+                    )
+                    .into());
+                }
+            }
+        }
+        let mut imported_segment_metas = Vec::with_capacity(segment_metas.len());
+        for segment_meta in segment_metas {
+            let mut imported_segment_meta = self
+                .index
+                .new_segment_meta(self.index.new_segment().id(), segment_meta.max_doc())
+                .with_routing_partition(segment_meta.routing_partition())
+                .with_fast_field_ranges(segment_meta.fast_field_ranges().to_vec())
+                .with_index_sort(self.index.settings().sort_by_field.clone());
+            let source_segment = source.segment(segment_meta.clone());
+            if let Some(delete_bitset) = source_segment.open_delete_bitset()? {
+                // The delete opstamp of the source index is meaningless in this index:
+                // none of the deletes of this index have been applied to the segment yet.
+                imported_segment_meta =
+                    imported_segment_meta.with_delete_meta(segment_meta.num_deleted_docs(), 0u64);
+                // The deleted docs may be spread over several files, and are rewritten in one.
+                let mut write = self
+                    .index
+                    .directory()
+                    .open_write(&imported_segment_meta.relative_path(SegmentComponent::Delete))?;
+                let deleted_docs =
+                    (0..segment_meta.max_doc()).filter(|&doc| delete_bitset.is_deleted(doc));
+                write_deleted_docs(deleted_docs, &mut write)?;
+                write.terminate()?;
+            }
+            for &component in &components {
+                if component == SegmentComponent::Delete {
+                    continue;
+                }
+                let data = source
+                    .directory()
+                    .open_read(&segment_meta.relative_path(component))?
+                    .read_bytes()?;
+                let mut write = self
+                    .index
+                    .directory()
+                    .open_write(&imported_segment_meta.relative_path(component))?;
+                write.write_all(data.as_slice())?;
+                write.terminate()?;
+            }
+            imported_segment_metas.push(imported_segment_meta);
+        }
+        let segment_entries = imported_segment_metas
+            .iter()
+            .map(|segment_meta| {
+                SegmentEntry::new(segment_meta.clone(), self.delete_queue.cursor(), None)
+            })
+            .collect();
+        block_on(self.segment_updater.schedule_add_segments(segment_entries))?;
+        Ok(imported_segment_metas)
+    }
+
     /// Creates a new segment.
     ///
     /// This method is useful only for users trying to do complex
@@ -397,9 +939,18 @@ impl IndexWriter {
     }
 
     /// Spawns a new worker thread for indexing.
-    /// The thread consumes documents from the pipeline.
-    fn add_indexing_worker(&mut self) -> crate::Result<()> {
+    /// The thread consumes documents from the pipeline, as well as the documents
+    /// routed to the partitions `p` such that `p % num_threads == worker_ordinal`.
+    fn add_indexing_worker(&mut self, worker_ordinal: usize) -> crate::Result<()> {
         let document_receiver_clone = self.operation_receiver.clone();
+        let routed_document_receivers_clone: Vec<(u32, OperationReceiver)> = self
+            .routed_operation_receivers
+            .iter()
+            .enumerate()
+            .skip(worker_ordinal)
+            .step_by(self.num_threads)
+            .map(|(routing_partition, receiver)| (routing_partition as u32, receiver.clone()))
+            .collect();
         let mut segment_updater = self.segment_updater.clone();
 
         let mut delete_cursor = self.delete_queue.cursor();
@@ -410,8 +961,11 @@ impl IndexWriter {
             .name(format!("thrd-tantivy-index{}", self.worker_id))
             .spawn(move || {
                 loop {
-                    let mut document_iterator =
-                        document_receiver_clone.clone().into_iter().peekable();
+                    let mut document_iterator = WorkerOperations {
+                        shared_receiver: Some(document_receiver_clone.clone()),
+                        routed_receivers: routed_document_receivers_clone.clone(),
+                    }
+                    .peekable();
 
                     // the peeking here is to avoid
                     // creating a new segment's files
@@ -420,7 +974,7 @@ impl IndexWriter {
                     // this is a valid guarantee as the
                     // peeked document now belongs to
                     // our local iterator.
-                    if let Some(operations) = document_iterator.peek() {
+                    if let Some((_, operations)) = document_iterator.peek() {
                         if let Some(first) = operations.first() {
                             delete_cursor.skip_to(first.opstamp);
                         } else {
@@ -457,14 +1011,176 @@ impl IndexWriter {
         self.segment_updater.set_merge_policy(merge_policy);
     }
 
+    /// Sets a scratch directory in which the segments resulting from merges are written.
+    ///
+    /// The files of a merged segment are only moved to the index directory once the merge
+    /// is complete. This makes it possible to keep the in-progress merges out of the volume
+    /// of the index, e.g. when it sits on a small and fast drive.
+    ///
+    /// Several index writers should not share the same scratch directory.
+    /// The scratch directory is kept across rollbacks.
+    ///
+    /// The available disk space is checked in the scratch directory before
+    /// starting a merge. See [`set_disk_space_reserve`](#method.set_disk_space_reserve).
+    pub fn set_merge_scratch_directory<D: Directory>(
+        &self,
+        scratch_directory: D,
+    ) -> crate::Result<()> {
+        let scratch_directory = ManagedDirectory::wrap(scratch_directory)?;
+        self.segment_updater
+            .set_merge_scratch_directory(scratch_directory);
+        Ok(())
+    }
+
+    /// Sets the listener notified of the doc id mapping of each merge.
+    ///
+    /// The listener is kept across rollbacks.
+    ///
+    /// See [`MergeListener`](./trait.MergeListener.html).
+    pub fn set_merge_listener<L: MergeListener>(&self, merge_listener: L) {
+        self.segment_updater
+            .set_merge_listener(Arc::new(merge_listener));
+    }
+
+    /// Sets the number of bytes that must remain available in the directory of the index.
+    ///
+    /// Once set, the available disk space is checked before flushing a new segment,
+    /// before starting a merge, and upon commit. The operation fails with a
+    /// `TantivyError::DiskFull` error if it would eat into the reserve, rather than
+    /// running out of disk space while writing files.
+    ///
+    /// The checks are skipped for directories that do not report their available
+    /// space (e.g. the `RamDirectory`).
+    ///
+    /// The reserve is kept across rollbacks.
+    pub fn set_disk_space_reserve(&self, num_bytes: u64) {
+        self.segment_updater.set_disk_space_reserve(num_bytes);
+    }
+
+    /// Sets the number of commit generations kept in the directory, the last one included.
+    ///
+    /// Each commit (and each merge) writes a new generation of the meta file.
+    /// The meta files of the last `num_generations` generations, and the segments they
+    /// refer to, are kept, so that these commits can be opened with
+    /// [`Index::open_commit`](./struct.Index.html#method.open_commit).
+    ///
+    /// By default, only the last generation is kept. It is used to recover
+    /// the last commit if `meta.json` gets corrupted.
+    /// Keeping more generations delays the deletion of the segments
+    /// that were merged or deleted since.
+    pub fn set_num_commit_generations(&self, num_generations: usize) {
+        self.segment_updater
+            .set_num_commit_generations(num_generations);
+    }
+
+    /// Sets the grace period of the files that are not used by the index anymore.
+    ///
+    /// Garbage collection only deletes a file once it has been unused for at least
+    /// `grace_period`, giving the searchers still using it some time to be released.
+    /// This matters when the index is read by other processes, e.g. over NFS, where
+    /// deleting a file that is still in use makes its reader fail.
+    /// Readers may also protect their files with a lease
+    /// (see [`IndexReaderBuilder::lease_duration`](./struct.IndexReaderBuilder.html#method.lease_duration)).
+    ///
+    /// By default, the grace period is zero.
+    pub fn set_gc_grace_period(&self, grace_period: Duration) {
+        self.index.directory().set_gc_grace_period(grace_period);
+    }
+
+    /// Returns true if the writer does not use any thread.
+    ///
+    /// See [`Index::single_threaded_writer`](../struct.Index.html#method.single_threaded_writer).
+    pub fn is_single_threaded(&self) -> bool {
+        self.inline_indexer.is_some()
+    }
+
+    /// Indexes the operations pending in the indexing pipeline on the calling thread.
+    ///
+    /// Only single-threaded writers, which do not have any indexing worker,
+    /// have pending operations to index.
+    fn index_pending_operations(&self) -> crate::Result<()> {
+        let mut inline_indexer = if let Some(inline_indexer) = self.inline_indexer.as_ref() {
+            inline_indexer.lock().expect("Inline indexer lock poisoned")
+        } else {
+            return Ok(());
+        };
+        let mut segment_updater = self.segment_updater.clone();
+        let mut document_iterator = PendingOperations {
+            shared_receiver: self.operation_receiver.clone(),
+            routed_receivers: self.routed_operation_receivers.clone(),
+        }
+        .peekable();
+        loop {
+            match document_iterator.peek() {
+                Some((_, operations)) => {
+                    if let Some(first) = operations.first() {
+                        inline_indexer.delete_cursor.skip_to(first.opstamp);
+                    } else {
+                        document_iterator.next();
+                        continue;
+                    }
+                }
+                None => return Ok(()),
+            }
+            let segment = self.index.new_segment();
+            index_documents(
+                self.heap_size_in_bytes_per_thread,
+                segment,
+                &mut document_iterator,
+                &mut segment_updater,
+                inline_indexer.delete_cursor.clone(),
+            )?;
+        }
+    }
+
+    /// Sends an operation group to the queue of `sender`.
+    ///
+    /// Single-threaded writers index the pending operations on the calling thread
+    /// when the queue is full, instead of waiting for an indexing worker to consume them.
+    fn send_operations(
+        &self,
+        sender: &OperationSender,
+        mut operations: OperationGroup,
+    ) -> Result<(), channel::SendError<OperationGroup>> {
+        if let Some(auto_commit) = self.auto_commit.as_ref() {
+            for operation in &operations {
+                auto_commit.record_document(&operation.document);
+            }
+        }
+        let inline_indexer = if let Some(inline_indexer) = self.inline_indexer.as_ref() {
+            inline_indexer
+        } else {
+            return sender.send(operations);
+        };
+        loop {
+            match sender.try_send(operations) {
+                Ok(()) => return Ok(()),
+                Err(channel::TrySendError::Full(rejected_operations)) => {
+                    operations = rejected_operations;
+                }
+                Err(channel::TrySendError::Disconnected(rejected_operations)) => {
+                    return Err(channel::SendError(rejected_operations));
+                }
+            }
+            if let Err(error) = self.index_pending_operations() {
+                error!("Failed to index the pending operations: {:?}", error);
+                let mut inline_indexer =
+                    inline_indexer.lock().expect("Inline indexer lock poisoned");
+                inline_indexer.error.get_or_insert(error);
+            }
+        }
+    }
+
     fn start_workers(&mut self) -> crate::Result<()> {
-        for _ in 0..self.num_threads {
-            self.add_indexing_worker()?;
+        for worker_ordinal in 0..self.num_threads {
+            self.add_indexing_worker(worker_ordinal)?;
         }
         Ok(())
     }
 
     /// Detects and removes the files that are not used by the index anymore.
+    ///
+    /// The returned future does not depend on any specific executor.
     pub fn garbage_collect_files(
         &self,
     ) -> impl Future<Output = crate::Result<GarbageCollectionResult>> {
@@ -517,6 +1233,13 @@ impl IndexWriter {
 
     /// Merges a given list of segments
     ///
+    /// The merge runs on the merge threads of the `IndexWriter`.
+    /// The returned future resolves once the merge has ended, and does not depend
+    /// on any specific executor: it can be awaited from tokio, async-std,
+    /// or simply with `futures::executor::block_on`.
+    ///
+    /// If the merge fails, the future resolves with the error that interrupted it.
+    ///
     /// `segment_ids` is required to be non-empty.
     pub fn merge(
         &mut self,
@@ -527,6 +1250,27 @@ impl IndexWriter {
         async move { segment_updater.start_merge(merge_operation)?.await }
     }
 
+    /// Rewrites the segments written in a previous index format in the current index format,
+    /// by merging them into a single segment.
+    ///
+    /// Returns the meta of the resulting segment, or `None` if all of the segments
+    /// were already written in the current index format.
+    ///
+    /// See [`Index::index_format_version`](../struct.Index.html#method.index_format_version).
+    pub fn upgrade_in_place(&mut self) -> crate::Result<Option<SegmentMeta>> {
+        let mut old_segment_ids = Vec::new();
+        for segment in self.index.searchable_segments()? {
+            if segment.index_format_version()? < crate::INDEX_FORMAT_VERSION {
+                old_segment_ids.push(segment.id());
+            }
+        }
+        if old_segment_ids.is_empty() {
+            return Ok(None);
+        }
+        let segment_meta = block_on(self.merge(&old_segment_ids))?;
+        Ok(Some(segment_meta))
+    }
+
     /// Closes the current document channel send.
     /// and replace all the channels by new ones.
     ///
@@ -539,6 +1283,16 @@ impl IndexWriter {
     fn recreate_document_channel(&mut self) -> OperationReceiver {
         let (document_sender, document_receiver): (OperationSender, OperationReceiver) =
             channel::bounded(PIPELINE_MAX_SIZE_IN_DOCS);
+        for (routed_sender, routed_receiver) in self
+            .routed_operation_senders
+            .iter_mut()
+            .zip(self.routed_operation_receivers.iter_mut())
+        {
+            let (document_sender, document_receiver): (OperationSender, OperationReceiver) =
+                channel::bounded(PIPELINE_MAX_SIZE_IN_DOCS);
+            *routed_sender = document_sender;
+            *routed_receiver = document_receiver;
+        }
         mem::replace(&mut self.operation_sender, document_sender);
         mem::replace(&mut self.operation_receiver, document_receiver)
     }
@@ -557,6 +1311,12 @@ impl IndexWriter {
         // segment updates will be ignored.
         self.segment_updater.kill();
         let document_receiver = self.operation_receiver.clone();
+        let routed_document_receivers = self.routed_operation_receivers.clone();
+        let deduplication_opt = self.deduplication();
+        let auto_commit_policy_opt = self.auto_commit_policy();
+        let merge_scratch_directory_opt = self.segment_updater.merge_scratch_directory();
+        let merge_listener_opt = self.segment_updater.merge_listener();
+        let disk_space_reserve_opt = self.segment_updater.disk_space_reserve();
 
         // take the directory lock to create a new index_writer.
         let directory_lock = self
@@ -564,12 +1324,44 @@ impl IndexWriter {
             .take()
             .expect("The IndexWriter does not have any lock. This is a bug, please report.");
 
-        let new_index_writer: IndexWriter = IndexWriter::new(
+        let mut new_index_writer: IndexWriter = IndexWriter::new(
             &self.index,
             self.num_threads,
             self.heap_size_in_bytes_per_thread,
             directory_lock,
+            self.is_single_threaded(),
         )?;
+        // The hashes of the documents added since the last commit are forgotten.
+        if let Some(deduplication) = deduplication_opt {
+            new_index_writer.set_deduplication(deduplication)?;
+        }
+        if let Some(auto_commit_policy) = auto_commit_policy_opt {
+            new_index_writer.set_auto_commit_policy(auto_commit_policy);
+        }
+        new_index_writer
+            .segment_updater
+            .keep_commit_generations_of(&self.segment_updater);
+        if let Some(merge_scratch_directory) = merge_scratch_directory_opt {
+            new_index_writer
+                .segment_updater
+                .set_merge_scratch_directory(merge_scratch_directory);
+        }
+        if let Some(merge_listener) = merge_listener_opt {
+            new_index_writer
+                .segment_updater
+                .set_merge_listener(merge_listener);
+        }
+        if let Some(disk_space_reserve) = disk_space_reserve_opt {
+            new_index_writer
+                .segment_updater
+                .set_disk_space_reserve(disk_space_reserve);
+        }
+        // The handles are moved to the new writer.
+        {
+            let mut handle_state = self.handle_state.write()?;
+            new_index_writer.refresh_handle_state(&mut handle_state);
+        }
+        mem::swap(&mut self.handle_state, &mut new_index_writer.handle_state);
 
         // the current `self` is dropped right away because of this call.
         //
@@ -583,6 +1375,9 @@ impl IndexWriter {
         // This will reach an end as the only document_sender
         // was dropped with the index_writer.
         for _ in document_receiver {}
+        for routed_document_receiver in routed_document_receivers {
+            for _ in routed_document_receiver {}
+        }
 
         Ok(self.committed_opstamp)
     }
@@ -608,6 +1403,10 @@ impl IndexWriter {
     /// It is also possible to add a payload to the `commit`
     /// using this API.
     /// See [`PreparedCommit::set_payload()`](PreparedCommit.html)
+    ///
+    /// If the indexing of some of the pending documents failed, e.g. because an
+    /// indexing worker panicked, the error is returned, and the following commits
+    /// fail until `.rollback()` is called.
     pub fn prepare_commit(&mut self) -> crate::Result<PreparedCommit> {
         // Here, because we join all of the worker threads,
         // all of the segment update for this commit have been
@@ -620,19 +1419,59 @@ impl IndexWriter {
         // This will move uncommitted segments to the state of
         // committed segments.
         info!("Preparing commit");
+        if let Some(indexing_failure) = self.indexing_failure.as_ref() {
+            return Err(TantivyError::ErrorInThread(format!(
+                "Indexing failed ({}). The writer must be rolled back before committing.",
+                indexing_failure
+            )));
+        }
+        self.segment_updater
+            .check_available_space(self.index.directory(), 0)?;
+        enter_span!("prepare_commit");
+
+        if let Some(inline_indexer) = self.inline_indexer.as_ref() {
+            let indexing_result = self.index_pending_operations();
+            let former_error = inline_indexer
+                .lock()
+                .expect("Inline indexer lock poisoned")
+                .error
+                .take();
+            if let Some(error) = former_error.or_else(|| indexing_result.err()) {
+                self.indexing_failure = Some(error.to_string());
+                return Err(error);
+            }
+            let commit_opstamp = self.stamper.stamp();
+            info!("Prepared commit {}", commit_opstamp);
+            return Ok(PreparedCommit::new(self, commit_opstamp));
+        }
+
+        // The operations of the handles are blocked until the commit is stamped,
+        // so that they belong either to this commit or to the next one.
+        let handle_state = self.handle_state.clone();
+        let mut handle_state_guard = handle_state.write()?;
 
         // this will drop the current document channel
         // and recreate a new one.
         self.recreate_document_channel();
+        self.refresh_handle_state(&mut handle_state_guard);
 
         let former_workers_join_handle = std::mem::take(&mut self.workers_join_handle);
 
-        for worker_handle in former_workers_join_handle {
-            let indexing_worker_result = worker_handle
-                .join()
-                .map_err(|e| TantivyError::ErrorInThread(format!("{:?}", e)))?;
-            indexing_worker_result?;
-            self.add_indexing_worker()?;
+        // All of the workers are restarted, even if one of them failed,
+        // so that the `IndexWriter` remains usable (e.g. to rollback).
+        // The operations of the failed worker are lost, so that the writer
+        // is marked as failed until it is rolled back.
+        let mut first_worker_error = None;
+        for (worker_ordinal, worker_handle) in former_workers_join_handle.into_iter().enumerate() {
+            if let Err(worker_error) = join_indexing_worker(worker_handle) {
+                error!("Indexing worker failed: {:?}", worker_error);
+                first_worker_error.get_or_insert(worker_error);
+            }
+            self.add_indexing_worker(worker_ordinal)?;
+        }
+        if let Some(worker_error) = first_worker_error {
+            self.indexing_failure = Some(worker_error.to_string());
+            return Err(worker_error);
         }
 
         let commit_opstamp = self.stamper.stamp();
@@ -659,51 +1498,453 @@ impl IndexWriter {
         self.prepare_commit()?.commit()
     }
 
-    pub(crate) fn segment_updater(&self) -> &SegmentUpdater {
-        &self.segment_updater
-    }
-
-    /// Delete all documents containing a given term.
+    /// Returns a handle to add and delete documents from other threads,
+    /// without locking the `IndexWriter`.
     ///
-    /// Delete operation only affects documents that
-    /// were added in previous commits, and documents
-    /// that were added previously in the same commit.
+    /// Handles are cheap to clone. They remain usable across commits and rollbacks,
+    /// and fail once the writer is dropped.
+    /// See [`IndexWriterHandle`](./struct.IndexWriterHandle.html).
     ///
-    /// Like adds, the deletion itself will be visible
-    /// only after calling `commit()`.
-    pub fn delete_term(&self, term: Term) -> Opstamp {
-        let opstamp = self.stamper.stamp();
-        let delete_operation = DeleteOperation { opstamp, term };
-        self.delete_queue.push(delete_operation);
-        opstamp
+    /// Single-threaded writers do not support handles, as they index
+    /// on the thread calling the writer.
+    pub fn handle(&self) -> crate::Result<IndexWriterHandle> {
+        if self.is_single_threaded() {
+            return Err(TantivyError::InvalidArgument(
+                "Single-threaded writers do not support handles".to_string(),
+            ));
+        }
+        let mut handle_state = self.handle_state.write()?;
+        if handle_state.is_none() {
+            *handle_state = Some(self.new_handle_state());
+        }
+        Ok(IndexWriterHandle::new(self.handle_state.clone()))
     }
 
-    /// Returns the opstamp of the last successful commit.
-    ///
-    /// This is, for instance, the opstamp the index will
-    /// rollback to if there is a failure like a power surge.
-    ///
-    /// This is also the opstamp of the commit that is currently
-    /// available for searchers.
-    pub fn commit_opstamp(&self) -> Opstamp {
-        self.committed_opstamp
+    fn new_handle_state(&self) -> HandleState {
+        HandleState {
+            operation_sender: self.operation_sender.clone(),
+            stamper: self.stamper.clone(),
+            delete_queue: self.delete_queue.clone(),
+            auto_commit: self.auto_commit.clone(),
+        }
     }
 
-    /// Adds a document.
-    ///
-    /// If the indexing pipeline is full, this call may block.
+    // Points the handles, if any, to the current operation channel of the writer.
+    fn refresh_handle_state(&self, handle_state: &mut Option<HandleState>) {
+        if handle_state.is_some() {
+            *handle_state = Some(self.new_handle_state());
+        }
+    }
+
+    /// Sets the policy deciding when the writer commits on its own.
     ///
-    /// The opstamp is an increasing `u64` that can
-    /// be used by the client to align commits with its own
-    /// document queue.
-    pub fn add_document(&self, document: Document) -> Opstamp {
-        let opstamp = self.stamper.stamp();
-        let add_operation = AddOperation { opstamp, document };
-        let send_result = self.operation_sender.send(smallvec![add_operation]);
-        if let Err(e) = send_result {
-            panic!("Failed to index document. Sending to indexing channel failed. This probably means all of the indexing threads have panicked. {:?}", e);
+    /// The policy is evaluated by `maybe_auto_commit`.
+    /// See [`AutoCommitPolicy`](./struct.AutoCommitPolicy.html).
+    pub fn set_auto_commit_policy(&mut self, auto_commit_policy: AutoCommitPolicy) {
+        self.auto_commit = Some(Arc::new(AutoCommitState::new(auto_commit_policy)));
+        if let Ok(mut handle_state) = self.handle_state.write() {
+            self.refresh_handle_state(&mut handle_state);
         }
-        opstamp
+    }
+
+    /// Returns the auto-commit policy of the writer, if any.
+    pub fn auto_commit_policy(&self) -> Option<AutoCommitPolicy> {
+        self.auto_commit
+            .as_ref()
+            .map(|auto_commit| auto_commit.policy().clone())
+    }
+
+    /// Commits if the auto-commit policy of the writer says so.
+    ///
+    /// The listener of the policy is called around the commit.
+    /// Returns the opstamp of the commit, or `None` if no commit was due,
+    /// or if the listener cancelled it.
+    ///
+    /// This should be called after adding documents, or periodically
+    /// for the time-based policies, e.g. with an
+    /// [`AutoCommitThread`](./struct.AutoCommitThread.html).
+    pub fn maybe_auto_commit(&mut self) -> crate::Result<Option<Opstamp>> {
+        let auto_commit = if let Some(auto_commit) = self.auto_commit.as_ref() {
+            auto_commit
+        } else {
+            return Ok(None);
+        };
+        let pending_changes = if let Some(pending_changes) = auto_commit.due_changes() {
+            pending_changes
+        } else {
+            return Ok(None);
+        };
+        let listener_opt = auto_commit.policy().listener();
+        if let Some(listener) = listener_opt.as_ref() {
+            if let Err(error) = listener.before_commit(&pending_changes) {
+                info!("Auto-commit cancelled: {:?}", error);
+                return Ok(None);
+            }
+        }
+        let commit_result = self.commit();
+        if let Some(listener) = listener_opt.as_ref() {
+            listener.after_commit(&commit_result);
+        }
+        commit_result.map(Some)
+    }
+
+    /// Forgets the changes tracked by the auto-commit policy, once they are committed.
+    pub(crate) fn reset_auto_commit(&self) {
+        if let Some(auto_commit) = self.auto_commit.as_ref() {
+            auto_commit.reset();
+        }
+    }
+
+    // Pushes a delete operation to the delete queue.
+    fn push_delete(&self, delete_operation: DeleteOperation) {
+        if let Some(auto_commit) = self.auto_commit.as_ref() {
+            auto_commit.record_delete();
+        }
+        self.delete_queue.push(delete_operation);
+    }
+
+    pub(crate) fn segment_updater(&self) -> &SegmentUpdater {
+        &self.segment_updater
+    }
+
+    /// Enables the deduplication of the documents added with `add_document`
+    /// and `add_document_with_routing_key`, by content hash.
+    ///
+    /// The hashes of the documents added by the last commit are loaded from the index,
+    /// so that the documents it contains are deduplicated too. Deduplication should
+    /// therefore be enabled before adding any document.
+    ///
+    /// The hashes of the documents added by a commit are persisted in the commit
+    /// if deduplication is enabled. Documents added with `run` or `upsert` are not
+    /// deduplicated.
+    ///
+    /// See [`Deduplication`](./struct.Deduplication.html).
+    pub fn set_deduplication(&mut self, deduplication: Deduplication) -> crate::Result<()> {
+        deduplication.validate(&self.index.schema())?;
+        let committed_hashes = self.index.load_metas()?.dedup_hashes;
+        self.deduplicator = Some(Mutex::new(Deduplicator::new(
+            deduplication,
+            &committed_hashes,
+        )));
+        Ok(())
+    }
+
+    /// Returns the deduplication of the writer, if it is enabled.
+    pub fn deduplication(&self) -> Option<Deduplication> {
+        self.deduplicator.as_ref().map(|deduplicator| {
+            deduplicator
+                .lock()
+                .expect("Deduplicator lock poisoned")
+                .deduplication()
+                .clone()
+        })
+    }
+
+    /// Returns the hashes to persist with the next commit.
+    pub(crate) fn pending_dedup_hashes(&self) -> Vec<u64> {
+        self.deduplicator
+            .as_ref()
+            .map(|deduplicator| {
+                deduplicator
+                    .lock()
+                    .expect("Deduplicator lock poisoned")
+                    .pending_hashes()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Marks the hashes of the documents added since the last commit as committed.
+    pub(crate) fn commit_dedup_hashes(&self) {
+        if let Some(deduplicator) = self.deduplicator.as_ref() {
+            deduplicator
+                .lock()
+                .expect("Deduplicator lock poisoned")
+                .commit();
+        }
+    }
+
+    // Deduplicates `document`, deleting the documents it replaces if need be.
+    //
+    // Returns false if the document should be skipped.
+    fn deduplicate(&self, document: &mut Document) -> bool {
+        let deduplicator = if let Some(deduplicator) = self.deduplicator.as_ref() {
+            deduplicator
+        } else {
+            return true;
+        };
+        let decision = deduplicator
+            .lock()
+            .expect("Deduplicator lock poisoned")
+            .deduplicate(document);
+        match decision {
+            DedupDecision::Add => true,
+            DedupDecision::Skip => false,
+            DedupDecision::Replace(term) => {
+                self.delete_term(term);
+                true
+            }
+        }
+    }
+
+    /// Delete all documents containing a given term.
+    ///
+    /// Delete operation only affects documents that
+    /// were added in previous commits, and documents
+    /// that were added previously in the same commit.
+    ///
+    /// Like adds, the deletion itself will be visible
+    /// only after calling `commit()`.
+    pub fn delete_term(&self, term: Term) -> Opstamp {
+        let opstamp = self.stamper.stamp();
+        let delete_operation = DeleteOperation {
+            opstamp,
+            target: DeleteTarget::Term(term),
+        };
+        self.push_delete(delete_operation);
+        opstamp
+    }
+
+    /// Delete all documents containing any of the given terms.
+    ///
+    /// The terms are deleted as a single operation, receiving a single opstamp:
+    /// it is equivalent to calling `delete_term` for each of the terms, but much
+    /// cheaper for large batches of terms, e.g. when purging many keys.
+    /// When the deletes are applied, the terms are sorted and merged with the term
+    /// dictionary of each segment in one pass, instead of being looked up one by one.
+    ///
+    /// Like adds, the deletion itself will be visible
+    /// only after calling `commit()`.
+    pub fn delete_terms(&self, mut terms: Vec<Term>) -> Opstamp {
+        terms.sort();
+        terms.dedup();
+        let opstamp = self.stamper.stamp();
+        let delete_operation = DeleteOperation {
+            opstamp,
+            target: DeleteTarget::Terms(terms),
+        };
+        self.push_delete(delete_operation);
+        opstamp
+    }
+
+    /// Applies a time-based retention policy.
+    ///
+    /// Segments in which all of the documents are expired are dropped as a whole,
+    /// which is much cheaper than deleting their documents.
+    ///
+    /// If the timestamp field is indexed, the other expired documents, including
+    /// the documents added before this call, are deleted with a single range delete.
+    /// Otherwise, the segments that also contain unexpired documents are kept until
+    /// all of their documents are expired.
+    ///
+    /// Like deletes, the retention will be visible only after calling `commit()`,
+    /// and is cancelled by `rollback()`.
+    pub fn apply_retention(&self, retention_policy: &RetentionPolicy) -> crate::Result<()> {
+        let schema = self.index.schema();
+        retention_policy.validate(&schema)?;
+        let timestamp_field = retention_policy.timestamp_field();
+        let is_timestamp_indexed = schema.get_field_entry(timestamp_field).is_indexed();
+        let expiration_timestamp = retention_policy.expiration_timestamp();
+        let mut expired_segment_ids = Vec::new();
+        for segment_meta in self.segment_updater.segment_metas() {
+            let segment_reader = SegmentReader::open(&self.index.segment(segment_meta))?;
+            let timestamp_reader = segment_reader.fast_fields().date(timestamp_field)?;
+            if timestamp_reader.max_value().timestamp() < expiration_timestamp {
+                expired_segment_ids.push(segment_reader.segment_id());
+            }
+        }
+        if is_timestamp_indexed {
+            // Date terms are encoded as their timestamp, in an order preserving way.
+            let expired_terms = Term::from_field_i64(timestamp_field, i64::MIN)
+                ..Term::from_field_i64(timestamp_field, expiration_timestamp);
+            self.push_delete(DeleteOperation {
+                opstamp: self.stamper.stamp(),
+                target: DeleteTarget::TermRange(expired_terms),
+            });
+        }
+        block_on(
+            self.segment_updater
+                .schedule_remove_segments(expired_segment_ids),
+        )
+    }
+
+    /// Checks the segments of the writer, committed or not, and drops the
+    /// segments in which problems are found, with all of their documents.
+    ///
+    /// Returns the report of the check.
+    /// See [`Index::check`](../struct.Index.html#method.check).
+    ///
+    /// Like deletes, dropping the segments will be visible only after calling `commit()`.
+    pub fn drop_corrupt_segments(&self) -> crate::Result<IndexCheck> {
+        let segments = self
+            .segment_updater
+            .segment_metas()
+            .into_iter()
+            .map(|segment_meta| check_segment(&self.index.segment(segment_meta)))
+            .collect();
+        let index_check = IndexCheck { segments };
+        let corrupt_segment_ids = index_check.corrupt_segment_ids();
+        if !corrupt_segment_ids.is_empty() {
+            warn!("Dropping corrupt segments {:?}", corrupt_segment_ids);
+        }
+        block_on(
+            self.segment_updater
+                .schedule_remove_segments(corrupt_segment_ids),
+        )?;
+        Ok(index_check)
+    }
+
+    /// Returns the opstamp of the last successful commit.
+    ///
+    /// This is, for instance, the opstamp the index will
+    /// rollback to if there is a failure like a power surge.
+    ///
+    /// This is also the opstamp of the commit that is currently
+    /// available for searchers.
+    pub fn commit_opstamp(&self) -> Opstamp {
+        self.committed_opstamp
+    }
+
+    /// Adds a document.
+    ///
+    /// If the indexing pipeline is full, this call may block.
+    ///
+    /// The opstamp is an increasing `u64` that can
+    /// be used by the client to align commits with its own
+    /// document queue.
+    ///
+    /// If deduplication is enabled (see `set_deduplication`), duplicate
+    /// documents may be skipped, in which case an opstamp is still returned.
+    pub fn add_document(&self, mut document: Document) -> Opstamp {
+        if !self.deduplicate(&mut document) {
+            return self.stamper.stamp();
+        }
+        let opstamp = self.stamper.stamp();
+        let add_operation = AddOperation { opstamp, document };
+        let send_result = self.send_operations(&self.operation_sender, smallvec![add_operation]);
+        if let Err(e) = send_result {
+            panic!("Failed to index document. Sending to indexing channel failed. This probably means all of the indexing threads have panicked. {:?}", e);
+        }
+        opstamp
+    }
+
+    /// Adds a batch of documents.
+    ///
+    /// The documents are stamped with contiguous opstamps, and sent to the
+    /// indexing workers at once. They are indexed into the same segment,
+    /// and committed at the same time.
+    ///
+    /// If the indexing pipeline is full, this call may block.
+    ///
+    /// Returns the range of the opstamps of the documents, in the order
+    /// of `documents`. If deduplication is enabled (see `set_deduplication`),
+    /// duplicate documents may be skipped, in which case their opstamp is still
+    /// part of the range.
+    pub fn add_documents(&self, documents: Vec<Document>) -> crate::Result<Range<Opstamp>> {
+        let opstamps = self.stamper.stamps(documents.len() as u64);
+        let mut add_operations = OperationGroup::default();
+        for (mut document, opstamp) in documents.into_iter().zip(opstamps.clone()) {
+            if self.deduplicate(&mut document) {
+                add_operations.push(AddOperation { opstamp, document });
+            }
+        }
+        if !add_operations.is_empty() {
+            self.send_operations(&self.operation_sender, add_operations)
+                .map_err(send_error_to_tantivy_error)?;
+        }
+        Ok(opstamps)
+    }
+
+    /// Adds a document, without blocking if the indexing pipeline is full.
+    ///
+    /// If the pipeline is full, `TryAddDocumentError::WouldBlock` hands
+    /// the document back, and the caller can apply its own backpressure
+    /// (e.g. slow down its ingestion) before trying again.
+    /// Other threads adding documents concurrently may still fill the pipeline
+    /// in the meantime, in which case the call waits for a slot to free up.
+    ///
+    /// Single-threaded writers are never full: like `add_document`, they index
+    /// the pending documents on the calling thread.
+    pub fn try_add_document(&self, mut document: Document) -> Result<Opstamp, TryAddDocumentError> {
+        // The capacity is checked before the document is deduplicated, so that
+        // a document handed back can be added again.
+        if self.inline_indexer.is_none() && self.operation_sender.is_full() {
+            return Err(TryAddDocumentError::WouldBlock(document));
+        }
+        if !self.deduplicate(&mut document) {
+            return Ok(self.stamper.stamp());
+        }
+        let opstamp = self.stamper.stamp();
+        let add_operation = AddOperation { opstamp, document };
+        self.send_operations(&self.operation_sender, smallvec![add_operation])
+            .map_err(send_error_to_tantivy_error)?;
+        Ok(opstamp)
+    }
+
+    /// Adds a document, routing it with the given routing key.
+    ///
+    /// The routing key is hashed into one of the routing partitions of the index
+    /// (see `IndexSettings::num_routing_partitions`). The partition of a key only depends
+    /// on the number of partitions, which is persisted with the index, and not on the
+    /// number of indexing threads of the writer.
+    ///
+    /// All of the documents of a routing partition are indexed by the same
+    /// indexing worker. As long as only routed documents are added, and the writer has
+    /// at least as many indexing threads as there are routing partitions, segments
+    /// therefore only contain documents of a single routing partition.
+    /// This improves the locality of the documents of a given key
+    /// (e.g. a tenant), and makes deleting them cheaper.
+    /// Workers indexing several partitions may produce segments mixing them,
+    /// which do not belong to any routing partition.
+    ///
+    /// The routing partition of a segment is available via
+    /// `SegmentMeta::routing_partition()`. Wrapping the merge policy
+    /// in a [`RoutingMergePolicy`](./merge_policy/struct.RoutingMergePolicy.html)
+    /// makes sure that merges preserve this locality.
+    ///
+    /// If the indexing pipeline is full, this call may block.
+    pub fn add_document_with_routing_key<K: Hash + ?Sized>(
+        &self,
+        routing_key: &K,
+        mut document: Document,
+    ) -> Opstamp {
+        if !self.deduplicate(&mut document) {
+            return self.stamper.stamp();
+        }
+        let routing_partition =
+            compute_routing_partition(routing_key, self.routed_operation_senders.len());
+        let opstamp = self.stamper.stamp();
+        let add_operation = AddOperation { opstamp, document };
+        let send_result = self.send_operations(
+            &self.routed_operation_senders[routing_partition],
+            smallvec![add_operation],
+        );
+        if let Err(e) = send_result {
+            panic!("Failed to index document. Sending to indexing channel failed. This probably means all of the indexing threads have panicked. {:?}", e);
+        }
+        opstamp
+    }
+
+    /// Adds a document, replacing the documents that share its primary key.
+    ///
+    /// The index is required to have a primary key
+    /// (see `IndexSettings::primary_key`), and the document is required to
+    /// have exactly one value for the primary key field.
+    ///
+    /// The documents previously added with the same primary key are deleted,
+    /// and the new document is added. Both operations are
+    /// committed at the same time.
+    ///
+    /// Documents can then be looked up by their primary key using
+    /// `Searcher::doc_by_key`.
+    pub fn upsert(&self, document: Document) -> crate::Result<Opstamp> {
+        let primary_key_field = self.index.primary_key_field()?.ok_or_else(|| {
+            TantivyError::InvalidArgument("The index does not have any primary key".to_string())
+        })?;
+        let primary_key_term = primary_key_term(primary_key_field, &document)?;
+        Ok(self.run(vec![
+            UserOperation::Delete(primary_key_term),
+            UserOperation::Add(document),
+        ]))
     }
 
     /// Gets a range of stamps from the stamper and "pops" the last stamp
@@ -751,8 +1992,11 @@ impl IndexWriter {
         for (user_op, opstamp) in user_operations.into_iter().zip(stamps) {
             match user_op {
                 UserOperation::Delete(term) => {
-                    let delete_operation = DeleteOperation { opstamp, term };
-                    self.delete_queue.push(delete_operation);
+                    let delete_operation = DeleteOperation {
+                        opstamp,
+                        target: DeleteTarget::Term(term),
+                    };
+                    self.push_delete(delete_operation);
                 }
                 UserOperation::Add(document) => {
                     let add_operation = AddOperation { opstamp, document };
@@ -760,7 +2004,7 @@ impl IndexWriter {
                 }
             }
         }
-        let send_result = self.operation_sender.send(adds);
+        let send_result = self.send_operations(&self.operation_sender, adds);
         if let Err(e) = send_result {
             panic!("Failed to index document. Sending to indexing channel failed. This probably means all of the indexing threads have panicked. {:?}", e);
         };
@@ -790,11 +2034,14 @@ mod tests {
     use proptest::strategy::Strategy;
 
     use super::super::operation::UserOperation;
+    use super::{compute_routing_partition, OperationGroup, TryAddDocumentError};
     use crate::collector::TopDocs;
     use crate::directory::error::LockError;
+    use crate::directory::Directory;
     use crate::error::*;
     use crate::fastfield::FastFieldReader;
     use crate::indexer::NoMergePolicy;
+    use crate::query::Query;
     use crate::query::QueryParser;
     use crate::query::TermQuery;
     use crate::schema::Cardinality;
@@ -808,8 +2055,12 @@ mod tests {
     use crate::DocAddress;
     use crate::Index;
     use crate::ReloadPolicy;
+    use crate::RetentionPolicy;
+    use crate::SegmentBuilder;
     use crate::Term;
+    use crate::{DedupMode, Deduplication};
     use crate::{IndexSettings, IndexSortByField, Order};
+    use crossbeam::channel;
 
     const LOREM: &str = "Doc Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed \
              do eiusmod tempor incididunt ut labore et dolore magna aliqua. \
@@ -835,6 +2086,61 @@ mod tests {
         assert_eq!(batch_opstamp1, 2u64);
     }
 
+    #[test]
+    fn test_add_documents() {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests().unwrap();
+        let opstamp = index_writer.add_document(doc!(text_field=>"a"));
+        let opstamps = index_writer
+            .add_documents(vec![doc!(text_field=>"b"), doc!(text_field=>"c")])
+            .unwrap();
+        assert_eq!(opstamps, opstamp + 1..opstamp + 3);
+        assert!(index_writer.add_documents(Vec::new()).unwrap().is_empty());
+        index_writer.commit().unwrap();
+        let searcher = index.reader().unwrap().searcher();
+        assert_eq!(searcher.num_docs(), 3);
+        let term_c = Term::from_field_text(text_field, "c");
+        assert_eq!(searcher.doc_freq(&term_c).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_try_add_document() {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests().unwrap();
+        let opstamp = index_writer
+            .try_add_document(doc!(text_field=>"a"))
+            .unwrap();
+        let next_opstamp = index_writer.add_document(doc!(text_field=>"b"));
+        assert_eq!(next_opstamp, opstamp + 1);
+        index_writer.commit().unwrap();
+        assert_eq!(index.reader().unwrap().searcher().num_docs(), 2);
+    }
+
+    #[test]
+    fn test_try_add_document_would_block() {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests().unwrap();
+        // A full pipeline, without any worker to consume it.
+        let (operation_sender, _operation_receiver) = channel::bounded(1);
+        operation_sender.send(OperationGroup::default()).unwrap();
+        index_writer.operation_sender = operation_sender;
+        match index_writer.try_add_document(doc!(text_field=>"a")) {
+            Err(TryAddDocumentError::WouldBlock(document)) => {
+                let text = document
+                    .get_first(text_field)
+                    .and_then(|value| value.text());
+                assert_eq!(text, Some("a"));
+            }
+            _ => panic!("Expected the pipeline to be full"),
+        }
+    }
+
     #[test]
     fn test_no_need_to_rewrite_delete_file_if_no_new_deletes() {
         let mut schema_builder = schema::Schema::builder();
@@ -875,6 +2181,46 @@ mod tests {
         assert_eq!(after_delete_opstamp, previous_delete_opstamp);
     }
 
+    #[test]
+    fn test_incremental_delete_files() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let id_field = schema_builder.add_u64_field("id", INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        for id in 0u64..100u64 {
+            index_writer.add_document(doc!(id_field => id));
+        }
+        index_writer.commit()?;
+        let reader = index.reader()?;
+        for num_deletes in 1u64..=10u64 {
+            index_writer.delete_term(Term::from_field_u64(id_field, num_deletes * 7));
+            index_writer.commit()?;
+            block_on(index_writer.garbage_collect_files())?;
+            let segment_meta = index.searchable_segment_metas()?[0].clone();
+            // Past `MAX_NUM_DELETE_FILES` files, the deletes are rewritten in a single file.
+            let delete_file_paths = segment_meta.delete_file_paths();
+            let expected_num_delete_files = if num_deletes as usize <= super::MAX_NUM_DELETE_FILES {
+                num_deletes as usize
+            } else {
+                num_deletes as usize - super::MAX_NUM_DELETE_FILES
+            };
+            assert_eq!(delete_file_paths.len(), expected_num_delete_files);
+            for path in &delete_file_paths {
+                assert!(index.directory().exists(path)?);
+            }
+            reader.reload()?;
+            let searcher = reader.searcher();
+            let segment_reader = searcher.segment_reader(0u32);
+            assert_eq!(segment_reader.num_docs(), 100 - num_deletes as u32);
+            for doc in 0u32..100u32 {
+                let is_deleted = doc % 7 == 0 && doc != 0 && u64::from(doc) <= num_deletes * 7;
+                assert_eq!(segment_reader.is_deleted(doc), is_deleted);
+            }
+        }
+        Ok(())
+    }
+
     #[test]
     fn test_ordered_batched_operations() {
         // * one delete for `doc!(field=>"a")`
@@ -1356,6 +2702,48 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_delete_terms() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let id_field = schema_builder.add_u64_field("id", INDEXED | FAST);
+        let tag_field = schema_builder.add_text_field("tag", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        for id in 0u64..10u64 {
+            let tag = if id == 5 { "x" } else { "y" };
+            index_writer.add_document(doc!(id_field => id, tag_field => tag));
+        }
+        index_writer.commit()?;
+        index_writer.add_document(doc!(id_field => 10u64, tag_field => "y"));
+        index_writer.add_document(doc!(id_field => 11u64, tag_field => "y"));
+        index_writer.delete_terms(vec![
+            Term::from_field_u64(id_field, 11u64),
+            Term::from_field_text(tag_field, "x"),
+            Term::from_field_u64(id_field, 3u64),
+            Term::from_field_u64(id_field, 100u64),
+            Term::from_field_u64(id_field, 3u64),
+            Term::from_field_u64(id_field, 7u64),
+            Term::from_field_text(tag_field, "z"),
+        ]);
+        // Documents added after the delete operation are not deleted.
+        index_writer.add_document(doc!(id_field => 12u64, tag_field => "x"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let mut alive_ids = Vec::new();
+        for segment_reader in searcher.segment_readers() {
+            let fast_field_reader = segment_reader.fast_fields().u64(id_field)?;
+            alive_ids.extend(
+                segment_reader
+                    .doc_ids_alive()
+                    .map(|doc| fast_field_reader.get(doc)),
+            );
+        }
+        alive_ids.sort_unstable();
+        assert_eq!(alive_ids, vec![0, 1, 2, 4, 6, 8, 9, 10, 12]);
+        Ok(())
+    }
+
     #[derive(Debug, Clone, Copy)]
     enum IndexingOp {
         AddDoc { id: u64 },
@@ -1641,4 +3029,718 @@ mod tests {
         let commit = index_writer.commit();
         assert!(commit.is_ok());
     }
+
+    #[test]
+    fn test_add_document_with_routing_key() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let tenant_field = schema_builder.add_text_field("tenant", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(4, 12_000_000)?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        for i in 0..100 {
+            let tenant = format!("tenant{}", i % 10);
+            index_writer
+                .add_document_with_routing_key(&tenant, doc!(tenant_field => tenant.clone()));
+        }
+        index_writer.commit()?;
+        for segment_meta in index.searchable_segment_metas()? {
+            assert!(segment_meta.routing_partition().is_some());
+        }
+        let searcher = index.reader()?.searcher();
+        for i in 0..10 {
+            let tenant_term = Term::from_field_text(tenant_field, &format!("tenant{}", i));
+            let mut num_segments_with_tenant = 0;
+            for segment_reader in searcher.segment_readers() {
+                if segment_reader
+                    .inverted_index(tenant_field)?
+                    .doc_freq(&tenant_term)?
+                    > 0
+                {
+                    num_segments_with_tenant += 1;
+                }
+            }
+            assert_eq!(num_segments_with_tenant, 1);
+        }
+        // Documents added without routing key do not belong to any partition.
+        index_writer.add_document(doc!(tenant_field => "tenant0"));
+        index_writer.commit()?;
+        let num_segments_without_partition = index
+            .searchable_segment_metas()?
+            .iter()
+            .filter(|segment_meta| segment_meta.routing_partition().is_none())
+            .count();
+        assert_eq!(num_segments_without_partition, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_routing_partition_does_not_depend_on_num_threads() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let tenant_field = schema_builder.add_text_field("tenant", STRING);
+        let index = Index::builder()
+            .schema(schema_builder.build())
+            .settings(IndexSettings {
+                num_routing_partitions: Some(2),
+                ..Default::default()
+            })
+            .create_in_ram()?;
+        let tenants: Vec<String> = (0..10).map(|i| format!("tenant{}", i)).collect();
+        for &num_threads in &[4, 2] {
+            let mut index_writer = index.writer_with_num_threads(num_threads, 12_000_000)?;
+            index_writer.set_merge_policy(Box::new(NoMergePolicy));
+            for tenant in &tenants {
+                index_writer
+                    .add_document_with_routing_key(tenant, doc!(tenant_field => tenant.clone()));
+            }
+            index_writer.commit()?;
+            index_writer.wait_merging_threads()?;
+        }
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.num_docs(), 20);
+        for tenant in &tenants {
+            let tenant_term = Term::from_field_text(tenant_field, tenant);
+            let expected_partition = compute_routing_partition(tenant, 2) as u32;
+            for segment_reader in searcher.segment_readers() {
+                if segment_reader
+                    .inverted_index(tenant_field)?
+                    .doc_freq(&tenant_term)?
+                    > 0
+                {
+                    let segment_meta = index
+                        .searchable_segment_metas()?
+                        .into_iter()
+                        .find(|segment_meta| segment_meta.id() == segment_reader.segment_id())
+                        .unwrap();
+                    assert_eq!(segment_meta.routing_partition(), Some(expected_partition));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_upsert() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let id_field = schema_builder.add_text_field("id", STRING);
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let settings = IndexSettings {
+            primary_key: Some("id".to_string()),
+            ..Default::default()
+        };
+        let index = Index::builder()
+            .schema(schema_builder.build())
+            .settings(settings)
+            .create_in_ram()?;
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.upsert(doc!(id_field => "a", text_field => "first"))?;
+        index_writer.upsert(doc!(id_field => "b", text_field => "other"))?;
+        index_writer.commit()?;
+        index_writer.upsert(doc!(id_field => "a", text_field => "second"))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.num_docs(), 2);
+        let doc_address = searcher
+            .doc_by_key(&Term::from_field_text(id_field, "a"))?
+            .unwrap();
+        let doc = searcher.doc(doc_address)?;
+        assert_eq!(doc.get_first(text_field).unwrap().text(), Some("second"));
+        assert!(searcher
+            .doc_by_key(&Term::from_field_text(id_field, "c"))?
+            .is_none());
+        assert!(matches!(
+            index_writer.upsert(doc!(text_field => "missing key")),
+            Err(TantivyError::InvalidArgument(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_upsert_invalid_primary_key() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let settings = IndexSettings {
+            primary_key: Some("text".to_string()),
+            ..Default::default()
+        };
+        let index = Index::builder()
+            .schema(schema_builder.build())
+            .settings(settings)
+            .create_in_ram()?;
+        let index_writer = index.writer_for_tests()?;
+        assert!(matches!(
+            index_writer.upsert(doc!(text_field => "hello")),
+            Err(TantivyError::SchemaError(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_deduplication_skip() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let event_field = schema_builder.add_text_field("event", STRING);
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        let num_docs = || -> crate::Result<u64> {
+            reader.reload()?;
+            Ok(reader.searcher().num_docs())
+        };
+        let deduplication = Deduplication::new(vec![event_field], DedupMode::Skip);
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_deduplication(deduplication.clone())?;
+        index_writer.add_document(doc!(event_field => "a", text_field => "first"));
+        index_writer.add_document(doc!(event_field => "a", text_field => "second"));
+        index_writer.add_document(doc!(event_field => "b"));
+        index_writer.add_document(doc!(text_field => "no event"));
+        index_writer.add_document(doc!(text_field => "no event"));
+        index_writer.commit()?;
+        assert_eq!(num_docs()?, 4);
+        // "a" was added by the last commit, "b" was not.
+        index_writer.add_document(doc!(event_field => "a"));
+        index_writer.add_document(doc!(event_field => "c"));
+        index_writer.commit()?;
+        assert_eq!(num_docs()?, 5);
+        index_writer.add_document(doc!(event_field => "a"));
+        index_writer.add_document(doc!(event_field => "b"));
+        index_writer.commit()?;
+        assert_eq!(num_docs()?, 6);
+        index_writer.add_document(doc!(event_field => "d"));
+        index_writer.rollback()?;
+        index_writer.add_document(doc!(event_field => "d"));
+        index_writer.commit()?;
+        assert_eq!(num_docs()?, 7);
+        drop(index_writer);
+
+        // The hashes of the last commit are persisted.
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_deduplication(deduplication)?;
+        index_writer.add_document(doc!(event_field => "d"));
+        index_writer.add_document(doc!(event_field => "e"));
+        index_writer.commit()?;
+        assert_eq!(num_docs()?, 8);
+        Ok(())
+    }
+
+    #[test]
+    fn test_deduplication_upsert() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let id_field = schema_builder.add_text_field("id", STRING);
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let hash_field = schema_builder.add_u64_field("hash", INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        assert!(matches!(
+            index_writer.set_deduplication(Deduplication::new(vec![], DedupMode::Skip)),
+            Err(TantivyError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            index_writer.set_deduplication(Deduplication::new(
+                vec![id_field],
+                DedupMode::Upsert(text_field)
+            )),
+            Err(TantivyError::SchemaError(_))
+        ));
+        index_writer.set_deduplication(Deduplication::new(
+            vec![id_field],
+            DedupMode::Upsert(hash_field),
+        ))?;
+        index_writer.add_document(doc!(id_field => "a", text_field => "first"));
+        index_writer.add_document(doc!(id_field => "b", text_field => "other"));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(id_field => "a", text_field => "second"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.num_docs(), 2);
+        let query = TermQuery::new(
+            Term::from_field_text(id_field, "a"),
+            IndexRecordOption::Basic,
+        );
+        let top_docs = searcher.search(&query, &TopDocs::with_limit(2))?;
+        assert_eq!(top_docs.len(), 1);
+        let doc = searcher.doc(top_docs[0].1)?;
+        assert_eq!(doc.get_first(text_field).unwrap().text(), Some("second"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_segments() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field => "existing"));
+        index_writer.commit()?;
+
+        let source = Index::create_in_ram(schema);
+        let segment_builder = SegmentBuilder::new(&source);
+        let segment_metas = vec![
+            segment_builder.build(vec![doc!(text_field => "a"), doc!(text_field => "b")])?,
+            segment_builder.build(vec![doc!(text_field => "c")])?,
+        ];
+        let imported_segment_metas = index_writer.add_segments(&source, &segment_metas)?;
+        assert_eq!(imported_segment_metas.len(), 2);
+        assert_eq!(imported_segment_metas[0].max_doc(), 2);
+        assert!(imported_segment_metas
+            .iter()
+            .all(|segment_meta| segment_meta.id() != segment_metas[0].id()));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.num_docs(), 4);
+        assert_eq!(
+            searcher.doc_freq(&Term::from_field_text(text_field, "c"))?,
+            1
+        );
+        assert!(index.validate_checksum()?.is_empty());
+
+        let mut other_schema_builder = schema::Schema::builder();
+        other_schema_builder.add_text_field("other", TEXT);
+        let other_source = Index::create_in_ram(other_schema_builder.build());
+        let other_segment_meta =
+            SegmentBuilder::new(&other_source).build(vec![schema::Document::default()])?;
+        assert!(matches!(
+            index_writer.add_segments(&other_source, &[other_segment_meta]),
+            Err(TantivyError::SchemaError(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_retention() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let timestamp_field = schema_builder.add_date_field("timestamp", INDEXED | FAST);
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        let now = chrono::Utc::now();
+        let old = now - chrono::Duration::days(10);
+        for _ in 0..3 {
+            index_writer.add_document(doc!(timestamp_field => old));
+        }
+        index_writer.commit()?;
+        index_writer.add_document(doc!(timestamp_field => old));
+        index_writer.add_document(doc!(timestamp_field => now));
+        index_writer.add_document(doc!(timestamp_field => now));
+        index_writer.commit()?;
+        assert_eq!(index.searchable_segment_metas()?.len(), 2);
+        let retention_policy =
+            RetentionPolicy::new(timestamp_field, std::time::Duration::from_secs(86_400));
+        index_writer.apply_retention(&retention_policy)?;
+        index_writer.commit()?;
+        let segment_metas = index.searchable_segment_metas()?;
+        assert_eq!(segment_metas.len(), 1);
+        assert_eq!(segment_metas[0].num_docs(), 2);
+        let invalid_policy =
+            RetentionPolicy::new(text_field, std::time::Duration::from_secs(86_400));
+        assert!(matches!(
+            index_writer.apply_retention(&invalid_policy),
+            Err(TantivyError::SchemaError(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_retention_precision_step() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let timestamp_options = IntOptions::default()
+            .set_indexed()
+            .set_fast(Cardinality::SingleValue)
+            .set_precision_step(8);
+        let timestamp_field = schema_builder.add_date_field("timestamp", timestamp_options);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        let now = chrono::Utc::now();
+        for days in 0..10 {
+            index_writer.add_document(doc!(timestamp_field => now - chrono::Duration::days(days)));
+        }
+        index_writer.commit()?;
+        let retention_policy =
+            RetentionPolicy::new(timestamp_field, std::time::Duration::from_secs(5 * 86_400));
+        index_writer.apply_retention(&retention_policy)?;
+        index_writer.commit()?;
+        // The documents of the last 5 days survive, even though their truncated
+        // prefix terms sort before the expiration timestamp.
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.num_docs(), 5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_retention_rollback() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let timestamp_field = schema_builder.add_date_field("timestamp", INDEXED | FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        let now = chrono::Utc::now();
+        let old = now - chrono::Duration::days(10);
+        for _ in 0..3 {
+            index_writer.add_document(doc!(timestamp_field => old));
+        }
+        index_writer.commit()?;
+        index_writer.add_document(doc!(timestamp_field => old));
+        index_writer.add_document(doc!(timestamp_field => now));
+        index_writer.commit()?;
+        let retention_policy =
+            RetentionPolicy::new(timestamp_field, std::time::Duration::from_secs(86_400));
+        index_writer.apply_retention(&retention_policy)?;
+        // The expired segment is only removed by the next commit.
+        assert_eq!(index_writer.segment_updater().segment_metas().len(), 1);
+        index_writer.rollback()?;
+        assert_eq!(index_writer.segment_updater().segment_metas().len(), 2);
+        index_writer.commit()?;
+        let segment_metas = index.searchable_segment_metas()?;
+        assert_eq!(segment_metas.len(), 2);
+        let num_docs: u32 = segment_metas
+            .iter()
+            .map(|segment_meta| segment_meta.num_docs())
+            .sum();
+        assert_eq!(num_docs, 5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_upgrade_in_place() -> crate::Result<()> {
+        use crate::collector::Count;
+        use crate::core::SegmentComponent;
+        use crate::directory::{Directory, Footer};
+
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.add_document(doc!(text_field => "b"));
+        index_writer.commit()?;
+        assert_eq!(index.index_format_version()?, crate::INDEX_FORMAT_VERSION);
+        assert!(index_writer.upgrade_in_place()?.is_none());
+
+        // Rewrites the footers of a segment, as if it had been written in the oldest
+        // supported index format.
+        let old_version = crate::Version {
+            major: 0,
+            minor: 15,
+            patch: 0,
+            index_format_version: crate::MIN_INDEX_FORMAT_VERSION,
+        };
+        let old_segment = index.searchable_segments()?.into_iter().next().unwrap();
+        for &component in SegmentComponent::iterator() {
+            let path = old_segment.relative_path(component);
+            if !index.directory().exists(&path)? {
+                continue;
+            }
+            let mut data = index.directory().open_read(&path)?.read_bytes()?.to_vec();
+            let mut hasher = crc32fast::Hasher::new();
+            hasher.update(&data);
+            let mut footer = Footer::new(hasher.finalize());
+            footer.version = old_version.clone();
+            footer.append_footer(&mut data)?;
+            index.directory().atomic_write(&path, &data)?;
+        }
+        assert_eq!(
+            index.index_format_version()?,
+            crate::MIN_INDEX_FORMAT_VERSION
+        );
+        let reader = index.reader()?;
+        let query = TermQuery::new(
+            Term::from_field_text(text_field, "a"),
+            IndexRecordOption::Basic,
+        );
+        assert_eq!(reader.searcher().search(&query, &Count)?, 2);
+
+        let segment_meta = index_writer.upgrade_in_place()?.unwrap();
+        assert_eq!(segment_meta.num_docs(), old_segment.meta().num_docs());
+        index_writer.wait_merging_threads()?;
+        assert_eq!(index.index_format_version()?, crate::INDEX_FORMAT_VERSION);
+        assert_eq!(index.searchable_segment_ids()?.len(), 2);
+        reader.reload()?;
+        assert_eq!(reader.searcher().search(&query, &Count)?, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_future_is_executor_agnostic() -> crate::Result<()> {
+        fn assert_send_static<F: std::future::Future + Send + 'static>(future: F) -> F {
+            future
+        }
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text_field => "b"));
+        index_writer.commit()?;
+        let segment_ids = index.searchable_segment_ids()?;
+        assert_eq!(segment_ids.len(), 2);
+
+        // The merge future can be moved to and awaited from any thread.
+        let merge_future = assert_send_static(index_writer.merge(&segment_ids));
+        let segment_meta = std::thread::spawn(move || block_on(merge_future))
+            .join()
+            .unwrap()?;
+        assert_eq!(segment_meta.num_docs(), 2);
+
+        // Merging segments that are not in the index fails with a proper error.
+        let merge_res = block_on(index_writer.merge(&segment_ids));
+        assert!(matches!(merge_res, Err(TantivyError::InvalidArgument(_))));
+        index_writer.wait_merging_threads()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_scratch_directory() -> crate::Result<()> {
+        use crate::core::SegmentComponent;
+        use crate::directory::{Directory, RamDirectory};
+
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let scratch_directory = RamDirectory::create();
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        index_writer.set_merge_scratch_directory(scratch_directory.clone())?;
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text_field => "b"));
+        index_writer.commit()?;
+        let segment_ids = index.searchable_segment_ids()?;
+        let segment_meta = block_on(index_writer.merge(&segment_ids))?;
+        index_writer.wait_merging_threads()?;
+
+        // The files of the merged segment were moved from the scratch directory to the index.
+        let merged_segment = index.segment(segment_meta);
+        for &component in &[SegmentComponent::Postings, SegmentComponent::Store] {
+            let path = merged_segment.relative_path(component);
+            assert!(!scratch_directory.exists(&path)?);
+            assert!(index.directory().exists(&path)?);
+        }
+        assert!(index.directory().list_damaged()?.is_empty());
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        assert_eq!(searcher.num_docs(), 2);
+        assert!(searcher.doc(DocAddress::new(0, 1)).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_disk_space_reserve() -> crate::Result<()> {
+        use crate::directory::error::{DeleteError, OpenReadError, OpenWriteError};
+        use crate::directory::{
+            Directory, FileHandle, RamDirectory, WatchCallback, WatchHandle, WritePtr,
+        };
+        use std::io;
+        use std::path::Path;
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use std::sync::Arc;
+
+        /// `RamDirectory` reporting an arbitrary available space.
+        #[derive(Clone, Debug, Default)]
+        struct FixedSpaceDirectory {
+            directory: RamDirectory,
+            available_bytes: Arc<AtomicU64>,
+        }
+
+        impl Directory for FixedSpaceDirectory {
+            fn get_file_handle(&self, path: &Path) -> Result<Box<dyn FileHandle>, OpenReadError> {
+                self.directory.get_file_handle(path)
+            }
+            fn delete(&self, path: &Path) -> Result<(), DeleteError> {
+                self.directory.delete(path)
+            }
+            fn exists(&self, path: &Path) -> Result<bool, OpenReadError> {
+                self.directory.exists(path)
+            }
+            fn open_write(&self, path: &Path) -> Result<WritePtr, OpenWriteError> {
+                self.directory.open_write(path)
+            }
+            fn atomic_read(&self, path: &Path) -> Result<Vec<u8>, OpenReadError> {
+                self.directory.atomic_read(path)
+            }
+            fn atomic_write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+                self.directory.atomic_write(path, data)
+            }
+            fn available_space(&self) -> io::Result<Option<u64>> {
+                Ok(Some(self.available_bytes.load(Ordering::SeqCst)))
+            }
+            fn watch(&self, watch_callback: WatchCallback) -> crate::Result<WatchHandle> {
+                self.directory.watch(watch_callback)
+            }
+        }
+
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let directory = FixedSpaceDirectory::default();
+        directory.available_bytes.store(u64::MAX, Ordering::SeqCst);
+        let index = Index::create(
+            directory.clone(),
+            schema_builder.build(),
+            IndexSettings::default(),
+        )?;
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        index_writer.set_disk_space_reserve(1_000);
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text_field => "b"));
+        index_writer.commit()?;
+
+        directory.available_bytes.store(500, Ordering::SeqCst);
+        assert!(matches!(
+            index_writer.commit(),
+            Err(TantivyError::DiskFull {
+                available_bytes: 500,
+                required_bytes: 1_000
+            })
+        ));
+
+        // Merging requires the space of the merged segments, on top of the reserve.
+        directory.available_bytes.store(1_001, Ordering::SeqCst);
+        let segment_ids = index.searchable_segment_ids()?;
+        assert!(matches!(
+            block_on(index_writer.merge(&segment_ids)),
+            Err(TantivyError::DiskFull { .. })
+        ));
+
+        // So does flushing a new segment.
+        index_writer.add_document(doc!(text_field => "c"));
+        assert!(matches!(
+            index_writer.commit(),
+            Err(TantivyError::DiskFull { .. })
+        ));
+        assert_eq!(index.reader()?.searcher().num_docs(), 2);
+
+        // The reserve survives a rollback.
+        index_writer.rollback()?;
+        directory.available_bytes.store(500, Ordering::SeqCst);
+        assert!(matches!(
+            index_writer.commit(),
+            Err(TantivyError::DiskFull {
+                available_bytes: 500,
+                required_bytes: 1_000
+            })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_indexing_worker_panic() -> crate::Result<()> {
+        use crate::tokenizer::{BoxTokenStream, SimpleTokenizer, Tokenizer};
+
+        #[derive(Clone)]
+        struct PanickingTokenizer;
+
+        impl Tokenizer for PanickingTokenizer {
+            fn token_stream<'a>(&self, text: &'a str) -> BoxTokenStream<'a> {
+                if text == "panic" {
+                    panic!("Tokenizer panicked");
+                }
+                SimpleTokenizer.token_stream(text)
+            }
+        }
+
+        let mut schema_builder = schema::Schema::builder();
+        let text_options = TextOptions::default()
+            .set_indexing_options(TextFieldIndexing::default().set_tokenizer("panicking"));
+        let text_field = schema_builder.add_text_field("text", text_options);
+        let index = Index::create_in_ram(schema_builder.build());
+        index.tokenizers().register("panicking", PanickingTokenizer);
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field => "hello"));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text_field => "panic"));
+        let commit_res = index_writer.commit();
+        assert!(matches!(
+            commit_res,
+            Err(TantivyError::WorkerPanicked { ref message, .. }) if message == "Tokenizer panicked"
+        ));
+
+        // The documents indexed by the other workers are not published
+        // until the writer is rolled back.
+        index_writer.add_document(doc!(text_field => "world"));
+        assert!(matches!(
+            index_writer.commit(),
+            Err(TantivyError::ErrorInThread(_))
+        ));
+        assert_eq!(index.reader()?.searcher().num_docs(), 1);
+
+        // The writer remains usable.
+        index_writer.rollback()?;
+        index_writer.add_document(doc!(text_field => "world"));
+        index_writer.commit()?;
+        assert_eq!(index.reader()?.searcher().num_docs(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_single_threaded_writer() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        let mut index_writer = index.single_threaded_writer(10_000_000)?;
+        assert!(index_writer.is_single_threaded());
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.add_document(doc!(text_field => "b"));
+        index_writer.delete_term(Term::from_field_text(text_field, "a"));
+        index_writer.add_document_with_routing_key("tenant", doc!(text_field => "a"));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text_field => "c"));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text_field => "d"));
+        index_writer.rollback()?;
+        assert!(index_writer.is_single_threaded());
+
+        let segment_ids = index.searchable_segment_ids()?;
+        assert_eq!(segment_ids.len(), 2);
+        block_on(index_writer.merge(&segment_ids))?;
+        index_writer.wait_merging_threads()?;
+        reader.reload()?;
+        let searcher = reader.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let term_count = |text: &str| {
+            let term_query = TermQuery::new(
+                Term::from_field_text(text_field, text),
+                IndexRecordOption::Basic,
+            );
+            term_query.count(&searcher).unwrap()
+        };
+        assert_eq!(term_count("a"), 1);
+        assert_eq!(term_count("b"), 1);
+        assert_eq!(term_count("c"), 1);
+        assert_eq!(term_count("d"), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_single_threaded_writer_full_pipeline() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let id_field = schema_builder.add_u64_field("id", INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(0, 10_000_000)?;
+        assert!(index_writer.is_single_threaded());
+        let num_docs = super::PIPELINE_MAX_SIZE_IN_DOCS as u64 + 10;
+        for id in 0..num_docs {
+            index_writer.add_document(doc!(id_field => id));
+        }
+        index_writer.delete_term(Term::from_field_u64(id_field, 0));
+        // The queue was full: some documents were indexed before the commit.
+        assert!(!index_writer.segment_updater().segment_metas().is_empty());
+        assert_eq!(index.reader()?.searcher().num_docs(), 0);
+        index_writer.commit()?;
+        assert_eq!(index.reader()?.searcher().num_docs(), num_docs - 1);
+        Ok(())
+    }
 }
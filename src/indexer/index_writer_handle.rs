@@ -0,0 +1,153 @@
+use super::auto_commit::AutoCommitState;
+use super::index_writer::OperationSender;
+use super::operation::{AddOperation, DeleteOperation, DeleteTarget};
+use crate::indexer::delete_queue::DeleteQueue;
+use crate::indexer::stamper::Stamper;
+use crate::schema::{Document, Term};
+use crate::{Opstamp, TantivyError};
+use smallvec::smallvec;
+use std::sync::{Arc, RwLock};
+
+/// What the handles of an `IndexWriter` need to send operations to its pipeline.
+///
+/// It is replaced by the writer whenever its operation channel is recreated
+/// (on commit and rollback), and removed when the writer is dropped.
+#[derive(Clone)]
+pub(crate) struct HandleState {
+    pub operation_sender: OperationSender,
+    pub stamper: Stamper,
+    pub delete_queue: DeleteQueue,
+    pub auto_commit: Option<Arc<AutoCommitState>>,
+}
+
+pub(crate) type SharedHandleState = Arc<RwLock<Option<HandleState>>>;
+
+/// A cheap, cloneable and thread-safe handle to add and delete documents
+/// with an `IndexWriter`.
+///
+/// Unlike the `IndexWriter`, the handle does not require to be wrapped in a lock
+/// to be shared across threads, so that ingestion is not serialized with
+/// the writer's `commit`. The operations are sent to the indexing pipeline of the
+/// writer, and committed by the next `commit` of the writer.
+///
+/// While the writer prepares a commit or rolls back, the operations of the handles
+/// block until it is done, so that each operation belongs either to the commit
+/// or to the next one.
+///
+/// Documents added through a handle are not deduplicated.
+/// Once the writer is dropped, the operations of the handles fail.
+///
+/// See [`IndexWriter::handle`](./struct.IndexWriter.html#method.handle).
+#[derive(Clone)]
+pub struct IndexWriterHandle {
+    state: SharedHandleState,
+}
+
+impl IndexWriterHandle {
+    pub(crate) fn new(state: SharedHandleState) -> IndexWriterHandle {
+        IndexWriterHandle { state }
+    }
+
+    fn with_state<T>(&self, operation: impl FnOnce(&HandleState) -> T) -> crate::Result<T> {
+        let state_guard = self.state.read()?;
+        let state = state_guard.as_ref().ok_or_else(|| {
+            TantivyError::ErrorInThread("The IndexWriter was dropped.".to_string())
+        })?;
+        Ok(operation(state))
+    }
+
+    /// Adds a document.
+    ///
+    /// See [`IndexWriter::add_document`](./struct.IndexWriter.html#method.add_document).
+    pub fn add_document(&self, document: Document) -> crate::Result<Opstamp> {
+        self.with_state(|state| {
+            if let Some(auto_commit) = state.auto_commit.as_ref() {
+                auto_commit.record_document(&document);
+            }
+            let opstamp = state.stamper.stamp();
+            let add_operation = AddOperation { opstamp, document };
+            state
+                .operation_sender
+                .send(smallvec![add_operation])
+                .map(|()| opstamp)
+                .map_err(|_| {
+                    TantivyError::ErrorInThread(
+                        "Failed to send the document to the indexing workers.".to_string(),
+                    )
+                })
+        })?
+    }
+
+    /// Deletes all documents containing a given term.
+    ///
+    /// See [`IndexWriter::delete_term`](./struct.IndexWriter.html#method.delete_term).
+    pub fn delete_term(&self, term: Term) -> crate::Result<Opstamp> {
+        self.with_state(|state| {
+            if let Some(auto_commit) = state.auto_commit.as_ref() {
+                auto_commit.record_delete();
+            }
+            let opstamp = state.stamper.stamp();
+            state.delete_queue.push(DeleteOperation {
+                opstamp,
+                target: DeleteTarget::Term(term),
+            });
+            opstamp
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::collector::Count;
+    use crate::query::AllQuery;
+    use crate::schema::{Schema, STRING};
+    use crate::{Index, Term};
+    use std::thread;
+
+    #[test]
+    fn test_index_writer_handle() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let id_field = schema_builder.add_text_field("id", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(2, 12_000_000)?;
+        let handle = index_writer.handle()?;
+        let join_handles: Vec<_> = (0..4)
+            .map(|thread_id| {
+                let handle = handle.clone();
+                thread::spawn(move || {
+                    for doc_id in 0..100 {
+                        let id = format!("{}-{}", thread_id, doc_id);
+                        handle.add_document(doc!(id_field => id)).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for join_handle in join_handles {
+            join_handle.join().unwrap();
+        }
+        index_writer.commit()?;
+        let reader = index.reader()?;
+        assert_eq!(reader.searcher().search(&AllQuery, &Count)?, 400);
+
+        handle.delete_term(Term::from_field_text(id_field, "0-0"))?;
+        index_writer.rollback()?;
+        // The handle keeps working after a rollback.
+        handle.delete_term(Term::from_field_text(id_field, "1-0"))?;
+        handle.add_document(doc!(id_field => "new"))?;
+        index_writer.commit()?;
+        reader.reload()?;
+        let searcher = reader.searcher();
+        assert_eq!(searcher.search(&AllQuery, &Count)?, 400);
+        assert_eq!(
+            searcher.doc_freq(&Term::from_field_text(id_field, "0-0"))?,
+            1
+        );
+
+        drop(index_writer);
+        assert!(handle.add_document(doc!(id_field => "late")).is_err());
+        assert!(handle
+            .delete_term(Term::from_field_text(id_field, "new"))
+            .is_err());
+        Ok(())
+    }
+}
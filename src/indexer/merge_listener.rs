@@ -0,0 +1,219 @@
+use crate::core::Segment;
+use crate::indexer::doc_id_mapping::SegmentDocidMapping;
+use crate::{DocId, SegmentId};
+use std::collections::HashMap;
+
+/// Mapping between the doc ids of a segment resulting from a merge,
+/// and the doc ids of the segments it merges.
+///
+/// Deleted documents do not make it into the merged segment.
+pub struct MergeDocIdMapping {
+    merged_segment_id: SegmentId,
+    new_doc_to_old: Vec<(SegmentId, DocId)>,
+    old_doc_to_new: HashMap<SegmentId, Vec<Option<DocId>>>,
+}
+
+impl MergeDocIdMapping {
+    pub(crate) fn new(
+        merged_segment_id: SegmentId,
+        merged_segments: &[Segment],
+        doc_id_mapping: &SegmentDocidMapping,
+    ) -> MergeDocIdMapping {
+        let mut old_doc_to_new: HashMap<SegmentId, Vec<Option<DocId>>> = merged_segments
+            .iter()
+            .map(|segment| (segment.id(), vec![None; segment.meta().max_doc() as usize]))
+            .collect();
+        let mut new_doc_to_old = Vec::with_capacity(doc_id_mapping.len());
+        for (new_doc, (old_doc, reader_with_ordinal)) in doc_id_mapping.iter().enumerate() {
+            let segment_id = reader_with_ordinal.reader.segment_id();
+            new_doc_to_old.push((segment_id, *old_doc));
+            if let Some(new_docs) = old_doc_to_new.get_mut(&segment_id) {
+                new_docs[*old_doc as usize] = Some(new_doc as DocId);
+            }
+        }
+        MergeDocIdMapping {
+            merged_segment_id,
+            new_doc_to_old,
+            old_doc_to_new,
+        }
+    }
+
+    /// Returns the id of the segment resulting from the merge.
+    pub fn merged_segment_id(&self) -> SegmentId {
+        self.merged_segment_id
+    }
+
+    /// Returns the ids of the merged segments.
+    pub fn source_segment_ids(&self) -> impl Iterator<Item = SegmentId> + '_ {
+        self.old_doc_to_new.keys().cloned()
+    }
+
+    /// Returns the number of documents of the segment resulting from the merge.
+    pub fn num_docs(&self) -> u32 {
+        self.new_doc_to_old.len() as u32
+    }
+
+    /// Returns the merged segment and the doc id in this segment
+    /// of the document `new_doc` of the segment resulting from the merge.
+    ///
+    /// # Panics
+    /// Panics if `new_doc` is not lower than `num_docs()`.
+    pub fn old_doc(&self, new_doc: DocId) -> (SegmentId, DocId) {
+        self.new_doc_to_old[new_doc as usize]
+    }
+
+    /// Returns the doc id, in the segment resulting from the merge, of the document
+    /// `old_doc` of the merged segment `segment_id`.
+    ///
+    /// Returns `None` if the document was deleted, or if `segment_id` was not merged.
+    pub fn new_doc(&self, segment_id: SegmentId, old_doc: DocId) -> Option<DocId> {
+        self.old_doc_to_new
+            .get(&segment_id)
+            .and_then(|new_docs| new_docs.get(old_doc as usize).cloned().flatten())
+    }
+
+    /// Iterates over the documents of the segment resulting from the merge, as
+    /// `(new_doc, segment_id, old_doc)` triplets, in increasing `new_doc` order.
+    pub fn iter(&self) -> impl Iterator<Item = (DocId, SegmentId, DocId)> + '_ {
+        self.new_doc_to_old
+            .iter()
+            .enumerate()
+            .map(|(new_doc, &(segment_id, old_doc))| (new_doc as DocId, segment_id, old_doc))
+    }
+}
+
+/// Listens to the merges of an `IndexWriter`.
+///
+/// Applications keeping data outside of the index, indexed by doc ids
+/// (e.g. vectors in another store), can use it to remap their data whenever
+/// segments get merged.
+///
+/// A listener is registered using
+/// [`IndexWriter::set_merge_listener`](./struct.IndexWriter.html#method.set_merge_listener).
+pub trait MergeListener: Send + Sync + 'static {
+    /// Called once the segment resulting from a merge is written,
+    /// before it replaces the merged segments in the index.
+    ///
+    /// Returning an error cancels the merge.
+    /// The merged segments may also remain in the index if the merge is cancelled
+    /// after this call, e.g. because the `IndexWriter` is rolled back.
+    fn on_merge(&self, doc_id_mapping: &MergeDocIdMapping) -> crate::Result<()>;
+}
+
+impl<F> MergeListener for F
+where
+    F: Fn(&MergeDocIdMapping) -> crate::Result<()> + Send + Sync + 'static,
+{
+    fn on_merge(&self, doc_id_mapping: &MergeDocIdMapping) -> crate::Result<()> {
+        self(doc_id_mapping)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MergeDocIdMapping;
+    use crate::fastfield::FastFieldReader;
+    use crate::indexer::NoMergePolicy;
+    use crate::schema::{Schema, FAST, INDEXED};
+    use crate::{DocId, Index, SegmentId, Term};
+    use futures::executor::block_on;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_merge_listener() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let id_field = schema_builder.add_u64_field("id", INDEXED | FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        let merges: Arc<Mutex<Vec<(SegmentId, Vec<(DocId, SegmentId, DocId)>)>>> = Arc::default();
+        let merges_clone = merges.clone();
+        index_writer.set_merge_listener(move |mapping: &MergeDocIdMapping| {
+            merges_clone
+                .lock()
+                .unwrap()
+                .push((mapping.merged_segment_id(), mapping.iter().collect()));
+            assert_eq!(mapping.source_segment_ids().count(), 2);
+            Ok(())
+        });
+        for id in 0..3u64 {
+            index_writer.add_document(doc!(id_field => id));
+        }
+        index_writer.commit()?;
+        for id in 3..5u64 {
+            index_writer.add_document(doc!(id_field => id));
+        }
+        index_writer.commit()?;
+        index_writer.delete_term(Term::from_field_u64(id_field, 1));
+        index_writer.commit()?;
+
+        let reader = index.reader()?;
+        let searcher_before_merge = reader.searcher();
+        let ids_before_merge: HashMap<SegmentId, Vec<u64>> = searcher_before_merge
+            .segment_readers()
+            .iter()
+            .map(|segment_reader| {
+                let id_reader = segment_reader.fast_fields().u64(id_field).unwrap();
+                let ids = (0..segment_reader.max_doc())
+                    .map(|doc| id_reader.get(doc))
+                    .collect();
+                (segment_reader.segment_id(), ids)
+            })
+            .collect();
+        let segment_ids = index.searchable_segment_ids()?;
+        let merged_segment_meta = block_on(index_writer.merge(&segment_ids))?;
+
+        let merges = merges.lock().unwrap();
+        assert_eq!(merges.len(), 1);
+        let (merged_segment_id, mapping) = &merges[0];
+        assert_eq!(*merged_segment_id, merged_segment_meta.id());
+        assert_eq!(mapping.len(), 4);
+        reader.reload()?;
+        let searcher = reader.searcher();
+        let merged_segment_reader = searcher.segment_reader(0);
+        assert_eq!(merged_segment_reader.segment_id(), *merged_segment_id);
+        let id_reader = merged_segment_reader.fast_fields().u64(id_field)?;
+        for (expected_new_doc, &(new_doc, segment_id, old_doc)) in mapping.iter().enumerate() {
+            assert_eq!(new_doc, expected_new_doc as DocId);
+            assert_eq!(
+                id_reader.get(new_doc),
+                ids_before_merge[&segment_id][old_doc as usize]
+            );
+            assert_ne!(id_reader.get(new_doc), 1);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_listener_after_rollback() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let id_field = schema_builder.add_u64_field("id", INDEXED | FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        let merged_segment_ids: Arc<Mutex<Vec<SegmentId>>> = Arc::default();
+        let merged_segment_ids_clone = merged_segment_ids.clone();
+        index_writer.set_merge_listener(move |mapping: &MergeDocIdMapping| {
+            merged_segment_ids_clone
+                .lock()
+                .unwrap()
+                .push(mapping.merged_segment_id());
+            Ok(())
+        });
+        index_writer.add_document(doc!(id_field => 0u64));
+        index_writer.rollback()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        for id in 0..2u64 {
+            index_writer.add_document(doc!(id_field => id));
+            index_writer.commit()?;
+        }
+        let segment_ids = index.searchable_segment_ids()?;
+        assert_eq!(segment_ids.len(), 2);
+        let merged_segment_meta = block_on(index_writer.merge(&segment_ids))?;
+        assert_eq!(
+            &merged_segment_ids.lock().unwrap()[..],
+            &[merged_segment_meta.id()]
+        );
+        Ok(())
+    }
+}
@@ -1,5 +1,6 @@
 use crate::core::SegmentId;
 use crate::core::SegmentMeta;
+use std::collections::BTreeMap;
 use std::fmt::Debug;
 use std::marker;
 
@@ -35,12 +36,82 @@ impl MergePolicy for NoMergePolicy {
     }
 }
 
+/// Wraps a merge policy so that only segments belonging to the same
+/// routing partition get merged together.
+///
+/// Segments are grouped by their routing partition
+/// (see `IndexWriter::add_document_with_routing_key`), and the wrapped
+/// merge policy is called on each group independently.
+/// Segments without routing partition form their own group.
+#[derive(Debug, Clone)]
+pub struct RoutingMergePolicy<TMergePolicy> {
+    merge_policy: TMergePolicy,
+}
+
+impl<TMergePolicy: MergePolicy> RoutingMergePolicy<TMergePolicy> {
+    /// Creates a new `RoutingMergePolicy` wrapping the given merge policy.
+    pub fn new(merge_policy: TMergePolicy) -> RoutingMergePolicy<TMergePolicy> {
+        RoutingMergePolicy { merge_policy }
+    }
+}
+
+impl<TMergePolicy: MergePolicy> MergePolicy for RoutingMergePolicy<TMergePolicy> {
+    fn compute_merge_candidates(&self, segments: &[SegmentMeta]) -> Vec<MergeCandidate> {
+        let mut segments_per_partition: BTreeMap<Option<u32>, Vec<SegmentMeta>> = BTreeMap::new();
+        for segment in segments {
+            segments_per_partition
+                .entry(segment.routing_partition())
+                .or_insert_with(Vec::new)
+                .push(segment.clone());
+        }
+        segments_per_partition
+            .values()
+            .flat_map(|partition_segments| {
+                self.merge_policy
+                    .compute_merge_candidates(partition_segments)
+            })
+            .collect()
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
 
     use super::*;
     use crate::core::SegmentId;
     use crate::core::SegmentMeta;
+    use crate::core::SegmentMetaInventory;
+
+    #[test]
+    fn test_routing_merge_policy() {
+        let inventory = SegmentMetaInventory::default();
+        let segment_metas: Vec<SegmentMeta> = [Some(0), None, Some(1), Some(0), None, Some(1)]
+            .iter()
+            .map(|&routing_partition| {
+                inventory
+                    .new_segment_meta(SegmentId::generate_random(), 10)
+                    .with_routing_partition(routing_partition)
+            })
+            .collect();
+        let merge_policy = RoutingMergePolicy::new(MergeWheneverPossible);
+        let merge_candidates = merge_policy.compute_merge_candidates(&segment_metas);
+        assert_eq!(merge_candidates.len(), 3);
+        for merge_candidate in merge_candidates {
+            let routing_partitions: Vec<Option<u32>> = merge_candidate
+                .0
+                .iter()
+                .map(|segment_id| {
+                    segment_metas
+                        .iter()
+                        .find(|segment_meta| segment_meta.id() == *segment_id)
+                        .unwrap()
+                        .routing_partition()
+                })
+                .collect();
+            assert_eq!(routing_partitions.len(), 2);
+            assert_eq!(routing_partitions[0], routing_partitions[1]);
+        }
+    }
 
     /// `MergePolicy` useful for test purposes.
     ///
@@ -15,8 +15,8 @@ use crate::postings::Postings;
 use crate::postings::{InvertedIndexSerializer, SegmentPostings};
 use crate::schema::Cardinality;
 use crate::schema::FieldType;
-use crate::schema::{Field, Schema};
-use crate::store::StoreWriter;
+use crate::schema::{Field, FieldNormEncoding, Schema};
+use crate::store::{StoreReader, StoreWriter};
 use crate::termdict::TermMerger;
 use crate::termdict::TermOrdinal;
 use crate::IndexSortByField;
@@ -29,10 +29,12 @@ use crate::{
     SegmentOrdinal,
 };
 use crate::{DocId, InvertedIndexReader, SegmentComponent};
+use fail::fail_point;
 use itertools::Itertools;
 use measure_time::debug_time;
 use std::cmp;
 use std::collections::HashMap;
+use std::ops::Range;
 use std::sync::Arc;
 use tantivy_bitpacker::minmax;
 
@@ -45,8 +47,12 @@ fn compute_total_num_tokens(readers: &[SegmentReader], field: Field) -> crate::R
             // using the fieldnorm
             let fieldnorms_reader = reader.get_fieldnorms_reader(field)?;
             for doc in reader.doc_ids_alive() {
-                let fieldnorm_id = fieldnorms_reader.fieldnorm_id(doc);
-                count[fieldnorm_id as usize] += 1;
+                if let Some(fieldnorm) = fieldnorms_reader.exact_fieldnorm(doc) {
+                    total_tokens += u64::from(fieldnorm);
+                } else {
+                    let fieldnorm_id = fieldnorms_reader.fieldnorm_id(doc);
+                    count[fieldnorm_id as usize] += 1;
+                }
             }
         } else {
             total_tokens += reader.inverted_index(field)?.total_num_tokens();
@@ -117,6 +123,22 @@ fn compute_min_max_val(
     }
 }
 
+// Stores a document read from `store_reader`, converting it to the
+// `DocSerialization` of `store_writer` if needed.
+fn store_doc_bytes(
+    store_writer: &mut StoreWriter,
+    store_reader: &StoreReader,
+    doc_bytes: &[u8],
+) -> crate::Result<()> {
+    if store_reader.doc_serialization() == store_writer.doc_serialization() {
+        store_writer.store_bytes(doc_bytes)?;
+    } else {
+        let doc = store_reader.doc_serialization().deserialize(doc_bytes)?;
+        store_writer.store(&doc)?;
+    }
+    Ok(())
+}
+
 struct TermOrdinalMapping {
     per_segment_new_term_ordinals: Vec<Vec<TermOrdinal>>,
 }
@@ -181,6 +203,10 @@ impl IndexMerger {
         let mut readers = vec![];
         let mut max_doc: u32 = 0u32;
         for segment in segments {
+            // The merge relies on the segments being sorted like the index.
+            segment
+                .meta()
+                .validate_index_sort(index_settings.sort_by_field.as_ref())?;
             if segment.meta().num_docs() > 0 {
                 let reader = SegmentReader::open(segment)?;
                 max_doc += reader.num_docs();
@@ -246,10 +272,21 @@ impl IndexMerger {
                 .iter()
                 .map(|reader| reader.get_fieldnorms_reader(field))
                 .collect::<Result<_, _>>()?;
+            let is_exact = self
+                .schema
+                .get_field_entry(field)
+                .field_type()
+                .get_fieldnorm_encoding()
+                == Some(FieldNormEncoding::Exact);
             for (doc_id, reader_with_ordinal) in doc_id_mapping.iter() {
                 let fieldnorms_reader = &fieldnorms_readers[reader_with_ordinal.ordinal as usize];
-                let fieldnorm_id = fieldnorms_reader.fieldnorm_id(*doc_id);
-                fieldnorms_data.push(fieldnorm_id);
+                if is_exact {
+                    let fieldnorm = fieldnorms_reader.fieldnorm(*doc_id);
+                    fieldnorms_data.extend_from_slice(&fieldnorm.to_le_bytes());
+                } else {
+                    let fieldnorm_id = fieldnorms_reader.fieldnorm_id(*doc_id);
+                    fieldnorms_data.push(fieldnorm_id);
+                }
             }
 
             fieldnorms_serializer.serialize_field(field, &fieldnorms_data[..])?;
@@ -258,10 +295,11 @@ impl IndexMerger {
         Ok(())
     }
 
+    // Writes the fast fields, except for the hierarchical facets fields,
+    // which depend on the merged term dictionaries.
     fn write_fast_fields(
         &self,
         fast_field_serializer: &mut CompositeFastFieldSerializer,
-        mut term_ord_mappings: HashMap<Field, TermOrdinalMapping>,
         doc_id_mapping: &SegmentDocidMapping,
     ) -> crate::Result<()> {
         debug_time!("write_fast_fields");
@@ -270,16 +308,7 @@ impl IndexMerger {
             let field_type = field_entry.field_type();
             match field_type {
                 FieldType::HierarchicalFacet(_) => {
-                    let term_ordinal_mapping = term_ord_mappings
-                        .remove(&field)
-                        .expect("Logic Error in Tantivy (Please report). HierarchicalFact field should have required a\
-                        `term_ordinal_mapping`.");
-                    self.write_hierarchical_facet_field(
-                        field,
-                        &term_ordinal_mapping,
-                        fast_field_serializer,
-                        doc_id_mapping,
-                    )?;
+                    // Written by `write_facet_fast_fields`, once the postings are merged.
                 }
                 FieldType::U64(ref options)
                 | FieldType::I64(ref options)
@@ -308,6 +337,29 @@ impl IndexMerger {
         Ok(())
     }
 
+    fn write_facet_fast_fields(
+        &self,
+        fast_field_serializer: &mut CompositeFastFieldSerializer,
+        mut term_ord_mappings: HashMap<Field, TermOrdinalMapping>,
+        doc_id_mapping: &SegmentDocidMapping,
+    ) -> crate::Result<()> {
+        for (field, field_entry) in self.schema.fields() {
+            if let FieldType::HierarchicalFacet(_) = field_entry.field_type() {
+                let term_ordinal_mapping = term_ord_mappings
+                    .remove(&field)
+                    .expect("Logic Error in Tantivy (Please report). HierarchicalFact field should have required a\
+                    `term_ordinal_mapping`.");
+                self.write_hierarchical_facet_field(
+                    field,
+                    &term_ordinal_mapping,
+                    fast_field_serializer,
+                    doc_id_mapping,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
     // used both to merge field norms, `u64/i64` single fast fields.
     fn write_single_fast_field(
         &self,
@@ -806,6 +858,7 @@ impl IndexMerger {
     ) -> crate::Result<Option<TermOrdinalMapping>> {
         debug_time!("write_postings_for_field");
         let mut positions_buffer: Vec<u32> = Vec::with_capacity(1_000);
+        let mut offsets_buffer: Vec<Range<usize>> = Vec::new();
         let mut delta_computer = DeltaComputer::new();
 
         let mut max_term_ords: Vec<TermOrdinal> = Vec::new();
@@ -875,7 +928,7 @@ impl IndexMerger {
         );
 
         let mut segment_postings_containing_the_term: Vec<(usize, SegmentPostings)> = vec![];
-        let mut doc_id_and_positions = vec![];
+        let mut doc_id_positions_and_offsets = vec![];
 
         while merged_terms.advance() {
             segment_postings_containing_the_term.clear();
@@ -934,6 +987,7 @@ impl IndexMerger {
                         // there is at least one document.
                         let term_freq = segment_postings.term_freq();
                         segment_postings.positions(&mut positions_buffer);
+                        segment_postings.offsets(&mut offsets_buffer);
                         // if doc_id_mapping exists, the docids are reordered, they are
                         // not just stacked. The field serializer expects monotonically increasing
                         // docids, so we collect and sort them first, before writing.
@@ -942,14 +996,16 @@ impl IndexMerger {
                         // avoid the loading into a vec via some form of kmerge, but then the merge
                         // logic would deviate much more from the stacking case (unsorted index)
                         if !doc_id_mapping.is_trivial() {
-                            doc_id_and_positions.push((
+                            doc_id_positions_and_offsets.push((
                                 remapped_doc_id,
                                 term_freq,
                                 positions_buffer.to_vec(),
+                                offsets_buffer.to_vec(),
                             ));
                         } else {
                             let delta_positions = delta_computer.compute_delta(&positions_buffer);
                             field_serializer.write_doc(remapped_doc_id, term_freq, delta_positions);
+                            field_serializer.write_offsets(&offsets_buffer);
                         }
                     }
 
@@ -957,11 +1013,12 @@ impl IndexMerger {
                 }
             }
             if !doc_id_mapping.is_trivial() {
-                doc_id_and_positions.sort_unstable_by_key(|&(doc_id, _, _)| doc_id);
-                for (doc_id, term_freq, positions) in &doc_id_and_positions {
+                doc_id_positions_and_offsets.sort_unstable_by_key(|&(doc_id, _, _, _)| doc_id);
+                for (doc_id, term_freq, positions, offsets) in &doc_id_positions_and_offsets {
                     field_serializer.write_doc(*doc_id, *term_freq, positions);
+                    field_serializer.write_offsets(offsets);
                 }
-                doc_id_and_positions.clear();
+                doc_id_positions_and_offsets.clear();
             }
 
             // closing the term.
@@ -1017,7 +1074,8 @@ impl IndexMerger {
                 let doc_bytes_it = &mut document_iterators[reader_with_ordinal.ordinal as usize];
                 if let Some(doc_bytes_res) = doc_bytes_it.next() {
                     let doc_bytes = doc_bytes_res?;
-                    store_writer.store_bytes(&doc_bytes)?;
+                    let store_reader = &store_readers[reader_with_ordinal.ordinal as usize];
+                    store_doc_bytes(store_writer, store_reader, &doc_bytes)?;
                 } else {
                     return Err(DataCorruption::comment_only(&format!(
                         "unexpected missing document in docstore on merge, doc id {:?}",
@@ -1045,10 +1103,11 @@ impl IndexMerger {
                     // take 7 in order to not walk over all checkpoints.
                     || store_reader.block_checkpoints().take(7).count() < 6
                     || store_reader.compressor() != store_writer.compressor()
+                    || store_reader.doc_serialization() != store_writer.doc_serialization()
                 {
                     for doc_bytes_res in store_reader.iter_raw(reader.delete_bitset()) {
                         let doc_bytes = doc_bytes_res?;
-                        store_writer.store_bytes(&doc_bytes)?;
+                        store_doc_bytes(store_writer, &store_reader, &doc_bytes)?;
                     }
                 } else {
                     store_writer.stack(&store_reader)?;
@@ -1058,46 +1117,89 @@ impl IndexMerger {
         Ok(())
     }
 
-    /// Writes the merged segment by pushing information
-    /// to the `SegmentSerializer`.
-    ///
-    /// # Returns
-    /// The number of documents in the resulting segment.
-    pub fn write(&self, mut serializer: SegmentSerializer) -> crate::Result<u32> {
-        let doc_id_mapping = if let Some(sort_by_field) = self.index_settings.sort_by_field.as_ref()
-        {
+    /// Computes the mapping from the doc ids of the merged segment
+    /// to the doc ids of the segments being merged.
+    pub(crate) fn doc_id_mapping(&self) -> crate::Result<SegmentDocidMapping> {
+        if let Some(sort_by_field) = self.index_settings.sort_by_field.as_ref() {
             // If the documents are already sorted and stackable, we ignore the mapping and execute
             // it as if there was no sorting
             if self.is_disjunct_and_sorted_on_sort_property(sort_by_field)? {
-                self.get_doc_id_from_concatenated_data()?
+                self.get_doc_id_from_concatenated_data()
             } else {
-                self.generate_doc_id_mapping(sort_by_field)?
+                self.generate_doc_id_mapping(sort_by_field)
             }
         } else {
-            self.get_doc_id_from_concatenated_data()?
-        };
-
-        if let Some(fieldnorms_serializer) = serializer.extract_fieldnorms_serializer() {
-            self.write_fieldnorms(fieldnorms_serializer, &doc_id_mapping)?;
+            self.get_doc_id_from_concatenated_data()
         }
-        let fieldnorm_data = serializer
-            .segment()
-            .open_read(SegmentComponent::FieldNorms)?;
-        let fieldnorm_readers = FieldNormReaders::open(fieldnorm_data)?;
-        let term_ord_mappings = self.write_postings(
-            serializer.get_postings_serializer(),
-            fieldnorm_readers,
-            &doc_id_mapping,
-        )?;
-        self.write_fast_fields(
+    }
+
+    /// Writes the merged segment by pushing information
+    /// to the `SegmentSerializer`.
+    ///
+    /// # Returns
+    /// The number of documents in the resulting segment.
+    pub fn write(&self, serializer: SegmentSerializer) -> crate::Result<u32> {
+        let doc_id_mapping = self.doc_id_mapping()?;
+        self.write_with_doc_id_mapping(serializer, &doc_id_mapping)
+    }
+
+    /// Writes the merged segment, with the doc ids given by `doc_id_mapping`.
+    pub(crate) fn write_with_doc_id_mapping(
+        &self,
+        mut serializer: SegmentSerializer,
+        doc_id_mapping: &SegmentDocidMapping,
+    ) -> crate::Result<u32> {
+        fail_point!(crate::failpoints::MERGE_WRITE, |msg| Err(
+            crate::failpoints::failpoint_error(msg).into()
+        ));
+        let fieldnorms_serializer_opt = serializer.extract_fieldnorms_serializer();
+        let segment = serializer.segment().clone();
+        let (postings_serializer, fast_field_serializer, store_writer) = serializer.split_mut();
+        let mut store_res = Ok(());
+        let mut fast_fields_res = Ok(());
+        // The doc store and the fast fields do not depend on the postings,
+        // and are merged on separate threads.
+        let term_ord_mappings_res = rayon::scope(|scope| {
+            scope.spawn(|_| {
+                store_res = self.write_storable_fields(store_writer, doc_id_mapping);
+            });
+            scope.spawn(|_| {
+                fast_fields_res = self.write_fast_fields(fast_field_serializer, doc_id_mapping);
+            });
+            self.write_fieldnorms_and_postings(
+                fieldnorms_serializer_opt,
+                &segment,
+                postings_serializer,
+                doc_id_mapping,
+            )
+        });
+        store_res?;
+        fast_fields_res?;
+        // The fast fields of the hierarchical facets hold term ordinals,
+        // and are therefore written once the term dictionaries are merged.
+        self.write_facet_fast_fields(
             serializer.get_fast_field_serializer(),
-            term_ord_mappings,
-            &doc_id_mapping,
+            term_ord_mappings_res?,
+            doc_id_mapping,
         )?;
-        self.write_storable_fields(serializer.get_store_writer(), &doc_id_mapping)?;
         serializer.close()?;
         Ok(self.max_doc)
     }
+
+    fn write_fieldnorms_and_postings(
+        &self,
+        fieldnorms_serializer_opt: Option<FieldNormsSerializer>,
+        segment: &Segment,
+        postings_serializer: &mut InvertedIndexSerializer,
+        doc_id_mapping: &SegmentDocidMapping,
+    ) -> crate::Result<HashMap<Field, TermOrdinalMapping>> {
+        if let Some(fieldnorms_serializer) = fieldnorms_serializer_opt {
+            self.write_fieldnorms(fieldnorms_serializer, doc_id_mapping)?;
+        }
+        let fieldnorm_data = segment.open_read(SegmentComponent::FieldNorms)?;
+        let fieldnorm_readers = FieldNormReaders::open(fieldnorm_data, self.schema.clone())?;
+        self.write_postings(postings_serializer, fieldnorm_readers, doc_id_mapping)
+    }
 }
 
 #[cfg(test)]
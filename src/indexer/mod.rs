@@ -1,15 +1,21 @@
+mod auto_commit;
+mod deduplication;
 pub mod delete_queue;
 
 pub mod doc_id_mapping;
 mod doc_opstamp_mapping;
 pub mod index_writer;
+mod index_writer_handle;
 mod log_merge_policy;
+mod merge_listener;
 mod merge_operation;
 pub mod merge_policy;
 pub mod merger;
 mod merger_sorted_index_test;
 pub mod operation;
 mod prepared_commit;
+mod retention_policy;
+mod segment_builder;
 mod segment_entry;
 mod segment_manager;
 mod segment_register;
@@ -17,12 +23,21 @@ pub mod segment_serializer;
 pub mod segment_updater;
 mod segment_writer;
 mod stamper;
+mod task_executor;
 
-pub use self::index_writer::IndexWriter;
+pub use self::auto_commit::{
+    AutoCommitListener, AutoCommitPolicy, AutoCommitThread, PendingChanges,
+};
+pub use self::deduplication::{DedupMode, Deduplication};
+pub use self::index_writer::{IndexWriter, TryAddDocumentError};
+pub use self::index_writer_handle::IndexWriterHandle;
 pub use self::log_merge_policy::LogMergePolicy;
+pub use self::merge_listener::{MergeDocIdMapping, MergeListener};
 pub use self::merge_operation::MergeOperation;
-pub use self::merge_policy::{MergeCandidate, MergePolicy, NoMergePolicy};
+pub use self::merge_policy::{MergeCandidate, MergePolicy, NoMergePolicy, RoutingMergePolicy};
 pub use self::prepared_commit::PreparedCommit;
+pub use self::retention_policy::RetentionPolicy;
+pub use self::segment_builder::SegmentBuilder;
 pub use self::segment_entry::SegmentEntry;
 pub use self::segment_manager::SegmentManager;
 pub use self::segment_serializer::SegmentSerializer;
@@ -1,19 +1,35 @@
 use crate::schema::Document;
 use crate::schema::Term;
 use crate::Opstamp;
+use std::ops::Range;
 
 /// Timestamped Delete operation.
 #[derive(Clone, Eq, PartialEq, Debug)]
 pub struct DeleteOperation {
     pub opstamp: Opstamp,
-    pub term: Term,
+    pub target: DeleteTarget,
+}
+
+/// Documents deleted by a `DeleteOperation`.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum DeleteTarget {
+    /// Documents containing the term.
+    Term(Term),
+    /// Documents containing any of the terms, which are sorted and deduplicated.
+    Terms(Vec<Term>),
+    /// Documents containing a term within the range.
+    /// Both bounds are terms of the same field.
+    ///
+    /// For fields indexed at several precisions, only the full precision
+    /// terms of the values are matched.
+    TermRange(Range<Term>),
 }
 
 impl Default for DeleteOperation {
     fn default() -> Self {
         DeleteOperation {
             opstamp: 0u64,
-            term: Term::new(),
+            target: DeleteTarget::Term(Term::new()),
         }
     }
 }
@@ -32,11 +32,15 @@ impl<'a> PreparedCommit<'a> {
 
     pub fn commit(self) -> crate::Result<Opstamp> {
         info!("committing {}", self.opstamp);
-        let _ = block_on(
-            self.index_writer
-                .segment_updater()
-                .schedule_commit(self.opstamp, self.payload),
-        );
+        enter_span!("commit", opstamp = self.opstamp);
+        let dedup_hashes = self.index_writer.pending_dedup_hashes();
+        block_on(self.index_writer.segment_updater().schedule_commit(
+            self.opstamp,
+            self.payload,
+            dedup_hashes,
+        ))?;
+        self.index_writer.commit_dedup_hashes();
+        self.index_writer.reset_auto_commit();
         Ok(self.opstamp)
     }
 }
@@ -0,0 +1,62 @@
+use crate::schema::{Cardinality, Field, FieldType, Schema};
+use crate::TantivyError;
+use chrono::Utc;
+use std::time::Duration;
+
+/// Time-based retention policy.
+///
+/// Documents are expected to carry a timestamp in a single-valued, fast, date field.
+/// Documents whose timestamp is older than the time-to-live are expired.
+///
+/// A retention policy is applied using
+/// [`IndexWriter::apply_retention`](./struct.IndexWriter.html#method.apply_retention).
+#[derive(Clone, Debug)]
+pub struct RetentionPolicy {
+    timestamp_field: Field,
+    ttl: Duration,
+}
+
+impl RetentionPolicy {
+    /// Creates a new retention policy, expiring the documents whose
+    /// `timestamp_field` is older than `ttl`.
+    pub fn new(timestamp_field: Field, ttl: Duration) -> RetentionPolicy {
+        RetentionPolicy {
+            timestamp_field,
+            ttl,
+        }
+    }
+
+    /// Returns the field holding the documents timestamp.
+    pub fn timestamp_field(&self) -> Field {
+        self.timestamp_field
+    }
+
+    /// Returns the time-to-live of the documents.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Returns the timestamp (in seconds) under which documents are expired.
+    pub(crate) fn expiration_timestamp(&self) -> i64 {
+        let ttl_secs = self.ttl.as_secs().min(i64::max_value() as u64) as i64;
+        Utc::now().timestamp().saturating_sub(ttl_secs)
+    }
+
+    /// Checks that the timestamp field is a single-valued fast date field.
+    pub(crate) fn validate(&self, schema: &Schema) -> crate::Result<()> {
+        let field_entry = schema.get_field_entry(self.timestamp_field);
+        let is_valid = match field_entry.field_type() {
+            FieldType::Date(int_options) => {
+                int_options.get_fastfield_cardinality() == Some(Cardinality::SingleValue)
+            }
+            _ => false,
+        };
+        if !is_valid {
+            return Err(TantivyError::SchemaError(format!(
+                "The retention policy timestamp field {:?} needs to be a single-valued fast date field",
+                field_entry.name()
+            )));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+use super::operation::AddOperation;
+use super::segment_writer::SegmentWriter;
+use crate::core::{Index, SegmentMeta};
+use crate::schema::Document;
+use crate::TantivyError;
+
+/// Default memory budget of a `SegmentBuilder`.
+const DEFAULT_MEMORY_BUDGET: usize = 50_000_000;
+
+/// `SegmentBuilder` builds a complete segment out of a batch of documents,
+/// without an `IndexWriter`.
+///
+/// No thread is spawned and the index lock is not acquired. This makes it possible to
+/// build segments offline, e.g. on the workers of a batch job, and to ship them
+/// to the nodes serving the index afterwards.
+///
+/// The returned `SegmentMeta` is not part of any commit. It can be introduced
+/// in the index using `IndexWriter::add_segment`.
+/// As long as the `SegmentMeta` is alive, the files of the segment will not
+/// be garbage collected.
+///
+/// All of the documents end up in the same segment: the memory usage grows
+/// with the size of the segment.
+pub struct SegmentBuilder {
+    index: Index,
+    memory_budget: usize,
+}
+
+impl SegmentBuilder {
+    /// Creates a `SegmentBuilder` writing its segments in the directory of `index`.
+    pub fn new(index: &Index) -> SegmentBuilder {
+        SegmentBuilder {
+            index: index.clone(),
+            memory_budget: DEFAULT_MEMORY_BUDGET,
+        }
+    }
+
+    /// Sets the memory budget, used to size the indexing hash tables.
+    pub fn memory_budget(mut self, memory_budget: usize) -> SegmentBuilder {
+        self.memory_budget = memory_budget;
+        self
+    }
+
+    /// Builds a new segment containing `docs`, and returns its `SegmentMeta`.
+    ///
+    /// Building a segment without any document is an error.
+    pub fn build<I>(&self, docs: I) -> crate::Result<SegmentMeta>
+    where
+        I: IntoIterator<Item = Document>,
+    {
+        let schema = self.index.schema();
+        let segment = self.index.new_segment();
+        let mut segment_writer =
+            SegmentWriter::for_segment(self.memory_budget, segment.clone(), &schema)?;
+        for document in docs {
+            // Documents get the opstamp 0, as no delete operation can apply to them.
+            let add_operation = AddOperation {
+                opstamp: 0u64,
+                document,
+            };
+            segment_writer.add_document(add_operation, &schema)?;
+        }
+        let max_doc = segment_writer.max_doc();
+        if max_doc == 0 {
+            return Err(TantivyError::InvalidArgument(
+                "Cannot build a segment without any document.".to_string(),
+            ));
+        }
+        segment_writer.finalize()?;
+        let segment = segment.with_max_doc(max_doc);
+        let fast_field_ranges = segment.compute_fast_field_ranges()?;
+        let segment_meta = segment
+            .meta()
+            .clone()
+            .with_fast_field_ranges(fast_field_ranges)
+            .with_index_sort(self.index.settings().sort_by_field.clone());
+        segment_meta.untrack_temp_docstore();
+        Ok(segment_meta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SegmentBuilder;
+    use crate::schema::{Schema, STORED, TEXT};
+    use crate::{Index, Term};
+
+    #[test]
+    fn test_segment_builder() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let segment_builder = SegmentBuilder::new(&index);
+        assert!(segment_builder.build(Vec::new()).is_err());
+        let segment_meta = segment_builder.build(vec![
+            doc!(text_field => "hello"),
+            doc!(text_field => "happy tax payer"),
+        ])?;
+        assert_eq!(segment_meta.max_doc(), 2);
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_segment(segment_meta)?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.num_docs(), 2);
+        assert_eq!(
+            searcher.doc_freq(&Term::from_field_text(text_field, "happy"))?,
+            1
+        );
+        Ok(())
+    }
+}
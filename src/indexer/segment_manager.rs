@@ -7,12 +7,14 @@ use crate::indexer::SegmentEntry;
 use std::collections::hash_set::HashSet;
 use std::fmt::{self, Debug, Formatter};
 use std::sync::RwLock;
-use std::sync::{RwLockReadGuard, RwLockWriteGuard};
+use std::sync::{PoisonError, RwLockReadGuard, RwLockWriteGuard};
 
 #[derive(Default)]
 struct SegmentRegisters {
     uncommitted: SegmentRegister,
     committed: SegmentRegister,
+    // Committed segments that will be removed by the next commit.
+    removed: HashSet<SegmentId>,
 }
 
 #[derive(PartialEq, Eq)]
@@ -27,7 +29,10 @@ impl SegmentRegisters {
     /// If some segment is missing or segments are in a different state (this should not happen
     /// if tantivy is used correctly), returns `None`.
     fn segments_status(&self, segment_ids: &[SegmentId]) -> Option<SegmentsStatus> {
-        if self.uncommitted.contains_all(segment_ids) {
+        if self.contains_removed(segment_ids) {
+            warn!("segment_ids: {:?} are being removed", segment_ids);
+            None
+        } else if self.uncommitted.contains_all(segment_ids) {
             Some(SegmentsStatus::Uncommitted)
         } else if self.committed.contains_all(segment_ids) {
             Some(SegmentsStatus::Committed)
@@ -41,6 +46,20 @@ impl SegmentRegisters {
             None
         }
     }
+
+    fn contains_removed(&self, segment_ids: &[SegmentId]) -> bool {
+        segment_ids
+            .iter()
+            .any(|segment_id| self.removed.contains(segment_id))
+    }
+
+    fn committed_segment_entries(&self) -> Vec<SegmentEntry> {
+        self.committed
+            .segment_entries()
+            .into_iter()
+            .filter(|segment_entry| !self.removed.contains(&segment_entry.segment_id()))
+            .collect()
+    }
 }
 
 /// The segment manager stores the list of segments
@@ -73,6 +92,7 @@ impl SegmentManager {
             registers: RwLock::new(SegmentRegisters {
                 uncommitted: SegmentRegister::default(),
                 committed: SegmentRegister::new(segment_metas, delete_cursor),
+                removed: HashSet::new(),
             }),
         }
     }
@@ -82,36 +102,44 @@ impl SegmentManager {
         in_merge_segment_ids: &HashSet<SegmentId>,
     ) -> (Vec<SegmentMeta>, Vec<SegmentMeta>) {
         let registers_lock = self.read();
+        let excluded_segment_ids: HashSet<SegmentId> = in_merge_segment_ids
+            .union(&registers_lock.removed)
+            .cloned()
+            .collect();
         (
             registers_lock
                 .committed
-                .get_mergeable_segments(in_merge_segment_ids),
+                .get_mergeable_segments(&excluded_segment_ids),
             registers_lock
                 .uncommitted
                 .get_mergeable_segments(in_merge_segment_ids),
         )
     }
-    /// Returns all of the segment entries (committed or uncommitted)
+    /// Returns all of the segment entries (committed or uncommitted),
+    /// except for the segments that will be removed by the next commit.
     pub fn segment_entries(&self) -> Vec<SegmentEntry> {
         let registers_lock = self.read();
         let mut segment_entries = registers_lock.uncommitted.segment_entries();
-        segment_entries.extend(registers_lock.committed.segment_entries());
+        segment_entries.extend(registers_lock.committed_segment_entries());
         segment_entries
     }
 
     // Lock poisoning should never happen :
     // The lock is acquired and released within this class,
     // and the operations cannot panic.
+    //
+    // Should it happen anyway, the registers are never left halfway
+    // through an update, so we keep using them rather than panicking.
     fn read(&self) -> RwLockReadGuard<'_, SegmentRegisters> {
         self.registers
             .read()
-            .expect("Failed to acquire read lock on SegmentManager.")
+            .unwrap_or_else(PoisonError::into_inner)
     }
 
     fn write(&self) -> RwLockWriteGuard<'_, SegmentRegisters> {
         self.registers
             .write()
-            .expect("Failed to acquire write lock on SegmentManager.")
+            .unwrap_or_else(PoisonError::into_inner)
     }
 
     /// Deletes all empty segments
@@ -133,12 +161,28 @@ impl SegmentManager {
         let mut registers_lock = self.write();
         registers_lock.committed.clear();
         registers_lock.uncommitted.clear();
+        registers_lock.removed.clear();
+    }
+
+    /// Removes the given segments, whether they are committed or not.
+    ///
+    /// Uncommitted segments are removed right away, while committed segments
+    /// remain part of the committed segments until the next commit.
+    pub(crate) fn remove_segments(&self, segment_ids: &[SegmentId]) {
+        let mut registers_lock = self.write();
+        for segment_id in segment_ids {
+            registers_lock.uncommitted.remove_segment(segment_id);
+            if registers_lock.committed.contains_all(&[*segment_id]) {
+                registers_lock.removed.insert(*segment_id);
+            }
+        }
     }
 
     pub fn commit(&self, segment_entries: Vec<SegmentEntry>) {
         let mut registers_lock = self.write();
         registers_lock.committed.clear();
         registers_lock.uncommitted.clear();
+        registers_lock.removed.clear();
         for segment_entry in segment_entries {
             registers_lock.committed.add_segment_entry(segment_entry);
         }
@@ -152,6 +196,10 @@ impl SegmentManager {
     pub fn start_merge(&self, segment_ids: &[SegmentId]) -> crate::Result<Vec<SegmentEntry>> {
         let registers_lock = self.read();
         let mut segment_entries = vec![];
+        if registers_lock.contains_removed(segment_ids) {
+            let error_msg = "Merge operation sent for segments that are being removed.".to_string();
+            return Err(TantivyError::InvalidArgument(error_msg));
+        }
         if registers_lock.uncommitted.contains_all(segment_ids) {
             for segment_id in segment_ids {
                 let segment_entry = registers_lock.uncommitted
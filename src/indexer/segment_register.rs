@@ -46,23 +46,33 @@ impl SegmentRegister {
         self.segment_states.clear();
     }
 
+    // Segments are listed in the order of their ids, rather than in the order of the
+    // `HashMap`, so that merges are deterministic when segment ids are.
     pub fn get_mergeable_segments(
         &self,
         in_merge_segment_ids: &HashSet<SegmentId>,
     ) -> Vec<SegmentMeta> {
-        self.segment_states
+        let mut segment_metas: Vec<SegmentMeta> = self
+            .segment_states
             .values()
             .filter(|segment_entry| !in_merge_segment_ids.contains(&segment_entry.segment_id()))
             .map(|segment_entry| segment_entry.meta().clone())
-            .collect()
+            .collect();
+        segment_metas.sort_by_key(SegmentMeta::id);
+        segment_metas
     }
 
     pub fn segment_ids(&self) -> Vec<SegmentId> {
-        self.segment_states.keys().cloned().collect()
+        let mut segment_ids: Vec<SegmentId> = self.segment_states.keys().cloned().collect();
+        segment_ids.sort();
+        segment_ids
     }
 
     pub fn segment_entries(&self) -> Vec<SegmentEntry> {
-        self.segment_states.values().cloned().collect()
+        let mut segment_entries: Vec<SegmentEntry> =
+            self.segment_states.values().cloned().collect();
+        segment_entries.sort_by_key(SegmentEntry::segment_id);
+        segment_entries
     }
 
     pub fn segment_metas(&self) -> Vec<SegmentMeta> {
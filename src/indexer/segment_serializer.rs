@@ -39,10 +39,13 @@ impl SegmentSerializer {
         let fieldnorms_serializer = FieldNormsSerializer::from_write(fieldnorms_write)?;
 
         let postings_serializer = InvertedIndexSerializer::open(&mut segment)?;
-        let compressor = segment.index().settings().docstore_compression;
+        let settings = segment.index().settings();
+        let store_writer = StoreWriter::new(store_write, settings.docstore_compression)
+            .with_doc_serialization(settings.docstore_serialization)
+            .with_large_value_threshold(settings.docstore_large_value_threshold);
         Ok(SegmentSerializer {
             segment,
-            store_writer: StoreWriter::new(store_write, compressor),
+            store_writer,
             fast_field_serializer,
             fieldnorms_serializer: Some(fieldnorms_serializer),
             postings_serializer,
@@ -84,6 +87,22 @@ impl SegmentSerializer {
         &mut self.store_writer
     }
 
+    /// Splits the serializer into the serializers of the postings, of the fast fields
+    /// and of the store, so that they can be written concurrently.
+    pub(crate) fn split_mut(
+        &mut self,
+    ) -> (
+        &mut InvertedIndexSerializer,
+        &mut CompositeFastFieldSerializer,
+        &mut StoreWriter,
+    ) {
+        (
+            &mut self.postings_serializer,
+            &mut self.fast_field_serializer,
+            &mut self.store_writer,
+        )
+    }
+
     /// Finalize the segment serialization.
     pub fn close(mut self) -> crate::Result<()> {
         if let Some(fieldnorms_serializer) = self.extract_fieldnorms_serializer() {
@@ -1,39 +1,51 @@
 use super::segment_manager::SegmentManager;
+use crate::common::HasLen;
+use crate::core::index::list_meta_generations;
+use crate::core::meta_generation_filepath;
 use crate::core::Index;
 use crate::core::IndexMeta;
 use crate::core::IndexSettings;
 use crate::core::Segment;
+use crate::core::SegmentComponent;
 use crate::core::SegmentId;
 use crate::core::SegmentMeta;
 use crate::core::META_FILEPATH;
-use crate::directory::{Directory, DirectoryClone, GarbageCollectionResult};
+use crate::directory::{Directory, DirectoryClone, GarbageCollectionResult, ManagedDirectory};
+use crate::error::{ErrorContext, ResultExt};
 use crate::indexer::delete_queue::DeleteCursor;
 use crate::indexer::index_writer::advance_deletes;
+use crate::indexer::merge_listener::{MergeDocIdMapping, MergeListener};
 use crate::indexer::merge_operation::MergeOperationInventory;
 use crate::indexer::merger::IndexMerger;
 use crate::indexer::segment_manager::SegmentsStatus;
 use crate::indexer::stamper::Stamper;
+use crate::indexer::task_executor::TaskExecutor;
 use crate::indexer::SegmentEntry;
 use crate::indexer::SegmentSerializer;
 use crate::indexer::{DefaultMergePolicy, MergePolicy};
 use crate::indexer::{MergeCandidate, MergeOperation};
 use crate::schema::Schema;
 use crate::Opstamp;
+use fail::fail_point;
 use futures::channel::oneshot;
-use futures::executor::{ThreadPool, ThreadPoolBuilder};
-use futures::future::Future;
-use futures::future::TryFutureExt;
+use futures::executor::ThreadPoolBuilder;
+use futures::future::{FutureExt, TryFutureExt};
 use std::borrow::BorrowMut;
-use std::collections::HashSet;
-use std::io::Write;
+use std::collections::{HashSet, VecDeque};
+use std::future::Future;
 use std::ops::Deref;
+use std::panic::AssertUnwindSafe;
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::sync::RwLock;
+use std::sync::{PoisonError, RwLock};
 
 const NUM_MERGE_THREADS: usize = 4;
 
+/// By default, only the meta file of the last commit generation is kept.
+/// It is enough to recover the last commit if `meta.json` gets corrupted.
+const DEFAULT_NUM_COMMIT_GENERATIONS: usize = 1;
+
 /// Save the index meta file.
 /// This operation is atomic :
 /// Either
@@ -55,7 +67,9 @@ pub fn save_new_metas(
             schema,
             opstamp: 0u64,
             payload: None,
+            dedup_hashes: Vec::new(),
         },
+        0u64,
         directory,
     )
 }
@@ -68,12 +82,18 @@ pub fn save_new_metas(
 /// - it success, and `meta.json` is written
 /// and flushed.
 ///
+/// The metas are also saved in the meta file of the commit generation `generation`.
+///
 /// This method is not part of tantivy's public API
-fn save_metas(metas: &IndexMeta, directory: &dyn Directory) -> crate::Result<()> {
+fn save_metas(metas: &IndexMeta, generation: u64, directory: &dyn Directory) -> crate::Result<()> {
+    fail_point!(crate::failpoints::SAVE_METAS, |msg| Err(
+        crate::failpoints::failpoint_error(msg).into()
+    ));
     info!("save metas");
-    let mut buffer = serde_json::to_vec_pretty(metas)?;
-    // Just adding a new line at the end of the buffer.
-    writeln!(&mut buffer)?;
+    let buffer = metas.serialize_meta_file(generation)?;
+    // The meta file of the generation is written first, so that the commit
+    // can be recovered from it if `meta.json` gets corrupted while it is written.
+    directory.atomic_write(&meta_generation_filepath(generation), &buffer[..])?;
     directory.atomic_write(&META_FILEPATH, &buffer[..])?;
     debug!("Saved metas {:?}", serde_json::to_string_pretty(&metas));
     Ok(())
@@ -111,10 +131,18 @@ async fn garbage_collect_files(
 
 /// Merges a list of segments the list of segment givens in the `segment_entries`.
 /// This function happens in the calling thread and is computationally expensive.
+///
+/// If a `scratch_directory` is given, the merged segment is written in it, and
+/// its files are only moved to the index directory once the merge is complete.
+///
+/// If a `merge_listener` is given, it is notified of the doc id mapping of the merge
+/// once the merged segment is written.
 fn merge(
     index: &Index,
     mut segment_entries: Vec<SegmentEntry>,
     target_opstamp: Opstamp,
+    scratch_directory: Option<ManagedDirectory>,
+    merge_listener: Option<Arc<dyn MergeListener>>,
 ) -> crate::Result<SegmentEntry> {
     // first we need to apply deletes to our segment.
     let merged_segment = index.new_segment();
@@ -137,13 +165,64 @@ fn merge(
         IndexMerger::open(index.schema(), index.settings().clone(), &segments[..])?;
 
     // ... we just serialize this index merger in our new segment to merge the segments.
-    let segment_serializer = SegmentSerializer::for_segment(merged_segment.clone(), true)?;
+    let output_segment = match scratch_directory.as_ref() {
+        Some(scratch_directory) => Segment::for_index(
+            index.with_directory(scratch_directory.clone()),
+            merged_segment.meta().clone(),
+        ),
+        None => merged_segment.clone(),
+    };
+    let segment_serializer = SegmentSerializer::for_segment(output_segment, true)?;
 
-    let num_docs = merger.write(segment_serializer)?;
+    let doc_id_mapping = merger.doc_id_mapping()?;
+    let num_docs = merger.write_with_doc_id_mapping(segment_serializer, &doc_id_mapping)?;
+
+    if let Some(scratch_directory) = scratch_directory.as_ref() {
+        for component in SegmentComponent::iterator() {
+            let path = merged_segment.relative_path(*component);
+            if scratch_directory.exists(&path)? {
+                index.directory().move_file_from(scratch_directory, &path)?;
+            }
+        }
+    }
 
     let merged_segment_id = merged_segment.id();
 
-    let segment_meta = index.new_segment_meta(merged_segment_id, num_docs);
+    if let Some(merge_listener) = merge_listener {
+        let merge_doc_id_mapping =
+            MergeDocIdMapping::new(merged_segment_id, &segments, &doc_id_mapping);
+        merge_listener.on_merge(&merge_doc_id_mapping)?;
+    }
+
+    // The merged segment keeps the routing partition of the merged segments
+    // if they all share the same one.
+    let first_routing_partition = segment_entries[0].meta().routing_partition();
+    let routing_partition = if segment_entries
+        .iter()
+        .all(|segment_entry| segment_entry.meta().routing_partition() == first_routing_partition)
+    {
+        first_routing_partition
+    } else {
+        None
+    };
+
+    let segment_meta = index
+        .new_segment_meta(merged_segment_id, num_docs)
+        .with_routing_partition(routing_partition)
+        .with_index_sort(index.settings().sort_by_field.clone());
+    let fast_field_ranges = index
+        .segment(segment_meta.clone())
+        .compute_fast_field_ranges()?;
+    let segment_meta = segment_meta.with_fast_field_ranges(fast_field_ranges);
+    let num_bytes_written: u64 = segment_meta
+        .list_files()
+        .iter()
+        .filter_map(|path| index.directory().open_read(path).ok())
+        .map(|file| file.len() as u64)
+        .sum();
+    index
+        .metrics()
+        .record_merge_bytes_written(num_bytes_written);
     Ok(SegmentEntry::new(segment_meta, delete_cursor, None))
 }
 
@@ -210,7 +289,13 @@ pub fn merge_segments<Dir: Directory>(
     let segment_serializer = SegmentSerializer::for_segment(merged_segment, true)?;
     let num_docs = merger.write(segment_serializer)?;
 
-    let segment_meta = merged_index.new_segment_meta(merged_segment_id, num_docs);
+    let segment_meta = merged_index
+        .new_segment_meta(merged_segment_id, num_docs)
+        .with_index_sort(merged_index.settings().sort_by_field.clone());
+    let fast_field_ranges = merged_index
+        .segment(segment_meta.clone())
+        .compute_fast_field_ranges()?;
+    let segment_meta = segment_meta.with_fast_field_ranges(fast_field_ranges);
 
     let stats = format!(
         "Segments Merge: [{}]",
@@ -230,10 +315,11 @@ pub fn merge_segments<Dir: Directory>(
         schema: target_schema,
         opstamp: 0u64,
         payload: Some(stats),
+        dedup_hashes: Vec::new(),
     };
 
     // save the meta.json
-    save_metas(&index_meta, merged_index.directory_mut())?;
+    save_metas(&index_meta, 0u64, merged_index.directory_mut())?;
 
     Ok(merged_index)
 }
@@ -246,12 +332,20 @@ pub(crate) struct InnerSegmentUpdater {
     // This should be up to date as all update happen through
     // the unique active `SegmentUpdater`.
     active_index_meta: RwLock<Arc<IndexMeta>>,
-    pool: ThreadPool,
-    merge_thread_pool: ThreadPool,
+    pool: TaskExecutor,
+    merge_thread_pool: TaskExecutor,
 
     index: Index,
     segment_manager: SegmentManager,
     merge_policy: RwLock<Arc<dyn MergePolicy>>,
+    merge_scratch_directory: RwLock<Option<ManagedDirectory>>,
+    merge_listener: RwLock<Option<Arc<dyn MergeListener>>>,
+    disk_space_reserve: RwLock<Option<u64>>,
+    // Metas of the last commit generations, the active one included.
+    // Keeping them alive prevents the garbage collection of their segments.
+    commit_generations: RwLock<VecDeque<(u64, Arc<IndexMeta>)>>,
+    num_commit_generations: AtomicUsize,
+    last_generation: AtomicU64,
     killed: AtomicBool,
     stamper: Stamper,
     merge_operations: MergeOperationInventory,
@@ -262,28 +356,48 @@ impl SegmentUpdater {
         index: Index,
         stamper: Stamper,
         delete_cursor: &DeleteCursor,
+        single_threaded: bool,
     ) -> crate::Result<SegmentUpdater> {
         let segments = index.searchable_segment_metas()?;
         let segment_manager = SegmentManager::from_segments(segments, delete_cursor);
-        let pool = ThreadPoolBuilder::new()
-            .name_prefix("segment_updater")
-            .pool_size(1)
-            .create()
-            .map_err(|_| {
-                crate::TantivyError::SystemError(
-                    "Failed to spawn segment updater thread".to_string(),
-                )
-            })?;
-        let merge_thread_pool = ThreadPoolBuilder::new()
-            .name_prefix("merge_thread")
-            .pool_size(NUM_MERGE_THREADS)
-            .create()
-            .map_err(|_| {
-                crate::TantivyError::SystemError(
-                    "Failed to spawn segment merging thread".to_string(),
-                )
-            })?;
+        let (pool, merge_thread_pool) = if single_threaded {
+            (TaskExecutor::CurrentThread, TaskExecutor::CurrentThread)
+        } else {
+            let pool = ThreadPoolBuilder::new()
+                .name_prefix("segment_updater")
+                .pool_size(1)
+                .create()
+                .map_err(|_| {
+                    crate::TantivyError::SystemError(
+                        "Failed to spawn segment updater thread".to_string(),
+                    )
+                })?;
+            let merge_thread_pool = ThreadPoolBuilder::new()
+                .name_prefix("merge_thread")
+                .pool_size(NUM_MERGE_THREADS)
+                .create()
+                .map_err(|_| {
+                    crate::TantivyError::SystemError(
+                        "Failed to spawn segment merging thread".to_string(),
+                    )
+                })?;
+            (
+                TaskExecutor::ThreadPool(pool),
+                TaskExecutor::ThreadPool(merge_thread_pool),
+            )
+        };
         let index_meta = index.load_metas()?;
+        let generations = list_meta_generations(index.directory());
+        let last_generation = generations.last().copied().unwrap_or(0u64);
+        let mut commit_generations = VecDeque::new();
+        for &generation in generations.iter().rev() {
+            if commit_generations.len() == DEFAULT_NUM_COMMIT_GENERATIONS {
+                break;
+            }
+            if let Ok(metas) = index.load_commit_metas(generation) {
+                commit_generations.push_front((generation, Arc::new(metas)));
+            }
+        }
         Ok(SegmentUpdater(Arc::new(InnerSegmentUpdater {
             active_index_meta: RwLock::new(Arc::new(index_meta)),
             pool,
@@ -291,6 +405,12 @@ impl SegmentUpdater {
             index,
             segment_manager,
             merge_policy: RwLock::new(Arc::new(DefaultMergePolicy::default())),
+            merge_scratch_directory: RwLock::new(None),
+            merge_listener: RwLock::new(None),
+            disk_space_reserve: RwLock::new(None),
+            commit_generations: RwLock::new(commit_generations),
+            num_commit_generations: AtomicUsize::new(DEFAULT_NUM_COMMIT_GENERATIONS),
+            last_generation: AtomicU64::new(last_generation),
             killed: AtomicBool::new(false),
             stamper,
             merge_operations: Default::default(),
@@ -298,12 +418,111 @@ impl SegmentUpdater {
     }
 
     pub fn get_merge_policy(&self) -> Arc<dyn MergePolicy> {
-        self.merge_policy.read().unwrap().clone()
+        self.merge_policy
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
     }
 
     pub fn set_merge_policy(&self, merge_policy: Box<dyn MergePolicy>) {
         let arc_merge_policy = Arc::from(merge_policy);
-        *self.merge_policy.write().unwrap() = arc_merge_policy;
+        *self
+            .merge_policy
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = arc_merge_policy;
+    }
+
+    pub fn set_merge_scratch_directory(&self, scratch_directory: ManagedDirectory) {
+        *self
+            .merge_scratch_directory
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Some(scratch_directory);
+    }
+
+    pub fn merge_scratch_directory(&self) -> Option<ManagedDirectory> {
+        self.merge_scratch_directory
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    pub fn set_merge_listener(&self, merge_listener: Arc<dyn MergeListener>) {
+        *self
+            .merge_listener
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Some(merge_listener);
+    }
+
+    pub fn merge_listener(&self) -> Option<Arc<dyn MergeListener>> {
+        self.merge_listener
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    pub fn set_num_commit_generations(&self, num_generations: usize) {
+        self.num_commit_generations
+            .store(num_generations.max(1), Ordering::SeqCst);
+    }
+
+    /// Takes over the number of commit generations of `previous`, as well as
+    /// the generations it keeps.
+    ///
+    /// Upon creation, only the default number of generations is reloaded from the directory,
+    /// so that the generations kept by a rolled back segment updater would be garbage
+    /// collected otherwise.
+    pub fn keep_commit_generations_of(&self, previous: &SegmentUpdater) {
+        self.set_num_commit_generations(previous.num_commit_generations.load(Ordering::SeqCst));
+        let previous_generations = previous
+            .commit_generations
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        *self
+            .commit_generations
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = previous_generations;
+    }
+
+    pub fn set_disk_space_reserve(&self, num_bytes: u64) {
+        *self
+            .disk_space_reserve
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Some(num_bytes);
+    }
+
+    pub fn disk_space_reserve(&self) -> Option<u64> {
+        *self
+            .disk_space_reserve
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Checks that writing `num_bytes` in `directory` would not eat into
+    /// the disk space reserve.
+    ///
+    /// Nothing is checked if no reserve was set, or if the available space
+    /// of the directory is unknown.
+    pub fn check_available_space(
+        &self,
+        directory: &dyn Directory,
+        num_bytes: u64,
+    ) -> crate::Result<()> {
+        let disk_space_reserve = if let Some(reserve) = *self.disk_space_reserve.read()? {
+            reserve
+        } else {
+            return Ok(());
+        };
+        if let Some(available_bytes) = directory.available_space()? {
+            let required_bytes = num_bytes.saturating_add(disk_space_reserve);
+            if available_bytes < required_bytes {
+                return Err(crate::TantivyError::DiskFull {
+                    available_bytes,
+                    required_bytes,
+                });
+            }
+        }
+        Ok(())
     }
 
     fn schedule_future<T: 'static + Send, F: Future<Output = crate::Result<T>> + 'static + Send>(
@@ -312,18 +531,27 @@ impl SegmentUpdater {
     ) -> impl Future<Output = crate::Result<T>> {
         let (sender, receiver) = oneshot::channel();
         if self.is_alive() {
-            self.pool.spawn_ok(async move {
-                let _ = sender.send(f.await);
-            });
+            self.pool.spawn_ok(in_current_span!(async move {
+                let result = match AssertUnwindSafe(f).catch_unwind().await {
+                    Ok(result) => result,
+                    Err(panic_payload) => Err(crate::TantivyError::worker_panicked(
+                        "segment updater",
+                        panic_payload,
+                    )),
+                };
+                let _ = sender.send(result);
+            }));
         } else {
             let _ = sender.send(Err(crate::TantivyError::SystemError(
                 "Segment updater killed".to_string(),
             )));
         }
+        // Panics are caught above: the sender can only be dropped without sending
+        // if the thread pool is shut down.
         receiver.unwrap_or_else(|_| {
-            let err_msg =
-                "A segment_updater future did not success. This should never happen.".to_string();
-            Err(crate::TantivyError::SystemError(err_msg))
+            Err(crate::TantivyError::ErrorInThread(
+                "A segment updater task was dropped before completion.".to_string(),
+            ))
         })
     }
 
@@ -339,11 +567,55 @@ impl SegmentUpdater {
         })
     }
 
+    /// Adds several segments at once.
+    ///
+    /// As commits are scheduled on the same executor, either none or all of
+    /// the segments are part of a given commit.
+    pub(crate) fn schedule_add_segments(
+        &self,
+        segment_entries: Vec<SegmentEntry>,
+    ) -> impl Future<Output = crate::Result<()>> {
+        let segment_updater = self.clone();
+        self.schedule_future(async move {
+            for segment_entry in segment_entries {
+                segment_updater.segment_manager.add_segment(segment_entry);
+            }
+            segment_updater.consider_merge_options().await;
+            Ok(())
+        })
+    }
+
     /// Orders `SegmentManager` to remove all segments
     pub(crate) fn remove_all_segments(&self) {
         self.segment_manager.remove_all_segments();
     }
 
+    /// Returns the metas of all of the segments, committed or uncommitted.
+    pub(crate) fn segment_metas(&self) -> Vec<SegmentMeta> {
+        self.segment_manager
+            .segment_entries()
+            .iter()
+            .map(|segment_entry| segment_entry.meta().clone())
+            .collect()
+    }
+
+    /// Schedules the removal of the given segments.
+    ///
+    /// The removal becomes persistent with the next commit.
+    /// Merges involving one of these segments will be discarded.
+    pub(crate) fn schedule_remove_segments(
+        &self,
+        segment_ids: Vec<SegmentId>,
+    ) -> impl Future<Output = crate::Result<()>> {
+        let segment_updater = self.clone();
+        self.schedule_future(async move {
+            segment_updater
+                .segment_manager
+                .remove_segments(&segment_ids);
+            Ok(())
+        })
+    }
+
     pub fn kill(&mut self) {
         self.killed.store(true, Ordering::Release);
     }
@@ -369,6 +641,7 @@ impl SegmentUpdater {
         &self,
         opstamp: Opstamp,
         commit_message: Option<String>,
+        dedup_hashes: Vec<u64>,
     ) -> crate::Result<()> {
         if self.is_alive() {
             let index = &self.index;
@@ -395,10 +668,31 @@ impl SegmentUpdater {
                 schema: index.schema(),
                 opstamp,
                 payload: commit_message,
+                dedup_hashes,
             };
+            let generation = self.last_generation.fetch_add(1, Ordering::SeqCst) + 1;
             // TODO add context to the error.
-            save_metas(&index_meta, directory.box_clone().borrow_mut())?;
+            save_metas(&index_meta, generation, directory.box_clone().borrow_mut())?;
+            let num_deleted_docs: u64 = index_meta
+                .segments
+                .iter()
+                .map(|segment_meta| u64::from(segment_meta.num_deleted_docs()))
+                .sum();
+            index
+                .metrics()
+                .record_segments(index_meta.segments.len(), num_deleted_docs);
             self.store_meta(&index_meta);
+            self.push_commit_generation(generation, &index_meta)?;
+        }
+        Ok(())
+    }
+
+    fn push_commit_generation(&self, generation: u64, index_meta: &IndexMeta) -> crate::Result<()> {
+        let num_commit_generations = self.num_commit_generations.load(Ordering::SeqCst);
+        let mut commit_generations = self.commit_generations.write()?;
+        commit_generations.push_back((generation, Arc::new(index_meta.clone())));
+        while commit_generations.len() > num_commit_generations {
+            commit_generations.pop_front();
         }
         Ok(())
     }
@@ -422,6 +716,13 @@ impl SegmentUpdater {
             .flat_map(|segment_meta| segment_meta.list_files())
             .collect();
         files.insert(META_FILEPATH.to_path_buf());
+        let commit_generations = self
+            .commit_generations
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        for (generation, _) in commit_generations.iter() {
+            files.insert(meta_generation_filepath(*generation));
+        }
         files
     }
 
@@ -429,12 +730,13 @@ impl SegmentUpdater {
         &self,
         opstamp: Opstamp,
         payload: Option<String>,
+        dedup_hashes: Vec<u64>,
     ) -> impl Future<Output = crate::Result<()>> {
         let segment_updater: SegmentUpdater = self.clone();
         self.schedule_future(async move {
             let segment_entries = segment_updater.purge_deletes(opstamp)?;
             segment_updater.segment_manager.commit(segment_entries);
-            segment_updater.save_metas(opstamp, payload)?;
+            segment_updater.save_metas(opstamp, payload, dedup_hashes)?;
             let _ = garbage_collect_files(segment_updater.clone()).await;
             segment_updater.consider_merge_options().await;
             Ok(())
@@ -442,11 +744,17 @@ impl SegmentUpdater {
     }
 
     fn store_meta(&self, index_meta: &IndexMeta) {
-        *self.active_index_meta.write().unwrap() = Arc::new(index_meta.clone());
+        *self
+            .active_index_meta
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Arc::new(index_meta.clone());
     }
 
     fn load_meta(&self) -> Arc<IndexMeta> {
-        self.active_index_meta.read().unwrap().clone()
+        self.active_index_meta
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
     }
 
     pub(crate) fn make_merge_operation(&self, segment_ids: &[SegmentId]) -> MergeOperation {
@@ -485,44 +793,77 @@ impl SegmentUpdater {
             .segment_manager
             .start_merge(merge_operation.segment_ids())?;
 
+        // The merged segment is at most as large as the segments it merges.
+        let merged_num_bytes: u64 = segment_entries
+            .iter()
+            .flat_map(|segment_entry| segment_entry.meta().list_files())
+            .filter_map(|path| self.index.directory().len_and_checksum(&path).ok())
+            .map(|(num_bytes, _)| num_bytes)
+            .sum();
+        let scratch_directory_opt = self.merge_scratch_directory.read()?.clone();
+        let merge_listener_opt = self.merge_listener.read()?.clone();
+        let merge_directory: &dyn Directory = match scratch_directory_opt.as_ref() {
+            Some(scratch_directory) => scratch_directory,
+            None => self.index.directory(),
+        };
+        self.check_available_space(merge_directory, merged_num_bytes)?;
+
         info!("Starting merge  - {:?}", merge_operation.segment_ids());
 
         let (merging_future_send, merging_future_recv) =
             oneshot::channel::<crate::Result<SegmentMeta>>();
 
-        self.merge_thread_pool.spawn_ok(async move {
-            // The fact that `merge_operation` is moved here is important.
-            // Its lifetime is used to track how many merging thread are currently running,
-            // as well as which segment is currently in merge and therefore should not be
-            // candidate for another merge.
-            match merge(
-                &segment_updater.index,
-                segment_entries,
-                merge_operation.target_opstamp(),
-            ) {
-                Ok(after_merge_segment_entry) => {
-                    let segment_meta = segment_updater
-                        .end_merge(merge_operation, after_merge_segment_entry)
-                        .await;
-                    let _send_result = merging_future_send.send(segment_meta);
-                }
-                Err(e) => {
-                    warn!(
-                        "Merge of {:?} was cancelled: {:?}",
-                        merge_operation.segment_ids().to_vec(),
-                        e
-                    );
-                    // ... cancel merge
-                    if cfg!(test) {
-                        panic!("Merge failed.");
+        let merge_future = instrument!(
+            async move {
+                // The fact that `merge_operation` is moved here is important.
+                // Its lifetime is used to track how many merging thread are currently running,
+                // as well as which segment is currently in merge and therefore should not be
+                // candidate for another merge.
+                match merge(
+                    &segment_updater.index,
+                    segment_entries,
+                    merge_operation.target_opstamp(),
+                    scratch_directory_opt,
+                    merge_listener_opt,
+                )
+                .with_context(|| {
+                    ErrorContext::Operation(format!(
+                        "merging segments {:?}",
+                        merge_operation.segment_ids()
+                    ))
+                }) {
+                    Ok(after_merge_segment_entry) => {
+                        let segment_meta = segment_updater
+                            .end_merge(merge_operation, after_merge_segment_entry)
+                            .await;
+                        let _send_result = merging_future_send.send(segment_meta);
+                    }
+                    Err(e) => {
+                        warn!(
+                            "Merge of {:?} was cancelled: {:?}",
+                            merge_operation.segment_ids().to_vec(),
+                            e
+                        );
+                        if cfg!(test) {
+                            panic!("Merge failed.");
+                        }
+                        let _send_result = merging_future_send.send(Err(e));
                     }
                 }
-            }
-        });
+            },
+            "merge",
+            segment_ids = ?merge_operation.segment_ids(),
+            num_docs = segment_entries
+                .iter()
+                .map(|segment_entry| segment_entry.meta().num_docs())
+                .sum::<u32>()
+        );
+        self.merge_thread_pool.spawn_ok(merge_future);
 
-        Ok(merging_future_recv.unwrap_or_else(|e| {
-            Err(crate::TantivyError::SystemError(
-                "Merge failed:".to_string() + &e.to_string(),
+        // The sender is only dropped without sending if the merge thread panicked.
+        Ok(merging_future_recv.unwrap_or_else(|_| {
+            Err(crate::TantivyError::ErrorInThread(
+                "The merge thread panicked before completion.".to_string(),
             ))
         }))
     }
@@ -576,6 +917,9 @@ impl SegmentUpdater {
         let segment_updater = self.clone();
         let after_merge_segment_meta = after_merge_segment_entry.meta().clone();
         let end_merge_future = self.schedule_future(async move {
+            fail_point!(crate::failpoints::MERGE_PUBLISH, |msg| Err(
+                crate::failpoints::failpoint_error(msg).into()
+            ));
             info!("End merge {:?}", after_merge_segment_entry.meta());
             {
                 let mut delete_cursor = after_merge_segment_entry.delete_cursor().clone();
@@ -610,8 +954,11 @@ impl SegmentUpdater {
                     .end_merge(merge_operation.segment_ids(), after_merge_segment_entry)?;
 
                 if segments_status == SegmentsStatus::Committed {
-                    segment_updater
-                        .save_metas(previous_metas.opstamp, previous_metas.payload.clone())?;
+                    segment_updater.save_metas(
+                        previous_metas.opstamp,
+                        previous_metas.payload.clone(),
+                        previous_metas.dedup_hashes.clone(),
+                    )?;
                 }
 
                 segment_updater.consider_merge_options().await;
@@ -2,23 +2,29 @@ use super::{
     doc_id_mapping::{get_doc_id_mapping_from_field, DocIdMapping},
     operation::AddOperation,
 };
+use crate::common::numeric_trie::prefix_lengths;
 use crate::fastfield::FastFieldsWriter;
 use crate::fieldnorm::{FieldNormReaders, FieldNormsWriter};
 use crate::indexer::segment_serializer::SegmentSerializer;
 use crate::postings::compute_table_size;
 use crate::postings::MultiFieldPostingsWriter;
+use crate::schema::dynamic_field::{encode_attribute_text, split_attribute_text};
 use crate::schema::FieldType;
 use crate::schema::Schema;
 use crate::schema::Term;
 use crate::schema::Value;
 use crate::schema::{Field, FieldEntry};
+use crate::schema::{TextFieldIndexing, DEFAULT_POSITION_GAP};
 use crate::store::StoreReader;
-use crate::tokenizer::{BoxTokenStream, PreTokenizedStream};
-use crate::tokenizer::{FacetTokenizer, TextAnalyzer};
+use crate::tokenizer::{BoxTokenStream, PreTokenizedStream, PrefixedTokenStream};
+use crate::tokenizer::{FacetTokenizer, LimitTokenCountFilter, TextAnalyzer, TokenFilter};
 use crate::tokenizer::{TokenStreamChain, Tokenizer};
 use crate::Opstamp;
 use crate::{core::Segment, store::StoreWriter};
-use crate::{DocId, SegmentComponent};
+use crate::{DocId, Score, SegmentComponent};
+
+/// Number of bits of the term hash table of the segments without any indexed field.
+const DOC_STORE_ONLY_TABLE_NUM_BITS: usize = 1;
 
 /// Computes the initial size of the hash table.
 ///
@@ -64,6 +70,7 @@ pub struct SegmentWriter {
     pub(crate) doc_opstamps: Vec<Opstamp>,
     tokenizers: Vec<Option<TextAnalyzer>>,
     term_buffer: Term,
+    has_indexed_fields: bool,
 }
 
 impl SegmentWriter {
@@ -82,7 +89,13 @@ impl SegmentWriter {
         schema: &Schema,
     ) -> crate::Result<SegmentWriter> {
         let tokenizer_manager = segment.index().tokenizers().clone();
-        let table_num_bits = initial_table_size(memory_budget)?;
+        let has_indexed_fields = schema.has_indexed_fields();
+        // Without any indexed field, the term hashmap remains empty.
+        let table_num_bits = if has_indexed_fields {
+            initial_table_size(memory_budget)?
+        } else {
+            DOC_STORE_ONLY_TABLE_NUM_BITS
+        };
         let segment_serializer = SegmentSerializer::for_segment(segment, false)?;
         let multifield_postings = MultiFieldPostingsWriter::new(schema, table_num_bits);
         let tokenizers = schema
@@ -108,6 +121,7 @@ impl SegmentWriter {
             doc_opstamps: Vec::with_capacity(1_000),
             tokenizers,
             term_buffer: Term::new(),
+            has_indexed_fields,
         })
     }
 
@@ -158,7 +172,13 @@ impl SegmentWriter {
 
         self.fast_field_writers.add_document(&doc);
 
-        for (field, field_values) in doc.get_sorted_field_values() {
+        // Without any indexed field, documents only go to the fast fields and the doc store.
+        let field_values_to_index = if self.has_indexed_fields {
+            doc.get_sorted_field_values()
+        } else {
+            Vec::new()
+        };
+        for (field, field_values) in field_values_to_index {
             let field_entry = schema.get_field_entry(field);
             let make_schema_error = || {
                 crate::TantivyError::SchemaError(format!(
@@ -202,13 +222,24 @@ impl SegmentWriter {
                         }
                     }
                 }
-                FieldType::Str(_) => {
+                FieldType::Str(ref text_options) => {
                     let mut token_streams: Vec<BoxTokenStream> = vec![];
                     let mut offsets = vec![];
                     let mut total_offset = 0;
+                    let is_dynamic = field_entry.is_dynamic();
+                    let make_attribute_error = || {
+                        crate::TantivyError::SchemaError(format!(
+                            "Expected the values of the dynamic field {:?} to be added \
+                             with `Document::add_dynamic_text`",
+                            field_entry.name()
+                        ))
+                    };
 
                     for field_value in field_values {
                         match field_value.value() {
+                            Value::PreTokStr(_) if is_dynamic => {
+                                return Err(make_attribute_error());
+                            }
                             Value::PreTokStr(tok_str) => {
                                 offsets.push(total_offset);
                                 if let Some(last_token) = tok_str.tokens.last() {
@@ -221,19 +252,48 @@ impl SegmentWriter {
                                 if let Some(ref mut tokenizer) =
                                     self.tokenizers[field.field_id() as usize]
                                 {
-                                    offsets.push(total_offset);
-                                    total_offset += text.len();
-                                    token_streams.push(tokenizer.token_stream(text));
+                                    if is_dynamic {
+                                        // The terms of an attribute are prefixed by its name.
+                                        let (attribute, text) = split_attribute_text(text)
+                                            .ok_or_else(make_attribute_error)?;
+                                        offsets.push(total_offset);
+                                        total_offset += text.len();
+                                        let prefix = encode_attribute_text(attribute, "");
+                                        let token_stream = tokenizer.token_stream(text);
+                                        token_streams.push(
+                                            PrefixedTokenStream::new(prefix, token_stream).into(),
+                                        );
+                                    } else {
+                                        offsets.push(total_offset);
+                                        total_offset += text.len();
+                                        token_streams.push(tokenizer.token_stream(text));
+                                    }
                                 }
                             }
                             _ => (),
                         }
                     }
 
+                    if let Some(max_num_tokens) = text_options
+                        .get_indexing_options()
+                        .and_then(TextFieldIndexing::max_num_tokens)
+                    {
+                        let limit_filter = LimitTokenCountFilter::limit(max_num_tokens as usize);
+                        token_streams = token_streams
+                            .into_iter()
+                            .map(|token_stream| limit_filter.transform(token_stream))
+                            .collect();
+                    }
+
                     let num_tokens = if token_streams.is_empty() {
                         0
                     } else {
-                        let mut token_stream = TokenStreamChain::new(offsets, token_streams);
+                        let position_gap = text_options
+                            .get_indexing_options()
+                            .map(TextFieldIndexing::position_gap)
+                            .unwrap_or(DEFAULT_POSITION_GAP);
+                        let mut token_stream = TokenStreamChain::new(offsets, token_streams)
+                            .with_position_gap(position_gap as usize);
                         multifield_postings.index_text(
                             doc_id,
                             field,
@@ -242,9 +302,10 @@ impl SegmentWriter {
                         )
                     };
 
-                    self.fieldnorms_writer.record(doc_id, field, num_tokens);
+                    let fieldnorm = boosted_fieldnorm(num_tokens, doc.field_boost(field));
+                    self.fieldnorms_writer.record(doc_id, field, fieldnorm);
                 }
-                FieldType::U64(_) => {
+                FieldType::U64(ref int_options) => {
                     for field_value in field_values {
                         term_buffer.set_field(field_value.field());
                         let u64_val = field_value
@@ -252,10 +313,15 @@ impl SegmentWriter {
                             .u64_value()
                             .ok_or_else(make_schema_error)?;
                         term_buffer.set_u64(u64_val);
-                        multifield_postings.subscribe(doc_id, term_buffer);
+                        subscribe_numeric_term(
+                            doc_id,
+                            int_options.get_precision_step(),
+                            term_buffer,
+                            multifield_postings,
+                        );
                     }
                 }
-                FieldType::Date(_) => {
+                FieldType::Date(ref int_options) => {
                     for field_value in field_values {
                         term_buffer.set_field(field_value.field());
                         let date_val = field_value
@@ -263,10 +329,15 @@ impl SegmentWriter {
                             .date_value()
                             .ok_or_else(make_schema_error)?;
                         term_buffer.set_i64(date_val.timestamp());
-                        multifield_postings.subscribe(doc_id, term_buffer);
+                        subscribe_numeric_term(
+                            doc_id,
+                            int_options.get_precision_step(),
+                            term_buffer,
+                            multifield_postings,
+                        );
                     }
                 }
-                FieldType::I64(_) => {
+                FieldType::I64(ref int_options) => {
                     for field_value in field_values {
                         term_buffer.set_field(field_value.field());
                         let i64_val = field_value
@@ -274,10 +345,15 @@ impl SegmentWriter {
                             .i64_value()
                             .ok_or_else(make_schema_error)?;
                         term_buffer.set_i64(i64_val);
-                        multifield_postings.subscribe(doc_id, term_buffer);
+                        subscribe_numeric_term(
+                            doc_id,
+                            int_options.get_precision_step(),
+                            term_buffer,
+                            multifield_postings,
+                        );
                     }
                 }
-                FieldType::F64(_) => {
+                FieldType::F64(ref int_options) => {
                     for field_value in field_values {
                         term_buffer.set_field(field_value.field());
                         let f64_val = field_value
@@ -285,7 +361,12 @@ impl SegmentWriter {
                             .f64_value()
                             .ok_or_else(make_schema_error)?;
                         term_buffer.set_f64(f64_val);
-                        multifield_postings.subscribe(doc_id, term_buffer);
+                        subscribe_numeric_term(
+                            doc_id,
+                            int_options.get_precision_step(),
+                            term_buffer,
+                            multifield_postings,
+                        );
                     }
                 }
                 FieldType::Bytes(_) => {
@@ -330,6 +411,37 @@ impl SegmentWriter {
     }
 }
 
+/// Indexes the numeric value held by `term_buffer`.
+///
+/// If the field has a precision step, the value is also indexed
+/// at each of the lower precisions.
+fn subscribe_numeric_term(
+    doc_id: DocId,
+    precision_step_opt: Option<u8>,
+    term_buffer: &mut Term,
+    multifield_postings: &mut MultiFieldPostingsWriter,
+) {
+    multifield_postings.subscribe(doc_id, term_buffer);
+    if let Some(precision_step) = precision_step_opt {
+        let mut value_bytes = [0u8; 8];
+        value_bytes.copy_from_slice(term_buffer.value_bytes());
+        for prefix_len in prefix_lengths(precision_step) {
+            term_buffer.set_bytes(&value_bytes[..prefix_len]);
+            multifield_postings.subscribe(doc_id, term_buffer);
+        }
+    }
+}
+
+/// Folds the index-time boost of a field into its fieldnorm.
+///
+/// Non-empty fields keep a fieldnorm of at least 1.
+fn boosted_fieldnorm(num_tokens: u32, boost: Score) -> u32 {
+    if num_tokens == 0 || boost == 1.0 {
+        return num_tokens;
+    }
+    ((num_tokens as Score / boost).round() as u32).max(1)
+}
+
 /// This method is used as a trick to workaround the borrow checker
 /// Writes a view of a segment by pushing information
 /// to the `SegmentSerializer`.
@@ -348,7 +460,7 @@ fn remap_and_write(
     let fieldnorm_data = serializer
         .segment()
         .open_read(SegmentComponent::FieldNorms)?;
-    let fieldnorm_readers = FieldNormReaders::open(fieldnorm_data)?;
+    let fieldnorm_readers = FieldNormReaders::open(fieldnorm_data, serializer.segment().schema())?;
     let term_ord_map = multifield_postings.serialize(
         serializer.get_postings_serializer(),
         fieldnorm_readers,
@@ -365,11 +477,11 @@ fn remap_and_write(
         let store_write = serializer
             .segment_mut()
             .open_write(SegmentComponent::Store)?;
-        let compressor = serializer.segment().index().settings().docstore_compression;
-        let old_store_writer = std::mem::replace(
-            &mut serializer.store_writer,
-            StoreWriter::new(store_write, compressor),
-        );
+        let settings = serializer.segment().index().settings();
+        let store_writer = StoreWriter::new(store_write, settings.docstore_compression)
+            .with_doc_serialization(settings.docstore_serialization)
+            .with_large_value_threshold(settings.docstore_large_value_threshold);
+        let old_store_writer = std::mem::replace(&mut serializer.store_writer, store_writer);
         old_store_writer.close()?;
         let store_read = StoreReader::open(
             serializer
@@ -0,0 +1,124 @@
+use futures::executor::ThreadPool;
+use futures::future::BoxFuture;
+use futures::task::{waker, ArcWake};
+use futures::FutureExt;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::Context;
+
+/// Executor of the tasks of the `SegmentUpdater`.
+pub(crate) enum TaskExecutor {
+    /// Runs the tasks on a thread pool.
+    ThreadPool(ThreadPool),
+    /// Runs the tasks on the thread spawning them, before `spawn_ok` returns.
+    ///
+    /// The tasks spawned by a running task are run right after it, so that
+    /// the futures waiting for the result of a task are ready as soon as the task
+    /// is spawned. This executor does not spawn any thread.
+    CurrentThread,
+}
+
+impl TaskExecutor {
+    /// Spawns a task. Spawning cannot fail.
+    pub fn spawn_ok<F: Future<Output = ()> + Send + 'static>(&self, future: F) {
+        match self {
+            TaskExecutor::ThreadPool(pool) => pool.spawn_ok(future),
+            TaskExecutor::CurrentThread => spawn_on_current_thread(future.boxed()),
+        }
+    }
+}
+
+thread_local! {
+    // Tasks spawned on the current thread that are not completed yet.
+    static PENDING_TASKS: RefCell<VecDeque<BoxFuture<'static, ()>>> = RefCell::new(VecDeque::new());
+    static IS_RUNNING: Cell<bool> = Cell::new(false);
+}
+
+// Records whether one of the polled tasks was woken up.
+struct WakeFlag(AtomicBool);
+
+impl ArcWake for WakeFlag {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+// Marks the pending tasks as not running anymore, even if a task panicked.
+struct RunningGuard;
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        IS_RUNNING.with(|is_running| is_running.set(false));
+    }
+}
+
+fn spawn_on_current_thread(task: BoxFuture<'static, ()>) {
+    PENDING_TASKS.with(|tasks| tasks.borrow_mut().push_back(task));
+    if IS_RUNNING.with(|is_running| is_running.replace(true)) {
+        // The task is spawned by a running task, and will be run after it.
+        return;
+    }
+    let _running_guard = RunningGuard;
+    run_pending_tasks();
+}
+
+fn num_pending_tasks() -> usize {
+    PENDING_TASKS.with(|tasks| tasks.borrow().len())
+}
+
+// Polls the pending tasks until they are all completed, or none of them can make progress.
+//
+// Tasks that cannot make progress remain pending, and are polled again
+// the next time a task is spawned on this thread.
+fn run_pending_tasks() {
+    let wake_flag = Arc::new(WakeFlag(AtomicBool::new(false)));
+    let task_waker = waker(wake_flag.clone());
+    let mut context = Context::from_waker(&task_waker);
+    loop {
+        let num_tasks = num_pending_tasks();
+        if num_tasks == 0 {
+            return;
+        }
+        wake_flag.0.store(false, Ordering::SeqCst);
+        let mut num_completed_tasks = 0;
+        for _ in 0..num_tasks {
+            let mut task = match PENDING_TASKS.with(|tasks| tasks.borrow_mut().pop_front()) {
+                Some(task) => task,
+                None => break,
+            };
+            if task.poll_unpin(&mut context).is_ready() {
+                num_completed_tasks += 1;
+            } else {
+                PENDING_TASKS.with(|tasks| tasks.borrow_mut().push_back(task));
+            }
+        }
+        let has_new_tasks = num_pending_tasks() + num_completed_tasks > num_tasks;
+        if num_completed_tasks == 0 && !has_new_tasks && !wake_flag.0.load(Ordering::SeqCst) {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TaskExecutor;
+    use futures::channel::oneshot;
+
+    #[test]
+    fn test_current_thread_executor_runs_nested_tasks() {
+        let (sender, mut receiver) = oneshot::channel();
+        TaskExecutor::CurrentThread.spawn_ok(async move {
+            let (nested_sender, nested_receiver) = oneshot::channel();
+            TaskExecutor::CurrentThread.spawn_ok(async move {
+                let _ = nested_sender.send(3);
+            });
+            let value: i32 = nested_receiver.await.unwrap();
+            let _ = sender.send(value * 2);
+        });
+        // The task is completed before `spawn_ok` returns.
+        assert_eq!(receiver.try_recv(), Ok(Some(6)));
+    }
+}
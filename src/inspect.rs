@@ -0,0 +1,237 @@
+/*!
+Structured information about the segments of an index, to investigate its size.
+
+[`inspect_index`](./fn.inspect_index.html) returns, for each segment, the size of the file of
+each of its components, and a per field breakdown of these sizes. Unlike
+[`space_usage`](../space_usage/index.html), the sizes of the files are the sizes of the files
+in the directory, footers included, and the number of terms of each field is reported.
+
+All of the returned structures can be serialized, typically to JSON, to be displayed
+or compared by external tools.
+
+```rust
+use tantivy::inspect::inspect_index;
+use tantivy::schema::{Schema, TEXT};
+use tantivy::{doc, Index};
+
+# fn main() -> tantivy::Result<()> {
+let mut schema_builder = Schema::builder();
+let title = schema_builder.add_text_field("title", TEXT);
+let index = Index::create_in_ram(schema_builder.build());
+let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+index_writer.add_document(doc!(title => "The diary of Muadib"));
+index_writer.commit()?;
+
+let index_inspection = inspect_index(&index)?;
+assert_eq!(index_inspection.segments.len(), 1);
+assert_eq!(index_inspection.segments[0].fields[0].num_terms, Some(4));
+let json = serde_json::to_string_pretty(&index_inspection)?;
+assert!(json.contains("\"num_terms\": 4"));
+# Ok(())
+# }
+```
+*/
+
+use crate::directory::error::OpenReadError;
+use crate::space_usage::{ByteCount, PerFieldSpaceUsage};
+use crate::{Index, Segment, SegmentComponent, SegmentId, SegmentReader};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Information about the committed segments of an index.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IndexInspection {
+    /// Segments of the index.
+    pub segments: Vec<SegmentInspection>,
+}
+
+impl IndexInspection {
+    /// Total number of bytes of the files of the segments.
+    pub fn num_bytes(&self) -> ByteCount {
+        self.segments.iter().map(SegmentInspection::num_bytes).sum()
+    }
+}
+
+/// Information about a segment.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SegmentInspection {
+    /// Id of the segment.
+    pub segment_id: SegmentId,
+    /// Number of documents of the segment, deleted documents included.
+    pub max_doc: u32,
+    /// Number of deleted documents.
+    pub num_deleted_docs: u32,
+    /// Files of the components of the segment.
+    pub files: Vec<ComponentFile>,
+    /// Breakdown of the component files per field.
+    pub fields: Vec<FieldInspection>,
+}
+
+impl SegmentInspection {
+    /// Total number of bytes of the files of the segment.
+    pub fn num_bytes(&self) -> ByteCount {
+        self.files.iter().map(|file| file.num_bytes).sum()
+    }
+}
+
+/// The file of a segment component.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ComponentFile {
+    /// Component stored in the file.
+    pub component: SegmentComponent,
+    /// Path of the file, relative to the directory of the index.
+    pub path: PathBuf,
+    /// Number of bytes of the file, footer included.
+    pub num_bytes: ByteCount,
+}
+
+/// Information about a field in a segment.
+///
+/// The number of bytes used by the field in each component excludes the headers and
+/// footers of the files.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FieldInspection {
+    /// Name of the field.
+    pub name: String,
+    /// Number of distinct terms of the field, if it is indexed.
+    pub num_terms: Option<u64>,
+    /// Number of bytes of the term dictionary of the field.
+    pub termdict_num_bytes: ByteCount,
+    /// Number of bytes of the postings of the field.
+    pub postings_num_bytes: ByteCount,
+    /// Number of bytes of the positions of the field.
+    pub positions_num_bytes: ByteCount,
+    /// Number of bytes of the fast field, if any.
+    pub fast_field_num_bytes: ByteCount,
+    /// Number of bytes of the fieldnorms of the field.
+    pub fieldnorms_num_bytes: ByteCount,
+}
+
+/// Inspects the segments of the last commit of `index`.
+pub fn inspect_index(index: &Index) -> crate::Result<IndexInspection> {
+    let segments = index
+        .searchable_segments()?
+        .iter()
+        .map(inspect_segment)
+        .collect::<crate::Result<Vec<_>>>()?;
+    Ok(IndexInspection { segments })
+}
+
+/// Inspects a segment.
+pub fn inspect_segment(segment: &Segment) -> crate::Result<SegmentInspection> {
+    let mut files = Vec::new();
+    let component_paths = SegmentComponent::iterator()
+        .filter(|&&component| component != SegmentComponent::Delete)
+        .map(|&component| (component, segment.relative_path(component)))
+        .chain(
+            // The deleted docs may be spread over several delete files.
+            segment
+                .meta()
+                .delete_file_paths()
+                .into_iter()
+                .map(|path| (SegmentComponent::Delete, path)),
+        );
+    for (component, path) in component_paths {
+        match segment.index().directory().file_num_bytes(&path) {
+            Ok(num_bytes) => files.push(ComponentFile {
+                component,
+                path,
+                num_bytes,
+            }),
+            // Components such as the temporary store or the deletes may not exist.
+            Err(OpenReadError::FileDoesNotExist(_)) => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+    let segment_reader = SegmentReader::open(segment)?;
+    let space_usage = segment_reader.space_usage()?;
+    let field_num_bytes = |per_field_space_usage: &PerFieldSpaceUsage, field| {
+        per_field_space_usage
+            .field(field)
+            .map(|field_usage| field_usage.total())
+            .unwrap_or(0)
+    };
+    let mut fields = Vec::new();
+    for (field, field_entry) in segment.schema().fields() {
+        let num_terms = if field_entry.is_indexed() {
+            let inverted_index = segment_reader.inverted_index(field)?;
+            Some(inverted_index.terms().num_terms() as u64)
+        } else {
+            None
+        };
+        fields.push(FieldInspection {
+            name: field_entry.name().to_string(),
+            num_terms,
+            termdict_num_bytes: field_num_bytes(space_usage.termdict(), field),
+            postings_num_bytes: field_num_bytes(space_usage.postings(), field),
+            positions_num_bytes: field_num_bytes(space_usage.positions(), field),
+            fast_field_num_bytes: field_num_bytes(space_usage.fast_fields(), field),
+            fieldnorms_num_bytes: field_num_bytes(space_usage.fieldnorms(), field),
+        });
+    }
+    Ok(SegmentInspection {
+        segment_id: segment.id(),
+        max_doc: segment_reader.max_doc(),
+        num_deleted_docs: segment_reader.num_deleted_docs(),
+        files,
+        fields,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::inspect_index;
+    use crate::schema::{Schema, FAST, INDEXED, STORED, TEXT};
+    use crate::{Index, SegmentComponent, Term};
+
+    #[test]
+    fn test_inspect_index() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT | STORED);
+        let id = schema_builder.add_u64_field("id", INDEXED | FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(title => "a b c", id => 1u64));
+        index_writer.add_document(doc!(title => "a d", id => 2u64));
+        index_writer.add_document(doc!(title => "e", id => 3u64));
+        index_writer.commit()?;
+        index_writer.delete_term(Term::from_field_u64(id, 3));
+        index_writer.commit()?;
+
+        let index_inspection = inspect_index(&index)?;
+        assert_eq!(index_inspection.segments.len(), 1);
+        let segment_inspection = &index_inspection.segments[0];
+        assert_eq!(segment_inspection.max_doc, 3);
+        assert_eq!(segment_inspection.num_deleted_docs, 1);
+        let components: Vec<SegmentComponent> = segment_inspection
+            .files
+            .iter()
+            .map(|file| file.component)
+            .collect();
+        assert!(components.contains(&SegmentComponent::Postings));
+        assert!(components.contains(&SegmentComponent::Delete));
+        assert!(!components.contains(&SegmentComponent::TempStore));
+        for file in &segment_inspection.files {
+            assert_eq!(
+                index.directory().file_num_bytes(&file.path)?,
+                file.num_bytes
+            );
+        }
+        assert_eq!(index_inspection.num_bytes(), segment_inspection.num_bytes());
+
+        let title_inspection = &segment_inspection.fields[0];
+        assert_eq!(title_inspection.name, "title");
+        assert_eq!(title_inspection.num_terms, Some(5));
+        assert!(title_inspection.postings_num_bytes > 0);
+        assert!(title_inspection.positions_num_bytes > 0);
+        assert_eq!(title_inspection.fast_field_num_bytes, 0);
+        let id_inspection = &segment_inspection.fields[1];
+        assert_eq!(id_inspection.num_terms, Some(3));
+        assert!(id_inspection.fast_field_num_bytes > 0);
+        assert_eq!(id_inspection.positions_num_bytes, 0);
+
+        let json = serde_json::to_string(&index_inspection)?;
+        assert!(json.contains(r#""component":"Postings""#));
+        Ok(())
+    }
+}
@@ -125,6 +125,7 @@ mod macros;
 
 pub use crate::error::TantivyError;
 pub use chrono;
+pub use tantivy_fst;
 
 /// Tantivy result.
 ///
@@ -143,10 +144,16 @@ mod indexer;
 pub mod error;
 pub mod tokenizer;
 
+pub mod check;
+pub mod codec;
 pub mod collector;
 pub mod directory;
+pub mod failpoints;
 pub mod fastfield;
 pub mod fieldnorm;
+pub mod inspect;
+pub mod metrics;
+pub mod percolator;
 pub mod positions;
 pub mod postings;
 pub mod query;
@@ -157,24 +164,39 @@ pub mod termdict;
 
 mod reader;
 
-pub use self::reader::{IndexReader, IndexReaderBuilder, ReloadPolicy};
+pub use self::reader::{
+    GenerationPin, IndexReader, IndexReaderBuilder, QueryCacheInfo, ReloadPolicy,
+    SearcherGeneration, SearcherPoolInfo, Warmer,
+};
 mod snippet;
 pub use self::snippet::{Snippet, SnippetGenerator};
 
 mod docset;
 pub use self::docset::{DocSet, TERMINATED};
+pub use crate::common::BitSet;
 pub use crate::common::HasLen;
-pub use crate::common::{f64_to_u64, i64_to_u64, u64_to_f64, u64_to_i64};
-pub use crate::core::{Executor, SegmentComponent};
+pub use crate::common::{f64_bounds_to_u64_bounds, f64_to_u64, i64_to_u64, u64_to_f64, u64_to_i64};
 pub use crate::core::{
-    Index, IndexBuilder, IndexMeta, IndexSettings, IndexSortByField, Order, Searcher, Segment,
-    SegmentId, SegmentMeta,
+    CommitFile, Index, IndexBuilder, IndexMeta, IndexSettings, IndexSortByField, Order, Searcher,
+    Segment, SegmentId, SegmentMeta,
 };
-pub use crate::core::{InvertedIndexReader, SegmentReader};
+pub use crate::core::{Executor, SegmentComponent};
+pub use crate::core::{ExpansionBudget, ExpansionOverflow};
+pub use crate::core::{FieldStats, ScoringStats, TermStats};
+pub use crate::core::{GlobalOrdinals, MultiTermStreamer, MultiTerms};
+#[cfg(feature = "mmap")]
+pub use crate::core::{IndexCatalog, IndexPartition, RolloverPolicy};
+pub use crate::core::{InvertedIndexReader, MemoryBudget, SegmentReader};
 pub use crate::directory::Directory;
 pub use crate::indexer::merge_segments;
 pub use crate::indexer::operation::UserOperation;
-pub use crate::indexer::IndexWriter;
+pub use crate::indexer::IndexWriterHandle;
+pub use crate::indexer::RetentionPolicy;
+pub use crate::indexer::SegmentBuilder;
+pub use crate::indexer::{AutoCommitListener, AutoCommitPolicy, AutoCommitThread, PendingChanges};
+pub use crate::indexer::{DedupMode, Deduplication};
+pub use crate::indexer::{IndexWriter, TryAddDocumentError};
+pub use crate::indexer::{MergeDocIdMapping, MergeListener};
 pub use crate::postings::Postings;
 pub use crate::reader::LeasedItem;
 pub use crate::schema::{Document, Term};
@@ -184,7 +206,17 @@ use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 
 /// Index format version.
-const INDEX_FORMAT_VERSION: u32 = 4;
+///
+/// - Format 5 adds bitmap posting lists and custom formats recorded in the footer of the files.
+/// - Format 6 adds chunked delete files, possibly several per segment.
+const INDEX_FORMAT_VERSION: u32 = 6;
+
+/// Oldest index format version that can still be read.
+///
+/// Formats 5 and 6 only add encodings that are flagged in the data itself (bitmap posting lists,
+/// custom formats recorded in the footer of the files) or in the segment metas (chunked delete
+/// files), so that segments written in format 4 are read by the same deserializers.
+const MIN_INDEX_FORMAT_VERSION: u32 = 4;
 
 /// Structure version for the index.
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -208,6 +240,13 @@ static VERSION: Lazy<Version> = Lazy::new(|| Version {
     index_format_version: INDEX_FORMAT_VERSION,
 });
 
+impl Version {
+    /// Returns the version of the index format.
+    pub fn index_format_version(&self) -> u32 {
+        self.index_format_version
+    }
+}
+
 impl ToString for Version {
     fn to_string(&self) -> String {
         format!(
@@ -237,6 +276,7 @@ pub mod merge_policy {
     pub use crate::indexer::LogMergePolicy;
     pub use crate::indexer::MergePolicy;
     pub use crate::indexer::NoMergePolicy;
+    pub use crate::indexer::RoutingMergePolicy;
 }
 
 /// A `u32` identifying a document within a segment.
@@ -63,6 +63,63 @@ macro_rules! doc(
     };
 );
 
+/// Enters a `tracing` span, which is exited at the end of the enclosing block.
+///
+/// The arguments are those of `tracing::info_span!`.
+/// Without the `tracing` feature, this macro expands to nothing,
+/// and its arguments are not evaluated.
+#[cfg(feature = "tracing")]
+macro_rules! enter_span {
+    ($($arg:tt)*) => {
+        let _span_guard = tracing::info_span!($($arg)*).entered();
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! enter_span {
+    ($($arg:tt)*) => {};
+}
+
+/// Instruments a future with a `tracing` span, entered each time the future is polled.
+///
+/// The arguments following the future are those of `tracing::info_span!`.
+/// They are evaluated before the future.
+/// Without the `tracing` feature, the future is returned as is.
+#[cfg(feature = "tracing")]
+macro_rules! instrument {
+    ($future:expr, $($arg:tt)*) => {
+        {
+            let span = tracing::info_span!($($arg)*);
+            tracing::Instrument::instrument($future, span)
+        }
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! instrument {
+    ($future:expr, $($arg:tt)*) => {
+        $future
+    };
+}
+
+/// Instruments a future with the current `tracing` span, so that the work it does
+/// on another thread is attributed to the operation that scheduled it.
+///
+/// Without the `tracing` feature, the future is returned as is.
+#[cfg(feature = "tracing")]
+macro_rules! in_current_span {
+    ($future:expr) => {
+        tracing::Instrument::in_current_span($future)
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! in_current_span {
+    ($future:expr) => {
+        $future
+    };
+}
+
 #[cfg(test)]
 mod test {
     use crate::schema::{Schema, FAST, TEXT};
@@ -0,0 +1,173 @@
+/*!
+Metrics reported by the indexer and the readers of an index.
+
+Tantivy does not depend on any metrics library. Instead, the indexer and the readers
+report their activity to the [`Metrics`](./trait.Metrics.html) implementation
+set on the `Index` with
+[`Index::set_metrics(...)`](../struct.Index.html#method.set_metrics).
+Forwarding these calls to the counters, gauges and histograms of a metrics
+registry (e.g. prometheus) is left to the implementation.
+
+By default, metrics are discarded.
+
+```rust
+use tantivy::metrics::Metrics;
+use tantivy::schema::{Schema, TEXT};
+use tantivy::{doc, Index};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+#[derive(Default)]
+struct DocsIndexedCounter(AtomicU64);
+
+impl Metrics for DocsIndexedCounter {
+    fn record_docs_indexed(&self, num_docs: u64) {
+        self.0.fetch_add(num_docs, Ordering::SeqCst);
+    }
+}
+
+# fn main() -> tantivy::Result<()> {
+let mut schema_builder = Schema::builder();
+let title = schema_builder.add_text_field("title", TEXT);
+let mut index = Index::create_in_ram(schema_builder.build());
+let counter = Arc::new(DocsIndexedCounter::default());
+index.set_metrics(counter.clone());
+let mut index_writer = index.writer(50_000_000)?;
+index_writer.add_document(doc!(title => "The Old Man and the Sea"));
+index_writer.commit()?;
+assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+# Ok(())
+# }
+```
+*/
+
+use std::time::Duration;
+
+/// Receives the metrics of an index.
+///
+/// All of the methods have a default implementation discarding the metric,
+/// so that implementations only need to override the ones they are interested in.
+///
+/// The methods are called from the indexing, merging and searching threads,
+/// and should return quickly.
+pub trait Metrics: Send + Sync + 'static {
+    /// Counter: `num_docs` documents were added to a new segment.
+    ///
+    /// This is called when a segment is flushed by an indexing thread,
+    /// before the documents are committed.
+    fn record_docs_indexed(&self, _num_docs: u64) {}
+
+    /// Gauge: number of committed segments, and number of deleted documents
+    /// they contain.
+    ///
+    /// This is called each time the list of committed segments changes,
+    /// i.e. upon commit and at the end of each merge.
+    fn record_segments(&self, _num_segments: usize, _num_deleted_docs: u64) {}
+
+    /// Counter: a merge wrote a new segment of `num_bytes` bytes.
+    fn record_merge_bytes_written(&self, _num_bytes: u64) {}
+
+    /// Counter: a lookup in the doc store cache of an `IndexReader`.
+    ///
+    /// `hit` is false if the block had to be read and decompressed.
+    fn record_doc_store_cache_lookup(&self, _hit: bool) {}
+
+    /// Histogram: a query was executed by a `Searcher` in `latency`.
+    fn record_query_latency(&self, _latency: Duration) {}
+}
+
+/// `Metrics` discarding all of the metrics.
+///
+/// This is the default `Metrics` of an `Index`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoMetrics;
+
+impl Metrics for NoMetrics {}
+
+#[cfg(test)]
+mod tests {
+    use super::Metrics;
+    use crate::collector::Count;
+    use crate::query::AllQuery;
+    use crate::schema::{Schema, STORED, TEXT};
+    use crate::{DocAddress, Index, Term};
+    use futures::executor::block_on;
+    use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[derive(Default)]
+    struct TestMetrics {
+        docs_indexed: AtomicU64,
+        num_segments: AtomicUsize,
+        num_deleted_docs: AtomicU64,
+        merge_bytes_written: AtomicU64,
+        cache_hits: AtomicUsize,
+        cache_misses: AtomicUsize,
+        num_queries: AtomicUsize,
+    }
+
+    impl Metrics for TestMetrics {
+        fn record_docs_indexed(&self, num_docs: u64) {
+            self.docs_indexed.fetch_add(num_docs, Ordering::SeqCst);
+        }
+
+        fn record_segments(&self, num_segments: usize, num_deleted_docs: u64) {
+            self.num_segments.store(num_segments, Ordering::SeqCst);
+            self.num_deleted_docs
+                .store(num_deleted_docs, Ordering::SeqCst);
+        }
+
+        fn record_merge_bytes_written(&self, num_bytes: u64) {
+            self.merge_bytes_written
+                .fetch_add(num_bytes, Ordering::SeqCst);
+        }
+
+        fn record_doc_store_cache_lookup(&self, hit: bool) {
+            if hit {
+                self.cache_hits.fetch_add(1, Ordering::SeqCst);
+            } else {
+                self.cache_misses.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        fn record_query_latency(&self, _latency: Duration) {
+            self.num_queries.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_metrics() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let mut index = Index::create_in_ram(schema_builder.build());
+        let metrics = Arc::new(TestMetrics::default());
+        index.set_metrics(metrics.clone());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.add_document(doc!(text_field => "b"));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text_field => "c"));
+        index_writer.delete_term(Term::from_field_text(text_field, "a"));
+        index_writer.commit()?;
+        assert_eq!(metrics.docs_indexed.load(Ordering::SeqCst), 3);
+        assert_eq!(metrics.num_segments.load(Ordering::SeqCst), 2);
+        assert_eq!(metrics.num_deleted_docs.load(Ordering::SeqCst), 1);
+
+        let segment_ids = index.searchable_segment_ids()?;
+        block_on(index_writer.merge(&segment_ids))?;
+        index_writer.wait_merging_threads()?;
+        assert_eq!(metrics.num_segments.load(Ordering::SeqCst), 1);
+        assert_eq!(metrics.num_deleted_docs.load(Ordering::SeqCst), 0);
+        assert!(metrics.merge_bytes_written.load(Ordering::SeqCst) > 0);
+
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.search(&AllQuery, &Count)?, 2);
+        assert_eq!(metrics.num_queries.load(Ordering::SeqCst), 1);
+        searcher.doc(DocAddress::new(0, 0))?;
+        searcher.doc(DocAddress::new(0, 1))?;
+        assert_eq!(metrics.cache_misses.load(Ordering::SeqCst), 1);
+        assert_eq!(metrics.cache_hits.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+}
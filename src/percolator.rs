@@ -0,0 +1,293 @@
+/*!
+Percolation matches documents against a set of registered queries.
+
+Search runs one query against many documents. Percolation is the inverse workflow:
+queries are registered once, e.g. the saved searches of the users of an alerting service,
+and each incoming document is matched against all of them.
+
+The registered queries are stored in a dedicated index, with the schema returned by
+[`Percolator::queries_schema()`](./struct.Percolator.html#method.queries_schema), so that
+they survive restarts. Each candidate document is indexed on its own, in a single in-memory
+segment, against which the registered queries are run.
+
+```rust
+use tantivy::percolator::Percolator;
+use tantivy::schema::{Schema, TEXT};
+use tantivy::{doc, Index};
+
+# fn main() -> tantivy::Result<()> {
+let mut schema_builder = Schema::builder();
+let title = schema_builder.add_text_field("title", TEXT);
+let index = Index::create_in_ram(schema_builder.build());
+
+let mut percolator = Percolator::create_in_ram(&index, vec![title])?;
+percolator.register(1, "title:diary")?;
+percolator.register(2, "cow AND dairy")?;
+percolator.commit()?;
+
+let matching_ids = percolator.match_document(&doc!(title => "The diary of a dairy cow"))?;
+assert_eq!(matching_ids, vec![1, 2]);
+# Ok(())
+# }
+```
+*/
+
+use crate::core::global_ordinals::GlobalOrdinalsCache;
+use crate::core::searcher::QueryBudgets;
+use crate::directory::{ManagedDirectory, RamDirectory};
+use crate::query::{AllQuery, Query, QueryParser};
+use crate::schema::{Document, Field, Schema, INDEXED, STORED};
+use crate::store::DocStoreCache;
+use crate::{Index, IndexWriter, Searcher, SegmentBuilder, SegmentReader, TantivyError, Term};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Name of the field holding the id of the registered queries.
+const ID_FIELD_NAME: &str = "id";
+/// Name of the field holding the text of the registered queries.
+const QUERY_FIELD_NAME: &str = "query";
+
+/// Memory budget used to index a candidate document.
+const DOCUMENT_MEMORY_BUDGET: usize = 1_000_000;
+/// Memory budget of the writer of the queries index.
+const QUERIES_WRITER_MEMORY_BUDGET: usize = 10_000_000;
+
+/// Registers queries, and matches documents against them.
+///
+/// Queries are parsed with a [`QueryParser`](../query/struct.QueryParser.html) for the
+/// schema and the tokenizers of the index of the documents.
+///
+/// Registering and unregistering queries is effective immediately for `match_document`,
+/// and persisted in the queries index by `commit`.
+pub struct Percolator {
+    document_index: Index,
+    query_parser: QueryParser,
+    queries_writer: IndexWriter,
+    id_field: Field,
+    query_field: Field,
+    queries: BTreeMap<u64, Box<dyn Query>>,
+}
+
+impl Percolator {
+    /// Returns the schema of the indexes storing the registered queries.
+    pub fn queries_schema() -> Schema {
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_u64_field(ID_FIELD_NAME, INDEXED | STORED);
+        schema_builder.add_text_field(QUERY_FIELD_NAME, STORED);
+        schema_builder.build()
+    }
+
+    /// Opens a percolator storing its queries in `queries_index`, and loads the queries
+    /// it contains.
+    ///
+    /// Documents are expected to follow the schema of `document_index`, and are tokenized
+    /// with its tokenizers. `default_fields` are the default fields of the query parser.
+    ///
+    /// The schema of `queries_index` must be [`queries_schema()`](#method.queries_schema).
+    /// The percolator holds the writer of `queries_index` until it is dropped.
+    pub fn open(
+        queries_index: Index,
+        document_index: &Index,
+        default_fields: Vec<Field>,
+    ) -> crate::Result<Percolator> {
+        let queries_schema = queries_index.schema();
+        if queries_schema != Percolator::queries_schema() {
+            return Err(TantivyError::SchemaError(
+                "The schema of the queries index is not the schema of the percolator queries"
+                    .to_string(),
+            ));
+        }
+        let id_field = queries_schema.get_field(ID_FIELD_NAME).unwrap();
+        let query_field = queries_schema.get_field(QUERY_FIELD_NAME).unwrap();
+        let query_parser = QueryParser::for_index(document_index, default_fields);
+        let mut queries = BTreeMap::new();
+        let searcher = queries_index.reader()?.searcher();
+        for doc_res in searcher.stored_docs_matching(&AllQuery)? {
+            let (_, doc) = doc_res?;
+            let id = doc.get_first_u64(id_field).ok_or_else(|| {
+                TantivyError::SchemaError("A percolator query has no id".to_string())
+            })?;
+            let query_text = doc
+                .get_first(query_field)
+                .and_then(|value| value.text())
+                .ok_or_else(|| {
+                    TantivyError::SchemaError(format!("The percolator query {} has no text", id))
+                })?;
+            queries.insert(id, query_parser.parse_query(query_text)?);
+        }
+        let queries_writer =
+            queries_index.writer_with_num_threads(1, QUERIES_WRITER_MEMORY_BUDGET)?;
+        Ok(Percolator {
+            document_index: document_index.clone(),
+            query_parser,
+            queries_writer,
+            id_field,
+            query_field,
+            queries,
+        })
+    }
+
+    /// Creates a percolator storing its queries in a new index in RAM.
+    ///
+    /// See [`open`](#method.open).
+    pub fn create_in_ram(
+        document_index: &Index,
+        default_fields: Vec<Field>,
+    ) -> crate::Result<Percolator> {
+        let queries_index = Index::create_in_ram(Percolator::queries_schema());
+        Percolator::open(queries_index, document_index, default_fields)
+    }
+
+    /// Returns the number of registered queries.
+    pub fn num_queries(&self) -> usize {
+        self.queries.len()
+    }
+
+    /// Registers `query`, in the query language of the `QueryParser`, under `id`.
+    ///
+    /// A query previously registered under the same `id` is replaced.
+    /// Returns an error if the query cannot be parsed.
+    pub fn register(&mut self, id: u64, query: &str) -> crate::Result<()> {
+        let parsed_query = self.query_parser.parse_query(query)?;
+        self.queries_writer
+            .delete_term(Term::from_field_u64(self.id_field, id));
+        self.queries_writer
+            .add_document(doc!(self.id_field => id, self.query_field => query));
+        self.queries.insert(id, parsed_query);
+        Ok(())
+    }
+
+    /// Unregisters the query registered under `id`.
+    ///
+    /// Returns false if there was no such query.
+    pub fn unregister(&mut self, id: u64) -> bool {
+        self.queries_writer
+            .delete_term(Term::from_field_u64(self.id_field, id));
+        self.queries.remove(&id).is_some()
+    }
+
+    /// Persists the registered queries in the queries index.
+    pub fn commit(&mut self) -> crate::Result<()> {
+        self.queries_writer.commit()?;
+        Ok(())
+    }
+
+    /// Returns the ids of the registered queries matching `doc`, in increasing order.
+    ///
+    /// The document is indexed on its own, in a single in-memory segment, against which
+    /// all of the registered queries are run.
+    pub fn match_document(&self, doc: &Document) -> crate::Result<Vec<u64>> {
+        let searcher = self.document_searcher(doc)?;
+        let mut matching_ids = Vec::new();
+        for (&id, query) in &self.queries {
+            if query.count(&searcher)? > 0 {
+                matching_ids.push(id);
+            }
+        }
+        Ok(matching_ids)
+    }
+
+    // Returns a searcher over a segment containing `doc` only.
+    fn document_searcher(&self, doc: &Document) -> crate::Result<Searcher> {
+        let directory = ManagedDirectory::wrap(RamDirectory::create())?;
+        let index = self.document_index.with_directory(directory);
+        let segment_meta = SegmentBuilder::new(&index)
+            .memory_budget(DOCUMENT_MEMORY_BUDGET)
+            .build(vec![doc.clone()])?;
+        let segment_reader = SegmentReader::open(&index.segment(segment_meta))?;
+        let searcher = Searcher::new(
+            index.schema(),
+            index,
+            vec![segment_reader],
+            DocStoreCache::with_capacity(1),
+            QueryBudgets::default(),
+            Arc::new(GlobalOrdinalsCache::default()),
+            None,
+        )?;
+        Ok(searcher)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Percolator;
+    use crate::schema::{Schema, INDEXED, TEXT};
+    use crate::tokenizer::{LowerCaser, SimpleTokenizer, TextAnalyzer};
+    use crate::{Index, TantivyError};
+
+    #[test]
+    fn test_percolator() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let year = schema_builder.add_u64_field("year", INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let queries_index = Index::create_in_ram(Percolator::queries_schema());
+
+        let mut percolator = Percolator::open(queries_index.clone(), &index, vec![title])?;
+        percolator.register(1, "title:diary")?;
+        percolator.register(2, "old AND man")?;
+        percolator.register(3, "year:[1950 TO 1960}")?;
+        percolator.register(4, "\"old man\" -sea")?;
+        assert!(percolator.register(5, "title:(").is_err());
+        assert_eq!(percolator.num_queries(), 4);
+        assert_eq!(
+            percolator
+                .match_document(&doc!(title => "The old man and the sea", year => 1952u64))?,
+            vec![2, 3]
+        );
+        assert_eq!(
+            percolator.match_document(&doc!(title => "The diary of an old man"))?,
+            vec![1, 2, 4]
+        );
+        assert!(percolator
+            .match_document(&doc!(year => 1970u64))?
+            .is_empty());
+
+        percolator.register(1, "title:journal")?;
+        assert!(percolator.unregister(4));
+        assert!(!percolator.unregister(4));
+        assert_eq!(
+            percolator.match_document(&doc!(title => "The diary of an old man"))?,
+            vec![2]
+        );
+        percolator.commit()?;
+        drop(percolator);
+
+        let percolator = Percolator::open(queries_index, &index, vec![title])?;
+        assert_eq!(percolator.num_queries(), 3);
+        assert_eq!(
+            percolator.match_document(&doc!(title => "A journal", year => 1950u64))?,
+            vec![1, 3]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_percolator_uses_document_index_tokenizers() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field(
+            "title",
+            crate::schema::TextOptions::default().set_indexing_options(
+                crate::schema::TextFieldIndexing::default().set_tokenizer("custom"),
+            ),
+        );
+        let index = Index::create_in_ram(schema_builder.build());
+        index.tokenizers().register(
+            "custom",
+            TextAnalyzer::from(SimpleTokenizer).filter(LowerCaser),
+        );
+        let mut percolator = Percolator::create_in_ram(&index, vec![title])?;
+        percolator.register(1, "DIARY")?;
+        assert_eq!(percolator.match_document(&doc!(title => "Diary"))?, vec![1]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_percolator_wrong_queries_schema() {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let err = Percolator::open(index.clone(), &index, vec![title]).err();
+        assert!(matches!(err, Some(TantivyError::SchemaError(_))));
+    }
+}
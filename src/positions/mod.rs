@@ -27,6 +27,7 @@
 mod reader;
 mod serializer;
 
+pub(crate) use self::reader::split_positions_and_offsets;
 pub use self::reader::PositionReader;
 pub use self::serializer::PositionSerializer;
 use bitpacking::{BitPacker, BitPacker4x};
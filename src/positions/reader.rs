@@ -1,10 +1,32 @@
 use std::io;
 
-use crate::common::{BinarySerializable, VInt};
+use crate::common::{BinarySerializable, FixedSize, VInt};
 use crate::directory::OwnedBytes;
 use crate::positions::COMPRESSION_BLOCK_SIZE;
 use crate::postings::compression::{BlockDecoder, VIntDecoder};
 
+/// Splits the positions data of a term into its positions and its offsets,
+/// for a field recording offsets.
+pub(crate) fn split_positions_and_offsets(
+    data: OwnedBytes,
+) -> io::Result<(OwnedBytes, OwnedBytes)> {
+    if data.len() < u32::SIZE_IN_BYTES {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "The positions of the term do not end with the length of its offsets",
+        ));
+    }
+    let (data, num_bytes_data) = data.split(data.len() - u32::SIZE_IN_BYTES);
+    let offsets_num_bytes = u32::deserialize(&mut num_bytes_data.as_slice())? as usize;
+    if offsets_num_bytes > data.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "The offsets of the term exceed its positions data",
+        ));
+    }
+    Ok(data.split(data.len() - offsets_num_bytes))
+}
+
 /// When accessing the position of a term, we get a positions_idx from the `Terminfo`.
 /// This means we need to skip to the `nth` positions efficiently.
 ///
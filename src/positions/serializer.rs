@@ -4,51 +4,34 @@ use crate::postings::compression::BlockEncoder;
 use crate::postings::compression::VIntEncoder;
 use std::io::{self, Write};
 
-/// The PositionSerializer is in charge of serializing all of the positions
-/// of all of the terms of a given field.
-///
-/// It is valid to call write_position_delta more than once per term.
-pub struct PositionSerializer<W: io::Write> {
+// Encodes a stream of integers by blocks of 128 values, for one term.
+struct BlockStream {
     block_encoder: BlockEncoder,
-    positions_wrt: CountingWriter<W>,
-    positions_buffer: Vec<u8>,
+    buffer: Vec<u8>,
     block: Vec<u32>,
     bit_widths: Vec<u8>,
 }
 
-impl<W: io::Write> PositionSerializer<W> {
-    /// Creates a new PositionSerializer writing into the given positions_wrt.
-    pub fn new(positions_wrt: W) -> PositionSerializer<W> {
-        PositionSerializer {
+impl BlockStream {
+    fn new() -> BlockStream {
+        BlockStream {
             block_encoder: BlockEncoder::new(),
-            positions_wrt: CountingWriter::wrap(positions_wrt),
-            positions_buffer: Vec::with_capacity(128_000),
+            buffer: Vec::with_capacity(128_000),
             block: Vec::with_capacity(128),
             bit_widths: Vec::new(),
         }
     }
 
-    /// Returns the number of bytes written in the positions write object
-    /// at this point.
-    /// When called before writing the positions of a term, this value is used as
-    /// start offset.
-    /// When called after writing the positions of a term, this value is used as a
-    /// end offset.
-    pub fn written_bytes(&self) -> u64 {
-        self.positions_wrt.written_bytes()
-    }
-
     fn remaining_block_len(&self) -> usize {
         COMPRESSION_BLOCK_SIZE - self.block.len()
     }
 
-    /// Writes all of the given positions delta.
-    pub fn write_positions_delta(&mut self, mut positions_delta: &[u32]) {
-        while !positions_delta.is_empty() {
+    fn write(&mut self, mut vals: &[u32]) {
+        while !vals.is_empty() {
             let remaining_block_len = self.remaining_block_len();
-            let num_to_write = remaining_block_len.min(positions_delta.len());
-            self.block.extend(&positions_delta[..num_to_write]);
-            positions_delta = &positions_delta[num_to_write..];
+            let num_to_write = remaining_block_len.min(vals.len());
+            self.block.extend(&vals[..num_to_write]);
+            vals = &vals[num_to_write..];
             if self.remaining_block_len() == 0 {
                 self.flush_block();
             }
@@ -56,7 +39,7 @@ impl<W: io::Write> PositionSerializer<W> {
     }
 
     fn flush_block(&mut self) {
-        // encode the positions in the block
+        // encode the values in the block
         if self.block.is_empty() {
             return;
         }
@@ -64,23 +47,94 @@ impl<W: io::Write> PositionSerializer<W> {
             let (bit_width, block_encoded): (u8, &[u8]) =
                 self.block_encoder.compress_block_unsorted(&self.block[..]);
             self.bit_widths.push(bit_width);
-            self.positions_buffer.extend(block_encoded);
+            self.buffer.extend(block_encoded);
         } else {
             debug_assert!(self.block.len() < COMPRESSION_BLOCK_SIZE);
             let block_vint_encoded = self.block_encoder.compress_vint_unsorted(&self.block[..]);
-            self.positions_buffer.extend_from_slice(block_vint_encoded);
+            self.buffer.extend_from_slice(block_vint_encoded);
         }
         self.block.clear();
     }
 
-    /// Close the positions for the given term.
-    pub fn close_term(&mut self) -> io::Result<()> {
+    // Writes the values of the term, and resets the stream for the next term.
+    fn close_term<W: io::Write>(&mut self, wrt: &mut W) -> io::Result<()> {
         self.flush_block();
-        VInt(self.bit_widths.len() as u64).serialize(&mut self.positions_wrt)?;
-        self.positions_wrt.write_all(&self.bit_widths[..])?;
-        self.positions_wrt.write_all(&self.positions_buffer)?;
+        VInt(self.bit_widths.len() as u64).serialize(wrt)?;
+        wrt.write_all(&self.bit_widths[..])?;
+        wrt.write_all(&self.buffer)?;
         self.bit_widths.clear();
-        self.positions_buffer.clear();
+        self.buffer.clear();
+        Ok(())
+    }
+}
+
+/// The PositionSerializer is in charge of serializing all of the positions
+/// of all of the terms of a given field.
+///
+/// If the field records offsets, the offsets of each term are serialized right after
+/// its positions, followed by their number of bytes as a `u32`.
+///
+/// It is valid to call write_position_delta more than once per term.
+pub struct PositionSerializer<W: io::Write> {
+    positions_wrt: CountingWriter<W>,
+    positions: BlockStream,
+    offsets_opt: Option<BlockStream>,
+    offsets_buffer: Vec<u8>,
+}
+
+impl<W: io::Write> PositionSerializer<W> {
+    /// Creates a new PositionSerializer writing into the given positions_wrt.
+    pub fn new(positions_wrt: W) -> PositionSerializer<W> {
+        PositionSerializer {
+            positions_wrt: CountingWriter::wrap(positions_wrt),
+            positions: BlockStream::new(),
+            offsets_opt: None,
+            offsets_buffer: Vec::new(),
+        }
+    }
+
+    /// Creates a new PositionSerializer writing the positions as well as the offsets
+    /// of the terms into the given positions_wrt.
+    pub fn with_offsets(positions_wrt: W) -> PositionSerializer<W> {
+        PositionSerializer {
+            offsets_opt: Some(BlockStream::new()),
+            ..PositionSerializer::new(positions_wrt)
+        }
+    }
+
+    /// Returns the number of bytes written in the positions write object
+    /// at this point.
+    /// When called before writing the positions of a term, this value is used as
+    /// start offset.
+    /// When called after writing the positions of a term, this value is used as a
+    /// end offset.
+    pub fn written_bytes(&self) -> u64 {
+        self.positions_wrt.written_bytes()
+    }
+
+    /// Writes all of the given positions delta.
+    pub fn write_positions_delta(&mut self, positions_delta: &[u32]) {
+        self.positions.write(positions_delta);
+    }
+
+    /// Writes the given encoded offsets, two values per position.
+    ///
+    /// Offsets are ignored if the serializer was not created with `with_offsets`.
+    pub fn write_offsets(&mut self, encoded_offsets: &[u32]) {
+        if let Some(offsets) = self.offsets_opt.as_mut() {
+            offsets.write(encoded_offsets);
+        }
+    }
+
+    /// Close the positions for the given term.
+    pub fn close_term(&mut self) -> io::Result<()> {
+        self.positions.close_term(&mut self.positions_wrt)?;
+        if let Some(offsets) = self.offsets_opt.as_mut() {
+            self.offsets_buffer.clear();
+            offsets.close_term(&mut self.offsets_buffer)?;
+            self.positions_wrt.write_all(&self.offsets_buffer)?;
+            (self.offsets_buffer.len() as u32).serialize(&mut self.positions_wrt)?;
+        }
         Ok(())
     }
 
@@ -7,6 +7,7 @@ use crate::fieldnorm::FieldNormReader;
 use crate::postings::compression::{
     AlignedBuffer, BlockDecoder, VIntDecoder, COMPRESSION_BLOCK_SIZE,
 };
+use crate::postings::dense;
 use crate::postings::{BlockInfo, FreqReadingOption, SkipReader};
 use crate::query::Bm25Weight;
 use crate::schema::IndexRecordOption;
@@ -33,6 +34,8 @@ pub struct BlockSegmentPostings {
 
     doc_freq: u32,
 
+    // If true, `data` is the bitmap of a dense posting list.
+    is_dense: bool,
     data: OwnedBytes,
     pub(crate) skip_reader: SkipReader,
 }
@@ -69,16 +72,23 @@ fn decode_vint_block(
     }
 }
 
+// Returns the skip data, the postings data, and whether the posting list is dense.
+//
+// The skip data of a dense posting list is rebuilt from its bitmap.
 fn split_into_skips_and_postings(
     doc_freq: u32,
     mut bytes: OwnedBytes,
-) -> (Option<OwnedBytes>, OwnedBytes) {
+) -> (Option<OwnedBytes>, OwnedBytes, bool) {
     if doc_freq < COMPRESSION_BLOCK_SIZE as u32 {
-        return (None, bytes);
+        return (None, bytes, false);
     }
     let skip_len = VInt::deserialize(&mut bytes).expect("Data corrupted").0 as usize;
+    if skip_len == 0 {
+        let skip_data = dense::build_skip_data(bytes.as_slice());
+        return (Some(skip_data), bytes, true);
+    }
     let (skip_data, postings_data) = bytes.split(skip_len);
-    (Some(skip_data), postings_data)
+    (Some(skip_data), postings_data, false)
 }
 
 impl BlockSegmentPostings {
@@ -94,7 +104,7 @@ impl BlockSegmentPostings {
             (_, _) => FreqReadingOption::ReadFreq,
         };
 
-        let (skip_data_opt, postings_data) =
+        let (skip_data_opt, postings_data, is_dense) =
             split_into_skips_and_postings(doc_freq, data.read_bytes()?);
         let skip_reader = match skip_data_opt {
             Some(skip_data) => SkipReader::new(skip_data, doc_freq, record_option),
@@ -108,6 +118,7 @@ impl BlockSegmentPostings {
             freq_reading_option,
             block_max_score_cache: None,
             doc_freq,
+            is_dense,
             data: postings_data,
             skip_reader,
         };
@@ -169,7 +180,9 @@ impl BlockSegmentPostings {
     //
     // This does not reset the positions list.
     pub(crate) fn reset(&mut self, doc_freq: u32, postings_data: OwnedBytes) {
-        let (skip_data_opt, postings_data) = split_into_skips_and_postings(doc_freq, postings_data);
+        let (skip_data_opt, postings_data, is_dense) =
+            split_into_skips_and_postings(doc_freq, postings_data);
+        self.is_dense = is_dense;
         self.data = postings_data;
         self.block_max_score_cache = None;
         self.loaded_offset = std::usize::MAX;
@@ -280,6 +293,10 @@ impl BlockSegmentPostings {
             return;
         }
         self.loaded_offset = offset;
+        if self.is_dense {
+            self.load_dense_block();
+            return;
+        }
         match self.skip_reader.block_info() {
             BlockInfo::BitPacked {
                 doc_num_bits,
@@ -322,6 +339,26 @@ impl BlockSegmentPostings {
         }
     }
 
+    fn load_dense_block(&mut self) {
+        let num_docs = match self.skip_reader.block_info() {
+            BlockInfo::BitPacked { .. } => COMPRESSION_BLOCK_SIZE,
+            BlockInfo::VInt { num_docs } => num_docs as usize,
+        };
+        if num_docs == 0 {
+            self.doc_decoder.fill(std::iter::empty(), 0, TERMINATED);
+            return;
+        }
+        // The first block starts at the beginning of the bitmap. The following blocks
+        // start right after the last doc of the previous block.
+        let first_doc = if self.skip_reader.byte_offset() == 0 {
+            0
+        } else {
+            self.skip_reader.last_doc_in_previous_block + 1
+        };
+        let docs = dense::docs_from(self.data.as_slice(), first_doc);
+        self.doc_decoder.fill(docs, num_docs, TERMINATED);
+    }
+
     /// Advance to the next block.
     ///
     /// Returns false iff there was no remaining blocks.
@@ -340,6 +377,7 @@ impl BlockSegmentPostings {
             freq_reading_option: FreqReadingOption::NoFreq,
             block_max_score_cache: None,
             doc_freq: 0,
+            is_dense: false,
             data: OwnedBytes::empty(),
             skip_reader: SkipReader::new(OwnedBytes::empty(), 0, IndexRecordOption::Basic),
         }
@@ -1,9 +1,17 @@
+//! Compression of the doc id and term frequency blocks of the postings.
+//!
+//! Blocks of `COMPRESSION_BLOCK_SIZE` values are bitpacked using `BitPacker4x`.
+//! They are decoded with explicit SIMD (AVX2 or SSE2 on x86_64, NEON on aarch64),
+//! selected at runtime, see the `simd` module. The format of the blocks, and hence
+//! of the index, does not depend on the instruction set.
+use self::simd::InstructionSet;
 use crate::common::FixedSize;
 use bitpacking::{BitPacker, BitPacker4x};
 
 pub const COMPRESSION_BLOCK_SIZE: usize = BitPacker4x::BLOCK_LEN;
 const COMPRESSED_BLOCK_MAX_SIZE: usize = COMPRESSION_BLOCK_SIZE * u32::SIZE_IN_BYTES;
 
+mod simd;
 mod vint;
 
 /// Returns the size in bytes of a compressed block, given `num_bits`.
@@ -57,7 +65,7 @@ pub(crate) struct AlignedBuffer(pub [u32; COMPRESSION_BLOCK_SIZE]);
 
 #[derive(Clone)]
 pub struct BlockDecoder {
-    bitpacker: BitPacker4x,
+    instruction_set: InstructionSet,
     output: AlignedBuffer,
     pub output_len: usize,
 }
@@ -71,7 +79,7 @@ impl Default for BlockDecoder {
 impl BlockDecoder {
     pub fn with_val(val: u32) -> BlockDecoder {
         BlockDecoder {
-            bitpacker: BitPacker4x::new(),
+            instruction_set: InstructionSet::selected(),
             output: AlignedBuffer([val; COMPRESSION_BLOCK_SIZE]),
             output_len: 0,
         }
@@ -84,14 +92,14 @@ impl BlockDecoder {
         num_bits: u8,
     ) -> usize {
         self.output_len = COMPRESSION_BLOCK_SIZE;
-        self.bitpacker
-            .decompress_sorted(offset, compressed_data, &mut self.output.0, num_bits)
+        self.instruction_set
+            .unpack_sorted(offset, compressed_data, num_bits, &mut self.output.0)
     }
 
     pub fn uncompress_block_unsorted(&mut self, compressed_data: &[u8], num_bits: u8) -> usize {
         self.output_len = COMPRESSION_BLOCK_SIZE;
-        self.bitpacker
-            .decompress(compressed_data, &mut self.output.0, num_bits)
+        self.instruction_set
+            .unpack(compressed_data, num_bits, &mut self.output.0)
     }
 
     #[inline]
@@ -99,6 +107,17 @@ impl BlockDecoder {
         &self.output.0[..self.output_len]
     }
 
+    /// Fills the block with the first `num_els` values of `vals`.
+    ///
+    /// The remaining of the block is filled with `padding`.
+    pub(crate) fn fill<I: Iterator<Item = u32>>(&mut self, vals: I, num_els: usize, padding: u32) {
+        self.output_len = num_els;
+        self.output.0.iter_mut().for_each(|el| *el = padding);
+        for (el, val) in self.output.0[..num_els].iter_mut().zip(vals) {
+            *el = val;
+        }
+    }
+
     #[inline]
     pub(crate) fn output_aligned(&self) -> &AlignedBuffer {
         &self.output
@@ -0,0 +1,359 @@
+//! Decoding of the bitpacked blocks of the postings, with the best instruction set
+//! of the CPU, selected at runtime.
+//!
+//! Blocks are encoded by `BitPacker4x`: the 128 values of a block are split into
+//! 4 interleaved lanes (value `i` belongs to lane `i % 4`), and each lane is bitpacked
+//! into a stream of 32-bit words. The `i`-th words of the 4 lanes are stored next to
+//! each other, so that a 128-bit register holds one word of each lane, and a row of
+//! 4 consecutive values is decoded with a couple of shifts.
+//!
+//! - AVX2 decodes two rows at a time, with per-element shifts.
+//! - SSE2, part of the x86_64 baseline, decodes one row at a time.
+//! - NEON, part of the aarch64 baseline, decodes one row at a time.
+//! - Other CPUs fall back on a scalar implementation.
+use super::{compressed_block_size, COMPRESSION_BLOCK_SIZE};
+use byteorder::{ByteOrder, LittleEndian};
+use once_cell::sync::Lazy;
+
+const NUM_ROWS: usize = COMPRESSION_BLOCK_SIZE / 4;
+
+static INSTRUCTION_SET: Lazy<InstructionSet> = Lazy::new(InstructionSet::detect);
+
+/// Instruction set used to decode the blocks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum InstructionSet {
+    Scalar,
+    #[cfg(target_arch = "x86_64")]
+    Sse2,
+    #[cfg(target_arch = "x86_64")]
+    Avx2,
+    #[cfg(target_arch = "aarch64")]
+    Neon,
+}
+
+impl InstructionSet {
+    /// Returns the best instruction set of the CPU, detected once.
+    pub fn selected() -> InstructionSet {
+        *INSTRUCTION_SET
+    }
+
+    fn detect() -> InstructionSet {
+        InstructionSet::available()
+            .last()
+            .cloned()
+            .unwrap_or(InstructionSet::Scalar)
+    }
+
+    /// Returns the instruction sets supported by the CPU, from the slowest to the fastest.
+    pub fn available() -> Vec<InstructionSet> {
+        let mut instruction_sets = vec![InstructionSet::Scalar];
+        #[cfg(target_arch = "x86_64")]
+        {
+            instruction_sets.push(InstructionSet::Sse2);
+            if is_x86_feature_detected!("avx2") {
+                instruction_sets.push(InstructionSet::Avx2);
+            }
+        }
+        #[cfg(target_arch = "aarch64")]
+        instruction_sets.push(InstructionSet::Neon);
+        instruction_sets
+    }
+
+    /// Decodes a block of values bitpacked with `num_bits` bits.
+    ///
+    /// Returns the number of bytes read from `compressed`.
+    pub fn unpack(
+        self,
+        compressed: &[u8],
+        num_bits: u8,
+        output: &mut [u32; COMPRESSION_BLOCK_SIZE],
+    ) -> usize {
+        let num_bytes = compressed_block_size(num_bits);
+        assert!(num_bits <= 32 && compressed.len() >= num_bytes);
+        if num_bits == 0 {
+            output.iter_mut().for_each(|val| *val = 0);
+            return 0;
+        }
+        let num_bits = num_bits as usize;
+        // Safe, as the instruction set is supported by the CPU and `compressed`
+        // holds the `num_bits` 128-bit words of the block.
+        unsafe {
+            match self {
+                InstructionSet::Scalar => unpack_scalar(compressed, num_bits, output),
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::Sse2 => x86::unpack_sse2(compressed, num_bits, output),
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::Avx2 => x86::unpack_avx2(compressed, num_bits, output),
+                #[cfg(target_arch = "aarch64")]
+                InstructionSet::Neon => neon::unpack_neon(compressed, num_bits, output),
+            }
+        }
+        num_bytes
+    }
+
+    /// Decodes a block of sorted values, delta-encoded from `initial`
+    /// and bitpacked with `num_bits` bits.
+    ///
+    /// Returns the number of bytes read from `compressed`.
+    pub fn unpack_sorted(
+        self,
+        initial: u32,
+        compressed: &[u8],
+        num_bits: u8,
+        output: &mut [u32; COMPRESSION_BLOCK_SIZE],
+    ) -> usize {
+        let num_bytes = self.unpack(compressed, num_bits, output);
+        unsafe {
+            match self {
+                InstructionSet::Scalar => integrate_scalar(initial, output),
+                #[cfg(target_arch = "x86_64")]
+                InstructionSet::Sse2 | InstructionSet::Avx2 => x86::integrate_sse2(initial, output),
+                #[cfg(target_arch = "aarch64")]
+                InstructionSet::Neon => neon::integrate_neon(initial, output),
+            }
+        }
+        num_bytes
+    }
+}
+
+// Returns the bit offset, within its lane, of the values of the given row,
+// as the index of the 32-bit word holding them and the offset within this word.
+#[inline]
+fn row_position(row: usize, num_bits: usize) -> (usize, u32) {
+    let bit_offset = row * num_bits;
+    (bit_offset / 32, (bit_offset % 32) as u32)
+}
+
+// True if the values of the row overflow onto the next word.
+#[inline]
+fn straddles(shift: u32, num_bits: usize) -> bool {
+    shift as usize + num_bits > 32
+}
+
+#[inline]
+fn value_mask(num_bits: usize) -> u32 {
+    ((1u64 << num_bits) - 1) as u32
+}
+
+fn unpack_scalar(compressed: &[u8], num_bits: usize, output: &mut [u32]) {
+    let word = |word_ord: usize, lane: usize| {
+        LittleEndian::read_u32(&compressed[word_ord * 16 + lane * 4..])
+    };
+    let mask = value_mask(num_bits);
+    for row in 0..NUM_ROWS {
+        let (word_ord, shift) = row_position(row, num_bits);
+        for lane in 0..4 {
+            let mut val = word(word_ord, lane) >> shift;
+            if straddles(shift, num_bits) {
+                val |= word(word_ord + 1, lane) << (32 - shift);
+            }
+            output[row * 4 + lane] = val & mask;
+        }
+    }
+}
+
+fn integrate_scalar(initial: u32, output: &mut [u32]) {
+    let mut val = initial;
+    for delta in output.iter_mut() {
+        val = val.wrapping_add(*delta);
+        *delta = val;
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use super::{row_position, straddles, value_mask, NUM_ROWS};
+    use std::arch::x86_64::*;
+
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn unpack_sse2(compressed: &[u8], num_bits: usize, output: &mut [u32]) {
+        let input_ptr = compressed.as_ptr() as *const __m128i;
+        let output_ptr = output.as_mut_ptr() as *mut __m128i;
+        let mask = _mm_set1_epi32(value_mask(num_bits) as i32);
+        for row in 0..NUM_ROWS {
+            let (word_ord, shift) = row_position(row, num_bits);
+            let words = _mm_loadu_si128(input_ptr.add(word_ord));
+            let mut vals = _mm_srl_epi32(words, _mm_cvtsi32_si128(shift as i32));
+            if straddles(shift, num_bits) {
+                let next_words = _mm_loadu_si128(input_ptr.add(word_ord + 1));
+                let high_bits = _mm_sll_epi32(next_words, _mm_cvtsi32_si128(32 - shift as i32));
+                vals = _mm_or_si128(vals, high_bits);
+            }
+            _mm_storeu_si128(output_ptr.add(row), _mm_and_si128(vals, mask));
+        }
+    }
+
+    // Loads the words holding the high bits of a row, or zeros if the row
+    // does not overflow onto the next word.
+    #[target_feature(enable = "sse2")]
+    unsafe fn load_high_words(
+        input_ptr: *const __m128i,
+        word_ord: usize,
+        shift: u32,
+        num_bits: usize,
+    ) -> __m128i {
+        if straddles(shift, num_bits) {
+            _mm_loadu_si128(input_ptr.add(word_ord + 1))
+        } else {
+            _mm_setzero_si128()
+        }
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn unpack_avx2(compressed: &[u8], num_bits: usize, output: &mut [u32]) {
+        let input_ptr = compressed.as_ptr() as *const __m128i;
+        let output_ptr = output.as_mut_ptr() as *mut __m256i;
+        let mask = _mm256_set1_epi32(value_mask(num_bits) as i32);
+        for row_pair in 0..NUM_ROWS / 2 {
+            let (low_word_ord, low_shift) = row_position(2 * row_pair, num_bits);
+            let (high_word_ord, high_shift) = row_position(2 * row_pair + 1, num_bits);
+            let words = _mm256_set_m128i(
+                _mm_loadu_si128(input_ptr.add(high_word_ord)),
+                _mm_loadu_si128(input_ptr.add(low_word_ord)),
+            );
+            let next_words = _mm256_set_m128i(
+                load_high_words(input_ptr, high_word_ord, high_shift, num_bits),
+                load_high_words(input_ptr, low_word_ord, low_shift, num_bits),
+            );
+            let (low_shift, high_shift) = (low_shift as i32, high_shift as i32);
+            let right_shifts = _mm256_setr_epi32(
+                low_shift, low_shift, low_shift, low_shift, high_shift, high_shift, high_shift,
+                high_shift,
+            );
+            // Shifting left by 32 bits zeroes the words.
+            let left_shifts = _mm256_sub_epi32(_mm256_set1_epi32(32), right_shifts);
+            let vals = _mm256_or_si256(
+                _mm256_srlv_epi32(words, right_shifts),
+                _mm256_sllv_epi32(next_words, left_shifts),
+            );
+            _mm256_storeu_si256(output_ptr.add(row_pair), _mm256_and_si256(vals, mask));
+        }
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn integrate_sse2(initial: u32, output: &mut [u32]) {
+        let mut offset = _mm_set1_epi32(initial as i32);
+        for row in output.chunks_exact_mut(4) {
+            let row_ptr = row.as_mut_ptr() as *mut __m128i;
+            let deltas = _mm_loadu_si128(row_ptr);
+            let partial_sums = _mm_add_epi32(deltas, _mm_slli_si128(deltas, 4));
+            let partial_sums = _mm_add_epi32(partial_sums, _mm_slli_si128(partial_sums, 8));
+            let vals = _mm_add_epi32(partial_sums, offset);
+            _mm_storeu_si128(row_ptr, vals);
+            offset = _mm_shuffle_epi32(vals, 0xff);
+        }
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod neon {
+    use super::{row_position, straddles, value_mask, NUM_ROWS};
+    use std::arch::aarch64::*;
+
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn unpack_neon(compressed: &[u8], num_bits: usize, output: &mut [u32]) {
+        let load_words = |word_ord: usize| {
+            vreinterpretq_u32_u8(vld1q_u8(compressed.as_ptr().add(word_ord * 16)))
+        };
+        let mask = vdupq_n_u32(value_mask(num_bits));
+        for row in 0..NUM_ROWS {
+            let (word_ord, shift) = row_position(row, num_bits);
+            // Negative shifts are right shifts.
+            let mut vals = vshlq_u32(load_words(word_ord), vdupq_n_s32(-(shift as i32)));
+            if straddles(shift, num_bits) {
+                let high_bits = vshlq_u32(load_words(word_ord + 1), vdupq_n_s32(32 - shift as i32));
+                vals = vorrq_u32(vals, high_bits);
+            }
+            vst1q_u32(output.as_mut_ptr().add(row * 4), vandq_u32(vals, mask));
+        }
+    }
+
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn integrate_neon(initial: u32, output: &mut [u32]) {
+        let zeros = vdupq_n_u32(0);
+        let mut offset = vdupq_n_u32(initial);
+        for row in output.chunks_exact_mut(4) {
+            let deltas = vld1q_u32(row.as_ptr());
+            let partial_sums = vaddq_u32(deltas, vextq_u32(zeros, deltas, 3));
+            let partial_sums = vaddq_u32(partial_sums, vextq_u32(zeros, partial_sums, 2));
+            let vals = vaddq_u32(partial_sums, offset);
+            vst1q_u32(row.as_mut_ptr(), vals);
+            offset = vdupq_laneq_u32(vals, 3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InstructionSet;
+    use crate::postings::compression::COMPRESSION_BLOCK_SIZE;
+    use bitpacking::{BitPacker, BitPacker4x};
+
+    // Values of at most `num_bits` bits, deterministic but irregular.
+    fn values(num_bits: u8, seed: u64) -> Vec<u32> {
+        let mask = ((1u64 << num_bits) - 1) as u32;
+        let mut state = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+        (0..COMPRESSION_BLOCK_SIZE)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6_364_136_223_846_793_005)
+                    .wrapping_add(1_442_695_040_888_963_407);
+                (state >> 32) as u32 & mask
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_selected_instruction_set() {
+        let available = InstructionSet::available();
+        assert_eq!(available[0], InstructionSet::Scalar);
+        assert_eq!(InstructionSet::selected(), *available.last().unwrap());
+        #[cfg(target_arch = "x86_64")]
+        assert_ne!(InstructionSet::selected(), InstructionSet::Scalar);
+    }
+
+    #[test]
+    fn test_unpack_matches_bitpacker() {
+        let bitpacker = BitPacker4x::new();
+        let mut compressed = vec![0u8; COMPRESSION_BLOCK_SIZE * 4];
+        for num_bits in 0..=32u8 {
+            let vals = values(num_bits, u64::from(num_bits));
+            let num_bytes = bitpacker.compress(&vals, &mut compressed, num_bits);
+            for instruction_set in InstructionSet::available() {
+                let mut output = [u32::MAX; COMPRESSION_BLOCK_SIZE];
+                let num_bytes_read =
+                    instruction_set.unpack(&compressed[..num_bytes], num_bits, &mut output);
+                assert_eq!(num_bytes_read, num_bytes);
+                assert_eq!(&output[..], &vals[..], "{:?} {}", instruction_set, num_bits);
+            }
+        }
+    }
+
+    #[test]
+    fn test_unpack_sorted_matches_bitpacker() {
+        let bitpacker = BitPacker4x::new();
+        let mut compressed = vec![0u8; COMPRESSION_BLOCK_SIZE * 4];
+        for max_delta_bits in 0..=24u8 {
+            let initial = 1_000u32;
+            let vals: Vec<u32> = values(max_delta_bits, 7)
+                .into_iter()
+                .scan(initial, |val, delta| {
+                    *val += delta;
+                    Some(*val)
+                })
+                .collect();
+            let num_bits = bitpacker.num_bits_sorted(initial, &vals);
+            let num_bytes = bitpacker.compress_sorted(initial, &vals, &mut compressed, num_bits);
+            for instruction_set in InstructionSet::available() {
+                let mut output = [0u32; COMPRESSION_BLOCK_SIZE];
+                instruction_set.unpack_sorted(
+                    initial,
+                    &compressed[..num_bytes],
+                    num_bits,
+                    &mut output,
+                );
+                assert_eq!(&output[..], &vals[..], "{:?} {}", instruction_set, num_bits);
+            }
+        }
+    }
+}
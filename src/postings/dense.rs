@@ -0,0 +1,347 @@
+//! Dense posting lists.
+//!
+//! The doc ids of a term appearing in a large fraction of the documents of
+//! a segment are cheaper to store and to decode as a bitmap than as
+//! delta-encoded blocks.
+//!
+//! The representation is chosen automatically when the posting list is serialized.
+//! It only applies to fields indexed with `IndexRecordOption::Basic`, for which
+//! the posting lists do not contain any term frequency.
+//!
+//! A dense posting list is serialized as:
+//! - `VInt(0)`: an empty skip list. A regular posting list of more than
+//! `COMPRESSION_BLOCK_SIZE` docs always has a non-empty skip list.
+//! - a bitmap of `(num_docs + 7) / 8` bytes, in which the doc `doc` is
+//! represented by the bit `doc % 8` of the byte `doc / 8`.
+//!
+//! The skip list is rebuilt from the bitmap when the posting list is opened.
+//! When only the doc ids are needed, the bitmap is read in place as a `DenseDocSet`.
+use crate::common::TinySet;
+use crate::directory::OwnedBytes;
+use crate::docset::{DocSet, TERMINATED};
+use crate::postings::compression::COMPRESSION_BLOCK_SIZE;
+use crate::postings::skip::SkipSerializer;
+use crate::DocId;
+
+/// A term is dense if it appears in at least one document out of `DENSITY_RATIO`.
+///
+/// Above this ratio, the bitmap is at most 4 bits per document,
+/// which is in the same ballpark as bitpacked blocks.
+const DENSITY_RATIO: u64 = 4;
+
+/// `num_bits` recorded in the rebuilt skip list of dense posting lists.
+///
+/// Skip entries of dense posting lists do not point to actual offsets in the
+/// posting list data. The resulting offsets are only used to identify the loaded block,
+/// and therefore need to be distinct.
+pub(crate) const DENSE_BLOCK_NUM_BITS: u8 = 1u8;
+
+/// Returns true if a term with the given `doc_freq`, in a segment of `num_docs` documents,
+/// should be serialized as a dense posting list.
+pub(crate) fn is_dense(doc_freq: u32, num_docs: u32) -> bool {
+    doc_freq >= COMPRESSION_BLOCK_SIZE as u32
+        && u64::from(doc_freq) * DENSITY_RATIO >= u64::from(num_docs)
+}
+
+/// Returns the number of bytes of the bitmap for a segment of `num_docs` documents.
+pub(crate) fn bitmap_num_bytes(num_docs: u32) -> usize {
+    (num_docs as usize + 7) / 8
+}
+
+/// Adds `doc` to the bitmap.
+pub(crate) fn insert_doc(bitmap: &mut [u8], doc: DocId) {
+    bitmap[(doc / 8) as usize] |= 1u8 << (doc % 8);
+}
+
+// Iterates over the docs of a single byte of the bitmap.
+fn byte_docs(byte_ord: usize, mut byte: u8) -> impl Iterator<Item = DocId> {
+    let doc_offset = (byte_ord * 8) as DocId;
+    std::iter::from_fn(move || {
+        if byte == 0 {
+            return None;
+        }
+        let bit = byte.trailing_zeros();
+        byte &= byte - 1;
+        Some(doc_offset + bit)
+    })
+}
+
+/// Iterates over the docs of the bitmap, greater or equal to `first_doc`.
+pub(crate) fn docs_from(bitmap: &[u8], first_doc: DocId) -> impl Iterator<Item = DocId> + '_ {
+    let first_byte_ord = ((first_doc / 8) as usize).min(bitmap.len());
+    let first_byte_mask = 0xFFu8 << (first_doc % 8);
+    bitmap[first_byte_ord..]
+        .iter()
+        .enumerate()
+        .flat_map(move |(i, &byte)| {
+            let byte = if i == 0 { byte & first_byte_mask } else { byte };
+            byte_docs(first_byte_ord + i, byte)
+        })
+}
+
+/// Rebuilds the skip list of a dense posting list.
+///
+/// The skip list only holds the last doc of each complete block,
+/// as in the skip list of a regular posting list with `IndexRecordOption::Basic`.
+pub(crate) fn build_skip_data(bitmap: &[u8]) -> OwnedBytes {
+    let block_size = COMPRESSION_BLOCK_SIZE as u32;
+    let mut skip_serializer = SkipSerializer::new();
+    let mut num_docs_in_block = 0u32;
+    for (byte_ord, &byte) in bitmap.iter().enumerate() {
+        let num_docs_in_byte = byte.count_ones();
+        if num_docs_in_block + num_docs_in_byte < block_size {
+            num_docs_in_block += num_docs_in_byte;
+            continue;
+        }
+        // The current block ends within this byte.
+        for doc in byte_docs(byte_ord, byte) {
+            num_docs_in_block += 1;
+            if num_docs_in_block == block_size {
+                skip_serializer.write_doc(doc, DENSE_BLOCK_NUM_BITS);
+                num_docs_in_block = 0;
+            }
+        }
+    }
+    OwnedBytes::new(skip_serializer.data().to_vec())
+}
+
+/// `DocSet` over the bitmap of a dense posting list.
+///
+/// The bitmap is read in place, 64 docs at a time, without being copied.
+pub(crate) struct DenseDocSet {
+    bitmap: OwnedBytes,
+    doc_freq: u32,
+    cursor_bucket: u32,
+    cursor_tinyset: TinySet,
+    doc: DocId,
+}
+
+impl DenseDocSet {
+    pub(crate) fn new(bitmap: OwnedBytes, doc_freq: u32) -> DenseDocSet {
+        let mut docset = DenseDocSet {
+            bitmap,
+            doc_freq,
+            cursor_bucket: 0,
+            cursor_tinyset: TinySet::empty(),
+            doc: 0,
+        };
+        docset.go_to_bucket(0);
+        docset.advance();
+        docset
+    }
+
+    fn num_buckets(&self) -> u32 {
+        ((self.bitmap.len() + 7) / 8) as u32
+    }
+
+    fn go_to_bucket(&mut self, bucket: u32) {
+        self.cursor_bucket = bucket;
+        let start = bucket as usize * 8;
+        let end = (start + 8).min(self.bitmap.len());
+        self.cursor_tinyset = TinySet::from_le_bytes(&self.bitmap.as_slice()[start..end]);
+    }
+}
+
+impl DocSet for DenseDocSet {
+    fn advance(&mut self) -> DocId {
+        loop {
+            if let Some(lower) = self.cursor_tinyset.pop_lowest() {
+                self.doc = self.cursor_bucket * 64u32 + lower;
+                return self.doc;
+            }
+            if self.cursor_bucket + 1 >= self.num_buckets() {
+                self.doc = TERMINATED;
+                return TERMINATED;
+            }
+            self.go_to_bucket(self.cursor_bucket + 1);
+        }
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        let target_bucket = target / 64u32;
+        if target_bucket >= self.num_buckets() {
+            self.doc = TERMINATED;
+            return TERMINATED;
+        }
+        if target_bucket > self.cursor_bucket {
+            self.go_to_bucket(target_bucket);
+            self.cursor_tinyset = self
+                .cursor_tinyset
+                .intersect(TinySet::range_greater_or_equal(target));
+            self.advance()
+        } else {
+            let mut doc = self.doc();
+            while doc < target {
+                doc = self.advance();
+            }
+            doc
+        }
+    }
+
+    fn doc(&self) -> DocId {
+        self.doc
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.doc_freq
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collector::{Count, TopDocs};
+    use crate::docset::DocSet;
+    use crate::postings::SkipReader;
+    use crate::query::{BooleanQuery, Query, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, STRING};
+    use crate::{Index, Searcher, Term, TERMINATED};
+    use futures::executor::block_on;
+
+    fn bitmap(docs: &[DocId], num_docs: u32) -> Vec<u8> {
+        let mut bitmap = vec![0u8; bitmap_num_bytes(num_docs)];
+        for &doc in docs {
+            insert_doc(&mut bitmap, doc);
+        }
+        bitmap
+    }
+
+    #[test]
+    fn test_is_dense() {
+        assert!(!is_dense(127, 128));
+        assert!(is_dense(128, 128));
+        assert!(is_dense(250, 1_000));
+        assert!(!is_dense(249, 1_000));
+    }
+
+    #[test]
+    fn test_docs_from() {
+        let docs: Vec<DocId> = vec![0, 3, 7, 8, 15, 16, 100];
+        let bitmap = bitmap(&docs, 101);
+        assert_eq!(docs_from(&bitmap, 0).collect::<Vec<_>>(), docs);
+        assert_eq!(
+            docs_from(&bitmap, 4).collect::<Vec<_>>(),
+            vec![7, 8, 15, 16, 100]
+        );
+        assert_eq!(docs_from(&bitmap, 16).collect::<Vec<_>>(), vec![16, 100]);
+        assert_eq!(docs_from(&bitmap, 101).count(), 0);
+        assert_eq!(docs_from(&bitmap, 1_000).count(), 0);
+    }
+
+    #[test]
+    fn test_build_skip_data() {
+        let docs: Vec<DocId> = (0..1_000).filter(|doc| doc % 3 != 0).collect();
+        let bitmap = bitmap(&docs, 1_000);
+        let skip_data = build_skip_data(&bitmap);
+        let doc_freq = docs.len() as u32;
+        let mut skip_reader = SkipReader::new(skip_data, doc_freq, IndexRecordOption::Basic);
+        for block in docs.chunks(COMPRESSION_BLOCK_SIZE) {
+            if block.len() == COMPRESSION_BLOCK_SIZE {
+                assert_eq!(skip_reader.last_doc_in_block(), *block.last().unwrap());
+            } else {
+                assert_eq!(skip_reader.last_doc_in_block(), TERMINATED);
+            }
+            skip_reader.advance();
+        }
+    }
+
+    #[test]
+    fn test_dense_docset() {
+        let docs: Vec<DocId> = vec![0, 3, 63, 64, 130, 700, 701];
+        let bitmap = OwnedBytes::new(bitmap(&docs, 702));
+        let mut docset = DenseDocSet::new(bitmap.clone(), docs.len() as u32);
+        for &doc in &docs {
+            assert_eq!(docset.doc(), doc);
+            docset.advance();
+        }
+        assert_eq!(docset.doc(), TERMINATED);
+        let mut docset = DenseDocSet::new(bitmap.clone(), docs.len() as u32);
+        assert_eq!(docset.seek(3), 3);
+        assert_eq!(docset.seek(4), 63);
+        assert_eq!(docset.seek(65), 130);
+        assert_eq!(docset.seek(131), 700);
+        assert_eq!(docset.seek(702), TERMINATED);
+        let empty_docset = DenseDocSet::new(OwnedBytes::new(vec![0u8; 3]), 0);
+        assert_eq!(empty_docset.doc(), TERMINATED);
+    }
+
+    #[test]
+    fn test_dense_postings() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let status_field = schema_builder.add_text_field("status", STRING);
+        let parity_field = schema_builder.add_text_field("parity", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for segment_ord in 0..2 {
+            for i in 0..500 {
+                let doc = segment_ord * 500 + i;
+                let status = if doc % 5 == 0 { "inactive" } else { "active" };
+                let parity = if doc % 2 == 0 { "even" } else { "odd" };
+                index_writer.add_document(doc!(status_field => status, parity_field => parity));
+            }
+            index_writer.commit()?;
+        }
+        let term_query = |field, text| -> Box<dyn Query> {
+            Box::new(TermQuery::new(
+                Term::from_field_text(field, text),
+                IndexRecordOption::Basic,
+            ))
+        };
+        let active = Term::from_field_text(status_field, "active");
+        let inactive = Term::from_field_text(status_field, "inactive");
+        let active_and_even = BooleanQuery::intersection(vec![
+            term_query(status_field, "active"),
+            term_query(parity_field, "even"),
+        ]);
+        let check_counts = |searcher: &Searcher, num_active: usize| -> crate::Result<()> {
+            let active_query = term_query(status_field, "active");
+            assert_eq!(searcher.search(&*active_query, &Count)?, num_active);
+            assert_eq!(searcher.search(&active_and_even, &Count)?, 400);
+            let top_docs = searcher.search(&active_and_even, &TopDocs::with_limit(1_000))?;
+            assert_eq!(top_docs.len(), 400);
+            Ok(())
+        };
+
+        let searcher = index.reader()?.searcher();
+        check_counts(&searcher, 800)?;
+        let segment_reader = searcher.segment_reader(0);
+        let inverted_index = segment_reader.inverted_index(status_field)?;
+        // 400 docs out of 500 are active, 100 are inactive.
+        assert!(inverted_index.read_dense_docset(&active)?.is_some());
+        assert!(inverted_index.read_dense_docset(&inactive)?.is_none());
+        let mut postings = inverted_index
+            .read_postings(&active, IndexRecordOption::Basic)?
+            .unwrap();
+        let expected_docs: Vec<u32> = (0..500).filter(|doc| doc % 5 != 0).collect();
+        for &doc in &expected_docs {
+            assert_eq!(postings.doc(), doc);
+            postings.advance();
+        }
+        assert_eq!(postings.doc(), TERMINATED);
+        let mut postings = inverted_index
+            .read_postings(&active, IndexRecordOption::Basic)?
+            .unwrap();
+        assert_eq!(postings.seek(300), 301);
+        assert_eq!(postings.seek(455), 456);
+        assert_eq!(postings.seek(499), 499);
+        assert_eq!(postings.seek(500), TERMINATED);
+
+        // After the merge, the segment only contains the even docs.
+        index_writer.delete_term(Term::from_field_text(parity_field, "odd"));
+        index_writer.commit()?;
+        let segment_ids = index.searchable_segment_ids()?;
+        block_on(index_writer.merge(&segment_ids))?;
+        index_writer.wait_merging_threads()?;
+        let reader = index.reader()?;
+        let searcher = reader.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        assert_eq!(searcher.num_docs(), 500);
+        check_counts(&searcher, 400)?;
+        assert!(searcher
+            .segment_reader(0)
+            .inverted_index(status_field)?
+            .read_dense_docset(&active)?
+            .is_some());
+        Ok(())
+    }
+}
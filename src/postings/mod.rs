@@ -5,6 +5,7 @@ Postings module (also called inverted index)
 mod block_search;
 mod block_segment_postings;
 pub(crate) mod compression;
+mod dense;
 mod postings;
 mod postings_writer;
 mod recorder;
@@ -16,6 +17,7 @@ mod term_info;
 
 pub(crate) use self::block_search::BlockSearcher;
 pub use self::block_segment_postings::BlockSegmentPostings;
+pub(crate) use self::dense::DenseDocSet;
 pub use self::postings::Postings;
 pub(crate) use self::postings_writer::MultiFieldPostingsWriter;
 pub use self::segment_postings::SegmentPostings;
@@ -55,6 +57,7 @@ pub mod tests {
     use crate::HasLen;
     use crate::Score;
     use std::mem;
+    use std::ops::Range;
 
     #[test]
     pub fn test_position_write() -> crate::Result<()> {
@@ -269,7 +272,7 @@ pub mod tests {
                 assert_eq!(postings_a.doc(), 0);
                 assert_eq!(postings_a.term_freq(), 6);
                 postings_a.positions(&mut positions);
-                assert_eq!(&positions[..], [0, 2, 4, 6, 7, 13]);
+                assert_eq!(&positions[..], [0, 2, 4, 6, 7, 111]);
                 assert_eq!(postings_a.advance(), 1u32);
                 assert_eq!(postings_a.doc(), 1u32);
                 assert_eq!(postings_a.term_freq(), 1);
@@ -331,6 +334,104 @@ pub mod tests {
         Ok(())
     }
 
+    #[test]
+    pub fn test_max_term_freq() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions)
+                .set_max_term_freq(3),
+        );
+        let text_field = schema_builder.add_text_field("text", text_options);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field => "a a a a a b a"));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text_field => "b a"));
+        index_writer.commit()?;
+        let segment_ids = index.searchable_segment_ids()?;
+        futures::executor::block_on(index_writer.merge(&segment_ids))?;
+        index_writer.wait_merging_threads()?;
+
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let inverted_index = searcher.segment_reader(0).inverted_index(text_field)?;
+        let term_a = Term::from_field_text(text_field, "a");
+        let mut postings = inverted_index
+            .read_postings(&term_a, IndexRecordOption::WithFreqsAndPositions)?
+            .unwrap();
+        let mut positions = Vec::new();
+        let mut term_freqs_and_positions = Vec::new();
+        while postings.doc() != TERMINATED {
+            postings.positions(&mut positions);
+            term_freqs_and_positions.push((postings.term_freq(), positions.clone()));
+            postings.advance();
+        }
+        // The order of the segments in the merged segment is not specified.
+        term_freqs_and_positions.sort();
+        assert_eq!(
+            term_freqs_and_positions,
+            vec![(1, vec![1]), (3, vec![0, 1, 2])]
+        );
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_offsets() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_index_option(IndexRecordOption::WithFreqsAndPositionsAndOffsets),
+        );
+        let text_field = schema_builder.add_text_field("text", text_options);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field => "hello world hello"));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text_field => "hello", text_field => "Big hello"));
+        index_writer.commit()?;
+        let read_offsets = |index: &Index| -> crate::Result<Vec<(Vec<u32>, Vec<Range<usize>>)>> {
+            let searcher = index.reader()?.searcher();
+            let term = Term::from_field_text(text_field, "hello");
+            let mut positions = Vec::new();
+            let mut offsets = Vec::new();
+            let mut positions_and_offsets = Vec::new();
+            for segment_reader in searcher.segment_readers() {
+                let inverted_index = segment_reader.inverted_index(text_field)?;
+                let first_doc_ord = positions_and_offsets.len();
+                let mut postings = inverted_index
+                    .read_postings(&term, IndexRecordOption::WithFreqsAndPositionsAndOffsets)?
+                    .unwrap();
+                while postings.doc() != TERMINATED {
+                    postings.positions(&mut positions);
+                    postings.offsets(&mut offsets);
+                    positions_and_offsets.push((positions.clone(), offsets.clone()));
+                    postings.advance();
+                }
+                // Offsets are not decoded unless they are requested.
+                let mut postings = inverted_index
+                    .read_postings(&term, IndexRecordOption::WithFreqsAndPositions)?
+                    .unwrap();
+                postings.positions(&mut positions);
+                assert_eq!(positions, positions_and_offsets[first_doc_ord].0);
+                postings.offsets(&mut offsets);
+                assert!(offsets.is_empty());
+            }
+            positions_and_offsets.sort_by(|left, right| left.0.cmp(&right.0));
+            Ok(positions_and_offsets)
+        };
+        let expected = vec![
+            (vec![0, 2], vec![0..5, 12..17]),
+            (vec![0, 101], vec![0..5, 9..14]),
+        ];
+        assert_eq!(read_offsets(&index)?, expected);
+        let segment_ids = index.searchable_segment_ids()?;
+        futures::executor::block_on(index_writer.merge(&segment_ids))?;
+        index_writer.wait_merging_threads()?;
+        assert_eq!(read_offsets(&index)?, expected);
+        Ok(())
+    }
+
     #[test]
     fn test_skip_next() -> crate::Result<()> {
         let term_0 = Term::from_field_u64(Field::from_field_id(0), 0);
@@ -2,6 +2,7 @@ use super::stacker::{Addr, MemoryArena, TermHashMap};
 
 use crate::postings::recorder::{
     BufferLender, NothingRecorder, Recorder, TermFrequencyRecorder, TfAndPositionRecorder,
+    TfPositionAndOffsetRecorder,
 };
 use crate::postings::UnorderedTermId;
 use crate::postings::{FieldSerializer, InvertedIndexSerializer};
@@ -32,6 +33,9 @@ fn posting_from_field_entry(field_entry: &FieldEntry) -> Box<dyn PostingsWriter>
                 IndexRecordOption::WithFreqsAndPositions => {
                     SpecializedPostingsWriter::<TfAndPositionRecorder>::new_boxed()
                 }
+                IndexRecordOption::WithFreqsAndPositionsAndOffsets => {
+                    SpecializedPostingsWriter::<TfPositionAndOffsetRecorder>::new_boxed()
+                }
             })
             .unwrap_or_else(|| SpecializedPostingsWriter::<NothingRecorder>::new_boxed()),
         FieldType::U64(_)
@@ -205,6 +209,16 @@ pub trait PostingsWriter {
         heap: &mut MemoryArena,
     ) -> UnorderedTermId;
 
+    /// Record that a document contains a token, at its position and offsets.
+    fn subscribe_token(
+        &mut self,
+        term_index: &mut TermHashMap,
+        doc: DocId,
+        token: &Token,
+        term: &Term,
+        heap: &mut MemoryArena,
+    ) -> UnorderedTermId;
+
     /// Serializes the postings on disk.
     /// The actual serialization format is handled by the `PostingsSerializer`.
     fn serialize(
@@ -231,7 +245,7 @@ pub trait PostingsWriter {
             // We skip all tokens with a len greater than u16.
             if token.text.len() <= MAX_TOKEN_LEN {
                 term_buffer.set_text(token.text.as_str());
-                self.subscribe(term_index, doc_id, token.position as u32, term_buffer, heap);
+                self.subscribe_token(term_index, doc_id, token, term_buffer, heap);
             } else {
                 warn!(
                     "A token exceeding MAX_TOKEN_LEN ({}>{}) was dropped. Search for \
@@ -269,14 +283,16 @@ impl<Rec: Recorder + 'static> SpecializedPostingsWriter<Rec> {
     }
 }
 
-impl<Rec: Recorder + 'static> PostingsWriter for SpecializedPostingsWriter<Rec> {
-    fn subscribe(
+impl<Rec: Recorder + 'static> SpecializedPostingsWriter<Rec> {
+    // Records the presence of the term in the document, then calls `record`
+    // on the recorder of the term.
+    fn record(
         &mut self,
         term_index: &mut TermHashMap,
         doc: DocId,
-        position: u32,
         term: &Term,
         heap: &mut MemoryArena,
+        record: impl Fn(&mut Rec, &mut MemoryArena),
     ) -> UnorderedTermId {
         debug_assert!(term.as_slice().len() >= 4);
         self.total_num_tokens += 1;
@@ -287,16 +303,45 @@ impl<Rec: Recorder + 'static> PostingsWriter for SpecializedPostingsWriter<Rec>
                     recorder.close_doc(heap);
                     recorder.new_doc(doc, heap);
                 }
-                recorder.record_position(position, heap);
+                record(&mut recorder, heap);
                 recorder
             } else {
                 let mut recorder = Rec::new();
                 recorder.new_doc(doc, heap);
-                recorder.record_position(position, heap);
+                record(&mut recorder, heap);
                 recorder
             }
         }) as UnorderedTermId
     }
+}
+
+impl<Rec: Recorder + 'static> PostingsWriter for SpecializedPostingsWriter<Rec> {
+    fn subscribe(
+        &mut self,
+        term_index: &mut TermHashMap,
+        doc: DocId,
+        position: u32,
+        term: &Term,
+        heap: &mut MemoryArena,
+    ) -> UnorderedTermId {
+        self.record(term_index, doc, term, heap, |recorder, heap| {
+            recorder.record_position(position, heap);
+        })
+    }
+
+    fn subscribe_token(
+        &mut self,
+        term_index: &mut TermHashMap,
+        doc: DocId,
+        token: &Token,
+        term: &Term,
+        heap: &mut MemoryArena,
+    ) -> UnorderedTermId {
+        self.record(term_index, doc, term, heap, |recorder, heap| {
+            recorder.record_position(token.position as u32, heap);
+            recorder.record_offsets(token.offset_from as u32, token.offset_to as u32, heap);
+        })
+    }
 
     fn serialize(
         &self,
@@ -5,6 +5,7 @@ use crate::{
     common::{read_u32_vint, write_u32_vint},
     indexer::doc_id_mapping::DocIdMapping,
 };
+use std::ops::Range;
 
 const POSITION_END: u32 = 0;
 
@@ -57,6 +58,7 @@ impl<'a> Iterator for VInt32Reader<'a> {
 ///   * the document id
 ///   * the term frequency
 ///   * the term positions
+///   * the term offsets
 pub(crate) trait Recorder: Copy + 'static {
     ///
     fn new() -> Self;
@@ -68,6 +70,9 @@ pub(crate) trait Recorder: Copy + 'static {
     /// Record the position of a term. For each document,
     /// this method will be called `term_freq` times.
     fn record_position(&mut self, position: u32, heap: &mut MemoryArena);
+    /// Record the offsets of the term whose position was just recorded.
+    /// Recorders that do not keep the offsets ignore them.
+    fn record_offsets(&mut self, _offset_from: u32, _offset_to: u32, _heap: &mut MemoryArena) {}
     /// Close the document. It will help record the term frequency.
     fn close_doc(&mut self, heap: &mut MemoryArena);
     /// Pushes the postings information to the serializer.
@@ -298,6 +303,102 @@ impl Recorder for TfAndPositionRecorder {
     }
 }
 
+/// Recorder encoding term frequencies, positions and offsets.
+#[derive(Clone, Copy)]
+pub struct TfPositionAndOffsetRecorder {
+    stack: ExpUnrolledLinkedList,
+    current_doc: DocId,
+    term_doc_freq: u32,
+}
+impl Recorder for TfPositionAndOffsetRecorder {
+    fn new() -> Self {
+        TfPositionAndOffsetRecorder {
+            stack: ExpUnrolledLinkedList::new(),
+            current_doc: u32::max_value(),
+            term_doc_freq: 0u32,
+        }
+    }
+
+    fn current_doc(&self) -> DocId {
+        self.current_doc
+    }
+
+    fn new_doc(&mut self, doc: DocId, heap: &mut MemoryArena) {
+        self.current_doc = doc;
+        self.term_doc_freq += 1u32;
+        let _ = write_u32_vint(doc, &mut self.stack.writer(heap));
+    }
+
+    fn record_position(&mut self, position: u32, heap: &mut MemoryArena) {
+        let _ = write_u32_vint(position + 1u32, &mut self.stack.writer(heap));
+    }
+
+    fn record_offsets(&mut self, offset_from: u32, offset_to: u32, heap: &mut MemoryArena) {
+        let mut writer = self.stack.writer(heap);
+        let _ = write_u32_vint(offset_from, &mut writer);
+        let _ = write_u32_vint(offset_to, &mut writer);
+    }
+
+    fn close_doc(&mut self, heap: &mut MemoryArena) {
+        let _ = write_u32_vint(POSITION_END, &mut self.stack.writer(heap));
+    }
+
+    fn serialize(
+        &self,
+        buffer_lender: &mut BufferLender,
+        serializer: &mut FieldSerializer<'_>,
+        heap: &MemoryArena,
+        doc_id_map: Option<&DocIdMapping>,
+    ) {
+        let (buffer_u8, buffer_positions) = buffer_lender.lend_all();
+        self.stack.read_to_end(heap, buffer_u8);
+        let mut u32_it = VInt32Reader::new(&buffer_u8[..]);
+        let mut buffer_offsets: Vec<Range<usize>> = Vec::new();
+        let mut doc_id_positions_and_offsets = vec![];
+        while let Some(doc) = u32_it.next() {
+            let mut prev_position_plus_one = 1u32;
+            buffer_positions.clear();
+            buffer_offsets.clear();
+            loop {
+                match u32_it.next() {
+                    Some(POSITION_END) | None => {
+                        break;
+                    }
+                    Some(position_plus_one) => {
+                        let delta_position = position_plus_one - prev_position_plus_one;
+                        buffer_positions.push(delta_position);
+                        prev_position_plus_one = position_plus_one;
+                        let offset_from = u32_it.next().unwrap_or(0u32);
+                        let offset_to = u32_it.next().unwrap_or(offset_from);
+                        buffer_offsets.push(offset_from as usize..offset_to as usize);
+                    }
+                }
+            }
+            if let Some(doc_id_map) = doc_id_map {
+                doc_id_positions_and_offsets.push((
+                    doc_id_map.get_new_doc_id(doc),
+                    buffer_positions.to_vec(),
+                    buffer_offsets.to_vec(),
+                ));
+            } else {
+                serializer.write_doc(doc, buffer_positions.len() as u32, buffer_positions);
+                serializer.write_offsets(&buffer_offsets);
+            }
+        }
+        if doc_id_map.is_some() {
+            doc_id_positions_and_offsets.sort_unstable_by_key(|&(doc_id, _, _)| doc_id);
+            for (doc_id, positions, offsets) in doc_id_positions_and_offsets {
+                serializer.write_doc(doc_id, positions.len() as u32, &positions);
+                serializer.write_offsets(&offsets);
+            }
+        }
+    }
+
+    fn term_doc_freq(&self) -> Option<u32> {
+        Some(self.term_doc_freq)
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
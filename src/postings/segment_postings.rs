@@ -7,6 +7,14 @@ use crate::postings::BlockSearcher;
 use crate::postings::BlockSegmentPostings;
 use crate::postings::Postings;
 use crate::{DocId, TERMINATED};
+use std::ops::Range;
+
+// Index, among all of the positions of the term, of the first position
+// of the document at the cursor `cur` of the current block.
+fn position_index(block_cursor: &BlockSegmentPostings, cur: usize) -> u64 {
+    block_cursor.position_offset()
+        + (block_cursor.freqs()[..cur].iter().cloned().sum::<u32>() as u64)
+}
 
 /// `SegmentPostings` represents the inverted list or postings associated to
 /// a term in a `Segment`.
@@ -18,6 +26,8 @@ pub struct SegmentPostings {
     pub(crate) block_cursor: BlockSegmentPostings,
     cur: usize,
     position_reader: Option<PositionReader>,
+    offsets_reader: Option<PositionReader>,
+    offsets_buffer: Vec<u32>,
     block_searcher: BlockSearcher,
 }
 
@@ -28,6 +38,8 @@ impl SegmentPostings {
             block_cursor: BlockSegmentPostings::empty(),
             cur: 0,
             position_reader: None,
+            offsets_reader: None,
+            offsets_buffer: Vec::new(),
             block_searcher: BlockSearcher::default(),
         }
     }
@@ -44,9 +56,7 @@ impl SegmentPostings {
             if doc == TERMINATED {
                 return doc_freq;
             }
-            if delete_bitset.is_alive(doc) {
-                doc_freq += 1u32;
-            }
+            doc_freq += delete_bitset.is_alive(doc) as u32;
             docset.advance();
         }
     }
@@ -154,9 +164,41 @@ impl SegmentPostings {
             block_cursor: segment_block_postings,
             cur: 0, // cursor within the block
             position_reader,
+            offsets_reader: None,
+            offsets_buffer: Vec::new(),
             block_searcher: BlockSearcher::default(),
         }
     }
+
+    pub(crate) fn with_offsets_reader(
+        mut self,
+        offsets_reader: Option<PositionReader>,
+    ) -> SegmentPostings {
+        self.offsets_reader = offsets_reader;
+        self
+    }
+
+    /// Returns the offsets (in bytes) of the occurences of the term in the current document,
+    /// in the order of their positions.
+    ///
+    /// The output vector will be resized to the `term_freq`, or cleared if the field
+    /// does not record offsets (see `IndexRecordOption::WithFreqsAndPositionsAndOffsets`),
+    /// or if the postings were not read with this option.
+    pub fn offsets(&mut self, output: &mut Vec<Range<usize>>) {
+        output.clear();
+        let term_freq = self.term_freq() as usize;
+        if let Some(offsets_reader) = self.offsets_reader.as_mut() {
+            let read_offset = position_index(&self.block_cursor, self.cur);
+            self.offsets_buffer.resize(2 * term_freq, 0u32);
+            offsets_reader.read(2 * read_offset, &mut self.offsets_buffer[..]);
+            let mut offset_from = 0u32;
+            for encoded_offsets in self.offsets_buffer.chunks_exact(2) {
+                offset_from = offset_from.wrapping_add(encoded_offsets[0]);
+                let offset_to = offset_from.wrapping_add(encoded_offsets[1]);
+                output.push(offset_from as usize..offset_to as usize);
+            }
+        }
+    }
 }
 
 impl DocSet for SegmentPostings {
@@ -246,11 +288,7 @@ impl Postings for SegmentPostings {
     fn positions_with_offset(&mut self, offset: u32, output: &mut Vec<u32>) {
         let term_freq = self.term_freq() as usize;
         if let Some(position_reader) = self.position_reader.as_mut() {
-            let read_offset = self.block_cursor.position_offset()
-                + (self.block_cursor.freqs()[..self.cur]
-                    .iter()
-                    .cloned()
-                    .sum::<u32>() as u64);
+            let read_offset = position_index(&self.block_cursor, self.cur);
             output.resize(term_freq, 0u32);
             position_reader.read(read_offset, &mut output[..]);
             let mut cum = offset;
@@ -6,14 +6,16 @@ use crate::directory::WritePtr;
 use crate::fieldnorm::FieldNormReader;
 use crate::positions::PositionSerializer;
 use crate::postings::compression::{BlockEncoder, VIntEncoder, COMPRESSION_BLOCK_SIZE};
+use crate::postings::dense;
 use crate::postings::skip::SkipSerializer;
 use crate::query::Bm25Weight;
 use crate::schema::{Field, FieldEntry, FieldType};
-use crate::schema::{IndexRecordOption, Schema};
+use crate::schema::{IndexRecordOption, Schema, TextFieldIndexing};
 use crate::termdict::{TermDictionaryBuilder, TermOrdinal};
 use crate::{DocId, Score};
 use std::cmp::Ordering;
 use std::io::{self, Write};
+use std::ops::Range;
 
 /// `InvertedIndexSerializer` is in charge of serializing
 /// postings on disk, in the
@@ -106,6 +108,11 @@ pub struct FieldSerializer<'a> {
     term_dictionary_builder: TermDictionaryBuilder<&'a mut CountingWriter<WritePtr>>,
     postings_serializer: PostingsSerializer<&'a mut CountingWriter<WritePtr>>,
     positions_serializer_opt: Option<PositionSerializer<&'a mut CountingWriter<WritePtr>>>,
+    // Term frequencies above this value are clipped.
+    max_term_freq: u32,
+    // Number of positions written for the last document.
+    num_doc_positions: usize,
+    encoded_offsets: Vec<u32>,
     current_term_info: TermInfo,
     term_open: bool,
     num_terms: TermOrdinal,
@@ -121,16 +128,16 @@ impl<'a> FieldSerializer<'a> {
         fieldnorm_reader: Option<FieldNormReader>,
     ) -> io::Result<FieldSerializer<'a>> {
         total_num_tokens.serialize(postings_write)?;
-        let mode = match field_type {
-            FieldType::Str(ref text_options) => {
-                if let Some(text_indexing_options) = text_options.get_indexing_options() {
-                    text_indexing_options.index_option()
-                } else {
-                    IndexRecordOption::Basic
-                }
-            }
-            _ => IndexRecordOption::Basic,
+        let text_indexing_options_opt = match field_type {
+            FieldType::Str(ref text_options) => text_options.get_indexing_options(),
+            _ => None,
         };
+        let mode = text_indexing_options_opt
+            .map(TextFieldIndexing::index_option)
+            .unwrap_or(IndexRecordOption::Basic);
+        let max_term_freq = text_indexing_options_opt
+            .and_then(TextFieldIndexing::max_term_freq)
+            .unwrap_or(u32::MAX);
         let term_dictionary_builder = TermDictionaryBuilder::create(term_dictionary_write)?;
         let average_fieldnorm = fieldnorm_reader
             .as_ref()
@@ -138,7 +145,9 @@ impl<'a> FieldSerializer<'a> {
             .unwrap_or(0.0);
         let postings_serializer =
             PostingsSerializer::new(postings_write, average_fieldnorm, mode, fieldnorm_reader);
-        let positions_serializer_opt = if mode.has_positions() {
+        let positions_serializer_opt = if mode.has_offsets() {
+            Some(PositionSerializer::with_offsets(positions_write))
+        } else if mode.has_positions() {
             Some(PositionSerializer::new(positions_write))
         } else {
             None
@@ -148,6 +157,9 @@ impl<'a> FieldSerializer<'a> {
             term_dictionary_builder,
             postings_serializer,
             positions_serializer_opt,
+            max_term_freq,
+            num_doc_positions: 0,
+            encoded_offsets: Vec::new(),
             current_term_info: TermInfo::default(),
             term_open: false,
             num_terms: TermOrdinal::default(),
@@ -196,13 +208,43 @@ impl<'a> FieldSerializer<'a> {
     /// For instance, if the positions are `2, 3, 17`,
     /// `position_deltas` is `2, 1, 14`
     ///
-    /// Term frequencies and positions may be ignored by the serializer depending
-    /// on the configuration of the field in the `Schema`.
+    /// Term frequencies and positions may be ignored or clipped by the serializer
+    /// depending on the configuration of the field in the `Schema`.
     pub fn write_doc(&mut self, doc_id: DocId, term_freq: u32, position_deltas: &[u32]) {
         self.current_term_info.doc_freq += 1;
+        let term_freq = term_freq.min(self.max_term_freq);
         self.postings_serializer.write_doc(doc_id, term_freq);
         if let Some(ref mut positions_serializer) = self.positions_serializer_opt.as_mut() {
-            positions_serializer.write_positions_delta(position_deltas);
+            let num_positions = position_deltas.len().min(term_freq as usize);
+            positions_serializer.write_positions_delta(&position_deltas[..num_positions]);
+            self.num_doc_positions = num_positions;
+        }
+    }
+
+    /// Serialize the offsets of the occurences of the current term in the document
+    /// written last, in the order of their positions.
+    ///
+    /// This method needs to be called right after `write_doc`, and is a no-op
+    /// if the field does not record offsets.
+    pub fn write_offsets(&mut self, offsets: &[Range<usize>]) {
+        if let Some(ref mut positions_serializer) = self.positions_serializer_opt.as_mut() {
+            // Each offset is encoded as its start, relative to the start of the previous one,
+            // followed by its length.
+            self.encoded_offsets.clear();
+            let mut prev_offset_from = 0u32;
+            for offset in offsets.iter().take(self.num_doc_positions) {
+                let offset_from = offset.start as u32;
+                let offset_to = offset.end as u32;
+                self.encoded_offsets
+                    .push(offset_from.wrapping_sub(prev_offset_from));
+                self.encoded_offsets
+                    .push(offset_to.wrapping_sub(offset_from));
+                prev_offset_from = offset_from;
+            }
+            // There are always two values per position.
+            self.encoded_offsets
+                .resize(2 * self.num_doc_positions, 0u32);
+            positions_serializer.write_offsets(&self.encoded_offsets);
         }
     }
 
@@ -304,6 +346,11 @@ pub struct PostingsSerializer<W: Write> {
 
     bm25_weight: Option<Bm25Weight>,
 
+    // Bitmap of the docs of the current term, only filled if the term
+    // may end up being serialized as a dense posting list.
+    dense_bitmap: Vec<u8>,
+    dense_candidate: bool,
+
     num_docs: u32, // Number of docs in the segment
     avg_fieldnorm: Score, // Average number of term in the field for that segment.
                    // this value is used to compute the block wand information.
@@ -335,6 +382,9 @@ impl<W: Write> PostingsSerializer<W> {
             fieldnorm_reader,
             bm25_weight: None,
 
+            dense_bitmap: Vec::new(),
+            dense_candidate: false,
+
             num_docs,
             avg_fieldnorm,
         }
@@ -349,6 +399,18 @@ impl<W: Write> PostingsSerializer<W> {
             );
             self.bm25_weight = Some(bm25_weight);
         }
+        // `term_doc_freq` is only an upper bound (e.g. when merging segments with deletes).
+        // The actual representation is decided when closing the term.
+        //
+        // The number of docs in the segment is only known if the field has fieldnorms.
+        self.dense_candidate = self.mode == IndexRecordOption::Basic
+            && self.num_docs > 0
+            && dense::is_dense(term_doc_freq, self.num_docs);
+        if self.dense_candidate {
+            self.dense_bitmap.clear();
+            self.dense_bitmap
+                .resize(dense::bitmap_num_bytes(self.num_docs), 0u8);
+        }
     }
 
     fn write_block(&mut self) {
@@ -376,7 +438,18 @@ impl<W: Write> PostingsSerializer<W> {
                 self.skip_write.write_total_term_freq(sum_freq);
             }
             let mut blockwand_params = (0u8, 0u32);
-            if let Some(bm25_weight) = self.bm25_weight.as_ref() {
+            if self.fieldnorm_reader.is_none() {
+                // Fieldnorms are disabled for this field: the fieldnorm id 0 combined with the
+                // highest term frequency of the block gives a valid upper bound of the block scores.
+                let max_term_freq = self
+                    .block
+                    .term_freqs()
+                    .iter()
+                    .cloned()
+                    .max()
+                    .unwrap_or(0u32);
+                blockwand_params = (0u8, max_term_freq);
+            } else if let Some(bm25_weight) = self.bm25_weight.as_ref() {
                 if let Some(fieldnorm_reader) = self.fieldnorm_reader.as_ref() {
                     let docs = self.block.doc_ids().iter().cloned();
                     let term_freqs = self.block.term_freqs().iter().cloned();
@@ -405,6 +478,9 @@ impl<W: Write> PostingsSerializer<W> {
     }
 
     pub fn write_doc(&mut self, doc_id: DocId, term_freq: u32) {
+        if self.dense_candidate {
+            dense::insert_doc(&mut self.dense_bitmap, doc_id);
+        }
         self.block.append_doc(doc_id, term_freq);
         if self.block.is_full() {
             self.write_block();
@@ -438,7 +514,12 @@ impl<W: Write> PostingsSerializer<W> {
             }
             self.block.clear();
         }
-        if doc_freq >= COMPRESSION_BLOCK_SIZE as u32 {
+        if self.dense_candidate && dense::is_dense(doc_freq, self.num_docs) {
+            // The term appears in a large fraction of the documents: its doc ids are
+            // serialized as a bitmap, preceded by an empty skip list.
+            VInt(0u64).serialize(&mut self.output_write)?;
+            self.output_write.write_all(&self.dense_bitmap[..])?;
+        } else if doc_freq >= COMPRESSION_BLOCK_SIZE as u32 {
             let skip_data = self.skip_write.data();
             VInt(skip_data.len() as u64).serialize(&mut self.output_write)?;
             self.output_write.write_all(skip_data)?;
@@ -449,6 +530,7 @@ impl<W: Write> PostingsSerializer<W> {
         self.skip_write.clear();
         self.postings_write.clear();
         self.bm25_weight = None;
+        self.dense_candidate = false;
         Ok(())
     }
 
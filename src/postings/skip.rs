@@ -197,7 +197,8 @@ impl SkipReader {
                     block_wand_term_freq,
                 };
             }
-            IndexRecordOption::WithFreqsAndPositions => {
+            IndexRecordOption::WithFreqsAndPositions
+            | IndexRecordOption::WithFreqsAndPositionsAndOffsets => {
                 let tf_num_bits = bytes[5];
                 let tf_sum = read_u32(&bytes[6..10]);
                 let block_wand_fieldnorm_id = bytes[10];
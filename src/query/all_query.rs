@@ -1,6 +1,7 @@
 use crate::core::Searcher;
 use crate::core::SegmentReader;
 use crate::docset::{DocSet, TERMINATED};
+use crate::fastfield::DeleteBitSet;
 use crate::query::boost_query::BoostScorer;
 use crate::query::explanation::does_not_match;
 use crate::query::{Explanation, Query, Scorer, Weight};
@@ -24,13 +25,14 @@ pub struct AllWeight;
 
 impl Weight for AllWeight {
     fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
-        let all_scorer = AllScorer {
-            doc: 0u32,
-            max_doc: reader.max_doc(),
-        };
+        let all_scorer = AllScorer::new(reader.max_doc(), reader.delete_bitset().cloned());
         Ok(Box::new(BoostScorer::new(all_scorer, boost)))
     }
 
+    fn count(&self, reader: &SegmentReader) -> crate::Result<u32> {
+        Ok(reader.num_docs())
+    }
+
     fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
         if doc >= reader.max_doc() {
             return Err(does_not_match(doc));
@@ -40,18 +42,52 @@ impl Weight for AllWeight {
 }
 
 /// Scorer associated to the `AllQuery` query.
+///
+/// It only matches the alive documents.
 pub struct AllScorer {
     doc: DocId,
     max_doc: DocId,
+    delete_bitset_opt: Option<DeleteBitSet>,
+}
+
+impl AllScorer {
+    fn new(max_doc: DocId, delete_bitset_opt: Option<DeleteBitSet>) -> AllScorer {
+        let mut all_scorer = AllScorer {
+            doc: 0u32,
+            max_doc,
+            delete_bitset_opt,
+        };
+        all_scorer.doc = all_scorer.alive_doc_from(0u32);
+        all_scorer
+    }
+
+    // Returns the first alive doc greater than or equal to `doc`, or `TERMINATED`.
+    fn alive_doc_from(&self, doc: DocId) -> DocId {
+        let alive_doc = if let Some(delete_bitset) = self.delete_bitset_opt.as_ref() {
+            delete_bitset.next_alive(doc)
+        } else {
+            doc
+        };
+        if alive_doc >= self.max_doc {
+            TERMINATED
+        } else {
+            alive_doc
+        }
+    }
 }
 
 impl DocSet for AllScorer {
     fn advance(&mut self) -> DocId {
-        if self.doc + 1 >= self.max_doc {
-            self.doc = TERMINATED;
-            return TERMINATED;
+        if self.doc != TERMINATED {
+            self.doc = self.alive_doc_from(self.doc + 1);
+        }
+        self.doc
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        if self.doc < target {
+            self.doc = self.alive_doc_from(target);
         }
-        self.doc += 1;
         self.doc
     }
 
@@ -75,8 +111,8 @@ mod tests {
     use super::AllQuery;
     use crate::docset::TERMINATED;
     use crate::query::Query;
-    use crate::schema::{Schema, TEXT};
-    use crate::Index;
+    use crate::schema::{Schema, INDEXED, TEXT};
+    use crate::{DocId, Index, Term};
 
     fn create_test_index() -> Index {
         let mut schema_builder = Schema::builder();
@@ -132,4 +168,40 @@ mod tests {
             assert_eq!(scorer.score(), 1.5);
         }
     }
+
+    #[test]
+    fn test_all_query_skips_deleted_docs() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let id = schema_builder.add_u64_field("id", INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for i in 0u64..200u64 {
+            index_writer.add_document(doc!(id => i));
+        }
+        index_writer.commit()?;
+        for i in (0u64..200u64).filter(|i| *i < 70 || *i % 3 == 0 || *i == 199) {
+            index_writer.delete_term(Term::from_field_u64(id, i));
+        }
+        index_writer.commit()?;
+        let reader = index.reader()?;
+        let searcher = reader.searcher();
+        let weight = AllQuery.weight(&searcher, false)?;
+        let segment_reader = searcher.segment_reader(0);
+        let mut scorer = weight.scorer(segment_reader, 1.0)?;
+        let mut docs = Vec::new();
+        while scorer.doc() != TERMINATED {
+            docs.push(scorer.doc());
+            scorer.advance();
+        }
+        let alive_docs: Vec<DocId> = segment_reader.alive_docs().collect();
+        assert_eq!(docs, alive_docs);
+        assert_eq!(docs.len() as u32, segment_reader.num_docs());
+        assert_eq!(docs[0], 70);
+        assert_eq!(*docs.last().unwrap(), 197);
+        assert_eq!(weight.count(segment_reader)?, segment_reader.num_docs());
+        let mut scorer = weight.scorer(segment_reader, 1.0)?;
+        assert_eq!(scorer.seek(75), 76);
+        assert_eq!(scorer.seek(198), TERMINATED);
+        Ok(())
+    }
 }
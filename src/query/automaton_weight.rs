@@ -2,19 +2,31 @@ use crate::common::BitSet;
 use crate::core::SegmentReader;
 use crate::query::ConstScorer;
 use crate::query::{BitSetDocSet, Explanation};
+use crate::query::{BooleanQuery, ConstScoreQuery, EmptyQuery, Query, TermQuery};
 use crate::query::{Scorer, Weight};
-use crate::schema::{Field, IndexRecordOption};
+use crate::schema::{Field, IndexRecordOption, Term};
 use crate::termdict::{TermDictionary, TermStreamer};
 use crate::TantivyError;
-use crate::{DocId, Score};
+use crate::{DocId, ExpansionBudget, MemoryBudget, Score, Searcher};
+use std::collections::BTreeSet;
 use std::io;
 use std::sync::Arc;
 use tantivy_fst::Automaton;
 
+/// Maximum number of terms for which an automaton query is rewritten into
+/// a union of term queries.
+///
+/// Iterating over the posting lists of a few terms is cheaper than
+/// building the bitset of the matching documents of each segment.
+const TERM_UNION_MAX_NUM_TERMS: usize = 16;
+
 /// A weight struct for Fuzzy Term and Regex Queries
 pub struct AutomatonWeight<A> {
     field: Field,
     automaton: Arc<A>,
+    memory_budget: MemoryBudget,
+    expansion_budget: ExpansionBudget,
+    max_num_terms: Option<usize>,
 }
 
 impl<A> AutomatonWeight<A>
@@ -27,6 +39,40 @@ where
         AutomatonWeight {
             field,
             automaton: automaton.into(),
+            memory_budget: MemoryBudget::unlimited(),
+            expansion_budget: ExpansionBudget::unlimited(),
+            max_num_terms: None,
+        }
+    }
+
+    /// Charges the bitset built for each segment to the given memory budget.
+    pub fn with_memory_budget(mut self, memory_budget: MemoryBudget) -> AutomatonWeight<A> {
+        self.memory_budget = memory_budget;
+        self
+    }
+
+    /// Charges the terms matched in each segment to the given expansion budget.
+    pub fn with_expansion_budget(
+        mut self,
+        expansion_budget: ExpansionBudget,
+    ) -> AutomatonWeight<A> {
+        self.expansion_budget = expansion_budget;
+        self
+    }
+
+    /// Makes the weight fail if the automaton matches more than `max_num_terms`
+    /// terms in a segment.
+    pub fn with_max_num_terms(mut self, max_num_terms: usize) -> AutomatonWeight<A> {
+        self.max_num_terms = Some(max_num_terms);
+        self
+    }
+
+    fn check_num_terms(&self, num_terms: usize) -> crate::Result<()> {
+        match self.max_num_terms {
+            Some(max_num_terms) if num_terms > max_num_terms => Err(TantivyError::InvalidArgument(
+                format!("The query matches more than {} terms", max_num_terms),
+            )),
+            _ => Ok(()),
         }
     }
 
@@ -38,6 +84,50 @@ where
         let term_stream_builder = term_dict.search(automaton);
         term_stream_builder.into_stream()
     }
+
+    /// Rewrites the automaton query into a constant score union of the terms it matches,
+    /// provided it matches at most `TERM_UNION_MAX_NUM_TERMS` terms in the
+    /// segments of the searcher.
+    ///
+    /// Returns `None` if the automaton matches more terms, or if the terms do not fit
+    /// in the degrading expansion budget of the searcher.
+    pub(crate) fn rewrite_into_term_union(
+        &self,
+        searcher: &Searcher,
+    ) -> crate::Result<Option<Box<dyn Query>>> {
+        let mut terms: BTreeSet<Vec<u8>> = BTreeSet::new();
+        for segment_reader in searcher.segment_readers() {
+            let inverted_index = segment_reader.inverted_index(self.field)?;
+            let mut term_stream = self.automaton_stream(inverted_index.terms())?;
+            let mut num_terms_in_segment = 0;
+            while term_stream.advance() {
+                num_terms_in_segment += 1;
+                self.check_num_terms(num_terms_in_segment)?;
+                if terms.contains(term_stream.key()) {
+                    continue;
+                }
+                if terms.len() == TERM_UNION_MAX_NUM_TERMS {
+                    return Ok(None);
+                }
+                terms.insert(term_stream.key().to_vec());
+            }
+        }
+        if terms.is_empty() {
+            return Ok(Some(Box::new(EmptyQuery)));
+        }
+        if !searcher.expansion_budget().expand(terms.len())? {
+            return Ok(None);
+        }
+        let term_queries: Vec<Box<dyn Query>> = terms
+            .iter()
+            .map(|term_bytes| {
+                let term = Term::from_field_bytes(self.field, term_bytes);
+                Box::new(TermQuery::new(term, IndexRecordOption::Basic)) as Box<dyn Query>
+            })
+            .collect();
+        let term_union = BooleanQuery::union(term_queries);
+        Ok(Some(Box::new(ConstScoreQuery::new(Box::new(term_union)))))
+    }
 }
 
 impl<A> Weight for AutomatonWeight<A>
@@ -47,11 +137,17 @@ where
 {
     fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
         let max_doc = reader.max_doc();
+        self.memory_budget.allocate(BitSet::memory_usage(max_doc))?;
         let mut doc_bitset = BitSet::with_max_value(max_doc);
         let inverted_index = reader.inverted_index(self.field)?;
         let term_dict = inverted_index.terms();
         let mut term_stream = self.automaton_stream(term_dict)?;
+        let mut num_terms = 0;
         while term_stream.advance() {
+            num_terms += 1;
+            self.check_num_terms(num_terms)?;
+            // Past a degrading budget, the terms keep filling the bitset.
+            self.expansion_budget.expand(1)?;
             let term_info = term_stream.value();
             let mut block_segment_postings = inverted_index
                 .read_block_postings_from_terminfo(term_info, IndexRecordOption::Basic)?;
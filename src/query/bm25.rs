@@ -1,5 +1,7 @@
 use crate::fieldnorm::FieldNormReader;
 use crate::query::Explanation;
+use crate::schema::FieldNormEncoding;
+use crate::DocId;
 use crate::Score;
 use crate::Searcher;
 use crate::Term;
@@ -63,17 +65,22 @@ impl Bm25Weight {
             );
         }
 
-        let mut total_num_tokens = 0u64;
-        let mut total_num_docs = 0u64;
-        for segment_reader in searcher.segment_readers() {
-            let inverted_index = segment_reader.inverted_index(field)?;
-            total_num_tokens += inverted_index.total_num_tokens();
-            total_num_docs += u64::from(segment_reader.max_doc());
-        }
-        let average_fieldnorm = total_num_tokens as Score / total_num_docs as Score;
+        let (total_num_docs, total_num_tokens) = searcher.scoring_field_stats(field)?;
+        let fieldnorm_encoding = searcher
+            .schema()
+            .get_field_entry(field)
+            .field_type()
+            .get_fieldnorm_encoding();
+        let average_fieldnorm = if fieldnorm_encoding == Some(FieldNormEncoding::Disabled) {
+            // All of the documents are considered to have a length of 1.
+            1.0
+        } else {
+            total_num_tokens as Score / total_num_docs as Score
+        };
 
         if terms.len() == 1 {
-            let term_doc_freq = searcher.doc_freq(&terms[0])?;
+            // Statistics supplied from the outside may not be consistent.
+            let term_doc_freq = searcher.scoring_doc_freq(&terms[0])?.min(total_num_docs);
             Ok(Bm25Weight::for_one_term(
                 term_doc_freq,
                 total_num_docs,
@@ -82,7 +89,7 @@ impl Bm25Weight {
         } else {
             let mut idf_sum: Score = 0.0;
             for term in terms {
-                let term_doc_freq = searcher.doc_freq(term)?;
+                let term_doc_freq = searcher.scoring_doc_freq(term)?.min(total_num_docs);
                 idf_sum += idf(term_doc_freq, total_num_docs);
             }
             let idf_explain = Explanation::new("idf", idf_sum);
@@ -121,6 +128,23 @@ impl Bm25Weight {
         self.weight * self.tf_factor(fieldnorm_id, term_freq)
     }
 
+    /// Scores a document, using its exact fieldnorm if the field records it.
+    #[inline]
+    pub fn score_doc(
+        &self,
+        fieldnorm_reader: &FieldNormReader,
+        doc: DocId,
+        term_freq: u32,
+    ) -> Score {
+        if let Some(fieldnorm) = fieldnorm_reader.exact_fieldnorm(doc) {
+            let term_freq = term_freq as Score;
+            let norm = cached_tf_component(fieldnorm, self.average_fieldnorm);
+            self.weight * term_freq / (term_freq + norm)
+        } else {
+            self.score(fieldnorm_reader.fieldnorm_id(doc), term_freq)
+        }
+    }
+
     pub fn max_score(&self) -> Score {
         self.score(255u8, 2_013_265_944)
     }
@@ -132,14 +156,13 @@ impl Bm25Weight {
         term_freq / (term_freq + norm)
     }
 
-    pub fn explain(&self, fieldnorm_id: u8, term_freq: u32) -> Explanation {
+    pub fn explain(&self, fieldnorm: u32, term_freq: u32) -> Explanation {
         // The explain format is directly copied from Lucene's.
         // (So, Kudos to Lucene)
-        let score = self.score(fieldnorm_id, term_freq);
-
-        let norm = self.cache[fieldnorm_id as usize];
+        let norm = cached_tf_component(fieldnorm, self.average_fieldnorm);
         let term_freq = term_freq as Score;
         let right_factor = term_freq / (term_freq + norm);
+        let score = self.weight * right_factor;
 
         let mut tf_explanation = Explanation::new(
             "freq / (freq + k1 * (1 - b + b * dl / avgdl))",
@@ -149,10 +172,7 @@ impl Bm25Weight {
         tf_explanation.add_const("freq, occurrences of term within document", term_freq);
         tf_explanation.add_const("k1, term saturation parameter", K1);
         tf_explanation.add_const("b, length normalization parameter", B);
-        tf_explanation.add_const(
-            "dl, length of field",
-            FieldNormReader::id_to_fieldnorm(fieldnorm_id) as Score,
-        );
+        tf_explanation.add_const("dl, length of field", fieldnorm as Score);
         tf_explanation.add_const("avgdl, average length of field", self.average_fieldnorm);
 
         let mut explanation = Explanation::new("TermQuery, product of...", score);
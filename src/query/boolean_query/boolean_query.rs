@@ -1,8 +1,10 @@
 use super::boolean_weight::BooleanWeight;
+use crate::query::rewrite::try_rewrite_query;
 use crate::query::Occur;
 use crate::query::Query;
 use crate::query::TermQuery;
 use crate::query::Weight;
+use crate::query::{AllQuery, EmptyQuery};
 use crate::schema::IndexRecordOption;
 use crate::schema::Term;
 use crate::Searcher;
@@ -159,6 +161,77 @@ impl Query for BooleanQuery {
         Ok(Box::new(BooleanWeight::new(sub_weights, scoring_enabled)))
     }
 
+    fn rewrite(
+        &self,
+        searcher: &Searcher,
+        scoring_enabled: bool,
+    ) -> crate::Result<Option<Box<dyn Query>>> {
+        let mut is_rewritten = false;
+        let mut has_match_all_filter = false;
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = Vec::with_capacity(self.subqueries.len());
+        for (occur, subquery) in &self.subqueries {
+            let occur = *occur;
+            let subquery_scoring_enabled = scoring_enabled && occur != Occur::MustNot;
+            let rewritten_subquery_opt =
+                try_rewrite_query(subquery.as_ref(), searcher, subquery_scoring_enabled)?;
+            is_rewritten |= rewritten_subquery_opt.is_some();
+            let subquery = rewritten_subquery_opt.unwrap_or_else(|| subquery.box_clone());
+            if subquery.is::<EmptyQuery>() {
+                if occur == Occur::Must {
+                    return Ok(Some(Box::new(EmptyQuery)));
+                }
+                is_rewritten = true;
+                continue;
+            }
+            if subquery.is::<AllQuery>() {
+                if occur == Occur::MustNot {
+                    return Ok(Some(Box::new(EmptyQuery)));
+                }
+                // Without scoring, a required clause matching all documents has no effect.
+                if occur == Occur::Must && !scoring_enabled {
+                    has_match_all_filter = true;
+                    is_rewritten = true;
+                    continue;
+                }
+            }
+            match subquery.downcast::<BooleanQuery>() {
+                Ok(boolean_query) if boolean_query.can_be_flattened_into(occur) => {
+                    for (sub_occur, sub_subquery) in boolean_query.subqueries {
+                        let sub_occur = if occur == Occur::Must {
+                            sub_occur
+                        } else {
+                            occur
+                        };
+                        clauses.push((sub_occur, sub_subquery));
+                    }
+                    is_rewritten = true;
+                }
+                Ok(boolean_query) => {
+                    clauses.push((occur, boolean_query));
+                }
+                Err(subquery) => {
+                    clauses.push((occur, subquery));
+                }
+            }
+        }
+        let has_positive_clause = clauses.iter().any(|(occur, _)| *occur != Occur::MustNot);
+        if !has_positive_clause {
+            if !has_match_all_filter {
+                // A boolean query without any positive clause does not match any document.
+                return Ok(Some(Box::new(EmptyQuery)));
+            }
+            clauses.push((Occur::Must, Box::new(AllQuery)));
+        }
+        if clauses.len() == 1 {
+            let (_occur, subquery) = clauses.pop().unwrap();
+            return Ok(Some(subquery));
+        }
+        if !is_rewritten {
+            return Ok(None);
+        }
+        Ok(Some(Box::new(BooleanQuery::new(clauses))))
+    }
+
     fn query_terms(&self, terms: &mut BTreeMap<Term, bool>) {
         for (_occur, subquery) in &self.subqueries {
             subquery.query_terms(terms);
@@ -202,6 +275,30 @@ impl BooleanQuery {
     pub fn clauses(&self) -> &[(Occur, Box<dyn Query>)] {
         &self.subqueries[..]
     }
+
+    // Returns true if the clauses of the query can be inlined in a parent boolean query,
+    // in which the query has the `occur` occurence.
+    //
+    // - `+(+a -b)` is equivalent to `+a -b`.
+    // - `(a b)` is equivalent to `a b`.
+    // - `-(a b)` is equivalent to `-a -b`.
+    fn can_be_flattened_into(&self, occur: Occur) -> bool {
+        match occur {
+            Occur::Must => {
+                self.subqueries
+                    .iter()
+                    .all(|(sub_occur, _)| *sub_occur != Occur::Should)
+                    && self
+                        .subqueries
+                        .iter()
+                        .any(|(sub_occur, _)| *sub_occur == Occur::Must)
+            }
+            Occur::Should | Occur::MustNot => self
+                .subqueries
+                .iter()
+                .all(|(sub_occur, _)| *sub_occur == Occur::Should),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -96,6 +96,9 @@ impl BooleanWeight {
         reader: &SegmentReader,
         boost: Score,
     ) -> crate::Result<SpecializedScorer> {
+        if !self.may_match_segment(reader) {
+            return Ok(SpecializedScorer::Other(Box::new(EmptyScorer)));
+        }
         let mut per_occur_scorers = self.per_occur_scorers(reader, boost)?;
 
         let should_scorer_opt: Option<SpecializedScorer> = per_occur_scorers
@@ -107,6 +110,7 @@ impl BooleanWeight {
             .map(scorer_union::<DoNothingCombiner>)
             .map(into_box_scorer::<DoNothingCombiner>);
 
+        // The required clauses are intersected by increasing cost.
         let must_scorer_opt: Option<Box<dyn Scorer>> = per_occur_scorers
             .remove(&Occur::Must)
             .map(intersect_scorers);
@@ -188,6 +192,26 @@ impl Weight for BooleanWeight {
         Ok(explanation)
     }
 
+    fn may_match_segment(&self, reader: &SegmentReader) -> bool {
+        let mut has_must_clause = false;
+        let mut should_may_match = false;
+        for &(occur, ref weight) in &self.weights {
+            match occur {
+                Occur::Must => {
+                    if !weight.may_match_segment(reader) {
+                        return false;
+                    }
+                    has_must_clause = true;
+                }
+                Occur::Should => {
+                    should_may_match = should_may_match || weight.may_match_segment(reader);
+                }
+                Occur::MustNot => {}
+            }
+        }
+        has_must_clause || should_may_match
+    }
+
     fn for_each(
         &self,
         reader: &SegmentReader,
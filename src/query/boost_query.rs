@@ -1,6 +1,7 @@
 use crate::fastfield::DeleteBitSet;
 use crate::query::explanation::does_not_match;
-use crate::query::{Explanation, Query, Scorer, Weight};
+use crate::query::rewrite::try_rewrite_query;
+use crate::query::{EmptyQuery, Explanation, Query, Scorer, Weight};
 use crate::{DocId, DocSet, Score, Searcher, SegmentReader, Term};
 use std::collections::BTreeMap;
 use std::fmt;
@@ -48,6 +49,30 @@ impl Query for BoostQuery {
         Ok(boosted_weight)
     }
 
+    fn rewrite(
+        &self,
+        searcher: &Searcher,
+        scoring_enabled: bool,
+    ) -> crate::Result<Option<Box<dyn Query>>> {
+        let rewritten_query_opt =
+            try_rewrite_query(self.query.as_ref(), searcher, scoring_enabled)?;
+        let query: &dyn Query = rewritten_query_opt
+            .as_deref()
+            .unwrap_or_else(|| self.query.as_ref());
+        if !scoring_enabled || query.is::<EmptyQuery>() {
+            return Ok(Some(query.box_clone()));
+        }
+        if let Some(boost_query) = query.downcast_ref::<BoostQuery>() {
+            let boost = self.boost * boost_query.boost;
+            return Ok(Some(Box::new(BoostQuery::new(
+                boost_query.query.box_clone(),
+                boost,
+            ))));
+        }
+        Ok(rewritten_query_opt
+            .map(|query| Box::new(BoostQuery::new(query, self.boost)) as Box<dyn Query>))
+    }
+
     fn query_terms(&self, terms: &mut BTreeMap<Term, bool>) {
         self.query.query_terms(terms)
     }
@@ -81,6 +106,10 @@ impl Weight for BoostWeight {
         Ok(explanation)
     }
 
+    fn may_match_segment(&self, reader: &SegmentReader) -> bool {
+        self.weight.may_match_segment(reader)
+    }
+
     fn count(&self, reader: &SegmentReader) -> crate::Result<u32> {
         self.weight.count(reader)
     }
@@ -118,6 +147,10 @@ impl<S: Scorer> DocSet for BoostScorer<S> {
         self.underlying.size_hint()
     }
 
+    fn cost(&self) -> u64 {
+        self.underlying.cost()
+    }
+
     fn count(&mut self, delete_bitset: &DeleteBitSet) -> u32 {
         self.underlying.count(delete_bitset)
     }
@@ -0,0 +1,137 @@
+use crate::query::explanation::does_not_match;
+use crate::query::rewrite::try_rewrite_query;
+use crate::query::{ConstScorer, EmptyQuery, Explanation, Query, Scorer, Weight};
+use crate::{DocId, DocSet, Score, Searcher, SegmentReader, Term};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// `ConstScoreQuery` matches the same documents as the underlying query,
+/// and gives all of them the same score.
+///
+/// The underlying query is executed with scoring disabled.
+/// The score of each document is the boost applied to the `ConstScoreQuery`, which
+/// is `1.0` unless the query is wrapped in a `BoostQuery`.
+pub struct ConstScoreQuery {
+    query: Box<dyn Query>,
+}
+
+impl ConstScoreQuery {
+    /// Builds a constant score query.
+    pub fn new(query: Box<dyn Query>) -> ConstScoreQuery {
+        ConstScoreQuery { query }
+    }
+
+    /// Returns the underlying query.
+    pub fn query(&self) -> &dyn Query {
+        self.query.as_ref()
+    }
+}
+
+impl Clone for ConstScoreQuery {
+    fn clone(&self) -> Self {
+        ConstScoreQuery {
+            query: self.query.box_clone(),
+        }
+    }
+}
+
+impl fmt::Debug for ConstScoreQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ConstScore(query={:?})", self.query)
+    }
+}
+
+impl Query for ConstScoreQuery {
+    fn weight(&self, searcher: &Searcher, scoring_enabled: bool) -> crate::Result<Box<dyn Weight>> {
+        let weight = self.query.weight(searcher, false)?;
+        if scoring_enabled {
+            Ok(Box::new(ConstScoreWeight { weight }))
+        } else {
+            Ok(weight)
+        }
+    }
+
+    fn rewrite(
+        &self,
+        searcher: &Searcher,
+        scoring_enabled: bool,
+    ) -> crate::Result<Option<Box<dyn Query>>> {
+        let rewritten_query_opt = try_rewrite_query(self.query.as_ref(), searcher, false)?;
+        let query: &dyn Query = rewritten_query_opt
+            .as_deref()
+            .unwrap_or_else(|| self.query.as_ref());
+        if !scoring_enabled || query.is::<EmptyQuery>() || query.is::<ConstScoreQuery>() {
+            return Ok(Some(query.box_clone()));
+        }
+        Ok(
+            rewritten_query_opt
+                .map(|query| Box::new(ConstScoreQuery::new(query)) as Box<dyn Query>),
+        )
+    }
+
+    fn query_terms(&self, terms: &mut BTreeMap<Term, bool>) {
+        self.query.query_terms(terms)
+    }
+}
+
+struct ConstScoreWeight {
+    weight: Box<dyn Weight>,
+}
+
+impl Weight for ConstScoreWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
+        let scorer = self.weight.scorer(reader, 1.0)?;
+        Ok(Box::new(ConstScorer::new(scorer, boost)))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(does_not_match(doc));
+        }
+        Ok(Explanation::new("ConstScoreQuery", scorer.score()))
+    }
+
+    fn may_match_segment(&self, reader: &SegmentReader) -> bool {
+        self.weight.may_match_segment(reader)
+    }
+
+    fn count(&self, reader: &SegmentReader) -> crate::Result<u32> {
+        self.weight.count(reader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConstScoreQuery;
+    use crate::collector::TopDocs;
+    use crate::query::{BoostQuery, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, TEXT};
+    use crate::{Index, Term};
+
+    #[test]
+    fn test_const_score_query() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field => "a a a b"));
+        index_writer.add_document(doc!(text_field => "a c"));
+        index_writer.add_document(doc!(text_field => "c"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let term_query = TermQuery::new(
+            Term::from_field_text(text_field, "a"),
+            IndexRecordOption::WithFreqs,
+        );
+        let const_score_query = ConstScoreQuery::new(Box::new(term_query));
+        let top_docs = searcher.search(&const_score_query, &TopDocs::with_limit(10))?;
+        assert_eq!(top_docs.len(), 2);
+        assert!(top_docs.iter().all(|(score, _)| *score == 1.0));
+        let boost_query = BoostQuery::new(Box::new(const_score_query), 2.0);
+        let top_docs = searcher.search(&boost_query, &TopDocs::with_limit(10))?;
+        assert_eq!(top_docs.len(), 2);
+        assert!(top_docs.iter().all(|(score, _)| *score == 2.0));
+        Ok(())
+    }
+}
@@ -0,0 +1,333 @@
+use crate::docset::{DocSet, TERMINATED};
+use crate::query::explanation::does_not_match;
+use crate::query::{EmptyScorer, Explanation, Query, Scorer, Weight};
+use crate::{DocId, Score, Searcher, SegmentReader, Term};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// `DisjunctionMaxQuery` matches the documents matched by any of its subqueries (the disjuncts).
+///
+/// Unlike a `BooleanQuery` of `Should` clauses, which sums the scores of its clauses,
+/// the score of a document is the maximum of the scores of the disjuncts matching it,
+/// plus `tie_breaker` times the scores of the other matching disjuncts.
+///
+/// This is typically useful to search the same terms in several fields,
+/// without favoring the documents matching the terms in many fields over
+/// the documents matching all of the terms in a single field.
+///
+/// ```rust
+/// use tantivy::collector::Count;
+/// use tantivy::query::{DisjunctionMaxQuery, Query, TermQuery};
+/// use tantivy::schema::{IndexRecordOption, Schema, TEXT};
+/// use tantivy::{doc, Index, Term};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let body = schema_builder.add_text_field("body", TEXT);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// index_writer.add_document(doc!(title => "The diary of Muadib", body => "a diary"));
+/// index_writer.add_document(doc!(title => "A Dairy Cow", body => "a cow"));
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let disjuncts: Vec<Box<dyn Query>> = vec![
+///     Box::new(TermQuery::new(Term::from_field_text(title, "diary"), IndexRecordOption::Basic)),
+///     Box::new(TermQuery::new(Term::from_field_text(body, "diary"), IndexRecordOption::Basic)),
+/// ];
+/// let query = DisjunctionMaxQuery::with_tie_breaker(disjuncts, 0.1);
+/// assert_eq!(searcher.search(&query, &Count)?, 1);
+/// # Ok(())
+/// # }
+/// ```
+pub struct DisjunctionMaxQuery {
+    disjuncts: Vec<Box<dyn Query>>,
+    tie_breaker: Score,
+}
+
+impl Clone for DisjunctionMaxQuery {
+    fn clone(&self) -> Self {
+        DisjunctionMaxQuery {
+            disjuncts: self
+                .disjuncts
+                .iter()
+                .map(|disjunct| disjunct.box_clone())
+                .collect(),
+            tie_breaker: self.tie_breaker,
+        }
+    }
+}
+
+impl fmt::Debug for DisjunctionMaxQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "DisjunctionMaxQuery(disjuncts={:?}, tie_breaker={})",
+            self.disjuncts, self.tie_breaker
+        )
+    }
+}
+
+impl DisjunctionMaxQuery {
+    /// Creates a `DisjunctionMaxQuery` scoring the documents with the maximum score of
+    /// their matching disjuncts only.
+    pub fn new(disjuncts: Vec<Box<dyn Query>>) -> DisjunctionMaxQuery {
+        DisjunctionMaxQuery::with_tie_breaker(disjuncts, 0.0)
+    }
+
+    /// Creates a `DisjunctionMaxQuery` adding `tie_breaker` times the scores of the
+    /// matching disjuncts that are not the maximum to the score of the documents.
+    pub fn with_tie_breaker(
+        disjuncts: Vec<Box<dyn Query>>,
+        tie_breaker: Score,
+    ) -> DisjunctionMaxQuery {
+        DisjunctionMaxQuery {
+            disjuncts,
+            tie_breaker,
+        }
+    }
+
+    /// Returns the disjuncts of the query.
+    pub fn disjuncts(&self) -> &[Box<dyn Query>] {
+        &self.disjuncts
+    }
+
+    /// Returns the tie breaker of the query.
+    pub fn tie_breaker(&self) -> Score {
+        self.tie_breaker
+    }
+}
+
+impl Query for DisjunctionMaxQuery {
+    fn weight(&self, searcher: &Searcher, scoring_enabled: bool) -> crate::Result<Box<dyn Weight>> {
+        let weights = self
+            .disjuncts
+            .iter()
+            .map(|disjunct| disjunct.weight(searcher, scoring_enabled))
+            .collect::<crate::Result<Vec<_>>>()?;
+        Ok(Box::new(DisjunctionMaxWeight {
+            weights,
+            tie_breaker: self.tie_breaker,
+        }))
+    }
+
+    fn query_terms(&self, terms: &mut BTreeMap<Term, bool>) {
+        for disjunct in &self.disjuncts {
+            disjunct.query_terms(terms);
+        }
+    }
+}
+
+struct DisjunctionMaxWeight {
+    weights: Vec<Box<dyn Weight>>,
+    tie_breaker: Score,
+}
+
+impl Weight for DisjunctionMaxWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
+        let mut scorers = Vec::with_capacity(self.weights.len());
+        for weight in &self.weights {
+            if weight.may_match_segment(reader) {
+                scorers.push(weight.scorer(reader, boost)?);
+            }
+        }
+        match scorers.len() {
+            0 => Ok(Box::new(EmptyScorer)),
+            1 => Ok(scorers.pop().unwrap()),
+            _ => Ok(Box::new(DisjunctionMaxScorer::new(
+                scorers,
+                self.tie_breaker,
+            ))),
+        }
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(does_not_match(doc));
+        }
+        let mut explanation = Explanation::new(
+            format!(
+                "DisjunctionMax (max + {} x others) of ...",
+                self.tie_breaker
+            ),
+            scorer.score(),
+        );
+        for weight in &self.weights {
+            if let Ok(disjunct_explanation) = weight.explain(reader, doc) {
+                explanation.add_detail(disjunct_explanation);
+            }
+        }
+        Ok(explanation)
+    }
+
+    fn may_match_segment(&self, reader: &SegmentReader) -> bool {
+        self.weights
+            .iter()
+            .any(|weight| weight.may_match_segment(reader))
+    }
+}
+
+/// Scorer matching the documents of any of its scorers, and scoring them with the
+/// maximum of their scores plus `tie_breaker` times the other scores.
+struct DisjunctionMaxScorer {
+    scorers: Vec<Box<dyn Scorer>>,
+    tie_breaker: Score,
+    doc: DocId,
+}
+
+impl DisjunctionMaxScorer {
+    fn new(scorers: Vec<Box<dyn Scorer>>, tie_breaker: Score) -> DisjunctionMaxScorer {
+        let mut disjunction_max_scorer = DisjunctionMaxScorer {
+            scorers,
+            tie_breaker,
+            doc: TERMINATED,
+        };
+        disjunction_max_scorer.refresh_doc();
+        disjunction_max_scorer
+    }
+
+    // Drops the terminated scorers, and positions the scorer on the smallest
+    // doc of the remaining ones.
+    fn refresh_doc(&mut self) {
+        self.scorers.retain(|scorer| scorer.doc() != TERMINATED);
+        self.doc = self
+            .scorers
+            .iter()
+            .map(|scorer| scorer.doc())
+            .min()
+            .unwrap_or(TERMINATED);
+    }
+}
+
+impl DocSet for DisjunctionMaxScorer {
+    fn advance(&mut self) -> DocId {
+        if self.doc == TERMINATED {
+            return TERMINATED;
+        }
+        for scorer in &mut self.scorers {
+            if scorer.doc() == self.doc {
+                scorer.advance();
+            }
+        }
+        self.refresh_doc();
+        self.doc
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        if self.doc >= target {
+            return self.doc;
+        }
+        for scorer in &mut self.scorers {
+            if scorer.doc() < target {
+                scorer.seek(target);
+            }
+        }
+        self.refresh_doc();
+        self.doc
+    }
+
+    fn doc(&self) -> DocId {
+        self.doc
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.scorers
+            .iter()
+            .map(|scorer| scorer.size_hint())
+            .max()
+            .unwrap_or(0u32)
+    }
+
+    fn cost(&self) -> u64 {
+        self.scorers.iter().map(|scorer| scorer.cost()).sum()
+    }
+}
+
+impl Scorer for DisjunctionMaxScorer {
+    fn score(&mut self) -> Score {
+        let mut max_score: Score = 0.0;
+        let mut sum_score: Score = 0.0;
+        for scorer in &mut self.scorers {
+            if scorer.doc() == self.doc {
+                let score = scorer.score();
+                sum_score += score;
+                max_score = max_score.max(score);
+            }
+        }
+        max_score + self.tie_breaker * (sum_score - max_score)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DisjunctionMaxQuery, DisjunctionMaxScorer};
+    use crate::collector::TopDocs;
+    use crate::docset::{DocSet, TERMINATED};
+    use crate::query::{ConstScorer, Query, Scorer, TermQuery, VecDocSet};
+    use crate::schema::{IndexRecordOption, Schema, TEXT};
+    use crate::{DocAddress, Index, Term};
+
+    #[test]
+    fn test_disjunction_max_scorer() {
+        let scorers: Vec<Box<dyn Scorer>> = vec![
+            Box::new(ConstScorer::new(VecDocSet::from(vec![1, 3, 5]), 1.0)),
+            Box::new(ConstScorer::new(VecDocSet::from(vec![3, 4]), 2.0)),
+            Box::new(ConstScorer::new(VecDocSet::from(vec![3, 5, 8]), 4.0)),
+        ];
+        let mut scorer = DisjunctionMaxScorer::new(scorers, 0.5);
+        let mut matched = Vec::new();
+        while scorer.doc() != TERMINATED {
+            matched.push((scorer.doc(), scorer.score()));
+            scorer.advance();
+        }
+        assert_eq!(
+            matched,
+            vec![(1, 1.0), (3, 5.5), (4, 2.0), (5, 4.5), (8, 4.0)]
+        );
+    }
+
+    #[test]
+    fn test_disjunction_max_query() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let body = schema_builder.add_text_field("body", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(title => "a b", body => "a b"));
+        index_writer.add_document(doc!(title => "c", body => "a"));
+        index_writer.add_document(doc!(title => "c", body => "d"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let term_query = |field, text| -> Box<dyn Query> {
+            Box::new(TermQuery::new(
+                Term::from_field_text(field, text),
+                IndexRecordOption::WithFreqs,
+            ))
+        };
+        let title_score = searcher.search(&*term_query(title, "a"), &TopDocs::with_limit(1))?[0].0;
+        let body_scores = searcher.search(&*term_query(body, "a"), &TopDocs::with_limit(2))?;
+        let body_score = body_scores
+            .iter()
+            .find(|(_, doc_address)| *doc_address == DocAddress::new(0, 0))
+            .unwrap()
+            .0;
+        let query = DisjunctionMaxQuery::with_tie_breaker(
+            vec![term_query(title, "a"), term_query(body, "a")],
+            0.5,
+        );
+        let top_docs = searcher.search(&query, &TopDocs::with_limit(3))?;
+        assert_eq!(top_docs.len(), 2);
+        let doc_0_score = top_docs
+            .iter()
+            .find(|(_, doc_address)| *doc_address == DocAddress::new(0, 0))
+            .unwrap()
+            .0;
+        let expected_score = title_score.max(body_score) + 0.5 * title_score.min(body_score);
+        assert!((doc_0_score - expected_score).abs() < 1e-5);
+        let explanation = query.explain(&searcher, DocAddress::new(0, 0))?;
+        assert!((explanation.value() - expected_score).abs() < 1e-5);
+        Ok(())
+    }
+}
@@ -41,6 +41,10 @@ impl Weight for EmptyWeight {
     fn explain(&self, _reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
         Err(does_not_match(doc))
     }
+
+    fn may_match_segment(&self, _reader: &SegmentReader) -> bool {
+        false
+    }
 }
 
 /// `EmptyScorer` is a dummy `Scorer` in which no document matches.
@@ -78,6 +78,16 @@ where
     fn size_hint(&self) -> u32 {
         self.underlying_docset.size_hint()
     }
+
+    /// The excluding docset is only sought to the documents
+    /// of the underlying docset.
+    fn cost(&self) -> u64 {
+        self.underlying_docset.cost()
+            + self
+                .excluding_docset
+                .cost()
+                .min(self.underlying_docset.size_hint() as u64)
+    }
 }
 
 impl<TScorer, TDocSetExclude> Scorer for Exclude<TScorer, TDocSetExclude>
@@ -0,0 +1,395 @@
+use crate::core::Searcher;
+use crate::core::SegmentReader;
+use crate::docset::DocSet;
+use crate::postings::{Postings, SegmentPostings};
+use crate::query::explanation::does_not_match;
+use crate::query::score_combiner::SumCombiner;
+use crate::query::{Explanation, Query, Scorer, Union, Weight};
+use crate::schema::{Field, IndexRecordOption, Term};
+use crate::tokenizer::term_freq_to_feature_weight;
+use crate::TantivyError;
+use crate::{DocId, Score};
+use std::collections::BTreeMap;
+
+/// Function applied to the weight of a feature stored in a document
+/// before it gets multiplied by the query weight of this feature.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FeatureFunction {
+    /// The document feature weight is used as is.
+    ///
+    /// Summed over all of the query features, this computes the dot product
+    /// of the query and the document sparse vectors.
+    Linear,
+    /// `weight / (weight + pivot)`.
+    ///
+    /// The resulting value lies within `[0, 1)` and is equal to `0.5` when
+    /// the weight is equal to `pivot`.
+    Saturation {
+        /// Weight for which the function returns `0.5`.
+        pivot: Score,
+    },
+    /// `ln(scaling_factor + weight)`.
+    ///
+    /// `scaling_factor` is expected to be greater or equal to `1`, so that
+    /// the function returns positive values.
+    Log {
+        /// Constant added to the weight before taking the logarithm.
+        scaling_factor: Score,
+    },
+}
+
+impl Default for FeatureFunction {
+    fn default() -> FeatureFunction {
+        FeatureFunction::Linear
+    }
+}
+
+impl FeatureFunction {
+    fn apply(self, weight: Score) -> Score {
+        match self {
+            FeatureFunction::Linear => weight,
+            FeatureFunction::Saturation { pivot } => weight / (weight + pivot),
+            FeatureFunction::Log { scaling_factor } => (scaling_factor + weight).ln(),
+        }
+    }
+}
+
+/// The `FeatureQuery` scores documents by the sum, over a set of features,
+/// of the query weight of the feature multiplied by the weight of this feature in the document
+/// (possibly transformed by a [`FeatureFunction`](./enum.FeatureFunction.html)).
+///
+/// With a `Linear` function, this is the dot product between the sparse vector
+/// of the query and the sparse vector of the document, as used by learned sparse models.
+///
+/// Features are indexed in a text field using the `feature` tokenizer, which encodes
+/// the weight of each feature in its term frequency.
+/// The field needs to be indexed with `IndexRecordOption::WithFreqs`.
+///
+/// Documents matching none of the features are not returned.
+///
+/// ```rust
+/// use tantivy::collector::TopDocs;
+/// use tantivy::query::{FeatureFunction, FeatureQuery};
+/// use tantivy::schema::{IndexRecordOption, Schema, TextFieldIndexing, TextOptions};
+/// use tantivy::{doc, DocAddress, Index};
+/// # fn test() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let feature_options = TextOptions::default().set_indexing_options(
+///     TextFieldIndexing::default()
+///         .set_tokenizer("feature")
+///         .set_index_option(IndexRecordOption::WithFreqs),
+/// );
+/// let features = schema_builder.add_text_field("features", feature_options);
+/// let schema = schema_builder.build();
+///
+/// let index = Index::create_in_ram(schema);
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// index_writer.add_document(doc!(features => "dog:2.0 cat:0.5"));
+/// index_writer.add_document(doc!(features => "dog:0.5 cat:1.0"));
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+/// let query = FeatureQuery::new(
+///     features,
+///     vec![("dog".to_string(), 1.0), ("cat".to_string(), 2.0)],
+///     FeatureFunction::Linear,
+/// );
+/// let top_docs = searcher.search(&query, &TopDocs::with_limit(2))?;
+/// assert_eq!(top_docs[0], (3.0, DocAddress::new(0, 0)));
+/// assert_eq!(top_docs[1], (2.5, DocAddress::new(0, 1)));
+/// Ok(())
+/// # }
+/// # assert!(test().is_ok());
+/// ```
+#[derive(Clone, Debug)]
+pub struct FeatureQuery {
+    field: Field,
+    features: Vec<(String, Score)>,
+    function: FeatureFunction,
+}
+
+impl FeatureQuery {
+    /// Creates a new `FeatureQuery` given a field, and a list of
+    /// `(feature, query weight)` pairs.
+    pub fn new(
+        field: Field,
+        features: Vec<(String, Score)>,
+        function: FeatureFunction,
+    ) -> FeatureQuery {
+        FeatureQuery {
+            field,
+            features,
+            function,
+        }
+    }
+
+    /// Returns the field the features are searched in.
+    pub fn field(&self) -> Field {
+        self.field
+    }
+
+    /// Returns the list of `(feature, query weight)` pairs.
+    pub fn features(&self) -> &[(String, Score)] {
+        &self.features
+    }
+
+    fn feature_terms(&self) -> impl Iterator<Item = (Term, Score)> + '_ {
+        self.features
+            .iter()
+            .map(move |(feature, weight)| (Term::from_field_text(self.field, feature), *weight))
+    }
+}
+
+impl Query for FeatureQuery {
+    fn weight(
+        &self,
+        searcher: &Searcher,
+        _scoring_enabled: bool,
+    ) -> crate::Result<Box<dyn Weight>> {
+        let field_entry = searcher.schema().get_field_entry(self.field);
+        let has_freq = field_entry
+            .field_type()
+            .get_index_record_option()
+            .map(IndexRecordOption::has_freq)
+            .unwrap_or(false);
+        if !has_freq {
+            return Err(TantivyError::SchemaError(format!(
+                "Field {:?} needs to be indexed with term frequencies to be used in a \
+                 FeatureQuery",
+                field_entry.name()
+            )));
+        }
+        Ok(Box::new(FeatureWeight {
+            feature_terms: self.feature_terms().collect(),
+            function: self.function,
+        }))
+    }
+
+    fn query_terms(&self, terms: &mut BTreeMap<Term, bool>) {
+        for (term, _) in self.feature_terms() {
+            terms.insert(term, false);
+        }
+    }
+}
+
+struct FeatureWeight {
+    feature_terms: Vec<(Term, Score)>,
+    function: FeatureFunction,
+}
+
+impl FeatureWeight {
+    fn feature_scorer(
+        &self,
+        reader: &SegmentReader,
+        term: &Term,
+        query_weight: Score,
+    ) -> crate::Result<Option<FeatureScorer>> {
+        let inverted_index = reader.inverted_index(term.field())?;
+        let postings_opt = inverted_index.read_postings(term, IndexRecordOption::WithFreqs)?;
+        Ok(postings_opt.map(|postings| FeatureScorer {
+            postings,
+            query_weight,
+            function: self.function,
+        }))
+    }
+}
+
+impl Weight for FeatureWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
+        let mut scorers = Vec::with_capacity(self.feature_terms.len());
+        for (term, query_weight) in &self.feature_terms {
+            if let Some(scorer) = self.feature_scorer(reader, term, query_weight * boost)? {
+                scorers.push(scorer);
+            }
+        }
+        if scorers.len() == 1 {
+            return Ok(Box::new(scorers.pop().unwrap()));
+        }
+        Ok(Box::new(Union::<FeatureScorer, SumCombiner>::from(scorers)))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
+        let mut explanation = Explanation::new("FeatureQuery, sum of:", 0.0);
+        let mut score = 0.0;
+        let mut matched = false;
+        for (term, query_weight) in &self.feature_terms {
+            let mut scorer = match self.feature_scorer(reader, term, *query_weight)? {
+                Some(scorer) => scorer,
+                None => continue,
+            };
+            if scorer.seek(doc) != doc {
+                continue;
+            }
+            matched = true;
+            let feature_score = scorer.score();
+            score += feature_score;
+            let mut feature_explanation = Explanation::new(
+                format!("query_weight * {:?}(doc_weight)", self.function),
+                feature_score,
+            );
+            feature_explanation.add_const("query_weight", *query_weight);
+            feature_explanation.add_const("doc_weight", scorer.doc_weight());
+            feature_explanation.add_context(format!("Feature ={:?}", term.text()));
+            explanation.add_detail(feature_explanation);
+        }
+        if !matched {
+            return Err(does_not_match(doc));
+        }
+        let mut result = Explanation::new("FeatureQuery", score);
+        result.add_detail(explanation);
+        Ok(result)
+    }
+}
+
+struct FeatureScorer {
+    postings: SegmentPostings,
+    query_weight: Score,
+    function: FeatureFunction,
+}
+
+impl FeatureScorer {
+    fn doc_weight(&self) -> Score {
+        term_freq_to_feature_weight(self.postings.term_freq())
+    }
+}
+
+impl DocSet for FeatureScorer {
+    fn advance(&mut self) -> DocId {
+        self.postings.advance()
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        self.postings.seek(target)
+    }
+
+    fn doc(&self) -> DocId {
+        self.postings.doc()
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.postings.size_hint()
+    }
+
+    fn cost(&self) -> u64 {
+        self.postings.cost()
+    }
+}
+
+impl Scorer for FeatureScorer {
+    fn score(&mut self) -> Score {
+        self.query_weight * self.function.apply(self.doc_weight())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FeatureFunction, FeatureQuery};
+    use crate::collector::TopDocs;
+    use crate::query::Query;
+    use crate::schema::{IndexRecordOption, Schema, TextFieldIndexing, TextOptions, TEXT};
+    use crate::{doc, DocAddress, Index, Score};
+
+    fn feature_options() -> TextOptions {
+        TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer("feature")
+                .set_index_option(IndexRecordOption::WithFreqs),
+        )
+    }
+
+    #[test]
+    fn test_feature_query_functions() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let features = schema_builder.add_text_field("features", feature_options());
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        // Powers of two are the weights the feature tokenizer encodes exactly.
+        index_writer.add_document(doc!(features => "a:1.0 b:4.0"));
+        index_writer.add_document(doc!(features => "a:4.0"));
+        index_writer.add_document(doc!(features => "c:1.0"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let search = |function: FeatureFunction| -> crate::Result<Vec<(Score, DocAddress)>> {
+            let query = FeatureQuery::new(
+                features,
+                vec![("a".to_string(), 1.0), ("b".to_string(), 0.5)],
+                function,
+            );
+            searcher.search(&query, &TopDocs::with_limit(3))
+        };
+        assert_eq!(
+            search(FeatureFunction::Linear)?,
+            vec![(4.0, DocAddress::new(0, 1)), (3.0, DocAddress::new(0, 0))]
+        );
+        let saturation_scores = search(FeatureFunction::Saturation { pivot: 1.0 })?;
+        assert_eq!(saturation_scores.len(), 2);
+        assert_eq!(saturation_scores[0].1, DocAddress::new(0, 0));
+        assert!((saturation_scores[0].0 - 0.9).abs() < 0.0001);
+        assert_eq!(saturation_scores[1].1, DocAddress::new(0, 1));
+        assert!((saturation_scores[1].0 - 0.8).abs() < 0.0001);
+        let log_scores = search(FeatureFunction::Log {
+            scaling_factor: 1.0,
+        })?;
+        assert_eq!(log_scores.len(), 2);
+        assert!((log_scores[0].0 - 5f32.ln()).abs() < 0.0001);
+        Ok(())
+    }
+
+    #[test]
+    fn test_feature_query_repeated_feature() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let features = schema_builder.add_text_field("features", feature_options());
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        // The term frequency of the repeated feature exceeds the one of the maximum weight.
+        index_writer.add_document(doc!(features => "a:200.0 a:200.0 a:200.0"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query = FeatureQuery::new(
+            features,
+            vec![("a".to_string(), 1.0)],
+            FeatureFunction::Linear,
+        );
+        let top_docs = searcher.search(&query, &TopDocs::with_limit(1))?;
+        assert_eq!(top_docs.len(), 1);
+        assert!((top_docs[0].0 - 234.75).abs() < 0.01);
+        Ok(())
+    }
+
+    #[test]
+    fn test_feature_query_explain() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let features = schema_builder.add_text_field("features", feature_options());
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(features => "a:1.0 b:4.0"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query = FeatureQuery::new(
+            features,
+            vec![("b".to_string(), 2.0), ("c".to_string(), 1.0)],
+            FeatureFunction::Linear,
+        );
+        let explanation = query.explain(&searcher, DocAddress::new(0, 0))?;
+        assert_eq!(explanation.value(), 8.0);
+        assert!(query.explain(&searcher, DocAddress::new(0, 1)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_feature_query_requires_freqs() {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let basic = schema_builder.add_text_field(
+            "basic",
+            TextOptions::default().set_indexing_options(
+                TextFieldIndexing::default().set_index_option(IndexRecordOption::Basic),
+            ),
+        );
+        let index = Index::create_in_ram(schema_builder.build());
+        let searcher = index.reader().unwrap().searcher();
+        let query_on = |field| FeatureQuery::new(field, vec![], FeatureFunction::Linear);
+        assert!(query_on(text).weight(&searcher, true).is_ok());
+        assert!(query_on(basic).weight(&searcher, true).is_err());
+    }
+}
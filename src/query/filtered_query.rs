@@ -0,0 +1,252 @@
+use crate::common::BitSet;
+use crate::query::explanation::does_not_match;
+use crate::query::rewrite::try_rewrite_query;
+use crate::query::{EmptyQuery, Explanation, Query, Scorer, Weight};
+use crate::{DocId, DocSet, Score, Searcher, SegmentReader, Term, TERMINATED};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Provides, for each segment, the set of documents that are allowed to match.
+///
+/// A typical use case is security trimming: the allow-list of a user is computed once
+/// per segment (and possibly cached), rather than being expressed as a boolean query
+/// over thousands of terms.
+///
+/// This trait is implemented for closures.
+pub trait DocFilter: Send + Sync + 'static {
+    /// Returns the bitset of the documents allowed in the segment.
+    ///
+    /// The bitset is expected to cover the segment's `max_doc`.
+    fn filter_bitset(&self, segment_reader: &SegmentReader) -> crate::Result<Arc<BitSet>>;
+}
+
+impl<F> DocFilter for F
+where
+    F: Fn(&SegmentReader) -> crate::Result<Arc<BitSet>> + Send + Sync + 'static,
+{
+    fn filter_bitset(&self, segment_reader: &SegmentReader) -> crate::Result<Arc<BitSet>> {
+        (self)(segment_reader)
+    }
+}
+
+/// `FilteredQuery` restricts the documents matched by a query to the documents
+/// allowed by a `DocFilter`.
+///
+/// The score of each document is the score of the underlying query:
+/// the filter does not alter scoring.
+pub struct FilteredQuery {
+    query: Box<dyn Query>,
+    filter: Arc<dyn DocFilter>,
+}
+
+impl FilteredQuery {
+    /// Builds a filtered query.
+    pub fn new(query: Box<dyn Query>, filter: Arc<dyn DocFilter>) -> FilteredQuery {
+        FilteredQuery { query, filter }
+    }
+}
+
+impl Clone for FilteredQuery {
+    fn clone(&self) -> Self {
+        FilteredQuery {
+            query: self.query.box_clone(),
+            filter: self.filter.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for FilteredQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Filtered(query={:?})", self.query)
+    }
+}
+
+impl Query for FilteredQuery {
+    fn weight(&self, searcher: &Searcher, scoring_enabled: bool) -> crate::Result<Box<dyn Weight>> {
+        let weight = self.query.weight(searcher, scoring_enabled)?;
+        Ok(Box::new(FilteredWeight::new(weight, self.filter.clone())))
+    }
+
+    fn rewrite(
+        &self,
+        searcher: &Searcher,
+        scoring_enabled: bool,
+    ) -> crate::Result<Option<Box<dyn Query>>> {
+        let rewritten_query_opt =
+            try_rewrite_query(self.query.as_ref(), searcher, scoring_enabled)?;
+        Ok(rewritten_query_opt.map(|query| {
+            if query.is::<EmptyQuery>() {
+                query
+            } else {
+                Box::new(FilteredQuery::new(query, self.filter.clone())) as Box<dyn Query>
+            }
+        }))
+    }
+
+    fn query_terms(&self, terms: &mut BTreeMap<Term, bool>) {
+        self.query.query_terms(terms)
+    }
+}
+
+/// Restricts the documents matched by a `Weight` to the documents allowed by a `DocFilter`.
+pub(crate) struct FilteredWeight {
+    weight: Box<dyn Weight>,
+    filter: Arc<dyn DocFilter>,
+}
+
+impl FilteredWeight {
+    pub fn new(weight: Box<dyn Weight>, filter: Arc<dyn DocFilter>) -> FilteredWeight {
+        FilteredWeight { weight, filter }
+    }
+}
+
+impl Weight for FilteredWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
+        let filter_bitset = self.filter.filter_bitset(reader)?;
+        let scorer = self.weight.scorer(reader, boost)?;
+        Ok(Box::new(FilteredScorer::new(scorer, filter_bitset)))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
+        let filter_bitset = self.filter.filter_bitset(reader)?;
+        if doc >= filter_bitset.max_value() || !filter_bitset.contains(doc) {
+            return Err(does_not_match(doc));
+        }
+        self.weight.explain(reader, doc)
+    }
+}
+
+/// Restricts the documents of a `DocSet` to the documents of a `BitSet`,
+/// leapfrogging between the underlying docset and the bitset.
+///
+/// The score of each document is the score of the underlying scorer.
+pub struct FilteredScorer<TDocSet = Box<dyn Scorer>> {
+    scorer: TDocSet,
+    filter_bitset: Arc<BitSet>,
+    doc: DocId,
+}
+
+impl<TDocSet: DocSet> FilteredScorer<TDocSet> {
+    /// Creates a `FilteredScorer` matching the documents of `scorer`
+    /// that belong to `filter_bitset`.
+    pub fn new(scorer: TDocSet, filter_bitset: Arc<BitSet>) -> FilteredScorer<TDocSet> {
+        let mut filtered_scorer = FilteredScorer {
+            scorer,
+            filter_bitset,
+            doc: TERMINATED,
+        };
+        let doc = filtered_scorer.scorer.doc();
+        filtered_scorer.doc = filtered_scorer.align(doc);
+        filtered_scorer
+    }
+
+    // Returns the first document greater or equal to `doc` that is both
+    // matched by the scorer and allowed by the filter.
+    //
+    // `doc` is expected to be the current document of the scorer.
+    fn align(&mut self, mut doc: DocId) -> DocId {
+        while doc != TERMINATED {
+            match self.filter_bitset.first_element_greater_or_equal(doc) {
+                Some(allowed_doc) if allowed_doc == doc => {
+                    return doc;
+                }
+                Some(allowed_doc) => {
+                    doc = self.scorer.seek(allowed_doc);
+                }
+                None => {
+                    return TERMINATED;
+                }
+            }
+        }
+        TERMINATED
+    }
+}
+
+impl<TDocSet: DocSet> DocSet for FilteredScorer<TDocSet> {
+    fn advance(&mut self) -> DocId {
+        if self.doc == TERMINATED {
+            return TERMINATED;
+        }
+        let doc = self.scorer.advance();
+        self.doc = self.align(doc);
+        self.doc
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        if self.doc >= target {
+            return self.doc;
+        }
+        let doc = self.scorer.seek(target);
+        self.doc = self.align(doc);
+        self.doc
+    }
+
+    fn doc(&self) -> DocId {
+        self.doc
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.scorer.size_hint().min(self.filter_bitset.len() as u32)
+    }
+
+    fn cost(&self) -> u64 {
+        self.scorer.cost()
+    }
+}
+
+impl<TScorer: Scorer> Scorer for FilteredScorer<TScorer> {
+    fn score(&mut self) -> Score {
+        self.scorer.score()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FilteredQuery;
+    use crate::collector::Count;
+    use crate::common::BitSet;
+    use crate::query::{AllQuery, Query, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, TEXT};
+    use crate::{DocAddress, Index, SegmentReader, Term};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_filtered_query() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for i in 0..200 {
+            let text = if i % 2 == 0 { "even" } else { "odd" };
+            index_writer.add_document(doc!(text_field => text));
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        // Only allows the documents that are multiples of 3.
+        let filter = Arc::new(
+            |segment_reader: &SegmentReader| -> crate::Result<Arc<BitSet>> {
+                let mut bitset = BitSet::with_max_value(segment_reader.max_doc());
+                for doc in (0..segment_reader.max_doc()).step_by(3) {
+                    bitset.insert(doc);
+                }
+                Ok(Arc::new(bitset))
+            },
+        );
+        let all_filtered = FilteredQuery::new(Box::new(AllQuery), filter.clone());
+        assert_eq!(searcher.search(&all_filtered, &Count)?, 67);
+        let term_query = TermQuery::new(
+            Term::from_field_text(text_field, "even"),
+            IndexRecordOption::Basic,
+        );
+        let even_filtered = FilteredQuery::new(Box::new(term_query), filter);
+        assert_eq!(searcher.search(&even_filtered, &Count)?, 34);
+        assert!(even_filtered
+            .explain(&searcher, DocAddress::new(0, 6))
+            .is_ok());
+        assert!(even_filtered
+            .explain(&searcher, DocAddress::new(0, 4))
+            .is_err());
+        Ok(())
+    }
+}
@@ -153,10 +153,24 @@ impl FuzzyTermQuery {
 impl Query for FuzzyTermQuery {
     fn weight(
         &self,
-        _searcher: &Searcher,
+        searcher: &Searcher,
         _scoring_enabled: bool,
     ) -> crate::Result<Box<dyn Weight>> {
-        Ok(Box::new(self.specialized_weight()?))
+        let memory_budget = searcher.memory_budget().clone();
+        let expansion_budget = searcher.expansion_budget().clone();
+        Ok(Box::new(
+            self.specialized_weight()?
+                .with_memory_budget(memory_budget)
+                .with_expansion_budget(expansion_budget),
+        ))
+    }
+
+    fn rewrite(
+        &self,
+        searcher: &Searcher,
+        _scoring_enabled: bool,
+    ) -> crate::Result<Option<Box<dyn Query>>> {
+        self.specialized_weight()?.rewrite_into_term_union(searcher)
     }
 }
 
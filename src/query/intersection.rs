@@ -10,9 +10,12 @@ use crate::Score;
 /// The score associated to the documents is the sum of the
 /// score of the `Scorer`s given in argument.
 ///
+/// The scorers are intersected by increasing cost,
+/// so that the cheapest scorer leads the intersection.
+///
 /// For better performance, the function uses a
 /// specialized implementation if the two
-/// shortest scorers are `TermScorer`s.
+/// cheapest scorers are `TermScorer`s.
 pub fn intersect_scorers(mut scorers: Vec<Box<dyn Scorer>>) -> Box<dyn Scorer> {
     if scorers.is_empty() {
         return Box::new(EmptyScorer);
@@ -20,7 +23,7 @@ pub fn intersect_scorers(mut scorers: Vec<Box<dyn Scorer>>) -> Box<dyn Scorer> {
     if scorers.len() == 1 {
         return scorers.pop().unwrap();
     }
-    scorers.sort_by_key(|scorer| scorer.size_hint());
+    scorers.sort_by_key(|scorer| scorer.cost());
     let doc = go_to_first_doc(&mut scorers[..]);
     if doc == TERMINATED {
         return Box::new(EmptyScorer);
@@ -68,10 +71,17 @@ fn go_to_first_doc<TDocSet: DocSet>(docsets: &mut [TDocSet]) -> DocId {
 }
 
 impl<TDocSet: DocSet> Intersection<TDocSet, TDocSet> {
-    pub(crate) fn new(mut docsets: Vec<TDocSet>) -> Intersection<TDocSet, TDocSet> {
+    /// Creates the intersection of `docsets`.
+    ///
+    /// The docsets are intersected by increasing cost.
+    ///
+    /// # Panics
+    ///
+    /// Panics if less than two docsets are given.
+    pub fn new(mut docsets: Vec<TDocSet>) -> Intersection<TDocSet, TDocSet> {
         let num_docsets = docsets.len();
         assert!(num_docsets >= 2);
-        docsets.sort_by_key(|docset| docset.size_hint());
+        docsets.sort_by_key(|docset| docset.cost());
         go_to_first_doc(&mut docsets);
         let left = docsets.remove(0);
         let right = docsets.remove(0);
@@ -142,8 +152,25 @@ impl<TDocSet: DocSet, TOtherDocSet: DocSet> DocSet for Intersection<TDocSet, TOt
         self.left.doc()
     }
 
+    /// The intersection cannot be larger than the smallest of its docsets.
     fn size_hint(&self) -> u32 {
-        self.left.size_hint()
+        self.others
+            .iter()
+            .map(DocSet::size_hint)
+            .fold(self.left.size_hint().min(self.right.size_hint()), u32::min)
+    }
+
+    /// The other docsets are sought at most once per document
+    /// of the leading docset.
+    fn cost(&self) -> u64 {
+        let lead_cost = self.left.cost();
+        lead_cost
+            + self.right.cost().min(lead_cost)
+            + self
+                .others
+                .iter()
+                .map(|docset| docset.cost().min(lead_cost))
+                .sum::<u64>()
     }
 }
 
@@ -187,6 +214,17 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_intersection_size_hint_and_cost() {
+        let a = VecDocSet::from(vec![1, 3, 9, 10, 11]);
+        let b = VecDocSet::from(vec![3, 9]);
+        let c = VecDocSet::from(vec![1, 3, 5, 9]);
+        let intersection = Intersection::new(vec![a, b, c]);
+        assert_eq!(intersection.size_hint(), 2);
+        // `b` leads the intersection, the other docsets are sought at most twice.
+        assert_eq!(intersection.cost(), 6);
+    }
+
     #[test]
     fn test_intersection_zero() {
         let left = VecDocSet::from(vec![0]);
@@ -0,0 +1,407 @@
+use crate::query::{
+    AllQuery, BooleanQuery, BoostQuery, DisjunctionMaxQuery, EmptyQuery, Occur, PhraseQuery, Query,
+    QueryParserError, RangeQuery, TermQuery,
+};
+use crate::schema::{Facet, Field, FieldType, IndexRecordOption, Schema};
+use crate::{Score, TantivyError, Term};
+use serde::Deserialize;
+use std::ops::Bound;
+use std::str::FromStr;
+
+/// A query of the JSON query DSL, as deserialized by serde.
+///
+/// Each query is an object with a single key, naming the type of the query:
+///
+/// - `{"all": {}}` matches all documents,
+/// - `{"term": {"field": "title", "value": "diary"}}` matches the documents containing a term.
+/// The value is not tokenized,
+/// - `{"phrase": {"field": "title", "terms": ["old", "man"]}}` matches the documents
+/// containing the terms at consecutive positions,
+/// - `{"range": {"field": "year", "gte": 1960, "lt": 1970}}` matches the documents with a value
+/// within the bounds. Each side is optional, and unbounded if missing,
+/// - `{"bool": {"must": [...], "should": [...], "must_not": [...]}}` combines queries
+/// as a `BooleanQuery`. Each list is optional,
+/// - `{"boost": {"query": ..., "boost": 2.0}}` multiplies the score of a query,
+/// - `{"dis_max": {"queries": [...], "tie_breaker": 0.1}}` is a `DisjunctionMaxQuery`.
+/// The tie breaker is optional, and defaults to 0.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum JsonQuery {
+    All {},
+    Term {
+        field: String,
+        value: serde_json::Value,
+    },
+    Phrase {
+        field: String,
+        terms: Vec<String>,
+    },
+    Range {
+        field: String,
+        #[serde(default)]
+        gt: Option<serde_json::Value>,
+        #[serde(default)]
+        gte: Option<serde_json::Value>,
+        #[serde(default)]
+        lt: Option<serde_json::Value>,
+        #[serde(default)]
+        lte: Option<serde_json::Value>,
+    },
+    Bool {
+        #[serde(default)]
+        must: Vec<JsonQuery>,
+        #[serde(default)]
+        should: Vec<JsonQuery>,
+        #[serde(default)]
+        must_not: Vec<JsonQuery>,
+    },
+    Boost {
+        query: Box<JsonQuery>,
+        boost: Score,
+    },
+    DisMax {
+        queries: Vec<JsonQuery>,
+        #[serde(default)]
+        tie_breaker: Score,
+    },
+}
+
+fn indexed_field(schema: &Schema, field_name: &str) -> crate::Result<Field> {
+    let field = schema
+        .get_field(field_name)
+        .ok_or_else(|| QueryParserError::FieldDoesNotExist(field_name.to_string()))?;
+    if !schema.get_field_entry(field).is_indexed() {
+        return Err(QueryParserError::FieldNotIndexed(field_name.to_string()).into());
+    }
+    Ok(field)
+}
+
+/// Builds the term of `field` for a JSON value.
+///
+/// Numbers, dates, facets and bytes are parsed from their text
+/// representation, as in the `QueryParser`. Text values are not tokenized.
+fn json_value_to_term(
+    schema: &Schema,
+    field: Field,
+    json_value: &serde_json::Value,
+) -> crate::Result<Term> {
+    let text = match json_value {
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Number(number) => number.to_string(),
+        _ => {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Expected a string or a number for the field '{}', got {}",
+                schema.get_field_name(field),
+                json_value
+            )))
+        }
+    };
+    let term = match *schema.get_field_entry(field).field_type() {
+        FieldType::Str(_) => Term::from_field_text(field, &text),
+        FieldType::U64(_) => {
+            Term::from_field_u64(field, u64::from_str(&text).map_err(QueryParserError::from)?)
+        }
+        FieldType::I64(_) => {
+            Term::from_field_i64(field, i64::from_str(&text).map_err(QueryParserError::from)?)
+        }
+        FieldType::F64(_) => {
+            Term::from_field_f64(field, f64::from_str(&text).map_err(QueryParserError::from)?)
+        }
+        FieldType::Date(_) => {
+            let date =
+                chrono::DateTime::parse_from_rfc3339(&text).map_err(QueryParserError::from)?;
+            Term::from_field_date(field, &date.with_timezone(&chrono::Utc))
+        }
+        FieldType::HierarchicalFacet(_) => {
+            let facet = Facet::from_text(&text).map_err(QueryParserError::from)?;
+            Term::from_facet(field, &facet)
+        }
+        FieldType::Bytes(_) => {
+            let bytes = base64::decode(&text).map_err(QueryParserError::ExpectedBase64)?;
+            Term::from_field_bytes(field, &bytes)
+        }
+    };
+    Ok(term)
+}
+
+fn bound(
+    schema: &Schema,
+    field: Field,
+    exclusive: Option<serde_json::Value>,
+    inclusive: Option<serde_json::Value>,
+) -> crate::Result<Bound<Term>> {
+    match (exclusive, inclusive) {
+        (Some(_), Some(_)) => Err(TantivyError::InvalidArgument(format!(
+            "A range on the field '{}' has both an inclusive and an exclusive bound on the same side",
+            schema.get_field_name(field)
+        ))),
+        (Some(value), None) => Ok(Bound::Excluded(json_value_to_term(schema, field, &value)?)),
+        (None, Some(value)) => Ok(Bound::Included(json_value_to_term(schema, field, &value)?)),
+        (None, None) => Ok(Bound::Unbounded),
+    }
+}
+
+impl JsonQuery {
+    fn into_query(self, schema: &Schema) -> crate::Result<Box<dyn Query>> {
+        match self {
+            JsonQuery::All {} => Ok(Box::new(AllQuery)),
+            JsonQuery::Term { field, value } => {
+                let field = indexed_field(schema, &field)?;
+                let term = json_value_to_term(schema, field, &value)?;
+                Ok(Box::new(TermQuery::new(term, IndexRecordOption::WithFreqs)))
+            }
+            JsonQuery::Phrase {
+                field: field_name,
+                terms,
+            } => {
+                let field = indexed_field(schema, &field_name)?;
+                let indexing_options = match schema.get_field_entry(field).field_type() {
+                    FieldType::Str(text_options) => text_options.get_indexing_options(),
+                    _ => None,
+                };
+                let indexing_options = indexing_options.ok_or_else(|| {
+                    TantivyError::InvalidArgument(format!(
+                        "Phrase queries are only supported on text fields: '{}'",
+                        field_name
+                    ))
+                })?;
+                let mut terms: Vec<Term> = terms
+                    .iter()
+                    .map(|text| Term::from_field_text(field, text))
+                    .collect();
+                if terms.len() <= 1 {
+                    return Ok(match terms.pop() {
+                        Some(term) => Box::new(TermQuery::new(term, IndexRecordOption::WithFreqs)),
+                        None => Box::new(EmptyQuery),
+                    });
+                }
+                if indexing_options.is_filter_only() {
+                    return Err(QueryParserError::FieldIsFilterOnly(field_name).into());
+                }
+                if !indexing_options.index_option().has_positions() {
+                    return Err(
+                        QueryParserError::FieldDoesNotHavePositionsIndexed(field_name).into(),
+                    );
+                }
+                Ok(Box::new(PhraseQuery::new(terms)))
+            }
+            JsonQuery::Range {
+                field,
+                gt,
+                gte,
+                lt,
+                lte,
+            } => {
+                let field = indexed_field(schema, &field)?;
+                let value_type = schema.get_field_entry(field).field_type().value_type();
+                let left_bound = bound(schema, field, gt, gte)?;
+                let right_bound = bound(schema, field, lt, lte)?;
+                Ok(Box::new(RangeQuery::new_term_bounds(
+                    field,
+                    value_type,
+                    &left_bound,
+                    &right_bound,
+                )))
+            }
+            JsonQuery::Bool {
+                must,
+                should,
+                must_not,
+            } => {
+                let mut clauses: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+                let mut add_clauses = |occur: Occur, queries: Vec<JsonQuery>| {
+                    for query in queries {
+                        clauses.push((occur, query.into_query(schema)?));
+                    }
+                    crate::Result::Ok(())
+                };
+                add_clauses(Occur::Must, must)?;
+                add_clauses(Occur::Should, should)?;
+                add_clauses(Occur::MustNot, must_not)?;
+                Ok(Box::new(BooleanQuery::new(clauses)))
+            }
+            JsonQuery::Boost { query, boost } => {
+                Ok(Box::new(BoostQuery::new(query.into_query(schema)?, boost)))
+            }
+            JsonQuery::DisMax {
+                queries,
+                tie_breaker,
+            } => {
+                let disjuncts = queries
+                    .into_iter()
+                    .map(|query| query.into_query(schema))
+                    .collect::<crate::Result<Vec<_>>>()?;
+                Ok(Box::new(DisjunctionMaxQuery::with_tie_breaker(
+                    disjuncts,
+                    tie_breaker,
+                )))
+            }
+        }
+    }
+}
+
+impl dyn Query {
+    /// Builds a query from its JSON representation.
+    ///
+    /// Unlike the [`QueryParser`](./struct.QueryParser.html), which parses queries
+    /// typed by users, the JSON query DSL makes it possible to send structured queries
+    /// over the wire. The field names are resolved, and the values parsed, with `schema`.
+    ///
+    /// The supported queries are `all`, `term`, `phrase`, `range`, `bool`, `boost` and
+    /// `dis_max`. Text values are used as terms as is: they are not tokenized.
+    ///
+    /// ```rust
+    /// use tantivy::query::Query;
+    /// use tantivy::schema::{Schema, INDEXED, TEXT};
+    ///
+    /// # fn main() -> tantivy::Result<()> {
+    /// let mut schema_builder = Schema::builder();
+    /// schema_builder.add_text_field("title", TEXT);
+    /// schema_builder.add_u64_field("year", INDEXED);
+    /// let schema = schema_builder.build();
+    /// let query = <dyn Query>::from_json(
+    ///     r#"{"bool": {
+    ///         "must": [{"term": {"field": "title", "value": "diary"}}],
+    ///         "must_not": [{"range": {"field": "year", "lt": 1960}}]
+    ///     }}"#,
+    ///     &schema,
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_json(json: &str, schema: &Schema) -> crate::Result<Box<dyn Query>> {
+        let json_query: JsonQuery = serde_json::from_str(json).map_err(|err| {
+            TantivyError::InvalidArgument(format!("Query is not valid JSON DSL: {}", err))
+        })?;
+        json_query.into_query(schema)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::collector::{Count, TopDocs};
+    use crate::query::{DisjunctionMaxQuery, Query, QueryParser};
+    use crate::schema::{Schema, INDEXED, STRING, TEXT};
+    use crate::{Index, TantivyError};
+
+    #[test]
+    fn test_query_from_json() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let body = schema_builder.add_text_field("body", TEXT);
+        let year = schema_builder.add_i64_field("year", INDEXED);
+        schema_builder.add_text_field("tag", STRING);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer
+            .add_document(doc!(title => "the old man and the sea", body => "a", year => 1952i64));
+        index_writer.add_document(doc!(title => "the man", body => "old sea", year => 1960i64));
+        index_writer.add_document(doc!(title => "sea", body => "man", year => -5i64));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let count = |json: &str| -> crate::Result<usize> {
+            let query = <dyn Query>::from_json(json, &schema)?;
+            searcher.search(&query, &Count)
+        };
+        assert_eq!(count(r#"{"all": {}}"#)?, 3);
+        assert_eq!(count(r#"{"term": {"field": "title", "value": "sea"}}"#)?, 2);
+        assert_eq!(count(r#"{"term": {"field": "year", "value": -5}}"#)?, 1);
+        assert_eq!(count(r#"{"term": {"field": "year", "value": "1960"}}"#)?, 1);
+        assert_eq!(
+            count(r#"{"phrase": {"field": "title", "terms": ["old", "man"]}}"#)?,
+            1
+        );
+        assert_eq!(
+            count(r#"{"range": {"field": "year", "gte": 1952, "lt": 1960}}"#)?,
+            1
+        );
+        assert_eq!(count(r#"{"range": {"field": "year", "gt": 0}}"#)?, 2);
+        assert_eq!(
+            count(
+                r#"{"bool": {
+                    "should": [
+                        {"term": {"field": "title", "value": "man"}},
+                        {"term": {"field": "body", "value": "man"}}
+                    ],
+                    "must_not": [{"range": {"field": "year", "lte": 1952}}]
+                }}"#
+            )?,
+            1
+        );
+        assert_eq!(
+            count(
+                r#"{"boost": {"query": {"dis_max": {"queries": [
+                    {"term": {"field": "title", "value": "old"}},
+                    {"term": {"field": "body", "value": "old"}}
+                ]}}, "boost": 2.0}}"#
+            )?,
+            2
+        );
+
+        let json_query = <dyn Query>::from_json(
+            r#"{"bool": {"should": [
+                {"term": {"field": "title", "value": "sea"}},
+                {"term": {"field": "body", "value": "sea"}}
+            ]}}"#,
+            &schema,
+        )?;
+        let parsed_query =
+            QueryParser::for_index(&index, vec![title, body]).parse_query("title:sea body:sea")?;
+        assert_eq!(
+            searcher.search(&json_query, &TopDocs::with_limit(3))?,
+            searcher.search(&parsed_query, &TopDocs::with_limit(3))?
+        );
+
+        let dis_max_query = <dyn Query>::from_json(
+            r#"{"dis_max": {"queries": [{"all": {}}], "tie_breaker": 0.3}}"#,
+            &schema,
+        )?;
+        let dis_max_query = dis_max_query.downcast_ref::<DisjunctionMaxQuery>().unwrap();
+        assert_eq!(dis_max_query.tie_breaker(), 0.3);
+        assert_eq!(dis_max_query.disjuncts().len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_from_json_errors() {
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_text_field("title", TEXT);
+        schema_builder.add_text_field("tag", STRING);
+        schema_builder.add_u64_field("count", INDEXED);
+        schema_builder.add_u64_field("stored_count", crate::schema::STORED);
+        let schema = schema_builder.build();
+        let is_invalid_argument = |json: &str| {
+            matches!(
+                <dyn Query>::from_json(json, &schema),
+                Err(TantivyError::InvalidArgument(_))
+            )
+        };
+        assert!(is_invalid_argument("{"));
+        assert!(is_invalid_argument(r#"{"unknown": {}}"#));
+        assert!(is_invalid_argument(
+            r#"{"term": {"field": "title", "value": "a", "boost": 2}}"#
+        ));
+        assert!(is_invalid_argument(
+            r#"{"term": {"field": "missing", "value": "a"}}"#
+        ));
+        assert!(is_invalid_argument(
+            r#"{"term": {"field": "stored_count", "value": 1}}"#
+        ));
+        assert!(is_invalid_argument(
+            r#"{"term": {"field": "count", "value": -1}}"#
+        ));
+        assert!(is_invalid_argument(
+            r#"{"term": {"field": "count", "value": true}}"#
+        ));
+        assert!(is_invalid_argument(
+            r#"{"range": {"field": "count", "gt": 1, "gte": 1}}"#
+        ));
+        assert!(is_invalid_argument(
+            r#"{"phrase": {"field": "tag", "terms": ["a", "b"]}}"#
+        ));
+        assert!(is_invalid_argument(
+            r#"{"phrase": {"field": "count", "terms": ["1", "2"]}}"#
+        ));
+    }
+}
@@ -0,0 +1,163 @@
+use crate::postings::Postings;
+use crate::query::rewrite::rewrite_query;
+use crate::query::Query;
+use crate::schema::IndexRecordOption;
+use crate::{DocAddress, DocSet, Searcher, Term};
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+/// A term of a query found in a document.
+///
+/// See [`Query::matched_terms`](./trait.Query.html#method.matched_terms).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MatchedTerm {
+    /// The term, which also identifies the field it was found in.
+    pub term: Term,
+    /// Number of occurrences of the term in the field of the document.
+    ///
+    /// It is 1 if the field is not indexed with term frequencies.
+    pub term_freq: u32,
+    /// Positions of the occurrences of the term.
+    ///
+    /// Empty if the field is not indexed with positions.
+    pub positions: Vec<u32>,
+    /// Byte offsets of the occurrences of the term in the text of the field.
+    ///
+    /// Empty if the field is not indexed with offsets.
+    pub offsets: Vec<Range<usize>>,
+}
+
+pub(crate) fn matched_terms(
+    query: &dyn Query,
+    searcher: &Searcher,
+    doc_address: DocAddress,
+) -> crate::Result<Vec<MatchedTerm>> {
+    // Multi-term queries (e.g. fuzzy or regex queries) only report their terms once
+    // they are rewritten into the terms they match.
+    let rewritten_query = rewrite_query(query, searcher, false)?;
+    let mut terms = BTreeMap::new();
+    rewritten_query.query_terms(&mut terms);
+    let segment_reader = searcher.segment_reader(doc_address.segment_ord);
+    let mut matched_terms = Vec::new();
+    for term in terms.into_iter().map(|(term, _)| term) {
+        let record_option = searcher
+            .schema()
+            .get_field_entry(term.field())
+            .field_type()
+            .get_index_record_option()
+            .unwrap_or(IndexRecordOption::Basic);
+        let inverted_index = segment_reader.inverted_index(term.field())?;
+        if let Some(mut postings) = inverted_index.read_postings(&term, record_option)? {
+            if postings.seek(doc_address.doc_id) != doc_address.doc_id {
+                continue;
+            }
+            let mut positions = Vec::new();
+            if record_option.has_positions() {
+                postings.positions(&mut positions);
+            }
+            let mut offsets = Vec::new();
+            if record_option.has_offsets() {
+                postings.offsets(&mut offsets);
+            }
+            matched_terms.push(MatchedTerm {
+                term,
+                term_freq: postings.term_freq(),
+                positions,
+                offsets,
+            });
+        }
+    }
+    Ok(matched_terms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MatchedTerm;
+    use crate::query::{BooleanQuery, FuzzyTermQuery, Occur, PhraseQuery, Query, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, TextFieldIndexing, TextOptions, STRING, TEXT};
+    use crate::{DocAddress, Index, Term};
+
+    #[test]
+    fn test_matched_terms() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_index_option(IndexRecordOption::WithFreqsAndPositionsAndOffsets),
+        );
+        let title_field = schema_builder.add_text_field("title", title_options);
+        let body_field = schema_builder.add_text_field("body", TEXT);
+        let tag_field = schema_builder.add_text_field("tag", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(
+            title_field => "The wind and the rain",
+            body_field => "A gust of wind, then another wind",
+            tag_field => "weather",
+        ));
+        index_writer.add_document(doc!(title_field => "The sun"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let term_query = |term: Term| -> Box<dyn Query> {
+            Box::new(TermQuery::new(term, IndexRecordOption::Basic))
+        };
+        let query = BooleanQuery::new(vec![
+            (
+                Occur::Should,
+                Box::new(PhraseQuery::new(vec![
+                    Term::from_field_text(title_field, "wind"),
+                    Term::from_field_text(title_field, "rain"),
+                ])) as Box<dyn Query>,
+            ),
+            (
+                Occur::Should,
+                Box::new(FuzzyTermQuery::new(
+                    Term::from_field_text(body_field, "winds"),
+                    1,
+                    true,
+                )) as Box<dyn Query>,
+            ),
+            (
+                Occur::Should,
+                term_query(Term::from_field_text(tag_field, "weather")),
+            ),
+            (
+                Occur::Should,
+                term_query(Term::from_field_text(tag_field, "sunny")),
+            ),
+        ]);
+        // The phrase does not match, but its terms are reported.
+        assert_eq!(
+            query.matched_terms(&searcher, DocAddress::new(0, 0))?,
+            vec![
+                MatchedTerm {
+                    term: Term::from_field_text(title_field, "rain"),
+                    term_freq: 1,
+                    positions: vec![4],
+                    offsets: vec![17..21],
+                },
+                MatchedTerm {
+                    term: Term::from_field_text(title_field, "wind"),
+                    term_freq: 1,
+                    positions: vec![1],
+                    offsets: vec![4..8],
+                },
+                MatchedTerm {
+                    term: Term::from_field_text(body_field, "wind"),
+                    term_freq: 2,
+                    positions: vec![3, 6],
+                    offsets: vec![],
+                },
+                MatchedTerm {
+                    term: Term::from_field_text(tag_field, "weather"),
+                    term_freq: 1,
+                    positions: vec![],
+                    offsets: vec![],
+                },
+            ]
+        );
+        assert!(query
+            .matched_terms(&searcher, DocAddress::new(0, 1))?
+            .is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,228 @@
+use crate::docset::{DocSet, TERMINATED};
+use crate::query::score_combiner::{DoNothingCombiner, ScoreCombiner};
+use crate::query::Scorer;
+use crate::DocId;
+use crate::Score;
+
+/// Creates a `DocSet` that iterates through the documents matched by
+/// at least `min_should_match` of the given `DocSet`s.
+///
+/// With `min_should_match == 1`, this is a plain union (see [`Union`](./struct.Union.html),
+/// which is faster in that case). With `min_should_match` equal to the number of
+/// `DocSet`s, this is an intersection.
+///
+/// The score of a document is computed by the `TScoreCombiner`,
+/// from the scorers that match the document.
+pub struct MinShouldMatch<TScorer, TScoreCombiner = DoNothingCombiner> {
+    scorers: Vec<TScorer>,
+    min_should_match: usize,
+    score_combiner: TScoreCombiner,
+    // Buffer used to find the `min_should_match`-th smallest doc of the scorers.
+    docs_buffer: Vec<DocId>,
+    doc: DocId,
+}
+
+impl<TScorer, TScoreCombiner> MinShouldMatch<TScorer, TScoreCombiner>
+where
+    TScorer: Scorer,
+    TScoreCombiner: ScoreCombiner,
+{
+    /// Creates a `MinShouldMatch` over `scorers`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min_should_match` is 0.
+    pub fn new(
+        scorers: Vec<TScorer>,
+        min_should_match: usize,
+    ) -> MinShouldMatch<TScorer, TScoreCombiner> {
+        assert!(min_should_match > 0, "min_should_match must be positive.");
+        let mut min_should_match_scorer = MinShouldMatch {
+            scorers,
+            min_should_match,
+            score_combiner: TScoreCombiner::default(),
+            docs_buffer: Vec::new(),
+            doc: TERMINATED,
+        };
+        min_should_match_scorer.doc = min_should_match_scorer.find_match();
+        min_should_match_scorer
+    }
+
+    // Positions the scorers on the next document matched by at least
+    // `min_should_match` of them, and returns it.
+    //
+    // A document matched by `min_should_match` scorers cannot be lower than the
+    // `min_should_match`-th smallest doc of the scorers, so that the scorers
+    // lagging behind this doc are moved to it, until it is matched.
+    fn find_match(&mut self) -> DocId {
+        loop {
+            self.scorers.retain(|scorer| scorer.doc() != TERMINATED);
+            if self.scorers.len() < self.min_should_match {
+                return TERMINATED;
+            }
+            self.docs_buffer.clear();
+            self.docs_buffer
+                .extend(self.scorers.iter().map(|scorer| scorer.doc()));
+            self.docs_buffer.sort_unstable();
+            let candidate = self.docs_buffer[self.min_should_match - 1];
+            if self.docs_buffer[0] == candidate {
+                self.score_combiner.clear();
+                for scorer in &mut self.scorers {
+                    if scorer.doc() == candidate {
+                        self.score_combiner.update(scorer);
+                    }
+                }
+                return candidate;
+            }
+            for scorer in &mut self.scorers {
+                if scorer.doc() < candidate {
+                    scorer.seek(candidate);
+                }
+            }
+        }
+    }
+}
+
+impl<TScorer, TScoreCombiner> DocSet for MinShouldMatch<TScorer, TScoreCombiner>
+where
+    TScorer: Scorer,
+    TScoreCombiner: ScoreCombiner,
+{
+    fn advance(&mut self) -> DocId {
+        if self.doc == TERMINATED {
+            return TERMINATED;
+        }
+        for scorer in &mut self.scorers {
+            if scorer.doc() == self.doc {
+                scorer.advance();
+            }
+        }
+        self.doc = self.find_match();
+        self.doc
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        if self.doc >= target {
+            return self.doc;
+        }
+        for scorer in &mut self.scorers {
+            if scorer.doc() < target {
+                scorer.seek(target);
+            }
+        }
+        self.doc = self.find_match();
+        self.doc
+    }
+
+    fn doc(&self) -> DocId {
+        self.doc
+    }
+
+    /// A matching document belongs to at least one of any
+    /// `num_scorers - min_should_match + 1` docsets.
+    ///
+    /// The sum of the sizes of the smallest of them is returned.
+    fn size_hint(&self) -> u32 {
+        if self.scorers.len() < self.min_should_match {
+            return 0u32;
+        }
+        let mut size_hints: Vec<u32> = self
+            .scorers
+            .iter()
+            .map(|scorer| scorer.size_hint())
+            .collect();
+        size_hints.sort_unstable();
+        size_hints[..self.scorers.len() - self.min_should_match + 1]
+            .iter()
+            .sum()
+    }
+
+    fn cost(&self) -> u64 {
+        self.scorers.iter().map(|scorer| scorer.cost()).sum()
+    }
+}
+
+impl<TScorer, TScoreCombiner> Scorer for MinShouldMatch<TScorer, TScoreCombiner>
+where
+    TScorer: Scorer,
+    TScoreCombiner: ScoreCombiner,
+{
+    fn score(&mut self) -> Score {
+        self.score_combiner.score()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MinShouldMatch;
+    use crate::docset::{DocSet, TERMINATED};
+    use crate::query::score_combiner::SumCombiner;
+    use crate::query::{ConstScorer, Scorer, VecDocSet};
+    use crate::DocId;
+    use std::collections::BTreeMap;
+
+    fn min_should_match(
+        vals: &[Vec<DocId>],
+        min_should_match: usize,
+    ) -> MinShouldMatch<ConstScorer<VecDocSet>, SumCombiner> {
+        let scorers = vals
+            .iter()
+            .map(|docs| ConstScorer::new(VecDocSet::from(docs.clone()), 1.0))
+            .collect();
+        MinShouldMatch::new(scorers, min_should_match)
+    }
+
+    fn aux_test_min_should_match(vals: Vec<Vec<DocId>>) {
+        let mut doc_counts: BTreeMap<DocId, usize> = BTreeMap::new();
+        for docs in &vals {
+            for &doc in docs {
+                *doc_counts.entry(doc).or_insert(0) += 1;
+            }
+        }
+        for k in 1..=vals.len() + 1 {
+            let expected: Vec<(DocId, usize)> = doc_counts
+                .iter()
+                .filter(|&(_, &count)| count >= k)
+                .map(|(&doc, &count)| (doc, count))
+                .collect();
+            let mut scorer = min_should_match(&vals, k);
+            assert!(scorer.size_hint() as usize >= expected.len());
+            let mut matched = Vec::new();
+            while scorer.doc() != TERMINATED {
+                matched.push((scorer.doc(), scorer.score() as usize));
+                scorer.advance();
+            }
+            assert_eq!(matched, expected);
+        }
+    }
+
+    #[test]
+    fn test_min_should_match() {
+        aux_test_min_should_match(vec![
+            vec![1, 3, 4, 7, 10],
+            vec![1, 2, 3, 10],
+            vec![3, 7, 8, 10],
+            vec![],
+        ]);
+        aux_test_min_should_match(vec![(0..100).collect(), (0..100).step_by(3).collect()]);
+        aux_test_min_should_match(vec![vec![5]]);
+    }
+
+    #[test]
+    fn test_min_should_match_seek() {
+        let vals = vec![
+            vec![1, 3, 4, 7, 10, 12],
+            vec![1, 2, 3, 10, 12],
+            vec![3, 7, 8, 10, 12],
+        ];
+        let mut scorer = min_should_match(&vals, 2);
+        assert_eq!(scorer.doc(), 1);
+        assert_eq!(scorer.seek(4), 7);
+        assert_eq!(scorer.score(), 2.0);
+        assert_eq!(scorer.seek(7), 7);
+        assert_eq!(scorer.seek(8), 10);
+        assert_eq!(scorer.score(), 3.0);
+        assert_eq!(scorer.advance(), 12);
+        assert_eq!(scorer.advance(), TERMINATED);
+    }
+}
@@ -1,4 +1,26 @@
-/*! Query Module */
+/*! Query Module
+
+# Building custom queries
+
+A custom `Query` only needs to build a [`Weight`](./trait.Weight.html) for a `Searcher`,
+which in turn builds a [`Scorer`](./trait.Scorer.html) for each segment.
+The scorers of the queries provided by tantivy can be combined into new scorers
+with the following building blocks:
+
+- [`Intersection`](./struct.Intersection.html) and [`intersect_scorers`](./fn.intersect_scorers.html)
+match the documents matched by all of their docsets,
+- [`Union`](./struct.Union.html) matches the documents matched by any of its docsets,
+and [`MinShouldMatch`](./struct.MinShouldMatch.html) those matched by at least `k` of them,
+- [`Exclude`](./struct.Exclude.html) removes the documents of a docset from another one,
+- [`RequiredOptionalScorer`](./struct.RequiredOptionalScorer.html) matches the documents of a
+required scorer, adding the score of an optional one,
+- [`FilteredScorer`](./struct.FilteredScorer.html) restricts a docset to the documents of a
+`BitSet`, and [`BitSetDocSet`](./struct.BitSetDocSet.html) iterates over a `BitSet`,
+- [`ConstScorer`](./struct.ConstScorer.html) gives a constant score to the documents of a docset.
+
+The way the scores of the combined scorers are aggregated is defined by a
+[`ScoreCombiner`](./trait.ScoreCombiner.html).
+*/
 
 mod all_query;
 mod automaton_weight;
@@ -6,11 +28,18 @@ mod bitset;
 mod bm25;
 mod boolean_query;
 mod boost_query;
+mod const_score_query;
+mod disjunction_max_query;
 mod empty_query;
 mod exclude;
 mod explanation;
+mod feature_query;
+mod filtered_query;
 mod fuzzy_query;
 mod intersection;
+mod json_query;
+mod matched_terms;
+mod min_should_match;
 mod more_like_this;
 mod phrase_query;
 mod query;
@@ -20,12 +49,15 @@ mod regex_query;
 mod reqopt_scorer;
 mod scorer;
 mod term_query;
+mod term_set_query;
 mod union;
 mod weight;
+mod wildcard_query;
 
 #[cfg(test)]
 mod vec_docset;
 
+pub(crate) mod rewrite;
 pub(crate) mod score_combiner;
 pub(crate) use self::bm25::Bm25Weight;
 pub use self::intersection::Intersection;
@@ -39,13 +71,20 @@ pub use self::automaton_weight::AutomatonWeight;
 pub use self::bitset::BitSetDocSet;
 pub use self::boolean_query::BooleanQuery;
 pub use self::boost_query::BoostQuery;
+pub use self::const_score_query::ConstScoreQuery;
+pub use self::disjunction_max_query::DisjunctionMaxQuery;
 pub use self::empty_query::{EmptyQuery, EmptyScorer, EmptyWeight};
 pub use self::exclude::Exclude;
 pub use self::explanation::Explanation;
+pub use self::feature_query::{FeatureFunction, FeatureQuery};
+pub(crate) use self::filtered_query::FilteredWeight;
+pub use self::filtered_query::{DocFilter, FilteredQuery, FilteredScorer};
 #[cfg(test)]
 pub(crate) use self::fuzzy_query::DfaWrapper;
 pub use self::fuzzy_query::FuzzyTermQuery;
 pub use self::intersection::intersect_scorers;
+pub use self::matched_terms::MatchedTerm;
+pub use self::min_should_match::MinShouldMatch;
 pub use self::more_like_this::{MoreLikeThisQuery, MoreLikeThisQueryBuilder};
 pub use self::phrase_query::PhraseQuery;
 pub use self::query::{Query, QueryClone};
@@ -54,10 +93,16 @@ pub use self::query_parser::QueryParserError;
 pub use self::range_query::RangeQuery;
 pub use self::regex_query::RegexQuery;
 pub use self::reqopt_scorer::RequiredOptionalScorer;
+pub use self::rewrite::rewrite_query;
+pub use self::score_combiner::{
+    DoNothingCombiner, ScoreCombiner, SumCombiner, SumWithCoordsCombiner,
+};
 pub use self::scorer::ConstScorer;
 pub use self::scorer::Scorer;
 pub use self::term_query::TermQuery;
+pub use self::term_set_query::TermSetQuery;
 pub use self::weight::Weight;
+pub use self::wildcard_query::WildcardQuery;
 pub use tantivy_query_grammar::Occur;
 
 #[cfg(test)]
@@ -14,7 +14,7 @@ pub mod tests {
     use crate::collector::tests::{TEST_COLLECTOR_WITHOUT_SCORE, TEST_COLLECTOR_WITH_SCORE};
     use crate::core::Index;
     use crate::query::Weight;
-    use crate::schema::{Schema, Term, TEXT};
+    use crate::schema::{Field, IndexRecordOption, Schema, Term, TEXT};
     use crate::DocId;
     use crate::{DocAddress, TERMINATED};
 
@@ -122,7 +122,6 @@ pub mod tests {
     #[test]
     pub fn test_phrase_query_no_positions() {
         let mut schema_builder = Schema::builder();
-        use crate::schema::IndexRecordOption;
         use crate::schema::TextFieldIndexing;
         use crate::schema::TextOptions;
         let no_positions = TextOptions::default().set_indexing_options(
@@ -246,4 +245,101 @@ pub mod tests {
         assert!(test_query(vec![(0, "a"), (2, "d")]).is_empty());
         assert_eq!(test_query(vec![(1, "a"), (3, "c")]), vec![0]);
     }
+
+    #[test]
+    pub fn test_phrase_query_multivalued_field() -> crate::Result<()> {
+        use crate::schema::{TextFieldIndexing, TextOptions};
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let contiguous_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions)
+                .set_position_gap(1),
+        );
+        let contiguous_field = schema_builder.add_text_field("contiguous", contiguous_options);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(
+            text_field => "happy tax",
+            text_field => "payer",
+            contiguous_field => "happy tax",
+            contiguous_field => "payer",
+        ));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let count_phrase = |field: Field| -> crate::Result<usize> {
+            let phrase_query = PhraseQuery::new(vec![
+                Term::from_field_text(field, "tax"),
+                Term::from_field_text(field, "payer"),
+            ])
+            .allow_gaps(true);
+            searcher.search(&phrase_query, &crate::collector::Count)
+        };
+        assert_eq!(count_phrase(text_field)?, 0);
+        assert_eq!(count_phrase(contiguous_field)?, 1);
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_phrase_query_allow_gaps() -> crate::Result<()> {
+        use crate::query::QueryParser;
+        use crate::schema::{TextFieldIndexing, TextOptions};
+        use crate::tokenizer::{LowerCaser, SimpleTokenizer, StopWordFilter, TextAnalyzer};
+        let mut schema_builder = Schema::builder();
+        let text_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer("en_stop_words")
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+        );
+        let text_field = schema_builder.add_text_field("text", text_options);
+        let index = Index::create_in_ram(schema_builder.build());
+        index.tokenizers().register(
+            "en_stop_words",
+            TextAnalyzer::from(SimpleTokenizer)
+                .filter(LowerCaser)
+                .filter(StopWordFilter::default()),
+        );
+        let mut index_writer = index.writer_for_tests()?;
+        for text in &[
+            "part time",
+            "part of time",
+            "part of the time",
+            "part of the whole time",
+            "time part",
+        ] {
+            index_writer.add_document(doc!(text_field => *text));
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let mut query_parser = QueryParser::for_index(&index, vec![text_field]);
+        let search = |query_parser: &QueryParser, query: &str| -> crate::Result<Vec<DocId>> {
+            let query = query_parser.parse_query(query)?;
+            let docs = searcher
+                .search(&query, &TEST_COLLECTOR_WITH_SCORE)?
+                .docs()
+                .iter()
+                .map(|doc_address| doc_address.doc_id)
+                .collect();
+            Ok(docs)
+        };
+        // The stop words leave gaps in the positions.
+        assert_eq!(search(&query_parser, "\"part of the time\"")?, vec![2]);
+        assert_eq!(search(&query_parser, "\"part time\"")?, vec![0]);
+        query_parser.set_allow_phrase_gaps(true);
+        assert_eq!(
+            search(&query_parser, "\"part of the time\"")?,
+            vec![0, 1, 2]
+        );
+        assert_eq!(search(&query_parser, "\"part of time\"")?, vec![0, 1]);
+        assert_eq!(search(&query_parser, "\"part time\"")?, vec![0]);
+
+        let phrase_query = PhraseQuery::new_with_offset(vec![
+            (0, Term::from_field_text(text_field, "part")),
+            (3, Term::from_field_text(text_field, "time")),
+        ])
+        .allow_gaps(true);
+        let docs = searcher.search(&phrase_query, &TEST_COLLECTOR_WITHOUT_SCORE)?;
+        assert_eq!(docs.docs().len(), 3);
+        Ok(())
+    }
 }
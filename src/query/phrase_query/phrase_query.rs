@@ -5,8 +5,8 @@ use crate::core::searcher::Searcher;
 use crate::query::bm25::Bm25Weight;
 use crate::query::Query;
 use crate::query::Weight;
-use crate::schema::IndexRecordOption;
 use crate::schema::{Field, Term};
+use crate::schema::{FieldType, IndexRecordOption, TextFieldIndexing};
 
 /// `PhraseQuery` matches a specific sequence of words.
 ///
@@ -22,10 +22,21 @@ use crate::schema::{Field, Term};
 /// Using a `PhraseQuery` on a field requires positions
 /// to be indexed for this field.
 ///
+/// Token filters removing tokens, such as the
+/// [`StopWordFilter`](../tokenizer/struct.StopWordFilter.html), leave gaps in the
+/// positions of the remaining tokens. The phrase `"the part time"` is therefore
+/// searched as `part` and `time` at the offsets `1` and `2`.
+/// By default, these offsets have to be matched exactly.
+/// If gaps are allowed (See [`allow_gaps(...)`](#method.allow_gaps)), the gaps of the phrase
+/// may be matched by fewer positions in the document, so that `"part of the time"`
+/// matches **part time** and **part of time** as well as **part of the time**, once stop words
+/// are removed.
+///
 #[derive(Clone, Debug)]
 pub struct PhraseQuery {
     field: Field,
     phrase_terms: Vec<(usize, Term)>,
+    allow_gaps: bool,
 }
 
 impl PhraseQuery {
@@ -56,9 +67,20 @@ impl PhraseQuery {
         PhraseQuery {
             field,
             phrase_terms: terms,
+            allow_gaps: false,
         }
     }
 
+    /// Allows the terms of the phrase to be closer in the documents than
+    /// their offsets in the phrase, as long as they remain in the same order.
+    ///
+    /// Consecutive terms of the phrase separated by a gap of `n` positions then match
+    /// terms of the documents separated by `1` to `n` positions.
+    pub fn allow_gaps(mut self, allow_gaps: bool) -> PhraseQuery {
+        self.allow_gaps = allow_gaps;
+        self
+    }
+
     /// The `Field` this `PhraseQuery` is targeting.
     pub fn field(&self) -> Field {
         self.field
@@ -83,6 +105,18 @@ impl PhraseQuery {
     ) -> crate::Result<PhraseWeight> {
         let schema = searcher.schema();
         let field_entry = schema.get_field_entry(self.field);
+        if let FieldType::Str(ref text_options) = field_entry.field_type() {
+            if text_options
+                .get_indexing_options()
+                .map(TextFieldIndexing::is_filter_only)
+                .unwrap_or(false)
+            {
+                return Err(crate::TantivyError::SchemaError(format!(
+                    "Applied phrase query on field {:?}, which is declared as filter-only",
+                    field_entry.name()
+                )));
+            }
+        }
         let has_positions = field_entry
             .field_type()
             .get_index_record_option()
@@ -101,6 +135,7 @@ impl PhraseQuery {
             self.phrase_terms.clone(),
             bm25_weight,
             scoring_enabled,
+            self.allow_gaps,
         ))
     }
 }
@@ -5,6 +5,7 @@ use crate::query::bm25::Bm25Weight;
 use crate::query::{Intersection, Scorer};
 use crate::{DocId, Score};
 use std::cmp::Ordering;
+use std::mem;
 
 struct PostingsWithOffset<TPostings> {
     offset: u32,
@@ -22,6 +23,11 @@ impl<TPostings: Postings> PostingsWithOffset<TPostings> {
     pub fn positions(&mut self, output: &mut Vec<u32>) {
         self.postings.positions_with_offset(self.offset, output)
     }
+
+    /// Positions of the term in the document, regardless of its offset in the phrase.
+    pub fn raw_positions(&mut self, output: &mut Vec<u32>) {
+        self.postings.positions(output)
+    }
 }
 
 impl<TPostings: Postings> DocSet for PostingsWithOffset<TPostings> {
@@ -40,6 +46,10 @@ impl<TPostings: Postings> DocSet for PostingsWithOffset<TPostings> {
     fn size_hint(&self) -> u32 {
         self.postings.size_hint()
     }
+
+    fn cost(&self) -> u64 {
+        self.postings.cost()
+    }
 }
 
 pub struct PhraseScorer<TPostings: Postings> {
@@ -51,6 +61,9 @@ pub struct PhraseScorer<TPostings: Postings> {
     fieldnorm_reader: FieldNormReader,
     similarity_weight: Bm25Weight,
     scoring_enabled: bool,
+    // If gaps are allowed, the ordinals of the docsets of the intersection in the order
+    // of the phrase, each with its offset relative to the previous term of the phrase.
+    gapped_terms: Option<Vec<(usize, u32)>>,
 }
 
 /// Returns true iff the two sorted array contain a common element
@@ -99,6 +112,31 @@ fn intersection_count(left: &[u32], right: &[u32]) -> usize {
     count
 }
 
+/// Keeps in `right` the positions following one of the positions of `left`
+/// by at least `min_distance` and at most `max_distance`.
+///
+/// Returns the number of positions kept.
+fn gapped_intersection(
+    left: &[u32],
+    right: &mut [u32],
+    min_distance: u32,
+    max_distance: u32,
+) -> usize {
+    let mut left_i = 0;
+    let mut count = 0;
+    for right_i in 0..right.len() {
+        let right_val = right[right_i];
+        while left_i < left.len() && left[left_i] + max_distance < right_val {
+            left_i += 1;
+        }
+        if left_i < left.len() && left[left_i] + min_distance <= right_val {
+            right[count] = right_val;
+            count += 1;
+        }
+    }
+    count
+}
+
 /// Intersect twos sorted arrays `left` and `right` and outputs the
 /// resulting array in left.
 ///
@@ -136,6 +174,7 @@ impl<TPostings: Postings> PhraseScorer<TPostings> {
         similarity_weight: Bm25Weight,
         fieldnorm_reader: FieldNormReader,
         scoring_enabled: bool,
+        allow_gaps: bool,
     ) -> PhraseScorer<TPostings> {
         let max_offset = term_postings
             .iter()
@@ -149,8 +188,32 @@ impl<TPostings: Postings> PhraseScorer<TPostings> {
                 PostingsWithOffset::new(postings, (max_offset - offset) as u32)
             })
             .collect::<Vec<_>>();
+        let mut intersection_docset = Intersection::new(postings_with_offsets);
+        let gapped_terms = if allow_gaps {
+            // The docsets of the intersection are sorted by cost, rather than by
+            // their offset in the phrase.
+            let mut offsets_and_ords: Vec<(u32, usize)> = (0..num_docsets)
+                .map(|ord| {
+                    let shift = intersection_docset.docset_mut_specialized(ord).offset;
+                    (max_offset as u32 - shift, ord)
+                })
+                .collect();
+            offsets_and_ords.sort_unstable();
+            let mut prev_offset = offsets_and_ords[0].0;
+            let gapped_terms = offsets_and_ords
+                .into_iter()
+                .map(|(offset, ord)| {
+                    let distance = offset - prev_offset;
+                    prev_offset = offset;
+                    (ord, distance)
+                })
+                .collect();
+            Some(gapped_terms)
+        } else {
+            None
+        };
         let mut scorer = PhraseScorer {
-            intersection_docset: Intersection::new(postings_with_offsets),
+            intersection_docset,
             num_terms: num_docsets,
             left: Vec::with_capacity(100),
             right: Vec::with_capacity(100),
@@ -158,6 +221,7 @@ impl<TPostings: Postings> PhraseScorer<TPostings> {
             similarity_weight,
             fieldnorm_reader,
             scoring_enabled,
+            gapped_terms,
         };
         if scorer.doc() != TERMINATED && !scorer.phrase_match() {
             scorer.advance();
@@ -170,6 +234,11 @@ impl<TPostings: Postings> PhraseScorer<TPostings> {
     }
 
     fn phrase_match(&mut self) -> bool {
+        if self.gapped_terms.is_some() {
+            let count = self.compute_gapped_phrase_count();
+            self.phrase_count = count;
+            return count > 0u32;
+        }
         if self.scoring_enabled {
             let count = self.compute_phrase_count();
             self.phrase_count = count;
@@ -226,6 +295,39 @@ impl<TPostings: Postings> PhraseScorer<TPostings> {
             .positions(&mut self.right);
         intersection_count(&self.left[..intersection_len], &self.right[..]) as u32
     }
+
+    // Counts the occurences of the phrase, where two consecutive terms of the phrase
+    // may be closer than their offsets in the phrase, as long as they remain in order.
+    // Such gaps are typically left by the stop words of the phrase.
+    fn compute_gapped_phrase_count(&mut self) -> u32 {
+        let gapped_terms = match self.gapped_terms.as_ref() {
+            Some(gapped_terms) => gapped_terms,
+            None => return 0u32,
+        };
+        let (first_ord, _) = gapped_terms[0];
+        self.intersection_docset
+            .docset_mut_specialized(first_ord)
+            .raw_positions(&mut self.left);
+        let mut num_matches = self.left.len();
+        for &(ord, max_distance) in &gapped_terms[1..] {
+            self.intersection_docset
+                .docset_mut_specialized(ord)
+                .raw_positions(&mut self.right);
+            // Terms sharing the same offset need to share the same position.
+            let min_distance = max_distance.min(1);
+            num_matches = gapped_intersection(
+                &self.left[..num_matches],
+                &mut self.right[..],
+                min_distance,
+                max_distance,
+            );
+            if num_matches == 0 {
+                return 0u32;
+            }
+            mem::swap(&mut self.left, &mut self.right);
+        }
+        num_matches as u32
+    }
 }
 
 impl<TPostings: Postings> DocSet for PhraseScorer<TPostings> {
@@ -254,20 +356,26 @@ impl<TPostings: Postings> DocSet for PhraseScorer<TPostings> {
     fn size_hint(&self) -> u32 {
         self.intersection_docset.size_hint()
     }
+
+    /// On top of the intersection, the positions of each term
+    /// are decoded for each of the candidate documents.
+    fn cost(&self) -> u64 {
+        self.intersection_docset.cost()
+            + self.intersection_docset.size_hint() as u64 * self.num_terms as u64
+    }
 }
 
 impl<TPostings: Postings> Scorer for PhraseScorer<TPostings> {
     fn score(&mut self) -> Score {
         let doc = self.doc();
-        let fieldnorm_id = self.fieldnorm_reader.fieldnorm_id(doc);
         self.similarity_weight
-            .score(fieldnorm_id, self.phrase_count)
+            .score_doc(&self.fieldnorm_reader, doc, self.phrase_count)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{intersection, intersection_count};
+    use super::{gapped_intersection, intersection, intersection_count};
 
     fn test_intersection_sym(left: &[u32], right: &[u32], expected: &[u32]) {
         test_intersection_aux(left, right, expected);
@@ -290,6 +398,28 @@ mod tests {
         test_intersection_sym(&[5, 7], &[1, 5, 10, 12], &[5]);
         test_intersection_sym(&[1, 5, 6, 9, 10, 12], &[6, 8, 9, 12], &[6, 9, 12]);
     }
+
+    fn test_gapped_intersection_aux(
+        left: &[u32],
+        right: &[u32],
+        min_distance: u32,
+        max_distance: u32,
+        expected: &[u32],
+    ) {
+        let mut right_vec = Vec::from(right);
+        let count = gapped_intersection(left, &mut right_vec[..], min_distance, max_distance);
+        assert_eq!(&right_vec[..count], expected);
+    }
+
+    #[test]
+    fn test_gapped_intersection() {
+        test_gapped_intersection_aux(&[1], &[2], 1, 1, &[2]);
+        test_gapped_intersection_aux(&[1], &[3], 1, 1, &[]);
+        test_gapped_intersection_aux(&[1], &[1, 2, 3, 4], 1, 2, &[2, 3]);
+        test_gapped_intersection_aux(&[1, 5], &[1, 3, 6, 9], 1, 3, &[3, 6]);
+        test_gapped_intersection_aux(&[2, 4], &[2, 3, 4], 0, 0, &[2, 4]);
+        test_gapped_intersection_aux(&[], &[2], 1, 2, &[]);
+    }
 }
 
 #[cfg(all(test, feature = "unstable"))]
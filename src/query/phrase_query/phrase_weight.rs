@@ -16,6 +16,7 @@ pub struct PhraseWeight {
     phrase_terms: Vec<(usize, Term)>,
     similarity_weight: Bm25Weight,
     scoring_enabled: bool,
+    allow_gaps: bool,
 }
 
 impl PhraseWeight {
@@ -24,11 +25,13 @@ impl PhraseWeight {
         phrase_terms: Vec<(usize, Term)>,
         similarity_weight: Bm25Weight,
         scoring_enabled: bool,
+        allow_gaps: bool,
     ) -> PhraseWeight {
         PhraseWeight {
             phrase_terms,
             similarity_weight,
             scoring_enabled,
+            allow_gaps,
         }
     }
 
@@ -77,6 +80,7 @@ impl PhraseWeight {
             similarity_weight,
             fieldnorm_reader,
             self.scoring_enabled,
+            self.allow_gaps,
         )))
     }
 }
@@ -100,10 +104,10 @@ impl Weight for PhraseWeight {
             return Err(does_not_match(doc));
         }
         let fieldnorm_reader = self.fieldnorm_reader(reader)?;
-        let fieldnorm_id = fieldnorm_reader.fieldnorm_id(doc);
+        let fieldnorm = fieldnorm_reader.fieldnorm(doc);
         let phrase_count = scorer.phrase_count();
         let mut explanation = Explanation::new("Phrase Scorer", scorer.score());
-        explanation.add_detail(self.similarity_weight.explain(fieldnorm_id, phrase_count));
+        explanation.add_detail(self.similarity_weight.explain(fieldnorm, phrase_count));
         Ok(explanation)
     }
 }
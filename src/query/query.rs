@@ -1,6 +1,7 @@
 use super::Weight;
 use crate::core::searcher::Searcher;
-use crate::query::Explanation;
+use crate::query::matched_terms::matched_terms;
+use crate::query::{Explanation, MatchedTerm};
 use crate::DocAddress;
 use crate::Term;
 use downcast_rs::impl_downcast;
@@ -49,16 +50,35 @@ pub trait Query: QueryClone + Send + Sync + downcast_rs::Downcast + fmt::Debug {
     /// See [`Weight`](./trait.Weight.html).
     fn weight(&self, searcher: &Searcher, scoring_enabled: bool) -> crate::Result<Box<dyn Weight>>;
 
+    /// Rewrites the query into an equivalent query that is cheaper to execute.
+    ///
+    /// Rewriting happens before the weight is created, and may rely on the
+    /// statistics of the searcher: multi-term queries can for instance be expanded into
+    /// the terms they match, nested boolean queries flattened, or clauses that
+    /// are always or never matching folded.
+    ///
+    /// The rewritten query must match the same documents. If `scoring_enabled`
+    /// is true, it must also give them the same scores.
+    ///
+    /// Returns `None` if the query cannot be simplified, which is the default.
+    fn rewrite(
+        &self,
+        _searcher: &Searcher,
+        _scoring_enabled: bool,
+    ) -> crate::Result<Option<Box<dyn Query>>> {
+        Ok(None)
+    }
+
     /// Returns an `Explanation` for the score of the document.
     fn explain(&self, searcher: &Searcher, doc_address: DocAddress) -> crate::Result<Explanation> {
         let reader = searcher.segment_reader(doc_address.segment_ord);
-        let weight = self.weight(searcher, true)?;
+        let weight = searcher.filter_weight(self.weight(searcher, true)?);
         weight.explain(reader, doc_address.doc_id)
     }
 
     /// Returns the number of documents matching the query.
     fn count(&self, searcher: &Searcher) -> crate::Result<usize> {
-        let weight = self.weight(searcher, false)?;
+        let weight = searcher.filter_weight(self.weight(searcher, false)?);
         let mut result = 0;
         for reader in searcher.segment_readers() {
             result += weight.count(reader)? as usize;
@@ -72,6 +92,23 @@ pub trait Query: QueryClone + Send + Sync + downcast_rs::Downcast + fmt::Debug {
     /// Each term is associated with a boolean indicating whether
     /// Positions are required or not.
     fn query_terms(&self, _term_set: &mut BTreeMap<Term, bool>) {}
+
+    /// Returns the terms of the query that are found in the given document, in term order,
+    /// along with their positions and offsets if their fields record them.
+    ///
+    /// This tells why a document matched, or which parts of it to highlight.
+    /// The query is rewritten beforehand, so that multi-term queries report the
+    /// terms they match. Terms are reported whether or not the document matches the query
+    /// as a whole, e.g. the terms of a phrase that are not adjacent in the document.
+    ///
+    /// Only the terms reported by [`query_terms`](#method.query_terms) are looked up.
+    fn matched_terms(
+        &self,
+        searcher: &Searcher,
+        doc_address: DocAddress,
+    ) -> crate::Result<Vec<MatchedTerm>> {
+        matched_terms(self.box_clone().as_ref(), searcher, doc_address)
+    }
 }
 
 /// Implements `box_clone`.
@@ -94,6 +131,14 @@ impl Query for Box<dyn Query> {
         self.as_ref().weight(searcher, scoring_enabled)
     }
 
+    fn rewrite(
+        &self,
+        searcher: &Searcher,
+        scoring_enabled: bool,
+    ) -> crate::Result<Option<Box<dyn Query>>> {
+        self.as_ref().rewrite(searcher, scoring_enabled)
+    }
+
     fn count(&self, searcher: &Searcher) -> crate::Result<usize> {
         self.as_ref().count(searcher)
     }
@@ -101,6 +146,14 @@ impl Query for Box<dyn Query> {
     fn query_terms(&self, terms: &mut BTreeMap<Term, bool>) {
         self.as_ref().query_terms(terms);
     }
+
+    fn matched_terms(
+        &self,
+        searcher: &Searcher,
+        doc_address: DocAddress,
+    ) -> crate::Result<Vec<MatchedTerm>> {
+        self.as_ref().matched_terms(searcher, doc_address)
+    }
 }
 
 impl QueryClone for Box<dyn Query> {
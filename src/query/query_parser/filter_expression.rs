@@ -0,0 +1,476 @@
+use super::QueryParserError;
+use crate::common::{f64_bounds_to_u64_bounds, BitSet};
+use crate::fastfield::FastFieldReader;
+use crate::query::DocFilter;
+use crate::schema::{Field, Term, Type};
+use crate::SegmentReader;
+use std::ops::Bound;
+use std::sync::Arc;
+
+/// Comparison operators of the filter expressions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum CompareOp {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// AST of a filter expression, before the resolution of its fields and values.
+///
+/// Values are kept as text, and parsed according to the type of their field
+/// by the `QueryParser`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum FilterAst {
+    And(Vec<FilterAst>),
+    Or(Vec<FilterAst>),
+    Not(Box<FilterAst>),
+    Compare {
+        field: String,
+        op: CompareOp,
+        value: String,
+    },
+    In {
+        field: String,
+        values: Vec<String>,
+    },
+    Between {
+        field: String,
+        lower: String,
+        upper: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    // A field name, a keyword or an unquoted value.
+    Word(String),
+    // A single-quoted value.
+    Quoted(String),
+    Op(&'static str),
+    LeftParen,
+    RightParen,
+    Comma,
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || "_.-+:".contains(c)
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, QueryParserError> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        match c {
+            '(' | ')' | ',' => {
+                chars.next();
+                tokens.push(match c {
+                    '(' => Token::LeftParen,
+                    ')' => Token::RightParen,
+                    _ => Token::Comma,
+                });
+            }
+            '\'' => {
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        // A quote is escaped by doubling it, as in SQL.
+                        Some('\'') if chars.peek() == Some(&'\'') => {
+                            chars.next();
+                            value.push('\'');
+                        }
+                        Some('\'') => break,
+                        Some(c) => value.push(c),
+                        None => {
+                            return Err(QueryParserError::FilterSyntaxError(
+                                "unterminated quoted value".to_string(),
+                            ))
+                        }
+                    }
+                }
+                tokens.push(Token::Quoted(value));
+            }
+            '=' | '!' | '<' | '>' => {
+                chars.next();
+                let op = match (c, chars.peek()) {
+                    ('!', Some('=')) | ('<', Some('>')) => "!=",
+                    ('<', Some('=')) => "<=",
+                    ('>', Some('=')) => ">=",
+                    ('!', _) => {
+                        return Err(QueryParserError::FilterSyntaxError(
+                            "expected '!='".to_string(),
+                        ))
+                    }
+                    ('=', _) => "=",
+                    ('<', _) => "<",
+                    _ => ">",
+                };
+                if op.len() == 2 {
+                    chars.next();
+                }
+                tokens.push(Token::Op(op));
+            }
+            _ if is_word_char(c) => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !is_word_char(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+            _ => {
+                return Err(QueryParserError::FilterSyntaxError(format!(
+                    "unexpected character '{}'",
+                    c
+                )))
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+const KEYWORDS: [&str; 5] = ["AND", "OR", "NOT", "IN", "BETWEEN"];
+
+fn is_keyword(word: &str) -> bool {
+    KEYWORDS
+        .iter()
+        .any(|keyword| keyword.eq_ignore_ascii_case(word))
+}
+
+struct FilterParser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl FilterParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn unexpected(&self, expected: &str) -> QueryParserError {
+        let found = match self.peek() {
+            Some(token) => format!("{:?}", token),
+            None => "the end of the expression".to_string(),
+        };
+        QueryParserError::FilterSyntaxError(format!("expected {}, found {}", expected, found))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if let Some(Token::Word(word)) = self.peek() {
+            if word.eq_ignore_ascii_case(keyword) {
+                self.pos += 1;
+                return true;
+            }
+        }
+        false
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), QueryParserError> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.unexpected(keyword))
+        }
+    }
+
+    fn expect(&mut self, expected_token: Token, expected: &str) -> Result<(), QueryParserError> {
+        if self.peek() == Some(&expected_token) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.unexpected(expected))
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<FilterAst, QueryParserError> {
+        let mut children = vec![self.parse_and()?];
+        while self.eat_keyword("OR") {
+            children.push(self.parse_and()?);
+        }
+        Ok(if children.len() == 1 {
+            children.pop().unwrap()
+        } else {
+            FilterAst::Or(children)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<FilterAst, QueryParserError> {
+        let mut children = vec![self.parse_not()?];
+        while self.eat_keyword("AND") {
+            children.push(self.parse_not()?);
+        }
+        Ok(if children.len() == 1 {
+            children.pop().unwrap()
+        } else {
+            FilterAst::And(children)
+        })
+    }
+
+    fn parse_not(&mut self) -> Result<FilterAst, QueryParserError> {
+        if self.eat_keyword("NOT") {
+            return Ok(FilterAst::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_field(&mut self) -> Result<String, QueryParserError> {
+        match self.peek() {
+            Some(Token::Word(word)) if !is_keyword(word) => {
+                let field = word.clone();
+                self.pos += 1;
+                Ok(field)
+            }
+            _ => Err(self.unexpected("a field name")),
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<String, QueryParserError> {
+        match self.peek() {
+            Some(Token::Word(word)) if !is_keyword(word) => {
+                let value = word.clone();
+                self.pos += 1;
+                Ok(value)
+            }
+            Some(Token::Quoted(value)) => {
+                let value = value.clone();
+                self.pos += 1;
+                Ok(value)
+            }
+            _ => Err(self.unexpected("a value")),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<FilterAst, QueryParserError> {
+        if self.peek() == Some(&Token::LeftParen) {
+            self.pos += 1;
+            let ast = self.parse_or()?;
+            self.expect(Token::RightParen, "')'")?;
+            return Ok(ast);
+        }
+        let field = self.parse_field()?;
+        let mut negated = self.eat_keyword("NOT");
+        let ast = if self.eat_keyword("IN") {
+            self.expect(Token::LeftParen, "'('")?;
+            let mut values = vec![self.parse_value()?];
+            while self.peek() == Some(&Token::Comma) {
+                self.pos += 1;
+                values.push(self.parse_value()?);
+            }
+            self.expect(Token::RightParen, "')'")?;
+            FilterAst::In { field, values }
+        } else if self.eat_keyword("BETWEEN") {
+            let lower = self.parse_value()?;
+            self.expect_keyword("AND")?;
+            let upper = self.parse_value()?;
+            FilterAst::Between {
+                field,
+                lower,
+                upper,
+            }
+        } else if negated {
+            return Err(self.unexpected("IN or BETWEEN"));
+        } else {
+            let op = match self.peek() {
+                Some(Token::Op(op)) => *op,
+                _ => return Err(self.unexpected("a comparison operator")),
+            };
+            self.pos += 1;
+            let value = self.parse_value()?;
+            let op = match op {
+                "=" => CompareOp::Eq,
+                "!=" => {
+                    negated = true;
+                    CompareOp::Eq
+                }
+                "<" => CompareOp::Lt,
+                "<=" => CompareOp::Le,
+                ">" => CompareOp::Gt,
+                _ => CompareOp::Ge,
+            };
+            FilterAst::Compare { field, op, value }
+        };
+        Ok(if negated {
+            FilterAst::Not(Box::new(ast))
+        } else {
+            ast
+        })
+    }
+}
+
+/// Parses a filter expression into its AST.
+pub(crate) fn parse_filter_expression(expression: &str) -> Result<FilterAst, QueryParserError> {
+    let mut parser = FilterParser {
+        tokens: tokenize(expression)?,
+        pos: 0,
+    };
+    let ast = parser.parse_or()?;
+    if parser.peek().is_some() {
+        return Err(parser.unexpected("the end of the expression"));
+    }
+    Ok(ast)
+}
+
+/// Filters the documents on the values of a single-valued numeric fast field,
+/// for the fields that are not indexed.
+///
+/// A document is allowed if its value belongs to any of the ranges.
+pub(crate) struct FastFieldFilter {
+    field: Field,
+    ranges: Vec<(Bound<u64>, Bound<u64>)>,
+}
+
+impl FastFieldFilter {
+    pub fn new(
+        field: Field,
+        value_type: Type,
+        ranges: Vec<(Bound<Term>, Bound<Term>)>,
+    ) -> FastFieldFilter {
+        let ranges = ranges
+            .into_iter()
+            .map(|(left_bound, right_bound)| {
+                if value_type == Type::F64 {
+                    let to_f64 = |bound: Bound<Term>| map_bound(bound, |term| term.get_f64());
+                    f64_bounds_to_u64_bounds(to_f64(left_bound), to_f64(right_bound))
+                } else {
+                    // The terms of numeric fields hold the value mapped to a `u64`,
+                    // as in the fast field.
+                    let to_u64 = |bound: Bound<Term>| map_bound(bound, |term| term.get_u64());
+                    (to_u64(left_bound), to_u64(right_bound))
+                }
+            })
+            .collect();
+        FastFieldFilter { field, ranges }
+    }
+}
+
+fn map_bound<TFrom, TTo>(bound: Bound<TFrom>, transform: impl Fn(TFrom) -> TTo) -> Bound<TTo> {
+    match bound {
+        Bound::Included(val) => Bound::Included(transform(val)),
+        Bound::Excluded(val) => Bound::Excluded(transform(val)),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+fn contains(range: &(Bound<u64>, Bound<u64>), val: u64) -> bool {
+    let above_left = match range.0 {
+        Bound::Included(left) => val >= left,
+        Bound::Excluded(left) => val > left,
+        Bound::Unbounded => true,
+    };
+    let below_right = match range.1 {
+        Bound::Included(right) => val <= right,
+        Bound::Excluded(right) => val < right,
+        Bound::Unbounded => true,
+    };
+    above_left && below_right
+}
+
+impl DocFilter for FastFieldFilter {
+    fn filter_bitset(&self, segment_reader: &SegmentReader) -> crate::Result<Arc<BitSet>> {
+        let fast_field_reader = segment_reader.fast_fields().u64_lenient(self.field)?;
+        let max_doc = segment_reader.max_doc();
+        let mut bitset = BitSet::with_max_value(max_doc);
+        for doc in 0..max_doc {
+            let val = fast_field_reader.get(doc);
+            if self.ranges.iter().any(|range| contains(range, val)) {
+                bitset.insert(doc);
+            }
+        }
+        Ok(Arc::new(bitset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_filter_expression, CompareOp, FilterAst};
+
+    fn compare(field: &str, op: CompareOp, value: &str) -> FilterAst {
+        FilterAst::Compare {
+            field: field.to_string(),
+            op,
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_filter_expression() {
+        assert_eq!(
+            parse_filter_expression(
+                "price < 100 AND category IN ('a', 'b''c') AND ts BETWEEN 1 and 5"
+            ),
+            Ok(FilterAst::And(vec![
+                compare("price", CompareOp::Lt, "100"),
+                FilterAst::In {
+                    field: "category".to_string(),
+                    values: vec!["a".to_string(), "b'c".to_string()],
+                },
+                FilterAst::Between {
+                    field: "ts".to_string(),
+                    lower: "1".to_string(),
+                    upper: "5".to_string(),
+                },
+            ]))
+        );
+        assert_eq!(
+            parse_filter_expression("a >= -1.5 OR b != 'x' AND NOT (c <= 2 OR d <> 3)"),
+            Ok(FilterAst::Or(vec![
+                compare("a", CompareOp::Ge, "-1.5"),
+                FilterAst::And(vec![
+                    FilterAst::Not(Box::new(compare("b", CompareOp::Eq, "x"))),
+                    FilterAst::Not(Box::new(FilterAst::Or(vec![
+                        compare("c", CompareOp::Le, "2"),
+                        FilterAst::Not(Box::new(compare("d", CompareOp::Eq, "3"))),
+                    ]))),
+                ]),
+            ]))
+        );
+        assert_eq!(
+            parse_filter_expression("ts NOT BETWEEN '2020-01-01T00:00:00Z' AND 3"),
+            Ok(FilterAst::Not(Box::new(FilterAst::Between {
+                field: "ts".to_string(),
+                lower: "2020-01-01T00:00:00Z".to_string(),
+                upper: "3".to_string(),
+            })))
+        );
+        assert_eq!(
+            parse_filter_expression("a>1"),
+            Ok(compare("a", CompareOp::Gt, "1"))
+        );
+    }
+
+    #[test]
+    fn test_parse_filter_expression_errors() {
+        for expression in &[
+            "",
+            "a",
+            "a = ",
+            "a = 'b",
+            "a = 1 AND",
+            "a IN ()",
+            "a IN (1, 2",
+            "a BETWEEN 1 OR 2",
+            "a NOT = 1",
+            "(a = 1",
+            "a = 1)",
+            "a ! 1",
+            "AND = 1",
+            "a = 1 b = 2",
+            "a = #",
+        ] {
+            assert!(
+                parse_filter_expression(expression).is_err(),
+                "{:?} should not parse",
+                expression
+            );
+        }
+    }
+}
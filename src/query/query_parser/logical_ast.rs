@@ -17,6 +17,10 @@ pub enum LogicalLiteral {
         upper: Bound<Term>,
     },
     All,
+    Wildcard {
+        field: Field,
+        pattern: String,
+    },
 }
 
 pub enum LogicalAst {
@@ -82,6 +86,14 @@ impl fmt::Debug for LogicalLiteral {
                 ..
             } => write!(formatter, "({:?} TO {:?})", lower, upper),
             LogicalLiteral::All => write!(formatter, "*"),
+            LogicalLiteral::Wildcard { field, ref pattern } => {
+                write!(
+                    formatter,
+                    "Wildcard(field={},pattern={})",
+                    field.field_id(),
+                    pattern
+                )
+            }
         }
     }
 }
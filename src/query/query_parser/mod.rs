@@ -1,3 +1,4 @@
+mod filter_expression;
 mod query_parser;
 
 pub mod logical_ast;
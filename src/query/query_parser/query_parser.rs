@@ -1,23 +1,29 @@
+use super::filter_expression::{parse_filter_expression, CompareOp, FastFieldFilter, FilterAst};
 use super::logical_ast::*;
 use crate::core::Index;
+use crate::query::wildcard_query::has_leading_wildcard;
 use crate::query::BooleanQuery;
+use crate::query::ConstScoreQuery;
 use crate::query::EmptyQuery;
+use crate::query::FilteredQuery;
 use crate::query::Occur;
 use crate::query::PhraseQuery;
 use crate::query::Query;
 use crate::query::RangeQuery;
 use crate::query::TermQuery;
+use crate::query::TermSetQuery;
+use crate::query::WildcardQuery;
 use crate::query::{AllQuery, BoostQuery};
-use crate::schema::{Facet, FacetParseError, IndexRecordOption};
+use crate::schema::{Cardinality, Facet, FacetParseError, IndexRecordOption, Type};
 use crate::schema::{Field, Schema};
 use crate::schema::{FieldType, Term};
 use crate::tokenizer::TokenizerManager;
 use crate::Score;
-use std::borrow::Cow;
 use std::collections::HashMap;
 use std::num::{ParseFloatError, ParseIntError};
 use std::ops::Bound;
 use std::str::FromStr;
+use std::sync::Arc;
 use tantivy_query_grammar::{UserInputAst, UserInputBound, UserInputLeaf};
 
 /// Possible error that may happen when parsing a query.
@@ -57,6 +63,9 @@ pub enum QueryParserError {
     /// have any positions indexed.
     #[error("The field '{0:?}' does not have positions indexed")]
     FieldDoesNotHavePositionsIndexed(String),
+    /// A phrase query was requested for a field declared as filter-only.
+    #[error("The field '{0:?}' is filter-only and does not support phrase queries")]
+    FieldIsFilterOnly(String),
     /// The tokenizer for the given field is unknown
     /// The two argument strings are the name of the field, the name of the tokenizer
     #[error("The tokenizer '{0:?}' for the field '{1:?}' is unknown")]
@@ -71,6 +80,20 @@ pub enum QueryParserError {
     /// The format for the facet field is invalid.
     #[error("The facet field is malformed: {0}")]
     FacetFormatError(FacetParseError),
+    /// The query contains a wildcard pattern starting with a wildcard,
+    /// while leading wildcards are not allowed.
+    #[error("Leading wildcards are not allowed: '{0:?}'")]
+    LeadingWildcardNotAllowed(String),
+    /// The query contains a wildcard pattern for a field that is not a text field.
+    #[error("Wildcard patterns are only supported on text fields: '{0:?}'")]
+    WildcardOnNonTextField(String),
+    /// The filter expression is not valid.
+    #[error("Syntax error in filter expression: {0}")]
+    FilterSyntaxError(String),
+    /// A value of a filter expression does not produce exactly one term
+    /// once tokenized.
+    #[error("The filter value '{0:?}' is not a single term")]
+    FilterValueIsNotASingleTerm(String),
 }
 
 impl From<ParseIntError> for QueryParserError {
@@ -97,6 +120,40 @@ impl From<FacetParseError> for QueryParserError {
     }
 }
 
+/// Parses the value of a `u64`, `i64`, `f64` or date field into a term.
+///
+/// Returns `None` if the field is not of one of these types.
+fn compute_numeric_term(
+    field: Field,
+    field_type: &FieldType,
+    phrase: &str,
+) -> Result<Option<Term>, QueryParserError> {
+    let term = match *field_type {
+        FieldType::I64(_) => Term::from_field_i64(field, i64::from_str(phrase)?),
+        FieldType::F64(_) => Term::from_field_f64(field, f64::from_str(phrase)?),
+        FieldType::Date(_) => {
+            let date = chrono::DateTime::parse_from_rfc3339(phrase)?;
+            Term::from_field_date(field, &date.with_timezone(&chrono::Utc))
+        }
+        FieldType::U64(_) => Term::from_field_u64(field, u64::from_str(phrase)?),
+        _ => return Ok(None),
+    };
+    Ok(Some(term))
+}
+
+/// Returns true if `field_type` is a single-valued `u64`, `i64`, `f64` or date fast field.
+fn is_single_valued_numeric_fast_field(field_type: &FieldType) -> bool {
+    match *field_type {
+        FieldType::U64(ref int_options)
+        | FieldType::I64(ref int_options)
+        | FieldType::F64(ref int_options)
+        | FieldType::Date(ref int_options) => {
+            int_options.get_fastfield_cardinality() == Some(Cardinality::SingleValue)
+        }
+        _ => false,
+    }
+}
+
 /// Recursively remove empty clause from the AST
 ///
 /// Returns `None` iff the `logical_ast` ended up being empty.
@@ -171,6 +228,16 @@ fn trim_ast(logical_ast: LogicalAst) -> Option<LogicalAst> {
 ///
 /// *  all docs query: A plain `*` will match all documents in the index.
 ///
+/// * wildcard terms: `*` matches any sequence of characters, and `?` matches a single
+///   character. e.g. `title:barr*` or `title:b?rack`. Wildcard patterns are matched against
+///   the terms of the term dictionary without being tokenized. On fields that are not indexed
+///   with the `raw` tokenizer, they are lowercased.
+///   Patterns starting with a wildcard are rejected, unless allowed via
+///   [`set_allow_leading_wildcard(...)`](#method.set_allow_leading_wildcard).
+///
+/// Filters over structured fields can also be expressed with SQL-like expressions,
+/// parsed with [`parse_filter(...)`](#method.parse_filter).
+///
 /// Parts of the queries can be boosted by appending `^boostfactor`.
 /// For instance, `"SRE"^2.0 OR devops^0.4` will boost documents containing `SRE` instead of
 /// devops. Negative boosts are not allowed.
@@ -178,6 +245,11 @@ fn trim_ast(logical_ast: LogicalAst) -> Option<LogicalAst> {
 /// It is also possible to define a boost for a some specific field, at the query parser level.
 /// (See [`set_boost(...)`](#method.set_field_boost) ). Typically you may want to boost a title
 /// field.
+///
+/// Queries can refer to fields through aliases (See [`set_field_alias(...)`](#method.set_field_alias)),
+/// or to several fields at once through named groups of fields
+/// (See [`set_field_group(...)`](#method.set_field_group)), so that the schema can evolve
+/// without changing the syntax of the queries.
 #[derive(Clone)]
 pub struct QueryParser {
     schema: Schema,
@@ -185,6 +257,10 @@ pub struct QueryParser {
     conjunction_by_default: bool,
     tokenizer_manager: TokenizerManager,
     boost: HashMap<Field, Score>,
+    field_aliases: HashMap<String, Field>,
+    field_groups: HashMap<String, Vec<(Field, Score)>>,
+    allow_leading_wildcard: bool,
+    allow_phrase_gaps: bool,
 }
 
 fn all_negative(ast: &LogicalAst) -> bool {
@@ -213,6 +289,10 @@ impl QueryParser {
             tokenizer_manager,
             conjunction_by_default: false,
             boost: Default::default(),
+            field_aliases: Default::default(),
+            field_groups: Default::default(),
+            allow_leading_wildcard: false,
+            allow_phrase_gaps: false,
         }
     }
 
@@ -233,6 +313,24 @@ impl QueryParser {
         self.conjunction_by_default = true;
     }
 
+    /// Allows or denies wildcard patterns starting with a wildcard, such as `*ing`.
+    ///
+    /// Such patterns have to scan the whole term dictionary of the field, and are
+    /// denied by default.
+    pub fn set_allow_leading_wildcard(&mut self, allow_leading_wildcard: bool) {
+        self.allow_leading_wildcard = allow_leading_wildcard;
+    }
+
+    /// Allows the gaps left in phrases by the stop words to be matched by fewer positions
+    /// in the documents.
+    ///
+    /// For instance, if `of` and `the` are stop words, the phrase `"part of the time"`
+    /// then matches `part time` as well. (See [`PhraseQuery::allow_gaps`](
+    ///     ../query/struct.PhraseQuery.html#method.allow_gaps))
+    pub fn set_allow_phrase_gaps(&mut self, allow_phrase_gaps: bool) {
+        self.allow_phrase_gaps = allow_phrase_gaps;
+    }
+
     /// Sets a boost for a specific field.
     ///
     /// The parse query will automatically boost this field.
@@ -244,6 +342,29 @@ impl QueryParser {
         self.boost.insert(field, boost);
     }
 
+    /// Registers `alias` as another name for `field`.
+    ///
+    /// For instance, after calling `set_field_alias("body", text_field)`, the query
+    /// `body:hello` searches for `hello` in `text_field`.
+    /// Aliases and field groups take precedence over the names of the fields of the schema.
+    pub fn set_field_alias(&mut self, alias: &str, field: Field) {
+        self.field_aliases.insert(alias.to_string(), field);
+    }
+
+    /// Registers a named group of fields, each given with a boost.
+    ///
+    /// For instance, after calling
+    /// `set_field_group("all", vec![(title, 2.0), (body, 1.0), (tags, 1.0)])`, the query
+    /// `all:hello` searches for `hello` in `title`, `body` and `tags`, as if these fields were
+    /// the default fields.
+    /// The boost of a field in the group is multiplied with the boost defined for the field
+    /// through [`set_field_boost(...)`](#method.set_field_boost), if any.
+    ///
+    /// Aliases and field groups take precedence over the names of the fields of the schema.
+    pub fn set_field_group(&mut self, name: &str, fields: Vec<(Field, Score)>) {
+        self.field_groups.insert(name.to_string(), fields);
+    }
+
     /// Parse a query
     ///
     /// Note that `parse_query` returns an error if the input
@@ -256,7 +377,186 @@ impl QueryParser {
     /// in [Issue 5](https://github.com/fulmicoton/tantivy/issues/5)
     pub fn parse_query(&self, query: &str) -> Result<Box<dyn Query>, QueryParserError> {
         let logical_ast = self.parse_query_to_logical_ast(query)?;
-        Ok(convert_to_query(logical_ast))
+        Ok(convert_to_query(logical_ast, self.allow_phrase_gaps))
+    }
+
+    /// Parses a SQL-like filter expression, such as
+    /// `price < 100 AND category IN ('a', 'b') AND ts BETWEEN '2021-01-01T00:00:00Z' AND '2021-02-01T00:00:00Z'`.
+    ///
+    /// The expression combines comparisons of a field to a value (`=`, `!=` or `<>`, `<`,
+    /// `<=`, `>`, `>=`), `field [NOT] IN (value, ...)` and
+    /// `field [NOT] BETWEEN lower AND upper` (both bounds included), with `AND`, `OR`, `NOT`
+    /// and parentheses. `AND` takes precedence over `OR`, and keywords are case insensitive.
+    /// Values are numbers, or single-quoted strings in which a quote is escaped by doubling it.
+    /// They are parsed according to the type of their field, as in [`parse_query`](#method.parse_query).
+    /// In particular, dates are rfc3339 formatted, and the values of text fields are tokenized
+    /// and must produce a single term.
+    ///
+    /// Indexed fields are filtered through their postings. Numeric and date fields that are not
+    /// indexed can be filtered if they are single-valued fast fields: their values are then
+    /// scanned for each segment.
+    ///
+    /// The returned query gives the same score to all the documents it matches, so that it can be
+    /// used as a filter clause (e.g. with `Occur::Must`) alongside full-text queries in a
+    /// `BooleanQuery` without changing their ranking.
+    pub fn parse_filter(&self, expression: &str) -> Result<Box<dyn Query>, QueryParserError> {
+        let filter_ast = parse_filter_expression(expression)?;
+        let query = self.compute_filter_query(filter_ast)?;
+        Ok(Box::new(ConstScoreQuery::new(query)))
+    }
+
+    fn compute_filter_query(
+        &self,
+        filter_ast: FilterAst,
+    ) -> Result<Box<dyn Query>, QueryParserError> {
+        match filter_ast {
+            FilterAst::And(children) => self.compute_filter_clauses(Occur::Must, children),
+            FilterAst::Or(children) => self.compute_filter_clauses(Occur::Should, children),
+            FilterAst::Not(child) => {
+                // A `BooleanQuery` made of excluded clauses only does not match anything.
+                let excluded_query = self.compute_filter_query(*child)?;
+                Ok(Box::new(BooleanQuery::new(vec![
+                    (Occur::Must, Box::new(AllQuery)),
+                    (Occur::MustNot, excluded_query),
+                ])))
+            }
+            FilterAst::Compare { field, op, value } => {
+                self.compute_filter_query_for_fields(&field, |field| {
+                    let term = self.compute_filter_term(field, &value)?;
+                    let range = match op {
+                        CompareOp::Eq => (Bound::Included(term.clone()), Bound::Included(term)),
+                        CompareOp::Lt => (Bound::Unbounded, Bound::Excluded(term)),
+                        CompareOp::Le => (Bound::Unbounded, Bound::Included(term)),
+                        CompareOp::Gt => (Bound::Excluded(term), Bound::Unbounded),
+                        CompareOp::Ge => (Bound::Included(term), Bound::Unbounded),
+                    };
+                    self.compute_filter_ranges_query(field, vec![range])
+                })
+            }
+            FilterAst::In { field, values } => {
+                self.compute_filter_query_for_fields(&field, |field| {
+                    let ranges = values
+                        .iter()
+                        .map(|value| {
+                            let term = self.compute_filter_term(field, value)?;
+                            Ok((Bound::Included(term.clone()), Bound::Included(term)))
+                        })
+                        .collect::<Result<Vec<_>, QueryParserError>>()?;
+                    self.compute_filter_ranges_query(field, ranges)
+                })
+            }
+            FilterAst::Between {
+                field,
+                lower,
+                upper,
+            } => self.compute_filter_query_for_fields(&field, |field| {
+                let range = (
+                    Bound::Included(self.compute_filter_term(field, &lower)?),
+                    Bound::Included(self.compute_filter_term(field, &upper)?),
+                );
+                self.compute_filter_ranges_query(field, vec![range])
+            }),
+        }
+    }
+
+    /// Builds the union of the queries built by `field_query` for each of the fields
+    /// `field_name` refers to.
+    fn compute_filter_query_for_fields(
+        &self,
+        field_name: &str,
+        field_query: impl Fn(Field) -> Result<Box<dyn Query>, QueryParserError>,
+    ) -> Result<Box<dyn Query>, QueryParserError> {
+        let mut queries = self
+            .resolved_fields(Some(field_name))?
+            .into_iter()
+            .map(|(field, _)| field_query(field))
+            .collect::<Result<Vec<_>, QueryParserError>>()?;
+        if queries.len() == 1 {
+            return Ok(queries.pop().unwrap());
+        }
+        Ok(Box::new(BooleanQuery::union(queries)))
+    }
+
+    fn compute_filter_clauses(
+        &self,
+        occur: Occur,
+        children: Vec<FilterAst>,
+    ) -> Result<Box<dyn Query>, QueryParserError> {
+        let clauses = children
+            .into_iter()
+            .map(|child| Ok((occur, self.compute_filter_query(child)?)))
+            .collect::<Result<Vec<_>, QueryParserError>>()?;
+        Ok(Box::new(BooleanQuery::new(clauses)))
+    }
+
+    fn compute_filter_term(&self, field: Field, value: &str) -> Result<Term, QueryParserError> {
+        let field_entry = self.schema.get_field_entry(field);
+        if !field_entry.is_indexed() {
+            return compute_numeric_term(field, field_entry.field_type(), value)?
+                .ok_or_else(|| QueryParserError::FieldNotIndexed(field_entry.name().to_string()));
+        }
+        let mut terms = self.compute_terms_for_string(field, value)?;
+        if terms.len() != 1 {
+            return Err(QueryParserError::FilterValueIsNotASingleTerm(
+                value.to_string(),
+            ));
+        }
+        Ok(terms.pop().unwrap().1)
+    }
+
+    /// Builds a query matching the documents with a value of `field` in any of `ranges`.
+    fn compute_filter_ranges_query(
+        &self,
+        field: Field,
+        ranges: Vec<(Bound<Term>, Bound<Term>)>,
+    ) -> Result<Box<dyn Query>, QueryParserError> {
+        let field_entry = self.schema.get_field_entry(field);
+        let value_type = field_entry.field_type().value_type();
+        if !field_entry.is_indexed() {
+            if !is_single_valued_numeric_fast_field(field_entry.field_type()) {
+                return Err(QueryParserError::FieldNotIndexed(
+                    field_entry.name().to_string(),
+                ));
+            }
+            let filter = FastFieldFilter::new(field, value_type, ranges);
+            return Ok(Box::new(FilteredQuery::new(
+                Box::new(AllQuery),
+                Arc::new(filter),
+            )));
+        }
+        // `f64` values are compared with range queries, so that `-0.0` equals `0.0`.
+        let is_single_term = |range: &(Bound<Term>, Bound<Term>)| match range {
+            (Bound::Included(lower), Bound::Included(upper)) => lower == upper,
+            _ => false,
+        };
+        if value_type != Type::F64 && ranges.iter().all(is_single_term) {
+            let mut terms: Vec<Term> = ranges
+                .into_iter()
+                .filter_map(|(lower, _)| match lower {
+                    Bound::Included(term) => Some(term),
+                    _ => None,
+                })
+                .collect();
+            if terms.len() == 1 {
+                let term = terms.pop().unwrap();
+                return Ok(Box::new(TermQuery::new(term, IndexRecordOption::Basic)));
+            }
+            let term_set_query =
+                TermSetQuery::new(field, terms).expect("The terms belong to the field");
+            return Ok(Box::new(term_set_query));
+        }
+        let mut range_queries: Vec<Box<dyn Query>> = ranges
+            .into_iter()
+            .map(|(lower, upper)| {
+                Box::new(RangeQuery::new_term_bounds(
+                    field, value_type, &lower, &upper,
+                )) as Box<dyn Query>
+            })
+            .collect();
+        if range_queries.len() == 1 {
+            return Ok(range_queries.pop().unwrap());
+        }
+        Ok(Box::new(BooleanQuery::union(range_queries)))
     }
 
     /// Parse the user query into an AST.
@@ -266,10 +566,26 @@ impl QueryParser {
         self.compute_logical_ast(user_input_ast)
     }
 
-    fn resolve_field_name(&self, field_name: &str) -> Result<Field, QueryParserError> {
-        self.schema
-            .get_field(field_name)
-            .ok_or_else(|| QueryParserError::FieldDoesNotExist(String::from(field_name)))
+    /// Resolves a field name of a query into the fields it refers to, each given with its boost
+    /// in the field group, and the attribute referred to if the field is dynamic.
+    fn resolve_field_name<'a>(
+        &self,
+        field_name: &'a str,
+    ) -> Result<Vec<(Field, Option<&'a str>, Score)>, QueryParserError> {
+        if let Some(fields) = self.field_groups.get(field_name) {
+            return Ok(fields
+                .iter()
+                .map(|&(field, boost)| (field, None, boost))
+                .collect());
+        }
+        if let Some(&field) = self.field_aliases.get(field_name) {
+            return Ok(vec![(field, None, 1.0)]);
+        }
+        let (field, attribute_opt) = self
+            .schema
+            .resolve_field_name(field_name)
+            .ok_or_else(|| QueryParserError::FieldDoesNotExist(field_name.to_string()))?;
+        Ok(vec![(field, attribute_opt, 1.0)])
     }
 
     fn compute_logical_ast(
@@ -299,28 +615,12 @@ impl QueryParser {
             let field_name = field_entry.name().to_string();
             return Err(QueryParserError::FieldNotIndexed(field_name));
         }
+        if let Some(term) = compute_numeric_term(field, field_type, phrase)? {
+            return Ok(vec![(0, term)]);
+        }
         match *field_type {
-            FieldType::I64(_) => {
-                let val: i64 = i64::from_str(phrase)?;
-                let term = Term::from_field_i64(field, val);
-                Ok(vec![(0, term)])
-            }
-            FieldType::F64(_) => {
-                let val: f64 = f64::from_str(phrase)?;
-                let term = Term::from_field_f64(field, val);
-                Ok(vec![(0, term)])
-            }
-            FieldType::Date(_) => match chrono::DateTime::parse_from_rfc3339(phrase) {
-                Ok(x) => Ok(vec![(
-                    0,
-                    Term::from_field_date(field, &x.with_timezone(&chrono::Utc)),
-                )]),
-                Err(e) => Err(QueryParserError::DateFormatError(e)),
-            },
-            FieldType::U64(_) => {
-                let val: u64 = u64::from_str(phrase)?;
-                let term = Term::from_field_u64(field, val);
-                Ok(vec![(0, term)])
+            FieldType::I64(_) | FieldType::F64(_) | FieldType::Date(_) | FieldType::U64(_) => {
+                unreachable!("Numeric terms are computed by compute_numeric_term")
             }
             FieldType::Str(ref str_options) => {
                 if let Some(option) = str_options.get_indexing_options() {
@@ -343,6 +643,10 @@ impl QueryParser {
                         Ok(vec![])
                     } else if terms.len() == 1 {
                         Ok(terms)
+                    } else if option.is_filter_only() {
+                        Err(QueryParserError::FieldIsFilterOnly(
+                            field_entry.name().to_string(),
+                        ))
                     } else {
                         let field_entry = self.schema.get_field_entry(field);
                         let field_type = field_entry.field_type();
@@ -382,9 +686,16 @@ impl QueryParser {
     fn compute_logical_ast_for_leaf(
         &self,
         field: Field,
+        attribute_opt: Option<&str>,
         phrase: &str,
     ) -> Result<Option<LogicalLiteral>, QueryParserError> {
-        let terms = self.compute_terms_for_string(field, phrase)?;
+        let mut terms = self.compute_terms_for_string(field, phrase)?;
+        if let Some(attribute) = attribute_opt {
+            // The terms of the attributes of a dynamic field are prefixed by their name.
+            for (_, term) in terms.iter_mut() {
+                *term = Term::from_dynamic_field_text(field, attribute, term.text());
+            }
+        }
         match &terms[..] {
             [] => Ok(None),
             [(_, term)] => Ok(Some(LogicalLiteral::Term(term.clone()))),
@@ -392,6 +703,31 @@ impl QueryParser {
         }
     }
 
+    fn compute_wildcard_pattern(
+        &self,
+        field: Field,
+        pattern: &str,
+    ) -> Result<String, QueryParserError> {
+        let field_entry = self.schema.get_field_entry(field);
+        let indexing_options = match field_entry.field_type() {
+            FieldType::Str(ref str_options) => str_options.get_indexing_options(),
+            _ => {
+                return Err(QueryParserError::WildcardOnNonTextField(
+                    field_entry.name().to_string(),
+                ));
+            }
+        };
+        match indexing_options {
+            Some(indexing_options) if indexing_options.tokenizer() == "raw" => {
+                Ok(pattern.to_string())
+            }
+            Some(_) => Ok(pattern.to_lowercase()),
+            None => Err(QueryParserError::FieldNotIndexed(
+                field_entry.name().to_string(),
+            )),
+        }
+    }
+
     fn default_occur(&self) -> Occur {
         if self.conjunction_by_default {
             Occur::Must
@@ -420,19 +756,34 @@ impl QueryParser {
         }
     }
 
+    /// Returns the fields searched for a field name of a query, or the default fields,
+    /// each given with its boost in the field group.
+    ///
+    /// The attributes of dynamic fields are not supported.
     fn resolved_fields(
         &self,
-        given_field: &Option<String>,
-    ) -> Result<Cow<'_, [Field]>, QueryParserError> {
-        match *given_field {
+        given_field: Option<&str>,
+    ) -> Result<Vec<(Field, Score)>, QueryParserError> {
+        match given_field {
             None => {
                 if self.default_fields.is_empty() {
                     Err(QueryParserError::NoDefaultFieldDeclared)
                 } else {
-                    Ok(Cow::from(&self.default_fields[..]))
+                    Ok(self
+                        .default_fields
+                        .iter()
+                        .map(|&field| (field, 1.0))
+                        .collect())
                 }
             }
-            Some(ref field) => Ok(Cow::from(vec![self.resolve_field_name(&*field)?])),
+            Some(field_name) => self
+                .resolve_field_name(field_name)?
+                .into_iter()
+                .map(|(field, attribute_opt, boost)| match attribute_opt {
+                    Some(_) => Err(QueryParserError::FieldDoesNotExist(field_name.to_string())),
+                    None => Ok((field, boost)),
+                })
+                .collect(),
         }
     }
 
@@ -469,27 +820,26 @@ impl QueryParser {
     ) -> Result<LogicalAst, QueryParserError> {
         match leaf {
             UserInputLeaf::Literal(literal) => {
-                let term_phrases: Vec<(Field, String)> = match literal.field_name {
-                    Some(ref field_name) => {
-                        let field = self.resolve_field_name(field_name)?;
-                        vec![(field, literal.phrase.clone())]
-                    }
+                let term_fields: Vec<(Field, Option<&str>, Score)> = match literal.field_name {
+                    Some(ref field_name) => self.resolve_field_name(field_name)?,
                     None => {
                         if self.default_fields.is_empty() {
                             return Err(QueryParserError::NoDefaultFieldDeclared);
                         } else {
                             self.default_fields
                                 .iter()
-                                .map(|default_field| (*default_field, literal.phrase.clone()))
-                                .collect::<Vec<(Field, String)>>()
+                                .map(|default_field| (*default_field, None, 1.0))
+                                .collect::<Vec<(Field, Option<&str>, Score)>>()
                         }
                     }
                 };
                 let mut asts: Vec<LogicalAst> = Vec::new();
-                for (field, phrase) in term_phrases {
-                    if let Some(ast) = self.compute_logical_ast_for_leaf(field, &phrase)? {
+                for (field, attribute_opt, group_boost) in term_fields {
+                    if let Some(ast) =
+                        self.compute_logical_ast_for_leaf(field, attribute_opt, &literal.phrase)?
+                    {
                         // Apply some field specific boost defined at the query parser level.
-                        let boost = self.field_boost(field);
+                        let boost = self.field_boost(field) * group_boost;
                         asts.push(LogicalAst::Leaf(Box::new(ast)).boost(boost));
                     }
                 }
@@ -501,16 +851,47 @@ impl QueryParser {
                 Ok(result_ast)
             }
             UserInputLeaf::All => Ok(LogicalAst::Leaf(Box::new(LogicalLiteral::All))),
+            UserInputLeaf::Wildcard {
+                field_name,
+                pattern,
+            } => {
+                if !self.allow_leading_wildcard && has_leading_wildcard(&pattern) {
+                    return Err(QueryParserError::LeadingWildcardNotAllowed(pattern));
+                }
+                let fields = self.resolved_fields(field_name.as_deref())?;
+                let mut clauses = fields
+                    .into_iter()
+                    .map(|(field, group_boost)| {
+                        let boost = self.field_boost(field) * group_boost;
+                        let logical_ast = LogicalAst::Leaf(Box::new(LogicalLiteral::Wildcard {
+                            field,
+                            pattern: self.compute_wildcard_pattern(field, &pattern)?,
+                        }));
+                        Ok(logical_ast.boost(boost))
+                    })
+                    .collect::<Result<Vec<_>, QueryParserError>>()?;
+                let result_ast = if clauses.len() == 1 {
+                    clauses.pop().unwrap()
+                } else {
+                    LogicalAst::Clause(
+                        clauses
+                            .into_iter()
+                            .map(|clause| (Occur::Should, clause))
+                            .collect(),
+                    )
+                };
+                Ok(result_ast)
+            }
             UserInputLeaf::Range {
                 field,
                 lower,
                 upper,
             } => {
-                let fields = self.resolved_fields(&field)?;
+                let fields = self.resolved_fields(field.as_deref())?;
                 let mut clauses = fields
-                    .iter()
-                    .map(|&field| {
-                        let boost = self.field_boost(field);
+                    .into_iter()
+                    .map(|(field, group_boost)| {
+                        let boost = self.field_boost(field) * group_boost;
                         let field_entry = self.schema.get_field_entry(field);
                         let value_type = field_entry.field_type().value_type();
                         let logical_ast = LogicalAst::Leaf(Box::new(LogicalLiteral::Range {
@@ -538,11 +919,14 @@ impl QueryParser {
     }
 }
 
-fn convert_literal_to_query(logical_literal: LogicalLiteral) -> Box<dyn Query> {
+fn convert_literal_to_query(
+    logical_literal: LogicalLiteral,
+    allow_phrase_gaps: bool,
+) -> Box<dyn Query> {
     match logical_literal {
         LogicalLiteral::Term(term) => Box::new(TermQuery::new(term, IndexRecordOption::WithFreqs)),
         LogicalLiteral::Phrase(term_with_offsets) => {
-            Box::new(PhraseQuery::new_with_offset(term_with_offsets))
+            Box::new(PhraseQuery::new_with_offset(term_with_offsets).allow_gaps(allow_phrase_gaps))
         }
         LogicalLiteral::Range {
             field,
@@ -553,15 +937,19 @@ fn convert_literal_to_query(logical_literal: LogicalLiteral) -> Box<dyn Query> {
             field, value_type, &lower, &upper,
         )),
         LogicalLiteral::All => Box::new(AllQuery),
+        LogicalLiteral::Wildcard { field, pattern } => {
+            // Leading wildcards have already been checked by the query parser.
+            Box::new(WildcardQuery::new(field, &pattern).allow_leading_wildcard(true))
+        }
     }
 }
 
-fn convert_to_query(logical_ast: LogicalAst) -> Box<dyn Query> {
+fn convert_to_query(logical_ast: LogicalAst, allow_phrase_gaps: bool) -> Box<dyn Query> {
     match trim_ast(logical_ast) {
         Some(LogicalAst::Clause(trimmed_clause)) => {
             let occur_subqueries = trimmed_clause
                 .into_iter()
-                .map(|(occur, subquery)| (occur, convert_to_query(subquery)))
+                .map(|(occur, subquery)| (occur, convert_to_query(subquery, allow_phrase_gaps)))
                 .collect::<Vec<_>>();
             assert!(
                 !occur_subqueries.is_empty(),
@@ -570,10 +958,10 @@ fn convert_to_query(logical_ast: LogicalAst) -> Box<dyn Query> {
             Box::new(BooleanQuery::new(occur_subqueries))
         }
         Some(LogicalAst::Leaf(trimmed_logical_literal)) => {
-            convert_literal_to_query(*trimmed_logical_literal)
+            convert_literal_to_query(*trimmed_logical_literal, allow_phrase_gaps)
         }
         Some(LogicalAst::Boost(ast, boost)) => {
-            let query = convert_to_query(*ast);
+            let query = convert_to_query(*ast, allow_phrase_gaps);
             let boosted_query = BoostQuery::new(query, boost);
             Box::new(boosted_query)
         }
@@ -619,6 +1007,7 @@ mod test {
         schema_builder.add_facet_field("facet_not_indexed", STORED);
         schema_builder.add_bytes_field("bytes", INDEXED);
         schema_builder.add_bytes_field("bytes_not_indexed", STORED);
+        schema_builder.add_dynamic_text_field("attr_*", TEXT);
         schema_builder.build()
     }
 
@@ -715,6 +1104,110 @@ mod test {
         );
     }
 
+    #[test]
+    pub fn test_parse_query_wildcard() {
+        test_parse_query_to_logical_ast_helper(
+            "title:Barr*",
+            "Wildcard(field=0,pattern=barr*)",
+            false,
+        );
+        test_parse_query_to_logical_ast_helper(
+            "b?rack",
+            "(Wildcard(field=0,pattern=b?rack) Wildcard(field=1,pattern=b?rack))",
+            false,
+        );
+        test_parse_query_to_logical_ast_helper(
+            "nottokenized:Barr*",
+            "Wildcard(field=7,pattern=Barr*)",
+            false,
+        );
+        assert_matches!(
+            parse_query_to_logical_ast("title:*ack", false),
+            Err(QueryParserError::LeadingWildcardNotAllowed(_))
+        );
+        assert_matches!(
+            parse_query_to_logical_ast("unsigned:1*", false),
+            Err(QueryParserError::WildcardOnNonTextField(_))
+        );
+        let mut query_parser = make_query_parser();
+        query_parser.set_allow_leading_wildcard(true);
+        let query = query_parser.parse_query("title:*ack").unwrap();
+        assert_eq!(
+            format!("{:?}", query),
+            "WildcardQuery { field: Field(0), pattern: \"*ack\", allow_leading_wildcard: true, \
+             max_expansions: 1024 }"
+        );
+    }
+
+    #[test]
+    pub fn test_parse_query_field_aliases_and_groups() {
+        let mut query_parser = make_query_parser();
+        let schema = make_schema();
+        let title_field = schema.get_field("title").unwrap();
+        let text_field = schema.get_field("text").unwrap();
+        let nottokenized_field = schema.get_field("nottokenized").unwrap();
+        query_parser.set_field_alias("body", text_field);
+        query_parser.set_field_alias("signed", text_field);
+        query_parser.set_field_group(
+            "all",
+            vec![
+                (title_field, 2.0),
+                (text_field, 1.0),
+                (nottokenized_field, 1.0),
+            ],
+        );
+        query_parser.set_field_boost(text_field, 1.5);
+        let parse = |query: &str| {
+            let logical_ast = query_parser.parse_query_to_logical_ast(query).unwrap();
+            format!("{:?}", logical_ast)
+        };
+        assert_eq!(
+            parse("body:hello"),
+            "Term(field=1,bytes=[104, 101, 108, 108, 111])^1.5"
+        );
+        // Aliases take precedence over the fields of the schema.
+        assert_eq!(
+            parse("signed:hello"),
+            "Term(field=1,bytes=[104, 101, 108, 108, 111])^1.5"
+        );
+        assert_eq!(
+            parse("all:hello"),
+            "(Term(field=0,bytes=[104, 101, 108, 108, 111])^2 \
+             Term(field=1,bytes=[104, 101, 108, 108, 111])^1.5 \
+             Term(field=7,bytes=[104, 101, 108, 108, 111]))"
+        );
+        assert_eq!(
+            parse("all:[a TO b}"),
+            "((Included(Term(field=0,bytes=[97])) TO Excluded(Term(field=0,bytes=[98])))^2 \
+             (Included(Term(field=1,bytes=[97])) TO Excluded(Term(field=1,bytes=[98])))^1.5 \
+             (Included(Term(field=7,bytes=[97])) TO Excluded(Term(field=7,bytes=[98]))))"
+        );
+        assert_eq!(
+            parse("all:hel*"),
+            "(Wildcard(field=0,pattern=hel*)^2 \
+             Wildcard(field=1,pattern=hel*)^1.5 \
+             Wildcard(field=7,pattern=hel*))"
+        );
+        assert!(query_parser.parse_filter("all = 'hello'").is_ok());
+        assert_matches!(
+            query_parser.parse_query("everything:hello"),
+            Err(QueryParserError::FieldDoesNotExist(_))
+        );
+    }
+
+    #[test]
+    pub fn test_parse_query_dynamic_field() {
+        test_parse_query_to_logical_ast_helper(
+            "attr_color:Red",
+            "Term(field=15,bytes=[99, 111, 108, 111, 114, 0, 114, 101, 100])",
+            false,
+        );
+        assert_matches!(
+            parse_query_to_logical_ast("attr_:red", false),
+            Err(QueryParserError::FieldDoesNotExist(_))
+        );
+    }
+
     #[test]
     pub fn test_parse_nonindexed_field_yields_error() {
         let query_parser = make_query_parser();
@@ -1005,6 +1498,23 @@ mod test {
         );
     }
 
+    #[test]
+    pub fn test_query_parser_filter_only() {
+        let mut schema_builder = Schema::builder();
+        let text_field_indexing = TextFieldIndexing::default()
+            .set_index_option(IndexRecordOption::WithFreqsAndPositions)
+            .set_filter_only();
+        let text_options = TextOptions::default().set_indexing_options(text_field_indexing);
+        let tags = schema_builder.add_text_field("tags", text_options);
+        let index = Index::create_in_ram(schema_builder.build());
+        let query_parser = QueryParser::for_index(&index, vec![tags]);
+        assert_eq!(
+            query_parser.parse_query("tags:\"happy tax\"").unwrap_err(),
+            QueryParserError::FieldIsFilterOnly("tags".to_string())
+        );
+        assert!(query_parser.parse_query("tags:happy").is_ok());
+    }
+
     #[test]
     pub fn test_query_parser_expected_int() {
         let query_parser = make_query_parser();
@@ -1132,4 +1642,93 @@ mod test {
             );
         }
     }
+
+    #[test]
+    fn test_parse_filter() -> crate::Result<()> {
+        use crate::collector::Count;
+        use crate::query::{BooleanQuery, Occur};
+        use crate::schema::{Cardinality, IntOptions, FAST};
+        use chrono::TimeZone;
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let category = schema_builder.add_text_field("category", STRING);
+        let price = schema_builder.add_f64_field("price", FAST);
+        let count = schema_builder.add_u64_field("count", INDEXED);
+        let ts = schema_builder.add_date_field("ts", INDEXED);
+        schema_builder.add_u64_field(
+            "counts",
+            IntOptions::default().set_fast(Cardinality::MultiValues),
+        );
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        let date = |day: u32| chrono::Utc.ymd(2021, 1, day).and_hms(0, 0, 0);
+        let docs = vec![
+            ("red apple", "a", 10.5f64, 1u64, 1u32),
+            ("green apple", "b", 150.0f64, 2u64, 2u32),
+            ("red car", "c", -0.0f64, 3u64, 3u32),
+            ("blue car", "b'c", 99.0f64, 4u64, 4u32),
+        ];
+        for (title_val, category_val, price_val, count_val, day) in docs {
+            index_writer.add_document(doc!(
+                title => title_val,
+                category => category_val,
+                price => price_val,
+                count => count_val,
+                ts => date(day),
+            ));
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query_parser = QueryParser::for_index(&index, vec![title]);
+        let count_filter = |expression: &str| -> crate::Result<usize> {
+            let query = query_parser.parse_filter(expression)?;
+            searcher.search(&query, &Count)
+        };
+        assert_eq!(count_filter("price < 100")?, 3);
+        assert_eq!(count_filter("price = 0")?, 1);
+        assert_eq!(count_filter("price >= 99 AND price <= 150")?, 2);
+        assert_eq!(count_filter("category IN ('a', 'b')")?, 2);
+        assert_eq!(count_filter("category = 'b''c'")?, 1);
+        assert_eq!(count_filter("category NOT IN ('a', 'b')")?, 2);
+        assert_eq!(count_filter("count != 2")?, 3);
+        assert_eq!(count_filter("count BETWEEN 2 AND 3 OR count > 3")?, 3);
+        assert_eq!(
+            count_filter(
+                "price < 100 AND category IN ('a','b','c') \
+                 AND ts BETWEEN '2021-01-01T00:00:00Z' AND '2021-01-02T00:00:00Z'"
+            )?,
+            1
+        );
+        assert_eq!(count_filter("NOT (title = 'red' OR price > 100)")?, 1);
+        assert_eq!(count_filter("title = 'Red'")?, 2);
+
+        let text_query = query_parser.parse_query("apple")?;
+        let filtered_query = BooleanQuery::new(vec![
+            (Occur::Must, text_query),
+            (Occur::Must, query_parser.parse_filter("price < 100")?),
+        ]);
+        assert_eq!(searcher.search(&filtered_query, &Count)?, 1);
+
+        assert_matches!(
+            query_parser.parse_filter("price <"),
+            Err(QueryParserError::FilterSyntaxError(_))
+        );
+        assert_matches!(
+            query_parser.parse_filter("title = 'red car'"),
+            Err(QueryParserError::FilterValueIsNotASingleTerm(_))
+        );
+        assert_matches!(
+            query_parser.parse_filter("counts = 1"),
+            Err(QueryParserError::FieldNotIndexed(_))
+        );
+        assert_matches!(
+            query_parser.parse_filter("missing = 1"),
+            Err(QueryParserError::FieldDoesNotExist(_))
+        );
+        assert_matches!(
+            query_parser.parse_filter("count = 'a'"),
+            Err(QueryParserError::ExpectedInt(_))
+        );
+        Ok(())
+    }
 }
@@ -1,17 +1,20 @@
-use crate::common::BitSet;
+use crate::common::numeric_trie::{split_range, PrefixRange};
+use crate::common::{f64_bounds_to_u64_bounds, BitSet};
 use crate::core::Searcher;
 use crate::core::SegmentReader;
 use crate::error::TantivyError;
 use crate::query::explanation::does_not_match;
-use crate::query::ConstScorer;
 use crate::query::{BitSetDocSet, Explanation};
+use crate::query::{ConstScorer, EmptyScorer};
 use crate::query::{Query, Scorer, Weight};
 use crate::schema::Type;
 use crate::schema::{Field, IndexRecordOption, Term};
 use crate::termdict::{TermDictionary, TermStreamer};
-use crate::{DocId, Score};
+use crate::{DocId, InvertedIndexReader, MemoryBudget, Score};
+use std::convert::TryFrom;
 use std::io;
-use std::ops::{Bound, Range};
+use std::ops::{Bound, Range, RangeInclusive};
+use tantivy_fst::Automaton;
 
 fn map_bound<TFrom, TTo, Transform: Fn(&TFrom) -> TTo>(
     bound: &Bound<TFrom>,
@@ -34,6 +37,11 @@ fn map_bound<TFrom, TTo, Transform: Fn(&TFrom) -> TTo>(
 /// The current implement will iterate over the terms within the range
 /// and append all of the document cross into a `BitSet`.
 ///
+/// If the field is a numeric field indexed at several precisions
+/// (see [`IntOptions::set_precision_step`](../schema/struct.IntOptions.html#method.set_precision_step)),
+/// the range is split into a few ranges of low precision terms, so that the number of
+/// terms read only grows logarithmically with the width of the range.
+///
 /// # Example
 ///
 /// ```rust
@@ -84,6 +92,17 @@ impl RangeQuery {
         left_bound: &Bound<Term>,
         right_bound: &Bound<Term>,
     ) -> RangeQuery {
+        if value_type == Type::F64 {
+            let unwrap_f64 = |val: &Term| {
+                assert_eq!(field, val.field());
+                val.get_f64()
+            };
+            return RangeQuery::new_f64_bounds(
+                field,
+                map_bound(left_bound, &unwrap_f64),
+                map_bound(right_bound, &unwrap_f64),
+            );
+        }
         let verify_and_unwrap_term = |val: &Term| {
             assert_eq!(field, val.field());
             val.value_bytes().to_owned()
@@ -146,6 +165,10 @@ impl RangeQuery {
     /// The two `Bound` arguments make it possible to create more complex
     /// ranges than semi-inclusive range.
     ///
+    /// The range follows the usual comparison of `f64`: `-0.0` and `0.0` are equal,
+    /// and NaN values are never matched, even by unbounded ranges.
+    /// A range with a NaN bound matches no document.
+    ///
     /// If the field is not of the type `f64`, tantivy
     /// will panic when the `Weight` object is created.
     pub fn new_f64_bounds(
@@ -153,7 +176,8 @@ impl RangeQuery {
         left_bound: Bound<f64>,
         right_bound: Bound<f64>,
     ) -> RangeQuery {
-        let make_term_val = |val: &f64| Term::from_field_f64(field, *val).value_bytes().to_owned();
+        let (left_bound, right_bound) = f64_bounds_to_u64_bounds(left_bound, right_bound);
+        let make_term_val = |val: &u64| Term::from_field_u64(field, *val).value_bytes().to_owned();
         RangeQuery {
             field,
             value_type: Type::F64,
@@ -259,18 +283,137 @@ impl Query for RangeQuery {
             );
             return Err(TantivyError::SchemaError(err_msg));
         }
+        let prefix_ranges = schema
+            .get_field_entry(self.field)
+            .field_type()
+            .get_precision_step()
+            .and_then(|precision_step| self.split_into_prefix_ranges(precision_step));
         Ok(Box::new(RangeWeight {
             field: self.field,
             left_bound: self.left_bound.clone(),
             right_bound: self.right_bound.clone(),
+            prefix_ranges,
+            numeric_range: self.numeric_range(),
+            memory_budget: searcher.memory_budget().clone(),
         }))
     }
 }
 
+fn numeric_value(bytes: &[u8]) -> Option<u64> {
+    <[u8; 8]>::try_from(bytes).ok().map(u64::from_be_bytes)
+}
+
+impl RangeQuery {
+    // Returns the inclusive range of the values matched by the query,
+    // or `None` if the bounds are not numeric values.
+    //
+    // The returned range is empty if no value can match.
+    fn numeric_range(&self) -> Option<RangeInclusive<u64>> {
+        use std::ops::Bound::*;
+        let empty_range = 1u64..=0u64;
+        let start = match self.left_bound {
+            Included(ref bytes) => numeric_value(bytes)?,
+            Excluded(ref bytes) => match numeric_value(bytes)?.checked_add(1) {
+                Some(start) => start,
+                None => return Some(empty_range),
+            },
+            Unbounded => 0u64,
+        };
+        let end = match self.right_bound {
+            Included(ref bytes) => numeric_value(bytes)?,
+            Excluded(ref bytes) => match numeric_value(bytes)?.checked_sub(1) {
+                Some(end) => end,
+                None => return Some(empty_range),
+            },
+            Unbounded => u64::MAX,
+        };
+        Some(start..=end)
+    }
+
+    // Returns the ranges of prefixes covering the range of values,
+    // or `None` if the bounds are not numeric values.
+    fn split_into_prefix_ranges(&self, precision_step: u8) -> Option<Vec<PrefixRange>> {
+        let numeric_range = self.numeric_range()?;
+        if numeric_range.is_empty() {
+            return Some(Vec::new());
+        }
+        Some(split_range(
+            *numeric_range.start(),
+            *numeric_range.end(),
+            precision_step,
+        ))
+    }
+}
+
+/// Automaton matching the low precision terms of a numeric field
+/// belonging to one of the given ranges of prefixes.
+struct PrefixRangesAutomaton<'a> {
+    prefix_ranges: &'a [PrefixRange],
+}
+
+/// State of a `PrefixRangesAutomaton`: the bytes accepted so far.
+#[derive(Clone, Copy)]
+struct PrefixState {
+    num_bytes: usize,
+    prefix: u64,
+}
+
+impl<'a> Automaton for PrefixRangesAutomaton<'a> {
+    type State = Option<PrefixState>;
+
+    fn start(&self) -> Self::State {
+        Some(PrefixState {
+            num_bytes: 0,
+            prefix: 0u64,
+        })
+    }
+
+    fn is_match(&self, state: &Self::State) -> bool {
+        state
+            .map(|state| {
+                self.prefix_ranges.iter().any(|prefix_range| {
+                    prefix_range.num_bytes == state.num_bytes
+                        && prefix_range.start <= state.prefix
+                        && state.prefix <= prefix_range.end
+                })
+            })
+            .unwrap_or(false)
+    }
+
+    fn can_match(&self, state: &Self::State) -> bool {
+        state.is_some()
+    }
+
+    fn accept(&self, state: &Self::State, byte: u8) -> Self::State {
+        let state = (*state)?;
+        let num_bytes = state.num_bytes + 1;
+        let prefix = (state.prefix << 8) | u64::from(byte);
+        // Terms longer than the prefixes of all of the ranges are skipped
+        // without being read.
+        let can_match = self.prefix_ranges.iter().any(|prefix_range| {
+            if prefix_range.num_bytes < num_bytes {
+                return false;
+            }
+            let shift = 8 * (prefix_range.num_bytes - num_bytes);
+            (prefix_range.start >> shift) <= prefix && prefix <= (prefix_range.end >> shift)
+        });
+        if can_match {
+            Some(PrefixState { num_bytes, prefix })
+        } else {
+            None
+        }
+    }
+}
+
 pub struct RangeWeight {
     field: Field,
     left_bound: Bound<Vec<u8>>,
     right_bound: Bound<Vec<u8>>,
+    // Set if the field is indexed at several precisions.
+    prefix_ranges: Option<Vec<PrefixRange>>,
+    // Range of the matched values, if the bounds are numeric values.
+    numeric_range: Option<RangeInclusive<u64>>,
+    memory_budget: MemoryBudget,
 }
 
 impl RangeWeight {
@@ -291,28 +434,47 @@ impl RangeWeight {
     }
 }
 
+fn insert_docs<A: Automaton>(
+    mut term_stream: TermStreamer<'_, A>,
+    inverted_index: &InvertedIndexReader,
+    doc_bitset: &mut BitSet,
+) -> io::Result<()> {
+    while term_stream.advance() {
+        let term_info = term_stream.value();
+        let mut block_segment_postings = inverted_index
+            .read_block_postings_from_terminfo(term_info, IndexRecordOption::Basic)?;
+        loop {
+            let docs = block_segment_postings.docs();
+            if docs.is_empty() {
+                break;
+            }
+            for &doc in block_segment_postings.docs() {
+                doc_bitset.insert(doc);
+            }
+            block_segment_postings.advance();
+        }
+    }
+    Ok(())
+}
+
 impl Weight for RangeWeight {
     fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
+        if !self.may_match_segment(reader) {
+            return Ok(Box::new(EmptyScorer));
+        }
         let max_doc = reader.max_doc();
+        self.memory_budget.allocate(BitSet::memory_usage(max_doc))?;
         let mut doc_bitset = BitSet::with_max_value(max_doc);
 
         let inverted_index = reader.inverted_index(self.field)?;
         let term_dict = inverted_index.terms();
-        let mut term_range = self.term_range(term_dict)?;
-        while term_range.advance() {
-            let term_info = term_range.value();
-            let mut block_segment_postings = inverted_index
-                .read_block_postings_from_terminfo(term_info, IndexRecordOption::Basic)?;
-            loop {
-                let docs = block_segment_postings.docs();
-                if docs.is_empty() {
-                    break;
-                }
-                for &doc in block_segment_postings.docs() {
-                    doc_bitset.insert(doc);
-                }
-                block_segment_postings.advance();
-            }
+        if let Some(prefix_ranges) = self.prefix_ranges.as_ref() {
+            let automaton = PrefixRangesAutomaton { prefix_ranges };
+            let term_stream = term_dict.search(automaton).into_stream()?;
+            insert_docs(term_stream, &inverted_index, &mut doc_bitset)?;
+        } else {
+            let term_range = self.term_range(term_dict)?;
+            insert_docs(term_range, &inverted_index, &mut doc_bitset)?;
         }
         let doc_bitset = BitSetDocSet::from(doc_bitset);
         Ok(Box::new(ConstScorer::new(doc_bitset, boost)))
@@ -325,16 +487,61 @@ impl Weight for RangeWeight {
         }
         Ok(Explanation::new("RangeQuery", 1.0))
     }
+
+    fn may_match_segment(&self, reader: &SegmentReader) -> bool {
+        match (
+            self.numeric_range.as_ref(),
+            reader.fast_field_range(self.field),
+        ) {
+            (Some(numeric_range), Some(segment_range)) => {
+                !numeric_range.is_empty()
+                    && numeric_range.start() <= segment_range.end()
+                    && segment_range.start() <= numeric_range.end()
+            }
+            _ => true,
+        }
+    }
+
+    fn count(&self, reader: &SegmentReader) -> crate::Result<u32> {
+        if !self.may_match_segment(reader) {
+            return Ok(0);
+        }
+        if let (Some(numeric_range), Some(segment_range)) = (
+            self.numeric_range.as_ref(),
+            reader.fast_field_range(self.field),
+        ) {
+            // If all of the values of the segment are in the range, all of its documents
+            // match, provided that none of them is missing the field. Documents missing the
+            // field get a default value in the fast field, hence the check of the doc count,
+            // which is cached by the segment reader.
+            if numeric_range.start() <= segment_range.start()
+                && segment_range.end() <= numeric_range.end()
+                && reader.field_doc_count(self.field)? == reader.max_doc()
+            {
+                return Ok(reader.num_docs());
+            }
+        }
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if let Some(delete_bitset) = reader.delete_bitset() {
+            Ok(scorer.count(delete_bitset))
+        } else {
+            Ok(scorer.count_including_deleted())
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
-    use super::RangeQuery;
+    use super::{PrefixRangesAutomaton, RangeQuery};
     use crate::collector::{Count, TopDocs};
-    use crate::query::QueryParser;
-    use crate::schema::{Document, Field, Schema, INDEXED, TEXT};
-    use crate::Index;
+    use crate::indexer::NoMergePolicy;
+    use crate::query::{AllQuery, BooleanQuery, Occur, Query, QueryParser, TermQuery};
+    use crate::schema::{
+        Document, Field, IndexRecordOption, IntOptions, Schema, FAST, INDEXED, TEXT,
+    };
+    use crate::{i64_to_u64, Index, Term};
+    use futures::executor::block_on;
     use std::ops::Bound;
 
     #[test]
@@ -481,6 +688,36 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_range_float_zero_and_nan() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let float_field = schema_builder.add_f64_field("float", INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for &val in &[-0.0f64, 0.0f64, f64::NAN, -f64::NAN, -1.5f64, f64::INFINITY] {
+            index_writer.add_document(doc!(float_field => val));
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let count = |left: Bound<f64>, right: Bound<f64>| {
+            searcher.search(
+                &RangeQuery::new_f64_bounds(float_field, left, right),
+                &Count,
+            )
+        };
+        assert_eq!(count(Bound::Included(0.0), Bound::Excluded(1.0))?, 2);
+        assert_eq!(count(Bound::Excluded(-1.0), Bound::Included(-0.0))?, 2);
+        assert_eq!(count(Bound::Excluded(-0.0), Bound::Unbounded)?, 1);
+        assert_eq!(count(Bound::Unbounded, Bound::Excluded(0.0))?, 1);
+        assert_eq!(count(Bound::Unbounded, Bound::Unbounded)?, 4);
+        assert_eq!(count(Bound::Included(f64::NAN), Bound::Unbounded)?, 0);
+
+        let query_parser = QueryParser::for_index(&index, vec![float_field]);
+        let query = query_parser.parse_query("float:[0 TO 1}")?;
+        assert_eq!(searcher.search(&query, &Count)?, 2);
+        Ok(())
+    }
+
     #[test]
     fn test_bug_reproduce_range_query() -> crate::Result<()> {
         let mut schema_builder = Schema::builder();
@@ -504,4 +741,201 @@ mod tests {
         assert_eq!(top_docs.len(), 1);
         Ok(())
     }
+
+    #[test]
+    fn test_range_query_precision_step() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let plain_field = schema_builder.add_i64_field("plain", INDEXED);
+        let trie_options = IntOptions::default().set_indexed().set_precision_step(8);
+        let trie_field = schema_builder.add_i64_field("trie", trie_options);
+        let float_options = IntOptions::default().set_indexed().set_precision_step(16);
+        let float_field = schema_builder.add_f64_field("float", float_options);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for i in 0..3_000i64 {
+            let val = (i - 1_500) * 37;
+            index_writer.add_document(doc!(
+                plain_field => val,
+                trie_field => val,
+                float_field => val as f64 / 10.0,
+            ));
+            if i % 1_000 == 999 {
+                index_writer.commit()?;
+            }
+        }
+        let reader = index.reader()?;
+        let check_ranges = || -> crate::Result<()> {
+            reader.reload()?;
+            let searcher = reader.searcher();
+            let count = |query: RangeQuery| searcher.search(&query, &Count);
+            let ranges = [
+                (-55_000, 55_000),
+                (-100_000, 100_000),
+                (-55_000, -1),
+                (0, 1),
+                (37, 38),
+                (-1_000_000, -60_000),
+                (i64::MIN, i64::MAX),
+            ];
+            for &(start, end) in &ranges {
+                let expected = count(RangeQuery::new_i64(plain_field, start..end))?;
+                assert_eq!(
+                    count(RangeQuery::new_i64(trie_field, start..end))?,
+                    expected
+                );
+                let float_range = start as f64 / 10.0..end as f64 / 10.0;
+                assert_eq!(
+                    count(RangeQuery::new_f64(float_field, float_range))?,
+                    expected
+                );
+                let excluded_included = |field| {
+                    RangeQuery::new_i64_bounds(field, Bound::Excluded(start), Bound::Included(end))
+                };
+                assert_eq!(
+                    count(excluded_included(trie_field))?,
+                    count(excluded_included(plain_field))?
+                );
+            }
+            assert_eq!(
+                count(RangeQuery::new_i64(trie_field, -55_000..55_000))?,
+                2_973
+            );
+            assert_eq!(count(RangeQuery::new_i64(trie_field, 0..1))?, 1);
+            assert_eq!(
+                count(RangeQuery::new_i64(trie_field, i64::MIN..i64::MAX))?,
+                3_000
+            );
+            let unbounded_query =
+                RangeQuery::new_i64_bounds(trie_field, Bound::Excluded(i64::MAX), Bound::Unbounded);
+            assert_eq!(count(unbounded_query)?, 0);
+            Ok(())
+        };
+        check_ranges()?;
+        let segment_ids = index.searchable_segment_ids()?;
+        block_on(index_writer.merge(&segment_ids))?;
+        index_writer.wait_merging_threads()?;
+        check_ranges()?;
+
+        // Most of the range is covered by low precision terms.
+        let searcher = reader.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let prefix_ranges = RangeQuery::new_i64(trie_field, -55_000..55_000)
+            .split_into_prefix_ranges(8)
+            .unwrap();
+        let inverted_index = searcher.segment_reader(0).inverted_index(trie_field)?;
+        let automaton = PrefixRangesAutomaton {
+            prefix_ranges: &prefix_ranges,
+        };
+        let mut term_stream = inverted_index.terms().search(automaton).into_stream()?;
+        let mut num_terms = 0;
+        while term_stream.advance() {
+            num_terms += 1;
+        }
+        assert!(num_terms < 600);
+        Ok(())
+    }
+
+    #[test]
+    fn test_range_query_segment_pruning() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let timestamp_field = schema_builder.add_i64_field("timestamp", INDEXED | FAST);
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for timestamp in 0i64..10i64 {
+            index_writer.add_document(doc!(timestamp_field => timestamp, text_field => "old"));
+        }
+        index_writer.commit()?;
+        for timestamp in 100i64..110i64 {
+            index_writer.add_document(doc!(timestamp_field => timestamp, text_field => "new"));
+        }
+        index_writer.commit()?;
+
+        let searcher = index.reader()?.searcher();
+        let mut segment_readers = searcher.segment_readers().to_vec();
+        segment_readers.sort_by_key(|segment_reader| {
+            segment_reader
+                .fast_field_range(timestamp_field)
+                .map(|range| *range.start())
+        });
+        let old_segment = &segment_readers[0];
+        let new_segment = &segment_readers[1];
+        assert_eq!(
+            old_segment.fast_field_range(timestamp_field),
+            Some(i64_to_u64(0)..=i64_to_u64(9))
+        );
+        assert_eq!(old_segment.fast_field_range(text_field), None);
+
+        let recent_query = RangeQuery::new_i64(timestamp_field, 50..200);
+        let recent_weight = recent_query.weight(&searcher, false)?;
+        assert!(!recent_weight.may_match_segment(old_segment));
+        assert!(recent_weight.may_match_segment(new_segment));
+        assert_eq!(searcher.search(&recent_query, &Count)?, 10);
+
+        let boolean_query = BooleanQuery::new(vec![
+            (Occur::Must, Box::new(recent_query) as Box<dyn Query>),
+            (
+                Occur::Must,
+                Box::new(TermQuery::new(
+                    Term::from_field_text(text_field, "old"),
+                    IndexRecordOption::Basic,
+                )),
+            ),
+        ]);
+        let boolean_weight = boolean_query.weight(&searcher, true)?;
+        assert!(!boolean_weight.may_match_segment(old_segment));
+        assert_eq!(searcher.search(&boolean_query, &Count)?, 0);
+
+        // Ranges are recorded for merged segments too.
+        let segment_ids = index.searchable_segment_ids()?;
+        block_on(index_writer.merge(&segment_ids))?;
+        index_writer.wait_merging_threads()?;
+        let segment_metas = index.searchable_segment_metas()?;
+        assert_eq!(segment_metas.len(), 1);
+        assert_eq!(
+            segment_metas[0].fast_field_range(timestamp_field),
+            Some(i64_to_u64(0)..=i64_to_u64(109))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_range_query_count() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let value_field = schema_builder.add_i64_field("value", INDEXED | FAST);
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        for value in 10i64..20i64 {
+            index_writer.add_document(doc!(value_field => value));
+        }
+        index_writer.commit()?;
+        for value in 10i64..15i64 {
+            index_writer.add_document(doc!(value_field => value));
+        }
+        // Missing the field, the document gets a default value in the fast field.
+        index_writer.add_document(doc!(text_field => "missing"));
+        index_writer.commit()?;
+        for value in 50i64..60i64 {
+            index_writer.add_document(doc!(value_field => value));
+        }
+        index_writer.commit()?;
+        index_writer.delete_term(Term::from_field_i64(value_field, 12i64));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 3);
+        let range_query = RangeQuery::new_i64(value_field, 0..30);
+        assert_eq!(searcher.search(&range_query, &Count)?, 13);
+        assert_eq!(searcher.docs_matching(&range_query)?.count(), 13);
+        let weight = range_query.weight(&searcher, false)?;
+        let segment_counts = searcher
+            .segment_readers()
+            .iter()
+            .map(|segment_reader| weight.count(segment_reader))
+            .collect::<crate::Result<Vec<u32>>>()?;
+        assert_eq!(segment_counts, vec![9, 4, 0]);
+        assert_eq!(searcher.search(&AllQuery, &Count)?, 24);
+        Ok(())
+    }
 }
@@ -58,6 +58,16 @@ where
     fn size_hint(&self) -> u32 {
         self.req_scorer.size_hint()
     }
+
+    /// The optional scorer is only sought to the documents
+    /// of the required scorer.
+    fn cost(&self) -> u64 {
+        self.req_scorer.cost()
+            + self
+                .opt_scorer
+                .cost()
+                .min(self.req_scorer.size_hint() as u64)
+    }
 }
 
 impl<TReqScorer, TOptScorer, TScoreCombiner> Scorer
@@ -0,0 +1,229 @@
+use crate::query::Query;
+use crate::Searcher;
+
+/// Maximum number of times a query is rewritten.
+///
+/// Rewriting is expected to converge after a handful of steps. The limit is
+/// only a safeguard against queries whose rewrites do not converge.
+const MAX_REWRITE_ROUNDS: usize = 32;
+
+/// Rewrites a query until it cannot be simplified anymore.
+///
+/// Returns `None` if the query was not rewritten.
+pub(crate) fn try_rewrite_query(
+    query: &dyn Query,
+    searcher: &Searcher,
+    scoring_enabled: bool,
+) -> crate::Result<Option<Box<dyn Query>>> {
+    let mut rewritten_query_opt: Option<Box<dyn Query>> = None;
+    for _ in 0..MAX_REWRITE_ROUNDS {
+        let current_query: &dyn Query = rewritten_query_opt.as_deref().unwrap_or(query);
+        match current_query.rewrite(searcher, scoring_enabled)? {
+            Some(rewritten_query) => {
+                rewritten_query_opt = Some(rewritten_query);
+            }
+            None => {
+                break;
+            }
+        }
+    }
+    Ok(rewritten_query_opt)
+}
+
+/// Rewrites a query into an equivalent query that is cheaper to execute.
+///
+/// See [`Query::rewrite`](./trait.Query.html#method.rewrite).
+///
+/// The rewritten query matches the same documents as `query`.
+/// If `scoring_enabled` is true, the documents also get the same scores.
+///
+/// `Searcher::search` rewrites queries before executing them:
+/// calling this function is only useful to inspect the resulting query.
+pub fn rewrite_query(
+    query: &dyn Query,
+    searcher: &Searcher,
+    scoring_enabled: bool,
+) -> crate::Result<Box<dyn Query>> {
+    let rewritten_query_opt = try_rewrite_query(query, searcher, scoring_enabled)?;
+    Ok(rewritten_query_opt.unwrap_or_else(|| query.box_clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rewrite_query, try_rewrite_query};
+    use crate::collector::{Count, TopDocs};
+    use crate::query::{
+        AllQuery, BooleanQuery, BoostQuery, ConstScoreQuery, EmptyQuery, Occur, Query, RegexQuery,
+        TermQuery,
+    };
+    use crate::schema::{Field, IndexRecordOption, Schema, STRING, TEXT};
+    use crate::{Index, Searcher, Term};
+
+    fn term_query(field: Field, text: &str) -> Box<dyn Query> {
+        Box::new(TermQuery::new(
+            Term::from_field_text(field, text),
+            IndexRecordOption::WithFreqs,
+        ))
+    }
+
+    fn create_index() -> crate::Result<(Index, Field, Field)> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let id_field = schema_builder.add_text_field("id", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field => "a b", id_field => "id0"));
+        index_writer.add_document(doc!(text_field => "a c", id_field => "id1"));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text_field => "b c d", id_field => "id2"));
+        for i in 3..40 {
+            index_writer.add_document(doc!(text_field => "e", id_field => format!("id{}", i)));
+        }
+        index_writer.commit()?;
+        Ok((index, text_field, id_field))
+    }
+
+    fn num_clauses(query: &dyn Query) -> usize {
+        query
+            .downcast_ref::<BooleanQuery>()
+            .unwrap()
+            .clauses()
+            .len()
+    }
+
+    #[test]
+    fn test_rewrite_flattens_boolean_queries() -> crate::Result<()> {
+        let (index, text_field, _) = create_index()?;
+        let searcher = index.reader()?.searcher();
+        // +(a (b c)) -(d e) is flattened into +(a b c) -d -e.
+        let query = BooleanQuery::new(vec![
+            (
+                Occur::Must,
+                Box::new(BooleanQuery::union(vec![
+                    term_query(text_field, "a"),
+                    Box::new(BooleanQuery::union(vec![
+                        term_query(text_field, "b"),
+                        term_query(text_field, "c"),
+                    ])),
+                ])),
+            ),
+            (
+                Occur::MustNot,
+                Box::new(BooleanQuery::union(vec![
+                    term_query(text_field, "d"),
+                    term_query(text_field, "e"),
+                ])),
+            ),
+        ]);
+        let rewritten_query = rewrite_query(&query, &searcher, true)?;
+        assert_eq!(num_clauses(rewritten_query.as_ref()), 3);
+        let (occur, must_subquery) = &rewritten_query
+            .downcast_ref::<BooleanQuery>()
+            .unwrap()
+            .clauses()[0];
+        assert_eq!(*occur, Occur::Must);
+        assert_eq!(num_clauses(must_subquery.as_ref()), 3);
+
+        // +(+a -d) +c is flattened into +a -d +c.
+        let query = BooleanQuery::intersection(vec![
+            Box::new(BooleanQuery::new(vec![
+                (Occur::Must, term_query(text_field, "a")),
+                (Occur::MustNot, term_query(text_field, "d")),
+            ])),
+            term_query(text_field, "c"),
+        ]);
+        let rewritten_query = rewrite_query(&query, &searcher, true)?;
+        assert_eq!(num_clauses(rewritten_query.as_ref()), 3);
+        let top_docs = searcher.search(&query, &TopDocs::with_limit(10))?;
+        let rewritten_top_docs = searcher.search(&*rewritten_query, &TopDocs::with_limit(10))?;
+        assert_eq!(top_docs.len(), 1);
+        assert_eq!(top_docs, rewritten_top_docs);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rewrite_folds_constant_clauses() -> crate::Result<()> {
+        let (index, text_field, _) = create_index()?;
+        let searcher = index.reader()?.searcher();
+        let single_clause = BooleanQuery::union(vec![term_query(text_field, "a")]);
+        let rewritten_query = rewrite_query(&single_clause, &searcher, true)?;
+        assert!(rewritten_query.is::<TermQuery>());
+
+        let with_empty_must = BooleanQuery::intersection(vec![
+            term_query(text_field, "a"),
+            Box::new(BooleanQuery::union(vec![Box::new(EmptyQuery)])),
+        ]);
+        assert!(rewrite_query(&with_empty_must, &searcher, true)?.is::<EmptyQuery>());
+
+        let with_all_must_not = BooleanQuery::new(vec![
+            (Occur::Should, term_query(text_field, "a")),
+            (Occur::MustNot, Box::new(AllQuery)),
+        ]);
+        assert!(rewrite_query(&with_all_must_not, &searcher, true)?.is::<EmptyQuery>());
+
+        let only_must_not = BooleanQuery::new(vec![
+            (Occur::MustNot, term_query(text_field, "a")),
+            (Occur::Should, Box::new(EmptyQuery)),
+        ]);
+        assert!(rewrite_query(&only_must_not, &searcher, true)?.is::<EmptyQuery>());
+
+        // The match-all filter only contributes to the score.
+        let filtered =
+            BooleanQuery::intersection(vec![Box::new(AllQuery), term_query(text_field, "a")]);
+        assert!(try_rewrite_query(&filtered, &searcher, true)?.is_none());
+        assert!(rewrite_query(&filtered, &searcher, false)?.is::<TermQuery>());
+        let all_but_a = BooleanQuery::new(vec![
+            (Occur::Must, Box::new(AllQuery)),
+            (Occur::MustNot, term_query(text_field, "a")),
+        ]);
+        let rewritten_query = rewrite_query(&all_but_a, &searcher, false)?;
+        assert_eq!(num_clauses(rewritten_query.as_ref()), 2);
+        assert_eq!(searcher.search(&all_but_a, &Count)?, 38);
+
+        let boost_query = BoostQuery::new(
+            Box::new(BoostQuery::new(term_query(text_field, "a"), 2.0)),
+            3.0,
+        );
+        let rewritten_query = rewrite_query(&boost_query, &searcher, true)?;
+        assert_eq!(
+            format!("{:?}", rewritten_query),
+            format!("Boost(query={:?}, boost=6)", term_query(text_field, "a"))
+        );
+        assert!(rewrite_query(&boost_query, &searcher, false)?.is::<TermQuery>());
+        Ok(())
+    }
+
+    fn check_regex_query(
+        searcher: &Searcher,
+        query: &RegexQuery,
+        expected_count: usize,
+    ) -> crate::Result<()> {
+        assert_eq!(searcher.search(query, &Count)?, expected_count);
+        let top_docs = searcher.search(query, &TopDocs::with_limit(100))?;
+        assert_eq!(top_docs.len(), expected_count);
+        assert!(top_docs.iter().all(|(score, _)| *score == 1.0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_rewrite_expands_automaton_queries() -> crate::Result<()> {
+        let (index, text_field, id_field) = create_index()?;
+        let searcher = index.reader()?.searcher();
+        // Matches `id1`, `id10`, ..., `id19` in the two segments.
+        let few_terms_query = RegexQuery::from_pattern("id1[0-9]?", id_field)?;
+        let rewritten_query = rewrite_query(&few_terms_query, &searcher, true)?;
+        let const_score_query = rewritten_query.downcast_ref::<ConstScoreQuery>().unwrap();
+        assert_eq!(num_clauses(const_score_query.query()), 11);
+        check_regex_query(&searcher, &few_terms_query, 11)?;
+
+        let many_terms_query = RegexQuery::from_pattern("id.*", id_field)?;
+        assert!(try_rewrite_query(&many_terms_query, &searcher, true)?.is_none());
+        check_regex_query(&searcher, &many_terms_query, 40)?;
+
+        let no_terms_query = RegexQuery::from_pattern("z.*", text_field)?;
+        let rewritten_query = rewrite_query(&no_terms_query, &searcher, true)?;
+        assert!(rewritten_query.is::<EmptyQuery>());
+        check_regex_query(&searcher, &no_terms_query, 0)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,41 @@
+use crate::core::SegmentReader;
+use crate::query::explanation::does_not_match;
+use crate::query::{ConstScorer, Explanation, Scorer, VecDocSet, Weight};
+use crate::schema::Field;
+use crate::{DocId, Score};
+
+/// Weight of a `TermQuery` on a numeric field with a lookup index.
+///
+/// Documents are found with the `FastFieldLookup` of the field, and get a constant score.
+pub(crate) struct FastFieldLookupWeight {
+    field: Field,
+    value: u64,
+}
+
+impl FastFieldLookupWeight {
+    pub fn new(field: Field, value: u64) -> FastFieldLookupWeight {
+        FastFieldLookupWeight { field, value }
+    }
+}
+
+impl Weight for FastFieldLookupWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
+        let lookup = reader.fast_field_lookup(self.field)?;
+        let docs = VecDocSet::from(lookup.docs(self.value).to_vec());
+        Ok(Box::new(ConstScorer::new(docs, boost)))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
+        let lookup = reader.fast_field_lookup(self.field)?;
+        if lookup.docs(self.value).binary_search(&doc).is_err() {
+            return Err(does_not_match(doc));
+        }
+        Ok(Explanation::new("FastFieldLookup", 1.0))
+    }
+
+    fn count(&self, reader: &SegmentReader) -> crate::Result<u32> {
+        let lookup = reader.fast_field_lookup(self.field)?;
+        let docs = lookup.docs(self.value);
+        Ok(docs.iter().filter(|&&doc| !reader.is_deleted(doc)).count() as u32)
+    }
+}
@@ -1,3 +1,4 @@
+mod lookup_weight;
 mod term_query;
 mod term_scorer;
 mod term_weight;
@@ -11,9 +12,10 @@ mod tests {
 
     use crate::collector::TopDocs;
     use crate::docset::DocSet;
+    use crate::indexer::NoMergePolicy;
     use crate::postings::compression::COMPRESSION_BLOCK_SIZE;
     use crate::query::{Query, QueryParser, Scorer, TermQuery};
-    use crate::schema::{Field, IndexRecordOption, Schema, STRING, TEXT};
+    use crate::schema::{Cardinality, Field, IndexRecordOption, IntOptions, Schema, STRING, TEXT};
     use crate::{assert_nearly_equals, DocAddress};
     use crate::{Index, Term, TERMINATED};
 
@@ -215,4 +217,51 @@ mod tests {
         }
         Ok(())
     }
+
+    #[test]
+    fn test_term_query_lookup_index() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let id_options = IntOptions::default()
+            .set_fast(Cardinality::SingleValue)
+            .set_lookup_index();
+        let id_field = schema_builder.add_i64_field("id", id_options);
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        index_writer.add_document(doc!(id_field => -3i64, text_field => "a"));
+        index_writer.add_document(doc!(id_field => 5i64, text_field => "b"));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(id_field => 5i64, text_field => "c"));
+        index_writer.add_document(doc!(id_field => 8i64, text_field => "d"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+        // The field is not indexed: the documents are found through the lookup index.
+        let term_query = TermQuery::new(
+            Term::from_field_i64(id_field, 5i64),
+            IndexRecordOption::Basic,
+        );
+        let top_docs = searcher.search(&term_query, &TopDocs::with_limit(10))?;
+        assert_eq!(
+            top_docs,
+            vec![(1.0, DocAddress::new(0, 1)), (1.0, DocAddress::new(1, 0))]
+        );
+        assert_eq!(term_query.count(&searcher)?, 2);
+        let key = Term::from_field_i64(id_field, -3i64);
+        assert_eq!(searcher.doc_by_key(&key)?, Some(DocAddress::new(0, 0)));
+        index_writer.delete_term(Term::from_field_text(text_field, "b"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(term_query.count(&searcher)?, 1);
+        assert_eq!(
+            searcher.doc_by_key(&Term::from_field_i64(id_field, 5i64))?,
+            Some(DocAddress::new(1, 0))
+        );
+        assert_eq!(
+            searcher.doc_by_key(&Term::from_field_i64(id_field, 4i64))?,
+            None
+        );
+        Ok(())
+    }
 }
@@ -1,3 +1,4 @@
+use super::lookup_weight::FastFieldLookupWeight;
 use super::term_weight::TermWeight;
 use crate::query::bm25::Bm25Weight;
 use crate::query::Weight;
@@ -108,7 +109,12 @@ impl TermQuery {
                 Bm25Weight::new(Explanation::new("<no score>".to_string(), 1.0f32), 1.0f32);
         }
         let index_record_option = if scoring_enabled {
-            self.index_record_option
+            // Never decode more than what was indexed for the field.
+            field_entry
+                .field_type()
+                .get_index_record_option()
+                .map(|field_option| self.index_record_option.downgrade(field_option))
+                .unwrap_or(IndexRecordOption::Basic)
         } else {
             IndexRecordOption::Basic
         };
@@ -117,12 +123,27 @@ impl TermQuery {
             index_record_option,
             bm25_weight,
             scoring_enabled,
+            searcher.memory_budget().clone(),
         ))
     }
 }
 
 impl Query for TermQuery {
     fn weight(&self, searcher: &Searcher, scoring_enabled: bool) -> crate::Result<Box<dyn Weight>> {
+        let field_type = searcher
+            .schema()
+            .get_field_entry(self.term.field())
+            .field_type();
+        if field_type.has_lookup_index() && self.term.value_bytes().len() == 8 {
+            let mut value_bytes = [0u8; 8];
+            value_bytes.copy_from_slice(self.term.value_bytes());
+            // Numeric terms and fast fields share the same order preserving `u64` encoding.
+            let value = u64::from_be_bytes(value_bytes);
+            return Ok(Box::new(FastFieldLookupWeight::new(
+                self.term.field(),
+                value,
+            )));
+        }
         Ok(Box::new(
             self.specialized_weight(searcher, scoring_enabled)?,
         ))
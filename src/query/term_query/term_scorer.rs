@@ -86,9 +86,9 @@ impl TermScorer {
     }
 
     pub fn explain(&self) -> Explanation {
-        let fieldnorm_id = self.fieldnorm_id();
+        let fieldnorm = self.fieldnorm_reader.fieldnorm(self.doc());
         let term_freq = self.term_freq();
-        self.similarity_weight.explain(fieldnorm_id, term_freq)
+        self.similarity_weight.explain(fieldnorm, term_freq)
     }
 
     pub fn max_score(&self) -> Score {
@@ -116,13 +116,17 @@ impl DocSet for TermScorer {
     fn size_hint(&self) -> u32 {
         self.postings.size_hint()
     }
+
+    fn cost(&self) -> u64 {
+        self.postings.cost()
+    }
 }
 
 impl Scorer for TermScorer {
     fn score(&mut self) -> Score {
-        let fieldnorm_id = self.fieldnorm_id();
         let term_freq = self.term_freq();
-        self.similarity_weight.score(fieldnorm_id, term_freq)
+        self.similarity_weight
+            .score_doc(&self.fieldnorm_reader, self.doc(), term_freq)
     }
 }
 
@@ -7,20 +7,30 @@ use crate::query::bm25::Bm25Weight;
 use crate::query::explanation::does_not_match;
 use crate::query::weight::for_each_scorer;
 use crate::query::Weight;
-use crate::query::{Explanation, Scorer};
+use crate::query::{ConstScorer, Explanation, Scorer};
 use crate::schema::IndexRecordOption;
 use crate::Term;
-use crate::{DocId, Score};
+use crate::{DocId, MemoryBudget, Score};
+use std::mem;
 
 pub struct TermWeight {
     term: Term,
     index_record_option: IndexRecordOption,
     similarity_weight: Bm25Weight,
     scoring_enabled: bool,
+    memory_budget: MemoryBudget,
 }
 
 impl Weight for TermWeight {
     fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
+        if !self.scoring_enabled {
+            // Dense posting lists are stored as bitmaps: they can be read in place,
+            // which makes seeking through them, e.g. in intersections, very cheap.
+            let inverted_index = reader.inverted_index(self.term.field())?;
+            if let Some(dense_docset) = inverted_index.read_dense_docset(&self.term)? {
+                return Ok(Box::new(ConstScorer::new(dense_docset, boost)));
+            }
+        }
         let term_scorer = self.specialized_scorer(reader, boost)?;
         Ok(Box::new(term_scorer))
     }
@@ -90,12 +100,14 @@ impl TermWeight {
         index_record_option: IndexRecordOption,
         similarity_weight: Bm25Weight,
         scoring_enabled: bool,
+        memory_budget: MemoryBudget,
     ) -> TermWeight {
         TermWeight {
             term,
             index_record_option,
             similarity_weight,
             scoring_enabled,
+            memory_budget,
         }
     }
 
@@ -104,6 +116,8 @@ impl TermWeight {
         reader: &SegmentReader,
         boost: Score,
     ) -> crate::Result<TermScorer> {
+        // The scorer embeds the buffers in which the blocks of the posting list are decoded.
+        self.memory_budget.allocate(mem::size_of::<TermScorer>())?;
         let field = self.term.field();
         let inverted_index = reader.inverted_index(field)?;
         let fieldnorm_reader = if self.scoring_enabled {
@@ -0,0 +1,289 @@
+use crate::error::TantivyError;
+use crate::query::{AutomatonWeight, Query, Weight};
+use crate::schema::{Field, Term};
+use crate::Searcher;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tantivy_fst::Automaton;
+
+/// State of a `TermSetAutomaton`: the range of terms sharing
+/// the `depth` bytes accepted so far.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct TermSetState {
+    start: usize,
+    end: usize,
+    depth: usize,
+}
+
+/// Automaton matching exactly the terms of a sorted set.
+///
+/// Searching the term dictionary with this automaton intersects the set with
+/// the dictionary in a single pass, skipping the branches of the dictionary
+/// that are not a prefix of any term of the set.
+pub(crate) struct TermSetAutomaton {
+    // Sorted and deduplicated.
+    terms: Arc<Vec<Vec<u8>>>,
+}
+
+// Returns the number of terms for which `predicate` is true, assuming these
+// terms are at the beginning of `terms`.
+fn partition_point<F: Fn(&[u8]) -> bool>(terms: &[Vec<u8>], predicate: F) -> usize {
+    terms
+        .binary_search_by(|term| {
+            if predicate(term) {
+                Ordering::Less
+            } else {
+                Ordering::Greater
+            }
+        })
+        .unwrap_err()
+}
+
+impl Automaton for TermSetAutomaton {
+    type State = Option<TermSetState>;
+
+    fn start(&self) -> Self::State {
+        if self.terms.is_empty() {
+            return None;
+        }
+        Some(TermSetState {
+            start: 0,
+            end: self.terms.len(),
+            depth: 0,
+        })
+    }
+
+    fn is_match(&self, state: &Self::State) -> bool {
+        // Within the range, the term equal to the prefix accepted so far, if any,
+        // is the first one.
+        state
+            .map(|state| self.terms[state.start].len() == state.depth)
+            .unwrap_or(false)
+    }
+
+    fn can_match(&self, state: &Self::State) -> bool {
+        state.is_some()
+    }
+
+    fn accept(&self, state: &Self::State, byte: u8) -> Self::State {
+        let state = (*state)?;
+        let depth = state.depth;
+        let terms = &self.terms[state.start..state.end];
+        let start = partition_point(terms, |term| term.len() <= depth || term[depth] < byte);
+        let end = partition_point(terms, |term| term.len() <= depth || term[depth] <= byte);
+        if start == end {
+            return None;
+        }
+        Some(TermSetState {
+            start: state.start + start,
+            end: state.start + end,
+            depth: depth + 1,
+        })
+    }
+}
+
+/// A Term Set Query matches all of the documents containing
+/// at least one of the terms of a set.
+///
+/// This is equivalent to a `BooleanQuery` made of a `Should` clause per term,
+/// but much cheaper to build and to execute when the set contains thousands of
+/// terms, as in the case of id-list or ACL filters.
+/// The set is intersected with the term dictionary of each segment in a single pass,
+/// and the posting lists of the matching terms are unioned into a bitset.
+///
+/// All of the matching documents get the same score, which is the boost applied
+/// to the query (`1.0` by default).
+///
+/// ```rust
+/// use tantivy::collector::Count;
+/// use tantivy::query::TermSetQuery;
+/// use tantivy::schema::{Schema, STRING};
+/// use tantivy::{doc, Index, Term};
+///
+/// # fn test() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let user_id = schema_builder.add_text_field("user_id", STRING);
+/// let schema = schema_builder.build();
+/// let index = Index::create_in_ram(schema);
+/// {
+///     let mut index_writer = index.writer(3_000_000)?;
+///     for id in 0..100 {
+///         index_writer.add_document(doc!(user_id => format!("user{}", id)));
+///     }
+///     index_writer.commit()?;
+/// }
+///
+/// let searcher = index.reader()?.searcher();
+/// let terms = (0..200)
+///     .step_by(2)
+///     .map(|id| Term::from_field_text(user_id, &format!("user{}", id)))
+///     .collect();
+/// let query = TermSetQuery::new(user_id, terms)?;
+/// assert_eq!(searcher.search(&query, &Count)?, 50);
+/// Ok(())
+/// # }
+/// # assert!(test().is_ok());
+/// ```
+#[derive(Clone, Debug)]
+pub struct TermSetQuery {
+    field: Field,
+    // Value bytes of the terms, sorted and deduplicated.
+    terms: Arc<Vec<Vec<u8>>>,
+}
+
+impl TermSetQuery {
+    /// Creates a new `TermSetQuery` matching the documents containing any of the `terms`.
+    ///
+    /// Returns an error if one of the terms does not belong to `field`.
+    pub fn new(field: Field, terms: Vec<Term>) -> crate::Result<TermSetQuery> {
+        let mut term_values: Vec<Vec<u8>> = Vec::with_capacity(terms.len());
+        for term in terms {
+            if term.field() != field {
+                return Err(TantivyError::InvalidArgument(format!(
+                    "The term {:?} does not belong to the field of the TermSetQuery",
+                    term
+                )));
+            }
+            term_values.push(term.value_bytes().to_vec());
+        }
+        term_values.sort_unstable();
+        term_values.dedup();
+        Ok(TermSetQuery {
+            field,
+            terms: Arc::new(term_values),
+        })
+    }
+
+    /// Returns the number of distinct terms in the set.
+    pub fn num_terms(&self) -> usize {
+        self.terms.len()
+    }
+
+    fn specialized_weight(&self) -> AutomatonWeight<TermSetAutomaton> {
+        let automaton = TermSetAutomaton {
+            terms: self.terms.clone(),
+        };
+        AutomatonWeight::new(self.field, automaton)
+    }
+}
+
+impl Query for TermSetQuery {
+    fn weight(
+        &self,
+        searcher: &Searcher,
+        _scoring_enabled: bool,
+    ) -> crate::Result<Box<dyn Weight>> {
+        let memory_budget = searcher.memory_budget().clone();
+        let expansion_budget = searcher.expansion_budget().clone();
+        Ok(Box::new(
+            self.specialized_weight()
+                .with_memory_budget(memory_budget)
+                .with_expansion_budget(expansion_budget),
+        ))
+    }
+
+    fn rewrite(
+        &self,
+        searcher: &Searcher,
+        _scoring_enabled: bool,
+    ) -> crate::Result<Option<Box<dyn Query>>> {
+        self.specialized_weight().rewrite_into_term_union(searcher)
+    }
+
+    fn query_terms(&self, term_set: &mut BTreeMap<Term, bool>) {
+        for term_value in self.terms.iter() {
+            term_set.insert(Term::from_field_bytes(self.field, term_value), false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TermSetAutomaton, TermSetQuery};
+    use crate::collector::{Count, TopDocs};
+    use crate::query::{rewrite_query, ConstScoreQuery, EmptyQuery};
+    use crate::schema::{Schema, STRING};
+    use crate::{Index, Term};
+    use std::sync::Arc;
+    use tantivy_fst::Automaton;
+
+    fn is_match(automaton: &TermSetAutomaton, key: &str) -> bool {
+        let mut state = automaton.start();
+        for &byte in key.as_bytes() {
+            if !automaton.can_match(&state) {
+                return false;
+            }
+            state = automaton.accept(&state, byte);
+        }
+        automaton.is_match(&state)
+    }
+
+    #[test]
+    fn test_term_set_automaton() {
+        let mut terms: Vec<Vec<u8>> = ["ab", "abd", "b", "bc", "bcde", "d"]
+            .iter()
+            .map(|term| term.as_bytes().to_vec())
+            .collect();
+        terms.sort();
+        let automaton = TermSetAutomaton {
+            terms: Arc::new(terms),
+        };
+        let dictionary_terms = [
+            "", "a", "ab", "abc", "abd", "b", "ba", "bcd", "bcde", "c", "d", "de",
+        ];
+        let matching_terms: Vec<&str> = dictionary_terms
+            .iter()
+            .cloned()
+            .filter(|term| is_match(&automaton, term))
+            .collect();
+        assert_eq!(matching_terms, vec!["ab", "abd", "b", "bcde", "d"]);
+
+        let empty_automaton = TermSetAutomaton {
+            terms: Arc::new(Vec::new()),
+        };
+        assert!(!empty_automaton.can_match(&empty_automaton.start()));
+        assert!(!is_match(&empty_automaton, ""));
+    }
+
+    #[test]
+    fn test_term_set_query() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let id_field = schema_builder.add_text_field("id", STRING);
+        let other_field = schema_builder.add_text_field("other", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for id in 0..1_000 {
+            index_writer.add_document(doc!(id_field => format!("id{}", id)));
+            if id % 100 == 99 {
+                index_writer.commit()?;
+            }
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let id_term = |id: u32| Term::from_field_text(id_field, &format!("id{}", id));
+
+        // Even ids, some of which do not exist, and duplicates.
+        let terms: Vec<Term> = (0..3_000).map(|id| id_term(id % 1_500 * 2)).collect();
+        let term_set_query = TermSetQuery::new(id_field, terms)?;
+        assert_eq!(term_set_query.num_terms(), 1_500);
+        assert_eq!(searcher.search(&term_set_query, &Count)?, 500);
+        let top_docs = searcher.search(&term_set_query, &TopDocs::with_limit(1_000))?;
+        assert_eq!(top_docs.len(), 500);
+        assert!(top_docs.iter().all(|(score, _)| *score == 1.0));
+
+        let small_term_set_query = TermSetQuery::new(id_field, vec![id_term(3), id_term(7)])?;
+        let rewritten_query = rewrite_query(&small_term_set_query, &searcher, true)?;
+        assert!(rewritten_query.is::<ConstScoreQuery>());
+        assert_eq!(searcher.search(&small_term_set_query, &Count)?, 2);
+
+        let empty_term_set_query = TermSetQuery::new(id_field, Vec::new())?;
+        let rewritten_query = rewrite_query(&empty_term_set_query, &searcher, true)?;
+        assert!(rewritten_query.is::<EmptyQuery>());
+
+        assert!(
+            TermSetQuery::new(id_field, vec![Term::from_field_text(other_field, "id1")]).is_err()
+        );
+        Ok(())
+    }
+}
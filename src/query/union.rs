@@ -205,6 +205,10 @@ where
         self.doc
     }
 
+    /// The size of the union is at least the size of its largest docset,
+    /// and at most the sum of the sizes of its docsets.
+    ///
+    /// The lower bound is returned.
     fn size_hint(&self) -> u32 {
         self.docsets
             .iter()
@@ -213,6 +217,11 @@ where
             .unwrap_or(0u32)
     }
 
+    /// All of the docsets of the union are entirely visited.
+    fn cost(&self) -> u64 {
+        self.docsets.iter().map(|docset| docset.cost()).sum()
+    }
+
     fn count_including_deleted(&mut self) -> u32 {
         if self.doc == TERMINATED {
             return 0;
@@ -310,6 +319,16 @@ mod tests {
         ]);
     }
 
+    #[test]
+    fn test_union_size_hint_and_cost() {
+        let union: Union<_, DoNothingCombiner> = Union::from(vec![
+            ConstScorer::from(VecDocSet::from(vec![1, 3])),
+            ConstScorer::from(VecDocSet::from(vec![1, 2, 5])),
+        ]);
+        assert_eq!(union.size_hint(), 3);
+        assert_eq!(union.cost(), 5);
+    }
+
     fn test_aux_union_skip(docs_list: &[Vec<DocId>], skip_targets: Vec<DocId>) {
         let mut btree_set = BTreeSet::new();
         for docs in docs_list {
@@ -56,6 +56,15 @@ pub trait Weight: Send + Sync + 'static {
     /// Returns an `Explanation` for the given document.
     fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation>;
 
+    /// Returns false if no document of the segment can match, judging by
+    /// the metadata of the segment only (e.g. its fast field ranges).
+    ///
+    /// This makes it possible to skip a segment without reading any of its data.
+    /// Returning true does not imply that the segment has any match.
+    fn may_match_segment(&self, _reader: &SegmentReader) -> bool {
+        true
+    }
+
     /// Returns the number documents within the given `SegmentReader`.
     fn count(&self, reader: &SegmentReader) -> crate::Result<u32> {
         let mut scorer = self.scorer(reader, 1.0)?;
@@ -0,0 +1,226 @@
+use crate::error::TantivyError;
+use crate::query::{AutomatonWeight, Query, Weight};
+use crate::schema::Field;
+use crate::Searcher;
+use tantivy_fst::Regex;
+
+/// Default maximum number of terms a `WildcardQuery` may match in a segment.
+pub const DEFAULT_MAX_EXPANSIONS: usize = 1_024;
+
+/// Returns true if the pattern starts with a wildcard.
+pub(crate) fn has_leading_wildcard(pattern: &str) -> bool {
+    pattern.starts_with(|c| c == '*' || c == '?')
+}
+
+/// Translates a wildcard pattern into an equivalent regular expression.
+fn wildcard_to_regex(pattern: &str) -> String {
+    let mut regex_pattern = String::with_capacity(pattern.len() * 2);
+    let mut chars = pattern.chars();
+    let mut buffer = [0u8; 4];
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => regex_pattern.push_str(".*"),
+            '?' => regex_pattern.push('.'),
+            '\\' => {
+                // An escaped wildcard matches the character itself.
+                let escaped_char = chars.next().unwrap_or('\\');
+                regex_pattern.push_str(&regex::escape(escaped_char.encode_utf8(&mut buffer)));
+            }
+            _ => regex_pattern.push_str(&regex::escape(c.encode_utf8(&mut buffer))),
+        }
+    }
+    regex_pattern
+}
+
+/// A Wildcard Query matches all of the documents
+/// containing a term matching a wildcard pattern.
+///
+/// In the pattern, `*` matches any sequence of characters (including the empty sequence),
+/// and `?` matches exactly one character. Wildcards can be escaped with a backslash.
+///
+/// Like all of the patterns matched against the term dictionary, the pattern is not
+/// tokenized: it should match the terms as they were emitted by the tokenizer of the field.
+///
+/// Patterns starting with a wildcard have to scan the whole term dictionary.
+/// They are rejected unless explicitly allowed
+/// with [`allow_leading_wildcard`](#method.allow_leading_wildcard).
+///
+/// To protect against patterns matching a huge number of terms, the query fails if the
+/// pattern matches more than [`max_expansions`](#method.max_expansions) terms
+/// in a segment.
+///
+/// ```rust
+/// use tantivy::collector::Count;
+/// use tantivy::query::WildcardQuery;
+/// use tantivy::schema::{Schema, TEXT};
+/// use tantivy::{doc, Index};
+///
+/// # fn test() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let schema = schema_builder.build();
+/// let index = Index::create_in_ram(schema);
+/// {
+///     let mut index_writer = index.writer(3_000_000)?;
+///     index_writer.add_document(doc!(
+///         title => "The Name of the Wind",
+///     ));
+///     index_writer.add_document(doc!(
+///         title => "The Diary of Muadib",
+///     ));
+///     index_writer.add_document(doc!(
+///         title => "A Dairy Cow",
+///     ));
+///     index_writer.add_document(doc!(
+///         title => "The Diary of a Young Girl",
+///     ));
+///     index_writer.commit()?;
+/// }
+///
+/// let reader = index.reader()?;
+/// let searcher = reader.searcher();
+///
+/// let query = WildcardQuery::new(title, "d??ry");
+/// let count = searcher.search(&query, &Count)?;
+/// assert_eq!(count, 3);
+///
+/// let query = WildcardQuery::new(title, "*ry").allow_leading_wildcard(true);
+/// let count = searcher.search(&query, &Count)?;
+/// assert_eq!(count, 3);
+/// Ok(())
+/// # }
+/// # assert!(test().is_ok());
+/// ```
+#[derive(Debug, Clone)]
+pub struct WildcardQuery {
+    field: Field,
+    pattern: String,
+    allow_leading_wildcard: bool,
+    max_expansions: usize,
+}
+
+impl WildcardQuery {
+    /// Creates a new WildcardQuery from a given pattern.
+    pub fn new(field: Field, pattern: &str) -> WildcardQuery {
+        WildcardQuery {
+            field,
+            pattern: pattern.to_string(),
+            allow_leading_wildcard: false,
+            max_expansions: DEFAULT_MAX_EXPANSIONS,
+        }
+    }
+
+    /// Allows or denies patterns starting with a wildcard.
+    ///
+    /// By default, leading wildcards are denied.
+    pub fn allow_leading_wildcard(mut self, allow_leading_wildcard: bool) -> WildcardQuery {
+        self.allow_leading_wildcard = allow_leading_wildcard;
+        self
+    }
+
+    /// Sets the maximum number of terms the pattern may match in a segment.
+    ///
+    /// By default, it is `DEFAULT_MAX_EXPANSIONS`.
+    pub fn max_expansions(mut self, max_expansions: usize) -> WildcardQuery {
+        self.max_expansions = max_expansions;
+        self
+    }
+
+    /// Returns the wildcard pattern.
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    fn specialized_weight(&self) -> crate::Result<AutomatonWeight<Regex>> {
+        if !self.allow_leading_wildcard && has_leading_wildcard(&self.pattern) {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Leading wildcards are not allowed: {}",
+                self.pattern
+            )));
+        }
+        let regex = Regex::new(&wildcard_to_regex(&self.pattern))
+            .map_err(|_| TantivyError::InvalidArgument(self.pattern.clone()))?;
+        Ok(AutomatonWeight::new(self.field, regex).with_max_num_terms(self.max_expansions))
+    }
+}
+
+impl Query for WildcardQuery {
+    fn weight(
+        &self,
+        searcher: &Searcher,
+        _scoring_enabled: bool,
+    ) -> crate::Result<Box<dyn Weight>> {
+        let memory_budget = searcher.memory_budget().clone();
+        let expansion_budget = searcher.expansion_budget().clone();
+        Ok(Box::new(
+            self.specialized_weight()?
+                .with_memory_budget(memory_budget)
+                .with_expansion_budget(expansion_budget),
+        ))
+    }
+
+    fn rewrite(
+        &self,
+        searcher: &Searcher,
+        _scoring_enabled: bool,
+    ) -> crate::Result<Option<Box<dyn Query>>> {
+        self.specialized_weight()?.rewrite_into_term_union(searcher)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{wildcard_to_regex, WildcardQuery};
+    use crate::collector::Count;
+    use crate::schema::{Schema, STRING};
+    use crate::{Index, TantivyError};
+
+    #[test]
+    fn test_wildcard_to_regex() {
+        assert_eq!(wildcard_to_regex("hel*o"), "hel.*o");
+        assert_eq!(wildcard_to_regex("h?llo"), "h.llo");
+        assert_eq!(wildcard_to_regex("a.b*"), "a\\.b.*");
+        assert_eq!(wildcard_to_regex("what\\?"), "what\\?");
+        assert_eq!(wildcard_to_regex("a\\"), "a\\\\");
+    }
+
+    #[test]
+    fn test_wildcard_query() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let name_field = schema_builder.add_text_field("name", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for name in &["hello", "hallo", "help", "hell?", "yellow"] {
+            index_writer.add_document(doc!(name_field => *name));
+        }
+        for i in 0..100 {
+            index_writer.add_document(doc!(name_field => format!("id{}", i)));
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let count = |query: WildcardQuery| searcher.search(&query, &Count);
+        assert_eq!(count(WildcardQuery::new(name_field, "hel*"))?, 3);
+        assert_eq!(count(WildcardQuery::new(name_field, "h?llo"))?, 2);
+        assert_eq!(count(WildcardQuery::new(name_field, "hell\\?"))?, 1);
+        assert_eq!(count(WildcardQuery::new(name_field, "h*p"))?, 1);
+        assert_eq!(count(WildcardQuery::new(name_field, "z*"))?, 0);
+        assert!(matches!(
+            count(WildcardQuery::new(name_field, "*llo")),
+            Err(TantivyError::InvalidArgument(_))
+        ));
+        let leading_wildcard_query =
+            WildcardQuery::new(name_field, "*llo*").allow_leading_wildcard(true);
+        assert_eq!(count(leading_wildcard_query)?, 3);
+        // Matches more terms than a union of term queries can handle.
+        assert_eq!(count(WildcardQuery::new(name_field, "id*"))?, 100);
+        assert!(matches!(
+            count(WildcardQuery::new(name_field, "id*").max_expansions(50)),
+            Err(TantivyError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            count(WildcardQuery::new(name_field, "h*").max_expansions(2)),
+            Err(TantivyError::InvalidArgument(_))
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,38 @@
+use crate::common::BitSet;
+use crate::query::DocFilter;
+use crate::{SegmentId, SegmentReader};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Caches the bitsets of the `DocFilter` installed on an `IndexReader`.
+///
+/// A new cache is created upon each reload, and shared by the searchers of the reload:
+/// the bitset of a segment is built the first time a query runs on the segment,
+/// and reused by the following queries until the next reload.
+pub(crate) struct DocFilterCache {
+    doc_filter: Arc<dyn DocFilter>,
+    bitsets: Mutex<HashMap<SegmentId, Arc<BitSet>>>,
+}
+
+impl DocFilterCache {
+    pub fn new(doc_filter: Arc<dyn DocFilter>) -> DocFilterCache {
+        DocFilterCache {
+            doc_filter,
+            bitsets: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl DocFilter for DocFilterCache {
+    // The lock is held while building, so that concurrent searchers
+    // do not build the bitset of the same segment twice.
+    fn filter_bitset(&self, segment_reader: &SegmentReader) -> crate::Result<Arc<BitSet>> {
+        let mut bitsets = self.bitsets.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(bitset) = bitsets.get(&segment_reader.segment_id()) {
+            return Ok(bitset.clone());
+        }
+        let bitset = self.doc_filter.filter_bitset(segment_reader)?;
+        bitsets.insert(segment_reader.segment_id(), bitset.clone());
+        Ok(bitset)
+    }
+}
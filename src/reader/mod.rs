@@ -1,17 +1,38 @@
+mod doc_filter_cache;
 mod pool;
+mod query_cache;
+mod warmer;
 
-pub use self::pool::LeasedItem;
+use self::doc_filter_cache::DocFilterCache;
 use self::pool::Pool;
+pub use self::pool::{LeasedItem, SearcherPoolInfo};
+use self::query_cache::QueryCache;
+pub use self::query_cache::QueryCacheInfo;
+pub use self::warmer::Warmer;
+use crate::collector::Collector;
+use crate::core::global_ordinals::GlobalOrdinalsCache;
+use crate::core::searcher::QueryBudgets;
 use crate::core::Segment;
 use crate::directory::WatchHandle;
 use crate::directory::META_LOCK;
-use crate::directory::{Directory, WatchCallback};
+use crate::directory::{Directory, Lease, WatchCallback};
+use crate::query::{DocFilter, Query};
+use crate::store::{DocStoreCache, DocStoreCacheInfo};
+use crate::Executor;
+use crate::ExpansionOverflow;
 use crate::Index;
 use crate::Searcher;
 use crate::SegmentReader;
-use std::sync::Arc;
+use crate::{SegmentId, TantivyError};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
 use std::{convert::TryInto, io};
 
+/// Default number of decompressed doc store blocks kept in cache by an `IndexReader`.
+const DEFAULT_DOC_STORE_CACHE_NUM_BLOCKS: usize = 1_000;
+
 /// Defines when a new version of the index should be reloaded.
 ///
 /// Regardless of whether you search and index in the same process, tantivy does not necessarily
@@ -40,10 +61,48 @@ pub enum ReloadPolicy {
 /// - `reload_policy` (by default `ReloadPolicy::OnCommit`):
 ///
 ///   See [`ReloadPolicy`](./enum.ReloadPolicy.html) for more details.
+/// - `doc_store_cache_num_blocks` (by default 1,000):
+///
+///   Number of decompressed doc store blocks kept in the cache shared by
+///   all of the searchers and segments.
+/// - `memory_budget_per_query` (by default, unlimited):
+///
+///   Memory budget, in bytes, of the transient allocations of each query.
+///   See [`MemoryBudget`](../struct.MemoryBudget.html) for more details.
+/// - `expansion_budget_per_query` (by default, unlimited):
+///
+///   Number of terms into which the multi-term queries of each query may expand.
+///   See [`ExpansionBudget`](../struct.ExpansionBudget.html) for more details.
+/// - `warmers` (by default, none):
+///
+///   See [`Warmer`](./trait.Warmer.html) for more details.
+/// - `lease_duration` (by default, none):
+///
+///   Duration of the lease protecting the files of the reader from garbage collection.
+/// - `query_cache_capacity` (by default 0, i.e. disabled) and `query_cache_ttl` (by default, none):
+///
+///   Number of search results kept in the cache of
+///   [`IndexReader::search_cached`](./struct.IndexReader.html#method.search_cached),
+///   and for how long.
+/// - `segment_open_concurrency` (by default 1):
+///
+///   Maximum number of segments opened at the same time upon a reload.
+///   The segments that did not change since the previous reload are not reopened.
+/// - `doc_filter` (by default, none):
+///
+///   Restricts the documents matched by all of the queries run on the searchers of the reader.
 #[derive(Clone)]
 pub struct IndexReaderBuilder {
     num_searchers: usize,
     reload_policy: ReloadPolicy,
+    doc_store_cache_num_blocks: usize,
+    query_budgets: QueryBudgets,
+    warmers: Vec<Arc<dyn Warmer>>,
+    lease_duration: Option<Duration>,
+    query_cache_capacity: usize,
+    query_cache_ttl: Option<Duration>,
+    segment_open_concurrency: usize,
+    doc_filter: Option<Arc<dyn DocFilter>>,
     index: Index,
 }
 
@@ -52,6 +111,14 @@ impl IndexReaderBuilder {
         IndexReaderBuilder {
             num_searchers: num_cpus::get(),
             reload_policy: ReloadPolicy::OnCommit,
+            doc_store_cache_num_blocks: DEFAULT_DOC_STORE_CACHE_NUM_BLOCKS,
+            query_budgets: QueryBudgets::default(),
+            warmers: Vec::new(),
+            lease_duration: None,
+            query_cache_capacity: 0,
+            query_cache_ttl: None,
+            segment_open_concurrency: 1,
+            doc_filter: None,
             index,
         }
     }
@@ -62,10 +129,26 @@ impl IndexReaderBuilder {
     /// to open different segment readers. It may take hundreds of milliseconds
     /// of time and it may return an error.
     pub fn try_into(self) -> crate::Result<IndexReader> {
+        let doc_store_cache = DocStoreCache::with_capacity(self.doc_store_cache_num_blocks)
+            .with_metrics(self.index.metrics().clone());
+        let segment_open_executor = if self.segment_open_concurrency > 1 {
+            Executor::multi_thread(self.segment_open_concurrency, "segment-open-")?
+        } else {
+            Executor::single_thread()
+        };
         let inner_reader = InnerIndexReader {
             index: self.index,
             num_searchers: self.num_searchers,
             searcher_pool: Pool::new(),
+            doc_store_cache,
+            query_budgets: self.query_budgets,
+            warmers: self.warmers,
+            lease_duration: self.lease_duration,
+            lease: Mutex::new(None),
+            query_cache: QueryCache::new(self.query_cache_capacity, self.query_cache_ttl),
+            segment_open_executor,
+            segment_readers: Mutex::new(Vec::new()),
+            doc_filter: self.doc_filter,
         };
         inner_reader.reload()?;
         let inner_reader_arc = Arc::new(inner_reader);
@@ -111,6 +194,116 @@ impl IndexReaderBuilder {
         self.num_searchers = num_searchers;
         self
     }
+
+    /// Sets the number of decompressed doc store blocks kept in cache.
+    ///
+    /// Setting it to 0 disables the cache.
+    pub fn doc_store_cache_num_blocks(
+        mut self,
+        doc_store_cache_num_blocks: usize,
+    ) -> IndexReaderBuilder {
+        self.doc_store_cache_num_blocks = doc_store_cache_num_blocks;
+        self
+    }
+
+    /// Caps the memory allocated by the transient data structures of each query
+    /// to `num_bytes` bytes.
+    ///
+    /// Queries exceeding their budget fail with `TantivyError::MemoryBudgetExceeded`.
+    pub fn memory_budget_per_query(mut self, num_bytes: usize) -> IndexReaderBuilder {
+        self.query_budgets.memory_budget_per_query = Some(num_bytes);
+        self
+    }
+
+    /// Caps the number of terms into which the multi-term queries (fuzzy, regex,
+    /// wildcard, prefix or term set queries) of each query expand to `max_num_terms`.
+    ///
+    /// Depending on `overflow`, queries exceeding their budget either fail with
+    /// `TantivyError::ExpansionBudgetExceeded`, or degrade to matching their
+    /// documents with a bitset per segment.
+    pub fn expansion_budget_per_query(
+        mut self,
+        max_num_terms: usize,
+        overflow: ExpansionOverflow,
+    ) -> IndexReaderBuilder {
+        self.query_budgets.expansion_budget_per_query = Some((max_num_terms, overflow));
+        self
+    }
+
+    /// Sets the warmers, run upon each reload before the new searchers
+    /// are made available.
+    pub fn warmers(mut self, warmers: Vec<Arc<dyn Warmer>>) -> IndexReaderBuilder {
+        self.warmers = warmers;
+        self
+    }
+
+    /// Makes the reader hold a [`Lease`](../directory/struct.Lease.html) on the files
+    /// of the segments it loaded, protecting them from garbage collection,
+    /// including by an `IndexWriter` running in another process.
+    ///
+    /// The lease is renewed for `lease_duration` upon each reload, and released
+    /// when the reader is dropped. The reader should therefore be reloaded more often
+    /// than `lease_duration`.
+    ///
+    /// The lease only covers the last reload: the searchers of previous reloads
+    /// that are still in use can be protected by a grace period
+    /// (see [`IndexWriter::set_gc_grace_period`](../struct.IndexWriter.html#method.set_gc_grace_period)).
+    pub fn lease_duration(mut self, lease_duration: Duration) -> IndexReaderBuilder {
+        self.lease_duration = Some(lease_duration);
+        self
+    }
+
+    /// Sets the number of search results kept in the cache of
+    /// [`IndexReader::search_cached`](./struct.IndexReader.html#method.search_cached).
+    ///
+    /// Setting it to 0 disables the cache.
+    pub fn query_cache_capacity(mut self, num_entries: usize) -> IndexReaderBuilder {
+        self.query_cache_capacity = num_entries;
+        self
+    }
+
+    /// Sets for how long a search result may be served from the cache of
+    /// [`IndexReader::search_cached`](./struct.IndexReader.html#method.search_cached).
+    ///
+    /// Regardless of this duration, results are dropped from the cache upon each reload.
+    pub fn query_cache_ttl(mut self, ttl: Duration) -> IndexReaderBuilder {
+        self.query_cache_ttl = Some(ttl);
+        self
+    }
+
+    /// Sets the maximum number of segments opened concurrently upon each reload.
+    ///
+    /// Opening a segment opens its files and reads their footers. With a value greater
+    /// than 1, the segments are opened on a dedicated pool of `segment_open_concurrency`
+    /// threads. Segment readers are reused across reloads, so that only the new segments are opened.
+    ///
+    /// A value of 0 is treated as 1.
+    pub fn segment_open_concurrency(
+        mut self,
+        segment_open_concurrency: usize,
+    ) -> IndexReaderBuilder {
+        self.segment_open_concurrency = segment_open_concurrency;
+        self
+    }
+
+    /// Installs a [`DocFilter`](../query/trait.DocFilter.html) restricting the documents
+    /// matched by all of the queries run on the searchers of the reader.
+    ///
+    /// This gives a central enforcement point for row-level security: the filter is
+    /// intersected into every query, including `Searcher::search`, `Searcher::docs_matching`,
+    /// `Query::count` and `Query::explain`, without the queries having to be rewritten.
+    ///
+    /// The bitset of a segment is built lazily, the first time a query runs on it,
+    /// and cached until the next reload. A filter depending on external state,
+    /// e.g. the permissions of the users, should therefore be paired with a reload
+    /// whenever that state changes.
+    ///
+    /// The filter does not apply to the fetching of documents by address,
+    /// nor to the statistics of the searcher (e.g. `Searcher::num_docs`).
+    pub fn doc_filter(mut self, doc_filter: Arc<dyn DocFilter>) -> IndexReaderBuilder {
+        self.doc_filter = Some(doc_filter);
+        self
+    }
 }
 
 impl TryInto<IndexReader> for IndexReaderBuilder {
@@ -124,6 +317,17 @@ impl TryInto<IndexReader> for IndexReaderBuilder {
 struct InnerIndexReader {
     num_searchers: usize,
     searcher_pool: Pool<Searcher>,
+    // Shared across reloads: blocks are keyed by segment id.
+    doc_store_cache: DocStoreCache,
+    query_budgets: QueryBudgets,
+    warmers: Vec<Arc<dyn Warmer>>,
+    lease_duration: Option<Duration>,
+    lease: Mutex<Option<Lease>>,
+    query_cache: QueryCache,
+    segment_open_executor: Executor,
+    // Segment readers of the last reload, reused by the next one.
+    segment_readers: Mutex<Vec<SegmentReader>>,
+    doc_filter: Option<Arc<dyn DocFilter>>,
     index: Index,
 }
 
@@ -132,21 +336,100 @@ impl InnerIndexReader {
         let segment_readers: Vec<SegmentReader> = {
             let _meta_lock = self.index.directory().acquire_lock(&META_LOCK)?;
             let searchable_segments = self.searchable_segments()?;
-            searchable_segments
-                .iter()
-                .map(SegmentReader::open)
-                .collect::<crate::Result<_>>()?
+            if let Some(lease_duration) = self.lease_duration {
+                // The lease has to be renewed before releasing the meta lock,
+                // so that the files are not garbage collected in between.
+                self.renew_lease(&searchable_segments, lease_duration)?;
+            }
+            self.open_segment_readers(&searchable_segments)?
         };
         let schema = self.index.schema();
+        // Shared by the searchers of this reload, as they all work on the same segments.
+        let global_ordinals_cache = Arc::new(GlobalOrdinalsCache::default());
+        // Filter bitsets are cached until the next reload.
+        let doc_filter_cache: Option<Arc<dyn DocFilter>> = self
+            .doc_filter
+            .clone()
+            .map(|doc_filter| Arc::new(DocFilterCache::new(doc_filter)) as Arc<dyn DocFilter>);
         let searchers: Vec<Searcher> = std::iter::repeat_with(|| {
-            Searcher::new(schema.clone(), self.index.clone(), segment_readers.clone())
+            Searcher::new(
+                schema.clone(),
+                self.index.clone(),
+                segment_readers.clone(),
+                self.doc_store_cache.clone(),
+                self.query_budgets,
+                global_ordinals_cache.clone(),
+                doc_filter_cache.clone(),
+            )
         })
         .take(self.num_searchers)
         .collect::<io::Result<_>>()?;
-        self.searcher_pool.publish_new_generation(searchers);
+        if let Some(searcher) = searchers.first() {
+            for warmer in &self.warmers {
+                warmer.warm(searcher)?;
+            }
+        }
+        let generation = self.searcher_pool.publish_new_generation(searchers);
+        self.query_cache.invalidate(generation);
         Ok(())
     }
 
+    /// Opens the readers of `segments`, reusing the readers of the previous reload.
+    ///
+    /// A segment is identified by its id and its delete opstamp: the readers of the
+    /// unchanged segments are reused as is, and the readers of the segments that only
+    /// have new deletes just reload their delete bitset.
+    fn open_segment_readers(&self, segments: &[Segment]) -> crate::Result<Vec<SegmentReader>> {
+        let mut previous_segment_readers = self
+            .segment_readers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let reusable_segment_readers: HashMap<SegmentId, &SegmentReader> = previous_segment_readers
+            .iter()
+            .map(|segment_reader| (segment_reader.segment_id(), segment_reader))
+            .collect();
+        let mut segment_readers: Vec<Option<SegmentReader>> = segments
+            .iter()
+            .map(|segment| {
+                let previous_segment_reader = reusable_segment_readers.get(&segment.id());
+                reuse_segment_reader(previous_segment_reader.copied(), segment)
+            })
+            .collect::<crate::Result<_>>()?;
+        let segments_to_open: Vec<(usize, &Segment)> = segments
+            .iter()
+            .enumerate()
+            .filter(|(ord, _)| segment_readers[*ord].is_none())
+            .collect();
+        let opened_segment_readers = self.segment_open_executor.map(
+            |(ord, segment)| Ok((ord, SegmentReader::open(segment)?)),
+            segments_to_open.into_iter(),
+        )?;
+        for (ord, segment_reader) in opened_segment_readers {
+            segment_readers[ord] = Some(segment_reader);
+        }
+        let segment_readers: Vec<SegmentReader> = segment_readers.into_iter().flatten().collect();
+        *previous_segment_readers = segment_readers.clone();
+        Ok(segment_readers)
+    }
+
+    fn renew_lease(&self, segments: &[Segment], lease_duration: Duration) -> crate::Result<()> {
+        let files: HashSet<PathBuf> = segments
+            .iter()
+            .flat_map(|segment| segment.meta().list_files())
+            .collect();
+        let mut lease_opt = self.lease.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(lease) = lease_opt.as_ref() {
+            lease.renew(files, lease_duration)
+        } else {
+            *lease_opt = Some(Lease::acquire(
+                self.index.directory(),
+                files,
+                lease_duration,
+            )?);
+            Ok(())
+        }
+    }
+
     /// Returns the list of segments that are searchable
     fn searchable_segments(&self) -> crate::Result<Vec<Segment>> {
         self.index.searchable_segments()
@@ -155,6 +438,103 @@ impl InnerIndexReader {
     fn searcher(&self) -> LeasedItem<Searcher> {
         self.searcher_pool.acquire()
     }
+
+    fn pin_generation(&self) -> GenerationPin {
+        let searcher = self.searcher();
+        let pinned_searcher = self
+            .searcher_pool
+            .lease_detached(searcher.generation(), searcher.for_query());
+        GenerationPin {
+            searcher: Arc::new(pinned_searcher),
+        }
+    }
+
+    fn searcher_for_generation(&self, generation: SearcherGeneration<'_>) -> LeasedItem<Searcher> {
+        match generation {
+            SearcherGeneration::Newest => self.searcher(),
+            SearcherGeneration::Pinned(generation_pin) => self.searcher_pool.lease_detached(
+                generation_pin.generation(),
+                generation_pin.searcher.for_query(),
+            ),
+        }
+    }
+
+    fn searcher_for_segments(&self, segment_ids: &[SegmentId]) -> crate::Result<Searcher> {
+        let searcher = self.searcher();
+        let segment_readers = searcher.segment_readers();
+        if let Some(missing_segment_id) = segment_ids.iter().find(|segment_id| {
+            !segment_readers
+                .iter()
+                .any(|segment_reader| segment_reader.segment_id() == **segment_id)
+        }) {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Segment {} is not part of the commit loaded by the reader",
+                missing_segment_id
+            )));
+        }
+        let segment_readers: Vec<SegmentReader> = segment_readers
+            .iter()
+            .filter(|segment_reader| segment_ids.contains(&segment_reader.segment_id()))
+            .cloned()
+            .collect();
+        let searcher = Searcher::new(
+            self.index.schema(),
+            self.index.clone(),
+            segment_readers,
+            self.doc_store_cache.clone(),
+            self.query_budgets,
+            // Global ordinals depend on the set of segments.
+            Arc::new(GlobalOrdinalsCache::default()),
+            // The bitsets of the filter do not: they are shared with the reload.
+            searcher.doc_filter().cloned(),
+        )?;
+        Ok(searcher)
+    }
+}
+
+// Returns the reader of `segment` derived from its reader in the previous reload, if any.
+fn reuse_segment_reader(
+    previous_segment_reader: Option<&SegmentReader>,
+    segment: &Segment,
+) -> crate::Result<Option<SegmentReader>> {
+    match previous_segment_reader {
+        Some(segment_reader)
+            if segment_reader.delete_opstamp() == segment.meta().delete_opstamp() =>
+        {
+            Ok(Some(segment_reader.clone()))
+        }
+        // Only the deletes of the segment changed.
+        Some(segment_reader) => segment_reader.reopen_with_deletes(segment).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Pins a generation of searchers, i.e. the version of the index loaded by a reload.
+///
+/// See [`IndexReader::pin_generation`](./struct.IndexReader.html#method.pin_generation).
+///
+/// The pin holds a searcher of its generation, and counts as one of its outstanding
+/// searchers until the pin and all of its clones are dropped.
+#[derive(Clone)]
+pub struct GenerationPin {
+    searcher: Arc<LeasedItem<Searcher>>,
+}
+
+impl GenerationPin {
+    /// Returns the pinned generation.
+    pub fn generation(&self) -> usize {
+        self.searcher.generation()
+    }
+}
+
+/// Generation of the searcher returned by
+/// [`IndexReader::searcher_for_generation`](./struct.IndexReader.html#method.searcher_for_generation).
+#[derive(Clone, Copy)]
+pub enum SearcherGeneration<'a> {
+    /// The generation of the last reload, as returned by `IndexReader::searcher`.
+    Newest,
+    /// A generation pinned with `IndexReader::pin_generation`.
+    Pinned(&'a GenerationPin),
 }
 
 /// `IndexReader` is your entry point to read and search the index.
@@ -202,4 +582,329 @@ impl IndexReader {
     pub fn searcher(&self) -> LeasedItem<Searcher> {
         self.inner.searcher()
     }
+
+    /// Pins the generation of the searchers of the last reload.
+    ///
+    /// The searchers returned by [`searcher_for_generation`](#method.searcher_for_generation)
+    /// for this pin all work on the same version of the index, even after the reader
+    /// reloads. This gives consistent results across the queries of a session,
+    /// e.g. when paginating through the results of a query.
+    ///
+    /// The pin keeps the segments of its generation open: it should be dropped
+    /// at the end of the session.
+    pub fn pin_generation(&self) -> GenerationPin {
+        self.inner.pin_generation()
+    }
+
+    /// Returns a searcher of the given generation.
+    ///
+    /// Searchers of the newest generation are taken from the pool of searchers,
+    /// as with [`searcher`](#method.searcher), and this may block.
+    /// Searchers of a pinned generation are created on demand, and do not block.
+    pub fn searcher_for_generation(
+        &self,
+        generation: SearcherGeneration<'_>,
+    ) -> LeasedItem<Searcher> {
+        self.inner.searcher_for_generation(generation)
+    }
+
+    /// Returns the number of searchers in use, per generation.
+    ///
+    /// This helps diagnosing why the files of an old version of the index
+    /// are retained, e.g. by searchers or pins that are never dropped.
+    pub fn searcher_pool_info(&self) -> SearcherPoolInfo {
+        SearcherPoolInfo {
+            newest_generation: self.inner.searcher_pool.generation(),
+            num_outstanding_searchers: self.inner.searcher_pool.num_outstanding_items(),
+        }
+    }
+
+    /// Returns a searcher restricted to the segments `segment_ids` of the
+    /// last loaded version of the index.
+    ///
+    /// This makes it possible to route a query to a subset of the segments,
+    /// e.g. to skip the segments that cannot contain any match for a time range.
+    /// The segments keep the ordinals given by their order in the searcher,
+    /// regardless of the order of `segment_ids`.
+    ///
+    /// The returned searcher shares the segment readers and the doc store cache of
+    /// the reader, but is not taken from its pool of searchers.
+    ///
+    /// Returns an `InvalidArgument` error if one of the segments is not
+    /// part of the last loaded version of the index.
+    pub fn searcher_for_segments(&self, segment_ids: &[SegmentId]) -> crate::Result<Searcher> {
+        self.inner.searcher_for_segments(segment_ids)
+    }
+
+    /// Returns the hit/miss statistics of the doc store block cache
+    /// shared by the searchers.
+    pub fn doc_store_cache_info(&self) -> DocStoreCacheInfo {
+        self.inner.doc_store_cache.info()
+    }
+
+    /// Same as [`Searcher::search`](../struct.Searcher.html#method.search), but reuses
+    /// the result of an identical search on the same version of the index, if it is
+    /// still in the query cache of the reader.
+    ///
+    /// Searches are identified by `cache_key`, along with the types of their query
+    /// and collector. The caller is responsible for giving different keys to searches
+    /// that may match or score documents differently, e.g. by deriving the key from
+    /// the user query and all of the options of the query and of the collector
+    /// (filters, limits, ...).
+    ///
+    /// Results are dropped from the cache upon each reload,
+    /// and after the `query_cache_ttl` of the
+    /// [`IndexReaderBuilder`](./struct.IndexReaderBuilder.html) if set.
+    ///
+    /// This is useful when the same searches are issued over and over, e.g. by dashboards.
+    /// If the query cache is disabled (the default), the search is always executed.
+    pub fn search_cached<C>(
+        &self,
+        cache_key: &str,
+        query: &dyn Query,
+        collector: &C,
+    ) -> crate::Result<C::Fruit>
+    where
+        C: Collector,
+        C::Fruit: Clone + Sync,
+    {
+        let searcher = self.inner.searcher();
+        self.inner.query_cache.search(
+            &searcher,
+            searcher.generation(),
+            cache_key,
+            query,
+            collector,
+        )
+    }
+
+    /// Returns the hit/miss statistics of the query cache
+    /// used by [`search_cached`](#method.search_cached).
+    pub fn query_cache_info(&self) -> QueryCacheInfo {
+        self.inner.query_cache.info()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ReloadPolicy, SearcherGeneration};
+    use crate::collector::Count;
+    use crate::common::BitSet;
+    use crate::indexer::NoMergePolicy;
+    use crate::query::{AllQuery, Query, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, INDEXED, STRING, TEXT};
+    use crate::TERMINATED;
+    use crate::{DocAddress, DocSet, Index, SegmentId, SegmentReader, TantivyError, Term};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_searcher_for_segments() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.commit()?;
+        index_writer.add_document(doc!(text_field => "b"));
+        index_writer.add_document(doc!(text_field => "c"));
+        index_writer.commit()?;
+        let reader = index.reader()?;
+        reader.reload()?;
+        let searcher = reader.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+        for segment_reader in searcher.segment_readers() {
+            let segment_searcher = reader.searcher_for_segments(&[segment_reader.segment_id()])?;
+            assert_eq!(segment_searcher.segment_readers().len(), 1);
+            assert_eq!(
+                segment_searcher.search(&AllQuery, &Count)?,
+                segment_reader.num_docs() as usize
+            );
+        }
+        let all_segment_ids: Vec<SegmentId> = searcher
+            .segment_readers()
+            .iter()
+            .map(|segment_reader| segment_reader.segment_id())
+            .collect();
+        assert_eq!(
+            reader
+                .searcher_for_segments(&all_segment_ids)?
+                .search(&AllQuery, &Count)?,
+            3
+        );
+        assert_eq!(reader.searcher_for_segments(&[])?.num_docs(), 0);
+        assert!(matches!(
+            reader.searcher_for_segments(&[SegmentId::generate_random()]),
+            Err(TantivyError::InvalidArgument(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_pinned_generation() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.commit()?;
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        let generation_pin = reader.pin_generation();
+        index_writer.add_document(doc!(text_field => "b"));
+        index_writer.commit()?;
+        reader.reload()?;
+        let newest_searcher = reader.searcher_for_generation(SearcherGeneration::Newest);
+        assert_eq!(newest_searcher.num_docs(), 2);
+        let pinned_searcher =
+            reader.searcher_for_generation(SearcherGeneration::Pinned(&generation_pin));
+        assert_eq!(pinned_searcher.generation(), generation_pin.generation());
+        assert_eq!(pinned_searcher.search(&AllQuery, &Count)?, 1);
+
+        let searcher_pool_info = reader.searcher_pool_info();
+        assert_eq!(
+            searcher_pool_info.newest_generation,
+            newest_searcher.generation()
+        );
+        let num_outstanding_searchers: Vec<(usize, usize)> = searcher_pool_info
+            .num_outstanding_searchers
+            .into_iter()
+            .collect();
+        assert_eq!(
+            num_outstanding_searchers,
+            vec![
+                (generation_pin.generation(), 2),
+                (newest_searcher.generation(), 1)
+            ]
+        );
+        drop(pinned_searcher);
+        drop(generation_pin);
+        drop(newest_searcher);
+        assert!(reader
+            .searcher_pool_info()
+            .num_outstanding_searchers
+            .is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_reload_reuses_segment_readers() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        for text in &["a", "b", "c"] {
+            index_writer.add_document(doc!(text_field => *text));
+            index_writer.add_document(doc!(text_field => "e"));
+            index_writer.commit()?;
+        }
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .segment_open_concurrency(2)
+            .try_into()?;
+        let old_searcher = reader.searcher();
+        assert_eq!(old_searcher.segment_readers().len(), 3);
+        index_writer.delete_term(Term::from_field_text(text_field, "a"));
+        index_writer.add_document(doc!(text_field => "d"));
+        index_writer.commit()?;
+        reader.reload()?;
+        let new_searcher = reader.searcher();
+        assert_eq!(new_searcher.segment_readers().len(), 4);
+        assert_eq!(new_searcher.search(&AllQuery, &Count)?, 6);
+        for old_segment_reader in old_searcher.segment_readers() {
+            let new_segment_reader = new_searcher
+                .segment_readers()
+                .iter()
+                .find(|segment_reader| {
+                    segment_reader.segment_id() == old_segment_reader.segment_id()
+                })
+                .unwrap();
+            // Reused segment readers share their cache of inverted index readers,
+            // including the reader of the segment with new deletes.
+            assert!(Arc::ptr_eq(
+                &old_segment_reader.inverted_index(text_field)?,
+                &new_segment_reader.inverted_index(text_field)?,
+            ));
+            assert!(!old_segment_reader.has_deletes());
+        }
+        let segment_readers_with_deletes: Vec<&SegmentReader> = new_searcher
+            .segment_readers()
+            .iter()
+            .filter(|segment_reader| segment_reader.has_deletes())
+            .collect();
+        assert_eq!(segment_readers_with_deletes.len(), 1);
+        assert_eq!(segment_readers_with_deletes[0].num_docs(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_reader_doc_filter() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let owner_field = schema_builder.add_u64_field("owner", INDEXED);
+        let tag_field = schema_builder.add_text_field("tag", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.set_merge_policy(Box::new(NoMergePolicy));
+        for owner in 0u64..10u64 {
+            let tag = if owner % 2 == 0 { "even" } else { "odd" };
+            index_writer.add_document(doc!(owner_field => owner % 3, tag_field => tag));
+        }
+        index_writer.commit()?;
+        let num_builds = Arc::new(AtomicUsize::new(0));
+        let num_builds_clone = num_builds.clone();
+        // Only allows the documents owned by the owner 0.
+        let doc_filter = move |segment_reader: &SegmentReader| -> crate::Result<Arc<BitSet>> {
+            num_builds_clone.fetch_add(1, Ordering::SeqCst);
+            let mut bitset = BitSet::with_max_value(segment_reader.max_doc());
+            let inverted_index = segment_reader.inverted_index(owner_field)?;
+            if let Some(mut postings) = inverted_index.read_postings(
+                &Term::from_field_u64(owner_field, 0),
+                IndexRecordOption::Basic,
+            )? {
+                let mut doc = postings.doc();
+                while doc != TERMINATED {
+                    bitset.insert(doc);
+                    doc = postings.advance();
+                }
+            }
+            Ok(Arc::new(bitset))
+        };
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .doc_filter(Arc::new(doc_filter))
+            .try_into()?;
+        let searcher = reader.searcher();
+        // Documents 0, 3, 6 and 9.
+        assert_eq!(searcher.search(&AllQuery, &Count)?, 4);
+        let even_query = TermQuery::new(
+            Term::from_field_text(tag_field, "even"),
+            IndexRecordOption::Basic,
+        );
+        assert_eq!(searcher.search(&even_query, &Count)?, 2);
+        assert_eq!(even_query.count(&searcher)?, 2);
+        let doc_addresses: Vec<DocAddress> = searcher
+            .docs_matching(&even_query)?
+            .collect::<crate::Result<_>>()?;
+        assert_eq!(
+            doc_addresses,
+            vec![DocAddress::new(0, 0), DocAddress::new(0, 6)]
+        );
+        assert!(even_query.explain(&searcher, DocAddress::new(0, 6)).is_ok());
+        assert!(even_query
+            .explain(&searcher, DocAddress::new(0, 2))
+            .is_err());
+        // The bitset is built once per segment and per reload.
+        assert_eq!(num_builds.load(Ordering::SeqCst), 1);
+        index_writer.add_document(doc!(owner_field => 0u64, tag_field => "even"));
+        index_writer.commit()?;
+        reader.reload()?;
+        assert_eq!(reader.searcher().search(&even_query, &Count)?, 3);
+        assert_eq!(num_builds.load(Ordering::SeqCst), 3);
+        Ok(())
+    }
 }
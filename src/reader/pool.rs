@@ -1,9 +1,11 @@
 use crossbeam::channel::unbounded;
 use crossbeam::channel::{Receiver, RecvError, Sender};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::ops::{Deref, DerefMut};
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, PoisonError};
 
 pub struct GenerationItem<T> {
     generation: usize,
@@ -42,6 +44,48 @@ impl<T> Queue<T> {
     }
 }
 
+/// Statistics on the searchers handed out by an `IndexReader`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SearcherPoolInfo {
+    /// Generation of the searchers of the last reload.
+    pub newest_generation: usize,
+    /// Number of searchers currently in use, per generation, including the
+    /// searchers held by the `GenerationPin`s.
+    ///
+    /// Searchers of older generations keep the segments they were opened on alive.
+    /// A long-lived entry for an old generation usually points at a leaked searcher or pin,
+    /// preventing the files of merged or deleted segments from being reclaimed.
+    pub num_outstanding_searchers: BTreeMap<usize, usize>,
+}
+
+/// Number of leased items, per generation.
+#[derive(Default)]
+struct OutstandingItems(Mutex<BTreeMap<usize, usize>>);
+
+impl OutstandingItems {
+    fn lease(&self, generation: usize) {
+        let mut counts = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        *counts.entry(generation).or_insert(0) += 1;
+    }
+
+    fn release(&self, generation: usize) {
+        let mut counts = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(count) = counts.get_mut(&generation) {
+            *count -= 1;
+            if *count == 0 {
+                counts.remove(&generation);
+            }
+        }
+    }
+
+    fn counts(&self) -> BTreeMap<usize, usize> {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
 /// An object pool
 ///
 /// This is used in tantivy to create a pool of `Searcher`.
@@ -51,6 +95,7 @@ pub struct Pool<T> {
     queue: Arc<Queue<GenerationItem<T>>>,
     freshest_generation: AtomicUsize,
     next_generation: AtomicUsize,
+    outstanding_items: Arc<OutstandingItems>,
 }
 
 impl<T> Pool<T> {
@@ -60,14 +105,15 @@ impl<T> Pool<T> {
             queue,
             freshest_generation: AtomicUsize::default(),
             next_generation: AtomicUsize::default(),
+            outstanding_items: Arc::default(),
         }
     }
 
     /// Publishes a new generation of `Searcher`.
     ///
     /// After publish, all new `Searcher` acquired will be
-    /// of the new generation.
-    pub fn publish_new_generation(&self, items: Vec<T>) {
+    /// of the new generation, which is returned.
+    pub fn publish_new_generation(&self, items: Vec<T>) -> usize {
         assert!(!items.is_empty());
         let next_generation = self.next_generation.fetch_add(1, Ordering::SeqCst) + 1;
         let num_items = items.len();
@@ -96,6 +142,7 @@ impl<T> Pool<T> {
         for _ in 0..=num_items {
             let _ = self.acquire();
         }
+        next_generation
     }
 
     /// At the exit of this method,
@@ -129,10 +176,16 @@ impl<T> Pool<T> {
         }
     }
 
-    fn generation(&self) -> usize {
+    /// Returns the generation of the items handed out by `acquire`.
+    pub fn generation(&self) -> usize {
         self.freshest_generation.load(Ordering::Acquire)
     }
 
+    /// Returns the number of leased items, per generation.
+    pub fn num_outstanding_items(&self) -> BTreeMap<usize, usize> {
+        self.outstanding_items.counts()
+    }
+
     /// Acquires a new searcher.
     ///
     /// If no searcher is available, this methods block until
@@ -142,9 +195,11 @@ impl<T> Pool<T> {
         loop {
             let gen_item = self.queue.pop().unwrap();
             if gen_item.generation >= generation {
+                self.outstanding_items.lease(gen_item.generation);
                 return LeasedItem {
                     gen_item: Some(gen_item),
-                    recycle_queue: Arc::clone(&self.queue),
+                    recycle_queue: Some(Arc::clone(&self.queue)),
+                    outstanding_items: Arc::clone(&self.outstanding_items),
                 };
             } else {
                 // this searcher is obsolete,
@@ -152,6 +207,19 @@ impl<T> Pool<T> {
             }
         }
     }
+
+    /// Leases an item of the given generation that is not part of the pool.
+    ///
+    /// The item counts as an outstanding item of its generation,
+    /// and is dropped rather than returned to the pool upon release.
+    pub fn lease_detached(&self, generation: usize, item: T) -> LeasedItem<T> {
+        self.outstanding_items.lease(generation);
+        LeasedItem {
+            gen_item: Some(GenerationItem { generation, item }),
+            recycle_queue: None,
+            outstanding_items: Arc::clone(&self.outstanding_items),
+        }
+    }
 }
 
 /// A LeasedItem holds an object borrowed from a Pool.
@@ -160,7 +228,19 @@ impl<T> Pool<T> {
 /// into the pool.
 pub struct LeasedItem<T> {
     gen_item: Option<GenerationItem<T>>,
-    recycle_queue: Arc<Queue<GenerationItem<T>>>,
+    // `None` for the items that are not part of the pool.
+    recycle_queue: Option<Arc<Queue<GenerationItem<T>>>>,
+    outstanding_items: Arc<OutstandingItems>,
+}
+
+impl<T> LeasedItem<T> {
+    /// Returns the generation of the item, i.e. the reload it was created by.
+    pub fn generation(&self) -> usize {
+        self.gen_item
+            .as_ref()
+            .expect("Unwrapping a leased item should never fail")
+            .generation
+    }
 }
 
 impl<T> Deref for LeasedItem<T> {
@@ -188,7 +268,10 @@ impl<T> DerefMut for LeasedItem<T> {
 impl<T> Drop for LeasedItem<T> {
     fn drop(&mut self) {
         if let Some(gen_item) = self.gen_item.take() {
-            self.recycle_queue.push(gen_item);
+            self.outstanding_items.release(gen_item.generation);
+            if let Some(recycle_queue) = self.recycle_queue.as_ref() {
+                recycle_queue.push(gen_item);
+            }
         }
     }
 }
@@ -216,6 +299,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_pool_num_outstanding_items() {
+        let pool = Pool::new();
+        pool.publish_new_generation(vec![1, 1]);
+        let leased_item = pool.acquire();
+        assert_eq!(leased_item.generation(), 1);
+        pool.publish_new_generation(vec![2, 2]);
+        let detached_item = pool.lease_detached(1, 1);
+        let _fresh_item = pool.acquire();
+        let counts: Vec<(usize, usize)> = pool.num_outstanding_items().into_iter().collect();
+        assert_eq!(counts, vec![(1, 2), (2, 1)]);
+        mem::drop(leased_item);
+        mem::drop(detached_item);
+        let counts: Vec<(usize, usize)> = pool.num_outstanding_items().into_iter().collect();
+        assert_eq!(counts, vec![(2, 1)]);
+    }
+
     #[test]
     fn test_queue() {
         let q = Queue::new();
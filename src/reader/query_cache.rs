@@ -0,0 +1,294 @@
+use crate::collector::Collector;
+use crate::query::Query;
+use crate::Searcher;
+use downcast_rs::Downcast;
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+use std::any::{Any, TypeId};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Hit/miss statistics of the query cache of an `IndexReader`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct QueryCacheInfo {
+    /// Number of searches whose result was found in the cache.
+    pub hit: usize,
+    /// Number of searches that had to be executed.
+    pub miss: usize,
+    /// Number of results currently in the cache.
+    pub num_entries: usize,
+    /// Maximum number of results in the cache.
+    pub capacity: usize,
+}
+
+// Searches are identified by the cache key given by the caller. The types of the
+// query and of the collector are part of the key, so that a fruit is always
+// downcast to the type it was inserted with.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct QueryCacheKey {
+    generation: usize,
+    cache_key: String,
+    query_type: TypeId,
+    collector_type: TypeId,
+}
+
+struct QueryCacheEntry {
+    fruit: Arc<dyn Any + Send + Sync>,
+    inserted_at: Instant,
+}
+
+struct InnerQueryCache {
+    entries: LruCache<QueryCacheKey, QueryCacheEntry>,
+    // Generation of the last reload. The results of older generations are not cached.
+    generation: usize,
+    hits: usize,
+    misses: usize,
+}
+
+/// LRU cache of the results of the searches run through an `IndexReader`.
+///
+/// Results are keyed by the generation of the searcher and the cache key of the search,
+/// so that they are only reused for identical searches on the same version of the index.
+/// The cache is emptied upon each reload.
+///
+/// A capacity of 0 disables caching.
+pub(crate) struct QueryCache {
+    capacity: usize,
+    ttl: Option<Duration>,
+    inner: Mutex<InnerQueryCache>,
+}
+
+impl QueryCache {
+    /// Creates a cache holding up to `capacity` results, for at most `ttl` if set.
+    pub fn new(capacity: usize, ttl: Option<Duration>) -> QueryCache {
+        QueryCache {
+            capacity,
+            ttl,
+            inner: Mutex::new(InnerQueryCache {
+                entries: LruCache::new(capacity),
+                generation: 0,
+                hits: 0,
+                misses: 0,
+            }),
+        }
+    }
+
+    /// Returns the hit/miss statistics of the cache.
+    pub fn info(&self) -> QueryCacheInfo {
+        let inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        QueryCacheInfo {
+            hit: inner.hits,
+            miss: inner.misses,
+            num_entries: inner.entries.len(),
+            capacity: self.capacity,
+        }
+    }
+
+    /// Drops all of the cached results, as `generation` is the new generation of searchers.
+    pub fn invalidate(&self, generation: usize) {
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        inner.generation = generation;
+        inner.entries.clear();
+    }
+
+    /// Returns the result of the search of `query` with `collector` on `searcher`,
+    /// of generation `generation`, from the cache if possible.
+    pub fn search<C>(
+        &self,
+        searcher: &Searcher,
+        generation: usize,
+        cache_key: &str,
+        query: &dyn Query,
+        collector: &C,
+    ) -> crate::Result<C::Fruit>
+    where
+        C: Collector,
+        C::Fruit: Clone + Sync,
+    {
+        if self.capacity == 0 {
+            return searcher.search(query, collector);
+        }
+        let key = QueryCacheKey {
+            generation,
+            cache_key: cache_key.to_string(),
+            query_type: query.as_any().type_id(),
+            collector_type: TypeId::of::<C>(),
+        };
+        if let Some(fruit) = self.get::<C::Fruit>(&key) {
+            return Ok(fruit);
+        }
+        let fruit = searcher.search(query, collector)?;
+        self.put(key, Arc::new(fruit.clone()));
+        Ok(fruit)
+    }
+
+    fn get<F: Clone + 'static>(&self, key: &QueryCacheKey) -> Option<F> {
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        let ttl = self.ttl;
+        let is_expired =
+            |entry: &QueryCacheEntry| ttl.map_or(false, |ttl| entry.inserted_at.elapsed() >= ttl);
+        if inner.entries.peek(key).map_or(false, is_expired) {
+            inner.entries.pop(key);
+        }
+        let fruit_opt = inner
+            .entries
+            .get(key)
+            .and_then(|entry| entry.fruit.downcast_ref::<F>().cloned());
+        if fruit_opt.is_some() {
+            inner.hits += 1;
+        } else {
+            inner.misses += 1;
+        }
+        fruit_opt
+    }
+
+    fn put(&self, key: QueryCacheKey, fruit: Arc<dyn Any + Send + Sync>) {
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        // The searcher may have been acquired before a reload.
+        if key.generation < inner.generation {
+            return;
+        }
+        let entry = QueryCacheEntry {
+            fruit,
+            inserted_at: Instant::now(),
+        };
+        inner.entries.put(key, entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::collector::{Count, TopDocs};
+    use crate::common::BitSet;
+    use crate::query::{DocFilter, FilteredQuery, TermQuery};
+    use crate::schema::{IndexRecordOption, Schema, TEXT};
+    use crate::{Index, ReloadPolicy, SegmentReader, Term};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_query_cache() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text => "a b"));
+        index_writer.add_document(doc!(text => "a"));
+        index_writer.commit()?;
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .query_cache_capacity(10)
+            .try_into()?;
+        let query_a = TermQuery::new(Term::from_field_text(text, "a"), IndexRecordOption::Basic);
+        let query_b = TermQuery::new(Term::from_field_text(text, "b"), IndexRecordOption::Basic);
+
+        assert_eq!(reader.search_cached("a", &query_a, &Count)?, 2);
+        assert_eq!(reader.search_cached("a", &query_a, &Count)?, 2);
+        assert_eq!(reader.search_cached("b", &query_b, &Count)?, 1);
+        assert_eq!(
+            reader
+                .search_cached("a-top1", &query_a, &TopDocs::with_limit(1))?
+                .len(),
+            1
+        );
+        assert_eq!(
+            reader
+                .search_cached("a-top2", &query_a, &TopDocs::with_limit(2))?
+                .len(),
+            2
+        );
+        let query_cache_info = reader.query_cache_info();
+        assert_eq!(query_cache_info.hit, 1);
+        assert_eq!(query_cache_info.miss, 4);
+        assert_eq!(query_cache_info.num_entries, 4);
+
+        index_writer.add_document(doc!(text => "a"));
+        index_writer.commit()?;
+        assert_eq!(reader.search_cached("a", &query_a, &Count)?, 2);
+        reader.reload()?;
+        assert_eq!(reader.query_cache_info().num_entries, 0);
+        assert_eq!(reader.search_cached("a", &query_a, &Count)?, 3);
+        assert_eq!(reader.search_cached("a", &query_a, &Count)?, 3);
+        assert_eq!(reader.query_cache_info().hit, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_cache_filtered_queries() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text => "a"));
+        index_writer.add_document(doc!(text => "a"));
+        index_writer.commit()?;
+        let reader = index.reader_builder().query_cache_capacity(10).try_into()?;
+        let filtered_query = |allow_all: bool| {
+            let filter: Arc<dyn DocFilter> = Arc::new(move |segment_reader: &SegmentReader| {
+                let mut bitset = BitSet::with_max_value(segment_reader.max_doc());
+                if allow_all {
+                    for doc in 0..segment_reader.max_doc() {
+                        bitset.insert(doc);
+                    }
+                }
+                Ok(Arc::new(bitset))
+            });
+            let term_query =
+                TermQuery::new(Term::from_field_text(text, "a"), IndexRecordOption::Basic);
+            FilteredQuery::new(Box::new(term_query), filter)
+        };
+        // The queries only differ by their filter, which their keys reflect.
+        assert_eq!(
+            reader.search_cached("a-allow-all", &filtered_query(true), &Count)?,
+            2
+        );
+        assert_eq!(
+            reader.search_cached("a-allow-none", &filtered_query(false), &Count)?,
+            0
+        );
+        assert_eq!(
+            reader.search_cached("a-allow-all", &filtered_query(true), &Count)?,
+            2
+        );
+        let query_cache_info = reader.query_cache_info();
+        assert_eq!(query_cache_info.num_entries, 2);
+        assert_eq!(query_cache_info.hit, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_cache_ttl() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text => "a"));
+        index_writer.commit()?;
+        let reader = index
+            .reader_builder()
+            .query_cache_capacity(10)
+            .query_cache_ttl(Duration::from_millis(0))
+            .try_into()?;
+        let query = TermQuery::new(Term::from_field_text(text, "a"), IndexRecordOption::Basic);
+        assert_eq!(reader.search_cached("a", &query, &Count)?, 1);
+        assert_eq!(reader.search_cached("a", &query, &Count)?, 1);
+        assert_eq!(reader.query_cache_info().hit, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_cache_disabled() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let reader = index.reader()?;
+        let query = TermQuery::new(Term::from_field_text(text, "a"), IndexRecordOption::Basic);
+        assert_eq!(reader.search_cached("a", &query, &Count)?, 0);
+        assert_eq!(reader.search_cached("a", &query, &Count)?, 0);
+        let query_cache_info = reader.query_cache_info();
+        assert_eq!(query_cache_info.capacity, 0);
+        assert_eq!(query_cache_info.hit + query_cache_info.miss, 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+use crate::Searcher;
+
+/// A `Warmer` prepares the searchers of an `IndexReader` upon each reload,
+/// before they are made available to search.
+///
+/// This is the place to build the data structures that would otherwise be
+/// built lazily by the first queries, e.g. global ordinals:
+///
+/// ```rust
+/// use tantivy::schema::{Schema, INDEXED};
+/// use tantivy::{Index, Searcher, Warmer};
+/// use std::sync::Arc;
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let category = schema_builder.add_facet_field("category", INDEXED);
+/// let index = Index::create_in_ram(schema_builder.build());
+/// let warmer = move |searcher: &Searcher| -> tantivy::Result<()> {
+///     searcher.global_ordinals(category)?;
+///     Ok(())
+/// };
+/// let _reader = index
+///     .reader_builder()
+///     .warmers(vec![Arc::new(warmer)])
+///     .try_into()?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// An error returned by a warmer fails the reload.
+pub trait Warmer: Send + Sync + 'static {
+    /// Warms the searchers of a reload, given one of them.
+    ///
+    /// The searchers of a reload share the same segment readers and caches,
+    /// so warming one of them warms all of them.
+    fn warm(&self, searcher: &Searcher) -> crate::Result<()>;
+}
+
+impl<F> Warmer for F
+where
+    F: Fn(&Searcher) -> crate::Result<()> + Send + Sync + 'static,
+{
+    fn warm(&self, searcher: &Searcher) -> crate::Result<()> {
+        self(searcher)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schema::{Schema, STRING};
+    use crate::{Index, ReloadPolicy, Searcher, TantivyError};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_warmer() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let num_warmed_docs = Arc::new(AtomicUsize::new(0));
+        let num_warmed_docs_clone = num_warmed_docs.clone();
+        let warmer = move |searcher: &Searcher| -> crate::Result<()> {
+            num_warmed_docs_clone.store(searcher.num_docs() as usize, Ordering::SeqCst);
+            Ok(())
+        };
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .warmers(vec![Arc::new(warmer)])
+            .try_into()?;
+        assert_eq!(num_warmed_docs.load(Ordering::SeqCst), 0);
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field => "hello"));
+        index_writer.add_document(doc!(text_field => "happy"));
+        index_writer.commit()?;
+        reader.reload()?;
+        assert_eq!(num_warmed_docs.load(Ordering::SeqCst), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_warmer_error_fails_reload() {
+        let index = Index::create_in_ram(Schema::builder().build());
+        let warmer = |_: &Searcher| -> crate::Result<()> {
+            Err(TantivyError::InvalidArgument("warming failed".to_string()))
+        };
+        let reader_res = index
+            .reader_builder()
+            .warmers(vec![Arc::new(warmer)])
+            .try_into();
+        assert!(matches!(reader_res, Err(TantivyError::InvalidArgument(_))));
+    }
+}
@@ -1,8 +1,10 @@
 use super::*;
 use crate::common::BinarySerializable;
 use crate::common::VInt;
+use crate::schema::dynamic_field::{encode_attribute_text, is_valid_attribute};
 use crate::tokenizer::PreTokenizedString;
 use crate::DateTime;
+use crate::Score;
 use std::io::{self, Read, Write};
 use std::mem;
 
@@ -12,6 +14,10 @@ use std::mem;
 /// Documents are fundamentally a collection of unordered couple `(field, value)`.
 /// In this list, one field may appear more than once.
 ///
+/// The doc store preserves the order in which the values were added to the document,
+/// as well as their multiplicity: a document retrieved with
+/// [`Searcher::doc`](../struct.Searcher.html#method.doc) lists its stored values
+/// in the same order, including after merges.
 ///
 
 /// Documents are really just a list of couple `(field, value)`.
@@ -19,11 +25,17 @@ use std::mem;
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize, Default)]
 pub struct Document {
     field_values: Vec<FieldValue>,
+    // Index-time boosts. They are not stored.
+    #[serde(skip)]
+    field_boosts: Vec<(Field, Score)>,
 }
 
 impl From<Vec<FieldValue>> for Document {
     fn from(field_values: Vec<FieldValue>) -> Self {
-        Document { field_values }
+        Document {
+            field_values,
+            field_boosts: Vec::new(),
+        }
     }
 }
 
@@ -78,6 +90,23 @@ impl Document {
         self.add(FieldValue::new(field, Value::Str(text.to_string())));
     }
 
+    /// Add the text of an attribute of a dynamic field.
+    ///
+    /// See [`SchemaBuilder::add_dynamic_text_field`](./struct.SchemaBuilder.html#method.add_dynamic_text_field).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the attribute is empty or contains a null character.
+    pub fn add_dynamic_text<S: ToString>(&mut self, field: Field, attribute: &str, text: S) {
+        assert!(
+            is_valid_attribute(attribute),
+            "Invalid attribute {:?}",
+            attribute
+        );
+        let value = Value::Str(encode_attribute_text(attribute, &text.to_string()));
+        self.add(FieldValue::new(field, value));
+    }
+
     /// Add a pre-tokenized text field.
     pub fn add_pre_tokenized_text(
         &mut self,
@@ -118,6 +147,36 @@ impl Document {
         self.field_values.push(field_value);
     }
 
+    /// Sets the index-time boost of the text field `field` for this document.
+    ///
+    /// The boost is folded into the fieldnorm of the field: the number of tokens
+    /// recorded for the document is divided by `boost`, so that a boost greater than 1
+    /// increases the BM25 score of the document for the terms of this field,
+    /// as if the field was shorter.
+    ///
+    /// Boosts are not stored, and have no effect on fields whose fieldnorms are disabled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `boost` is not strictly positive.
+    pub fn set_field_boost(&mut self, field: Field, boost: Score) {
+        assert!(boost > 0.0, "Field boost must be positive, got {}", boost);
+        self.field_boosts
+            .retain(|&(boosted_field, _)| boosted_field != field);
+        self.field_boosts.push((field, boost));
+    }
+
+    /// Returns the index-time boost of the field `field` for this document.
+    ///
+    /// Fields are not boosted by default, i.e. their boost is `1.0`.
+    pub fn field_boost(&self, field: Field) -> Score {
+        self.field_boosts
+            .iter()
+            .find(|&&(boosted_field, _)| boosted_field == field)
+            .map(|&(_, boost)| boost)
+            .unwrap_or(1.0)
+    }
+
     /// field_values accessor
     pub fn field_values(&self) -> &[FieldValue] {
         &self.field_values
@@ -173,6 +232,66 @@ impl Document {
         self.get_all(field).next()
     }
 
+    /// Returns the first `u64` value associated to the given field.
+    ///
+    /// Values of a different type are ignored.
+    pub fn get_first_u64(&self, field: Field) -> Option<u64> {
+        self.get_all_u64s(field).next()
+    }
+
+    /// Returns the first `i64` value associated to the given field.
+    ///
+    /// Values of a different type are ignored.
+    pub fn get_first_i64(&self, field: Field) -> Option<i64> {
+        self.get_all_i64s(field).next()
+    }
+
+    /// Returns the first `f64` value associated to the given field.
+    ///
+    /// Values of a different type are ignored.
+    pub fn get_first_f64(&self, field: Field) -> Option<f64> {
+        self.get_all_f64s(field).next()
+    }
+
+    /// Returns the first date associated to the given field.
+    ///
+    /// Values of a different type are ignored.
+    pub fn get_first_date(&self, field: Field) -> Option<&DateTime> {
+        self.get_all_dates(field).next()
+    }
+
+    /// Returns all of the `u64` values associated to the given field,
+    /// in the order in which they were added.
+    ///
+    /// Values of a different type are ignored.
+    pub fn get_all_u64s(&self, field: Field) -> impl Iterator<Item = u64> + '_ {
+        self.get_all(field).filter_map(Value::u64_value)
+    }
+
+    /// Returns all of the `i64` values associated to the given field,
+    /// in the order in which they were added.
+    ///
+    /// Values of a different type are ignored.
+    pub fn get_all_i64s(&self, field: Field) -> impl Iterator<Item = i64> + '_ {
+        self.get_all(field).filter_map(Value::i64_value)
+    }
+
+    /// Returns all of the `f64` values associated to the given field,
+    /// in the order in which they were added.
+    ///
+    /// Values of a different type are ignored.
+    pub fn get_all_f64s(&self, field: Field) -> impl Iterator<Item = f64> + '_ {
+        self.get_all(field).filter_map(Value::f64_value)
+    }
+
+    /// Returns all of the dates associated to the given field,
+    /// in the order in which they were added.
+    ///
+    /// Values of a different type are ignored.
+    pub fn get_all_dates(&self, field: Field) -> impl Iterator<Item = &DateTime> {
+        self.get_all(field).filter_map(Value::date_value)
+    }
+
     /// Prepares Document for being stored in the document store
     ///
     /// Method transforms PreTokenizedString values into String
@@ -223,6 +342,41 @@ mod tests {
         assert_eq!(doc.field_values().len(), 1);
     }
 
+    #[test]
+    fn test_typed_getters() {
+        let mut schema_builder = Schema::builder();
+        let num_field = schema_builder.add_u64_field("num", STORED);
+        let date_field = schema_builder.add_date_field("date", STORED);
+        let first_date = crate::DateTime::from_utc(
+            chrono::NaiveDateTime::from_timestamp(1_600_000_000, 0),
+            chrono::Utc,
+        );
+        let second_date = crate::DateTime::from_utc(
+            chrono::NaiveDateTime::from_timestamp(1_500_000_000, 0),
+            chrono::Utc,
+        );
+        let mut doc = Document::default();
+        doc.add_u64(num_field, 3);
+        doc.add_date(date_field, &first_date);
+        doc.add_i64(num_field, -1);
+        doc.add_u64(num_field, 1);
+        doc.add_u64(num_field, 3);
+        doc.add_date(date_field, &second_date);
+        assert_eq!(doc.get_first_u64(num_field), Some(3));
+        assert_eq!(
+            doc.get_all_u64s(num_field).collect::<Vec<_>>(),
+            vec![3, 1, 3]
+        );
+        assert_eq!(doc.get_first_i64(num_field), Some(-1));
+        assert_eq!(doc.get_first_f64(num_field), None);
+        assert_eq!(doc.get_first_date(date_field), Some(&first_date));
+        assert_eq!(
+            doc.get_all_dates(date_field).collect::<Vec<_>>(),
+            vec![&first_date, &second_date]
+        );
+        assert_eq!(doc.get_first_u64(date_field), None);
+    }
+
     #[test]
     fn test_prepare_for_store() {
         let mut schema_builder = Schema::builder();
@@ -237,6 +391,7 @@ mod tests {
                 position: 0,
                 text: String::from("A"),
                 position_length: 1,
+                stacked: false,
             }],
         };
 
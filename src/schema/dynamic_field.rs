@@ -0,0 +1,192 @@
+/// Separates the attribute from the text, in the values and in the terms
+/// of a dynamic field.
+pub(crate) const ATTRIBUTE_SEP: char = '\u{0}';
+
+/// Returns the prefix of the field names matched by a dynamic field,
+/// or `None` if `field_name` is not the pattern of a dynamic field.
+///
+/// The pattern of a dynamic field is a prefix followed by a `*`, e.g. `attr_*`.
+pub(crate) fn dynamic_field_prefix(field_name: &str) -> Option<&str> {
+    if field_name.ends_with('*') {
+        Some(&field_name[..field_name.len() - 1])
+    } else {
+        None
+    }
+}
+
+/// Returns true if `pattern` can be used as the pattern of a dynamic field.
+pub(crate) fn is_valid_dynamic_field_pattern(pattern: &str) -> bool {
+    match dynamic_field_prefix(pattern) {
+        Some(prefix) => !prefix.is_empty() && !prefix.contains('*'),
+        None => false,
+    }
+}
+
+/// Returns true if `attribute` can be used as the attribute of a dynamic field.
+pub(crate) fn is_valid_attribute(attribute: &str) -> bool {
+    !attribute.is_empty() && !attribute.contains(ATTRIBUTE_SEP)
+}
+
+/// Encodes the text of an attribute of a dynamic field, as it is
+/// stored in the document and indexed.
+pub(crate) fn encode_attribute_text(attribute: &str, text: &str) -> String {
+    let mut encoded = String::with_capacity(attribute.len() + 1 + text.len());
+    encoded.push_str(attribute);
+    encoded.push(ATTRIBUTE_SEP);
+    encoded.push_str(text);
+    encoded
+}
+
+/// Splits the value of a dynamic field into its attribute and its text.
+///
+/// Returns `None` if the value was not encoded with `encode_attribute_text`.
+pub(crate) fn split_attribute_text(encoded: &str) -> Option<(&str, &str)> {
+    let sep_pos = encoded.find(ATTRIBUTE_SEP)?;
+    Some((&encoded[..sep_pos], &encoded[sep_pos + 1..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        dynamic_field_prefix, encode_attribute_text, is_valid_dynamic_field_pattern,
+        split_attribute_text,
+    };
+    use crate::collector::Count;
+    use crate::query::{QueryParser, TermQuery};
+    use crate::schema::{
+        DocParsingError, Document, IndexRecordOption, Schema, Term, Value, STORED, STRING, TEXT,
+    };
+    use crate::{DocAddress, Index};
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_dynamic_field_pattern() {
+        assert_eq!(dynamic_field_prefix("attr_*"), Some("attr_"));
+        assert_eq!(dynamic_field_prefix("attr"), None);
+        assert!(is_valid_dynamic_field_pattern("attr_*"));
+        assert!(!is_valid_dynamic_field_pattern("*"));
+        assert!(!is_valid_dynamic_field_pattern("attr_"));
+        assert!(!is_valid_dynamic_field_pattern("a*b*"));
+    }
+
+    #[test]
+    fn test_encode_attribute_text() {
+        let encoded = encode_attribute_text("color", "dark red");
+        assert_eq!(split_attribute_text(&encoded), Some(("color", "dark red")));
+        assert_eq!(split_attribute_text("dark red"), None);
+    }
+
+    #[test]
+    fn test_schema_dynamic_field() {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let attr = schema_builder.add_dynamic_text_field("attr_*", STRING | STORED);
+        let attr_text = schema_builder.add_dynamic_text_field("attr_text_*", TEXT);
+        let schema = schema_builder.build();
+        assert!(schema.get_field_entry(attr).is_dynamic());
+        assert!(!schema.get_field_entry(title).is_dynamic());
+        assert_eq!(
+            schema.get_dynamic_field("attr_color"),
+            Some((attr, "color"))
+        );
+        assert_eq!(
+            schema.get_dynamic_field("attr_text_desc"),
+            Some((attr_text, "desc"))
+        );
+        assert_eq!(schema.get_dynamic_field("attr_"), None);
+        assert_eq!(schema.get_dynamic_field("title"), None);
+
+        let doc = schema
+            .parse_document(r#"{"title": "a", "attr_color": ["red", "blue"], "attr_size": "xl"}"#)
+            .unwrap();
+        let mut expected_doc = Document::new();
+        expected_doc.add_text(title, "a");
+        expected_doc.add_dynamic_text(attr, "color", "red");
+        expected_doc.add_dynamic_text(attr, "color", "blue");
+        expected_doc.add_dynamic_text(attr, "size", "xl");
+        assert_eq!(
+            schema.to_json(&doc),
+            r#"{"attr_color":["red","blue"],"attr_size":["xl"],"title":["a"]}"#
+        );
+        assert_eq!(schema.to_json(&doc), schema.to_json(&expected_doc));
+        let named_doc = schema.to_named_doc(&doc);
+        let converted_doc = schema.convert_named_doc(named_doc).unwrap();
+        assert_eq!(schema.to_json(&converted_doc), schema.to_json(&doc));
+        assert!(matches!(
+            schema.parse_document(r#"{"attr_color": 3}"#),
+            Err(DocParsingError::ValueError(..))
+        ));
+        assert!(matches!(
+            schema.parse_document(r#"{"other": "a"}"#),
+            Err(DocParsingError::NoSuchFieldInSchema(_))
+        ));
+
+        // The dynamic fields are preserved by the serialization of the schema.
+        let schema_json = serde_json::to_string(&schema).unwrap();
+        let deserialized_schema: Schema = serde_json::from_str(&schema_json).unwrap();
+        assert_eq!(
+            deserialized_schema.get_dynamic_field("attr_color"),
+            Some((attr, "color"))
+        );
+    }
+
+    #[test]
+    fn test_index_dynamic_field() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let attr = schema_builder.add_dynamic_text_field("attr_*", TEXT | STORED);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(
+            schema.parse_document(r#"{"attr_color": "dark red", "attr_material": "red wood"}"#)?,
+        );
+        index_writer.commit()?;
+        let mut doc = Document::new();
+        doc.add_dynamic_text(attr, "color", "red");
+        doc.add_dynamic_text(attr, "shape", "round");
+        index_writer.add_document(doc);
+        index_writer.commit()?;
+        let reader = index.reader()?;
+        let query_parser = QueryParser::for_index(&index, vec![]);
+        let check = || -> crate::Result<()> {
+            reader.reload()?;
+            let searcher = reader.searcher();
+            let count = |query: &str| -> crate::Result<usize> {
+                let query = query_parser.parse_query(query)?;
+                searcher.search(&query, &Count)
+            };
+            assert_eq!(count("attr_color:red")?, 2);
+            assert_eq!(count("attr_material:red")?, 1);
+            assert_eq!(count("attr_color:wood")?, 0);
+            assert_eq!(count("attr_color:\"dark red\"")?, 1);
+            assert_eq!(count("attr_material:\"dark red\"")?, 0);
+            assert_eq!(count("attr_shape:round")?, 1);
+            let term_query = TermQuery::new(
+                Term::from_dynamic_field_text(attr, "shape", "round"),
+                IndexRecordOption::Basic,
+            );
+            assert_eq!(searcher.search(&term_query, &Count)?, 1);
+            Ok(())
+        };
+        check()?;
+
+        let segment_ids = index.searchable_segment_ids()?;
+        block_on(index_writer.merge(&segment_ids))?;
+        index_writer.wait_merging_threads()?;
+        check()?;
+
+        let searcher = reader.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let stored_doc = searcher.doc(DocAddress::new(0, 0))?;
+        let named_doc = schema.to_named_doc(&stored_doc);
+        assert_eq!(
+            named_doc.0.get("attr_color"),
+            Some(&vec![Value::Str("dark red".to_string())])
+        );
+        assert_eq!(
+            named_doc.0.get("attr_material"),
+            Some(&vec![Value::Str("red wood".to_string())])
+        );
+        Ok(())
+    }
+}
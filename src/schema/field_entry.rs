@@ -3,6 +3,7 @@ use crate::schema::TextOptions;
 use crate::schema::{is_valid_field_name, IntOptions};
 
 use crate::schema::bytes_options::BytesOptions;
+use crate::schema::dynamic_field::is_valid_dynamic_field_pattern;
 use crate::schema::FieldType;
 use serde::de::{self, MapAccess, Visitor};
 use serde::ser::SerializeStruct;
@@ -109,6 +110,14 @@ impl FieldEntry {
         &self.field_type
     }
 
+    /// Returns true iff the field is a dynamic field, i.e. a text field
+    /// whose name is a pattern such as `attr_*`.
+    ///
+    /// See [`SchemaBuilder::add_dynamic_text_field`](./struct.SchemaBuilder.html#method.add_dynamic_text_field).
+    pub fn is_dynamic(&self) -> bool {
+        matches!(self.field_type, FieldType::Str(_)) && is_valid_dynamic_field_pattern(&self.name)
+    }
+
     /// Returns true iff the field is indexed
     pub fn is_indexed(&self) -> bool {
         match self.field_type {
@@ -1,6 +1,7 @@
 use crate::schema::bytes_options::BytesOptions;
 use crate::schema::facet_options::FacetOptions;
 use crate::schema::Facet;
+use crate::schema::FieldNormEncoding;
 use crate::schema::IndexRecordOption;
 use crate::schema::TextFieldIndexing;
 use crate::schema::Value;
@@ -129,6 +130,57 @@ impl FieldType {
         }
     }
 
+    /// Returns the precision step of a numeric field indexed at several precisions.
+    ///
+    /// See [`IntOptions::set_precision_step`](./struct.IntOptions.html#method.set_precision_step).
+    ///
+    /// If the field is not indexed, then returns `None`.
+    pub fn get_precision_step(&self) -> Option<u8> {
+        match *self {
+            FieldType::U64(ref int_options)
+            | FieldType::I64(ref int_options)
+            | FieldType::F64(ref int_options)
+            | FieldType::Date(ref int_options)
+                if int_options.is_indexed() =>
+            {
+                int_options.get_precision_step()
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns true iff the values of the numeric field are looked up through a map
+    /// built from its fast field.
+    ///
+    /// See [`IntOptions::set_lookup_index`](./struct.IntOptions.html#method.set_lookup_index).
+    pub fn has_lookup_index(&self) -> bool {
+        match *self {
+            FieldType::U64(ref int_options)
+            | FieldType::I64(ref int_options)
+            | FieldType::F64(ref int_options)
+            | FieldType::Date(ref int_options) => int_options.has_lookup_index(),
+            _ => false,
+        }
+    }
+
+    /// Given a field configuration, return the `FieldNormEncoding`
+    /// of its fieldnorms.
+    ///
+    /// Only text fields can be configured. Other indexed fields use
+    /// the compressed encoding.
+    ///
+    /// If the field is not indexed, then returns `None`.
+    pub fn get_fieldnorm_encoding(&self) -> Option<FieldNormEncoding> {
+        match *self {
+            FieldType::Str(ref text_options) => text_options
+                .get_indexing_options()
+                .map(TextFieldIndexing::fieldnorm_encoding),
+            _ => self
+                .get_index_record_option()
+                .map(|_| FieldNormEncoding::Compressed),
+        }
+    }
+
     /// Parses a field value from json, given the target FieldType.
     ///
     /// Tantivy will not try to cast values.
@@ -316,6 +368,7 @@ mod tests {
                     position: 0,
                     text: String::from("The"),
                     position_length: 1,
+                    stacked: false,
                 },
                 Token {
                     offset_from: 4,
@@ -323,6 +376,7 @@ mod tests {
                     position: 1,
                     text: String::from("Old"),
                     position_length: 1,
+                    stacked: false,
                 },
                 Token {
                     offset_from: 8,
@@ -330,6 +384,7 @@ mod tests {
                     position: 2,
                     text: String::from("Man"),
                     position_length: 1,
+                    stacked: false,
                 },
             ],
         });
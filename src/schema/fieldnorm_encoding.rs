@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+/// `FieldNormEncoding` describes how the fieldnorms (the number of tokens
+/// of the field, for each document) of a text field are recorded.
+///
+/// Fieldnorms are used by the BM25 similarity for length normalization.
+/// (See [`TextFieldIndexing.set_fieldnorm_encoding`](
+///     ../schema/struct.TextFieldIndexing.html#method.set_fieldnorm_encoding))
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FieldNormEncoding {
+    /// The fieldnorm is approximated over one byte per document,
+    /// using the same log-scale as Lucene.
+    #[serde(rename = "compressed")]
+    Compressed,
+    /// The exact fieldnorm is recorded over 4 bytes per document.
+    #[serde(rename = "exact")]
+    Exact,
+    /// No fieldnorm is recorded. The BM25 similarity then
+    /// does not apply any length normalization.
+    #[serde(rename = "disabled")]
+    Disabled,
+}
+
+impl FieldNormEncoding {
+    /// Returns true iff this is the default encoding.
+    pub(crate) fn is_compressed(&self) -> bool {
+        *self == FieldNormEncoding::Compressed
+    }
+}
+
+impl Default for FieldNormEncoding {
+    fn default() -> FieldNormEncoding {
+        FieldNormEncoding::Compressed
+    }
+}
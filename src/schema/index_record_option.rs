@@ -28,6 +28,16 @@ pub enum IndexRecordOption {
     /// Positions are required to run [PhraseQueries](../query/struct.PhraseQuery.html).
     #[serde(rename = "position")]
     WithFreqsAndPositions,
+    /// records the document id, the term frequency, the positions of
+    /// the occurences in the document, as well as their offsets (in bytes) in the text.
+    /// Offsets make it possible to highlight the occurences of a term without
+    /// tokenizing the text again (See [`SegmentPostings::offsets`](
+    ///     ../postings/struct.SegmentPostings.html#method.offsets)).
+    ///
+    /// The offsets of the values of a multivalued field are expressed as if
+    /// these values were concatenated.
+    #[serde(rename = "offsets")]
+    WithFreqsAndPositionsAndOffsets,
 }
 
 impl IndexRecordOption {
@@ -36,7 +46,9 @@ impl IndexRecordOption {
     pub fn has_freq(self) -> bool {
         match self {
             IndexRecordOption::Basic => false,
-            IndexRecordOption::WithFreqs | IndexRecordOption::WithFreqsAndPositions => true,
+            IndexRecordOption::WithFreqs
+            | IndexRecordOption::WithFreqsAndPositions
+            | IndexRecordOption::WithFreqsAndPositionsAndOffsets => true,
         }
     }
 
@@ -45,7 +57,22 @@ impl IndexRecordOption {
     pub fn has_positions(self) -> bool {
         match self {
             IndexRecordOption::Basic | IndexRecordOption::WithFreqs => false,
-            IndexRecordOption::WithFreqsAndPositions => true,
+            IndexRecordOption::WithFreqsAndPositions
+            | IndexRecordOption::WithFreqsAndPositionsAndOffsets => true,
         }
     }
+
+    /// Returns true iff this option include encoding
+    /// the offsets of the terms.
+    pub fn has_offsets(self) -> bool {
+        self == IndexRecordOption::WithFreqsAndPositionsAndOffsets
+    }
+
+    /// Returns the richest option that is included in both `self` and `other`.
+    ///
+    /// This is typically used to downgrade the option requested by a query
+    /// to the information that was actually indexed for the field.
+    pub fn downgrade(self, other: IndexRecordOption) -> IndexRecordOption {
+        self.min(other)
+    }
 }
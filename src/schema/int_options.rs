@@ -21,6 +21,14 @@ pub struct IntOptions {
     #[serde(skip_serializing_if = "Option::is_none")]
     fast: Option<Cardinality>,
     stored: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    precision_step: Option<u8>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    lookup_index: bool,
+}
+
+fn is_false(val: &bool) -> bool {
+    !*val
 }
 
 impl IntOptions {
@@ -68,6 +76,65 @@ impl IntOptions {
         self
     }
 
+    /// Indexes the values of the field at several precisions, so that range queries
+    /// over a wide range of values read a logarithmic number of posting lists.
+    ///
+    /// In addition to the term of each value, a term is indexed for each of the
+    /// prefixes of the value obtained by dropping a multiple of `precision_step` bits.
+    /// A range query then reads the posting lists of the few low precision terms
+    /// covering the middle of the range, and only reads full precision terms at its edges.
+    ///
+    /// A smaller precision step makes range queries faster, at the cost of a bigger
+    /// index. The precision step is expressed in bits, and must be a multiple of 8
+    /// between 8 and 56.
+    ///
+    /// The low precision terms share the term dictionary of the field, but are neither
+    /// enumerated by `Searcher::terms` nor counted in the number of terms of
+    /// `Searcher::field_stats`.
+    ///
+    /// This option only has an effect if the field is indexed.
+    pub fn set_precision_step(mut self, precision_step: u8) -> IntOptions {
+        assert!(
+            precision_step % 8 == 0 && (8..64).contains(&precision_step),
+            "The precision step must be a multiple of 8 between 8 and 56, got {}.",
+            precision_step
+        );
+        self.precision_step = Some(precision_step);
+        self
+    }
+
+    /// Returns the precision step of the field, if it is indexed at several precisions.
+    ///
+    /// See [`set_precision_step`](#method.set_precision_step).
+    pub fn get_precision_step(&self) -> Option<u8> {
+        self.precision_step
+    }
+
+    /// Looks up the values of the field through a map from each value to the documents
+    /// holding it, built in each segment from the fast field.
+    ///
+    /// `TermQuery`s on the field, as well as `Searcher::doc_by_key`, then find their
+    /// documents with the map instead of encoding the value as a term and reading
+    /// its posting list. This dramatically speeds up lookups by numeric id, e.g.
+    /// primary key joins. `TermQuery`s on the field give a constant score of 1.
+    ///
+    /// The map of a segment is built the first time it is needed, and kept as long as the
+    /// segment reader. See [`FastFieldLookup`](../fastfield/struct.FastFieldLookup.html).
+    ///
+    /// This option only has an effect if the field is a single-valued fast field.
+    pub fn set_lookup_index(mut self) -> IntOptions {
+        self.lookup_index = true;
+        self
+    }
+
+    /// Returns true iff the values of the field are looked up through a map
+    /// built from the fast field.
+    ///
+    /// See [`set_lookup_index`](#method.set_lookup_index).
+    pub fn has_lookup_index(&self) -> bool {
+        self.lookup_index && self.fast == Some(Cardinality::SingleValue)
+    }
+
     /// Returns the cardinality of the fastfield.
     ///
     /// If the field has not been declared as a fastfield, then
@@ -83,6 +150,8 @@ impl Default for IntOptions {
             indexed: false,
             stored: false,
             fast: None,
+            precision_step: None,
+            lookup_index: false,
         }
     }
 }
@@ -99,6 +168,8 @@ impl From<FastFlag> for IntOptions {
             indexed: false,
             stored: false,
             fast: Some(Cardinality::SingleValue),
+            precision_step: None,
+            lookup_index: false,
         }
     }
 }
@@ -109,6 +180,8 @@ impl From<StoredFlag> for IntOptions {
             indexed: false,
             stored: true,
             fast: None,
+            precision_step: None,
+            lookup_index: false,
         }
     }
 }
@@ -119,6 +192,8 @@ impl From<IndexedFlag> for IntOptions {
             indexed: true,
             stored: false,
             fast: None,
+            precision_step: None,
+            lookup_index: false,
         }
     }
 }
@@ -132,6 +207,8 @@ impl<T: Into<IntOptions>> BitOr<T> for IntOptions {
             indexed: self.indexed | other.indexed,
             stored: self.stored | other.stored,
             fast: self.fast.or(other.fast),
+            precision_step: self.precision_step.or(other.precision_step),
+            lookup_index: self.lookup_index | other.lookup_index,
         }
     }
 }
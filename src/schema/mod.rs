@@ -103,6 +103,7 @@ let schema = schema_builder.build();
 */
 
 mod document;
+pub(crate) mod dynamic_field;
 mod facet;
 mod facet_options;
 mod schema;
@@ -114,6 +115,7 @@ mod field_value;
 
 mod bytes_options;
 mod field;
+mod fieldnorm_encoding;
 mod index_record_option;
 mod int_options;
 mod named_field_document;
@@ -140,10 +142,13 @@ pub use self::field_entry::FieldEntry;
 pub use self::field_type::{FieldType, Type};
 pub use self::field_value::FieldValue;
 
+pub use self::fieldnorm_encoding::FieldNormEncoding;
 pub use self::index_record_option::IndexRecordOption;
 pub use self::text_options::TextFieldIndexing;
 pub use self::text_options::TextOptions;
+pub use self::text_options::DEFAULT_POSITION_GAP;
 pub use self::text_options::STRING;
+pub use self::text_options::STRING_NORMALIZED;
 pub use self::text_options::TEXT;
 
 pub use self::bytes_options::BytesOptions;
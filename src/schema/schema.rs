@@ -5,6 +5,10 @@ use std::sync::Arc;
 
 use super::*;
 use crate::schema::bytes_options::BytesOptions;
+use crate::schema::dynamic_field::{
+    dynamic_field_prefix, encode_attribute_text, is_valid_attribute,
+    is_valid_dynamic_field_pattern, split_attribute_text,
+};
 use serde::de::{SeqAccess, Visitor};
 use serde::ser::SerializeSeq;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
@@ -145,6 +149,40 @@ impl SchemaBuilder {
         self.add_field(field_entry)
     }
 
+    /// Adds a dynamic text field to the schema.
+    ///
+    /// A dynamic field is declared with a pattern made of a prefix followed by a `*`,
+    /// e.g. `attr_*`. Documents can then contain any number of attributes whose name
+    /// matches the pattern (`attr_color`, `attr_size`, ...) without declaring them in
+    /// the schema: all of them share the options of the dynamic field.
+    ///
+    /// Attributes are added to documents with
+    /// [`Document::add_dynamic_text`](./struct.Document.html#method.add_dynamic_text),
+    /// and searched with terms built with
+    /// [`Term::from_dynamic_field_text`](./struct.Term.html#method.from_dynamic_field_text).
+    /// `Schema::parse_document` and the query parser resolve attribute names such as `attr_color`.
+    ///
+    /// All of the attributes are indexed in the dynamic field itself, with their terms
+    /// prefixed by the name of the attribute. As a result, the space of fields handled by
+    /// fieldnorms, fast fields and merges stays bounded, but the fieldnorm of a document
+    /// is the total number of tokens of all of its attributes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pattern does not consist of a non-empty prefix followed by a `*`.
+    pub fn add_dynamic_text_field<T: Into<TextOptions>>(
+        &mut self,
+        pattern: &str,
+        field_options: T,
+    ) -> Field {
+        assert!(
+            is_valid_dynamic_field_pattern(pattern),
+            "Invalid dynamic field pattern {:?}",
+            pattern
+        );
+        self.add_text_field(pattern, field_options)
+    }
+
     /// Adds a facet field to the schema.
     pub fn add_facet_field<T: Into<FacetOptions>>(
         &mut self,
@@ -186,16 +224,28 @@ impl SchemaBuilder {
     /// Finalize the creation of a `Schema`
     /// This will consume your `SchemaBuilder`
     pub fn build(self) -> Schema {
+        let dynamic_fields = self
+            .fields
+            .iter()
+            .enumerate()
+            .filter(|(_, field_entry)| field_entry.is_dynamic())
+            .filter_map(|(field_id, field_entry)| {
+                let prefix = dynamic_field_prefix(field_entry.name())?;
+                Some((prefix.to_string(), Field::from_field_id(field_id as u32)))
+            })
+            .collect();
         Schema(Arc::new(InnerSchema {
             fields: self.fields,
             fields_map: self.fields_map,
+            dynamic_fields,
         }))
     }
 }
 #[derive(Debug)]
 struct InnerSchema {
     fields: Vec<FieldEntry>,
-    fields_map: HashMap<String, Field>, // transient
+    fields_map: HashMap<String, Field>,   // transient
+    dynamic_fields: Vec<(String, Field)>, // transient
 }
 
 impl PartialEq for InnerSchema {
@@ -249,6 +299,14 @@ impl Schema {
             .map(|(field_id, field_entry)| (Field::from_field_id(field_id as u32), field_entry))
     }
 
+    /// Returns true if at least one of the fields is indexed.
+    ///
+    /// Indexes whose schema has no indexed field are pure document stores:
+    /// their segments do not have any inverted index.
+    pub fn has_indexed_fields(&self) -> bool {
+        self.0.fields.iter().any(FieldEntry::is_indexed)
+    }
+
     /// Creates a new builder.
     pub fn builder() -> SchemaBuilder {
         SchemaBuilder::default()
@@ -259,6 +317,34 @@ impl Schema {
         self.0.fields_map.get(field_name).cloned()
     }
 
+    /// Returns the dynamic field whose pattern matches a given name,
+    /// as well as the name of the attribute.
+    ///
+    /// If several dynamic fields match the name, the one with the longest prefix is returned.
+    ///
+    /// See [`SchemaBuilder::add_dynamic_text_field`](./struct.SchemaBuilder.html#method.add_dynamic_text_field).
+    pub fn get_dynamic_field<'a>(&self, field_name: &'a str) -> Option<(Field, &'a str)> {
+        self.0
+            .dynamic_fields
+            .iter()
+            .filter(|(prefix, _)| field_name.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(prefix, field)| (*field, &field_name[prefix.len()..]))
+            .filter(|(_, attribute)| is_valid_attribute(attribute))
+    }
+
+    /// Resolves a field name, which may refer to an attribute of a dynamic field.
+    pub(crate) fn resolve_field_name<'a>(
+        &self,
+        field_name: &'a str,
+    ) -> Option<(Field, Option<&'a str>)> {
+        if let Some(field) = self.get_field(field_name) {
+            return Some((field, None));
+        }
+        let (field, attribute) = self.get_dynamic_field(field_name)?;
+        Some((field, Some(attribute)))
+    }
+
     /// Create a named document off the doc.
     pub fn convert_named_doc(
         &self,
@@ -266,10 +352,13 @@ impl Schema {
     ) -> Result<Document, DocParsingError> {
         let mut document = Document::new();
         for (field_name, values) in named_doc.0 {
-            if let Some(field) = self.get_field(&field_name) {
+            if let Some((field, attribute_opt)) = self.resolve_field_name(&field_name) {
                 for value in values {
-                    let field_value = FieldValue::new(field, value);
-                    document.add(field_value);
+                    let value = match attribute_opt {
+                        Some(attribute) => attribute_value(&field_name, attribute, value)?,
+                        None => value,
+                    };
+                    document.add(FieldValue::new(field, value));
                 }
             } else {
                 return Err(DocParsingError::NoSuchFieldInSchema(field_name));
@@ -279,10 +368,33 @@ impl Schema {
     }
 
     /// Create a named document off the doc.
+    ///
+    /// The values of dynamic fields are listed under the name of their attribute.
     pub fn to_named_doc(&self, doc: &Document) -> NamedFieldDocument {
         let mut field_map = BTreeMap::new();
         for (field, field_values) in doc.get_sorted_field_values() {
-            let field_name = self.get_field_name(field);
+            let field_entry = self.get_field_entry(field);
+            if field_entry.is_dynamic() {
+                let prefix = dynamic_field_prefix(field_entry.name()).unwrap_or("");
+                for field_value in field_values {
+                    let (field_name, value) = match field_value.value() {
+                        Value::Str(encoded) => match split_attribute_text(encoded) {
+                            Some((attribute, text)) => (
+                                format!("{}{}", prefix, attribute),
+                                Value::Str(text.to_string()),
+                            ),
+                            None => (field_entry.name().to_string(), field_value.value().clone()),
+                        },
+                        value => (field_entry.name().to_string(), value.clone()),
+                    };
+                    field_map
+                        .entry(field_name)
+                        .or_insert_with(Vec::new)
+                        .push(value);
+                }
+                continue;
+            }
+            let field_name = field_entry.name();
             let values: Vec<Value> = field_values
                 .into_iter()
                 .map(FieldValue::value)
@@ -314,25 +426,28 @@ impl Schema {
 
         let mut doc = Document::default();
         for (field_name, json_value) in json_obj.iter() {
-            let field = self
-                .get_field(field_name)
+            let (field, attribute_opt) = self
+                .resolve_field_name(field_name)
                 .ok_or_else(|| DocParsingError::NoSuchFieldInSchema(field_name.clone()))?;
             let field_entry = self.get_field_entry(field);
             let field_type = field_entry.field_type();
+            let parse_value = |json_value: &JsonValue| {
+                let value = field_type
+                    .value_from_json(json_value)
+                    .map_err(|e| DocParsingError::ValueError(field_name.clone(), e))?;
+                match attribute_opt {
+                    Some(attribute) => attribute_value(field_name, attribute, value),
+                    None => Ok(value),
+                }
+            };
             match *json_value {
                 JsonValue::Array(ref json_items) => {
                     for json_item in json_items {
-                        let value = field_type
-                            .value_from_json(json_item)
-                            .map_err(|e| DocParsingError::ValueError(field_name.clone(), e))?;
-                        doc.add(FieldValue::new(field, value));
+                        doc.add(FieldValue::new(field, parse_value(json_item)?));
                     }
                 }
                 _ => {
-                    let value = field_type
-                        .value_from_json(json_value)
-                        .map_err(|e| DocParsingError::ValueError(field_name.clone(), e))?;
-                    doc.add(FieldValue::new(field, value));
+                    doc.add(FieldValue::new(field, parse_value(json_value)?));
                 }
             }
         }
@@ -340,6 +455,24 @@ impl Schema {
     }
 }
 
+// Encodes the value of an attribute of a dynamic field.
+fn attribute_value(
+    field_name: &str,
+    attribute: &str,
+    value: Value,
+) -> Result<Value, DocParsingError> {
+    match value {
+        Value::Str(text) => Ok(Value::Str(encode_attribute_text(attribute, &text))),
+        _ => Err(DocParsingError::ValueError(
+            field_name.to_string(),
+            ValueParsingError::TypeError(format!(
+                "Expected a string for the attribute of a dynamic field, got {:?}",
+                value
+            )),
+        )),
+    }
+}
+
 impl Serialize for Schema {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -2,6 +2,7 @@ use std::fmt;
 
 use super::Field;
 use crate::common;
+use crate::schema::dynamic_field::encode_attribute_text;
 use crate::schema::Facet;
 use crate::DateTime;
 use std::str;
@@ -11,7 +12,30 @@ const INT_TERM_LEN: usize = 4 + 8;
 
 /// Term represents the value that the token can take.
 ///
-/// It actually wraps a `Vec<u8>`.
+/// It actually wraps a `Vec<u8>`, or any other container of bytes.
+///
+/// Building a `Term` with one of the `from_field_*` constructors requires an allocation.
+/// In hot paths, these allocations can be avoided by either
+/// - reusing a single `Term` as a buffer, using `Term::for_field` and the `set_*` methods.
+/// - wrapping already serialized bytes (e.g. a term dictionary key prefixed by its field)
+///   with `Term::wrap`, which does not copy them.
+///
+/// Read-only APIs, such as `Searcher::doc_freq` or `InvertedIndexReader::read_postings`,
+/// accept any `Term<B>`.
+///
+/// ```rust
+/// use tantivy::schema::{Schema, Term, TEXT};
+///
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let mut term = Term::for_field(title);
+/// for word in &["hello", "happy"] {
+///     term.set_text(word);
+///     assert_eq!(term.text(), *word);
+/// }
+/// let borrowed_term: Term<&[u8]> = Term::wrap(term.as_slice());
+/// assert_eq!(borrowed_term.field(), title);
+/// ```
 #[derive(Clone, PartialEq, PartialOrd, Ord, Eq, Hash)]
 pub struct Term<B = Vec<u8>>(B)
 where
@@ -82,6 +106,16 @@ impl Term {
         term
     }
 
+    /// Builds a term given a dynamic field, the name of one of its attributes,
+    /// and a string value.
+    ///
+    /// The attribute is the part of the field name matched by the `*` of the
+    /// dynamic field pattern, e.g. `color` for the field name `attr_color`
+    /// and the dynamic field `attr_*`.
+    pub fn from_dynamic_field_text(field: Field, attribute: &str, text: &str) -> Term {
+        Term::from_field_text(field, &encode_attribute_text(attribute, text))
+    }
+
     /// Builds a term given a field, and a u64-value
     ///
     /// Assuming the term has a field id of 1, and a u64 value of 3234,
@@ -103,14 +137,18 @@ impl Term {
         term
     }
 
-    /// Creates a new Term for a given field.
-    pub(crate) fn for_field(field: Field) -> Term {
+    /// Creates a new Term for a given field, with an empty value.
+    ///
+    /// Its value can then be set, and reset, without any new allocation
+    /// in most cases, using the `set_*` methods.
+    pub fn for_field(field: Field) -> Term {
         let mut term = Term(Vec::with_capacity(100));
         term.set_field(field);
         term
     }
 
-    pub(crate) fn set_field(&mut self, field: Field) {
+    /// Sets the field of the term, and clears its value.
+    pub fn set_field(&mut self, field: Field) {
         self.0.clear();
         self.0
             .extend_from_slice(field.field_id().to_be_bytes().as_ref());
@@ -229,7 +267,10 @@ where
     }
 }
 
-impl fmt::Debug for Term {
+impl<B> fmt::Debug for Term<B>
+where
+    B: AsRef<[u8]>,
+{
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
@@ -245,6 +286,22 @@ mod tests {
 
     use crate::schema::*;
 
+    #[test]
+    pub fn test_term_buffer_and_borrowed_term() {
+        let mut schema_builder = Schema::builder();
+        let title_field = schema_builder.add_text_field("title", STRING);
+        let count_field = schema_builder.add_u64_field("count", INDEXED);
+        let mut term = Term::for_field(title_field);
+        term.set_text("test");
+        assert_eq!(term, Term::from_field_text(title_field, "test"));
+        term.set_field(count_field);
+        term.set_u64(983u64);
+        assert_eq!(term, Term::from_field_u64(count_field, 983u64));
+        let borrowed_term: Term<&[u8]> = Term::wrap(term.as_slice());
+        assert_eq!(borrowed_term.field(), count_field);
+        assert_eq!(borrowed_term.get_u64(), 983u64);
+    }
+
     #[test]
     pub fn test_term() {
         let mut schema_builder = Schema::builder();
@@ -1,5 +1,6 @@
 use crate::schema::flags::SchemaFlagList;
 use crate::schema::flags::StoredFlag;
+use crate::schema::FieldNormEncoding;
 use crate::schema::IndexRecordOption;
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
@@ -34,6 +35,29 @@ impl TextOptions {
         self.indexing = Some(indexing);
         self
     }
+
+    /// Sets whether the fieldnorms of the field are recorded.
+    ///
+    /// Fields for which length normalization is unnecessary (ids, tags, ...) can skip
+    /// their fieldnorms, saving one byte per document.
+    /// Disabling the fieldnorms sets the `FieldNormEncoding::Disabled` encoding,
+    /// while enabling them restores the default encoding if they were disabled.
+    ///
+    /// This has no effect on a field that is not indexed,
+    /// and should therefore be called after setting the indexing options.
+    pub fn set_fieldnorms(mut self, fieldnorms: bool) -> TextOptions {
+        self.indexing = self.indexing.map(|indexing| {
+            let fieldnorm_encoding = indexing.fieldnorm_encoding();
+            if !fieldnorms {
+                indexing.set_fieldnorm_encoding(FieldNormEncoding::Disabled)
+            } else if fieldnorm_encoding == FieldNormEncoding::Disabled {
+                indexing.set_fieldnorm_encoding(FieldNormEncoding::Compressed)
+            } else {
+                indexing
+            }
+        });
+        self
+    }
 }
 
 impl Default for TextOptions {
@@ -51,17 +75,45 @@ impl Default for TextOptions {
 /// - the amount of information that should be stored about the presence of a term in a document.
 /// Essentially, should we store the term frequency and/or the positions (See [`IndexRecordOption`](./enum.IndexRecordOption.html)).
 /// - the name of the `Tokenizer` that should be used to process the field.
+/// - how the length of the field should be recorded (See [`FieldNormEncoding`](./enum.FieldNormEncoding.html)).
+/// - whether the field is only used to filter documents (See [`set_filter_only`](#method.set_filter_only)).
+/// - the maximum term frequency recorded (See [`set_max_term_freq`](#method.set_max_term_freq)).
+/// - the gap between the positions of the values of a multivalued field (See [`set_position_gap`](#method.set_position_gap)).
+/// - the maximum number of tokens indexed per value (See [`set_max_num_tokens`](#method.set_max_num_tokens)).
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct TextFieldIndexing {
     record: IndexRecordOption,
     tokenizer: Cow<'static, str>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "FieldNormEncoding::is_compressed")]
+    fieldnorms: FieldNormEncoding,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    filter_only: bool,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_term_freq: Option<u32>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    position_gap: Option<u32>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_num_tokens: Option<u32>,
 }
 
+/// Default gap between the positions of the values of a multivalued text field.
+pub const DEFAULT_POSITION_GAP: u32 = 100;
+
 impl Default for TextFieldIndexing {
     fn default() -> TextFieldIndexing {
         TextFieldIndexing {
             tokenizer: Cow::Borrowed("default"),
             record: IndexRecordOption::Basic,
+            fieldnorms: FieldNormEncoding::Compressed,
+            filter_only: false,
+            max_term_freq: None,
+            position_gap: None,
+            max_num_tokens: None,
         }
     }
 }
@@ -88,9 +140,127 @@ impl TextFieldIndexing {
 
     /// Returns the indexing options associated to this field.
     ///
+    /// Filter-only fields are always indexed with `IndexRecordOption::Basic`,
+    /// regardless of the option that was set.
+    ///
     /// See [IndexRecordOption](./enum.IndexRecordOption.html) for more detail.
     pub fn index_option(&self) -> IndexRecordOption {
-        self.record
+        if self.filter_only {
+            IndexRecordOption::Basic
+        } else {
+            self.record
+        }
+    }
+
+    /// Declares the field as only used to filter documents.
+    ///
+    /// A filter-only field never indexes nor decodes term frequencies and positions,
+    /// and does not record fieldnorms: its terms do not contribute to the score of
+    /// the documents. Phrase queries on a filter-only field are rejected.
+    pub fn set_filter_only(mut self) -> TextFieldIndexing {
+        self.filter_only = true;
+        self
+    }
+
+    /// Returns true iff the field is only used to filter documents.
+    pub fn is_filter_only(&self) -> bool {
+        self.filter_only
+    }
+
+    /// Sets how the fieldnorms of the field should be recorded.
+    ///
+    /// See [FieldNormEncoding](./enum.FieldNormEncoding.html) for more detail.
+    pub fn set_fieldnorm_encoding(
+        mut self,
+        fieldnorm_encoding: FieldNormEncoding,
+    ) -> TextFieldIndexing {
+        self.fieldnorms = fieldnorm_encoding;
+        self
+    }
+
+    /// Returns how the fieldnorms of the field are recorded.
+    ///
+    /// The fieldnorms of filter-only fields are always disabled.
+    pub fn fieldnorm_encoding(&self) -> FieldNormEncoding {
+        if self.filter_only {
+            FieldNormEncoding::Disabled
+        } else {
+            self.fieldnorms
+        }
+    }
+
+    /// Clips the term frequencies recorded for the field to `max_term_freq`.
+    ///
+    /// Repeating a term over and over again in a document then no longer inflates
+    /// the size of the postings. As BM25 saturates with the term frequency, this has
+    /// little effect on the scores for reasonable values (e.g. 255).
+    ///
+    /// If the field records positions, only the positions of the
+    /// first `max_term_freq` occurrences of a term in a document are recorded.
+    ///
+    /// The term frequencies of existing segments are clipped when they are merged.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_term_freq` is 0.
+    pub fn set_max_term_freq(mut self, max_term_freq: u32) -> TextFieldIndexing {
+        assert!(
+            max_term_freq > 0,
+            "The max term frequency must be positive."
+        );
+        self.max_term_freq = Some(max_term_freq);
+        self
+    }
+
+    /// Returns the maximum term frequency recorded for the field, if any.
+    pub fn max_term_freq(&self) -> Option<u32> {
+        self.max_term_freq
+    }
+
+    /// Sets the gap inserted between the positions of the successive values
+    /// of a multivalued field.
+    ///
+    /// The first token of a value is positioned `position_gap` positions after the last token
+    /// of the previous value, so that phrase queries do not match across the values.
+    /// Defaults to [`DEFAULT_POSITION_GAP`](./constant.DEFAULT_POSITION_GAP.html).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `position_gap` is 0.
+    pub fn set_position_gap(mut self, position_gap: u32) -> TextFieldIndexing {
+        assert!(position_gap > 0, "The position gap must be positive.");
+        self.position_gap = Some(position_gap);
+        self
+    }
+
+    /// Returns the gap between the positions of the values of a multivalued field.
+    pub fn position_gap(&self) -> u32 {
+        self.position_gap.unwrap_or(DEFAULT_POSITION_GAP)
+    }
+
+    /// Caps the number of tokens indexed for each value of the field to `max_num_tokens`.
+    ///
+    /// The tokens past the limit are dropped, and the remainder of the value is not
+    /// tokenized, so that pathologically large values do not blow up the indexing memory
+    /// and the size of the postings.
+    ///
+    /// See [`LimitTokenCountFilter`](../tokenizer/struct.LimitTokenCountFilter.html).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_num_tokens` is 0.
+    pub fn set_max_num_tokens(mut self, max_num_tokens: u32) -> TextFieldIndexing {
+        assert!(
+            max_num_tokens > 0,
+            "The max number of tokens must be positive."
+        );
+        self.max_num_tokens = Some(max_num_tokens);
+        self
+    }
+
+    /// Returns the maximum number of tokens indexed for each value of the field, if any.
+    pub fn max_num_tokens(&self) -> Option<u32> {
+        self.max_num_tokens
     }
 }
 
@@ -99,6 +269,28 @@ pub const STRING: TextOptions = TextOptions {
     indexing: Some(TextFieldIndexing {
         tokenizer: Cow::Borrowed("raw"),
         record: IndexRecordOption::Basic,
+        fieldnorms: FieldNormEncoding::Compressed,
+        filter_only: false,
+        max_term_freq: None,
+        position_gap: None,
+        max_num_tokens: None,
+    }),
+    stored: false,
+};
+
+/// The field will be untokenized and indexed, after being trimmed and lowercased,
+/// so that exact matches are case insensitive.
+///
+/// See [`KeywordTokenizer`](../tokenizer/struct.KeywordTokenizer.html).
+pub const STRING_NORMALIZED: TextOptions = TextOptions {
+    indexing: Some(TextFieldIndexing {
+        tokenizer: Cow::Borrowed("keyword_normalized"),
+        record: IndexRecordOption::Basic,
+        fieldnorms: FieldNormEncoding::Compressed,
+        filter_only: false,
+        max_term_freq: None,
+        position_gap: None,
+        max_num_tokens: None,
     }),
     stored: false,
 };
@@ -108,6 +300,11 @@ pub const TEXT: TextOptions = TextOptions {
     indexing: Some(TextFieldIndexing {
         tokenizer: Cow::Borrowed("default"),
         record: IndexRecordOption::WithFreqsAndPositions,
+        fieldnorms: FieldNormEncoding::Compressed,
+        filter_only: false,
+        max_term_freq: None,
+        position_gap: None,
+        max_num_tokens: None,
     }),
     stored: false,
 };
@@ -169,9 +366,146 @@ mod tests {
                 if text_options.get_indexing_options().unwrap().tokenizer() == "default"));
     }
 
+    #[test]
+    fn test_set_fieldnorms() {
+        let fieldnorm_encoding = |text_options: TextOptions| {
+            text_options
+                .get_indexing_options()
+                .map(TextFieldIndexing::fieldnorm_encoding)
+        };
+        assert_eq!(
+            fieldnorm_encoding(TEXT.set_fieldnorms(false)),
+            Some(FieldNormEncoding::Disabled)
+        );
+        assert_eq!(
+            fieldnorm_encoding(STRING.set_fieldnorms(false).set_fieldnorms(true)),
+            Some(FieldNormEncoding::Compressed)
+        );
+        assert_eq!(fieldnorm_encoding(STORED.into()), None);
+        assert_eq!(
+            fieldnorm_encoding(TextOptions::from(STORED).set_fieldnorms(false)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_filter_only() {
+        let indexing = TextFieldIndexing::default()
+            .set_index_option(IndexRecordOption::WithFreqsAndPositions)
+            .set_filter_only();
+        assert!(indexing.is_filter_only());
+        assert_eq!(indexing.index_option(), IndexRecordOption::Basic);
+        assert_eq!(indexing.fieldnorm_encoding(), FieldNormEncoding::Disabled);
+        let json = serde_json::to_string(&indexing).unwrap();
+        assert!(json.contains("\"filter_only\":true"));
+        let indexing_deser: TextFieldIndexing = serde_json::from_str(&json).unwrap();
+        assert_eq!(indexing_deser, indexing);
+        assert!(!serde_json::to_string(&TextFieldIndexing::default())
+            .unwrap()
+            .contains("filter_only"));
+    }
+
+    #[test]
+    fn test_max_term_freq() {
+        let indexing = TextFieldIndexing::default().set_max_term_freq(255);
+        assert_eq!(indexing.max_term_freq(), Some(255));
+        let json = serde_json::to_string(&indexing).unwrap();
+        assert!(json.contains("\"max_term_freq\":255"));
+        let indexing_deser: TextFieldIndexing = serde_json::from_str(&json).unwrap();
+        assert_eq!(indexing_deser, indexing);
+        assert!(!serde_json::to_string(&TextFieldIndexing::default())
+            .unwrap()
+            .contains("max_term_freq"));
+    }
+
+    #[test]
+    fn test_max_num_tokens() -> crate::Result<()> {
+        use crate::Index;
+
+        let indexing = TextFieldIndexing::default()
+            .set_tokenizer("default")
+            .set_max_num_tokens(2);
+        assert_eq!(indexing.max_num_tokens(), Some(2));
+        let json = serde_json::to_string(&indexing).unwrap();
+        assert!(json.contains("\"max_num_tokens\":2"));
+        assert!(!serde_json::to_string(&TextFieldIndexing::default())
+            .unwrap()
+            .contains("max_num_tokens"));
+
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field(
+            "text",
+            TextOptions::default().set_indexing_options(indexing),
+        );
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        let mut doc = doc!(text => "one two three");
+        doc.add_text(text, "four five six");
+        index_writer.add_document(doc);
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        // The limit applies to each value.
+        for (word, doc_freq) in &[("two", 1), ("three", 0), ("five", 1), ("six", 0)] {
+            let term = Term::from_field_text(text, word);
+            assert_eq!(searcher.doc_freq(&term)?, *doc_freq);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_position_gap() {
+        assert_eq!(
+            TextFieldIndexing::default().position_gap(),
+            DEFAULT_POSITION_GAP
+        );
+        let indexing = TextFieldIndexing::default().set_position_gap(10);
+        assert_eq!(indexing.position_gap(), 10);
+        let json = serde_json::to_string(&indexing).unwrap();
+        assert!(json.contains("\"position_gap\":10"));
+        let indexing_deser: TextFieldIndexing = serde_json::from_str(&json).unwrap();
+        assert_eq!(indexing_deser, indexing);
+        assert!(!serde_json::to_string(&TextFieldIndexing::default())
+            .unwrap()
+            .contains("position_gap"));
+    }
+
+    #[test]
+    fn test_downgrade_index_record_option() {
+        assert_eq!(
+            IndexRecordOption::WithFreqsAndPositions.downgrade(IndexRecordOption::WithFreqs),
+            IndexRecordOption::WithFreqs
+        );
+        assert_eq!(
+            IndexRecordOption::Basic.downgrade(IndexRecordOption::WithFreqsAndPositions),
+            IndexRecordOption::Basic
+        );
+    }
+
     #[test]
     fn test_cmp_index_record_option() {
         assert!(IndexRecordOption::WithFreqsAndPositions > IndexRecordOption::WithFreqs);
         assert!(IndexRecordOption::WithFreqs > IndexRecordOption::Basic);
     }
+
+    #[test]
+    fn test_string_normalized() -> crate::Result<()> {
+        use crate::collector::Count;
+        use crate::query::QueryParser;
+        use crate::Index;
+
+        let mut schema_builder = Schema::builder();
+        let email = schema_builder.add_text_field("email", STRING_NORMALIZED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(email => " Paul@Example.com "));
+        index_writer.add_document(doc!(email => "paul@example.org"));
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let term = Term::from_field_text(email, "paul@example.com");
+        assert_eq!(searcher.doc_freq(&term)?, 1);
+        let query_parser = QueryParser::for_index(&index, vec![email]);
+        let query = query_parser.parse_query("\"PAUL@example.COM\"")?;
+        assert_eq!(searcher.search(&query, &Count)?, 1);
+        Ok(())
+    }
 }
@@ -28,16 +28,21 @@ pub enum ComponentSpaceUsage {
 }
 
 /// Represents combined space usage of an entire searcher and its component segments.
+///
+/// The space usage of the segments is the number of bytes of their files, which are
+/// typically mmapped. The caches, on the other hand, live on the heap.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SearcherSpaceUsage {
     segments: Vec<SegmentSpaceUsage>,
+    caches: CacheSpaceUsage,
     total: ByteCount,
 }
 
 impl SearcherSpaceUsage {
-    pub(crate) fn new() -> SearcherSpaceUsage {
+    pub(crate) fn new(caches: CacheSpaceUsage) -> SearcherSpaceUsage {
         SearcherSpaceUsage {
             segments: Vec::new(),
+            caches,
             total: 0,
         }
     }
@@ -54,11 +59,59 @@ impl SearcherSpaceUsage {
         &self.segments[..]
     }
 
+    /// Heap usage of the caches of the searcher
+    pub fn caches(&self) -> &CacheSpaceUsage {
+        &self.caches
+    }
+
     /// Returns total byte usage of this searcher, including all large subcomponents.
-    /// Does not account for smaller things like `meta.json`.
+    /// Does not account for smaller things like `meta.json`, nor for the caches.
     pub fn total(&self) -> ByteCount {
         self.total
     }
+
+    /// Estimate of the number of bytes resident in memory while searching.
+    ///
+    /// This is the sum of the resident estimates of the segments
+    /// and of the heap usage of the caches.
+    pub fn resident_estimate(&self) -> ByteCount {
+        self.segments
+            .iter()
+            .map(SegmentSpaceUsage::resident_estimate)
+            .sum::<ByteCount>()
+            + self.caches.total()
+    }
+}
+
+/// Represents the heap usage of the caches shared by the searchers of an `IndexReader`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CacheSpaceUsage {
+    doc_store: ByteCount,
+    global_ordinals: ByteCount,
+}
+
+impl CacheSpaceUsage {
+    pub(crate) fn new(doc_store: ByteCount, global_ordinals: ByteCount) -> CacheSpaceUsage {
+        CacheSpaceUsage {
+            doc_store,
+            global_ordinals,
+        }
+    }
+
+    /// Bytes of the decompressed blocks held by the doc store cache
+    pub fn doc_store(&self) -> ByteCount {
+        self.doc_store
+    }
+
+    /// Estimated bytes of the global ordinals built so far
+    pub fn global_ordinals(&self) -> ByteCount {
+        self.global_ordinals
+    }
+
+    /// Total heap usage of the caches
+    pub fn total(&self) -> ByteCount {
+        self.doc_store + self.global_ordinals
+    }
 }
 
 /// Represents combined space usage for all of the large components comprising a segment.
@@ -174,6 +227,16 @@ impl SegmentSpaceUsage {
     pub fn total(&self) -> ByteCount {
         self.total
     }
+
+    /// Estimate of the number of bytes of this segment resident in memory while searching.
+    ///
+    /// Fast fields, fieldnorms and deletes are accessed for most of the documents
+    /// matching a query, and are therefore expected to be entirely resident.
+    /// The term dictionary, postings, positions and store are only read partially,
+    /// and are left to the page cache.
+    pub fn resident_estimate(&self) -> ByteCount {
+        self.fast_fields.total() + self.fieldnorms.total() + self.deletes
+    }
 }
 
 /// Represents space usage for the Store for this segment.
@@ -229,6 +292,11 @@ impl PerFieldSpaceUsage {
         self.fields.iter()
     }
 
+    /// Space usage of a given field, if it is present in the represented file.
+    pub fn field(&self, field: Field) -> Option<&FieldUsage> {
+        self.fields.get(&field)
+    }
+
     /// Bytes used by the represented file
     pub fn total(&self) -> ByteCount {
         self.total
@@ -288,10 +356,10 @@ mod test {
     use crate::core::Index;
     use crate::schema::Field;
     use crate::schema::Schema;
-    use crate::schema::{FAST, INDEXED, STORED, TEXT};
+    use crate::schema::{Facet, FAST, INDEXED, STORED, TEXT};
     use crate::space_usage::ByteCount;
     use crate::space_usage::PerFieldSpaceUsage;
-    use crate::Term;
+    use crate::{DocAddress, Term};
 
     #[test]
     fn test_empty() {
@@ -477,4 +545,46 @@ mod test {
         assert!(segment_space_usage.deletes() > 0);
         Ok(())
     }
+
+    #[test]
+    fn test_caches() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let name = schema_builder.add_text_field("name", STORED);
+        let facet = schema_builder.add_facet_field("facet", INDEXED);
+        let count = schema_builder.add_u64_field("count", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_for_tests()?;
+            index_writer.add_document(doc!(
+                name => "hello",
+                facet => Facet::from("/a/b"),
+                count => 3u64,
+            ));
+            index_writer.commit()?;
+        }
+        let reader = index.reader()?;
+        let searcher = reader.searcher();
+        let space_usage = searcher.space_usage()?;
+        assert_eq!(space_usage.caches().total(), 0);
+        let segment_space_usage = &space_usage.segments()[0];
+        assert_eq!(
+            segment_space_usage.resident_estimate(),
+            segment_space_usage.fast_fields().total() + segment_space_usage.fieldnorms().total()
+        );
+        assert_eq!(
+            space_usage.resident_estimate(),
+            segment_space_usage.resident_estimate()
+        );
+
+        searcher.doc(DocAddress::new(0, 0))?;
+        searcher.global_ordinals(facet)?;
+        let space_usage = searcher.space_usage()?;
+        assert!(space_usage.caches().doc_store() > 0);
+        assert!(space_usage.caches().global_ordinals() > 0);
+        assert_eq!(
+            space_usage.resident_estimate(),
+            segment_space_usage.resident_estimate() + space_usage.caches().total()
+        );
+        Ok(())
+    }
 }
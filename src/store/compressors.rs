@@ -56,6 +56,22 @@ impl Compressor {
             Self::Snappy => 3,
         }
     }
+    /// Returns the feature flag required to use the compressor.
+    pub(crate) fn feature_flag(&self) -> &'static str {
+        match self {
+            Self::Lz4 => "lz4-compression",
+            Self::Brotli => "brotli-compression",
+            Self::Snappy => "snappy-compression",
+        }
+    }
+    /// Returns true if tantivy was compiled with the feature flag of the compressor.
+    pub(crate) fn is_enabled(&self) -> bool {
+        match self {
+            Self::Lz4 => cfg!(feature = "lz4-compression"),
+            Self::Brotli => cfg!(feature = "brotli-compression"),
+            Self::Snappy => cfg!(feature = "snappy-compression"),
+        }
+    }
     #[inline]
     pub(crate) fn compress(&self, uncompressed: &[u8], compressed: &mut Vec<u8>) -> io::Result<()> {
         match self {
@@ -0,0 +1,289 @@
+use super::Compressor;
+use crate::common::{BinarySerializable, VInt};
+use crate::schema::{Document, Field, FieldValue, Value};
+use serde::{Deserialize, Serialize};
+use std::io;
+
+/// DocSerialization can be used on `IndexSettings` to choose
+/// the format in which the stored fields of a document are serialized in the doc store.
+///
+/// The format is recorded in the footer of the doc store of each segment,
+/// so that changing it only affects the segments written afterwards.
+/// Merging segments rewrites their documents in the format of the index settings.
+///
+/// Note that there is no `bincode` format: the serde representation of a
+/// `Value` is self-describing, which `bincode` does not support.
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DocSerialization {
+    #[serde(rename = "default")]
+    /// The field values are serialized one after the other.
+    ///
+    /// This is the most compact format, but reading any field requires
+    /// decoding the whole document.
+    Default,
+    #[serde(rename = "indexed")]
+    /// The field values are preceded by a table of their fields and offsets.
+    ///
+    /// Reading a subset of the fields of a document with
+    /// [`StoreReader::get_fields`](./struct.StoreReader.html#method.get_fields)
+    /// only decodes these fields, at the cost of 4 extra bytes per value.
+    Indexed,
+}
+
+impl Default for DocSerialization {
+    fn default() -> Self {
+        DocSerialization::Default
+    }
+}
+
+// Size of an entry of the table of an `Indexed` document:
+// the field (4 bytes) and the end offset of its value (4 bytes).
+const INDEXED_ENTRY_NUM_BYTES: usize = 8;
+
+// Type code of the values compressed on their own. It is not a type code of `Value`.
+const COMPRESSED_VALUE_CODE: u8 = 255;
+
+/// Compression of the stored values larger than a threshold, on their own.
+///
+/// See `IndexSettings::docstore_large_value_threshold`.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct LargeValueCompression {
+    /// Values whose serialized size exceeds `threshold` bytes are compressed.
+    pub threshold: usize,
+    /// Compressor used to compress the values.
+    pub compressor: Compressor,
+}
+
+/// Appends the serialized `value` to `buffer`, compressed if it is larger than the
+/// threshold of `large_value_compression`.
+///
+/// A compressed value is serialized as `COMPRESSED_VALUE_CODE`, the id of its
+/// compressor, and the length prefixed compressed bytes of the serialized value.
+/// It is only kept if compression actually saves space.
+fn serialize_value(
+    value: &Value,
+    large_value_compression: Option<LargeValueCompression>,
+    buffer: &mut Vec<u8>,
+) -> io::Result<()> {
+    let start = buffer.len();
+    value.serialize(buffer)?;
+    let value_num_bytes = buffer.len() - start;
+    if let Some(large_value_compression) = large_value_compression {
+        if value_num_bytes > large_value_compression.threshold {
+            let mut compressed = Vec::new();
+            large_value_compression
+                .compressor
+                .compress(&buffer[start..], &mut compressed)?;
+            if compressed.len() < value_num_bytes {
+                buffer.truncate(start);
+                COMPRESSED_VALUE_CODE.serialize(buffer)?;
+                large_value_compression
+                    .compressor
+                    .get_id()
+                    .serialize(buffer)?;
+                VInt(compressed.len() as u64).serialize(buffer)?;
+                buffer.extend_from_slice(&compressed);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Deserializes a value serialized by `serialize_value`, decompressing it if needed.
+fn deserialize_value(reader: &mut &[u8]) -> io::Result<Value> {
+    if reader.first() != Some(&COMPRESSED_VALUE_CODE) {
+        return Value::deserialize(reader);
+    }
+    *reader = &reader[1..];
+    let compressor = Compressor::from_id(u8::deserialize(reader)?);
+    let compressed_num_bytes = VInt::deserialize(reader)?.val() as usize;
+    if reader.len() < compressed_num_bytes {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "doc store compressed value is truncated",
+        ));
+    }
+    let (compressed, rest) = reader.split_at(compressed_num_bytes);
+    *reader = rest;
+    let mut decompressed = Vec::new();
+    compressor.decompress(compressed, &mut decompressed)?;
+    Value::deserialize(&mut &decompressed[..])
+}
+
+impl DocSerialization {
+    pub(crate) fn from_id(id: u8) -> io::Result<DocSerialization> {
+        match id {
+            0 => Ok(DocSerialization::Default),
+            1 => Ok(DocSerialization::Indexed),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unknown doc serialization id {:?}", id),
+            )),
+        }
+    }
+
+    pub(crate) fn get_id(&self) -> u8 {
+        match self {
+            DocSerialization::Default => 0,
+            DocSerialization::Indexed => 1,
+        }
+    }
+
+    /// Appends the serialized `doc` to `buffer`.
+    ///
+    /// If `large_value_compression` is set, the values larger than its threshold
+    /// are compressed on their own.
+    pub(crate) fn serialize(
+        &self,
+        doc: &Document,
+        large_value_compression: Option<LargeValueCompression>,
+        buffer: &mut Vec<u8>,
+    ) -> io::Result<()> {
+        let field_values = doc.field_values();
+        match self {
+            DocSerialization::Default => {
+                VInt(field_values.len() as u64).serialize(buffer)?;
+                for field_value in field_values {
+                    field_value.field().serialize(buffer)?;
+                    serialize_value(field_value.value(), large_value_compression, buffer)?;
+                }
+                Ok(())
+            }
+            DocSerialization::Indexed => {
+                VInt(field_values.len() as u64).serialize(buffer)?;
+                let table_start = buffer.len();
+                buffer.resize(
+                    table_start + field_values.len() * INDEXED_ENTRY_NUM_BYTES,
+                    0u8,
+                );
+                let values_start = buffer.len();
+                for (i, field_value) in field_values.iter().enumerate() {
+                    serialize_value(field_value.value(), large_value_compression, buffer)?;
+                    let end_offset = (buffer.len() - values_start) as u32;
+                    let entry_start = table_start + i * INDEXED_ENTRY_NUM_BYTES;
+                    let mut entry = &mut buffer[entry_start..entry_start + INDEXED_ENTRY_NUM_BYTES];
+                    field_value.field().serialize(&mut entry)?;
+                    end_offset.serialize(&mut entry)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Deserializes a whole document.
+    pub(crate) fn deserialize(&self, doc_bytes: &[u8]) -> io::Result<Document> {
+        self.deserialize_fields(doc_bytes, |_| true)
+    }
+
+    /// Deserializes the values of the fields for which `accept` returns true.
+    ///
+    /// With the `Indexed` format, the other values are skipped without being decoded.
+    pub(crate) fn deserialize_fields<F: Fn(Field) -> bool>(
+        &self,
+        mut doc_bytes: &[u8],
+        accept: F,
+    ) -> io::Result<Document> {
+        match self {
+            DocSerialization::Default => {
+                let num_values = VInt::deserialize(&mut doc_bytes)?.val() as usize;
+                let mut field_values = Vec::with_capacity(num_values);
+                for _ in 0..num_values {
+                    let field = Field::deserialize(&mut doc_bytes)?;
+                    let value = deserialize_value(&mut doc_bytes)?;
+                    if accept(field) {
+                        field_values.push(FieldValue::new(field, value));
+                    }
+                }
+                Ok(Document::from(field_values))
+            }
+            DocSerialization::Indexed => {
+                let num_values = VInt::deserialize(&mut doc_bytes)?.val() as usize;
+                let table_num_bytes = num_values * INDEXED_ENTRY_NUM_BYTES;
+                if doc_bytes.len() < table_num_bytes {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "doc store document is truncated",
+                    ));
+                }
+                let (mut table, values) = doc_bytes.split_at(table_num_bytes);
+                let mut field_values = Vec::new();
+                let mut start_offset = 0usize;
+                for _ in 0..num_values {
+                    let field = Field::deserialize(&mut table)?;
+                    let end_offset = u32::deserialize(&mut table)? as usize;
+                    if accept(field) {
+                        let mut value_bytes =
+                            values.get(start_offset..end_offset).ok_or_else(|| {
+                                io::Error::new(
+                                    io::ErrorKind::InvalidData,
+                                    "invalid value offsets in doc store document",
+                                )
+                            })?;
+                        let value = deserialize_value(&mut value_bytes)?;
+                        field_values.push(FieldValue::new(field, value));
+                    }
+                    start_offset = end_offset;
+                }
+                Ok(Document::from(field_values))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DocSerialization, LargeValueCompression};
+    use crate::schema::{Document, Schema, FAST, STORED, TEXT};
+    use crate::store::Compressor;
+
+    #[test]
+    fn test_doc_serialization() -> std::io::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT | STORED);
+        let body = schema_builder.add_text_field("body", TEXT | STORED);
+        let count = schema_builder.add_u64_field("count", FAST | STORED);
+        let doc = doc!(title => "hello", body => "a long body", count => 3u64, title => "world");
+        for &doc_serialization in &[DocSerialization::Default, DocSerialization::Indexed] {
+            let mut buffer = Vec::new();
+            doc_serialization.serialize(&doc, None, &mut buffer)?;
+            assert_eq!(doc_serialization.deserialize(&buffer)?, doc);
+            let partial_doc: Document = doc_serialization
+                .deserialize_fields(&buffer, |field| field == title || field == count)?;
+            assert_eq!(
+                partial_doc,
+                doc!(title => "hello", count => 3u64, title => "world")
+            );
+            assert_eq!(
+                DocSerialization::from_id(doc_serialization.get_id())?,
+                doc_serialization
+            );
+        }
+        assert!(DocSerialization::from_id(2).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_doc_serialization_large_value_compression() -> std::io::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT | STORED);
+        let body = schema_builder.add_text_field("body", TEXT | STORED);
+        let large_body = "lorem ipsum ".repeat(1_000);
+        let doc = doc!(title => "hello", body => large_body.as_str(), title => "world");
+        let large_value_compression = LargeValueCompression {
+            threshold: 100,
+            compressor: Compressor::default(),
+        };
+        for &doc_serialization in &[DocSerialization::Default, DocSerialization::Indexed] {
+            let mut uncompressed_buffer = Vec::new();
+            doc_serialization.serialize(&doc, None, &mut uncompressed_buffer)?;
+            let mut buffer = Vec::new();
+            doc_serialization.serialize(&doc, Some(large_value_compression), &mut buffer)?;
+            assert!(buffer.len() < uncompressed_buffer.len() / 10);
+            assert_eq!(doc_serialization.deserialize(&buffer)?, doc);
+            let partial_doc: Document =
+                doc_serialization.deserialize_fields(&buffer, |field| field == title)?;
+            assert_eq!(partial_doc, doc!(title => "hello", title => "world"));
+        }
+        Ok(())
+    }
+}
@@ -1,7 +1,7 @@
 use crate::{
     common::{BinarySerializable, FixedSize, HasLen},
     directory::FileSlice,
-    store::Compressor,
+    store::{Compressor, DocSerialization},
 };
 use std::io;
 
@@ -9,28 +9,33 @@ use std::io;
 pub struct DocStoreFooter {
     pub offset: u64,
     pub compressor: Compressor,
+    pub doc_serialization: DocSerialization,
 }
 
 /// Serialises the footer to a byte-array
 /// - offset : 8 bytes
 ///-  compressor id: 1 byte
-/// - reserved for future use: 15 bytes
+/// - doc serialization id: 1 byte (0 in the stores written before it was introduced)
+/// - reserved for future use: 14 bytes
 impl BinarySerializable for DocStoreFooter {
     fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
         BinarySerializable::serialize(&self.offset, writer)?;
         BinarySerializable::serialize(&self.compressor.get_id(), writer)?;
-        writer.write_all(&[0; 15])?;
+        BinarySerializable::serialize(&self.doc_serialization.get_id(), writer)?;
+        writer.write_all(&[0; 14])?;
         Ok(())
     }
 
     fn deserialize<R: io::Read>(reader: &mut R) -> io::Result<Self> {
         let offset = u64::deserialize(reader)?;
         let compressor_id = u8::deserialize(reader)?;
-        let mut skip_buf = [0; 15];
+        let doc_serialization_id = u8::deserialize(reader)?;
+        let mut skip_buf = [0; 14];
         reader.read_exact(&mut skip_buf)?;
         Ok(DocStoreFooter {
             offset,
             compressor: Compressor::from_id(compressor_id),
+            doc_serialization: DocSerialization::from_id(doc_serialization_id)?,
         })
     }
 }
@@ -40,8 +45,12 @@ impl FixedSize for DocStoreFooter {
 }
 
 impl DocStoreFooter {
-    pub fn new(offset: u64, compressor: Compressor) -> Self {
-        DocStoreFooter { offset, compressor }
+    pub fn new(offset: u64, compressor: Compressor, doc_serialization: DocSerialization) -> Self {
+        DocStoreFooter {
+            offset,
+            compressor,
+            doc_serialization,
+        }
     }
 
     pub fn extract_footer(file: FileSlice) -> io::Result<(DocStoreFooter, FileSlice)> {
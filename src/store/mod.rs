@@ -4,18 +4,22 @@ Compressed/slow/row-oriented storage for documents.
 A field needs to be marked as stored in the schema in
 order to be handled in the `Store`.
 
-Internally, documents (or rather their stored fields) are serialized to a buffer.
+Internally, documents (or rather their stored fields) are serialized to a buffer,
+in the [`DocSerialization`](./enum.DocSerialization.html) format of the index settings.
 When the buffer exceeds 16K, the buffer is compressed using `brotli`, `LZ4` or `snappy`
 and the resulting block is written to disk.
+Values larger than `IndexSettings::docstore_large_value_threshold` are additionally
+compressed on their own, so that reading their neighbors does not require decompressing them.
 
 One can then request for a specific `DocId`.
 A skip list helps navigating to the right block,
 decompresses it entirely and returns the document within it.
 
-If the last document requested was in the same block,
-the reader is smart enough to avoid decompressing
-the block a second time, but their is no real
-*uncompressed block* cache.
+Decompressed blocks are kept in an LRU cache, so that
+documents from a recently read block can be fetched without decompressing
+the block a second time. The searchers of an `IndexReader` share a single
+[`DocStoreCache`](./struct.DocStoreCache.html) across all segments, whose
+capacity is set by `IndexReaderBuilder::doc_store_cache_num_blocks`.
 
 A typical use case for the store is, once
 the search result page has been computed, returning
@@ -34,12 +38,14 @@ and should rely on either
 !*/
 
 mod compressors;
+mod doc_serialization;
 mod footer;
 mod index;
 mod reader;
 mod writer;
 pub use self::compressors::Compressor;
-pub use self::reader::StoreReader;
+pub use self::doc_serialization::DocSerialization;
+pub use self::reader::{DocStoreCache, DocStoreCacheInfo, StoreReader};
 pub use self::writer::StoreWriter;
 
 #[cfg(feature = "lz4-compression")]
@@ -340,6 +346,173 @@ pub mod tests {
         assert_eq!(store.block_checkpoints().count(), 1);
         Ok(())
     }
+
+    #[test]
+    fn test_store_preserves_value_order_across_merges() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let num_field = schema_builder.add_u64_field("num", STORED);
+        let date_field = schema_builder.add_date_field("date", STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let date = |timestamp: i64| {
+            crate::DateTime::from_utc(
+                chrono::NaiveDateTime::from_timestamp(timestamp, 0),
+                chrono::Utc,
+            )
+        };
+        let docs = vec![
+            doc!(
+                text_field => "b",
+                num_field => 3u64,
+                date_field => date(20),
+                text_field => "a",
+                num_field => 1u64,
+                num_field => 3u64,
+                date_field => date(10),
+                date_field => date(20)
+            ),
+            doc!(
+                num_field => 2u64,
+                text_field => "c",
+                num_field => 2u64,
+                num_field => 0u64
+            ),
+        ];
+        let mut index_writer = index.writer_for_tests()?;
+        for doc in &docs {
+            index_writer.add_document(doc.clone());
+            index_writer.commit()?;
+        }
+        let segment_ids = index.searchable_segment_ids()?;
+        block_on(index_writer.merge(&segment_ids))?;
+        index_writer.wait_merging_threads()?;
+
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let store = searcher.segment_reader(0).get_store_reader()?;
+        let retrieved_docs = store.iter(None).collect::<crate::Result<Vec<Document>>>()?;
+        assert_eq!(retrieved_docs.len(), docs.len());
+        // The order of the segments in the merged segment is not specified.
+        let find_retrieved_doc = |doc: &Document| {
+            retrieved_docs
+                .iter()
+                .find(|retrieved_doc| {
+                    retrieved_doc.get_first(text_field) == doc.get_first(text_field)
+                })
+                .unwrap()
+        };
+        for doc in &docs {
+            assert_eq!(find_retrieved_doc(doc).field_values(), doc.field_values());
+        }
+        let retrieved_doc = find_retrieved_doc(&docs[0]);
+        assert_eq!(
+            retrieved_doc.get_all_u64s(num_field).collect::<Vec<_>>(),
+            vec![3, 1, 3]
+        );
+        assert_eq!(
+            retrieved_doc.get_all_dates(date_field).collect::<Vec<_>>(),
+            vec![&date(20), &date(10), &date(20)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_large_value_compression() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let title_field = schema_builder.add_text_field("title", TEXT | STORED);
+        let body_field = schema_builder.add_text_field("body", STORED);
+        let mut index = Index::create_in_ram(schema_builder.build());
+        index.settings_mut().docstore_large_value_threshold = Some(1_000);
+        let large_body = LOREM.repeat(100);
+        {
+            let mut index_writer = index.writer_for_tests()?;
+            for i in 0..10 {
+                index_writer.add_document(doc!(
+                    title_field => format!("title {}", i),
+                    body_field => if i == 5 { large_body.as_str() } else { "small" }
+                ));
+            }
+            index_writer.commit()?;
+            // Merging copies the documents with their compressed values.
+            index_writer.add_document(doc!(title_field => "title 10", body_field => "small"));
+            index_writer.commit()?;
+            let segment_ids = index.searchable_segment_ids()?;
+            block_on(index_writer.merge(&segment_ids))?;
+            index_writer.wait_merging_threads()?;
+        }
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let store = searcher.segment_reader(0).get_store_reader()?;
+        let docs = store.iter(None).collect::<crate::Result<Vec<Document>>>()?;
+        assert_eq!(docs.len(), 11);
+        for (doc_id, doc) in docs.iter().enumerate() {
+            let body = doc.get_first(body_field).unwrap().text().unwrap();
+            if doc.get_first(title_field).unwrap().text() == Some("title 5") {
+                assert_eq!(body, large_body);
+                let doc_bytes = store.get_document_bytes(doc_id as crate::DocId)?;
+                assert!(doc_bytes.len() < large_body.len() / 10);
+            } else {
+                assert_eq!(body, "small");
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_with_changed_doc_serialization() -> crate::Result<()> {
+        let mut schema_builder = schema::Schema::builder();
+        let title_field = schema_builder.add_text_field("title", TEXT | STORED);
+        let body_field = schema_builder.add_text_field("body", TEXT | STORED);
+        let mut index = Index::create_in_ram(schema_builder.build());
+        index.settings_mut().docstore_serialization = DocSerialization::Indexed;
+        {
+            let mut index_writer = index.writer_for_tests()?;
+            for i in 0..200 {
+                index_writer.add_document(doc!(
+                    title_field => format!("title {}", i),
+                    body_field => LOREM
+                ));
+            }
+            index_writer.commit()?;
+        }
+        let searcher = index.reader()?.searcher();
+        let store = searcher.segment_reader(0).get_store_reader()?;
+        assert_eq!(store.doc_serialization(), DocSerialization::Indexed);
+        assert_eq!(
+            store.get_fields(3, &[title_field])?,
+            doc!(title_field => "title 3")
+        );
+        assert_eq!(
+            store.get(3)?,
+            doc!(title_field => "title 3", body_field => LOREM)
+        );
+
+        // Documents are converted to the new format upon merge.
+        index.settings_mut().docstore_serialization = DocSerialization::Default;
+        {
+            let mut index_writer = index.writer_for_tests()?;
+            index_writer.add_document(doc!(title_field => "title 200", body_field => LOREM));
+            index_writer.commit()?;
+            let segment_ids = index.searchable_segment_ids()?;
+            block_on(index_writer.merge(&segment_ids))?;
+            index_writer.wait_merging_threads()?;
+        }
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let store = searcher.segment_reader(0).get_store_reader()?;
+        assert_eq!(store.doc_serialization(), DocSerialization::Default);
+        let docs = store.iter(None).collect::<crate::Result<Vec<Document>>>()?;
+        assert_eq!(docs.len(), 201);
+        for doc in docs {
+            assert_eq!(doc.get_first(body_field).unwrap().text(), Some(LOREM));
+        }
+        let doc_address = crate::DocAddress::new(0, 0);
+        assert_eq!(
+            searcher.doc_fields(doc_address, &[body_field])?,
+            doc!(body_field => LOREM)
+        );
+        Ok(())
+    }
 }
 
 #[cfg(all(test, feature = "unstable"))]
@@ -1,8 +1,10 @@
-use super::Compressor;
 use super::{footer::DocStoreFooter, index::SkipIndex};
+use super::{Compressor, DocSerialization};
+use crate::core::SegmentId;
 use crate::directory::{FileSlice, OwnedBytes};
-use crate::schema::Document;
-use crate::space_usage::StoreSpaceUsage;
+use crate::metrics::{Metrics, NoMetrics};
+use crate::schema::{Document, Field};
+use crate::space_usage::{ByteCount, StoreSpaceUsage};
 use crate::store::index::Checkpoint;
 use crate::DocId;
 use crate::{
@@ -11,6 +13,7 @@ use crate::{
     fastfield::DeleteBitSet,
 };
 use lru::LruCache;
+use serde::{Deserialize, Serialize};
 use std::io;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
@@ -19,22 +22,128 @@ const LRU_CACHE_CAPACITY: usize = 100;
 
 type Block = OwnedBytes;
 
-type BlockCache = Arc<Mutex<LruCache<usize, Block>>>;
+// Blocks are identified by the segment of their store (if the cache is shared
+// across segments) and their offset in the store.
+type BlockCacheKey = (Option<SegmentId>, usize);
+
+/// Hit/miss statistics of a `DocStoreCache`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DocStoreCacheInfo {
+    /// Number of blocks that were found in the cache.
+    pub hit: usize,
+    /// Number of blocks that had to be read and decompressed.
+    pub miss: usize,
+    /// Number of blocks currently in the cache.
+    pub num_blocks: usize,
+    /// Maximum number of blocks in the cache.
+    pub capacity: usize,
+}
+
+/// LRU cache of decompressed doc store blocks.
+///
+/// A `DocStoreCache` can be shared across the store readers of different segments,
+/// in which case the capacity bounds the number of blocks held for all of them.
+/// Cloning a `DocStoreCache` returns a handle to the same cache.
+///
+/// A capacity of 0 disables caching.
+#[derive(Clone)]
+pub struct DocStoreCache {
+    cache: Arc<Mutex<LruCache<BlockCacheKey, Block>>>,
+    capacity: usize,
+    hits: Arc<AtomicUsize>,
+    misses: Arc<AtomicUsize>,
+    metrics: Arc<dyn Metrics>,
+}
+
+impl DocStoreCache {
+    /// Creates a cache holding up to `capacity` decompressed blocks.
+    ///
+    /// Blocks are 16KB large before compression.
+    pub fn with_capacity(capacity: usize) -> DocStoreCache {
+        DocStoreCache {
+            cache: Arc::new(Mutex::new(LruCache::new(capacity))),
+            capacity,
+            hits: Default::default(),
+            misses: Default::default(),
+            metrics: Arc::new(NoMetrics),
+        }
+    }
+
+    /// Reports the lookups in the cache to `metrics`.
+    pub(crate) fn with_metrics(mut self, metrics: Arc<dyn Metrics>) -> DocStoreCache {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Returns the hit/miss statistics of the cache.
+    pub fn info(&self) -> DocStoreCacheInfo {
+        DocStoreCacheInfo {
+            hit: self.hits.load(Ordering::SeqCst),
+            miss: self.misses.load(Ordering::SeqCst),
+            num_blocks: self.cache.lock().unwrap().len(),
+            capacity: self.capacity,
+        }
+    }
+
+    /// Returns the number of bytes of the blocks currently in the cache.
+    pub(crate) fn num_bytes(&self) -> ByteCount {
+        self.cache
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(_, block)| block.len())
+            .sum()
+    }
+
+    fn get(&self, key: &BlockCacheKey) -> Option<Block> {
+        let block_opt = self.cache.lock().unwrap().get(key).cloned();
+        if block_opt.is_some() {
+            self.hits.fetch_add(1, Ordering::SeqCst);
+        } else {
+            self.misses.fetch_add(1, Ordering::SeqCst);
+        }
+        self.metrics
+            .record_doc_store_cache_lookup(block_opt.is_some());
+        block_opt
+    }
+
+    fn put(&self, key: BlockCacheKey, block: Block) {
+        if self.capacity > 0 {
+            self.cache.lock().unwrap().put(key, block);
+        }
+    }
+}
 
 /// Reads document off tantivy's [`Store`](./index.html)
 pub struct StoreReader {
     compressor: Compressor,
+    doc_serialization: DocSerialization,
     data: FileSlice,
-    cache: BlockCache,
-    cache_hits: Arc<AtomicUsize>,
-    cache_misses: Arc<AtomicUsize>,
+    cache: DocStoreCache,
+    segment_id: Option<SegmentId>,
     skip_index: Arc<SkipIndex>,
     space_usage: StoreSpaceUsage,
 }
 
 impl StoreReader {
-    /// Opens a store reader
+    /// Opens a store reader, with its own block cache.
     pub fn open(store_file: FileSlice) -> io::Result<StoreReader> {
+        StoreReader::open_with_cache(
+            store_file,
+            DocStoreCache::with_capacity(LRU_CACHE_CAPACITY),
+            None,
+        )
+    }
+
+    /// Opens a store reader using a block cache that may be shared with the
+    /// store readers of other segments.
+    ///
+    /// `segment_id` is required to be set if the cache is shared.
+    pub(crate) fn open_with_cache(
+        store_file: FileSlice,
+        cache: DocStoreCache,
+        segment_id: Option<SegmentId>,
+    ) -> io::Result<StoreReader> {
         let (footer, data_and_offset) = DocStoreFooter::extract_footer(store_file)?;
 
         let (data_file, offset_index_file) = data_and_offset.split(footer.offset as usize);
@@ -43,10 +152,10 @@ impl StoreReader {
         let skip_index = SkipIndex::open(index_data);
         Ok(StoreReader {
             compressor: footer.compressor,
+            doc_serialization: footer.doc_serialization,
             data: data_file,
-            cache: Arc::new(Mutex::new(LruCache::new(LRU_CACHE_CAPACITY))),
-            cache_hits: Default::default(),
-            cache_misses: Default::default(),
+            cache,
+            segment_id,
             skip_index: Arc::new(skip_index),
             space_usage,
         })
@@ -60,6 +169,11 @@ impl StoreReader {
         self.compressor
     }
 
+    /// Returns the format in which the documents of the store are serialized.
+    pub fn doc_serialization(&self) -> DocSerialization {
+        self.doc_serialization
+    }
+
     fn block_checkpoint(&self, doc_id: DocId) -> Option<Checkpoint> {
         self.skip_index.seek(doc_id)
     }
@@ -72,24 +186,24 @@ impl StoreReader {
         self.data.slice(checkpoint.byte_range.clone()).read_bytes()
     }
 
+    /// Returns the block cache of the store reader.
+    pub fn cache(&self) -> &DocStoreCache {
+        &self.cache
+    }
+
     fn read_block(&self, checkpoint: &Checkpoint) -> io::Result<Block> {
-        if let Some(block) = self.cache.lock().unwrap().get(&checkpoint.byte_range.start) {
-            self.cache_hits.fetch_add(1, Ordering::SeqCst);
-            return Ok(block.clone());
+        let cache_key = (self.segment_id, checkpoint.byte_range.start);
+        if let Some(block) = self.cache.get(&cache_key) {
+            return Ok(block);
         }
 
-        self.cache_misses.fetch_add(1, Ordering::SeqCst);
-
         let compressed_block = self.compressed_block(checkpoint)?;
         let mut decompressed_block = vec![];
         self.compressor
             .decompress(compressed_block.as_slice(), &mut decompressed_block)?;
 
         let block = OwnedBytes::new(decompressed_block);
-        self.cache
-            .lock()
-            .unwrap()
-            .put(checkpoint.byte_range.start, block.clone());
+        self.cache.put(cache_key, block.clone());
 
         Ok(block)
     }
@@ -104,8 +218,20 @@ impl StoreReader {
     /// It should not be called to score documents
     /// for instance.
     pub fn get(&self, doc_id: DocId) -> crate::Result<Document> {
-        let mut doc_bytes = self.get_document_bytes(doc_id)?;
-        Ok(Document::deserialize(&mut doc_bytes)?)
+        let doc_bytes = self.get_document_bytes(doc_id)?;
+        Ok(self.doc_serialization.deserialize(doc_bytes.as_slice())?)
+    }
+
+    /// Reads the values of the given `fields` of a document.
+    ///
+    /// If the store was written with `DocSerialization::Indexed`, the values of
+    /// the other fields are not decoded, which makes it cheaper than `.get(doc)`
+    /// for large documents.
+    pub fn get_fields(&self, doc_id: DocId, fields: &[Field]) -> crate::Result<Document> {
+        let doc_bytes = self.get_document_bytes(doc_id)?;
+        Ok(self
+            .doc_serialization
+            .deserialize_fields(doc_bytes.as_slice(), |field| fields.contains(&field))?)
     }
 
     /// Reads raw bytes of a given document. Returns `RawDocument`, which contains the block of a document and its start and end
@@ -141,9 +267,9 @@ impl StoreReader {
         &'b self,
         delete_bitset: Option<&'a DeleteBitSet>,
     ) -> impl Iterator<Item = crate::Result<Document>> + 'b {
-        self.iter_raw(delete_bitset).map(|doc_bytes_res| {
-            let mut doc_bytes = doc_bytes_res?;
-            Ok(Document::deserialize(&mut doc_bytes)?)
+        self.iter_raw(delete_bitset).map(move |doc_bytes_res| {
+            let doc_bytes = doc_bytes_res?;
+            Ok(self.doc_serialization.deserialize(doc_bytes.as_slice())?)
         })
     }
 
@@ -260,56 +386,59 @@ mod tests {
         let store_file = directory.open_read(path)?;
         let store = StoreReader::open(store_file)?;
 
-        assert_eq!(store.cache.lock().unwrap().len(), 0);
-        assert_eq!(store.cache_hits.load(Ordering::SeqCst), 0);
-        assert_eq!(store.cache_misses.load(Ordering::SeqCst), 0);
+        assert_eq!(store.cache.info().num_blocks, 0);
+        assert_eq!(store.cache.info().hit, 0);
+        assert_eq!(store.cache.info().miss, 0);
 
         let doc = store.get(0)?;
         assert_eq!(get_text_field(&doc, &title), Some("Doc 0"));
 
-        assert_eq!(store.cache.lock().unwrap().len(), 1);
-        assert_eq!(store.cache_hits.load(Ordering::SeqCst), 0);
-        assert_eq!(store.cache_misses.load(Ordering::SeqCst), 1);
+        assert_eq!(store.cache.info().num_blocks, 1);
+        assert_eq!(store.cache.info().hit, 0);
+        assert_eq!(store.cache.info().miss, 1);
         assert_eq!(
             store
+                .cache
                 .cache
                 .lock()
                 .unwrap()
                 .peek_lru()
-                .map(|(&k, _)| k as usize),
+                .map(|(&(_, k), _)| k),
             Some(0)
         );
 
         let doc = store.get(499)?;
         assert_eq!(get_text_field(&doc, &title), Some("Doc 499"));
 
-        assert_eq!(store.cache.lock().unwrap().len(), 2);
-        assert_eq!(store.cache_hits.load(Ordering::SeqCst), 0);
-        assert_eq!(store.cache_misses.load(Ordering::SeqCst), 2);
+        assert_eq!(store.cache.info().num_blocks, 2);
+        assert_eq!(store.cache.info().hit, 0);
+        assert_eq!(store.cache.info().miss, 2);
 
         assert_eq!(
             store
+                .cache
                 .cache
                 .lock()
                 .unwrap()
                 .peek_lru()
-                .map(|(&k, _)| k as usize),
+                .map(|(&(_, k), _)| k),
             Some(0)
         );
 
         let doc = store.get(0)?;
         assert_eq!(get_text_field(&doc, &title), Some("Doc 0"));
 
-        assert_eq!(store.cache.lock().unwrap().len(), 2);
-        assert_eq!(store.cache_hits.load(Ordering::SeqCst), 1);
-        assert_eq!(store.cache_misses.load(Ordering::SeqCst), 2);
+        assert_eq!(store.cache.info().num_blocks, 2);
+        assert_eq!(store.cache.info().hit, 1);
+        assert_eq!(store.cache.info().miss, 2);
         assert_eq!(
             store
+                .cache
                 .cache
                 .lock()
                 .unwrap()
                 .peek_lru()
-                .map(|(&k, _)| k as usize),
+                .map(|(&(_, k), _)| k),
             Some(9210)
         );
 
@@ -1,6 +1,7 @@
+use super::doc_serialization::LargeValueCompression;
 use super::index::SkipIndexBuilder;
 use super::StoreReader;
-use super::{compressors::Compressor, footer::DocStoreFooter};
+use super::{compressors::Compressor, footer::DocStoreFooter, DocSerialization};
 use crate::common::CountingWriter;
 use crate::common::{BinarySerializable, VInt};
 use crate::directory::TerminatingWrite;
@@ -22,6 +23,8 @@ const BLOCK_SIZE: usize = 16_384;
 ///
 pub struct StoreWriter {
     compressor: Compressor,
+    doc_serialization: DocSerialization,
+    large_value_threshold: Option<usize>,
     doc: DocId,
     first_doc_in_block: DocId,
     offset_index_writer: SkipIndexBuilder,
@@ -38,6 +41,8 @@ impl StoreWriter {
     pub fn new(writer: WritePtr, compressor: Compressor) -> StoreWriter {
         StoreWriter {
             compressor,
+            doc_serialization: DocSerialization::default(),
+            large_value_threshold: None,
             doc: 0,
             first_doc_in_block: 0,
             offset_index_writer: SkipIndexBuilder::new(),
@@ -47,10 +52,43 @@ impl StoreWriter {
         }
     }
 
+    /// Sets the format in which the documents are serialized.
+    ///
+    /// It should be set before any document is added.
+    pub fn with_doc_serialization(mut self, doc_serialization: DocSerialization) -> StoreWriter {
+        self.doc_serialization = doc_serialization;
+        self
+    }
+
+    /// Sets the size, in bytes, above which a stored value is compressed on its own,
+    /// in addition to the compression of its block.
+    ///
+    /// Reading the other documents of the block then does not require decompressing it.
+    pub fn with_large_value_threshold(
+        mut self,
+        large_value_threshold: Option<usize>,
+    ) -> StoreWriter {
+        self.large_value_threshold = large_value_threshold;
+        self
+    }
+
     pub(crate) fn compressor(&self) -> Compressor {
         self.compressor
     }
 
+    pub(crate) fn doc_serialization(&self) -> DocSerialization {
+        self.doc_serialization
+    }
+
+    fn large_value_compression(&self) -> Option<LargeValueCompression> {
+        let compressor = self.compressor;
+        self.large_value_threshold
+            .map(|threshold| LargeValueCompression {
+                threshold,
+                compressor,
+            })
+    }
+
     /// The memory used (inclusive childs)
     pub fn mem_usage(&self) -> usize {
         self.intermediary_buffer.capacity() + self.current_block.capacity()
@@ -58,6 +96,9 @@ impl StoreWriter {
 
     /// Store bytes of a serialized document.
     ///
+    /// The document is expected to be serialized in the
+    /// `DocSerialization` of the store writer.
+    ///
     /// The document id is implicitely the current number
     /// of documents.
     ///
@@ -79,7 +120,12 @@ impl StoreWriter {
     ///
     pub fn store(&mut self, stored_document: &Document) -> io::Result<()> {
         self.intermediary_buffer.clear();
-        stored_document.serialize(&mut self.intermediary_buffer)?;
+        let large_value_compression = self.large_value_compression();
+        self.doc_serialization.serialize(
+            stored_document,
+            large_value_compression,
+            &mut self.intermediary_buffer,
+        )?;
         // calling store bytes would be preferable for code reuse, but then we can't use
         // intermediary_buffer due to the borrow checker
         // a new buffer costs ~1% indexing performance
@@ -154,7 +200,7 @@ impl StoreWriter {
             self.write_and_compress_block()?;
         }
         let header_offset: u64 = self.writer.written_bytes() as u64;
-        let footer = DocStoreFooter::new(header_offset, self.compressor);
+        let footer = DocStoreFooter::new(header_offset, self.compressor, self.doc_serialization);
         self.offset_index_writer.write(&mut self.writer)?;
         footer.serialize(&mut self.writer)?;
         self.writer.terminate()
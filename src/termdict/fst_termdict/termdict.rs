@@ -201,4 +201,29 @@ impl TermDictionary {
         let stream_builder = self.fst_index.search(automaton);
         TermStreamerBuilder::<A>::new(self, stream_builder)
     }
+
+    /// Returns the terms accepted by `automaton`, with their term ordinal,
+    /// in the order of the terms.
+    ///
+    /// The automaton is run on the bytes of the terms, without their field.
+    pub fn intersect<A: Automaton>(&self, automaton: A) -> io::Result<Vec<(Vec<u8>, TermOrdinal)>> {
+        let mut stream = self.search(automaton).into_stream()?;
+        let mut terms = Vec::new();
+        while stream.advance() {
+            terms.push((stream.key().to_vec(), stream.term_ord()));
+        }
+        Ok(terms)
+    }
+
+    /// Returns the `fst` map underlying the term dictionary.
+    ///
+    /// The map associates the bytes of each term, without its field,
+    /// to its term ordinal. Its `TermInfo` can then be fetched with
+    /// [`.term_info_from_ord(...)`](#method.term_info_from_ord).
+    ///
+    /// This makes it possible to run any algorithm of the
+    /// [`tantivy_fst`](../tantivy_fst/index.html) crate on the term dictionary.
+    pub fn fst_map(&self) -> &tantivy_fst::Map<OwnedBytes> {
+        &self.fst_index
+    }
 }
@@ -18,6 +18,66 @@ and then treated as a `u64`.
 as `u64`.
 
 A second datastructure makes it possible to access a [`TermInfo`](../postings/struct.TermInfo.html).
+
+# Custom automatons
+
+The terms of a dictionary can be intersected with any automaton implementing the
+[`Automaton`](../tantivy_fst/trait.Automaton.html) trait of the `tantivy_fst` crate,
+re-exported by tantivy, with [`TermDictionary::search`](./struct.TermDictionary.html#method.search)
+or [`TermDictionary::intersect`](./struct.TermDictionary.html#method.intersect).
+The underlying `fst` map is also available with
+[`TermDictionary::fst_map`](./struct.TermDictionary.html#method.fst_map).
+
+```rust
+use tantivy::schema::{Schema, STRING};
+use tantivy::tantivy_fst::Automaton;
+use tantivy::{doc, Index};
+
+/// Matches the terms containing a given byte.
+struct ContainsByte(u8);
+
+impl Automaton for ContainsByte {
+    type State = bool;
+
+    fn start(&self) -> bool {
+        false
+    }
+
+    fn is_match(&self, found: &bool) -> bool {
+        *found
+    }
+
+    fn will_always_match(&self, found: &bool) -> bool {
+        *found
+    }
+
+    fn accept(&self, found: &bool, byte: u8) -> bool {
+        *found || byte == self.0
+    }
+}
+
+# fn main() -> tantivy::Result<()> {
+let mut schema_builder = Schema::builder();
+let country = schema_builder.add_text_field("country", STRING);
+let index = Index::create_in_ram(schema_builder.build());
+let mut index_writer = index.writer(3_000_000)?;
+for name in &["Spain", "Sweden", "Serbia", "Slovenia"] {
+    index_writer.add_document(doc!(country => *name));
+}
+index_writer.commit()?;
+
+let searcher = index.reader()?.searcher();
+let inverted_index = searcher.segment_reader(0).inverted_index(country)?;
+let terms: Vec<Vec<u8>> = inverted_index
+    .terms()
+    .intersect(ContainsByte(b'b'))?
+    .into_iter()
+    .map(|(term, _term_ord)| term)
+    .collect();
+assert_eq!(terms, vec![b"Serbia".to_vec()]);
+# Ok(())
+# }
+```
 */
 
 use tantivy_fst::automaton::AlwaysMatch;
@@ -426,5 +426,14 @@ fn test_automaton_search() -> crate::Result<()> {
     assert!(range.advance());
     assert_eq!("Spain".as_bytes(), range.key());
     assert!(!range.advance());
+
+    let automaton = DfaWrapper(lev_automaton_builder.build_dfa("Slovenia"));
+    assert_eq!(
+        term_dict.intersect(automaton)?,
+        vec![(b"Slovakia".to_vec(), 2), (b"Slovenia".to_vec(), 3)]
+    );
+    let fst_map = term_dict.fst_map();
+    assert_eq!(fst_map.len(), COUNTRIES.len());
+    assert_eq!(fst_map.get("Sweden"), Some(5));
     Ok(())
 }
@@ -0,0 +1,358 @@
+//! # Example
+//! ```rust
+//! use tantivy::tokenizer::*;
+//!
+//! let tokenizer = TextAnalyzer::from(SimpleTokenizer)
+//!   .filter(LowerCaser)
+//!   .filter(CjkBigramFilter::default());
+//!
+//! let mut stream = tokenizer.token_stream("東京タワー Tokyo");
+//! assert_eq!(stream.next().unwrap().text, "東京");
+//! assert_eq!(stream.next().unwrap().text, "京タ");
+//! assert_eq!(stream.next().unwrap().text, "タワ");
+//! assert_eq!(stream.next().unwrap().text, "ワー");
+//! assert_eq!(stream.next().unwrap().text, "tokyo");
+//! assert!(stream.next().is_none());
+//! ```
+use super::{BoxTokenStream, Token, TokenFilter, TokenStream};
+use std::collections::VecDeque;
+
+/// CJK scripts handled by the `CjkBigramFilter`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CjkScript {
+    /// Chinese characters, also used in Japanese (kanji) and Korean (hanja).
+    Han,
+    /// Japanese hiragana.
+    Hiragana,
+    /// Japanese katakana, including the halfwidth forms.
+    Katakana,
+    /// Korean hangul.
+    Hangul,
+}
+
+impl CjkScript {
+    fn of(c: char) -> Option<CjkScript> {
+        match c as u32 {
+            0x3400..=0x4DBF
+            | 0x4E00..=0x9FFF
+            | 0xF900..=0xFAFF
+            | 0x20000..=0x2A6DF
+            | 0x2A700..=0x2EBEF
+            | 0x2F800..=0x2FA1F
+            | 0x30000..=0x3134F => Some(CjkScript::Han),
+            0x3040..=0x309F => Some(CjkScript::Hiragana),
+            0x30A0..=0x30FF | 0x31F0..=0x31FF | 0xFF66..=0xFF9F => Some(CjkScript::Katakana),
+            0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => Some(CjkScript::Hangul),
+            _ => None,
+        }
+    }
+}
+
+/// `TokenFilter` that splits the CJK characters of tokens into overlapping bigrams,
+/// the usual way to index languages that are not written with spaces between words.
+///
+/// Within a token, each run of adjacent characters of the bigrammed scripts
+/// is split into overlapping bigrams (a run of a single character is emitted as is).
+/// The characters of the other CJK scripts are emitted as unigrams, and tokens or
+/// parts of tokens that are not written in a CJK script (e.g. latin words) pass through.
+///
+/// Optionally, the unigrams of the bigrammed characters are emitted as well, and
+/// the bigrams are stacked on the unigram they start. This allows single character queries
+/// to match, at the cost of a larger index.
+///
+/// The positions of the following tokens are shifted by the number of grams,
+/// so that phrase queries work on grams. The offsets of the grams are the ones of
+/// the characters they are made of, provided the filters applied before this one did
+/// not change the length of the text of the token.
+#[derive(Clone, Copy, Debug)]
+pub struct CjkBigramFilter {
+    han: bool,
+    hiragana: bool,
+    katakana: bool,
+    hangul: bool,
+    output_unigrams: bool,
+}
+
+impl CjkBigramFilter {
+    /// Creates a filter splitting all of the CJK scripts into bigrams.
+    pub fn new() -> CjkBigramFilter {
+        CjkBigramFilter::for_scripts(&[
+            CjkScript::Han,
+            CjkScript::Hiragana,
+            CjkScript::Katakana,
+            CjkScript::Hangul,
+        ])
+    }
+
+    /// Creates a filter splitting the given scripts into bigrams,
+    /// and the other CJK scripts into unigrams.
+    pub fn for_scripts(scripts: &[CjkScript]) -> CjkBigramFilter {
+        CjkBigramFilter {
+            han: scripts.contains(&CjkScript::Han),
+            hiragana: scripts.contains(&CjkScript::Hiragana),
+            katakana: scripts.contains(&CjkScript::Katakana),
+            hangul: scripts.contains(&CjkScript::Hangul),
+            output_unigrams: false,
+        }
+    }
+
+    /// Also emits the unigrams of the bigrammed characters.
+    pub fn output_unigrams(mut self, output_unigrams: bool) -> CjkBigramFilter {
+        self.output_unigrams = output_unigrams;
+        self
+    }
+
+    fn is_bigrammed(&self, script: CjkScript) -> bool {
+        match script {
+            CjkScript::Han => self.han,
+            CjkScript::Hiragana => self.hiragana,
+            CjkScript::Katakana => self.katakana,
+            CjkScript::Hangul => self.hangul,
+        }
+    }
+
+    fn char_kind(&self, c: char) -> CharKind {
+        match CjkScript::of(c) {
+            Some(script) if self.is_bigrammed(script) => CharKind::Bigram,
+            Some(_) => CharKind::Unigram,
+            None => CharKind::Other,
+        }
+    }
+}
+
+impl Default for CjkBigramFilter {
+    fn default() -> CjkBigramFilter {
+        CjkBigramFilter::new()
+    }
+}
+
+impl TokenFilter for CjkBigramFilter {
+    fn transform<'a>(&self, token_stream: BoxTokenStream<'a>) -> BoxTokenStream<'a> {
+        BoxTokenStream::from(CjkBigramFilterStream {
+            filter: *self,
+            tail: token_stream,
+            grams: VecDeque::new(),
+            gram: Token::default(),
+            emits_gram: false,
+            position_shift: 0,
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CharKind {
+    Bigram,
+    Unigram,
+    Other,
+}
+
+pub struct CjkBigramFilterStream<'a> {
+    filter: CjkBigramFilter,
+    tail: BoxTokenStream<'a>,
+    // Grams of the current token that remain to be emitted.
+    grams: VecDeque<Token>,
+    gram: Token,
+    // True if the current token is a gram, false if it is the token of the tail.
+    emits_gram: bool,
+    // Number of positions added by the grams of the previous tokens.
+    position_shift: usize,
+}
+
+impl<'a> CjkBigramFilterStream<'a> {
+    // Splits the current token of the tail into grams, and returns the number of
+    // positions they take.
+    fn split_into_grams(&mut self) -> usize {
+        let token = self.tail.token();
+        let keeps_offsets = token.offset_to - token.offset_from == token.text.len();
+        let chars: Vec<(usize, char)> = token.text.char_indices().collect();
+        let char_start = |i: usize| chars[i].0;
+        let char_end = |i: usize| {
+            chars
+                .get(i + 1)
+                .map(|&(offset, _)| offset)
+                .unwrap_or_else(|| token.text.len())
+        };
+        let grams = &mut self.grams;
+        // Stacked grams are bigrams emitted along with their unigrams: they span
+        // the positions of their two unigrams.
+        let mut push_gram = |from: usize, to: usize, position: usize, stacked: bool| {
+            let (offset_from, offset_to) = if keeps_offsets {
+                (token.offset_from + from, token.offset_from + to)
+            } else {
+                (token.offset_from, token.offset_to)
+            };
+            grams.push_back(Token {
+                offset_from,
+                offset_to,
+                position: token.position + position,
+                text: token.text[from..to].to_string(),
+                position_length: if stacked { 2 } else { 1 },
+                stacked,
+            });
+        };
+        let mut num_positions = 0;
+        let mut start = 0;
+        while start < chars.len() {
+            let kind = self.filter.char_kind(chars[start].1);
+            let mut end = start + 1;
+            if kind != CharKind::Unigram {
+                while end < chars.len() && self.filter.char_kind(chars[end].1) == kind {
+                    end += 1;
+                }
+            }
+            if kind != CharKind::Bigram || end - start == 1 {
+                push_gram(char_start(start), char_end(end - 1), num_positions, false);
+                num_positions += 1;
+                start = end;
+                continue;
+            }
+            let output_unigrams = self.filter.output_unigrams;
+            for i in start..end - 1 {
+                if output_unigrams {
+                    push_gram(char_start(i), char_end(i), num_positions, false);
+                }
+                push_gram(
+                    char_start(i),
+                    char_end(i + 1),
+                    num_positions,
+                    output_unigrams,
+                );
+                num_positions += 1;
+            }
+            if output_unigrams {
+                push_gram(char_start(end - 1), char_end(end - 1), num_positions, false);
+                num_positions += 1;
+            }
+            start = end;
+        }
+        num_positions
+    }
+}
+
+impl<'a> TokenStream for CjkBigramFilterStream<'a> {
+    fn advance(&mut self) -> bool {
+        if let Some(gram) = self.grams.pop_front() {
+            self.gram = gram;
+            return true;
+        }
+        if !self.tail.advance() {
+            return false;
+        }
+        let position_shift = self.position_shift;
+        self.tail.token_mut().position += position_shift;
+        let filter = self.filter;
+        let is_cjk = self
+            .tail
+            .token()
+            .text
+            .chars()
+            .any(|c| filter.char_kind(c) != CharKind::Other);
+        if !is_cjk {
+            self.emits_gram = false;
+            return true;
+        }
+        let num_positions = self.split_into_grams();
+        self.position_shift += num_positions.saturating_sub(1);
+        self.emits_gram = true;
+        match self.grams.pop_front() {
+            Some(gram) => {
+                self.gram = gram;
+                true
+            }
+            None => self.advance(),
+        }
+    }
+
+    fn token(&self) -> &Token {
+        if self.emits_gram {
+            &self.gram
+        } else {
+            self.tail.token()
+        }
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        if self.emits_gram {
+            &mut self.gram
+        } else {
+            self.tail.token_mut()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CjkBigramFilter, CjkScript};
+    use crate::tokenizer::{SimpleTokenizer, TextAnalyzer, Token, TokenFilter};
+
+    fn analyze<F: TokenFilter>(filter: F, text: &str) -> Vec<(String, usize)> {
+        TextAnalyzer::from(SimpleTokenizer)
+            .filter(filter)
+            .analyze(text)
+            .into_iter()
+            .map(|token: Token| (token.text, token.position))
+            .collect()
+    }
+
+    fn grams(grams: &[(&str, usize)]) -> Vec<(String, usize)> {
+        grams
+            .iter()
+            .map(|&(text, position)| (text.to_string(), position))
+            .collect()
+    }
+
+    #[test]
+    fn test_cjk_bigram_filter() {
+        assert_eq!(
+            analyze(CjkBigramFilter::default(), "hello 東京都 world 日"),
+            grams(&[
+                ("hello", 0),
+                ("東京", 1),
+                ("京都", 2),
+                ("world", 3),
+                ("日", 4)
+            ])
+        );
+        // Mixed scripts within a token.
+        assert_eq!(
+            analyze(CjkBigramFilter::default(), "abc한국어"),
+            grams(&[("abc", 0), ("한국", 1), ("국어", 2)])
+        );
+    }
+
+    #[test]
+    fn test_cjk_bigram_filter_unigrams() {
+        assert_eq!(
+            analyze(CjkBigramFilter::new().output_unigrams(true), "東京都 a"),
+            grams(&[
+                ("東", 0),
+                ("東京", 0),
+                ("京", 1),
+                ("京都", 1),
+                ("都", 2),
+                ("a", 3)
+            ])
+        );
+    }
+
+    #[test]
+    fn test_cjk_bigram_filter_scripts() {
+        let filter = CjkBigramFilter::for_scripts(&[CjkScript::Han]);
+        assert_eq!(
+            analyze(filter, "東京タワー"),
+            grams(&[("東京", 0), ("タ", 1), ("ワ", 2), ("ー", 3)])
+        );
+    }
+
+    #[test]
+    fn test_cjk_bigram_filter_offsets() {
+        let tokens = TextAnalyzer::from(SimpleTokenizer)
+            .filter(CjkBigramFilter::default())
+            .analyze("x 東京都");
+        let offsets: Vec<(usize, usize)> = tokens
+            .iter()
+            .map(|token| (token.offset_from, token.offset_to))
+            .collect();
+        assert_eq!(offsets, vec![(0, 1), (2, 8), (5, 11)]);
+    }
+}
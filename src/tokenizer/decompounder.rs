@@ -0,0 +1,269 @@
+//! # Example
+//! ```rust
+//! use tantivy::tokenizer::*;
+//!
+//! let tokenizer = TextAnalyzer::from(SimpleTokenizer)
+//!   .filter(LowerCaser)
+//!   .filter(DictionaryDecompounder::new(vec!["dampf".to_string(), "schiff".to_string()]));
+//!
+//! let mut stream = tokenizer.token_stream("Dampfschiff");
+//! assert_eq!(stream.next().unwrap().text, "dampfschiff");
+//! assert_eq!(stream.next().unwrap().text, "dampf");
+//! assert_eq!(stream.next().unwrap().text, "schiff");
+//! assert!(stream.next().is_none());
+//! ```
+use super::{BoxTokenStream, Token, TokenFilter, TokenStream};
+use fnv::FnvHashSet;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// `TokenFilter` that splits compound words into the subwords they are made of,
+/// using a dictionary of subwords, as needed for languages such as German, Dutch
+/// or the Scandinavian languages.
+///
+/// Each token is emitted as is, followed by the subwords of the dictionary it contains.
+/// The subwords are stacked on the token: they are emitted at its position, so that
+/// phrase queries still match, and do not change the number of tokens of the field.
+///
+/// Subwords are looked up as is: the dictionary is usually lowercased, and the filter
+/// applied after a `LowerCaser`.
+#[derive(Clone)]
+pub struct DictionaryDecompounder {
+    dictionary: Arc<FnvHashSet<String>>,
+    min_word_len: usize,
+    min_subword_len: usize,
+    max_subword_len: usize,
+    only_longest_match: bool,
+}
+
+impl DictionaryDecompounder {
+    /// Creates a `DictionaryDecompounder` given the list of subwords.
+    ///
+    /// By default, words of at least 5 characters are split into subwords
+    /// of 2 to 15 characters.
+    pub fn new(words: Vec<String>) -> DictionaryDecompounder {
+        DictionaryDecompounder {
+            dictionary: Arc::new(words.into_iter().collect()),
+            min_word_len: 5,
+            min_subword_len: 2,
+            max_subword_len: 15,
+            only_longest_match: false,
+        }
+    }
+
+    /// Sets the minimum length, in characters, of the words to split.
+    pub fn min_word_len(mut self, min_word_len: usize) -> DictionaryDecompounder {
+        self.min_word_len = min_word_len;
+        self
+    }
+
+    /// Sets the minimum length, in characters, of the subwords.
+    pub fn min_subword_len(mut self, min_subword_len: usize) -> DictionaryDecompounder {
+        self.min_subword_len = min_subword_len;
+        self
+    }
+
+    /// Sets the maximum length, in characters, of the subwords.
+    pub fn max_subword_len(mut self, max_subword_len: usize) -> DictionaryDecompounder {
+        self.max_subword_len = max_subword_len;
+        self
+    }
+
+    /// Only emits the longest subword starting at each character, e.g. `fussball`
+    /// rather than `fuss` and `fussball`.
+    pub fn only_longest_match(mut self, only_longest_match: bool) -> DictionaryDecompounder {
+        self.only_longest_match = only_longest_match;
+        self
+    }
+
+    // Pushes the subwords of the token, stacked on it.
+    fn decompound(&self, token: &Token, subwords: &mut VecDeque<Token>) {
+        let chars: Vec<(usize, char)> = token.text.char_indices().collect();
+        if chars.len() < self.min_word_len {
+            return;
+        }
+        let keeps_offsets = token.offset_to - token.offset_from == token.text.len();
+        let char_offset = |i: usize| {
+            chars
+                .get(i)
+                .map(|&(offset, _)| offset)
+                .unwrap_or_else(|| token.text.len())
+        };
+        for start in 0..chars.len() {
+            let max_end = chars.len().min(start + self.max_subword_len);
+            let mut longest_subword = None;
+            for end in start + self.min_subword_len..=max_end {
+                if end - start == chars.len() {
+                    // The token itself.
+                    break;
+                }
+                let (from, to) = (char_offset(start), char_offset(end));
+                if !self.dictionary.contains(&token.text[from..to]) {
+                    continue;
+                }
+                if self.only_longest_match {
+                    longest_subword = Some((from, to));
+                } else {
+                    subwords.push_back(stacked_subword(token, from, to, keeps_offsets));
+                }
+            }
+            if let Some((from, to)) = longest_subword {
+                subwords.push_back(stacked_subword(token, from, to, keeps_offsets));
+            }
+        }
+    }
+}
+
+fn stacked_subword(token: &Token, from: usize, to: usize, keeps_offsets: bool) -> Token {
+    let (offset_from, offset_to) = if keeps_offsets {
+        (token.offset_from + from, token.offset_from + to)
+    } else {
+        (token.offset_from, token.offset_to)
+    };
+    Token {
+        offset_from,
+        offset_to,
+        position: token.position,
+        text: token.text[from..to].to_string(),
+        position_length: 1,
+        stacked: true,
+    }
+}
+
+impl TokenFilter for DictionaryDecompounder {
+    fn transform<'a>(&self, token_stream: BoxTokenStream<'a>) -> BoxTokenStream<'a> {
+        BoxTokenStream::from(DictionaryDecompounderStream {
+            decompounder: self.clone(),
+            tail: token_stream,
+            subwords: VecDeque::new(),
+            subword: Token::default(),
+            emits_subword: false,
+        })
+    }
+}
+
+pub struct DictionaryDecompounderStream<'a> {
+    decompounder: DictionaryDecompounder,
+    tail: BoxTokenStream<'a>,
+    // Subwords of the current token of the tail that remain to be emitted.
+    subwords: VecDeque<Token>,
+    subword: Token,
+    // True if the current token is a subword, false if it is the token of the tail.
+    emits_subword: bool,
+}
+
+impl<'a> TokenStream for DictionaryDecompounderStream<'a> {
+    fn advance(&mut self) -> bool {
+        if let Some(subword) = self.subwords.pop_front() {
+            self.subword = subword;
+            self.emits_subword = true;
+            return true;
+        }
+        if !self.tail.advance() {
+            return false;
+        }
+        self.decompounder
+            .decompound(self.tail.token(), &mut self.subwords);
+        self.emits_subword = false;
+        true
+    }
+
+    fn token(&self) -> &Token {
+        if self.emits_subword {
+            &self.subword
+        } else {
+            self.tail.token()
+        }
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        if self.emits_subword {
+            &mut self.subword
+        } else {
+            self.tail.token_mut()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DictionaryDecompounder;
+    use crate::tokenizer::{LowerCaser, SimpleTokenizer, TextAnalyzer, Token, TokenStream};
+
+    fn decompounder() -> DictionaryDecompounder {
+        let words = ["fuss", "fussball", "ball", "spiel", "platz"];
+        DictionaryDecompounder::new(words.iter().map(|word| word.to_string()).collect())
+    }
+
+    fn analyze(decompounder: DictionaryDecompounder, text: &str) -> Vec<Token> {
+        TextAnalyzer::from(SimpleTokenizer)
+            .filter(LowerCaser)
+            .filter(decompounder)
+            .analyze(text)
+    }
+
+    fn texts_and_positions(tokens: &[Token]) -> Vec<(&str, usize)> {
+        tokens
+            .iter()
+            .map(|token| (token.text.as_str(), token.position))
+            .collect()
+    }
+
+    #[test]
+    fn test_dictionary_decompounder() {
+        let tokens = analyze(decompounder(), "Fussballspielplatz im Park");
+        assert_eq!(
+            texts_and_positions(&tokens),
+            vec![
+                ("fussballspielplatz", 0),
+                ("fuss", 0),
+                ("fussball", 0),
+                ("ball", 0),
+                ("spiel", 0),
+                ("platz", 0),
+                ("im", 1),
+                ("park", 2)
+            ]
+        );
+        assert!(!tokens[0].stacked);
+        assert!(tokens[1..6].iter().all(|token| token.stacked));
+        assert_eq!((tokens[4].offset_from, tokens[4].offset_to), (8, 13));
+    }
+
+    #[test]
+    fn test_dictionary_decompounder_only_longest_match() {
+        let tokens = analyze(decompounder().only_longest_match(true), "Fussballplatz");
+        assert_eq!(
+            texts_and_positions(&tokens),
+            vec![
+                ("fussballplatz", 0),
+                ("fussball", 0),
+                ("ball", 0),
+                ("platz", 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dictionary_decompounder_min_word_len() {
+        let tokens = analyze(decompounder().min_word_len(10), "Fussball Spielplatz");
+        assert_eq!(
+            texts_and_positions(&tokens),
+            vec![
+                ("fussball", 0),
+                ("spielplatz", 1),
+                ("spiel", 1),
+                ("platz", 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dictionary_decompounder_num_tokens() {
+        let mut token_stream = TextAnalyzer::from(SimpleTokenizer)
+            .filter(decompounder())
+            .token_stream("fussballplatz gross");
+        // Stacked subwords are not counted.
+        assert_eq!(token_stream.process(&mut |_| {}), 2);
+    }
+}
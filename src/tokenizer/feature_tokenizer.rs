@@ -0,0 +1,178 @@
+use super::BoxTokenStream;
+use super::{Token, TokenStream, Tokenizer};
+use std::str::SplitWhitespace;
+
+/// Smallest feature weight that can be represented, encoded as a term frequency of 1.
+const MIN_FEATURE_WEIGHT: f32 = 1f32 / 256f32;
+
+/// Number of term frequency units per doubling of the feature weight.
+///
+/// Feature weights are quantized on a log scale: two consecutive term frequencies
+/// encode weights that differ by about 4.4%.
+const STEPS_PER_DOUBLING: u32 = 16;
+
+/// Maximum term frequency a single feature can be encoded into, i.e. the maximum
+/// number of times its token is emitted.
+///
+/// It encodes a weight of about `234.8`. Larger weights are capped to this value.
+const MAX_FEATURE_TERM_FREQ: u32 = 255;
+
+/// Encodes a feature weight into a term frequency.
+///
+/// Returns `None` if the weight is not strictly positive and finite,
+/// or if it is too small to be represented.
+pub(crate) fn feature_weight_to_term_freq(weight: f32) -> Option<u32> {
+    if !weight.is_finite() || weight <= 0f32 {
+        return None;
+    }
+    let step = ((weight / MIN_FEATURE_WEIGHT).log2() * STEPS_PER_DOUBLING as f32).round();
+    if step < 0f32 {
+        return None;
+    }
+    Some((step as u32 + 1).min(MAX_FEATURE_TERM_FREQ))
+}
+
+/// Decodes a term frequency back into its (quantized) feature weight.
+///
+/// The term frequency read at query time can exceed `MAX_FEATURE_TERM_FREQ`, e.g. if
+/// a feature is repeated in a document. It is then capped to the maximum weight.
+pub(crate) fn term_freq_to_feature_weight(term_freq: u32) -> f32 {
+    let step = term_freq.max(1).min(MAX_FEATURE_TERM_FREQ) - 1;
+    let num_doublings = step / STEPS_PER_DOUBLING;
+    let remaining_steps = step % STEPS_PER_DOUBLING;
+    // Whole doublings are applied exactly, so that powers of two are represented exactly.
+    MIN_FEATURE_WEIGHT
+        * (1u32 << num_doublings) as f32
+        * 2f32.powf(remaining_steps as f32 / STEPS_PER_DOUBLING as f32)
+}
+
+/// Tokenizer for "rank features" fields.
+///
+/// The text is expected to be a whitespace separated list of
+/// `feature:weight` pairs, for instance `"dog:1.25 cat:0.5"`.
+///
+/// Each feature is emitted as a token, repeated as many times as required
+/// for its term frequency to encode its weight. Weights are expected to be strictly positive,
+/// and are quantized on a log scale, with a relative precision of about 2%, from `1/256`
+/// up to a maximum of about `234.8`. A feature is therefore repeated at most 255 times.
+/// Pairs that cannot be parsed, or that have a non positive weight, are silently ignored.
+///
+/// The field should be indexed with `IndexRecordOption::WithFreqs`.
+/// Its features can then be scored using a [`FeatureQuery`](../query/struct.FeatureQuery.html).
+#[derive(Clone)]
+pub struct FeatureTokenizer;
+
+pub struct FeatureTokenStream<'a> {
+    text: &'a str,
+    pairs: SplitWhitespace<'a>,
+    remaining: u32,
+    token: Token,
+}
+
+impl Tokenizer for FeatureTokenizer {
+    fn token_stream<'a>(&self, text: &'a str) -> BoxTokenStream<'a> {
+        BoxTokenStream::from(FeatureTokenStream {
+            text,
+            pairs: text.split_whitespace(),
+            remaining: 0,
+            token: Token::default(),
+        })
+    }
+}
+
+impl<'a> FeatureTokenStream<'a> {
+    // Moves on to the next valid `feature:weight` pair.
+    fn next_feature(&mut self) -> bool {
+        for pair in &mut self.pairs {
+            let separator_pos = if let Some(separator_pos) = pair.rfind(':') {
+                separator_pos
+            } else {
+                continue;
+            };
+            let (feature, weight_str) = (&pair[..separator_pos], &pair[separator_pos + 1..]);
+            if feature.is_empty() {
+                continue;
+            }
+            let term_freq_opt = weight_str
+                .parse::<f32>()
+                .ok()
+                .and_then(feature_weight_to_term_freq);
+            if let Some(term_freq) = term_freq_opt {
+                let offset_from = pair.as_ptr() as usize - self.text.as_ptr() as usize;
+                self.token.text.clear();
+                self.token.text.push_str(feature);
+                self.token.offset_from = offset_from;
+                self.token.offset_to = offset_from + feature.len();
+                self.token.position = self.token.position.wrapping_add(1);
+                self.remaining = term_freq;
+                return true;
+            }
+        }
+        false
+    }
+}
+
+impl<'a> TokenStream for FeatureTokenStream<'a> {
+    fn advance(&mut self) -> bool {
+        if self.remaining == 0 && !self.next_feature() {
+            return false;
+        }
+        self.remaining -= 1;
+        true
+    }
+
+    fn token(&self) -> &Token {
+        &self.token
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.token
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        feature_weight_to_term_freq, term_freq_to_feature_weight, FeatureTokenizer,
+        MAX_FEATURE_TERM_FREQ,
+    };
+    use crate::tokenizer::{TextAnalyzer, Token};
+
+    #[test]
+    fn test_feature_tokenizer() {
+        let mut tokens: Vec<Token> = vec![];
+        TextAnalyzer::from(FeatureTokenizer)
+            .token_stream("dog:0.01 invalid cat:0.0 :1.0 bird:abc fish:0.5 whale:1e30")
+            .process(&mut |token: &Token| tokens.push(token.clone()));
+        let num_tokens = |text: &str| tokens.iter().filter(|token| token.text == text).count();
+        assert_eq!(num_tokens("dog"), 23);
+        assert_eq!(num_tokens("fish"), 113);
+        assert_eq!(num_tokens("whale"), 255);
+        assert_eq!(tokens.len(), 391);
+        assert_eq!(tokens[0].offset_from, 0);
+        assert_eq!(tokens[0].offset_to, 3);
+        assert_eq!(tokens[0].position, 0);
+        assert_eq!(tokens[23].position, 1);
+    }
+
+    #[test]
+    fn test_feature_weight_encoding() {
+        assert_eq!(feature_weight_to_term_freq(0f32), None);
+        assert_eq!(feature_weight_to_term_freq(-1f32), None);
+        assert_eq!(feature_weight_to_term_freq(f32::NAN), None);
+        assert_eq!(feature_weight_to_term_freq(0.0001f32), None);
+        assert_eq!(feature_weight_to_term_freq(1f32 / 256f32), Some(1));
+        assert_eq!(feature_weight_to_term_freq(1f32), Some(129));
+        assert_eq!(feature_weight_to_term_freq(1_000f32), Some(255));
+        assert_eq!(term_freq_to_feature_weight(129), 1f32);
+        assert_eq!(term_freq_to_feature_weight(161), 4f32);
+        let max_weight = term_freq_to_feature_weight(MAX_FEATURE_TERM_FREQ);
+        assert_eq!(term_freq_to_feature_weight(513), max_weight);
+        assert_eq!(term_freq_to_feature_weight(u32::MAX), max_weight);
+        for &weight in &[0.01f32, 0.3f32, 1.5f32, 3f32, 100f32, 234f32] {
+            let term_freq = feature_weight_to_term_freq(weight).unwrap();
+            let decoded_weight = term_freq_to_feature_weight(term_freq);
+            assert!((decoded_weight - weight).abs() < 0.025f32 * weight);
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use super::{Token, TokenStream, Tokenizer};
+use crate::tokenizer::BoxTokenStream;
+
+/// For each value of the field, emits the whole value as a single token,
+/// optionally normalized.
+///
+/// Without normalization, it behaves as the `RawTokenizer`. With normalization,
+/// it makes exact-match fields case insensitive, or insensitive to leading and
+/// trailing whitespaces, e.g. for identifiers, tags or email addresses.
+///
+/// The `keyword_normalized` tokenizer of the `TokenizerManager`, used by
+/// [`STRING_NORMALIZED`](../schema/constant.STRING_NORMALIZED.html) fields,
+/// both trims and lowercases values.
+///
+/// ```rust
+/// use tantivy::tokenizer::*;
+///
+/// let tokenizer = KeywordTokenizer::default().lowercase(true).trim(true);
+/// let mut stream = tokenizer.token_stream("  New-York City ");
+/// assert_eq!(stream.next().unwrap().text, "new-york city");
+/// assert!(stream.next().is_none());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct KeywordTokenizer {
+    lowercase: bool,
+    trim: bool,
+}
+
+impl KeywordTokenizer {
+    /// Lowercases the token.
+    pub fn lowercase(mut self, lowercase: bool) -> KeywordTokenizer {
+        self.lowercase = lowercase;
+        self
+    }
+
+    /// Removes the leading and trailing whitespaces of the token.
+    pub fn trim(mut self, trim: bool) -> KeywordTokenizer {
+        self.trim = trim;
+        self
+    }
+}
+
+pub struct KeywordTokenStream {
+    token: Token,
+    has_token: bool,
+}
+
+impl Tokenizer for KeywordTokenizer {
+    fn token_stream<'a>(&self, text: &'a str) -> BoxTokenStream<'a> {
+        let (offset_from, value) = if self.trim {
+            let trimmed_start = text.trim_start();
+            (text.len() - trimmed_start.len(), trimmed_start.trim_end())
+        } else {
+            (0, text)
+        };
+        let token_text = if self.lowercase {
+            value.to_lowercase()
+        } else {
+            value.to_string()
+        };
+        let token = Token {
+            offset_from,
+            offset_to: offset_from + value.len(),
+            position: 0,
+            text: token_text,
+            position_length: 1,
+            stacked: false,
+        };
+        KeywordTokenStream {
+            token,
+            has_token: true,
+        }
+        .into()
+    }
+}
+
+impl TokenStream for KeywordTokenStream {
+    fn advance(&mut self) -> bool {
+        let result = self.has_token;
+        self.has_token = false;
+        result
+    }
+
+    fn token(&self) -> &Token {
+        &self.token
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.token
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KeywordTokenizer;
+    use crate::tokenizer::tests::assert_token;
+    use crate::tokenizer::{TextAnalyzer, Token};
+
+    fn analyze(tokenizer: KeywordTokenizer, text: &str) -> Vec<Token> {
+        TextAnalyzer::from(tokenizer).analyze(text)
+    }
+
+    #[test]
+    fn test_keyword_tokenizer() {
+        let tokens = analyze(KeywordTokenizer::default(), " Hello, World ");
+        assert_eq!(tokens.len(), 1);
+        assert_token(&tokens[0], 0, " Hello, World ", 0, 14);
+
+        let tokens = analyze(KeywordTokenizer::default().lowercase(true), " Ünïcode ");
+        assert_token(&tokens[0], 0, " ünïcode ", 0, 11);
+
+        let tokens = analyze(
+            KeywordTokenizer::default().lowercase(true).trim(true),
+            " Hello, World ",
+        );
+        assert_eq!(tokens.len(), 1);
+        assert_token(&tokens[0], 0, "hello, world", 1, 13);
+    }
+}
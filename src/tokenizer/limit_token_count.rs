@@ -0,0 +1,90 @@
+//! # Example
+//! ```rust
+//! use tantivy::tokenizer::*;
+//!
+//! let tokenizer = TextAnalyzer::from(SimpleTokenizer)
+//!   .filter(LimitTokenCountFilter::limit(2));
+//!
+//! let mut stream = tokenizer.token_stream("one two three four");
+//! assert_eq!(stream.next().unwrap().text, "one");
+//! assert_eq!(stream.next().unwrap().text, "two");
+//! assert!(stream.next().is_none());
+//! ```
+//!
+use super::{Token, TokenFilter, TokenStream};
+use crate::tokenizer::BoxTokenStream;
+
+/// `LimitTokenCountFilter` stops the token stream after a given number of tokens.
+///
+/// It protects the indexing memory and the size of the postings from
+/// pathologically large values, e.g. multi-megabyte documents. The remainder of
+/// the text is not tokenized at all.
+///
+/// It can be set on a text field with
+/// [`TextFieldIndexing::set_max_num_tokens`](../schema/struct.TextFieldIndexing.html#method.set_max_num_tokens),
+/// in which case it applies to each of the values of the field.
+#[derive(Clone)]
+pub struct LimitTokenCountFilter {
+    max_num_tokens: usize,
+}
+
+impl LimitTokenCountFilter {
+    /// Creates a `LimitTokenCountFilter` emitting at most `max_num_tokens` tokens.
+    pub fn limit(max_num_tokens: usize) -> LimitTokenCountFilter {
+        LimitTokenCountFilter { max_num_tokens }
+    }
+}
+
+impl TokenFilter for LimitTokenCountFilter {
+    fn transform<'a>(&self, token_stream: BoxTokenStream<'a>) -> BoxTokenStream<'a> {
+        BoxTokenStream::from(LimitTokenCountFilterStream {
+            num_tokens_left: self.max_num_tokens,
+            tail: token_stream,
+        })
+    }
+}
+
+pub struct LimitTokenCountFilterStream<'a> {
+    num_tokens_left: usize,
+    tail: BoxTokenStream<'a>,
+}
+
+impl<'a> TokenStream for LimitTokenCountFilterStream<'a> {
+    fn advance(&mut self) -> bool {
+        if self.num_tokens_left == 0 {
+            return false;
+        }
+        self.num_tokens_left -= 1;
+        self.tail.advance()
+    }
+
+    fn token(&self) -> &Token {
+        self.tail.token()
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        self.tail.token_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LimitTokenCountFilter;
+    use crate::tokenizer::{SimpleTokenizer, TextAnalyzer};
+
+    fn texts(max_num_tokens: usize, text: &str) -> Vec<String> {
+        TextAnalyzer::from(SimpleTokenizer)
+            .filter(LimitTokenCountFilter::limit(max_num_tokens))
+            .analyze(text)
+            .into_iter()
+            .map(|token| token.text)
+            .collect()
+    }
+
+    #[test]
+    fn test_limit_token_count_filter() {
+        assert_eq!(texts(2, "a b c"), vec!["a", "b"]);
+        assert_eq!(texts(3, "a b"), vec!["a", "b"]);
+        assert!(texts(0, "a b").is_empty());
+    }
+}
@@ -54,9 +54,97 @@ impl<'a> TokenStream for LowerCaserTokenStream<'a> {
     }
 }
 
+impl TokenFilter for CaseFolder {
+    fn transform<'a>(&self, token_stream: BoxTokenStream<'a>) -> BoxTokenStream<'a> {
+        BoxTokenStream::from(CaseFolderTokenStream {
+            tail: token_stream,
+            buffer: String::with_capacity(100),
+        })
+    }
+}
+
+/// Token filter that applies Unicode full case folding to terms.
+///
+/// Case folding is a variant of lowercasing meant for caseless matching:
+/// besides lowercasing, it maps the characters that have several lowercase forms
+/// to a single one (e.g. `ß` and `ẞ` to `ss`, the final sigma `ς` to `σ`,
+/// or the ligature `ﬁ` to `fi`), so that "STRASSE" and "straße" match.
+#[derive(Clone)]
+pub struct CaseFolder;
+
+pub struct CaseFolderTokenStream<'a> {
+    buffer: String,
+    tail: BoxTokenStream<'a>,
+}
+
+// Characters whose full case folding differs from their lowercase form.
+fn fold_special_case(c: char) -> Option<&'static str> {
+    let folded = match c {
+        '\u{00B5}' => "\u{03BC}",              // MICRO SIGN
+        '\u{00DF}' | '\u{1E9E}' => "ss",       // SHARP S
+        '\u{0149}' => "\u{02BC}n",             // N PRECEDED BY APOSTROPHE
+        '\u{017F}' => "s",                     // LONG S
+        '\u{01F0}' => "j\u{030C}",             // J WITH CARON
+        '\u{0345}' | '\u{1FBE}' => "\u{03B9}", // YPOGEGRAMMENI
+        '\u{03C2}' => "\u{03C3}",              // FINAL SIGMA
+        '\u{03D0}' => "\u{03B2}",              // BETA SYMBOL
+        '\u{03D1}' => "\u{03B8}",              // THETA SYMBOL
+        '\u{03D5}' => "\u{03C6}",              // PHI SYMBOL
+        '\u{03D6}' => "\u{03C0}",              // PI SYMBOL
+        '\u{03F0}' => "\u{03BA}",              // KAPPA SYMBOL
+        '\u{03F1}' => "\u{03C1}",              // RHO SYMBOL
+        '\u{03F5}' => "\u{03B5}",              // LUNATE EPSILON SYMBOL
+        '\u{1E9B}' => "\u{1E61}",              // LONG S WITH DOT ABOVE
+        '\u{FB00}' => "ff",                    // LIGATURE FF
+        '\u{FB01}' => "fi",                    // LIGATURE FI
+        '\u{FB02}' => "fl",                    // LIGATURE FL
+        '\u{FB03}' => "ffi",                   // LIGATURE FFI
+        '\u{FB04}' => "ffl",                   // LIGATURE FFL
+        '\u{FB05}' | '\u{FB06}' => "st",       // LIGATURE ST
+        _ => return None,
+    };
+    Some(folded)
+}
+
+// writes a case folded version of text into output.
+fn to_case_folded_unicode(text: &mut String, output: &mut String) {
+    output.clear();
+    for c in text.chars() {
+        if let Some(folded) = fold_special_case(c) {
+            output.push_str(folded);
+        } else {
+            output.extend(c.to_lowercase());
+        }
+    }
+}
+
+impl<'a> TokenStream for CaseFolderTokenStream<'a> {
+    fn advance(&mut self) -> bool {
+        if !self.tail.advance() {
+            return false;
+        }
+        if self.token_mut().text.is_ascii() {
+            // fast track for ascii.
+            self.token_mut().text.make_ascii_lowercase();
+        } else {
+            to_case_folded_unicode(&mut self.tail.token_mut().text, &mut self.buffer);
+            mem::swap(&mut self.tail.token_mut().text, &mut self.buffer);
+        }
+        true
+    }
+
+    fn token(&self) -> &Token {
+        self.tail.token()
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        self.tail.token_mut()
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::tokenizer::{LowerCaser, SimpleTokenizer, TextAnalyzer};
+    use crate::tokenizer::{CaseFolder, LowerCaser, SimpleTokenizer, TextAnalyzer};
 
     #[test]
     fn test_to_lower_case() {
@@ -83,4 +171,23 @@ mod tests {
         assert_eq!(lowercase_helper("Tree"), vec!["tree".to_string()]);
         assert_eq!(lowercase_helper("Русский"), vec!["русский".to_string()]);
     }
+
+    fn case_fold_helper(text: &str) -> Vec<String> {
+        TextAnalyzer::from(SimpleTokenizer)
+            .filter(CaseFolder)
+            .analyze(text)
+            .into_iter()
+            .map(|token| token.text)
+            .collect()
+    }
+
+    #[test]
+    fn test_case_folder() {
+        assert_eq!(case_fold_helper("Tree"), vec!["tree".to_string()]);
+        assert_eq!(case_fold_helper("Русский"), vec!["русский".to_string()]);
+        assert_eq!(case_fold_helper("STRASSE"), case_fold_helper("straße"));
+        assert_eq!(case_fold_helper("Straẞe"), vec!["strasse".to_string()]);
+        assert_eq!(case_fold_helper("ΣΟΦΟΣ"), case_fold_helper("σοφος"));
+        assert_eq!(case_fold_helper("ﬁre"), vec!["fire".to_string()]);
+    }
 }
@@ -52,6 +52,12 @@
 //! remove their inflection. This tokenizer is slower than the default one,
 //! but is recommended to improve recall.
 //!
+//! ## `feature`
+//!
+//! Parses a list of whitespace separated `feature:weight` pairs, and encodes
+//! each weight in the term frequency of its feature. See
+//! [`FeatureTokenizer`](./struct.FeatureTokenizer.html).
+//!
 //!
 //! # Custom tokenizers
 //!
@@ -119,9 +125,15 @@
 //!
 mod alphanum_only;
 mod ascii_folding_filter;
+mod cjk_bigram_filter;
+mod decompounder;
 mod facet_tokenizer;
+mod feature_tokenizer;
+mod keyword_tokenizer;
+mod limit_token_count;
 mod lower_caser;
 mod ngram_tokenizer;
+mod prefixed_token_stream;
 mod raw_tokenizer;
 mod remove_long;
 mod simple_tokenizer;
@@ -134,9 +146,16 @@ mod tokenizer_manager;
 
 pub use self::alphanum_only::AlphaNumOnlyFilter;
 pub use self::ascii_folding_filter::AsciiFoldingFilter;
+pub use self::cjk_bigram_filter::{CjkBigramFilter, CjkScript};
+pub use self::decompounder::DictionaryDecompounder;
 pub use self::facet_tokenizer::FacetTokenizer;
-pub use self::lower_caser::LowerCaser;
+pub(crate) use self::feature_tokenizer::term_freq_to_feature_weight;
+pub use self::feature_tokenizer::FeatureTokenizer;
+pub use self::keyword_tokenizer::KeywordTokenizer;
+pub use self::limit_token_count::LimitTokenCountFilter;
+pub use self::lower_caser::{CaseFolder, LowerCaser};
 pub use self::ngram_tokenizer::NgramTokenizer;
+pub(crate) use self::prefixed_token_stream::PrefixedTokenStream;
 pub use self::raw_tokenizer::RawTokenizer;
 pub use self::remove_long::RemoveLongFilter;
 pub use self::simple_tokenizer::SimpleTokenizer;
@@ -146,7 +165,8 @@ pub(crate) use self::token_stream_chain::TokenStreamChain;
 
 pub use self::tokenized_string::{PreTokenizedStream, PreTokenizedString};
 pub use self::tokenizer::{
-    BoxTokenFilter, BoxTokenStream, TextAnalyzer, Token, TokenFilter, TokenStream, Tokenizer,
+    AnalysisExplanation, AnalysisStep, BoxTokenFilter, BoxTokenStream, TextAnalyzer, Token,
+    TokenFilter, TokenStream, Tokenizer,
 };
 
 pub use self::tokenizer_manager::TokenizerManager;
@@ -225,6 +245,31 @@ pub mod tests {
         assert_token(&tokens[3], 3, "payer", 17, 22);
     }
 
+    #[test]
+    fn test_folded_tokenizers() {
+        let tokenizer_manager = TokenizerManager::default();
+        let texts = |tokenizer_name: &str, text: &str| -> Vec<String> {
+            tokenizer_manager
+                .analyze(tokenizer_name, text)
+                .unwrap()
+                .into_iter()
+                .map(|token| token.text)
+                .collect()
+        };
+        assert_eq!(
+            texts("default_folded", "Café CRÈME Straße"),
+            vec!["cafe", "creme", "strasse"]
+        );
+        assert_eq!(
+            texts("default_folded", "cafe"),
+            texts("default_folded", "café")
+        );
+        assert_eq!(
+            texts("en_stem_folded", "Cafés"),
+            texts("en_stem_folded", "cafes")
+        );
+    }
+
     #[test]
     fn test_non_en_tokenizer() {
         let tokenizer_manager = TokenizerManager::default();
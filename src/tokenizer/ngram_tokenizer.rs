@@ -5,7 +5,8 @@ use crate::tokenizer::BoxTokenStream;
 ///
 /// With this tokenizer, the `position` is always 0.
 /// Beware however, in presence of multiple value for the same field,
-/// the position will be `POSITION_GAP * index of value`.
+/// the position will be the position gap of the field times the index of the value
+/// (See [`TextFieldIndexing::set_position_gap`](../schema/struct.TextFieldIndexing.html#method.set_position_gap)).
 ///
 /// Example 1: `hello` would be tokenized as (min_gram: 2, max_gram: 3, prefix_only: false)
 ///
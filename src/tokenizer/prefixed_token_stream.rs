@@ -0,0 +1,72 @@
+use crate::tokenizer::{BoxTokenStream, Token, TokenStream};
+
+/// Token stream prepending a prefix to the text of all of the tokens
+/// of an underlying token stream.
+///
+/// It is used to index the attributes of dynamic fields.
+pub(crate) struct PrefixedTokenStream<'a> {
+    prefix: String,
+    token_stream: BoxTokenStream<'a>,
+    token: Token,
+}
+
+impl<'a> PrefixedTokenStream<'a> {
+    pub fn new(prefix: String, token_stream: BoxTokenStream<'a>) -> PrefixedTokenStream<'a> {
+        PrefixedTokenStream {
+            prefix,
+            token_stream,
+            token: Token::default(),
+        }
+    }
+}
+
+impl<'a> TokenStream for PrefixedTokenStream<'a> {
+    fn advance(&mut self) -> bool {
+        if !self.token_stream.advance() {
+            return false;
+        }
+        let token = self.token_stream.token();
+        self.token.offset_from = token.offset_from;
+        self.token.offset_to = token.offset_to;
+        self.token.position = token.position;
+        self.token.position_length = token.position_length;
+        self.token.stacked = token.stacked;
+        self.token.text.clear();
+        self.token.text.push_str(&self.prefix);
+        self.token.text.push_str(&token.text);
+        true
+    }
+
+    fn token(&self) -> &Token {
+        &self.token
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.token
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PrefixedTokenStream;
+    use crate::tokenizer::{SimpleTokenizer, TokenStream, Tokenizer};
+
+    #[test]
+    fn test_prefixed_token_stream() {
+        let token_stream = SimpleTokenizer.token_stream("hello happy tax payer");
+        let mut prefixed_token_stream = PrefixedTokenStream::new("a:".to_string(), token_stream);
+        let mut tokens = Vec::new();
+        prefixed_token_stream.process(&mut |token| {
+            tokens.push((token.position, token.text.clone()));
+        });
+        assert_eq!(
+            tokens,
+            vec![
+                (0, "a:hello".to_string()),
+                (1, "a:happy".to_string()),
+                (2, "a:tax".to_string()),
+                (3, "a:payer".to_string())
+            ]
+        );
+    }
+}
@@ -21,6 +21,9 @@ type StopWordHasher = BuildHasherDefault<FnvHasher>;
 type StopWordHashSet = HashSet<String, StopWordHasher>;
 
 /// `TokenFilter` that removes stop words from a token stream
+///
+/// The positions of the remaining tokens are left unchanged: each removed word
+/// leaves a gap in the positions.
 #[derive(Clone)]
 pub struct StopWordFilter {
     words: StopWordHashSet,
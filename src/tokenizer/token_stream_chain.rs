@@ -6,6 +6,7 @@ const POSITION_GAP: usize = 2;
 pub(crate) struct TokenStreamChain<'a> {
     offsets: Vec<usize>,
     token_streams: Vec<BoxTokenStream<'a>>,
+    position_gap: usize,
     position_shift: usize,
     stream_idx: usize,
     token: Token,
@@ -20,10 +21,18 @@ impl<'a> TokenStreamChain<'a> {
             offsets,
             stream_idx: 0,
             token_streams,
+            position_gap: POSITION_GAP,
             position_shift: 0,
             token: Token::default(),
         }
     }
+
+    /// Sets the gap between the position of the last token of a token stream
+    /// and the position of the first token of the next one.
+    pub fn with_position_gap(mut self, position_gap: usize) -> TokenStreamChain<'a> {
+        self.position_gap = position_gap;
+        self
+    }
 }
 
 impl<'a> TokenStream for TokenStreamChain<'a> {
@@ -36,12 +45,13 @@ impl<'a> TokenStream for TokenStreamChain<'a> {
                 self.token.offset_from = token.offset_from + offset_offset;
                 self.token.offset_to = token.offset_to + offset_offset;
                 self.token.position = token.position + self.position_shift;
+                self.token.stacked = token.stacked;
                 self.token.text.clear();
                 self.token.text.push_str(token.text.as_str());
                 return true;
             } else {
                 self.stream_idx += 1;
-                self.position_shift = self.token.position.wrapping_add(POSITION_GAP);
+                self.position_shift = self.token.position.wrapping_add(self.position_gap);
             }
         }
         false
@@ -92,4 +102,19 @@ mod tests {
 
         assert!(!token_chain.advance());
     }
+
+    #[test]
+    fn test_chain_position_gap() {
+        let token_streams = vec![
+            SimpleTokenizer.token_stream("hello world"),
+            SimpleTokenizer.token_stream("happy tax payer"),
+        ];
+        let mut token_chain =
+            TokenStreamChain::new(vec![0, 11], token_streams).with_position_gap(10);
+        let mut positions = vec![];
+        while token_chain.advance() {
+            positions.push(token_chain.token().position);
+        }
+        assert_eq!(positions, vec![0, 1, 11, 12, 13]);
+    }
 }
@@ -9,18 +9,34 @@ use std::ops::{Deref, DerefMut};
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 pub struct Token {
     /// Offset (byte index) of the first character of the token.
-    /// Offsets shall not be modified by token filters.
+    /// Offsets shall not be modified by token filters, except by the ones splitting
+    /// tokens into several tokens.
     pub offset_from: usize,
     /// Offset (byte index) of the last character of the token + 1.
     /// The text that generated the token should be obtained by
     /// &text[token.offset_from..token.offset_to]
     pub offset_to: usize,
     /// Position, expressed in number of tokens.
+    ///
+    /// Token filters removing tokens leave the position of the following tokens unchanged,
+    /// so that the removed tokens leave a gap in the positions. These gaps are recorded
+    /// by the indexer, and taken in account by phrase queries.
     pub position: usize,
     /// Actual text content of the token.
     pub text: String,
     /// Is the length expressed in term of number of original tokens.
     pub position_length: usize,
+    /// True if the token is stacked on the previous token, at the same position,
+    /// e.g. a subword emitted by a decompounder along with the compound word.
+    ///
+    /// Stacked tokens are not counted in the number of tokens of the field,
+    /// used as fieldnorm to score documents.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub stacked: bool,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
 }
 
 impl Default for Token {
@@ -31,15 +47,62 @@ impl Default for Token {
             position: usize::max_value(),
             text: String::with_capacity(200),
             position_length: 1,
+            stacked: false,
         }
     }
 }
 
+/// Tokens emitted by one of the steps of a `TextAnalyzer`.
+///
+/// See [`TextAnalyzer::explain`](./struct.TextAnalyzer.html#method.explain).
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct AnalysisStep {
+    /// Name of the type of the tokenizer or of the token filter, e.g. `LowerCaser`.
+    pub name: String,
+    /// Tokens emitted by the step.
+    pub tokens: Vec<Token>,
+}
+
+/// Describes how a `TextAnalyzer` turns a text into tokens.
+///
+/// The first step is the tokenizer, and is followed by the token filters,
+/// in the order they are applied.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct AnalysisExplanation {
+    /// Steps of the analysis.
+    pub steps: Vec<AnalysisStep>,
+}
+
+impl AnalysisExplanation {
+    /// Returns the tokens emitted by the last step, that is the tokens that get indexed.
+    pub fn tokens(&self) -> &[Token] {
+        self.steps
+            .last()
+            .map(|step| &step.tokens[..])
+            .unwrap_or(&[])
+    }
+}
+
+// Returns the name of the type, stripped of its module path.
+fn short_type_name<T>() -> &'static str {
+    let type_name = std::any::type_name::<T>();
+    let path = type_name.split('<').next().unwrap_or(type_name);
+    let name_start = path.rfind("::").map(|pos| pos + 2).unwrap_or(0);
+    &type_name[name_start..]
+}
+
+fn collect_tokens(mut token_stream: BoxTokenStream<'_>) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    token_stream.process(&mut |token| tokens.push(token.clone()));
+    tokens
+}
+
 /// `TextAnalyzer` tokenizes an input text into tokens and modifies the resulting `TokenStream`.
 ///
 /// It simply wraps a `Tokenizer` and a list of `TokenFilter` that are applied sequentially.
 pub struct TextAnalyzer {
     tokenizer: Box<dyn Tokenizer>,
+    tokenizer_name: &'static str,
     token_filters: Vec<BoxTokenFilter>,
 }
 
@@ -57,6 +120,7 @@ impl TextAnalyzer {
     pub fn new<T: Tokenizer>(tokenizer: T, token_filters: Vec<BoxTokenFilter>) -> TextAnalyzer {
         TextAnalyzer {
             tokenizer: Box::new(tokenizer),
+            tokenizer_name: short_type_name::<T>(),
             token_filters,
         }
     }
@@ -115,12 +179,51 @@ impl TextAnalyzer {
         }
         token_stream
     }
+
+    /// Returns the tokens emitted for the given text.
+    ///
+    /// ```rust
+    /// use tantivy::tokenizer::*;
+    ///
+    /// let tokenizer = TextAnalyzer::from(SimpleTokenizer).filter(LowerCaser);
+    /// let tokens = tokenizer.analyze("Hello, happy tax payer");
+    /// assert_eq!(tokens.len(), 4);
+    /// assert_eq!(&tokens[0].text, "hello");
+    /// ```
+    pub fn analyze(&self, text: &str) -> Vec<Token> {
+        collect_tokens(self.token_stream(text))
+    }
+
+    /// Returns the tokens emitted for the given text by the tokenizer,
+    /// and after each of the token filters.
+    ///
+    /// This is meant to help debugging an analysis pipeline, as every
+    /// step runs the pipeline from the start.
+    pub fn explain(&self, text: &str) -> AnalysisExplanation {
+        let mut steps = Vec::with_capacity(self.token_filters.len() + 1);
+        steps.push(AnalysisStep {
+            name: self.tokenizer_name.to_string(),
+            tokens: collect_tokens(self.tokenizer.token_stream(text)),
+        });
+        for (num_filters, token_filter) in self.token_filters.iter().enumerate() {
+            let mut token_stream = self.tokenizer.token_stream(text);
+            for previous_filter in &self.token_filters[..=num_filters] {
+                token_stream = previous_filter.transform(token_stream);
+            }
+            steps.push(AnalysisStep {
+                name: token_filter.name.to_string(),
+                tokens: collect_tokens(token_stream),
+            });
+        }
+        AnalysisExplanation { steps }
+    }
 }
 
 impl Clone for TextAnalyzer {
     fn clone(&self) -> Self {
         TextAnalyzer {
             tokenizer: self.tokenizer.box_clone(),
+            tokenizer_name: self.tokenizer_name,
             token_filters: self
                 .token_filters
                 .iter()
@@ -200,19 +303,25 @@ impl<'a> DerefMut for BoxTokenStream<'a> {
 /// Simple wrapper of `Box<dyn TokenFilter + 'a>`.
 ///
 /// See `TokenStream` for more information.
-pub struct BoxTokenFilter(Box<dyn TokenFilter>);
+pub struct BoxTokenFilter {
+    token_filter: Box<dyn TokenFilter>,
+    name: &'static str,
+}
 
 impl Deref for BoxTokenFilter {
     type Target = dyn TokenFilter;
 
     fn deref(&self) -> &dyn TokenFilter {
-        &*self.0
+        &*self.token_filter
     }
 }
 
 impl<T: TokenFilter> From<T> for BoxTokenFilter {
     fn from(tokenizer: T) -> BoxTokenFilter {
-        BoxTokenFilter(Box::new(tokenizer))
+        BoxTokenFilter {
+            token_filter: Box::new(tokenizer),
+            name: short_type_name::<T>(),
+        }
     }
 }
 
@@ -283,12 +392,17 @@ pub trait TokenStream {
     /// Helper function to consume the entire `TokenStream`
     /// and push the tokens to a sink function.
     ///
+    /// Returns the number of tokens pushed, stacked tokens excluded.
+    ///
     /// Remove this.
     fn process(&mut self, sink: &mut dyn FnMut(&Token)) -> u32 {
         let mut num_tokens_pushed = 0u32;
         while self.advance() {
-            sink(self.token());
-            num_tokens_pushed += 1u32;
+            let token = self.token();
+            sink(token);
+            if !token.stacked {
+                num_tokens_pushed += 1u32;
+            }
         }
         num_tokens_pushed
     }
@@ -312,7 +426,8 @@ impl<T: TokenFilter + Clone> TokenFilterClone for T {
 
 #[cfg(test)]
 mod test {
-    use super::Token;
+    use super::{TextAnalyzer, Token};
+    use crate::tokenizer::{LowerCaser, RemoveLongFilter, SimpleTokenizer, TokenizerManager};
 
     #[test]
     fn clone() {
@@ -322,6 +437,7 @@ mod test {
             offset_to: 3,
             text: "abc".to_string(),
             position_length: 1,
+            stacked: false,
         };
         let t2 = t1.clone();
 
@@ -330,4 +446,49 @@ mod test {
         assert_eq!(t1.offset_to, t2.offset_to);
         assert_eq!(t1.text, t2.text);
     }
+
+    #[test]
+    fn test_explain_analysis() {
+        let text_analyzer = TextAnalyzer::from(SimpleTokenizer)
+            .filter(RemoveLongFilter::limit(6))
+            .filter(LowerCaser);
+        let explanation = text_analyzer.explain("Hello Tantivy World");
+        let steps: Vec<(&str, Vec<&str>)> = explanation
+            .steps
+            .iter()
+            .map(|step| {
+                let texts = step
+                    .tokens
+                    .iter()
+                    .map(|token| token.text.as_str())
+                    .collect();
+                (step.name.as_str(), texts)
+            })
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                ("SimpleTokenizer", vec!["Hello", "Tantivy", "World"]),
+                ("RemoveLongFilter", vec!["Hello", "World"]),
+                ("LowerCaser", vec!["hello", "world"]),
+            ]
+        );
+        assert_eq!(
+            explanation.tokens(),
+            &text_analyzer.analyze("Hello Tantivy World")[..]
+        );
+        let tokenizers = TokenizerManager::default();
+        assert_eq!(
+            tokenizers.analyze("default", "Hello World"),
+            Some(
+                TextAnalyzer::from(SimpleTokenizer)
+                    .filter(LowerCaser)
+                    .analyze("Hello World")
+            )
+        );
+        assert!(tokenizers.analyze("missing", "Hello World").is_none());
+        assert!(tokenizers
+            .explain_analysis("missing", "Hello World")
+            .is_none());
+    }
 }
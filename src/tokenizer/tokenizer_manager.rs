@@ -1,10 +1,15 @@
 use crate::tokenizer::stemmer::Language;
-use crate::tokenizer::tokenizer::TextAnalyzer;
+use crate::tokenizer::tokenizer::{AnalysisExplanation, TextAnalyzer};
+use crate::tokenizer::AsciiFoldingFilter;
+use crate::tokenizer::CaseFolder;
+use crate::tokenizer::FeatureTokenizer;
+use crate::tokenizer::KeywordTokenizer;
 use crate::tokenizer::LowerCaser;
 use crate::tokenizer::RawTokenizer;
 use crate::tokenizer::RemoveLongFilter;
 use crate::tokenizer::SimpleTokenizer;
 use crate::tokenizer::Stemmer;
+use crate::tokenizer::Token;
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 
@@ -14,12 +19,17 @@ use std::sync::{Arc, RwLock};
 /// By default, it is populated with the following managers.
 ///
 ///  * `raw` : does not process nor tokenize the text.
+///  * `keyword_normalized` : does not tokenize the text, but trims and lowercases it.
 ///  * `default` : Chops the text on according to whitespace and
 ///  punctuation, removes tokens that are too long, and lowercases
 ///  tokens
 ///  * `en_stem` : Like `default`, but also applies stemming on the
 ///  resulting tokens. Stemming can improve the recall of your
 ///  search engine.
+///  * `default_folded` and `en_stem_folded` : Like `default` and `en_stem`,
+///  but case fold tokens rather than lowercasing them, and fold them to ASCII,
+///  so that "Café" matches "cafe".
+///  * `feature` : Parses `feature:weight` pairs, encoding weights as term frequencies.
 #[derive(Clone)]
 pub struct TokenizerManager {
     tokenizers: Arc<RwLock<HashMap<String, TextAnalyzer>>>,
@@ -46,6 +56,38 @@ impl TokenizerManager {
             .get(tokenizer_name)
             .cloned()
     }
+
+    /// Returns the tokens emitted by the tokenizer registered under the given name
+    /// for a text.
+    ///
+    /// Returns `None` if no tokenizer is registered under this name.
+    ///
+    /// ```rust
+    /// use tantivy::tokenizer::TokenizerManager;
+    ///
+    /// let tokenizers = TokenizerManager::default();
+    /// let tokens = tokenizers.analyze("en_stem", "Running dogs").unwrap();
+    /// let texts: Vec<&str> = tokens.iter().map(|token| token.text.as_str()).collect();
+    /// assert_eq!(texts, vec!["run", "dog"]);
+    /// ```
+    pub fn analyze(&self, tokenizer_name: &str, text: &str) -> Option<Vec<Token>> {
+        self.get(tokenizer_name)
+            .map(|text_analyzer| text_analyzer.analyze(text))
+    }
+
+    /// Describes how the tokenizer registered under the given name transforms a text,
+    /// step by step.
+    ///
+    /// Returns `None` if no tokenizer is registered under this name.
+    /// See [`TextAnalyzer::explain`](./struct.TextAnalyzer.html#method.explain).
+    pub fn explain_analysis(
+        &self,
+        tokenizer_name: &str,
+        text: &str,
+    ) -> Option<AnalysisExplanation> {
+        self.get(tokenizer_name)
+            .map(|text_analyzer| text_analyzer.explain(text))
+    }
 }
 
 impl Default for TokenizerManager {
@@ -59,6 +101,10 @@ impl Default for TokenizerManager {
             tokenizers: Arc::new(RwLock::new(HashMap::new())),
         };
         manager.register("raw", RawTokenizer);
+        manager.register(
+            "keyword_normalized",
+            KeywordTokenizer::default().lowercase(true).trim(true),
+        );
         manager.register(
             "default",
             TextAnalyzer::from(SimpleTokenizer)
@@ -72,6 +118,22 @@ impl Default for TokenizerManager {
                 .filter(LowerCaser)
                 .filter(Stemmer::new(Language::English)),
         );
+        manager.register(
+            "default_folded",
+            TextAnalyzer::from(SimpleTokenizer)
+                .filter(RemoveLongFilter::limit(40))
+                .filter(CaseFolder)
+                .filter(AsciiFoldingFilter),
+        );
+        manager.register(
+            "en_stem_folded",
+            TextAnalyzer::from(SimpleTokenizer)
+                .filter(RemoveLongFilter::limit(40))
+                .filter(CaseFolder)
+                .filter(AsciiFoldingFilter)
+                .filter(Stemmer::new(Language::English)),
+        );
+        manager.register("feature", FeatureTokenizer);
         manager
     }
 }
@@ -1,7 +1,10 @@
+use futures::executor::block_on;
 use std::path::Path;
 use tantivy::directory::{Directory, ManagedDirectory, RamDirectory, TerminatingWrite};
 use tantivy::doc;
-use tantivy::schema::{Schema, TEXT};
+use tantivy::failpoints::{self, FailScenario};
+use tantivy::merge_policy::NoMergePolicy;
+use tantivy::schema::{Field, Schema, TEXT};
 use tantivy::{Index, Term};
 
 #[test]
@@ -64,3 +67,76 @@ fn test_write_commit_fails() -> tantivy::Result<()> {
     assert_eq!(num_docs_containing("b")?, 0);
     Ok(())
 }
+
+fn index_with_two_segments() -> tantivy::Result<(Index, tantivy::IndexWriter, Field)> {
+    let mut schema_builder = Schema::builder();
+    let text_field = schema_builder.add_text_field("text", TEXT);
+    let index = Index::create_in_ram(schema_builder.build());
+    let mut index_writer = index.writer_with_num_threads(1, 3_000_000)?;
+    index_writer.set_merge_policy(Box::new(NoMergePolicy));
+    index_writer.add_document(doc!(text_field => "a"));
+    index_writer.commit()?;
+    index_writer.add_document(doc!(text_field => "b"));
+    index_writer.commit()?;
+    Ok((index, index_writer, text_field))
+}
+
+#[test]
+fn test_failpoints_merge_fails() -> tantivy::Result<()> {
+    let _fail_scenario_guard = FailScenario::setup();
+    let (index, mut index_writer, _) = index_with_two_segments()?;
+    let mut segment_ids = index.searchable_segment_ids()?;
+    segment_ids.sort();
+    for failpoint in &[failpoints::MERGE_WRITE, failpoints::MERGE_PUBLISH] {
+        failpoints::fail_with_error(failpoint)?;
+        assert!(block_on(index_writer.merge(&segment_ids)).is_err());
+        failpoints::clear(failpoint);
+        // The merged segments are untouched.
+        let mut searchable_segment_ids = index.searchable_segment_ids()?;
+        searchable_segment_ids.sort();
+        assert_eq!(searchable_segment_ids, segment_ids);
+        assert_eq!(index.reader()?.searcher().num_docs(), 2);
+    }
+    block_on(index_writer.merge(&segment_ids))?;
+    index_writer.commit()?;
+    assert_eq!(index.searchable_segment_ids()?.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_failpoints_delete_bitset_write_fails() -> tantivy::Result<()> {
+    let _fail_scenario_guard = FailScenario::setup();
+    let (index, mut index_writer, text_field) = index_with_two_segments()?;
+    index_writer.delete_term(Term::from_field_text(text_field, "a"));
+    failpoints::fail_with_error(failpoints::DELETE_BITSET_WRITE)?;
+    assert!(index_writer.commit().is_err());
+    failpoints::clear(failpoints::DELETE_BITSET_WRITE);
+    assert_eq!(index.reader()?.searcher().num_docs(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_failpoints_save_metas_fails() -> tantivy::Result<()> {
+    let _fail_scenario_guard = FailScenario::setup();
+    let (index, mut index_writer, text_field) = index_with_two_segments()?;
+    index_writer.add_document(doc!(text_field => "c"));
+    failpoints::fail_with_error(failpoints::SAVE_METAS)?;
+    assert!(index_writer.commit().is_err());
+    failpoints::clear(failpoints::SAVE_METAS);
+    // The segment of the failed commit is not published.
+    assert_eq!(index.searchable_segment_ids()?.len(), 2);
+    assert_eq!(index.reader()?.searcher().num_docs(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_failpoints_garbage_collect_fails() -> tantivy::Result<()> {
+    let _fail_scenario_guard = FailScenario::setup();
+    let (_index, index_writer, _) = index_with_two_segments()?;
+    failpoints::fail_after(failpoints::GARBAGE_COLLECT, 1)?;
+    assert!(block_on(index_writer.garbage_collect_files()).is_ok());
+    assert!(block_on(index_writer.garbage_collect_files()).is_err());
+    failpoints::clear(failpoints::GARBAGE_COLLECT);
+    assert!(block_on(index_writer.garbage_collect_files()).is_ok());
+    Ok(())
+}